@@ -0,0 +1,69 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+const float PI = 3.14159265359;
+const uint sampleCount = 1024u;
+
+float radicalInverse(uint bits)
+{
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec3 importanceSampleGGX(vec2 xi, float roughness)
+{
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    return vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+}
+
+float geometrySchlickGGX(float normalDotDirection, float roughness)
+{
+    // Image based lighting uses a different remapping of the roughness than direct lighting
+    float k = (roughness * roughness) / 2.0;
+    return normalDotDirection / (normalDotDirection * (1.0 - k) + k);
+}
+
+// Integrates the specular BRDF over the hemisphere for the angle between the normal and view direction
+// along the x axis and the roughness along the y axis. The result is the scale and bias applied to the
+// Fresnel reflectance at normal incidence
+void main()
+{
+    float normalDotView = max(texCoords.x, 0.0001);
+    float roughness = texCoords.y;
+
+    vec3 view = vec3(sqrt(1.0 - normalDotView * normalDotView), 0.0, normalDotView);
+    float scale = 0.0;
+    float bias = 0.0;
+
+    for(uint i = 0u; i < sampleCount; ++i)
+    {
+        vec2 xi = vec2(float(i) / float(sampleCount), radicalInverse(i));
+        vec3 halfway = importanceSampleGGX(xi, roughness);
+        vec3 lightDirection = normalize(2.0 * dot(view, halfway) * halfway - view);
+
+        float normalDotLight = max(lightDirection.z, 0.0);
+        float normalDotHalfway = max(halfway.z, 0.0);
+        float viewDotHalfway = max(dot(view, halfway), 0.0);
+
+        if(normalDotLight > 0.0)
+        {
+            float geometry = geometrySchlickGGX(normalDotView, roughness) * geometrySchlickGGX(normalDotLight, roughness);
+            float visibility = (geometry * viewDotHalfway) / (normalDotHalfway * normalDotView);
+            float fresnel = pow(1.0 - viewDotHalfway, 5.0);
+
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+
+    FragColor = vec4(scale / float(sampleCount), bias / float(sampleCount), 0.0, 1.0);
+}
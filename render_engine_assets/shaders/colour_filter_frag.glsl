@@ -0,0 +1,12 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D sceneTexture;
+uniform mat3 colourMatrix;
+
+void main()
+{
+    vec3 colour = texture(sceneTexture, texCoords).rgb;
+    FragColor = vec4(clamp(colourMatrix * colour, 0.0, 1.0), 1.0);
+}
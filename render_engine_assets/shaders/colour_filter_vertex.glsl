@@ -0,0 +1,9 @@
+out vec2 texCoords;
+
+// Fullscreen triangle generated from the vertex index, requiring no vertex buffers
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    texCoords = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,30 @@
+in vec4 lineColour;
+in vec3 worldPosition;
+flat in int depthTested;
+
+out vec4 FragColor;
+
+uniform sampler2DArray gPosition;
+
+uniform vec3 cameraPosition;
+uniform vec2 windowDimensions;
+uniform bool positionsAvailable;
+
+void main()
+{
+    if(positionsAvailable && depthTested == 1)
+    {
+        vec3 scenePosition = texture(gPosition, vec3(gl_FragCoord.xy / windowDimensions, 0)).xyz;
+
+        // The sky box, light sources and pixels nothing was drawn to do not have a world position
+        bool hasPosition = !any(isinf(scenePosition)) && !any(isnan(scenePosition)) && scenePosition != vec3(0.0);
+
+        // A small bias keeps lines drawn on the surface of geometry, such as the edges of a bounding box, visible
+        if(hasPosition && distance(scenePosition, cameraPosition) + 0.01 < distance(worldPosition, cameraPosition))
+        {
+            discard;
+        }
+    }
+
+    FragColor = lineColour;
+}
\ No newline at end of file
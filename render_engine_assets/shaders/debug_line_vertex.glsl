@@ -0,0 +1,29 @@
+struct DebugVertex
+{
+    // xyz is the position relative to the render origin, w is 1 if the line is hidden behind geometry
+    vec4 positionDepthTested;
+    vec4 colour;
+};
+
+layout (std430, binding = 20) readonly buffer DebugVertices
+{
+    DebugVertex vertices[];
+};
+
+out vec4 lineColour;
+out vec3 worldPosition;
+flat out int depthTested;
+
+uniform mat4 viewProjection;
+
+// Every pair of vertices is a line
+void main()
+{
+    DebugVertex debugVertex = vertices[gl_VertexID];
+
+    worldPosition = debugVertex.positionDepthTested.xyz;
+    lineColour = debugVertex.colour;
+    depthTested = int(debugVertex.positionDepthTested.w);
+
+    gl_Position = viewProjection * vec4(worldPosition, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,26 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+// 0 shows the colour of the G-Buffer layout, 1 shows the normals of the layout and 2 fills with heatColour
+uniform int viewMode;
+uniform sampler2D gBufferLayout;
+uniform vec4 heatColour;
+
+void main()
+{
+    switch(viewMode)
+    {
+        case 0:
+            FragColor = vec4(texture(gBufferLayout, texCoords).rgb, 1.0);
+            break;
+
+        case 1:
+            FragColor = vec4(normalize(texture(gBufferLayout, texCoords).xyz) * 0.5 + 0.5, 1.0);
+            break;
+
+        default:
+            FragColor = heatColour;
+            break;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D equirectangularMap;
+
+const vec2 inverseAtan = vec2(0.1591, 0.3183);
+
+void main()
+{
+    vec3 direction = cubeFaceDirection(texCoords);
+
+    // The image is loaded flipped vertically, so the top row of the image is at v = 1
+    vec2 uv = vec2(atan(direction.z, direction.x), asin(clamp(direction.y, -1.0, 1.0))) * inverseAtan + 0.5;
+    FragColor = vec4(texture(equirectangularMap, uv).rgb, 1.0);
+}
@@ -3,6 +3,11 @@ const vec3 LIGHT_SOURCE_FRAG = vec3(0.0, 1.0 / 0.0, 0.0);
 
 uniform vec3 skyboxBrightness;
 uniform uint drawingModelsWithTextures;
+// Set to 1 by the mipmap level debug render mode
+uniform int debugMipmapLevel;
+
+// Colours of the mipmap levels shown by the mipmap level debug render mode, from the full resolution level
+const vec3 MIPMAP_LEVEL_COLOURS[6] = vec3[](vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 1.0), vec3(0.0, 1.0, 0.0), vec3(1.0, 1.0, 0.0), vec3(1.0, 0.5, 0.0), vec3(1.0, 0.0, 0.0));
 
 struct TextureInformation
 {
@@ -36,6 +41,19 @@ TextureInformation specular_texture_info(uvec4 tex_info)
     return TextureInformation( (tex_info.z & uint(0xFC00)) >> 10, tex_info.z & uint(0x3FF) );
 }
 
+// The mipmap level sampled for the texture coordinates, shown as a colour. Only the texture array loaded with
+// the textures of models has mipmaps
+vec4 mipmapLevelColour(TextureInformation textureLocation, vec2 scaledTexCoords)
+{
+    if(textureLocation.array_index != 1)
+    {
+        return vec4(MIPMAP_LEVEL_COLOURS[0], 1.0);
+    }
+
+    float level = clamp(textureQueryLod(textureArray, scaledTexCoords).x, 0.0, 5.0);
+    return vec4(mix(MIPMAP_LEVEL_COLOURS[int(floor(level))], MIPMAP_LEVEL_COLOURS[int(ceil(level))], fract(level)), 1.0);
+}
+
 vec4 textureColour()
 {
     if(drawingModelsWithTextures == 0)
@@ -83,5 +101,32 @@ void main()
     // and the diffuse per-fragment color
     gAlbedoSpec = textureColour();
 
+#ifdef DAMAGE_MASKS
+    if(useSkyboxTexture == 0 && drawingLightSource == 0)
+    {
+        gAlbedoSpec.rgb = applyDamage(gAlbedoSpec.rgb, damageMaskLayer, objectPosition);
+    }
+#endif
+
+    // The instance parameters of the default render system tint an entity towards the colour in rgb, by
+    // the amount in a
+    if(useSkyboxTexture == 0 && drawingLightSource == 0)
+    {
+        gAlbedoSpec.rgb = mix(gAlbedoSpec.rgb, instanceTint.rgb, clamp(instanceTint.a, 0.0, 1.0));
+    }
+
+#ifdef DISSOLVE
+    if(useSkyboxTexture == 0 && drawingLightSource == 0)
+    {
+        gAlbedoSpec.rgb = applyDissolve(gAlbedoSpec.rgb, dissolveState, objectPosition);
+    }
+#endif
+
+    if(debugMipmapLevel == 1 && useSkyboxTexture == 0 && drawingModelsWithTextures == 1)
+    {
+        vec2 scaledTexCoords = vec2(textureCoords.x * textureCoords.z, textureCoords.y * textureCoords.w);
+        gAlbedoSpec = mipmapLevelColour(diffuse_texture_info(textureLayer), scaledTexCoords);
+    }
+
     gLightPosition = lightFragPos[2];
 }
\ No newline at end of file
@@ -17,6 +17,11 @@ void main()
 
     adjustBrightnessLightSource = lightSource;
 
+    objectPosition = aPos;
+    damageMaskLayer = damageLayer.x;
+    instanceTint = instanceParams;
+    dissolveState = dissolve;
+
     if (renderingSkybox == 1)
     {
       normalizedVertexNormal = normalize(normal);
@@ -0,0 +1,11 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D fogTexture;
+
+void main()
+{
+    // Blended with the frame as fog + frame * transmittance
+    FragColor = texture(fogTexture, texCoords);
+}
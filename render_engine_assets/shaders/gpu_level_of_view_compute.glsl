@@ -0,0 +1,258 @@
+layout (local_size_x = 64) in;
+
+struct LevelOfViewGroup
+{
+    // x is the first instance of the group, y the number of instances, z the number of level of views and w is
+    // non-zero if the instances of each level of view are kept in the order they were written
+    uvec4 range;
+    // The furthest distance each level of view is used at
+    vec4 maxDistances[2];
+};
+
+struct DrawCommand
+{
+    uint count;
+    uint instanceCount;
+    uint firstIndex;
+    int baseVertex;
+    uint baseInstance;
+};
+
+struct Instance
+{
+    // xyz is the position of the instance, w holds the bits of the index of its group
+    vec4 position;
+    // Radius around the position holding the bounding volume of the instance, or a negative radius if the
+    // instance is never culled
+    float boundingRadius;
+};
+
+layout (std430, binding = 10) readonly buffer InstancePositions
+{
+    Instance instances[];
+};
+
+// The level of view of each instance in the top 8 bits, and its index within the instances of that level of view.
+// The index is only written here for groups that do not keep their order. Instances that were culled are marked
+// with CULLED_PLACEMENT
+layout (std430, binding = 11) buffer InstancePlacements
+{
+    uint placements[];
+};
+
+layout (std430, binding = 12) readonly buffer LevelOfViewGroups
+{
+    LevelOfViewGroup groups[];
+};
+
+// The number of instances of each level of view of each group
+layout (std430, binding = 13) buffer BucketCounts
+{
+    uint bucketCounts[];
+};
+
+layout (std430, binding = 14) readonly buffer SourceInstances
+{
+    uint sourceInstances[];
+};
+
+layout (std430, binding = 15) writeonly buffer DestinationInstances
+{
+    uint destinationInstances[];
+};
+
+layout (std430, binding = 16) buffer DrawCommands
+{
+    DrawCommand commands[];
+};
+
+// The group and level of view each draw command draws
+layout (std430, binding = 17) readonly buffer DrawCommandBuckets
+{
+    uvec2 commandBuckets[];
+};
+
+const uint MAX_LEVEL_OF_VIEWS = 8u;
+const uint CULLED_PLACEMENT = 0xFFFFFFFFu;
+
+uniform uint stage;
+uniform uint numberItems;
+uniform vec3 cameraPosition;
+uniform uint numberLayouts;
+// x is the first word of the layout in the instance buffers, y is the number of words of each instance
+uniform uvec2 instanceLayouts[MAX_INSTANCE_LAYOUTS];
+
+uniform bool cullInstances;
+// The planes of the view frustum, with their normals pointing into the frustum
+uniform vec4 frustumPlanes[6];
+uniform bool occlusionCulling;
+// The view projection matrix of the frame the Hi-Z buffer was built from
+uniform mat4 occluderViewProjection;
+uniform sampler2D hiZBuffer;
+uniform vec2 hiZSize;
+uniform float hiZLevels;
+
+bool insideFrustum(vec3 centre, float radius)
+{
+    for(int i = 0; i < 6; ++i)
+    {
+        if(dot(frustumPlanes[i].xyz, centre) + frustumPlanes[i].w < -radius)
+        {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+// Projects the box around the bounding sphere into the frame the Hi-Z buffer was built from, and checks if its
+// nearest depth is behind the furthest depth drawn where it would be. The level read is the one where the box
+// covers at most two texels in each direction, so four texels hold the furthest depth of everything the box covers
+bool occluded(vec3 centre, float radius)
+{
+    vec2 minimumCorner = vec2(1.0);
+    vec2 maximumCorner = vec2(0.0);
+    float nearestDepth = 1.0;
+
+    for(int i = 0; i < 8; ++i)
+    {
+        vec3 offset = vec3((i & 1) == 0 ? -1.0 : 1.0, (i & 2) == 0 ? -1.0 : 1.0, (i & 4) == 0 ? -1.0 : 1.0);
+        vec4 clipCorner = occluderViewProjection * vec4(centre + offset * radius, 1.0);
+
+        // Boxes reaching behind the camera cannot be projected, so are never occluded
+        if(clipCorner.w <= 0.0)
+        {
+            return false;
+        }
+
+        vec3 windowCorner = clipCorner.xyz / clipCorner.w * 0.5 + 0.5;
+        minimumCorner = min(minimumCorner, windowCorner.xy);
+        maximumCorner = max(maximumCorner, windowCorner.xy);
+        nearestDepth = min(nearestDepth, windowCorner.z);
+    }
+
+    minimumCorner = clamp(minimumCorner, 0.0, 1.0);
+    maximumCorner = clamp(maximumCorner, 0.0, 1.0);
+
+    vec2 coveredTexels = (maximumCorner - minimumCorner) * hiZSize;
+    float level = clamp(ceil(log2(max(max(coveredTexels.x, coveredTexels.y), 1.0))), 0.0, hiZLevels - 1.0);
+
+    float furthestDepth = max(max(textureLod(hiZBuffer, minimumCorner, level).r, textureLod(hiZBuffer, vec2(maximumCorner.x, minimumCorner.y), level).r),
+                              max(textureLod(hiZBuffer, vec2(minimumCorner.x, maximumCorner.y), level).r, textureLod(hiZBuffer, maximumCorner, level).r));
+
+    return nearestDepth > furthestDepth;
+}
+
+bool culled(Instance instance)
+{
+    if(!cullInstances || instance.boundingRadius < 0.0)
+    {
+        return false;
+    }
+
+    return !insideFrustum(instance.position.xyz, instance.boundingRadius) ||
+           (occlusionCulling && occluded(instance.position.xyz, instance.boundingRadius));
+}
+
+uint bucketOffset(uint group, uint levelOfView)
+{
+    uint offset = 0u;
+
+    for(uint i = 0u; i < levelOfView; ++i)
+    {
+        offset += bucketCounts[group * MAX_LEVEL_OF_VIEWS + i];
+    }
+
+    return offset;
+}
+
+// The number of instances written before the given one in its group that were given the same level of view, so
+// that the instances of each level of view keep the order they were written in. Culled instances never match, as
+// their placement holds no valid level of view
+uint orderedBucketIndex(uint group, uint index, uint levelOfView)
+{
+    uint bucketIndex = 0u;
+
+    for(uint i = groups[group].range.x; i < index; ++i)
+    {
+        if((placements[i] >> 24) == levelOfView)
+        {
+            ++bucketIndex;
+        }
+    }
+
+    return bucketIndex;
+}
+
+// The pass runs in three stages: culling and choosing the level of view of every instance, copying every instance
+// that was not culled to the instances of its level of view, and writing the instances each draw command draws
+void main()
+{
+    uint index = gl_GlobalInvocationID.x;
+
+    if(index >= numberItems)
+    {
+        return;
+    }
+
+    if(stage == 0u)
+    {
+        Instance instance = instances[index];
+
+        if(culled(instance))
+        {
+            placements[index] = CULLED_PLACEMENT;
+            return;
+        }
+
+        vec4 position = instance.position;
+        uint group = floatBitsToUint(position.w);
+        uint numberLevelOfViews = groups[group].range.z;
+        float distanceToCamera = distance(position.xyz, cameraPosition);
+
+        uint levelOfView = numberLevelOfViews - 1u;
+        for(uint i = 0u; i < numberLevelOfViews; ++i)
+        {
+            if(distanceToCamera <= groups[group].maxDistances[i / 4u][i % 4u])
+            {
+                levelOfView = i;
+                break;
+            }
+        }
+
+        uint bucketIndex = atomicAdd(bucketCounts[group * MAX_LEVEL_OF_VIEWS + levelOfView], 1u);
+
+        // The order the atomic indexes are handed out in is arbitrary, so groups keeping their order find the
+        // index of each instance once every placement is known
+        placements[index] = groups[group].range.w != 0u ? levelOfView << 24 : (levelOfView << 24) | bucketIndex;
+    }
+    else if(stage == 1u)
+    {
+        uint placement = placements[index];
+
+        if(placement == CULLED_PLACEMENT)
+        {
+            return;
+        }
+
+        uint group = floatBitsToUint(instances[index].position.w);
+        uint bucketIndex = groups[group].range.w != 0u ? orderedBucketIndex(group, index, placement >> 24) : placement & 0xFFFFFFu;
+        uint destination = groups[group].range.x + bucketOffset(group, placement >> 24) + bucketIndex;
+
+        for(uint layoutIndex = 0u; layoutIndex < numberLayouts; ++layoutIndex)
+        {
+            uvec2 instanceLayout = instanceLayouts[layoutIndex];
+
+            for(uint word = 0u; word < instanceLayout.y; ++word)
+            {
+                destinationInstances[instanceLayout.x + destination * instanceLayout.y + word] = sourceInstances[instanceLayout.x + index * instanceLayout.y + word];
+            }
+        }
+    }
+    else
+    {
+        uvec2 bucket = commandBuckets[index];
+        commands[index].instanceCount = bucketCounts[bucket.x * MAX_LEVEL_OF_VIEWS + bucket.y];
+        commands[index].baseInstance = groups[bucket.x].range.x + bucketOffset(bucket.x, bucket.y);
+    }
+}
\ No newline at end of file
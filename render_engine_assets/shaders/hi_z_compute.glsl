@@ -0,0 +1,49 @@
+layout (local_size_x = 8, local_size_y = 8) in;
+
+// The depth of the frame, read when the first level is written
+uniform sampler2D depthMap;
+
+layout (r32f, binding = 0) readonly uniform image2D sourceLevel;
+layout (r32f, binding = 1) writeonly uniform image2D destinationLevel;
+
+uniform int level;
+uniform ivec2 sourceSize;
+uniform ivec2 destinationSize;
+
+// The first level is a copy of the depth of the frame. Every other level holds the furthest depth of the texels of
+// the level before it that each of its texels covers, so a texel of any level holds the furthest depth of the region
+// of the frame it covers
+void main()
+{
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+
+    if(any(greaterThanEqual(texel, destinationSize)))
+    {
+        return;
+    }
+
+    if(level == 0)
+    {
+        imageStore(destinationLevel, texel, vec4(texelFetch(depthMap, texel, 0).r));
+        return;
+    }
+
+    // A level with an odd size has one more row or column than twice the size of the next level, which the last
+    // texel of the next level covers as well
+    ivec2 lastTexel = destinationSize - 1;
+    ivec2 coveredTexels = ivec2(texel.x == lastTexel.x && (sourceSize.x & 1) == 1 ? 3 : 2,
+                                texel.y == lastTexel.y && (sourceSize.y & 1) == 1 ? 3 : 2);
+
+    float furthestDepth = 0.0;
+
+    for(int y = 0; y < coveredTexels.y; ++y)
+    {
+        for(int x = 0; x < coveredTexels.x; ++x)
+        {
+            ivec2 sourceTexel = min(texel * 2 + ivec2(x, y), sourceSize - 1);
+            furthestDepth = max(furthestDepth, imageLoad(sourceLevel, sourceTexel).r);
+        }
+    }
+
+    imageStore(destinationLevel, texel, vec4(furthestDepth));
+}
\ No newline at end of file
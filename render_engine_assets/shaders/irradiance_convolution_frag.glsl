@@ -0,0 +1,35 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform samplerCube environmentMap;
+
+const float PI = 3.14159265359;
+const float sampleDelta = 0.025;
+
+void main()
+{
+    vec3 normal = cubeFaceDirection(texCoords);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    vec3 irradiance = vec3(0.0);
+    float numberSamples = 0.0;
+
+    // Uniformly samples the hemisphere around the normal, weighting every sample by the cosine of its
+    // angle to the normal, and by the sine to account for samples being denser towards the pole
+    for(float phi = 0.0; phi < 2.0 * PI; phi += sampleDelta)
+    {
+        for(float theta = 0.0; theta < 0.5 * PI; theta += sampleDelta)
+        {
+            vec3 tangentSample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sampleDirection = tangentSample.x * right + tangentSample.y * up + tangentSample.z * normal;
+
+            irradiance += texture(environmentMap, sampleDirection).rgb * cos(theta) * sin(theta);
+            numberSamples += 1.0;
+        }
+    }
+
+    FragColor = vec4(PI * irradiance / numberSamples, 1.0);
+}
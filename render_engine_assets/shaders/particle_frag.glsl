@@ -0,0 +1,37 @@
+in vec2 texCoords;
+in vec4 particleColour;
+in vec3 worldPosition;
+
+out vec4 FragColor;
+
+uniform sampler2DArray gPosition;
+
+uniform vec3 cameraPosition;
+uniform vec2 windowDimensions;
+uniform bool positionsAvailable;
+
+void main()
+{
+    // Particles are soft discs fading out towards their edge
+    float fade = 1.0 - smoothstep(0.5, 1.0, length(texCoords * 2.0 - 1.0));
+
+    if(fade <= 0.0)
+    {
+        discard;
+    }
+
+    if(positionsAvailable)
+    {
+        vec3 scenePosition = texture(gPosition, vec3(gl_FragCoord.xy / windowDimensions, 0)).xyz;
+
+        // The sky box, light sources and pixels nothing was drawn to do not have a world position
+        bool hasPosition = !any(isinf(scenePosition)) && !any(isnan(scenePosition)) && scenePosition != vec3(0.0);
+
+        if(hasPosition && distance(scenePosition, cameraPosition) < distance(worldPosition, cameraPosition))
+        {
+            discard;
+        }
+    }
+
+    FragColor = particleColour * fade;
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+layout (local_size_x = 64) in;
+
+uniform float deltaTime;
+uniform vec3 originShift;
+uniform uint frameSeed;
+
+uint hash(uint value)
+{
+    value ^= value >> 16;
+    value *= 0x7feb352du;
+    value ^= value >> 15;
+    value *= 0x846ca68bu;
+    value ^= value >> 16;
+    return value;
+}
+
+float randomFraction(inout uint seed)
+{
+    seed = hash(seed);
+    return float(seed) / 4294967295.0;
+}
+
+// Every invocation is one particle of one emitter; the y dimension of the dispatch is the emitter, and
+// the x dimension the particle within the particles given to the emitter
+void main()
+{
+    uint emitterIndex = gl_GlobalInvocationID.y;
+    uint particleIndex = gl_GlobalInvocationID.x;
+    ParticleEmitter emitter = emitters[emitterIndex];
+
+    if(particleIndex >= emitter.slots.y)
+    {
+        return;
+    }
+
+    uint slot = emitter.slots.x + particleIndex;
+    Particle particle = particles[slot];
+
+    // Particles are spawned into the slots following the first particle to spawn, wrapping around
+    uint spawnOffset = (particleIndex + emitter.slots.y - emitter.slots.z) % emitter.slots.y;
+
+    if(spawnOffset < emitter.slots.w)
+    {
+        uint seed = hash(slot ^ hash(frameSeed));
+
+        float z = randomFraction(seed) * 2.0 - 1.0;
+        float angle = randomFraction(seed) * 6.28318530718;
+        vec3 direction = vec3(sqrt(1.0 - z * z) * cos(angle), sqrt(1.0 - z * z) * sin(angle), z);
+        vec3 spread = direction * randomFraction(seed) * emitter.positionSpread.w;
+
+        particle.positionAge = vec4(emitter.positionSpread.xyz, 0.0);
+        particle.spreadLifetime = vec4(spread, emitter.lifetimeSize.x);
+    }
+    else if(particle.positionAge.w < particle.spreadLifetime.w)
+    {
+        float age = particle.positionAge.w + deltaTime;
+        vec3 velocity = evaluateCurve(emitter.velocityTimes, emitter.velocityKeys, age / particle.spreadLifetime.w).xyz + particle.spreadLifetime.xyz;
+
+        particle.positionAge = vec4(particle.positionAge.xyz + originShift + velocity * deltaTime, age);
+    }
+    else
+    {
+        return;
+    }
+
+    // The index of an emitter changes as emitters are added and removed, so it is written every frame
+    particle.emitter = uvec4(emitterIndex, 0, 0, 0);
+    particles[slot] = particle;
+}
\ No newline at end of file
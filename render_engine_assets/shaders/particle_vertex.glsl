@@ -0,0 +1,35 @@
+out vec2 texCoords;
+out vec4 particleColour;
+out vec3 worldPosition;
+
+uniform mat4 viewProjection;
+uniform vec3 cameraRight;
+uniform vec3 cameraUp;
+
+// Every particle is a quad facing the camera, generated from the vertex index as a triangle strip. Dead
+// particles are moved outside of the view so that nothing is drawn for them
+void main()
+{
+    Particle particle = particles[gl_InstanceID];
+
+    if(particle.positionAge.w >= particle.spreadLifetime.w)
+    {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+
+    ParticleEmitter emitter = emitters[particle.emitter.x];
+    float lifeFraction = particle.positionAge.w / particle.spreadLifetime.w;
+
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    float size = mix(emitter.lifetimeSize.y, emitter.lifetimeSize.z, lifeFraction);
+    vec4 colour = evaluateCurve(emitter.colourTimes, emitter.colourKeys, lifeFraction);
+
+    worldPosition = particle.positionAge.xyz + (cameraRight * (corner.x - 0.5) + cameraUp * (corner.y - 0.5)) * size;
+    texCoords = corner;
+
+    // Colours are premultiplied by their opacity. Additive particles have no opacity, so nothing behind them is darkened
+    particleColour = vec4(colour.rgb * colour.a, emitter.lifetimeSize.w > 0.5 ? 0.0 : colour.a);
+
+    gl_Position = viewProjection * vec4(worldPosition, 1.0);
+}
\ No newline at end of file
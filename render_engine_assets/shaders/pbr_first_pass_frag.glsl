@@ -0,0 +1,161 @@
+const vec3 SKY_BOX_FRAG = vec3(1.0 / 0.0, 0.0, 0.0);
+const vec3 LIGHT_SOURCE_FRAG = vec3(0.0, 1.0 / 0.0, 0.0);
+
+uniform vec3 skyboxBrightness;
+uniform uint drawingModelsWithTextures;
+// Set to 1 by the mipmap level debug render mode
+uniform int debugMipmapLevel;
+
+// Colours of the mipmap levels shown by the mipmap level debug render mode, from the full resolution level
+const vec3 MIPMAP_LEVEL_COLOURS[6] = vec3[](vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 1.0), vec3(0.0, 1.0, 0.0), vec3(1.0, 1.0, 0.0), vec3(1.0, 0.5, 0.0), vec3(1.0, 0.0, 0.0));
+
+struct TextureInformation
+{
+    uint array_index;
+    uint index_offset;
+};
+
+// Each component of the texture layers holds the locations of two maps
+TextureInformation lower_texture_info(uint packed_info)
+{
+    return TextureInformation( (packed_info & uint(0xFC00)) >> 10, packed_info & uint(0x3FF) );
+}
+
+TextureInformation upper_texture_info(uint packed_info)
+{
+    return TextureInformation( packed_info >> uint(26), (packed_info >> 16) & uint(0x3FF) );
+}
+
+// The mipmap level sampled for the texture coordinates, shown as a colour. Only the texture array loaded with
+// the textures of models has mipmaps
+vec4 mipmapLevelColour(TextureInformation textureLocation, vec2 scaledTexCoords)
+{
+    if(textureLocation.array_index != 1)
+    {
+        return vec4(MIPMAP_LEVEL_COLOURS[0], 1.0);
+    }
+
+    float level = clamp(textureQueryLod(textureArray, scaledTexCoords).x, 0.0, 5.0);
+    return vec4(mix(MIPMAP_LEVEL_COLOURS[int(floor(level))], MIPMAP_LEVEL_COLOURS[int(ceil(level))], fract(level)), 1.0);
+}
+
+vec4 sampleTexture(TextureInformation textureLocation, vec2 scaledTexCoords)
+{
+    switch(textureLocation.array_index)
+    {
+        case 0:
+            return vec4(texture(errorTextureArray, vec3(scaledTexCoords, textureLocation.index_offset)));
+
+        case 1:
+            return vec4(texture(textureArray, vec3(scaledTexCoords, textureLocation.index_offset)));
+
+        case 2:
+            return vec4(texture(solidColour, vec3(scaledTexCoords, textureLocation.index_offset)));
+
+        default:
+            return vec4(0.7, 0.0, 0.0, 0.0);
+    }
+}
+
+// Maps that were not given by the material of a model are left in the error texture array
+bool hasMap(TextureInformation textureLocation)
+{
+    return textureLocation.array_index != 0;
+}
+
+// Without the tangent space of the model, the tangent frame of the normal map is found from how the position
+// and texture coordinates change across the screen
+vec3 perturbNormal(vec3 surfaceNormal, vec3 mapNormal, vec3 positionDx, vec3 positionDy, vec2 texCoordsDx, vec2 texCoordsDy)
+{
+    vec3 perpendicularDy = cross(positionDy, surfaceNormal);
+    vec3 perpendicularDx = cross(surfaceNormal, positionDx);
+    vec3 tangent = perpendicularDy * texCoordsDx.x + perpendicularDx * texCoordsDy.x;
+    vec3 bitangent = perpendicularDy * texCoordsDx.y + perpendicularDx * texCoordsDy.y;
+
+    float largestLength = max(dot(tangent, tangent), dot(bitangent, bitangent));
+
+    if(largestLength == 0.0)
+    {
+        return surfaceNormal;
+    }
+
+    float scale = inversesqrt(largestLength);
+    return normalize(mat3(tangent * scale, bitangent * scale, surfaceNormal) * mapNormal);
+}
+
+void main()
+{
+    vec2 scaledTexCoords = vec2(textureCoords.x * textureCoords.z, textureCoords.y * textureCoords.w);
+    vec3 surfaceNormal = normalize(normalizedVertexNormal);
+
+    // Derivatives are only defined outside of branches that differ between neighbouring fragments
+    vec3 positionDx = dFdx(fragPosition);
+    vec3 positionDy = dFdy(fragPosition);
+    vec2 texCoordsDx = dFdx(scaledTexCoords);
+    vec2 texCoordsDy = dFdy(scaledTexCoords);
+
+    gPosition = useSkyboxTexture == 1 ? SKY_BOX_FRAG : drawingLightSource == 1 ? LIGHT_SOURCE_FRAG : fragPosition;
+    gLightPosition = lightFragPos[2];
+    gNormal = surfaceNormal;
+
+    // Metallic, roughness, occlusion and whether the fragment is lit
+    gMaterial = vec4(materialSurface.x, materialSurface.y, 1.0, 1.0);
+
+    if(useSkyboxTexture == 1)
+    {
+        vec4 skyBoxColour = vec4(texture(skyBox, skyBoxTexCoords));
+        gAlbedoSpec = vec4(skyBoxColour.rgb * skyboxBrightness, skyBoxColour.a);
+        gMaterial = vec4(0.0);
+        return;
+    }
+
+    if(drawingLightSource == 1)
+    {
+        gMaterial = vec4(0.0);
+    }
+
+    if(drawingModelsWithTextures == 0)
+    {
+        gAlbedoSpec = textureCoords * materialAlbedo;
+        return;
+    }
+
+    float brightnessAdjustment = adjustBrightnessLightSource == 1 ? 2.0 : 1.0;
+    gAlbedoSpec = sampleTexture(lower_texture_info(textureLayer.x), scaledTexCoords) * materialAlbedo * brightnessAdjustment;
+
+    if(debugMipmapLevel == 1)
+    {
+        gAlbedoSpec = mipmapLevelColour(lower_texture_info(textureLayer.x), scaledTexCoords);
+    }
+
+    TextureInformation normalMap = lower_texture_info(textureLayer.y);
+    TextureInformation metallicMap = upper_texture_info(textureLayer.z);
+    TextureInformation roughnessMap = lower_texture_info(textureLayer.w);
+    TextureInformation occlusionMap = upper_texture_info(textureLayer.w);
+
+    if(hasMap(normalMap))
+    {
+        vec3 mapNormal = sampleTexture(normalMap, scaledTexCoords).rgb * 2.0 - 1.0;
+        mapNormal.xy *= materialSurface.z;
+#ifdef TANGENT_SPACE
+        gNormal = normalize(tangentBasis * normalize(mapNormal));
+#else
+        gNormal = perturbNormal(surfaceNormal, normalize(mapNormal), positionDx, positionDy, texCoordsDx, texCoordsDy);
+#endif
+    }
+
+    if(hasMap(metallicMap))
+    {
+        gMaterial.r *= sampleTexture(metallicMap, scaledTexCoords).r;
+    }
+
+    if(hasMap(roughnessMap))
+    {
+        gMaterial.g *= sampleTexture(roughnessMap, scaledTexCoords).r;
+    }
+
+    if(hasMap(occlusionMap))
+    {
+        gMaterial.b = mix(1.0, sampleTexture(occlusionMap, scaledTexCoords).r, materialSurface.w);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,275 @@
+in flat uint intRenderingLightVolumes;
+in vec2 textureCoords;
+
+const float PI = 3.14159265359;
+
+// Reflectance of dielectric surfaces looked at straight on
+const vec3 DIELECTRIC_REFLECTANCE = vec3(0.04);
+
+struct Surface
+{
+    vec3 position;
+    vec3 normal;
+    vec3 viewDirection;
+    vec3 albedo;
+    float metallic;
+    float roughness;
+    float occlusion;
+    vec3 reflectance;
+};
+
+// ***** Begin function declarations *****
+
+// *** Light type functions ***
+vec3 calculateDirectionLights(Surface surface);
+vec3 calculatePointLights(Surface surface);
+vec3 calculateSpotLights(Surface surface, vec4 lightFragPosition);
+
+// *** Light calculation functions ***
+vec3 calculateAmbient(Surface surface, vec4 ambientColour);
+vec3 calculateReflectedLight(Surface surface, vec3 lightDirection, vec3 radiance);
+float distributionGGX(vec3 normal, vec3 halfway, float roughness);
+float geometrySmith(float normalDotView, float normalDotLight, float roughness);
+vec3 fresnelSchlick(float cosTheta, vec3 reflectance);
+float calculateAttenuation(vec3 fragPosition, float linear, float quadratic, vec3 lightPosition);
+float shadowCalculation(vec3 lightDirection, vec4 lightFragPosition, vec3 objectNormal);
+
+// ***** End function declarations ******
+
+void main()
+{
+#ifdef BAKED_LIGHTING
+    vec3 bakedIrradiance;
+#endif
+
+    vec4 material = texture(gMaterial, vec3(textureCoords, 0));
+
+    if(intRenderingLightVolumes == 1)
+    {
+
+    }
+    // Sky boxes and light sources are not lit
+    else if(renderSkybox == 1 || material.a == 0.0)
+    {
+        FragColor = texture(gAlbedoSpec, vec3(textureCoords, 0));
+    }
+    else
+    {
+        Surface surface;
+        surface.position = texture(gPosition, vec3(textureCoords, 0)).rgb;
+        surface.normal = normalize(texture(gNormal, vec3(textureCoords, 0)).rgb);
+        surface.viewDirection = normalize(cameraPosition - surface.position);
+        surface.albedo = texture(gAlbedoSpec, vec3(textureCoords, 0)).rgb;
+        surface.metallic = clamp(material.r, 0.0, 1.0);
+        // Perfectly smooth surfaces give infinitely small highlights
+        surface.roughness = clamp(material.g, 0.04, 1.0);
+        surface.occlusion = material.b;
+        surface.reflectance = mix(DIELECTRIC_REFLECTANCE, surface.albedo, surface.metallic);
+
+        vec3 lightColour = vec3(0.0);
+        vec3 ambient = surface.albedo * defaultDiffuseFactor;
+
+#ifdef ENVIRONMENT_LIGHTING
+        vec3 environmentAmbient;
+
+        if(environmentLighting(surface.position, cameraPosition, surface.normal, surface.albedo, surface.roughness, surface.metallic, environmentAmbient))
+        {
+            ambient = environmentAmbient;
+        }
+#endif
+
+#ifdef BAKED_LIGHTING
+        // Static world sections are lit entirely by the lighting baked for them
+        if(sampleBakedLighting(surface.position, bakedIrradiance))
+        {
+            lightColour = surface.albedo * bakedIrradiance * surface.occlusion;
+        }
+        else
+#endif
+        if(anyLightSourceVisible == 0)
+        {
+            lightColour = ambient * surface.occlusion;
+        }
+        else
+        {
+            vec4 lightFragPosition = texture(gLightPosition, vec3(textureCoords, 0));
+
+            lightColour = calculateDirectionLights(surface);
+            lightColour += calculatePointLights(surface);
+            lightColour += calculateSpotLights(surface, lightFragPosition);
+
+            // Surfaces far from any light are given the ambient lighting, as they are without any lights
+            lightColour.r += int(lightColour.r < noLightSourceCutoff) * ambient.r * surface.occlusion;
+            lightColour.g += int(lightColour.g < noLightSourceCutoff) * ambient.g * surface.occlusion;
+            lightColour.b += int(lightColour.b < noLightSourceCutoff) * ambient.b * surface.occlusion;
+        }
+
+        FragColor = vec4(max(lightColour, 0.0), 1.0);
+    }
+}
+
+vec3 calculateDirectionLights(Surface surface)
+{
+    vec3 lightColour = vec3(0.0, 0.0, 0.0);
+
+    for(int i = 0; i < numberDirectionLights; ++i)
+    {
+        DirectionLight light = directionLights[i];
+
+        lightColour += calculateAmbient(surface, light.ambientColour);
+        lightColour += calculateReflectedLight(surface, normalize(-light.direction.xyz), light.diffuseColour.rgb);
+    }
+
+    return lightColour;
+}
+
+vec3 calculatePointLights(Surface surface)
+{
+    vec3 lightColour = vec3(0.0, 0.0, 0.0);
+
+#ifdef CLUSTERED_LIGHTING
+    uvec4 cluster = findLightCluster(surface.position);
+
+    for(uint i = 0; i < cluster.y; ++i)
+    {
+        PointLight light = pointLights[clusterLightIndexes[cluster.x + i]];
+#else
+    for(int i = 0; i < numberPointLights; ++i)
+    {
+        PointLight light = pointLights[i];
+#endif
+
+        vec3 negativeLightDirection = normalize(light.position.xyz - surface.position);
+        float attenuation = calculateAttenuation(surface.position, light.attenuation.x, light.attenuation.y, light.position.xyz);
+
+        lightColour += calculateAmbient(surface, light.ambientColour) * attenuation;
+        lightColour += calculateReflectedLight(surface, negativeLightDirection, light.diffuseColour.rgb) * attenuation;
+    }
+
+    return lightColour;
+}
+
+vec3 calculateSpotLights(Surface surface, vec4 lightFragPosition)
+{
+    vec3 lightColour = vec3(0.0, 0.0, 0.0);
+
+#ifdef CLUSTERED_LIGHTING
+    uvec4 cluster = findLightCluster(surface.position);
+
+    for(uint i = 0; i < cluster.w; ++i)
+    {
+        SpotLight light = spotLights[clusterLightIndexes[cluster.z + i]];
+#else
+    for(int i = 0; i < numberSpotLights; ++i)
+    {
+        SpotLight light = spotLights[i];
+#endif
+
+        // Area and emissive lights shine from the point of their shape closest to the fragment
+        vec3 lightPoint;
+
+        if(!closestSpotLightPoint(light, surface.position, lightPoint))
+        {
+            continue;
+        }
+
+        // The w component of the position holds the radius of the light
+        if(length(lightPoint - surface.position) > light.position.w)
+        {
+            continue;
+        }
+
+        vec3 negativeLightDirection = normalize(lightPoint - surface.position);
+        float shadowValue = shadowCalculation(negativeLightDirection, lightFragPosition, surface.normal);
+
+        float attenuation = calculateAttenuation(surface.position, light.attenuation.x, light.attenuation.y, lightPoint);
+        lightColour += calculateAmbient(surface, light.ambientColour) * attenuation;
+        lightColour += calculateReflectedLight(surface, negativeLightDirection, light.diffuseColour.rgb) * attenuation * shadowValue;
+    }
+
+    return lightColour;
+}
+
+vec3 calculateAmbient(Surface surface, vec4 ambientColour)
+{
+    return surface.albedo * ambientColour.rgb * ambientColour.a * surface.occlusion;
+}
+
+// Cook-Torrance reflection of the light arriving from the given direction
+vec3 calculateReflectedLight(Surface surface, vec3 lightDirection, vec3 radiance)
+{
+    vec3 halfway = normalize(surface.viewDirection + lightDirection);
+    float normalDotView = max(dot(surface.normal, surface.viewDirection), 0.0);
+    float normalDotLight = max(dot(surface.normal, lightDirection), 0.0);
+
+    float distribution = distributionGGX(surface.normal, halfway, surface.roughness);
+    float geometry = geometrySmith(normalDotView, normalDotLight, surface.roughness);
+    vec3 fresnel = fresnelSchlick(max(dot(halfway, surface.viewDirection), 0.0), surface.reflectance);
+
+    vec3 specular = distribution * geometry * fresnel / (4.0 * normalDotView * normalDotLight + 0.0001);
+
+    // Light that is not reflected is refracted into the surface and scattered diffusely, except by metals
+    // which absorb it
+    vec3 diffuseFactor = (vec3(1.0) - fresnel) * (1.0 - surface.metallic);
+
+    return (diffuseFactor * surface.albedo / PI + specular) * radiance * normalDotLight;
+}
+
+float distributionGGX(vec3 normal, vec3 halfway, float roughness)
+{
+    float alpha = roughness * roughness;
+    float alphaSquared = alpha * alpha;
+    float normalDotHalfway = max(dot(normal, halfway), 0.0);
+    float denominator = normalDotHalfway * normalDotHalfway * (alphaSquared - 1.0) + 1.0;
+    return alphaSquared / (PI * denominator * denominator);
+}
+
+float geometrySmith(float normalDotView, float normalDotLight, float roughness)
+{
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    float viewGeometry = normalDotView / (normalDotView * (1.0 - k) + k);
+    float lightGeometry = normalDotLight / (normalDotLight * (1.0 - k) + k);
+    return viewGeometry * lightGeometry;
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 reflectance)
+{
+    return reflectance + (1.0 - reflectance) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+float calculateAttenuation(vec3 fragPosition, float linear, float quadratic, vec3 lightPosition)
+{
+    float distanceToFrag = length(lightPosition - fragPosition);
+    return 1.0 / (1.0 + linear * distanceToFrag + quadratic * distanceToFrag * distanceToFrag);
+}
+
+float shadowCalculation(vec3 lightDirection, vec4 lightFragPosition, vec3 objectNormal)
+{
+    float bias = max(0.005 * (1.0 - dot(objectNormal, lightDirection)), 0.00001);
+
+    vec3 projCoords = lightFragPosition.xyz / lightFragPosition.w;
+    projCoords = projCoords * 0.5 + 0.5;
+
+    if(projCoords.z > 1.0)
+    {
+        return 1.0;
+    }
+
+    if(projCoords.x < 0 || projCoords.x > 1 || projCoords.y < 0 || projCoords.y > 1)
+    {
+        return 1.0;
+    }
+
+    float currentDepth = projCoords.z - 0.00005;
+    float closestDepth = texture(shadowMaps, vec3(projCoords.xy, 2)).r;
+
+    if(currentDepth < closestDepth && currentDepth > 0.01)
+    {
+        return 1.0;
+    }
+
+    // The filtering of the shadow map is generated from the shadow quality of the render system. If shadow
+    // maps aren't available for whatever reason, a light should still influence the scene
+    float shadow = shadowFactor(projCoords, currentDepth - bias, 2);
+    return max(1.0 - shadow, 0.5);
+}
\ No newline at end of file
@@ -0,0 +1,124 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D sourceTexture;
+uniform sampler2D bloomTexture;
+
+// The effect drawn, and its parameters
+uniform int effect;
+uniform vec4 parameters;
+// The size of one texel of the source texture
+uniform vec2 texelSize;
+
+const int EFFECT_COPY = 0;
+const int EFFECT_BRIGHT_PASS = 1;
+const int EFFECT_BLUR = 2;
+const int EFFECT_BLOOM_COMPOSITE = 3;
+const int EFFECT_TONE_MAP = 4;
+const int EFFECT_GAMMA = 5;
+const int EFFECT_VIGNETTE = 6;
+const int EFFECT_FXAA = 7;
+
+const vec3 LUMINANCE = vec3(0.2126, 0.7152, 0.0722);
+
+// Weights of a nine tap gaussian blur, from the centre outwards
+const float BLUR_WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+vec3 blur(vec2 direction)
+{
+    vec3 colour = texture(sourceTexture, texCoords).rgb * BLUR_WEIGHTS[0];
+
+    for(int i = 1; i < 5; ++i)
+    {
+        vec2 offset = direction * texelSize * float(i);
+        colour += texture(sourceTexture, texCoords + offset).rgb * BLUR_WEIGHTS[i];
+        colour += texture(sourceTexture, texCoords - offset).rgb * BLUR_WEIGHTS[i];
+    }
+
+    return colour;
+}
+
+vec3 toneMap(vec3 colour, float exposure, bool aces)
+{
+    colour *= exposure;
+
+    if(aces)
+    {
+        return clamp((colour * (2.51 * colour + 0.03)) / (colour * (2.43 * colour + 0.59) + 0.14), 0.0, 1.0);
+    }
+
+    return colour / (colour + vec3(1.0));
+}
+
+vec3 fxaa()
+{
+    const float SPAN_MAX = 8.0;
+    const float REDUCE_MULTIPLIER = 1.0 / 8.0;
+    const float REDUCE_MIN = 1.0 / 128.0;
+
+    vec3 colourNorthWest = texture(sourceTexture, texCoords + vec2(-1.0, -1.0) * texelSize).rgb;
+    vec3 colourNorthEast = texture(sourceTexture, texCoords + vec2(1.0, -1.0) * texelSize).rgb;
+    vec3 colourSouthWest = texture(sourceTexture, texCoords + vec2(-1.0, 1.0) * texelSize).rgb;
+    vec3 colourSouthEast = texture(sourceTexture, texCoords + vec2(1.0, 1.0) * texelSize).rgb;
+    vec3 colourMiddle = texture(sourceTexture, texCoords).rgb;
+
+    float lumaNorthWest = dot(colourNorthWest, LUMINANCE);
+    float lumaNorthEast = dot(colourNorthEast, LUMINANCE);
+    float lumaSouthWest = dot(colourSouthWest, LUMINANCE);
+    float lumaSouthEast = dot(colourSouthEast, LUMINANCE);
+    float lumaMiddle = dot(colourMiddle, LUMINANCE);
+
+    float lumaMin = min(lumaMiddle, min(min(lumaNorthWest, lumaNorthEast), min(lumaSouthWest, lumaSouthEast)));
+    float lumaMax = max(lumaMiddle, max(max(lumaNorthWest, lumaNorthEast), max(lumaSouthWest, lumaSouthEast)));
+
+    // The edge runs perpendicular to the direction the brightness changes fastest in
+    vec2 direction = vec2(-((lumaNorthWest + lumaNorthEast) - (lumaSouthWest + lumaSouthEast)), (lumaNorthWest + lumaSouthWest) - (lumaNorthEast + lumaSouthEast));
+
+    float directionReduce = max((lumaNorthWest + lumaNorthEast + lumaSouthWest + lumaSouthEast) * 0.25 * REDUCE_MULTIPLIER, REDUCE_MIN);
+    float inverseDirectionMin = 1.0 / (min(abs(direction.x), abs(direction.y)) + directionReduce);
+    direction = clamp(direction * inverseDirectionMin, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texelSize;
+
+    vec3 colourNear = 0.5 * (texture(sourceTexture, texCoords + direction * (1.0 / 3.0 - 0.5)).rgb +
+                             texture(sourceTexture, texCoords + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 colourFar = colourNear * 0.5 + 0.25 * (texture(sourceTexture, texCoords - direction * 0.5).rgb +
+                                                texture(sourceTexture, texCoords + direction * 0.5).rgb);
+
+    // Sampling further along the edge crossed another edge, so only the nearer samples are used
+    float lumaFar = dot(colourFar, LUMINANCE);
+    return (lumaFar < lumaMin || lumaFar > lumaMax) ? colourNear : colourFar;
+}
+
+void main()
+{
+    vec4 colour = texture(sourceTexture, texCoords);
+
+    switch(effect)
+    {
+        case EFFECT_BRIGHT_PASS:
+            colour.rgb = dot(colour.rgb, LUMINANCE) > parameters.x ? colour.rgb : vec3(0.0);
+            break;
+        case EFFECT_BLUR:
+            colour.rgb = blur(parameters.xy);
+            break;
+        case EFFECT_BLOOM_COMPOSITE:
+            colour.rgb += texture(bloomTexture, texCoords).rgb * parameters.x;
+            break;
+        case EFFECT_TONE_MAP:
+            colour.rgb = toneMap(max(colour.rgb, vec3(0.0)), parameters.x, parameters.y != 0.0);
+            break;
+        case EFFECT_GAMMA:
+            colour.rgb = pow(max(colour.rgb, vec3(0.0)), vec3(1.0 / parameters.x));
+            break;
+        case EFFECT_VIGNETTE:
+            colour.rgb *= 1.0 - parameters.x * smoothstep(parameters.y, 1.0, distance(texCoords, vec2(0.5)) * 1.41421356);
+            break;
+        case EFFECT_FXAA:
+            colour.rgb = fxaa();
+            break;
+        default:
+            break;
+    }
+
+    FragColor = vec4(colour.rgb, 1.0);
+}
\ No newline at end of file
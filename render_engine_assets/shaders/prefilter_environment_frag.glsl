@@ -0,0 +1,77 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform samplerCube environmentMap;
+uniform float roughness;
+uniform float environmentResolution;
+
+const float PI = 3.14159265359;
+const uint sampleCount = 512u;
+
+float radicalInverse(uint bits)
+{
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec3 importanceSampleGGX(vec2 xi, vec3 normal, float sampleRoughness)
+{
+    float a = sampleRoughness * sampleRoughness;
+    float phi = 2.0 * PI * xi.x;
+    float cosTheta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sinTheta = sqrt(1.0 - cosTheta * cosTheta);
+
+    vec3 halfway = vec3(cos(phi) * sinTheta, sin(phi) * sinTheta, cosTheta);
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+
+    return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+}
+
+float distributionGGX(float normalDotHalfway, float sampleRoughness)
+{
+    float a = sampleRoughness * sampleRoughness;
+    float denominator = normalDotHalfway * normalDotHalfway * (a * a - 1.0) + 1.0;
+    return (a * a) / (PI * denominator * denominator);
+}
+
+void main()
+{
+    // The view direction is assumed to be the same as the reflected direction, which loses the stretched
+    // reflections seen at grazing angles but lets the result be looked up by direction alone
+    vec3 normal = cubeFaceDirection(texCoords);
+    vec3 view = normal;
+
+    vec3 prefilteredColour = vec3(0.0);
+    float totalWeight = 0.0;
+
+    for(uint i = 0u; i < sampleCount; ++i)
+    {
+        vec2 xi = vec2(float(i) / float(sampleCount), radicalInverse(i));
+        vec3 halfway = importanceSampleGGX(xi, normal, roughness);
+        vec3 lightDirection = normalize(2.0 * dot(view, halfway) * halfway - view);
+        float normalDotLight = max(dot(normal, lightDirection), 0.0);
+
+        if(normalDotLight > 0.0)
+        {
+            // Samples covering a larger solid angle read a smaller mip of the environment, which avoids
+            // bright spots from samples landing on small, bright parts of the environment
+            float normalDotHalfway = max(dot(normal, halfway), 0.0);
+            float pdf = distributionGGX(normalDotHalfway, roughness) * 0.25 + 0.0001;
+            float texelSolidAngle = 4.0 * PI / (6.0 * environmentResolution * environmentResolution);
+            float sampleSolidAngle = 1.0 / (float(sampleCount) * pdf + 0.0001);
+            float mipLevel = roughness == 0.0 ? 0.0 : 0.5 * log2(sampleSolidAngle / texelSolidAngle);
+
+            prefilteredColour += textureLod(environmentMap, lightDirection, mipLevel).rgb * normalDotLight;
+            totalWeight += normalDotLight;
+        }
+    }
+
+    FragColor = vec4(prefilteredColour / max(totalWeight, 0.0001), 1.0);
+}
@@ -0,0 +1,11 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2D reflectionTexture;
+
+void main()
+{
+    // Blended with the frame by the alpha, which is how much of the reflection is seen
+    FragColor = texture(reflectionTexture, texCoords);
+}
\ No newline at end of file
@@ -0,0 +1,143 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2DArray gPosition;
+uniform sampler2DArray gNormal;
+uniform sampler2DArray gMaterial;
+uniform sampler2D sceneTexture;
+
+uniform mat4 viewMatrix;
+uniform mat4 projectionMatrix;
+uniform vec3 cameraPosition;
+uniform int useMaterialRoughness;
+uniform int steps;
+uniform float thickness;
+uniform float edgeFade;
+uniform float maxDistance;
+
+// The G-Buffer of the default render system holds no material properties, so its surfaces are given the
+// roughness the second pass lights them with
+const float DEFAULT_ROUGHNESS = 0.8;
+
+// Number of times the step that passed behind a surface is halved to find where the ray hit it
+const int REFINEMENT_STEPS = 4;
+
+// Distance between the ray and the surface of the frame at the same place on the screen; positive if the
+// ray is behind the surface. The screen position is written to screenPosition
+float depthDifference(vec3 rayPosition, out vec2 screenPosition)
+{
+    vec4 viewPosition = viewMatrix * vec4(rayPosition, 1.0);
+
+    // Behind the camera, where nothing of the frame can be reflected
+    if(viewPosition.z >= 0.0)
+    {
+        screenPosition = vec2(-1.0);
+        return 0.0;
+    }
+
+    vec4 clipPosition = projectionMatrix * viewPosition;
+    screenPosition = clipPosition.xy / clipPosition.w * 0.5 + 0.5;
+
+    vec3 surfacePosition = texture(gPosition, vec3(screenPosition, 0)).rgb;
+
+    // Sky boxes and light sources are infinitely far away
+    if(any(isinf(surfacePosition)))
+    {
+        return -1.0 / 0.0;
+    }
+
+    float surfaceDepth = -(viewMatrix * vec4(surfacePosition, 1.0)).z;
+    return -viewPosition.z - surfaceDepth;
+}
+
+bool onScreen(vec2 screenPosition)
+{
+    return all(greaterThanEqual(screenPosition, vec2(0.0))) && all(lessThanEqual(screenPosition, vec2(1.0)));
+}
+
+void main()
+{
+    FragColor = vec4(0.0);
+
+    vec3 position = texture(gPosition, vec3(texCoords, 0)).rgb;
+
+    // Sky boxes and light sources do not reflect
+    if(any(isinf(position)))
+    {
+        return;
+    }
+
+    float roughness = DEFAULT_ROUGHNESS;
+
+    if(useMaterialRoughness == 1)
+    {
+        vec4 material = texture(gMaterial, vec3(texCoords, 0));
+
+        // Unlit fragments, such as light sources, do not reflect
+        if(material.a == 0.0)
+        {
+            return;
+        }
+
+        roughness = material.g;
+    }
+
+    float reflectivity = 1.0 - clamp(roughness, 0.0, 1.0);
+
+    if(reflectivity <= 0.0)
+    {
+        return;
+    }
+
+    vec3 normal = normalize(texture(gNormal, vec3(texCoords, 0)).rgb);
+    vec3 viewDirection = normalize(position - cameraPosition);
+    vec3 reflectedDirection = normalize(reflect(viewDirection, normal));
+    vec3 rayStep = reflectedDirection * (maxDistance / float(steps));
+
+    vec3 rayPosition = position;
+    vec2 screenPosition;
+
+    for(int i = 1; i <= steps; ++i)
+    {
+        rayPosition += rayStep;
+        float difference = depthDifference(rayPosition, screenPosition);
+
+        if(!onScreen(screenPosition))
+        {
+            return;
+        }
+
+        if(difference <= 0.0 || difference > thickness)
+        {
+            continue;
+        }
+
+        // The ray hit a surface within the last step; halve the step to move the ray closer to the surface
+        vec3 refineStep = rayStep;
+
+        for(int j = 0; j < REFINEMENT_STEPS; ++j)
+        {
+            refineStep *= 0.5;
+            rayPosition += difference > 0.0 ? -refineStep : refineStep;
+
+            vec2 refinedScreenPosition;
+            float refinedDifference = depthDifference(rayPosition, refinedScreenPosition);
+
+            if(onScreen(refinedScreenPosition))
+            {
+                difference = refinedDifference;
+                screenPosition = refinedScreenPosition;
+            }
+        }
+
+        // Reflections fade out towards the edges of the window and the end of the ray, where they would
+        // otherwise stop abruptly
+        vec2 edgeDistance = min(screenPosition, 1.0 - screenPosition);
+        float edgeFactor = edgeFade > 0.0 ? clamp(min(edgeDistance.x, edgeDistance.y) / edgeFade, 0.0, 1.0) : 1.0;
+        float distanceFactor = 1.0 - float(i - 1) / float(steps);
+
+        FragColor = vec4(texture(sceneTexture, screenPosition).rgb, reflectivity * edgeFactor * distanceFactor);
+        return;
+    }
+}
\ No newline at end of file
@@ -17,8 +17,18 @@ float shadowCalculation(vec3 lightDirection, vec4 lightFragPosition, vec3 object
 
 // ***** End function declarations ******
 
+#ifdef ENVIRONMENT_LIGHTING
+// The G-Buffer holds no material properties, so surfaces are treated as rough dielectrics that mostly
+// reflect the environment diffusely
+const float ENVIRONMENT_ROUGHNESS = 0.8;
+#endif
+
 void main()
 {
+#ifdef BAKED_LIGHTING
+    vec3 bakedIrradiance;
+#endif
+
     if(intRenderingLightVolumes == 1)
     {
 
@@ -27,10 +37,28 @@ void main()
     {
         FragColor = texture(gAlbedoSpec, vec3(textureCoords, 0));
     }
+#ifdef BAKED_LIGHTING
+    // Static world sections are lit entirely by the lighting baked for them
+    else if(sampleBakedLighting(texture(gPosition, vec3(textureCoords, 0)).rgb, bakedIrradiance))
+    {
+        vec3 objectDiffuse = texture(gAlbedoSpec, vec3(textureCoords, 0)).rgb;
+        FragColor = vec4(max(objectDiffuse * bakedIrradiance, 0.0), 1.0);
+    }
+#endif
     else if(anyLightSourceVisible == 0)
     {
         vec3 objectDiffuse = texture(gAlbedoSpec, vec3(textureCoords, 0)).rgb;
         FragColor = vec4(calculateAmbient(objectDiffuse, vec4(1.0, 1.0, 1.0, defaultDiffuseFactor)), 1.0);
+
+#ifdef ENVIRONMENT_LIGHTING
+        vec3 environmentAmbient;
+
+        if(environmentLighting(texture(gPosition, vec3(textureCoords, 0)).rgb, cameraPosition, texture(gNormal, vec3(textureCoords, 0)).rgb,
+                               objectDiffuse, ENVIRONMENT_ROUGHNESS, 0.0, environmentAmbient))
+        {
+            FragColor = vec4(max(environmentAmbient, 0.0), 1.0);
+        }
+#endif
     }
     else
     {
@@ -47,9 +75,18 @@ void main()
         lightColour.g += int(lightColour.g < noLightSourceCutoff) * objectDiffuse.g * defaultDiffuseFactor;
         lightColour.b += int(lightColour.b < noLightSourceCutoff) * objectDiffuse.b * defaultDiffuseFactor;
 
-        lightColour.r = clamp(lightColour.r, 0.0, 1.0);
-        lightColour.g = clamp(lightColour.g, 0.0, 1.0);
-        lightColour.b = clamp(lightColour.b, 0.0, 1.0);
+#ifdef ENVIRONMENT_LIGHTING
+        vec3 environmentAmbient;
+
+        if(environmentLighting(fragPosition, cameraPosition, objectNormal, objectDiffuse, ENVIRONMENT_ROUGHNESS, 0.0, environmentAmbient))
+        {
+            lightColour += environmentAmbient;
+        }
+#endif
+
+        lightColour.r = max(lightColour.r, 0.0);
+        lightColour.g = max(lightColour.g, 0.0);
+        lightColour.b = max(lightColour.b, 0.0);
 
         FragColor = vec4(lightColour, 1.0);
     }
@@ -61,9 +98,11 @@ vec3 calculateDirectionLights(vec3 fragPosition, vec3 objectNormal, vec3 objectD
 
     for(int i = 0; i < numberDirectionLights; ++i)
     {
-        lightColour += calculateAmbient(objectDiffuse, directionLightAmbientColour[i]);
-        lightColour += calculateDiffuse(-directionLightDirection[i], directionLightDiffuseColour[i], objectNormal, objectDiffuse);
-        lightColour += calculateSpecular(fragPosition, -directionLightDirection[i], directionLightSpecularColour[i], objectNormal, 64.0);
+        DirectionLight light = directionLights[i];
+
+        lightColour += calculateAmbient(objectDiffuse, light.ambientColour);
+        lightColour += calculateDiffuse(-light.direction.xyz, light.diffuseColour.rgb, objectNormal, objectDiffuse);
+        lightColour += calculateSpecular(fragPosition, -light.direction.xyz, light.specularColour.rgb, objectNormal, 64.0);
     }
 
     return lightColour;
@@ -73,18 +112,29 @@ vec3 calculatePointLights(vec3 fragPosition, vec3 objectNormal, vec3 objectDiffu
 {
     vec3 lightColour = vec3(0.0, 0.0, 0.0);
 
+#ifdef CLUSTERED_LIGHTING
+    uvec4 cluster = findLightCluster(fragPosition);
+
+    for(uint i = 0; i < cluster.y; ++i)
+    {
+        PointLight light = pointLights[clusterLightIndexes[cluster.x + i]];
+#else
     for(int i = 0; i < numberPointLights; ++i)
     {
-        float angleFragLight = dot((normalize(fragPosition) - pointLightPosition[i]), normalize(pointLightDirection[i]));
-        float epsilon = cutOff[i] - outerCutoff[i];
-        float intensity = clamp((angleFragLight - outerCutoff[i]) / epsilon, 0.0, 1.0);
+        PointLight light = pointLights[i];
+#endif
 
-        vec3 negativeLightDirection = normalize(pointLightPosition[i] - fragPosition);
+        // Attenuation stores: linear coefficient, quadratic coefficient, cutoff, outer cutoff
+        float angleFragLight = dot((normalize(fragPosition) - light.position.xyz), normalize(light.direction.xyz));
+        float epsilon = light.attenuation.z - light.attenuation.w;
+        float intensity = clamp((angleFragLight - light.attenuation.w) / epsilon, 0.0, 1.0);
 
-        float attenuation = calculateAttenuation(fragPosition, pointLightLinearCoefficient[i], pointLightQuadraticCoefficient[i], pointLightPosition[i]);
-        lightColour += calculateAmbient(objectDiffuse, pointLightAmbientColour[i]) * attenuation;
-        lightColour += calculateDiffuse(negativeLightDirection, pointLightDiffuseColour[i], objectNormal, objectDiffuse) * attenuation * intensity;
-        lightColour += calculateSpecular(fragPosition, negativeLightDirection, pointLightSpecularColour[i], objectNormal, 64.0) * attenuation;
+        vec3 negativeLightDirection = normalize(light.position.xyz - fragPosition);
+
+        float attenuation = calculateAttenuation(fragPosition, light.attenuation.x, light.attenuation.y, light.position.xyz);
+        lightColour += calculateAmbient(objectDiffuse, light.ambientColour) * attenuation;
+        lightColour += calculateDiffuse(negativeLightDirection, light.diffuseColour.rgb, objectNormal, objectDiffuse) * attenuation * intensity;
+        lightColour += calculateSpecular(fragPosition, negativeLightDirection, light.specularColour.rgb, objectNormal, 64.0) * attenuation;
     }
 
     return lightColour;
@@ -94,20 +144,39 @@ vec3 calculateSpotLights(vec3 fragPosition, vec3 objectNormal, vec3 objectDiffus
 {
     vec3 lightColour = vec3(0.0, 0.0, 0.0);
 
+#ifdef CLUSTERED_LIGHTING
+    uvec4 cluster = findLightCluster(fragPosition);
+
+    for(uint i = 0; i < cluster.w; ++i)
+    {
+        SpotLight light = spotLights[clusterLightIndexes[cluster.z + i]];
+#else
     for(int i = 0; i < numberSpotLights; ++i)
     {
-        if(length(spotLightPosition[i] - fragPosition) > spotLightRadius[i])
+        SpotLight light = spotLights[i];
+#endif
+
+        // Area and emissive lights shine from the point of their shape closest to the fragment
+        vec3 lightPoint;
+
+        if(!closestSpotLightPoint(light, fragPosition, lightPoint))
         {
             continue;
         }
 
-        vec3 negativeLightDirection = normalize(spotLightPosition[i] - fragPosition);
+        // The w component of the position holds the radius of the light
+        if(length(lightPoint - fragPosition) > light.position.w)
+        {
+            continue;
+        }
+
+        vec3 negativeLightDirection = normalize(lightPoint - fragPosition);
         float shadowValue = shadowCalculation(negativeLightDirection, lightFragPosition, objectNormal);
 
-        float attenuation = calculateAttenuation(fragPosition, spotLightLinearCoefficient[i], spotLightQuadraticCoefficient[i], spotLightPosition[i]);
-        lightColour += calculateAmbient(objectDiffuse, spotLightAmbientColour[i]) * attenuation;
-        lightColour += calculateDiffuse(negativeLightDirection, spotLightDiffuseColour[i], objectNormal, objectDiffuse) * attenuation;
-        lightColour += calculateSpecular(fragPosition, negativeLightDirection, spotLightSpecularColour[i], objectNormal, 64.0) * attenuation;
+        float attenuation = calculateAttenuation(fragPosition, light.attenuation.x, light.attenuation.y, lightPoint);
+        lightColour += calculateAmbient(objectDiffuse, light.ambientColour) * attenuation;
+        lightColour += calculateDiffuse(negativeLightDirection, light.diffuseColour.rgb, objectNormal, objectDiffuse) * attenuation;
+        lightColour += calculateSpecular(fragPosition, negativeLightDirection, light.specularColour.rgb, objectNormal, 64.0) * attenuation;
     }
 
     return lightColour;
@@ -182,24 +251,13 @@ float shadowCalculation(vec3 lightDirection, vec4 lightFragPosition, vec3 object
                 clamping will simply reduce the effective number of texels for filtering, which is not ideal but is still correct.
             */
 
-            float shadow = 0.0;
-            vec2 texelSize = 1.0 / textureSize(shadowMaps, 0).xy;
-
-            for(int x = -1; x < 2; ++x)
-            {
-                for(int y = -1; y < 2; ++y)
-                {
-                    float xCoord = clamp((projCoords.x + y) * texelSize.x, 0.0, 1.0);
-                    float yCoord = clamp((projCoords.y + y) * texelSize.y, 0.0, 1.0);
-
-                   float pcfDepth = texture(shadowMaps, vec3(xCoord, yCoord, 2)).r;
-                   shadow += currentDepth - bias > pcfDepth  ? 1.0 : 0.0;
-                }
-            }
+            // The filtering of the shadow map, including the clamping described above, is generated
+            // from the shadow quality of the render system
+            float shadow = shadowFactor(projCoords, currentDepth - bias, 2);
 
             // If shadow maps aren't available for whatever reason, a light should still influence the scene.
             // If the return value is 0 then it won't
-            return max(shadow / 9.0, 0.5);
+            return max(1.0 - shadow, 0.5);
         }
     }
 
@@ -0,0 +1,8 @@
+in vec3 colour;
+
+out vec4 FragColor;
+
+void main()
+{
+    FragColor = vec4(colour, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+out vec3 colour;
+
+// Two triangles generated from the vertex and instance index, requiring no vertex buffers. The first
+// instance is an orange triangle in front of the second, a larger blue triangle, so that the depth
+// test decides the colour where they overlap
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) - 0.5;
+
+    if(gl_InstanceID == 0)
+    {
+        colour = vec3(1.0, 0.5, 0.25);
+        gl_Position = vec4(position, 0.0, 1.0);
+    }
+    else
+    {
+        colour = vec3(0.0, 0.0, 1.0);
+        gl_Position = vec4(position * 1.5, 0.6, 1.0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,10 @@
+in vec3 direction;
+
+out vec4 FragColor;
+
+uniform samplerCube skybox;
+
+void main()
+{
+    FragColor = vec4(texture(skybox, direction).rgb, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,14 @@
+uniform mat4 inverseViewProjection;
+
+out vec3 direction;
+
+// Fullscreen triangle on the far plane, giving the direction from the camera through each fragment
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    vec4 farPoint = inverseViewProjection * vec4(position, 1.0, 1.0);
+    direction = farPoint.xyz / farPoint.w;
+
+    // A depth equal to the cleared depth, so the sky only covers fragments nothing was drawn to
+    gl_Position = vec4(position, 1.0, 1.0);
+}
\ No newline at end of file
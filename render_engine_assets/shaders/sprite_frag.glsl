@@ -0,0 +1,39 @@
+in vec2 texCoords;
+in vec4 spriteColour;
+in vec3 worldPosition;
+flat in int occluded;
+
+out vec4 FragColor;
+
+uniform sampler2D spriteTexture;
+uniform sampler2DArray gPosition;
+
+uniform vec3 cameraPosition;
+uniform vec2 windowDimensions;
+uniform bool positionsAvailable;
+
+void main()
+{
+    vec4 colour = texture(spriteTexture, texCoords) * spriteColour;
+
+    if(colour.a <= 0.0)
+    {
+        discard;
+    }
+
+    if(positionsAvailable && occluded == 1)
+    {
+        vec3 scenePosition = texture(gPosition, vec3(gl_FragCoord.xy / windowDimensions, 0)).xyz;
+
+        // The sky box, light sources and pixels nothing was drawn to do not have a world position
+        bool hasPosition = !any(isinf(scenePosition)) && !any(isnan(scenePosition)) && scenePosition != vec3(0.0);
+
+        if(hasPosition && distance(scenePosition, cameraPosition) < distance(worldPosition, cameraPosition))
+        {
+            discard;
+        }
+    }
+
+    // Colours are premultiplied by their opacity when blended over the frame
+    FragColor = vec4(colour.rgb * colour.a, colour.a);
+}
\ No newline at end of file
@@ -0,0 +1,43 @@
+struct Sprite
+{
+    // xyz is the position of the pivot relative to the render origin, w is 1 if the sprite is hidden behind geometry
+    vec4 pivotOccluded;
+    // xyz is the direction and length of the width of the sprite, w is the horizontal pivot
+    vec4 rightPivot;
+    // xyz is the direction and length of the height of the sprite, w is the vertical pivot
+    vec4 upPivot;
+    // xy is the top left of the texture region, zw its bottom right
+    vec4 region;
+    vec4 colour;
+};
+
+layout (std430, binding = 18) readonly buffer Sprites
+{
+    Sprite sprites[];
+};
+
+out vec2 texCoords;
+out vec4 spriteColour;
+out vec3 worldPosition;
+flat out int occluded;
+
+uniform mat4 viewProjection;
+uniform int firstSprite;
+
+// Every sprite is a quad generated from the vertex index as a triangle strip
+void main()
+{
+    Sprite sprite = sprites[firstSprite + gl_InstanceID];
+
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec2 pivot = vec2(sprite.rightPivot.w, sprite.upPivot.w);
+
+    worldPosition = sprite.pivotOccluded.xyz + sprite.rightPivot.xyz * (corner.x - pivot.x) + sprite.upPivot.xyz * (corner.y - pivot.y);
+
+    // Regions are given from the top left of the image, which is loaded without being flipped
+    texCoords = mix(sprite.region.xy, sprite.region.zw, vec2(corner.x, 1.0 - corner.y));
+    spriteColour = sprite.colour;
+    occluded = int(sprite.pivotOccluded.w);
+
+    gl_Position = viewProjection * vec4(worldPosition, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,39 @@
+in vec2 texCoords;
+in vec4 glyphColour;
+in vec3 worldPosition;
+flat in int occluded;
+
+out vec4 FragColor;
+
+uniform sampler2D fontAtlas;
+uniform sampler2DArray gPosition;
+
+uniform vec3 cameraPosition;
+uniform vec2 windowDimensions;
+uniform bool positionsAvailable;
+
+void main()
+{
+    // The atlas holds how much of each texel is covered by the glyph, which smooths its edges
+    float coverage = texture(fontAtlas, texCoords).r;
+
+    if(coverage <= 0.0)
+    {
+        discard;
+    }
+
+    if(positionsAvailable && occluded == 1)
+    {
+        vec3 scenePosition = texture(gPosition, vec3(gl_FragCoord.xy / windowDimensions, 0)).xyz;
+
+        // The sky box, light sources and pixels nothing was drawn to do not have a world position
+        bool hasPosition = !any(isinf(scenePosition)) && !any(isnan(scenePosition)) && scenePosition != vec3(0.0);
+
+        if(hasPosition && distance(scenePosition, cameraPosition) + 0.05 < distance(worldPosition, cameraPosition))
+        {
+            discard;
+        }
+    }
+
+    FragColor = vec4(glyphColour.rgb, glyphColour.a * coverage);
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+struct TextGlyph
+{
+    // xyz is where the text is placed, w is 0 for text over the window, 1 for text in the world and 2 for text in the world hidden behind geometry.
+    // Over the window, xy is the bottom left of the text in pixels; in the world, xyz is its bottom centre relative to the render origin
+    vec4 originMode;
+    // xy is the bottom left of the glyph relative to the origin, zw is its width and height. In pixels over the window, and world units in the world
+    vec4 offsetSize;
+    // xy is the top left of the glyph in the atlas, zw its bottom right
+    vec4 atlasRect;
+    vec4 colour;
+};
+
+layout (std430, binding = 19) readonly buffer TextGlyphs
+{
+    TextGlyph glyphs[];
+};
+
+out vec2 texCoords;
+out vec4 glyphColour;
+out vec3 worldPosition;
+flat out int occluded;
+
+uniform mat4 viewProjection;
+uniform vec3 cameraRight;
+uniform vec3 cameraUp;
+uniform vec2 windowDimensions;
+uniform int firstGlyph;
+
+// Every glyph is a quad generated from the vertex index as a triangle strip
+void main()
+{
+    TextGlyph glyph = glyphs[firstGlyph + gl_InstanceID];
+
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec2 position = glyph.offsetSize.xy + corner * glyph.offsetSize.zw;
+    int mode = int(glyph.originMode.w + 0.5);
+
+    texCoords = mix(glyph.atlasRect.xy, glyph.atlasRect.zw, vec2(corner.x, 1.0 - corner.y));
+    glyphColour = glyph.colour;
+    occluded = mode == 2 ? 1 : 0;
+
+    if(mode == 0)
+    {
+        worldPosition = vec3(0.0);
+        gl_Position = vec4((glyph.originMode.xy + position) / windowDimensions * 2.0 - 1.0, 0.0, 1.0);
+    }
+    else
+    {
+        worldPosition = glyph.originMode.xyz + cameraRight * position.x + cameraUp * position.y;
+        gl_Position = viewProjection * vec4(worldPosition, 1.0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+in vec2 texCoords;
+in vec4 vertexColour;
+
+out vec4 FragColor;
+
+uniform sampler2D uiTexture;
+
+// Both the vertex colour and the texture have premultiplied alpha
+void main()
+{
+    FragColor = vertexColour * texture(uiTexture, texCoords);
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+// Every vertex is the pos, uv and color of an egui vertex, with the colour packed into the last float
+layout (std430, binding = 21) readonly buffer UiVertices
+{
+    float uiVertices[];
+};
+
+layout (std430, binding = 22) readonly buffer UiIndices
+{
+    uint uiIndices[];
+};
+
+out vec2 texCoords;
+out vec4 vertexColour;
+
+// The size of the window in points, with the origin at its top left
+uniform vec2 screenSize;
+
+void main()
+{
+    uint base = uiIndices[gl_VertexID] * 5;
+
+    vec2 position = vec2(uiVertices[base], uiVertices[base + 1]);
+    texCoords = vec2(uiVertices[base + 2], uiVertices[base + 3]);
+    vertexColour = unpackUnorm4x8(floatBitsToUint(uiVertices[base + 4]));
+
+    gl_Position = vec4(position.x / screenSize.x * 2.0 - 1.0, 1.0 - position.y / screenSize.y * 2.0, 0.0, 1.0);
+}
\ No newline at end of file
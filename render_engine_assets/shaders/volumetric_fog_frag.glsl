@@ -0,0 +1,98 @@
+in vec2 texCoords;
+
+out vec4 FragColor;
+
+uniform sampler2DArray gPosition;
+uniform sampler2DArray shadowMaps;
+
+uniform mat4 inverseViewProjection;
+uniform vec3 cameraPosition;
+
+uniform mat4 lightMatrices[6];
+uniform int lightLayers[6];
+uniform int numberLightMatrices;
+
+uniform vec3 fogColour;
+uniform float fogDensity;
+uniform float fogBaseHeight;
+uniform float fogHeightFalloff;
+uniform vec3 lightShaftColour;
+uniform float lightShaftScattering;
+uniform float maxDistance;
+uniform int steps;
+
+// Fraction of light reaching the sample from the lights with shadow maps
+float sampleLit(vec3 samplePosition)
+{
+    float lit = 0.0;
+
+    for(int i = 0; i < numberLightMatrices; ++i)
+    {
+        vec4 lightSpace = lightMatrices[i] * vec4(samplePosition, 1.0);
+
+        if(lightSpace.w <= 0.0)
+        {
+            continue;
+        }
+
+        vec3 projCoords = lightSpace.xyz / lightSpace.w * 0.5 + 0.5;
+
+        if(any(lessThan(projCoords, vec3(0.0))) || any(greaterThan(projCoords, vec3(1.0))))
+        {
+            continue;
+        }
+
+        float closestDepth = texture(shadowMaps, vec3(projCoords.xy, lightLayers[i])).r;
+        lit = max(lit, projCoords.z - 0.002 <= closestDepth ? 1.0 : 0.0);
+    }
+
+    return lit;
+}
+
+// Offsets the start of every ray by a different fraction of a step, turning banding into noise
+float interleavedGradientNoise(vec2 pixel)
+{
+    return fract(52.9829189 * fract(dot(pixel, vec2(0.06711056, 0.00583715))));
+}
+
+void main()
+{
+    vec3 worldPosition = texture(gPosition, vec3(texCoords, 0)).xyz;
+
+    // The sky box, light sources and pixels nothing was drawn to do not have a world position
+    vec4 farPoint = inverseViewProjection * vec4(texCoords * 2.0 - 1.0, 1.0, 1.0);
+    vec3 rayDirection = normalize(farPoint.xyz / farPoint.w - cameraPosition);
+    float rayLength = maxDistance;
+
+    if(!any(isinf(worldPosition)) && !any(isnan(worldPosition)) && worldPosition != vec3(0.0))
+    {
+        rayDirection = normalize(worldPosition - cameraPosition);
+        rayLength = min(distance(worldPosition, cameraPosition), maxDistance);
+    }
+
+    float stepLength = rayLength / float(steps);
+    float offset = interleavedGradientNoise(gl_FragCoord.xy);
+
+    vec3 scattered = vec3(0.0);
+    float transmittance = 1.0;
+
+    for(int i = 0; i < steps; ++i)
+    {
+        vec3 samplePosition = cameraPosition + rayDirection * (float(i) + offset) * stepLength;
+
+        float density = fogDensity * exp(-max(samplePosition.y - fogBaseHeight, 0.0) * fogHeightFalloff);
+        float stepTransmittance = exp(-density * stepLength);
+
+        vec3 light = fogColour;
+
+        if(lightShaftScattering > 0.0)
+        {
+            light += lightShaftColour * lightShaftScattering * sampleLit(samplePosition);
+        }
+
+        scattered += transmittance * (1.0 - stepTransmittance) * light;
+        transmittance *= stepTransmittance;
+    }
+
+    FragColor = vec4(scattered, transmittance);
+}
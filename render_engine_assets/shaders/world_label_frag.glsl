@@ -0,0 +1,36 @@
+in vec2 texCoords;
+in vec4 glyphColour;
+in vec3 worldPosition;
+flat in float occluded;
+
+out vec4 FragColor;
+
+uniform sampler2D glyphAtlas;
+uniform sampler2DArray gPosition;
+
+uniform vec3 cameraPosition;
+uniform vec2 windowDimensions;
+uniform bool positionsAvailable;
+
+void main()
+{
+    if(texture(glyphAtlas, texCoords).r < 0.5)
+    {
+        discard;
+    }
+
+    if(occluded > 0.5 && positionsAvailable)
+    {
+        vec3 scenePosition = texture(gPosition, vec3(gl_FragCoord.xy / windowDimensions, 0)).xyz;
+
+        // The sky box, light sources and pixels nothing was drawn to do not have a world position
+        bool hasPosition = !any(isinf(scenePosition)) && !any(isnan(scenePosition)) && scenePosition != vec3(0.0);
+
+        if(hasPosition && distance(scenePosition, cameraPosition) + 0.05 < distance(worldPosition, cameraPosition))
+        {
+            discard;
+        }
+    }
+
+    FragColor = glyphColour;
+}
@@ -0,0 +1,40 @@
+struct LabelGlyph
+{
+    // xyz is the bottom centre of the label in the world, w is the size of a glyph texel in the world
+    vec4 anchor;
+    // xy is the bottom left of the glyph relative to the anchor in glyph texels, z is 1 if the glyph is occluded by geometry
+    vec4 offset;
+    // xy is the bottom left of the glyph in the atlas, zw is the top right
+    vec4 atlasRect;
+    vec4 colour;
+};
+
+layout (std430, binding = 6) readonly buffer LabelGlyphs
+{
+    LabelGlyph glyphs[];
+};
+
+out vec2 texCoords;
+out vec4 glyphColour;
+out vec3 worldPosition;
+flat out float occluded;
+
+uniform mat4 viewProjection;
+uniform vec3 cameraRight;
+uniform vec3 cameraUp;
+uniform vec2 glyphSize;
+
+// Every glyph is a quad facing the camera, generated from the vertex index as a triangle strip
+void main()
+{
+    LabelGlyph glyph = glyphs[gl_InstanceID];
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec2 labelPosition = (glyph.offset.xy + corner * glyphSize) * glyph.anchor.w;
+
+    worldPosition = glyph.anchor.xyz + cameraRight * labelPosition.x + cameraUp * labelPosition.y;
+    texCoords = mix(glyph.atlasRect.xy, glyph.atlasRect.zw, corner);
+    glyphColour = glyph.colour;
+    occluded = glyph.offset.z;
+
+    gl_Position = viewProjection * vec4(worldPosition, 1.0);
+}
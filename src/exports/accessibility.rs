@@ -0,0 +1,178 @@
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+lazy_static!
+{
+    static ref ACCESSIBILITY_SETTINGS: Mutex<AccessibilitySettings> = Mutex::new(AccessibilitySettings::default());
+}
+
+/// Types of colour blindness that the rendered frame can be filtered for
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColourBlindness
+{
+    /// Red cones are missing
+    Protanopia,
+    /// Green cones are missing
+    Deuteranopia,
+    /// Blue cones are missing
+    Tritanopia,
+    /// No colour is seen, only brightness
+    Achromatopsia,
+}
+
+/// How the rendered frame is filtered for a type of colour blindness
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColourFilter
+{
+    /// The frame is not filtered
+    None,
+    /// The frame is shown as it is seen with the colour blindness, to check that the game is playable with it
+    Simulate(ColourBlindness),
+    /// Colours that cannot be told apart with the colour blindness are shifted towards colours that can be.
+    /// Achromatopsia cannot be corrected for, so the frame is not filtered for it
+    Correct(ColourBlindness),
+}
+
+/// Options that make the game easier to use for players with different needs
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AccessibilitySettings
+{
+    pub colour_filter: ColourFilter,
+    /// Multiplier of the size of everything drawn in the overlay pass after the scene is rendered
+    pub ui_scale: f32,
+}
+
+impl Default for AccessibilitySettings
+{
+    fn default() -> Self
+    {
+        AccessibilitySettings{ colour_filter: ColourFilter::None, ui_scale: 1.0 }
+    }
+}
+
+impl ColourBlindness
+{
+    /// Get the matrix converting a linear RGB colour to how it is seen with the colour blindness, stored
+    /// in column major order. Values are from Machado et al. 2009 at full severity
+    fn simulation_matrix(&self) -> [f32; 9]
+    {
+        match *self
+        {
+            ColourBlindness::Protanopia =>
+                [
+                    0.152286, 0.114503, -0.003882,
+                    1.052583, 0.786281, -0.048116,
+                    -0.204868, 0.099216, 1.051998
+                ],
+            ColourBlindness::Deuteranopia =>
+                [
+                    0.367322, 0.280085, -0.011820,
+                    0.860646, 0.672501, 0.042940,
+                    -0.227968, 0.047413, 0.968881
+                ],
+            ColourBlindness::Tritanopia =>
+                [
+                    1.255528, -0.078411, 0.004733,
+                    -0.076749, 0.930809, 0.691367,
+                    -0.178779, 0.147602, 0.303900
+                ],
+            ColourBlindness::Achromatopsia =>
+                [
+                    0.2126, 0.2126, 0.2126,
+                    0.7152, 0.7152, 0.7152,
+                    0.0722, 0.0722, 0.0722
+                ],
+        }
+    }
+
+    /// Get the matrix moving the colour information lost with the colour blindness into the channels that
+    /// are still seen, stored in column major order
+    fn error_shift_matrix(&self) -> [f32; 9]
+    {
+        match *self
+        {
+            // Lost red information is shifted into green and blue
+            ColourBlindness::Protanopia | ColourBlindness::Deuteranopia =>
+                [
+                    0.0, 0.7, 0.7,
+                    0.0, 1.0, 0.0,
+                    0.0, 0.0, 1.0
+                ],
+            // Lost blue information is shifted into red and green
+            ColourBlindness::Tritanopia =>
+                [
+                    1.0, 0.0, 0.0,
+                    0.0, 1.0, 0.0,
+                    0.7, 0.7, 0.0
+                ],
+            ColourBlindness::Achromatopsia => [0.0; 9],
+        }
+    }
+}
+
+impl ColourFilter
+{
+    /// Get the matrix applied to the colour of every pixel of the rendered frame, stored in column major
+    /// order. None is returned if the frame is not changed by the filter
+    pub(crate) fn colour_matrix(&self) -> Option<[f32; 9]>
+    {
+        match *self
+        {
+            ColourFilter::None | ColourFilter::Correct(ColourBlindness::Achromatopsia) => None,
+            ColourFilter::Simulate(i) => Some(i.simulation_matrix()),
+            ColourFilter::Correct(i) =>
+                {
+                    // Daltonization: corrected = colour + shift * (colour - simulated(colour)),
+                    // which is the matrix identity + shift * (identity - simulation)
+                    let simulation = nalgebra_glm::make_mat3(&i.simulation_matrix());
+                    let shift = nalgebra_glm::make_mat3(&i.error_shift_matrix());
+                    let identity = nalgebra_glm::Mat3::identity();
+
+                    let correction = identity + shift * (identity - simulation);
+
+                    let mut matrix = [0.0; 9];
+                    matrix.copy_from_slice(correction.as_slice());
+                    Some(matrix)
+                }
+        }
+    }
+}
+
+/// Changes the accessibility settings. The changes are visible from the next rendered frame
+///
+/// `settings` - the new accessibility settings
+pub fn set_accessibility_settings(settings: AccessibilitySettings)
+{
+    let mut settings = settings;
+    settings.ui_scale = settings.ui_scale.max(0.1);
+
+    *ACCESSIBILITY_SETTINGS.lock() = settings;
+}
+
+/// Get the current accessibility settings
+pub fn accessibility_settings() -> AccessibilitySettings
+{
+    *ACCESSIBILITY_SETTINGS.lock()
+}
+
+/// Changes the filter applied to the rendered frame for colour blindness
+///
+/// `colour_filter` - the filter to apply to the rendered frame
+pub fn set_colour_filter(colour_filter: ColourFilter)
+{
+    ACCESSIBILITY_SETTINGS.lock().colour_filter = colour_filter;
+}
+
+/// Changes the multiplier of the size of everything drawn in the overlay pass
+///
+/// `ui_scale` - the multiplier of the size of the overlay. Values below 0.1 are treated as 0.1
+pub fn set_ui_scale(ui_scale: f32)
+{
+    ACCESSIBILITY_SETTINGS.lock().ui_scale = ui_scale.max(0.1);
+}
+
+/// Get the multiplier of the size of everything drawn in the overlay pass
+pub fn ui_scale() -> f32
+{
+    ACCESSIBILITY_SETTINGS.lock().ui_scale
+}
@@ -0,0 +1,196 @@
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use nalgebra_glm::TVec3;
+use parking_lot::Mutex;
+use crate::exports::fog::FogSettings;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+lazy_static!
+{
+    static ref AMBIENT_OVERRIDES: Mutex<AmbientOverrides> = Mutex::new(AmbientOverrides{ next_id: 0, overrides: HashMap::default() });
+}
+
+/// The ambient lighting parameters the lighting pass uses for fragments that are not lit by a light source
+#[derive(Copy, Clone, Debug)]
+pub struct AmbientSettings
+{
+    pub no_light_source_cutoff: f32,
+    pub default_diffuse_factor: f32,
+}
+
+/// Identifies an ambient override that was added, so that it can later be removed
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct AmbientOverrideId
+{
+    id: usize
+}
+
+/// Ambient lighting, and optionally fog, to use when the camera is within a region of the game world.
+/// Outside of the region, the override is blended with the surrounding ambient lighting and fog over
+/// the blend distance
+#[derive(Copy, Clone, Debug)]
+pub struct AmbientOverride
+{
+    pub volume: StaticAABB,
+    pub blend_distance: f32,
+    pub settings: AmbientSettings,
+    pub fog: Option<FogSettings>,
+}
+
+struct AmbientOverrides
+{
+    next_id: usize,
+    overrides: HashMap<AmbientOverrideId, AmbientOverride>,
+}
+
+impl AmbientOverrides
+{
+    /// Get the overrides in the order they were added
+    fn in_order(&self) -> Vec<&AmbientOverride>
+    {
+        let mut overrides = self.overrides.iter().collect::<Vec<(&AmbientOverrideId, &AmbientOverride)>>();
+        overrides.sort_by_key(|(id, _)| **id);
+        overrides.into_iter().map(|(_, x)| x).collect()
+    }
+}
+
+impl AmbientSettings
+{
+    /// Linearly interpolates between this and the other ambient settings
+    ///
+    /// `other` - the settings to blend towards
+    /// `factor` - how much of the other settings to use, from 0 to 1
+    fn blend(&self, other: &AmbientSettings, factor: f32) -> AmbientSettings
+    {
+        AmbientSettings
+        {
+            no_light_source_cutoff: self.no_light_source_cutoff + (other.no_light_source_cutoff - self.no_light_source_cutoff) * factor,
+            default_diffuse_factor: self.default_diffuse_factor + (other.default_diffuse_factor - self.default_diffuse_factor) * factor,
+        }
+    }
+}
+
+impl AmbientOverride
+{
+    /// Creates an ambient override that applies within the given volume, such as a trigger volume
+    ///
+    /// `volume` - the region in which the override fully applies
+    /// `blend_distance` - the distance outside of the volume over which the override fades out
+    /// `settings` - the ambient lighting to use within the volume
+    pub fn new(volume: StaticAABB, blend_distance: f32, settings: AmbientSettings) -> AmbientOverride
+    {
+        debug_assert!(blend_distance >= 0.0, "Ambient override cannot have a negative blend distance");
+
+        AmbientOverride{ volume, blend_distance, settings, fog: None }
+    }
+
+    /// Specifies the fog to use within the override. Without it, the override leaves the fog unchanged
+    ///
+    /// `fog` - the fog to use within the volume
+    pub fn with_fog(mut self, fog: FogSettings) -> Self
+    {
+        self.fog = Some(fog);
+        self
+    }
+
+    /// Creates an ambient override that applies within the given world section
+    ///
+    /// `tree` - the bounding box tree the world section is a part of
+    /// `world_section` - the world section in which the override fully applies
+    /// `blend_distance` - the distance outside of the world section over which the override fades out
+    /// `settings` - the ambient lighting to use within the world section
+    pub fn for_world_section(tree: &BoundingBoxTree, world_section: UniqueWorldSectionId, blend_distance: f32, settings: AmbientSettings) -> AmbientOverride
+    {
+        AmbientOverride::new(tree.world_section_aabb(world_section), blend_distance, settings)
+    }
+
+    /// Finds how much the override applies at the given position, from 0 to 1
+    ///
+    /// `position` - the position to find the influence of the override at
+    fn influence(&self, position: TVec3<f32>) -> f32
+    {
+        let distance = self.volume.distance_to_point(position);
+
+        if self.blend_distance == 0.0
+        {
+            return if distance == 0.0 { 1.0 } else { 0.0 };
+        }
+
+        (1.0 - distance / self.blend_distance).max(0.0)
+    }
+}
+
+/// Adds an ambient override that is used by all render systems, returning the id needed to remove it
+///
+/// `ambient_override` - the override to add
+pub fn add_ambient_override(ambient_override: AmbientOverride) -> AmbientOverrideId
+{
+    let mut ambient_overrides = AMBIENT_OVERRIDES.lock();
+
+    let id = AmbientOverrideId{ id: ambient_overrides.next_id };
+    ambient_overrides.next_id += 1;
+    ambient_overrides.overrides.insert(id, ambient_override);
+
+    id
+}
+
+/// Removes a previously added ambient override. Removing an override that does not exist has no effect
+///
+/// `id` - the id of the override to remove
+pub fn remove_ambient_override(id: AmbientOverrideId)
+{
+    AMBIENT_OVERRIDES.lock().overrides.remove(&id);
+}
+
+/// Finds the ambient settings to use for the given camera position. Overrides are blended on top of the
+/// default settings in the order they were added, so later overrides take precedence when they overlap
+///
+/// `camera_position` - the position of the camera that the scene is rendered from
+/// `default_settings` - the ambient settings used when no overrides apply
+pub(crate) fn blend_ambient_overrides(camera_position: TVec3<f32>, default_settings: AmbientSettings) -> AmbientSettings
+{
+    let mut blended_settings = default_settings;
+
+    for ambient_override in AMBIENT_OVERRIDES.lock().in_order()
+    {
+        let influence = ambient_override.influence(camera_position);
+
+        if influence > 0.0
+        {
+            blended_settings = blended_settings.blend(&ambient_override.settings, influence);
+        }
+    }
+
+    blended_settings
+}
+
+/// Finds the fog to use for the given camera position. Overrides with fog are blended on top of the default
+/// fog in the order they were added. If there is no default fog, the fog of an override fades in from
+/// having no density as the camera approaches it
+///
+/// `camera_position` - the position of the camera that the scene is rendered from
+/// `default_fog` - the fog used when no overrides apply, if any
+pub(crate) fn blend_fog_overrides(camera_position: TVec3<f32>, default_fog: Option<FogSettings>) -> Option<FogSettings>
+{
+    let mut blended_fog = default_fog;
+
+    for ambient_override in AMBIENT_OVERRIDES.lock().in_order()
+    {
+        let override_fog = match ambient_override.fog
+        {
+            Some(i) => i,
+            None => continue
+        };
+
+        let influence = ambient_override.influence(camera_position);
+
+        if influence > 0.0
+        {
+            let surrounding_fog = blended_fog.unwrap_or(FogSettings{ density: 0.0, light_shaft_scattering: 0.0, ..override_fog });
+            blended_fog = Some(surrounding_fog.blend(&override_fog, influence));
+        }
+    }
+
+    blended_fog
+}
@@ -0,0 +1,250 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use tracing::error;
+use crate::exports::camera_object::Camera;
+use crate::exports::camera_path::{CameraPath, CameraPathController};
+use crate::exports::frame_statistics::FrameStatistics;
+
+/// Runs the loaded scene with the camera following a fixed path, measuring every frame. When the camera
+/// reaches the end of the path the engine exits and a summary of the frame statistics is printed, making
+/// runs comparable between machines and between versions of the engine
+#[derive(Clone)]
+pub struct Benchmark
+{
+    path: CameraPath,
+    warmup_time: f32,
+    report_file: Option<PathBuf>,
+}
+
+/// Summary of the frames measured during a benchmark. Times are in milliseconds
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BenchmarkReport
+{
+    pub frame_count: usize,
+    /// Time taken to run the camera path, in seconds
+    pub duration: f32,
+    pub average_fps: f32,
+    pub min_fps: f32,
+    pub max_fps: f32,
+    /// Frames per second of the slowest 1% of frames, which shows stutters that the average hides
+    pub one_percent_low_fps: f32,
+    pub average_frame_time: f32,
+    /// The frame time that 99% of frames were faster than
+    pub p99_frame_time: f32,
+    pub average_cpu_time: f32,
+    pub average_gpu_wait_time: f32,
+    pub average_draw_calls: f32,
+    pub average_visible_sections: f32,
+}
+
+/// Progress of a benchmark being run by the render thread
+pub(crate) struct BenchmarkRun
+{
+    benchmark: Benchmark,
+    elapsed_time: f32,
+    frames: Vec<FrameStatistics>,
+}
+
+impl Benchmark
+{
+    /// Creates a benchmark that moves the camera along the given path
+    ///
+    /// `path` - the flythrough the camera follows during the benchmark
+    pub fn new(path: CameraPath) -> Benchmark
+    {
+        Benchmark{ path, warmup_time: 2.0, report_file: None }
+    }
+
+    /// Specifies how long the camera waits at the start of the path before frames are measured, giving
+    /// time for the scene to finish loading. Defaults to 2 seconds
+    ///
+    /// `warmup_time` - the time to wait before measuring, in seconds
+    pub fn with_warmup_time(&mut self, warmup_time: f32) -> &mut Self
+    {
+        self.warmup_time = warmup_time.max(0.0);
+        self
+    }
+
+    /// Specifies a file that the report is written to in addition to being printed
+    ///
+    /// `report_file` - the location of the file to write the report to
+    pub fn with_report_file(&mut self, report_file: PathBuf) -> &mut Self
+    {
+        self.report_file = Some(report_file);
+        self
+    }
+}
+
+impl BenchmarkReport
+{
+    /// Summarizes the statistics of the measured frames
+    ///
+    /// `frames` - the statistics of every measured frame
+    pub fn from_frames(frames: &[FrameStatistics]) -> BenchmarkReport
+    {
+        if frames.is_empty()
+        {
+            return BenchmarkReport::default();
+        }
+
+        let frame_count = frames.len() as f32;
+        let average = |value: &dyn Fn(&FrameStatistics) -> f32| frames.iter().map(value).sum::<f32>() / frame_count;
+
+        let mut frame_times = frames.iter().map(|x| x.frame_time).collect::<Vec<f32>>();
+        frame_times.sort_by(f32::total_cmp);
+
+        let to_fps = |frame_time: f32| if frame_time > 0.0 { 1000.0 / frame_time } else { 0.0 };
+
+        let slowest_percent_count = (frames.len() / 100).max(1);
+        let slowest_percent_time = frame_times.iter().rev().take(slowest_percent_count).sum::<f32>() / slowest_percent_count as f32;
+        let p99_index = ((frames.len() as f32 * 0.99).ceil() as usize).clamp(1, frames.len()) - 1;
+
+        let duration = frame_times.iter().sum::<f32>() / 1000.0;
+        let average_frame_time = average(&|x| x.frame_time);
+
+        BenchmarkReport
+        {
+            frame_count: frames.len(),
+            duration,
+            average_fps: to_fps(average_frame_time),
+            min_fps: to_fps(*frame_times.last().unwrap()),
+            max_fps: to_fps(frame_times[0]),
+            one_percent_low_fps: to_fps(slowest_percent_time),
+            average_frame_time,
+            p99_frame_time: frame_times[p99_index],
+            average_cpu_time: average(&|x| x.cpu_time),
+            average_gpu_wait_time: average(&|x| x.gpu_wait_time),
+            average_draw_calls: average(&|x| x.draw_calls as f32),
+            average_visible_sections: average(&|x| x.visible_sections as f32),
+        }
+    }
+}
+
+impl Display for BenchmarkReport
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        writeln!(f, "Benchmark results")?;
+        writeln!(f, "  Frames:           {} over {:.2}s", self.frame_count, self.duration)?;
+        writeln!(f, "  FPS:              {:.1} average, {:.1} min, {:.1} max, {:.1} 1% low", self.average_fps, self.min_fps, self.max_fps, self.one_percent_low_fps)?;
+        writeln!(f, "  Frame time:       {:.2}ms average, {:.2}ms 99th percentile", self.average_frame_time, self.p99_frame_time)?;
+        writeln!(f, "  CPU time:         {:.2}ms average", self.average_cpu_time)?;
+        writeln!(f, "  GPU wait time:    {:.2}ms average", self.average_gpu_wait_time)?;
+        writeln!(f, "  Draw calls:       {:.1} average", self.average_draw_calls)?;
+        write!(f, "  Visible sections: {:.1} average", self.average_visible_sections)
+    }
+}
+
+impl BenchmarkRun
+{
+    /// Starts the benchmark by placing the camera on the path of the benchmark
+    ///
+    /// `benchmark` - the benchmark to run
+    /// `camera` - the camera rendering the scene
+    pub(crate) fn start(benchmark: Benchmark, camera: &mut Camera) -> BenchmarkRun
+    {
+        let mut controller = CameraPathController::new(benchmark.path.clone());
+        controller.with_start_delay(benchmark.warmup_time);
+        camera.set_controller(controller);
+
+        BenchmarkRun{ benchmark, elapsed_time: 0.0, frames: Vec::new() }
+    }
+
+    /// Records the statistics of a rendered frame. Once the camera has reached the end of the path,
+    /// the report of the benchmark is returned
+    ///
+    /// `statistics` - the statistics of the rendered frame
+    /// `delta_time` - the time the frame took, in seconds
+    pub(crate) fn record_frame(&mut self, statistics: FrameStatistics, delta_time: f32) -> Option<BenchmarkReport>
+    {
+        self.elapsed_time += delta_time;
+
+        if self.elapsed_time <= self.benchmark.warmup_time
+        {
+            return None;
+        }
+
+        self.frames.push(statistics);
+
+        if self.elapsed_time < self.benchmark.warmup_time + self.benchmark.path.duration()
+        {
+            return None;
+        }
+
+        let report = BenchmarkReport::from_frames(&self.frames);
+
+        if let Some(ref report_file) = self.benchmark.report_file
+        {
+            if let Err(err) = fs::write(report_file, report.to_string())
+            {
+                error!("Failed to write benchmark report to {:?}: {}", report_file, err);
+            }
+        }
+
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::exports::benchmark::BenchmarkReport;
+    use crate::exports::frame_statistics::FrameStatistics;
+
+    fn frame(frame_time: f32) -> FrameStatistics
+    {
+        FrameStatistics{ frame_time, cpu_time: frame_time / 2.0, draw_calls: 10, visible_sections: 4, ..FrameStatistics::default() }
+    }
+
+    #[test]
+    fn empty_report()
+    {
+        assert_eq!(BenchmarkReport::default(), BenchmarkReport::from_frames(&[]));
+    }
+
+    #[test]
+    fn single_frame_report()
+    {
+        let report = BenchmarkReport::from_frames(&[frame(20.0)]);
+
+        assert_eq!(1, report.frame_count);
+        assert_eq!(0.02, report.duration);
+        assert_eq!(50.0, report.average_fps);
+        assert_eq!(50.0, report.min_fps);
+        assert_eq!(50.0, report.max_fps);
+        assert_eq!(50.0, report.one_percent_low_fps);
+        assert_eq!(20.0, report.average_frame_time);
+        assert_eq!(20.0, report.p99_frame_time);
+        assert_eq!(10.0, report.average_cpu_time);
+        assert_eq!(10.0, report.average_draw_calls);
+        assert_eq!(4.0, report.average_visible_sections);
+    }
+
+    #[test]
+    fn hundred_frame_report()
+    {
+        // Frame times of 1ms to 100ms, given from the slowest to the fastest
+        let frames = (1..=100).rev().map(|x| frame(x as f32)).collect::<Vec<FrameStatistics>>();
+        let report = BenchmarkReport::from_frames(&frames);
+
+        assert_eq!(100, report.frame_count);
+        assert_eq!(5.05, report.duration);
+        assert_eq!(50.5, report.average_frame_time);
+        assert_eq!(1000.0 / 50.5, report.average_fps);
+        assert_eq!(10.0, report.min_fps);
+        assert_eq!(1000.0, report.max_fps);
+        // The slowest 1% of 100 frames is the single 100ms frame
+        assert_eq!(10.0, report.one_percent_low_fps);
+        assert_eq!(99.0, report.p99_frame_time);
+    }
+
+    #[test]
+    fn nan_frame_time_does_not_panic()
+    {
+        let report = BenchmarkReport::from_frames(&[frame(10.0), frame(f32::NAN), frame(20.0)]);
+
+        assert_eq!(3, report.frame_count);
+        assert_eq!(100.0, report.max_fps);
+    }
+}
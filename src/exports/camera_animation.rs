@@ -0,0 +1,252 @@
+use std::f32::consts::PI;
+use nalgebra_glm::{TVec3, vec3};
+
+/// Number of sine waves summed for each axis of a camera shake. Waves of different frequencies
+/// make the shake look irregular rather than like an oscillation
+const NUMBER_SHAKE_WAVES: usize = 3;
+
+/// Amplitude below which a camera shake is considered finished
+const SHAKE_FINISHED_AMPLITUDE: f32 = 0.001;
+
+/// How the progress of an animation changes over its duration
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EaseCurve
+{
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts quickly and slows down
+    EaseOut,
+    /// Starts and ends slowly
+    EaseInOut,
+}
+
+/// Moves the camera to a position, direction and field of view over a period of time. Properties that
+/// are not given are left as they are
+#[derive(Copy, Clone, Debug)]
+pub struct CameraAnimation
+{
+    position: Option<TVec3<f32>>,
+    direction: Option<TVec3<f32>>,
+    fov: Option<f32>,
+    duration: f32,
+    curve: EaseCurve,
+}
+
+/// Shakes the camera, such as for an explosion. The shake moves the point the scene is rendered from
+/// without changing the position of the camera, so it does not affect culling or gameplay
+#[derive(Copy, Clone, Debug)]
+pub struct CameraShake
+{
+    amplitude: f32,
+    frequency: f32,
+    decay: f32,
+}
+
+/// Progress of an animation being applied to a camera
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ActiveCameraAnimation
+{
+    animation: CameraAnimation,
+    start_position: TVec3<f32>,
+    start_direction: TVec3<f32>,
+    start_fov: f32,
+    elapsed_time: f32,
+}
+
+/// Progress of a shake being applied to a camera
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ActiveCameraShake
+{
+    shake: CameraShake,
+    phases: [[f32; NUMBER_SHAKE_WAVES]; 3],
+    elapsed_time: f32,
+}
+
+impl EaseCurve
+{
+    /// Get the progress of the animation at the given fraction of its duration
+    ///
+    /// `time` - the fraction of the duration that has passed, from 0 to 1
+    pub fn apply(&self, time: f32) -> f32
+    {
+        let time = time.clamp(0.0, 1.0);
+
+        match *self
+        {
+            EaseCurve::Linear => time,
+            EaseCurve::EaseIn => time * time * time,
+            EaseCurve::EaseOut => 1.0 - (1.0 - time).powi(3),
+            EaseCurve::EaseInOut => time * time * (3.0 - 2.0 * time),
+        }
+    }
+}
+
+impl CameraAnimation
+{
+    /// Creates an animation lasting the given duration that does not change the camera until properties
+    /// to animate are given
+    ///
+    /// `duration` - how long the animation lasts, in seconds
+    pub fn new(duration: f32) -> CameraAnimation
+    {
+        CameraAnimation{ position: None, direction: None, fov: None, duration: duration.max(0.0), curve: EaseCurve::EaseInOut }
+    }
+
+    /// Specifies the position the camera is moved to
+    ///
+    /// `position` - the position of the camera at the end of the animation
+    pub fn with_position(&mut self, position: TVec3<f32>) -> &mut Self
+    {
+        self.position = Some(position);
+        self
+    }
+
+    /// Specifies the direction the camera is turned to. The direction is normalized
+    ///
+    /// `direction` - the direction of the camera at the end of the animation
+    pub fn with_direction(&mut self, direction: TVec3<f32>) -> &mut Self
+    {
+        self.direction = Some(nalgebra_glm::normalize(&direction));
+        self
+    }
+
+    /// Specifies the field of view the camera is changed to
+    ///
+    /// `fov` - the field of view in degrees at the end of the animation
+    pub fn with_fov(&mut self, fov: f32) -> &mut Self
+    {
+        self.fov = Some(fov);
+        self
+    }
+
+    /// Specifies how the progress of the animation changes over its duration
+    ///
+    /// `curve` - the ease curve of the animation
+    pub fn with_curve(&mut self, curve: EaseCurve) -> &mut Self
+    {
+        self.curve = curve;
+        self
+    }
+}
+
+impl CameraShake
+{
+    /// Creates a camera shake
+    ///
+    /// `amplitude` - the furthest distance the camera is moved by the shake when it starts
+    /// `frequency` - how many times per second the camera moves back and forth
+    /// `decay` - how quickly the shake fades; the amplitude halves every (ln 2 / decay) seconds. With a
+    ///           decay of 0 the camera shakes until the shake is stopped
+    pub fn new(amplitude: f32, frequency: f32, decay: f32) -> CameraShake
+    {
+        CameraShake{ amplitude: amplitude.abs(), frequency: frequency.abs(), decay: decay.max(0.0) }
+    }
+}
+
+impl ActiveCameraAnimation
+{
+    /// Starts the animation from the current state of the camera
+    ///
+    /// `animation` - the animation to apply
+    /// `position` - the position of the camera
+    /// `direction` - the direction of the camera
+    /// `fov` - the field of view of the camera
+    pub(crate) fn new(animation: CameraAnimation, position: TVec3<f32>, direction: TVec3<f32>, fov: f32) -> ActiveCameraAnimation
+    {
+        ActiveCameraAnimation{ animation, start_position: position, start_direction: direction, start_fov: fov, elapsed_time: 0.0 }
+    }
+
+    /// Advances the animation, returning the position, direction and field of view the camera should have.
+    /// None is returned for properties that are not animated
+    ///
+    /// `delta_time` - the amount of time that has passed since the last frame in seconds
+    pub(crate) fn advance(&mut self, delta_time: f32) -> (Option<TVec3<f32>>, Option<TVec3<f32>>, Option<f32>)
+    {
+        self.elapsed_time += delta_time;
+
+        let time = if self.animation.duration == 0.0 { 1.0 } else { self.elapsed_time / self.animation.duration };
+        let progress = self.animation.curve.apply(time);
+
+        let position = self.animation.position.map(|x| nalgebra_glm::lerp(&self.start_position, &x, progress));
+        let direction = self.animation.direction.map(|x|
+            {
+                let direction = nalgebra_glm::lerp(&self.start_direction, &x, progress);
+
+                // Directions that are opposite pass through zero length halfway through
+                if nalgebra_glm::length(&direction) > f32::EPSILON { direction } else { x }
+            });
+        let fov = self.animation.fov.map(|x| self.start_fov + (x - self.start_fov) * progress);
+
+        (position, direction, fov)
+    }
+
+    /// Checks if the animation has reached its end
+    pub(crate) fn is_finished(&self) -> bool
+    {
+        self.elapsed_time >= self.animation.duration
+    }
+}
+
+impl ActiveCameraShake
+{
+    /// Starts the shake with random phases, so that shakes started together do not move in unison
+    ///
+    /// `shake` - the shake to apply
+    pub(crate) fn new(shake: CameraShake) -> ActiveCameraShake
+    {
+        let mut phases = [[0.0; NUMBER_SHAKE_WAVES]; 3];
+
+        for axis in phases.iter_mut()
+        {
+            for phase in axis.iter_mut()
+            {
+                *phase = rand::random::<f32>() * 2.0 * PI;
+            }
+        }
+
+        ActiveCameraShake{ shake, phases, elapsed_time: 0.0 }
+    }
+
+    /// Advances the shake, returning how far the camera is moved from its position
+    ///
+    /// `delta_time` - the amount of time that has passed since the last frame in seconds
+    pub(crate) fn advance(&mut self, delta_time: f32) -> TVec3<f32>
+    {
+        self.elapsed_time += delta_time;
+
+        let amplitude = self.current_amplitude();
+        let mut offset = [0.0; 3];
+
+        for (axis, phases) in self.phases.iter().enumerate()
+        {
+            // Each wave is faster and weaker than the last, normalized so that the sum stays within the amplitude
+            let mut weight_sum = 0.0;
+
+            for (wave, phase) in phases.iter().enumerate()
+            {
+                let wave_frequency = self.shake.frequency * (1.0 + wave as f32 * 0.77);
+                let weight = 1.0 / (wave + 1) as f32;
+
+                offset[axis] += (2.0 * PI * wave_frequency * self.elapsed_time + phase).sin() * weight;
+                weight_sum += weight;
+            }
+
+            offset[axis] *= amplitude / weight_sum;
+        }
+
+        vec3(offset[0], offset[1], offset[2])
+    }
+
+    /// Checks if the shake has faded out
+    pub(crate) fn is_finished(&self) -> bool
+    {
+        self.current_amplitude() < SHAKE_FINISHED_AMPLITUDE
+    }
+
+    /// Get the amplitude of the shake after it has decayed
+    fn current_amplitude(&self) -> f32
+    {
+        self.shake.amplitude * (-self.shake.decay * self.elapsed_time).exp()
+    }
+}
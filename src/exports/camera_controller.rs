@@ -0,0 +1,303 @@
+use glfw::{Key, MouseButton};
+use nalgebra_glm::{TVec3, vec3};
+use crate::exports::camera_object::{Camera, MovementFactor};
+use crate::exports::movement_components::Position;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::window::gl_window::MIDDLE_BUTTON;
+use crate::window::input_state::{CurrentFrameInput, InputHistory};
+
+/// The state of the game a camera controller can use to move the camera
+pub struct CameraControllerArgs<'a>
+{
+    pub ecs: &'a ECS,
+    pub input_history: &'a InputHistory,
+    pub current_input: &'a CurrentFrameInput,
+    pub delta_time: f32,
+}
+
+/// Moves a camera every frame. The controller of a camera is updated before the visible world sections
+/// are found, so that the frame is rendered from where the controller placed the camera
+pub trait CameraController: Send + Sync
+{
+    /// Moves the camera for the current frame
+    ///
+    /// `camera` - the camera being controlled
+    /// `args` - the state of the game for the current frame
+    fn update(&mut self, camera: &mut Camera, args: &CameraControllerArgs);
+
+    /// Get a copy of this controller. Required as cameras can be copied
+    fn clone_box(&self) -> Box<dyn CameraController>;
+}
+
+impl Clone for Box<dyn CameraController>
+{
+    fn clone(&self) -> Self
+    {
+        self.clone_box()
+    }
+}
+
+/// Moves the camera with the WASD keys in the direction it is facing, and turns it with the mouse
+#[derive(Copy, Clone)]
+pub struct FreeFlyController
+{
+    look_button: Option<MouseButton>,
+    movement_speed: Option<f32>,
+}
+
+impl FreeFlyController
+{
+    /// Creates a controller that turns the camera while the middle mouse button is held, moving at the
+    /// movement speed of the camera
+    pub fn new() -> FreeFlyController
+    {
+        FreeFlyController{ look_button: Some(MIDDLE_BUTTON), movement_speed: None }
+    }
+
+    /// Specifies the mouse button that has to be held for the camera to turn. If None, the camera
+    /// always turns with the mouse
+    ///
+    /// `look_button` - the button to hold to turn the camera
+    pub fn with_look_button(&mut self, look_button: Option<MouseButton>) -> &mut Self
+    {
+        self.look_button = look_button;
+        self
+    }
+
+    /// Specifies how fast the camera moves, in units per second
+    ///
+    /// `movement_speed` - the speed of the camera
+    pub fn with_movement_speed(&mut self, movement_speed: f32) -> &mut Self
+    {
+        self.movement_speed = Some(movement_speed);
+        self
+    }
+}
+
+impl Default for FreeFlyController
+{
+    fn default() -> Self
+    {
+        FreeFlyController::new()
+    }
+}
+
+impl CameraController for FreeFlyController
+{
+    fn update(&mut self, camera: &mut Camera, args: &CameraControllerArgs)
+    {
+        let movement_speed = self.movement_speed.unwrap_or_else(|| camera.get_movement_speed_factor());
+
+        let mut move_factor = MovementFactor{ forwards_backwards: 0.0, left_right: 0.0 };
+
+        if args.input_history.is_key_down(Key::W)
+        {
+            move_factor.forwards_backwards += movement_speed;
+        }
+
+        if args.input_history.is_key_down(Key::S)
+        {
+            move_factor.forwards_backwards -= movement_speed;
+        }
+
+        if args.input_history.is_key_down(Key::D)
+        {
+            move_factor.left_right += movement_speed;
+        }
+
+        if args.input_history.is_key_down(Key::A)
+        {
+            move_factor.left_right -= movement_speed;
+        }
+
+        if move_factor.forwards_backwards != 0.0 || move_factor.left_right != 0.0
+        {
+            camera.float_position(move_factor, args.delta_time);
+        }
+
+        let looking = self.look_button.is_none_or(|x| args.input_history.is_mouse_down(x));
+
+        if looking
+        {
+            if let Some((cursor_x, cursor_y)) = args.current_input.get_latest_cursor_pos()
+            {
+                camera.rotate(cursor_x, cursor_y);
+            }
+        }
+        else
+        {
+            camera.first_rotation = true;
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn CameraController>
+    {
+        Box::new(*self)
+    }
+}
+
+/// Keeps the camera at a distance from an entity, looking at it. Dragging the mouse rotates the camera
+/// around the entity
+#[derive(Copy, Clone)]
+pub struct OrbitController
+{
+    target: EntityId,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    rotate_button: MouseButton,
+    last_cursor_pos: Option<(i32, i32)>,
+}
+
+impl OrbitController
+{
+    /// Creates a controller that orbits the given entity, rotating while the middle mouse button is held
+    ///
+    /// `target` - the entity to orbit around
+    /// `distance` - the distance between the camera and the entity
+    pub fn new(target: EntityId, distance: f32) -> OrbitController
+    {
+        OrbitController{ target, distance, yaw: -90.0, pitch: 20.0, rotate_button: MIDDLE_BUTTON, last_cursor_pos: None }
+    }
+
+    /// Specifies the mouse button that has to be held to rotate around the entity
+    ///
+    /// `rotate_button` - the button to hold to rotate the camera
+    pub fn with_rotate_button(&mut self, rotate_button: MouseButton) -> &mut Self
+    {
+        self.rotate_button = rotate_button;
+        self
+    }
+
+    /// Specifies the starting angles of the camera around the entity, in degrees
+    ///
+    /// `yaw` - the angle around the vertical axis
+    /// `pitch` - the angle above the entity
+    pub fn with_angles(&mut self, yaw: f32, pitch: f32) -> &mut Self
+    {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-89.0, 89.0);
+        self
+    }
+
+    /// Changes the distance between the camera and the entity
+    ///
+    /// `distance` - the new distance from the entity
+    pub fn set_distance(&mut self, distance: f32)
+    {
+        self.distance = distance.max(0.0);
+    }
+}
+
+impl CameraController for OrbitController
+{
+    fn update(&mut self, camera: &mut Camera, args: &CameraControllerArgs)
+    {
+        if args.input_history.is_mouse_down(self.rotate_button)
+        {
+            if let Some(cursor_pos) = args.current_input.get_latest_cursor_pos()
+            {
+                if let Some(last_cursor_pos) = self.last_cursor_pos
+                {
+                    self.yaw += (cursor_pos.0 - last_cursor_pos.0) as f32 * camera.get_mouse_sensitivity();
+                    self.pitch = (self.pitch + (cursor_pos.1 - last_cursor_pos.1) as f32 * camera.get_mouse_sensitivity()).clamp(-89.0, 89.0);
+                }
+
+                self.last_cursor_pos = Some(cursor_pos);
+            }
+        }
+        else
+        {
+            self.last_cursor_pos = None;
+        }
+
+        let target_position = match args.ecs.get_copy::<Position>(self.target)
+        {
+            Some(i) => i.get_position(),
+            None => return
+        };
+
+        let direction = vec3
+            (
+                self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
+                -self.pitch.to_radians().sin(),
+                self.yaw.to_radians().sin() * self.pitch.to_radians().cos()
+            );
+
+        camera.set_position_and_direction(target_position - direction * self.distance, direction);
+    }
+
+    fn clone_box(&self) -> Box<dyn CameraController>
+    {
+        Box::new(*self)
+    }
+}
+
+/// Follows an entity from behind, smoothly catching up to it as it moves
+#[derive(Copy, Clone)]
+pub struct FollowController
+{
+    target: EntityId,
+    offset: TVec3<f32>,
+    smoothing: f32,
+}
+
+impl FollowController
+{
+    /// Creates a controller that stays at the given offset from the entity, looking at it
+    ///
+    /// `target` - the entity to follow
+    /// `offset` - the position of the camera relative to the entity
+    pub fn new(target: EntityId, offset: TVec3<f32>) -> FollowController
+    {
+        FollowController{ target, offset, smoothing: 5.0 }
+    }
+
+    /// Specifies how quickly the camera catches up to the entity. Higher values catch up faster; if 0,
+    /// the camera is always at the offset from the entity
+    ///
+    /// `smoothing` - how quickly the camera catches up, per second
+    pub fn with_smoothing(&mut self, smoothing: f32) -> &mut Self
+    {
+        self.smoothing = smoothing.max(0.0);
+        self
+    }
+}
+
+impl CameraController for FollowController
+{
+    fn update(&mut self, camera: &mut Camera, args: &CameraControllerArgs)
+    {
+        let target_position = match args.ecs.get_copy::<Position>(self.target)
+        {
+            Some(i) => i.get_position(),
+            None => return
+        };
+
+        let desired_position = target_position + self.offset;
+
+        let position = if self.smoothing == 0.0
+        {
+            desired_position
+        }
+        else
+        {
+            // Frame rate independent interpolation towards the desired position
+            let interpolation = 1.0 - (-self.smoothing * args.delta_time).exp();
+            nalgebra_glm::lerp(&camera.get_position(), &desired_position, interpolation)
+        };
+
+        let direction = target_position - position;
+
+        if nalgebra_glm::length(&direction) > f32::EPSILON
+        {
+            camera.set_position_and_direction(position, direction);
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn CameraController>
+    {
+        Box::new(*self)
+    }
+}
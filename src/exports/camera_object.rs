@@ -1,5 +1,17 @@
-use nalgebra_glm::{cross, look_at, normalize, ortho, perspective, TMat4, TVec3, vec3};
+use nalgebra_glm::{cross, look_at, mat4_to_mat3, normalize, ortho, perspective, TMat4, TVec3, vec3, vec4};
 use serde::{Serialize, Deserialize};
+use crate::exports::camera_animation::{ActiveCameraAnimation, ActiveCameraShake, CameraAnimation, CameraShake};
+use crate::exports::camera_controller::{CameraController, CameraControllerArgs};
+use crate::exports::logic_components::RenderSystemIndex;
+use crate::exports::world_precision::{is_double_precision_positions_enabled, to_f64, to_render_space};
+use crate::flows::shared_constants::REQUESTED_CAMERA_VIEW_CHANGES;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::prelude::frustum::Frustum;
+use crate::world::bounding_box_tree_v2::BoundingBoxTree;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+use crate::world::bounding_volumes::bounding_sphere::BoundingSphere;
+use crate::world::bounding_volumes::ray::Ray;
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct MovementFactor
@@ -42,6 +54,17 @@ pub struct Camera
     view_matrix_changed: bool,
     draw_param_changed: bool, // near, far, fov
 window_dimensions_change: bool,
+
+    #[serde(skip)]
+    controller: Option<Box<dyn CameraController>>,
+    #[serde(skip)]
+    animation: Option<ActiveCameraAnimation>,
+    #[serde(skip)]
+    shakes: Vec<ActiveCameraShake>,
+    #[serde(skip)]
+    shake_applied: bool,
+    #[serde(skip)]
+    shake_offset: TVec3<f32>,
 }
 
 /// Stores data to be serialized about the camera into one package
@@ -162,6 +185,18 @@ impl Camera
         self.direction
     }
 
+    /// Get the sensitivity of the camera to mouse movement
+    pub fn get_mouse_sensitivity(&self) -> f32
+    {
+        self.mouse_sensitivity
+    }
+
+    /// Get the speed factor the camera moves with
+    pub fn get_movement_speed_factor(&self) -> f32
+    {
+        self.movement_speed_factor
+    }
+
     /// Changes to the camera are marked as not having occurred. Prevents duplicate copies of the
     /// camera's changes from being stored in the game history
     pub fn reset_change_param(&mut self)
@@ -191,6 +226,34 @@ impl Camera
         self.view_matrix
     }
 
+    /// Get the view matrix of the camera for positions relative to the render origin. This is the view
+    /// matrix shaders should be given, as the instance data uploaded for entities is relative to the render
+    /// origin. The same as the view matrix if double precision positions are disabled
+    pub fn get_render_view_matrix(&self) -> TMat4<f32>
+    {
+        if !is_double_precision_positions_enabled()
+        {
+            return self.view_matrix;
+        }
+
+        // The rotation of the view does not depend on the position of the camera, so only the translation is
+        // found again. Doing so in double precision keeps it small when the camera is near the render origin
+        let eye = self.get_render_position() + self.shake_offset;
+        let translation = -(mat4_to_mat3(&self.view_matrix) * eye);
+
+        let mut view_matrix = self.view_matrix;
+        view_matrix[(0, 3)] = translation.x;
+        view_matrix[(1, 3)] = translation.y;
+        view_matrix[(2, 3)] = translation.z;
+        view_matrix
+    }
+
+    /// Get the position of the camera relative to the render origin
+    pub fn get_render_position(&self) -> TVec3<f32>
+    {
+        to_render_space(to_f64(self.position))
+    }
+
     /// Get serializable data for the camera
     pub fn get_serializable_data(&self) -> SerializableCameraInfo
     {
@@ -265,6 +328,217 @@ impl Camera
         self.view_matrix =  look_at(&self.position, &(&self.position + &self.direction), &vec3(0.0, 1.0, 0.0));
         self.view_matrix_changed = true;
     }
+
+    /// Places the camera at the given position, facing the given direction. The pitch and yaw of the camera
+    /// are updated to match the direction, so that rotating the camera afterwards continues from it
+    ///
+    /// `position` - the new position of the camera
+    /// `direction` - the direction the camera faces. The direction is normalized
+    pub fn set_position_and_direction(&mut self, position: TVec3<f32>, direction: TVec3<f32>)
+    {
+        self.position = position;
+        self.direction = normalize(&direction);
+        self.pitch = self.direction.y.clamp(-1.0, 1.0).asin().to_degrees();
+        self.yaw = self.direction.z.atan2(self.direction.x).to_degrees();
+        self.view_matrix =  look_at(&self.position, &(self.position + self.direction), &vec3(0.0, 1.0, 0.0));
+        self.view_matrix_changed = true;
+    }
+
+    /// Moves the camera backwards or forwards along the direction it faces until the given volume fills as
+    /// much of the view as it can while staying entirely in view
+    ///
+    /// `aabb` - the volume to frame, such as the bounding volume of a group of entities
+    pub fn frame_aabb(&mut self, aabb: &StaticAABB)
+    {
+        let sphere = BoundingSphere::from_aabb(aabb);
+        let aspect_ratio = self.window_width.max(1) as f32 / self.window_height.max(1) as f32;
+
+        // The sphere around the volume must fit within the narrower of the vertical and horizontal field of view
+        let half_vertical_fov = self.fov.to_radians() * 0.5;
+        let half_horizontal_fov = (half_vertical_fov.tan() * aspect_ratio).atan();
+        let half_fov = half_vertical_fov.min(half_horizontal_fov).max(0.01);
+
+        let distance = (sphere.radius / half_fov.sin()).max(self.near_draw_distance + sphere.radius);
+        let direction = self.direction;
+        self.set_position_and_direction(sphere.centre - direction * distance, direction);
+    }
+
+    /// Frames the bounding volume of the group of entities, as described in frame_aabb. False is returned if
+    /// the group has no bounding volume
+    ///
+    /// `group` - the entity identifying the group
+    /// `bounding_box_tree` - the tree holding the bounding volume of the group
+    pub fn frame_group(&mut self, group: EntityId, bounding_box_tree: &BoundingBoxTree) -> bool
+    {
+        match bounding_box_tree.get_group_aabb(group).copied()
+        {
+            Some(aabb) =>
+                {
+                    self.frame_aabb(&aabb);
+                    true
+                },
+            None => false
+        }
+    }
+
+    /// Specifies the controller that moves the camera every frame, replacing any existing controller
+    ///
+    /// `controller` - the controller to move the camera with
+    pub fn set_controller<C: CameraController + 'static>(&mut self, controller: C)
+    {
+        self.controller = Some(Box::new(controller));
+    }
+
+    /// Removes the controller of the camera, leaving the camera to be moved by user input logic
+    pub fn clear_controller(&mut self)
+    {
+        self.controller = None;
+    }
+
+    /// Starts moving the camera with the given animation, replacing any animation that is playing. The
+    /// animation starts from the current state of the camera
+    ///
+    /// `animation` - the animation to play
+    pub fn animate(&mut self, animation: CameraAnimation)
+    {
+        self.animation = Some(ActiveCameraAnimation::new(animation, self.position, self.direction, self.fov));
+    }
+
+    /// Checks if an animation is playing
+    pub fn is_animating(&self) -> bool
+    {
+        self.animation.is_some()
+    }
+
+    /// Stops the animation that is playing, leaving the camera where the animation placed it
+    pub fn stop_animation(&mut self)
+    {
+        self.animation = None;
+    }
+
+    /// Starts shaking the camera. The shake is added to any shakes that are already playing
+    ///
+    /// `shake` - the shake to play
+    pub fn shake(&mut self, shake: CameraShake)
+    {
+        self.shakes.push(ActiveCameraShake::new(shake));
+    }
+
+    /// Stops all shakes that are playing
+    pub fn stop_shaking(&mut self)
+    {
+        self.shakes.clear();
+    }
+
+    /// Advances the animation and shakes of the camera, updating the view matrix
+    ///
+    /// `delta_time` - the amount of time that has passed since the last frame in seconds
+    pub(crate) fn update_effects(&mut self, delta_time: f32)
+    {
+        if let Some(mut animation) = self.animation.take()
+        {
+            let (position, direction, fov) = animation.advance(delta_time);
+
+            if position.is_some() || direction.is_some()
+            {
+                self.set_position_and_direction(position.unwrap_or(self.position), direction.unwrap_or(self.direction));
+            }
+
+            if let Some(fov) = fov
+            {
+                self.change_draw_param(self.near_draw_distance, self.far_draw_distance, fov);
+            }
+
+            if !animation.is_finished()
+            {
+                self.animation = Some(animation);
+            }
+        }
+
+        if self.shakes.is_empty() && !self.shake_applied
+        {
+            return;
+        }
+
+        let mut offset = self.shakes.iter_mut().fold(vec3(0.0, 0.0, 0.0), |sum, x| sum + x.advance(delta_time));
+        self.shakes.retain(|x| !x.is_finished());
+
+        // Once the shakes finish, the view matrix is set back to the unshaken position
+        if self.shakes.is_empty()
+        {
+            offset = vec3(0.0, 0.0, 0.0);
+        }
+
+        // Only the view matrix is shaken, so the shake is not stored in the game history or used for culling
+        let shaken_position = self.position + offset;
+        self.view_matrix =  look_at(&shaken_position, &(shaken_position + self.direction), &vec3(0.0, 1.0, 0.0));
+        self.shake_applied = !self.shakes.is_empty();
+        self.shake_offset = offset;
+    }
+
+    /// Moves the camera with its controller, if it has one
+    ///
+    /// `args` - the state of the game for the current frame
+    pub(crate) fn update_controller(&mut self, args: &CameraControllerArgs)
+    {
+        // The controller is taken out of the camera so that it can be given the camera mutably
+        if let Some(mut controller) = self.controller.take()
+        {
+            controller.update(self, args);
+
+            if self.controller.is_none()
+            {
+                self.controller = Some(controller);
+            }
+        }
+    }
+
+    /// Get the volume the camera currently sees, which is the same volume used to decide what is rendered
+    pub fn frustum(&self) -> Frustum
+    {
+        Frustum::from_camera(self)
+    }
+
+    /// Get the ray in world space that starts at the near plane of the camera and passes through the given
+    /// point on the screen, such as the position of the cursor
+    ///
+    /// `mouse_x` - the x-coordinate of the point, measured from the left of the window
+    /// `mouse_y` - the y-coordinate of the point, measured from the top of the window
+    /// `viewport` - the area of the window the camera renders to, as (x, y, width, height) with the
+    ///              position measured from the top left of the window
+    pub fn screen_ray(&self, mouse_x: i32, mouse_y: i32, viewport: (i32, i32, i32, i32)) -> Ray
+    {
+        let ndc_x = 2.0 * (mouse_x - viewport.0) as f32 / viewport.2.max(1) as f32 - 1.0;
+        let ndc_y = 1.0 - 2.0 * (mouse_y - viewport.1) as f32 / viewport.3.max(1) as f32;
+
+        let inverse_view_projection = nalgebra_glm::inverse(&(self.projection_matrix * self.view_matrix));
+
+        let near_point = inverse_view_projection * vec4(ndc_x, ndc_y, -1.0, 1.0);
+        let far_point = inverse_view_projection * vec4(ndc_x, ndc_y, 1.0, 1.0);
+
+        let near_point = near_point.xyz() / near_point.w;
+        let far_point = far_point.xyz() / far_point.w;
+
+        Ray::new(near_point, far_point - near_point)
+    }
+
+    /// Finds the closest entity under the given point on the screen that is within the render distance
+    /// of the camera. Entities surrounding the camera, such as the entity the camera is attached to,
+    /// are not picked
+    ///
+    /// `mouse_x` - the x-coordinate of the point, measured from the left of the window
+    /// `mouse_y` - the y-coordinate of the point, measured from the top of the window
+    /// `viewport` - the area of the window the camera renders to, as (x, y, width, height)
+    /// `ecs` - the ECS holding the entities
+    /// `bounding_box_tree` - the tree holding the location of the entities
+    pub fn pick_entity(&self, mouse_x: i32, mouse_y: i32, viewport: (i32, i32, i32, i32), ecs: &ECS, bounding_box_tree: &BoundingBoxTree) -> Option<EntityId>
+    {
+        let ray = self.screen_ray(mouse_x, mouse_y, viewport);
+
+        bounding_box_tree.find_entities_along_ray(ecs, &ray, self.far_draw_distance).into_iter()
+            .find(|(_, distance)| *distance > 0.0)
+            .map(|(entity_id, _)| entity_id)
+    }
 }
 
 /// A builder to provide a cleaner interface to specify values to a created Camera.
@@ -297,6 +571,8 @@ pub struct CameraBuilder
     top: f32,
     near: f32,
     far: f32,
+
+    controller: Option<Box<dyn CameraController>>,
 }
 
 impl CameraBuilder
@@ -332,7 +608,8 @@ impl CameraBuilder
             window_dimensions, direction, fov, position, near_draw_distance, far_draw_distance, up,
             max_angle_look_down, max_angle_look_up,
             pitch, yaw, mouse_sensitivity, movement_speed_factor,
-            left, right, top, bottom, near, far, is_orthographic
+            left, right, top, bottom, near, far, is_orthographic,
+            controller: None
         }
     }
 
@@ -382,6 +659,12 @@ impl CameraBuilder
             view_matrix_changed: false,
             draw_param_changed: false,
             window_dimensions_change: false,
+
+            controller: self.controller.clone(),
+            animation: None,
+            shakes: Vec::new(),
+            shake_applied: false,
+            shake_offset: vec3(0.0, 0.0, 0.0),
         }
     }
 
@@ -511,6 +794,15 @@ impl CameraBuilder
         self
     }
 
+    /// Specifies the controller that moves the created camera every frame
+    ///
+    /// `controller` - the controller to move the camera with
+    pub fn with_controller<C: CameraController + 'static>(&mut self, controller: C) -> &mut Self
+    {
+        self.controller = Some(Box::new(controller));
+        self
+    }
+
     /// Marks the created camera to be an orthographic camera
 
     pub fn as_orthographic(&mut self) -> &mut Self
@@ -578,4 +870,65 @@ impl CameraBuilder
         self.near = near;
         self
     }
+}
+
+// The main camera renders to the window. Additional cameras can be registered as camera views, which are
+// rendered every frame into an FBO with the name of the view, for uses such as rear-view mirrors and
+// security camera screens. A render system can also be assigned a camera view, in which case the render
+// system renders to the window from the perspective of that view rather than the main camera
+
+/// Describes a camera that renders the game world every frame into an FBO with the name of the view, accessible
+/// to the draw functions of the display render system. The FBO has one colour attachment holding the capture
+#[derive(Clone)]
+pub struct CameraViewDescription
+{
+    pub name: String,
+    pub camera: Camera,
+    pub render_systems: Vec<RenderSystemIndex>,
+    pub display_render_system: RenderSystemIndex,
+}
+
+/// Changes to camera views requested by the user, to be applied by the render flow
+pub(crate) enum CameraViewRequest
+{
+    Add(CameraViewDescription),
+    Remove(String),
+    UpdateCamera(String, Camera),
+    AssignRenderSystem(RenderSystemIndex, Option<String>),
+}
+
+/// Adds a camera view that is rendered every frame, starting in the next frame. A view with the same
+/// name as an existing view replaces it
+///
+/// `description` - the camera view to add
+pub fn add_camera_view(description: CameraViewDescription)
+{
+    REQUESTED_CAMERA_VIEW_CHANGES.lock().push(CameraViewRequest::Add(description));
+}
+
+/// Removes the camera view with the given name, along with its FBO. Render systems assigned the view
+/// render from the main camera again
+///
+/// `name` - the name of the camera view to remove
+pub fn remove_camera_view<A: AsRef<str>>(name: A)
+{
+    REQUESTED_CAMERA_VIEW_CHANGES.lock().push(CameraViewRequest::Remove(name.as_ref().to_string()));
+}
+
+/// Replaces the camera of a camera view, such as to follow a moving vehicle
+///
+/// `name` - the name of the camera view to change
+/// `camera` - the new camera of the view
+pub fn update_camera_view<A: AsRef<str>>(name: A, camera: Camera)
+{
+    REQUESTED_CAMERA_VIEW_CHANGES.lock().push(CameraViewRequest::UpdateCamera(name.as_ref().to_string(), camera));
+}
+
+/// Makes a render system render to the window from the perspective of a camera view instead of the main camera
+///
+/// `render_system_index` - the render system to assign the camera to
+/// `camera_view` - the name of the camera view to render from, or None to render from the main camera
+pub fn assign_camera_to_render_system(render_system_index: RenderSystemIndex, camera_view: Option<&str>)
+{
+    REQUESTED_CAMERA_VIEW_CHANGES.lock().push(CameraViewRequest::AssignRenderSystem(render_system_index, camera_view.map(|x| x.to_string())));
 }
\ No newline at end of file
@@ -0,0 +1,194 @@
+use nalgebra_glm::TVec3;
+use crate::exports::camera_controller::{CameraController, CameraControllerArgs};
+use crate::exports::camera_object::Camera;
+
+/// A point the camera passes through on a camera path
+#[derive(Copy, Clone, Debug)]
+pub struct CameraKeyframe
+{
+    /// Time since the start of the path at which the camera is at this keyframe, in seconds
+    pub time: f32,
+    pub position: TVec3<f32>,
+    pub direction: TVec3<f32>,
+}
+
+/// A smooth path through keyframes for the camera to follow, such as for cutscenes or flythroughs.
+/// The camera moves along a Catmull-Rom spline through the positions of the keyframes, so it passes
+/// through every keyframe without sudden changes of direction
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath
+{
+    keyframes: Vec<CameraKeyframe>,
+}
+
+/// Moves the camera along a camera path
+#[derive(Clone)]
+pub struct CameraPathController
+{
+    path: CameraPath,
+    elapsed_time: f32,
+    start_delay: f32,
+    looping: bool,
+}
+
+impl CameraPath
+{
+    /// Creates a path without any keyframes
+    pub fn new() -> CameraPath
+    {
+        CameraPath{ keyframes: Vec::new() }
+    }
+
+    /// Adds a keyframe to the path. Keyframes can be added in any order
+    ///
+    /// `time` - the time since the start of the path at which the camera is at the keyframe, in seconds
+    /// `position` - the position of the camera at the keyframe
+    /// `direction` - the direction of the camera at the keyframe
+    pub fn with_keyframe(&mut self, time: f32, position: TVec3<f32>, direction: TVec3<f32>) -> &mut Self
+    {
+        let keyframe = CameraKeyframe{ time: time.max(0.0), position, direction: nalgebra_glm::normalize(&direction) };
+        let index = self.keyframes.iter().position(|x| x.time > keyframe.time).unwrap_or(self.keyframes.len());
+
+        self.keyframes.insert(index, keyframe);
+        self
+    }
+
+    /// Get the keyframes of the path, ordered by time
+    pub fn keyframes(&self) -> &[CameraKeyframe]
+    {
+        &self.keyframes
+    }
+
+    /// Get the time at which the camera reaches the last keyframe, in seconds
+    pub fn duration(&self) -> f32
+    {
+        self.keyframes.last().map(|x| x.time).unwrap_or(0.0)
+    }
+
+    /// Get the position and direction of the camera at the given time. None is returned if the path has
+    /// no keyframes. Times outside of the path give the first or last keyframe
+    ///
+    /// `time` - the time since the start of the path, in seconds
+    pub fn sample(&self, time: f32) -> Option<(TVec3<f32>, TVec3<f32>)>
+    {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if time <= first.time
+        {
+            return Some((first.position, first.direction));
+        }
+
+        if time >= last.time
+        {
+            return Some((last.position, last.direction));
+        }
+
+        // The keyframe after the given time exists, as the time is before the last keyframe
+        let next = self.keyframes.iter().position(|x| x.time > time).unwrap();
+        let current = next - 1;
+
+        // The ends of the path use their own keyframe as the missing neighbour
+        let before = current.saturating_sub(1);
+        let after = (next + 1).min(self.keyframes.len() - 1);
+
+        let segment_length = self.keyframes[next].time - self.keyframes[current].time;
+        let t = if segment_length > 0.0 { (time - self.keyframes[current].time) / segment_length } else { 1.0 };
+
+        let position = catmull_rom(&self.keyframes[before].position, &self.keyframes[current].position,
+                                   &self.keyframes[next].position, &self.keyframes[after].position, t);
+
+        let direction = catmull_rom(&self.keyframes[before].direction, &self.keyframes[current].direction,
+                                    &self.keyframes[next].direction, &self.keyframes[after].direction, t);
+
+        // Opposite directions pass through zero length partway between the keyframes
+        let direction = if nalgebra_glm::length(&direction) > f32::EPSILON
+        {
+            nalgebra_glm::normalize(&direction)
+        }
+        else
+        {
+            self.keyframes[next].direction
+        };
+
+        Some((position, direction))
+    }
+}
+
+impl CameraPathController
+{
+    /// Creates a controller that moves the camera along the path once
+    ///
+    /// `path` - the path to move the camera along
+    pub fn new(path: CameraPath) -> CameraPathController
+    {
+        CameraPathController{ path, elapsed_time: 0.0, start_delay: 0.0, looping: false }
+    }
+
+    /// Specifies how long the camera is held at the first keyframe before it starts moving
+    ///
+    /// `start_delay` - the time to wait before moving, in seconds
+    pub fn with_start_delay(&mut self, start_delay: f32) -> &mut Self
+    {
+        self.start_delay = start_delay.max(0.0);
+        self
+    }
+
+    /// Specifies if the camera returns to the start of the path after reaching its end
+    ///
+    /// `looping` - true if the path should repeat
+    pub fn with_looping(&mut self, looping: bool) -> &mut Self
+    {
+        self.looping = looping;
+        self
+    }
+
+    /// Checks if the camera has reached the end of the path. A looping path never finishes
+    pub fn is_finished(&self) -> bool
+    {
+        !self.looping && self.elapsed_time >= self.start_delay + self.path.duration()
+    }
+}
+
+impl CameraController for CameraPathController
+{
+    fn update(&mut self, camera: &mut Camera, args: &CameraControllerArgs)
+    {
+        self.elapsed_time += args.delta_time;
+
+        let mut path_time = (self.elapsed_time - self.start_delay).max(0.0);
+
+        if self.looping && self.path.duration() > 0.0
+        {
+            path_time %= self.path.duration();
+        }
+
+        if let Some((position, direction)) = self.path.sample(path_time)
+        {
+            camera.set_position_and_direction(position, direction);
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn CameraController>
+    {
+        Box::new(self.clone())
+    }
+}
+
+/// Interpolates between the second and third points of a uniform Catmull-Rom spline
+///
+/// `p0` - the point before the segment
+/// `p1` - the start of the segment
+/// `p2` - the end of the segment
+/// `p3` - the point after the segment
+/// `t` - how far along the segment to interpolate, from 0 to 1
+fn catmull_rom(p0: &TVec3<f32>, p1: &TVec3<f32>, p2: &TVec3<f32>, p3: &TVec3<f32>, t: f32) -> TVec3<f32>
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (p1 * 2.0 +
+        (p2 - p0) * t +
+        (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 +
+        (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
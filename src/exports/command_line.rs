@@ -0,0 +1,199 @@
+use std::env;
+use std::path::PathBuf;
+use crate::exports::load_models::UserUploadInformation;
+
+// Engine options that can be given on the command line, so that a run can be tweaked without changing code:
+//
+//     --debug-replay          replay the recorded gameplay history instead of recording it
+//     --windowed              open a window instead of going fullscreen
+//     --resolution 1920x1080  size of the window
+//     --max-fps 144           maximum frames per second
+//     --capture path          folder the gameplay history is recorded to and replayed from
+//
+// Arguments that are not engine options are ignored, allowing the game to define its own arguments
+
+/// Engine options read from the command line. Options that were not given are left as they are
+/// in the UserUploadInformation they are applied to
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandLineOptions
+{
+    pub debug_replay: bool,
+    pub windowed: bool,
+    pub resolution: Option<(u32, u32)>,
+    pub max_fps: Option<i64>,
+    pub capture: Option<PathBuf>,
+}
+
+/// Errors that occur when reading the engine options from the command line
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CommandLineError
+{
+    /// The option requires a value but none was given
+    MissingValue(String),
+    /// The value given to the option could not be parsed. Holds the option and the value
+    InvalidValue(String, String),
+}
+
+impl CommandLineOptions
+{
+    /// Reads the engine options from the arguments the program was launched with
+    pub fn from_env() -> Result<CommandLineOptions, CommandLineError>
+    {
+        CommandLineOptions::parse(env::args().skip(1))
+    }
+
+    /// Reads the engine options from the given arguments. The arguments should not include the
+    /// name of the program
+    ///
+    /// `args` - the arguments to read the engine options from
+    pub fn parse<I, S>(args: I) -> Result<CommandLineOptions, CommandLineError>
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        let mut options = CommandLineOptions::default();
+        let mut args = args.into_iter().map(|x| x.into());
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--debug-replay" => options.debug_replay = true,
+                "--windowed" => options.windowed = true,
+                "--resolution" =>
+                    {
+                        let value = CommandLineOptions::next_value(&arg, &mut args)?;
+                        options.resolution = Some(CommandLineOptions::parse_resolution(&value).ok_or(CommandLineError::InvalidValue(arg, value))?);
+                    },
+                "--max-fps" =>
+                    {
+                        let value = CommandLineOptions::next_value(&arg, &mut args)?;
+                        match value.parse::<i64>()
+                        {
+                            Ok(i) if i > 0 => options.max_fps = Some(i),
+                            _ => return Err(CommandLineError::InvalidValue(arg, value))
+                        }
+                    },
+                "--capture" => options.capture = Some(PathBuf::from(CommandLineOptions::next_value(&arg, &mut args)?)),
+                _ => {}
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Overrides the upload information with the options that were given on the command line
+    ///
+    /// `upload_info` - the upload information to modify
+    pub fn apply(&self, upload_info: &mut UserUploadInformation)
+    {
+        if self.debug_replay
+        {
+            upload_info.is_debugging = true;
+        }
+
+        if self.windowed
+        {
+            upload_info.fullscreen = false;
+        }
+
+        if let Some(resolution) = self.resolution
+        {
+            upload_info.window_resolution = resolution;
+            upload_info.initial_camera.account_window_change((resolution.0 as i32, resolution.1 as i32));
+        }
+
+        if let Some(max_fps) = self.max_fps
+        {
+            upload_info.max_fps = max_fps;
+        }
+
+        if let Some(ref capture) = self.capture
+        {
+            upload_info.capture_directory = Some(capture.clone());
+        }
+    }
+
+    /// Get the value following an option that requires one
+    ///
+    /// `option` - the option the value is for
+    /// `args` - the remaining arguments
+    fn next_value<I: Iterator<Item = String>>(option: &str, args: &mut I) -> Result<String, CommandLineError>
+    {
+        match args.next()
+        {
+            Some(i) if !i.starts_with("--") => Ok(i),
+            _ => Err(CommandLineError::MissingValue(option.to_string()))
+        }
+    }
+
+    /// Parses a resolution in the format widthxheight
+    ///
+    /// `value` - the resolution to parse
+    fn parse_resolution(value: &str) -> Option<(u32, u32)>
+    {
+        let (width, height) = value.split_once(['x', 'X'])?;
+        let width = width.trim().parse::<u32>().ok()?;
+        let height = height.trim().parse::<u32>().ok()?;
+
+        if width == 0 || height == 0
+        {
+            return None;
+        }
+
+        Some((width, height))
+    }
+}
+
+/// Overrides the upload information with the engine options given on the command line
+///
+/// `upload_info` - the upload information to modify
+pub fn apply_command_line_arguments(upload_info: &mut UserUploadInformation) -> Result<(), CommandLineError>
+{
+    CommandLineOptions::from_env()?.apply(upload_info);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::path::PathBuf;
+    use crate::exports::command_line::{CommandLineError, CommandLineOptions};
+
+    #[test]
+    fn parse_each_flag()
+    {
+        assert_eq!(CommandLineOptions{ debug_replay: true, ..Default::default() }, CommandLineOptions::parse(vec!["--debug-replay"]).unwrap());
+        assert_eq!(CommandLineOptions{ windowed: true, ..Default::default() }, CommandLineOptions::parse(vec!["--windowed"]).unwrap());
+        assert_eq!(CommandLineOptions{ max_fps: Some(144), ..Default::default() }, CommandLineOptions::parse(vec!["--max-fps", "144"]).unwrap());
+        assert_eq!(CommandLineOptions{ capture: Some(PathBuf::from("captures")), ..Default::default() }, CommandLineOptions::parse(vec!["--capture", "captures"]).unwrap());
+        assert_eq!(CommandLineOptions::default(), CommandLineOptions::parse(Vec::<String>::new()).unwrap());
+    }
+
+    #[test]
+    fn parse_resolution()
+    {
+        assert_eq!(Some((1920, 1080)), CommandLineOptions::parse(vec!["--resolution", "1920x1080"]).unwrap().resolution);
+
+        assert_eq!(Err(CommandLineError::InvalidValue("--resolution".to_string(), "0x10".to_string())), CommandLineOptions::parse(vec!["--resolution", "0x10"]));
+        assert_eq!(Err(CommandLineError::InvalidValue("--resolution".to_string(), "abc".to_string())), CommandLineOptions::parse(vec!["--resolution", "abc"]));
+    }
+
+    #[test]
+    fn reject_zero_max_fps()
+    {
+        assert_eq!(Err(CommandLineError::InvalidValue("--max-fps".to_string(), "0".to_string())), CommandLineOptions::parse(vec!["--max-fps", "0"]));
+    }
+
+    #[test]
+    fn missing_value()
+    {
+        assert_eq!(Err(CommandLineError::MissingValue("--resolution".to_string())), CommandLineOptions::parse(vec!["--resolution", "--windowed"]));
+        assert_eq!(Err(CommandLineError::MissingValue("--capture".to_string())), CommandLineOptions::parse(vec!["--capture"]));
+    }
+
+    #[test]
+    fn ignore_unknown_arguments()
+    {
+        let options = CommandLineOptions::parse(vec!["--game-option", "value", "--windowed", "level1"]).unwrap();
+        assert_eq!(CommandLineOptions{ windowed: true, ..Default::default() }, options);
+    }
+}
@@ -0,0 +1,192 @@
+use std::any::TypeId;
+use std::mem::size_of;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::objects::ecs::{ECS, TypeIdentifier};
+use crate::objects::entity_change_request::{EntityChangeInformation, EntityChangeRequest};
+use crate::objects::entity_id::EntityId;
+
+// Components are stored as bytes in the ECS, which have no meaning without their type. Registering a
+// component for reflection stores functions that convert the bytes of the component to and from a JSON
+// value using the component's serde implementation, along with a name to show the component as. The
+// registry is shared by all ECS instances, as it only describes types.
+//
+// Entity types can also be registered with a name, so that files referring to them, such as exported world
+// sections, stay valid between builds of the game where the type IDs differ
+
+/// Functions converting the bytes of one component type to and from JSON
+#[derive(Copy, Clone)]
+struct ComponentReflection
+{
+    name: &'static str,
+    size: usize,
+    to_json: fn(&[u8]) -> Result<Value, serde_json::Error>,
+    from_json: fn(Value) -> Result<Vec<u8>, serde_json::Error>,
+}
+
+/// The value of a component of an entity, in a form that can be displayed and edited
+#[derive(Clone, Debug)]
+pub struct InspectedComponent
+{
+    pub name: &'static str,
+    pub type_id: TypeIdentifier,
+    pub value: Value,
+}
+
+/// Reasons a component could not be edited through reflection
+#[derive(Debug)]
+pub enum ReflectionError
+{
+    UnknownComponent(String),
+    InvalidValue(serde_json::Error),
+}
+
+lazy_static!
+{
+    static ref REFLECTED_COMPONENTS: RwLock<HashMap<TypeIdentifier, ComponentReflection>> = RwLock::new(HashMap::default());
+    static ref REFLECTED_ENTITY_TYPES: RwLock<HashMap<TypeIdentifier, &'static str>> = RwLock::new(HashMap::default());
+}
+
+/// Allows components of the given type to be inspected and edited by name. Registering a type again
+/// replaces its name
+///
+/// `name` - the name the component is shown and found with
+pub fn register_reflected_component<T: 'static + Copy + Serialize + DeserializeOwned>(name: &'static str)
+{
+    let reflection = ComponentReflection
+    {
+        name,
+        size: size_of::<T>(),
+        to_json: component_to_json::<T>,
+        from_json: component_from_json::<T>,
+    };
+
+    REFLECTED_COMPONENTS.write().insert(TypeIdentifier::from(TypeId::of::<T>()), reflection);
+}
+
+/// Get the name a component type was registered for reflection with
+///
+/// `type_id` - the type of the component
+pub fn reflected_component_name(type_id: TypeIdentifier) -> Option<&'static str>
+{
+    REFLECTED_COMPONENTS.read().get(&type_id).map(|x| x.name)
+}
+
+/// Allows entities of the given type to be referred to by name. Registering a type again replaces its name
+///
+/// `name` - the name the entity type is found with
+pub fn register_reflected_entity_type<T: 'static>(name: &'static str)
+{
+    REFLECTED_ENTITY_TYPES.write().insert(TypeIdentifier::from(TypeId::of::<T>()), name);
+}
+
+/// Get the name an entity type was registered for reflection with
+///
+/// `type_id` - the entity type
+pub fn reflected_entity_type_name(type_id: TypeIdentifier) -> Option<&'static str>
+{
+    REFLECTED_ENTITY_TYPES.read().get(&type_id).copied()
+}
+
+/// Get the entity type registered for reflection with the given name
+///
+/// `name` - the name the entity type was registered with
+pub fn reflected_entity_type(name: &str) -> Option<TypeIdentifier>
+{
+    REFLECTED_ENTITY_TYPES.read().iter().find(|(_, x)| **x == name).map(|(type_id, _)| *type_id)
+}
+
+/// Get the values of all of the components of the entity that were registered for reflection, ordered
+/// by the order the component types were registered with the ECS
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to inspect
+pub fn inspect_entity(ecs: &ECS, entity_id: EntityId) -> Vec<InspectedComponent>
+{
+    let reflected_components = REFLECTED_COMPONENTS.read();
+    let mut inspected_components = Vec::new();
+
+    for type_id in ecs.get_component_types(entity_id)
+    {
+        let reflection = match reflected_components.get(&type_id)
+        {
+            Some(i) => i,
+            None => continue
+        };
+
+        let value = ecs.get_component_bytes(entity_id, type_id, reflection.size)
+            .map(|x| (reflection.to_json)(x).unwrap_or(Value::Null))
+            .unwrap_or(Value::Null);
+
+        inspected_components.push(InspectedComponent{ name: reflection.name, type_id, value });
+    }
+
+    inspected_components
+}
+
+/// Creates the change setting a component of the entity to the given value. Return the change from a
+/// logic function to have the component written at the end of the frame, which records the edit in the
+/// history of the game
+///
+/// `entity_id` - the entity whose component is edited
+/// `component_name` - the name the component was registered for reflection with
+/// `value` - the new value of the component, in the form given by inspect_entity
+pub fn edit_component(entity_id: EntityId, component_name: &str, value: Value) -> Result<EntityChangeInformation, ReflectionError>
+{
+    let (type_id, serialized_value) = serialize_reflected_component(component_name, value)?;
+
+    let mut change_request = EntityChangeRequest::new(entity_id);
+    change_request.type_id.push((type_id, serialized_value));
+
+    Ok(EntityChangeInformation::ModifyRequest(change_request))
+}
+
+/// Converts the value of a component, in the form given by inspect_entity, into the type of the component
+/// and the bytes of the component as they are stored in the ECS
+///
+/// `component_name` - the name the component was registered for reflection with
+/// `value` - the value of the component
+pub(crate) fn serialize_reflected_component(component_name: &str, value: Value) -> Result<(TypeIdentifier, Vec<u8>), ReflectionError>
+{
+    let (type_id, reflection) = match REFLECTED_COMPONENTS.read().iter().find(|(_, x)| x.name == component_name)
+    {
+        Some((type_id, reflection)) => (*type_id, *reflection),
+        None => return Err(ReflectionError::UnknownComponent(component_name.to_string()))
+    };
+
+    let serialized_value = (reflection.from_json)(value).map_err(ReflectionError::InvalidValue)?;
+    Ok((type_id, serialized_value))
+}
+
+/// Converts the bytes of a component stored in the ECS into a JSON value
+///
+/// `bytes` - the bytes of the component
+fn component_to_json<T: Copy + Serialize>(bytes: &[u8]) -> Result<Value, serde_json::Error>
+{
+    let value = unsafe
+        {
+            std::ptr::read_unaligned(bytes.as_ptr() as *const T)
+        };
+
+    serde_json::to_value(value)
+}
+
+/// Converts a JSON value into the bytes of a component as they are stored in the ECS
+///
+/// `value` - the JSON value of the component
+fn component_from_json<T: Copy + DeserializeOwned>(value: Value) -> Result<Vec<u8>, serde_json::Error>
+{
+    let component: T = serde_json::from_value(value)?;
+    let mut serialized_value: Vec<u8> = vec![0; size_of::<T>()];
+
+    unsafe
+        {
+            std::ptr::write_unaligned(serialized_value.as_mut_ptr() as *mut T, component);
+        }
+
+    Ok(serialized_value)
+}
@@ -0,0 +1,229 @@
+use hashbrown::{HashMap, HashSet};
+use lazy_static::lazy_static;
+use nalgebra_glm::{TVec3, vec4};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::exports::movement_components::TransformationMatrix;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+
+// Damage is accumulated into a small mask for every damaged entity, so that models visibly accumulate
+// scorch marks without having to be swapped for damaged versions. The mask is a map of the directions
+// around the centre of the model, so every point on the surface of a model reads the same texel of the
+// mask regardless of the UV layout of the model. Render systems sample the masks in their first pass
+// fragment shader when created with damage masks; the default render system does so. Static entities
+// only show the first hit on them once their world section is next sorted, as the instance data of static
+// entities is not rewritten every frame
+
+/// Number of texels around the model in each damage mask
+pub const DAMAGE_MASK_WIDTH: usize = 64;
+
+/// Number of texels from the bottom to the top of the model in each damage mask
+pub const DAMAGE_MASK_HEIGHT: usize = 32;
+
+/// Maximum number of entities that can be damaged at the same time
+pub const MAX_DAMAGED_ENTITIES: usize = 256;
+
+lazy_static!
+{
+    static ref DAMAGE_MASKS: Mutex<DamageMasks> = Mutex::new(DamageMasks
+    {
+        pending_hits: Vec::new(),
+        masks: HashMap::default(),
+        free_layers: (0..MAX_DAMAGED_ENTITIES).rev().collect(),
+        dirty_layers: HashSet::default()
+    });
+
+    // Read for every instance written to the instance buffers, so it is kept apart from the masks to
+    // avoid waiting on hits being applied
+    static ref DAMAGE_LAYERS: RwLock<HashMap<EntityId, usize>> = RwLock::new(HashMap::default());
+}
+
+/// The per-instance data telling the shader which damage mask an entity uses. The first component is
+/// the layer of the mask plus one, or 0 if the entity is not damaged
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DamageLayer([u32; 4]);
+
+/// A hit on an entity that leaves scorch on its surface
+#[derive(Copy, Clone, Debug)]
+pub struct DamageHit
+{
+    /// Position of the hit in the world, typically where a projectile collided with the entity
+    pub position: TVec3<f32>,
+    /// Distance in the world around the hit that is scorched
+    pub radius: f32,
+    /// How much scorch the hit leaves at its centre, from 0 to 1. Hits in the same area add up
+    pub strength: f32,
+}
+
+struct DamageMasks
+{
+    pending_hits: Vec<(EntityId, DamageHit)>,
+    masks: HashMap<usize, Vec<u8>>,
+    free_layers: Vec<usize>,
+    dirty_layers: HashSet<usize>,
+}
+
+impl DamageMasks
+{
+    /// Releases the mask of the entity, so that another entity can be damaged
+    ///
+    /// `entity_id` - the entity whose mask is released
+    fn release(&mut self, entity_id: EntityId)
+    {
+        if let Some(layer) = DAMAGE_LAYERS.write().remove(&entity_id)
+        {
+            self.masks.remove(&layer);
+            self.dirty_layers.remove(&layer);
+            self.free_layers.push(layer);
+        }
+    }
+
+    /// Accumulates the hit into the mask of the entity, taking a free mask if the entity was not damaged
+    /// before. Returns false if there is no free mask for the entity
+    ///
+    /// `entity_id` - the entity that was hit
+    /// `hit` - the hit on the entity
+    /// `transformation` - the transformation of the entity
+    fn splat(&mut self, entity_id: EntityId, hit: DamageHit, transformation: &TransformationMatrix) -> bool
+    {
+        let existing_layer = DAMAGE_LAYERS.read().get(&entity_id).copied();
+
+        let layer = match existing_layer.or_else(|| self.free_layers.pop())
+        {
+            Some(i) => i,
+            None => return false
+        };
+
+        DAMAGE_LAYERS.write().insert(entity_id, layer);
+
+        let matrix = transformation.get_matrix();
+        let object_position = nalgebra_glm::inverse(&matrix) * vec4(hit.position.x, hit.position.y, hit.position.z, 1.0);
+        let object_position = object_position.xyz();
+        let distance_to_centre = nalgebra_glm::length(&object_position);
+
+        // The mask stores directions from the centre of the model, so a hit at the centre has no direction
+        if distance_to_centre <= f32::EPSILON
+        {
+            return true;
+        }
+
+        // The radius is given in the world, and is scaled into the space of the model before being turned
+        // into an angle around the centre of the model
+        let scale = nalgebra_glm::length(&matrix.column(0).xyz()).max(f32::EPSILON);
+        let angular_radius = (hit.radius / scale / distance_to_centre).min(std::f32::consts::PI);
+        let hit_direction = object_position / distance_to_centre;
+
+        let mask = self.masks.entry(layer).or_insert_with(|| vec![0; DAMAGE_MASK_WIDTH * DAMAGE_MASK_HEIGHT]);
+
+        for (index, texel) in mask.iter_mut().enumerate()
+        {
+            let texel_direction = mask_direction(index % DAMAGE_MASK_WIDTH, index / DAMAGE_MASK_WIDTH);
+            let angle = nalgebra_glm::dot(&texel_direction, &hit_direction).clamp(-1.0, 1.0).acos();
+
+            if angle < angular_radius
+            {
+                let added_scorch = hit.strength.clamp(0.0, 1.0) * (1.0 - angle / angular_radius) * 255.0;
+                *texel = (*texel as f32 + added_scorch).min(255.0) as u8;
+            }
+        }
+
+        self.dirty_layers.insert(layer);
+        true
+    }
+}
+
+/// Finds the direction from the centre of a model that the centre of a damage mask texel covers. This
+/// matches the mapping done by the shaders sampling the mask
+///
+/// `x` - the column of the texel, going around the model
+/// `y` - the row of the texel, going from the bottom of the model to the top
+fn mask_direction(x: usize, y: usize) -> TVec3<f32>
+{
+    let longitude = ((x as f32 + 0.5) / DAMAGE_MASK_WIDTH as f32 - 0.5) * std::f32::consts::PI * 2.0;
+    let latitude = ((y as f32 + 0.5) / DAMAGE_MASK_HEIGHT as f32 - 0.5) * std::f32::consts::PI;
+
+    TVec3::new(latitude.cos() * longitude.cos(), latitude.sin(), latitude.cos() * longitude.sin())
+}
+
+/// Damages the entity, scorching its surface around the hit. The hit is applied when the next frame is
+/// rendered, using the transformation of the entity at that time. If too many entities are damaged, the
+/// hit is ignored until the damage of another entity is repaired
+///
+/// `entity_id` - the entity that was hit
+/// `hit` - where and how strongly the entity was hit
+pub fn apply_damage(entity_id: EntityId, hit: DamageHit)
+{
+    DAMAGE_MASKS.lock().pending_hits.push((entity_id, hit));
+}
+
+/// Removes all damage from the entity, freeing its mask for other entities. The masks of entities that
+/// are removed from the world are freed automatically
+///
+/// `entity_id` - the entity whose damage is removed
+pub fn repair_damage(entity_id: EntityId)
+{
+    let mut damage_masks = DAMAGE_MASKS.lock();
+    damage_masks.pending_hits.retain(|(x, _)| *x != entity_id);
+    damage_masks.release(entity_id);
+}
+
+/// Checks if the entity has any damage applied to it
+///
+/// `entity_id` - the entity to check
+pub fn is_damaged(entity_id: EntityId) -> bool
+{
+    DAMAGE_LAYERS.read().contains_key(&entity_id)
+}
+
+/// Get the per-instance damage data of the entity to write into an instance buffer
+///
+/// `entity_id` - the entity being written into the instance buffer
+pub(crate) fn damage_layer(entity_id: EntityId) -> DamageLayer
+{
+    match DAMAGE_LAYERS.read().get(&entity_id)
+    {
+        Some(layer) => DamageLayer([*layer as u32 + 1, 0, 0, 0]),
+        None => DamageLayer::default()
+    }
+}
+
+/// Applies the pending hits, returning the masks that changed along with their layer so that they can
+/// be uploaded. Masks of entities that no longer exist are released
+///
+/// `ecs` - the ECS holding the transformations of the damaged entities
+pub(crate) fn take_changed_damage_masks(ecs: &ECS) -> Vec<(usize, Vec<u8>)>
+{
+    let mut damage_masks = DAMAGE_MASKS.lock();
+
+    let removed_entities = DAMAGE_LAYERS.read().keys()
+        .filter(|x| ecs.get_ref::<TransformationMatrix>(**x).is_none())
+        .copied()
+        .collect::<Vec<EntityId>>();
+
+    for entity_id in removed_entities
+    {
+        damage_masks.release(entity_id);
+    }
+
+    let pending_hits = std::mem::take(&mut damage_masks.pending_hits);
+
+    for (entity_id, hit) in pending_hits
+    {
+        if let Some(transformation) = ecs.get_copy::<TransformationMatrix>(entity_id)
+        {
+            if !damage_masks.splat(entity_id, hit, &transformation)
+            {
+                warn!("Cannot damage entity {:?}: all {} damage masks are in use", entity_id, MAX_DAMAGED_ENTITIES);
+            }
+        }
+    }
+
+    let dirty_layers = std::mem::take(&mut damage_masks.dirty_layers);
+
+    dirty_layers.into_iter()
+        .map(|x| (x, damage_masks.masks[&x].clone()))
+        .collect()
+}
@@ -0,0 +1,165 @@
+use lazy_static::lazy_static;
+use nalgebra_glm::{TMat4, TVec3, TVec4, vec3, vec4};
+use parking_lot::Mutex;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+// Debug drawing queues lines from any thread, such as from logic or from draw functions, that are drawn over
+// the next frame rendered into the window and then discarded. Shapes such as bounding volumes are queued as
+// the lines of their outline, so anything queued is drawn with one draw call. Lines are given in world space
+// and drawn after post-processing; depth tested lines are hidden behind geometry using the world positions
+// of the frame, as with particles. Lines queued faster than frames are rendered, such as when the logic
+// thread runs ahead, are all drawn in the next frame
+
+lazy_static!
+{
+    static ref DEBUG_LINES: Mutex<Vec<DebugLine>> = Mutex::new(Vec::new());
+}
+
+/// Maximum number of lines queued at once. Lines queued beyond this are dropped, so that nothing grows
+/// without bound while no frames are rendered
+const MAX_DEBUG_LINES: usize = 1 << 20;
+
+/// Number of segments each circle of a debug sphere is drawn with
+const SPHERE_SEGMENTS: usize = 24;
+
+/// The corners joined by the edges of a box whose corners are ordered by their x, then y, then z coordinate,
+/// so that the bits of a corner index tell which of its coordinates is the larger one
+const BOX_EDGES: [(usize, usize); 12] = [(0, 1), (2, 3), (4, 5), (6, 7), (0, 2), (1, 3), (4, 6), (5, 7), (0, 4), (1, 5), (2, 6), (3, 7)];
+
+/// A line queued to be drawn over the next frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DebugLine
+{
+    pub start: TVec3<f32>,
+    pub end: TVec3<f32>,
+    pub colour: TVec4<f32>,
+    /// If true, the line is hidden where geometry is in front of it
+    pub depth_tested: bool,
+}
+
+/// Queues lines and outlines of shapes to be drawn over the next frame rendered into the window
+#[derive(Copy, Clone, Debug)]
+pub struct DebugDraw
+{
+    depth_tested: bool,
+}
+
+/// Get a handle to queue lines that are hidden behind geometry
+pub fn debug_draw() -> DebugDraw
+{
+    DebugDraw{ depth_tested: true }
+}
+
+impl DebugDraw
+{
+    /// Get a handle that queues lines drawn over all geometry, such as to see bounding volumes inside walls
+    pub fn without_depth_test(self) -> DebugDraw
+    {
+        DebugDraw{ depth_tested: false }
+    }
+
+    /// Queues a line between two points
+    ///
+    /// `start` - the start of the line, in world space
+    /// `end` - the end of the line, in world space
+    /// `colour` - the colour of the line, including its opacity
+    pub fn draw_line(&self, start: TVec3<f32>, end: TVec3<f32>, colour: TVec4<f32>)
+    {
+        self.queue_lines(&[(start, end)], colour);
+    }
+
+    /// Queues a line starting at a point and going along a direction, such as a ray used for picking
+    ///
+    /// `origin` - the start of the ray, in world space
+    /// `direction` - the direction of the ray. Does not need to be normalized
+    /// `length` - the length of the drawn ray
+    /// `colour` - the colour of the line, including its opacity
+    pub fn draw_ray(&self, origin: TVec3<f32>, direction: TVec3<f32>, length: f32, colour: TVec4<f32>)
+    {
+        if nalgebra_glm::length2(&direction) <= f32::EPSILON
+        {
+            return;
+        }
+
+        self.draw_line(origin, origin + nalgebra_glm::normalize(&direction) * length, colour);
+    }
+
+    /// Queues the twelve edges of an axis aligned bounding box
+    ///
+    /// `aabb` - the bounding box, in world space
+    /// `colour` - the colour of the edges, including their opacity
+    pub fn draw_aabb(&self, aabb: &StaticAABB, colour: TVec4<f32>)
+    {
+        let points = aabb.get_aabb_points();
+        let lines = BOX_EDGES.iter().map(|(a, b)| (points[*a], points[*b])).collect::<Vec<(TVec3<f32>, TVec3<f32>)>>();
+        self.queue_lines(&lines, colour);
+    }
+
+    /// Queues a sphere as three circles, one around each axis
+    ///
+    /// `centre` - the centre of the sphere, in world space
+    /// `radius` - the radius of the sphere
+    /// `colour` - the colour of the circles, including their opacity
+    pub fn draw_sphere(&self, centre: TVec3<f32>, radius: f32, colour: TVec4<f32>)
+    {
+        let mut lines = Vec::with_capacity(SPHERE_SEGMENTS * 3);
+        let point_on_circle = |index: usize| -> (f32, f32)
+            {
+                let angle = index as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+                (angle.cos() * radius, angle.sin() * radius)
+            };
+
+        for index in 0..SPHERE_SEGMENTS
+        {
+            let (a_cos, a_sin) = point_on_circle(index);
+            let (b_cos, b_sin) = point_on_circle(index + 1);
+
+            lines.push((centre + vec3(a_cos, a_sin, 0.0), centre + vec3(b_cos, b_sin, 0.0)));
+            lines.push((centre + vec3(a_cos, 0.0, a_sin), centre + vec3(b_cos, 0.0, b_sin)));
+            lines.push((centre + vec3(0.0, a_cos, a_sin), centre + vec3(0.0, b_cos, b_sin)));
+        }
+
+        self.queue_lines(&lines, colour);
+    }
+
+    /// Queues the edges of the volume seen through a view projection matrix, such as the frustum of a camera
+    /// or the volume covered by the shadow map of a light
+    ///
+    /// `view_projection` - the projection matrix multiplied by the view matrix, taking world space to clip space
+    /// `colour` - the colour of the edges, including their opacity
+    pub fn draw_frustum(&self, view_projection: &TMat4<f32>, colour: TVec4<f32>)
+    {
+        let inverse = nalgebra_glm::inverse(view_projection);
+
+        // Corners are ordered the same way as the points of a bounding box
+        let mut corners = [vec3(0.0, 0.0, 0.0); 8];
+
+        for (index, corner) in corners.iter_mut().enumerate()
+        {
+            let clip_position = vec4(if index & 4 != 0 { 1.0 } else { -1.0 }, if index & 2 != 0 { 1.0 } else { -1.0 }, if index & 1 != 0 { 1.0 } else { -1.0 }, 1.0);
+            let world_position = inverse * clip_position;
+            *corner = world_position.xyz() / world_position.w;
+        }
+
+        let lines = BOX_EDGES.iter().map(|(a, b)| (corners[*a], corners[*b])).collect::<Vec<(TVec3<f32>, TVec3<f32>)>>();
+        self.queue_lines(&lines, colour);
+    }
+
+    /// Adds lines to those drawn over the next frame
+    ///
+    /// `lines` - the start and end of every line
+    /// `colour` - the colour of the lines
+    fn queue_lines(&self, lines: &[(TVec3<f32>, TVec3<f32>)], colour: TVec4<f32>)
+    {
+        let mut debug_lines = DEBUG_LINES.lock();
+        let room = MAX_DEBUG_LINES.saturating_sub(debug_lines.len());
+
+        debug_lines.extend(lines.iter().take(room).map(|(start, end)| DebugLine{ start: *start, end: *end, colour, depth_tested: self.depth_tested }));
+    }
+}
+
+/// Takes every line queued since the last frame, leaving none queued
+pub(crate) fn take_debug_lines() -> Vec<DebugLine>
+{
+    std::mem::take(&mut *DEBUG_LINES.lock())
+}
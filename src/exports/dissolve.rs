@@ -0,0 +1,124 @@
+use std::any::TypeId;
+use nalgebra_glm::{TVec3, vec3};
+use serde::{Deserialize, Serialize};
+use crate::objects::ecs::{ECS, TypeIdentifier};
+use crate::objects::entity_change_request::{EntityChangeInformation, EntityChangeRequest};
+use crate::objects::entity_id::EntityId;
+
+// An entity with a Dissolve component burns away, or burns into view, over the duration of the dissolve.
+// The engine advances every dissolve each frame, and the amount an entity has dissolved is written to the
+// dissolve layout of render systems created with dissolve support. Their first pass fragment shader
+// discards the parts of the model whose noise is below that amount, drawing a glowing edge along the
+// border. Once a dissolve completes the entity is deleted if it was despawning; otherwise the component is
+// removed. Static entities only show the dissolve once their world section is next sorted, as the instance
+// data of static entities is not rewritten every frame
+
+/// Dissolves an entity into or out of view over time
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Dissolve
+{
+    /// Seconds the dissolve takes to complete
+    pub duration: f32,
+    /// Seconds since the dissolve started
+    pub elapsed: f32,
+    /// If true, the entity starts fully dissolved and appears over the duration
+    pub appearing: bool,
+    /// If true, the entity is deleted when the dissolve completes
+    pub despawn_when_complete: bool,
+    /// Colour of the edge between the dissolved and the visible parts of the model
+    pub edge_colour: TVec3<f32>,
+}
+
+impl Dissolve
+{
+    /// Creates a dissolve that makes an entity appear, such as when it is spawned
+    ///
+    /// `duration` - seconds the entity takes to appear
+    pub fn appear(duration: f32) -> Dissolve
+    {
+        Dissolve{ duration, elapsed: 0.0, appearing: true, despawn_when_complete: false, edge_colour: vec3(1.0, 0.55, 0.15) }
+    }
+
+    /// Creates a dissolve that makes an entity disappear, deleting it once it has fully dissolved
+    ///
+    /// `duration` - seconds the entity takes to disappear
+    pub fn despawn(duration: f32) -> Dissolve
+    {
+        Dissolve{ duration, elapsed: 0.0, appearing: false, despawn_when_complete: true, edge_colour: vec3(1.0, 0.55, 0.15) }
+    }
+
+    /// Get how much of the entity has dissolved, from 0 for fully visible to 1 for fully dissolved
+    pub fn amount(&self) -> f32
+    {
+        let progress = if self.duration > 0.0 { (self.elapsed / self.duration).clamp(0.0, 1.0) } else { 1.0 };
+
+        if self.appearing { 1.0 - progress } else { progress }
+    }
+
+    /// Checks if the dissolve has run for its entire duration
+    pub fn is_complete(&self) -> bool
+    {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Creates the change that starts dissolving an entity, replacing any dissolve it already has
+///
+/// `entity_id` - the entity to dissolve
+/// `dissolve` - the dissolve to apply to the entity
+pub fn start_dissolve(entity_id: EntityId, dissolve: Dissolve) -> EntityChangeInformation
+{
+    let mut change_request = EntityChangeRequest::new(entity_id);
+    change_request.add_new_change::<Dissolve>(dissolve);
+    EntityChangeInformation::ModifyRequest(change_request)
+}
+
+/// Advances the dissolve of every dissolving entity, creating the changes that store the advanced
+/// dissolves and that end the completed ones
+///
+/// `ecs` - the ECS holding the dissolving entities
+/// `elapsed_time` - the time the frame advances by in seconds
+pub(crate) fn advance_dissolves(ecs: &ECS, elapsed_time: f32) -> Vec<EntityChangeInformation>
+{
+    let mut changes = Vec::new();
+
+    for (entity_id, (dissolve, )) in ecs.query_read::<(&Dissolve, )>()
+    {
+        // The completed dissolve is kept for a frame so the entity is drawn fully dissolved before it is removed
+        if dissolve.is_complete()
+        {
+            if dissolve.despawn_when_complete
+            {
+                changes.push(EntityChangeInformation::DeleteRequest(entity_id));
+            }
+            else
+            {
+                changes.push(EntityChangeInformation::RemoveComponent((entity_id, TypeIdentifier::from(TypeId::of::<Dissolve>()))));
+            }
+
+            continue;
+        }
+
+        let mut dissolve = *dissolve;
+        dissolve.elapsed = (dissolve.elapsed + elapsed_time).min(dissolve.duration);
+        changes.push(start_dissolve(entity_id, dissolve));
+    }
+
+    changes
+}
+
+/// Appends the dissolve of an entity to the instance data being written for a render system. The amount
+/// dissolved is written first, followed by the edge colour
+///
+/// `ecs` - the ECS holding the entity
+/// `buffer_write_destination` - the instance data of the dissolve layout
+/// `entity_id` - the entity being written
+pub(crate) fn write_dissolve(ecs: &ECS, buffer_write_destination: &mut Vec<u8>, entity_id: EntityId)
+{
+    let values = ecs.get_copy::<Dissolve>(entity_id).map_or([0.0; 4], |x| [x.amount(), x.edge_colour.x, x.edge_colour.y, x.edge_colour.z]);
+
+    for value in values.iter()
+    {
+        buffer_write_destination.extend_from_slice(&value.to_ne_bytes());
+    }
+}
@@ -0,0 +1,177 @@
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::warn;
+use crate::exports::camera_object::Camera;
+use crate::exports::logic_components::RenderSystemIndex;
+use crate::exports::ui_layout::{UiElement, UiRect};
+use crate::flows::shared_constants::REQUESTED_ENGINE_HOOK_CHANGES;
+use crate::render_components::gl_state_guard::GLStateGuard;
+
+// Engine hooks let user code run at fixed points of every frame, such as to start and stop profiler
+// markers, draw overlays between render systems or stream resources in before a frame starts. Each
+// subscriber gives an order; subscribers of the same hook are called from the lowest order to the highest,
+// and in the order they subscribed when their orders are equal.
+//
+// Hooks are called on the render thread with the OpenGL context current. As with frame callbacks, the
+// OpenGL state is saved before each subscriber is called and restored after it
+
+lazy_static!
+{
+    static ref ENGINE_HOOK_SUBSCRIBERS: Mutex<Vec<EngineHookSubscriber>> = Mutex::new(Vec::new());
+}
+
+/// Function called on the render thread when the hook it subscribed to is reached
+pub type EngineHookCallback = Box<dyn FnMut(&EngineHookContext) + Send>;
+
+/// The points of a frame that user code can subscribe to
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EngineHook
+{
+    /// Before anything of the frame is rendered
+    FrameStart,
+    /// Before a shadow map is rendered. Not every frame renders a shadow map
+    BeforeShadowPass,
+    /// After a shadow map is rendered
+    AfterShadowPass,
+    /// Before an enabled render system draws to the window. The context holds the render system
+    BeforeRenderSystem,
+    /// After an enabled render system draws to the window. The context holds the render system
+    AfterRenderSystem,
+    /// After everything of the frame is drawn, before the window buffers are swapped
+    FrameEnd,
+}
+
+/// Information about the frame being rendered that is given to the subscribers of a hook
+pub struct EngineHookContext<'a>
+{
+    hook: EngineHook,
+    camera: &'a Camera,
+    window_dimensions: (i32, i32),
+    render_system: Option<RenderSystemIndex>,
+}
+
+/// Changes to the subscribers of hooks requested by the user, to be applied by the render thread
+pub(crate) enum EngineHookRequest
+{
+    Subscribe(EngineHookSubscriber),
+    Unsubscribe(String),
+}
+
+/// A function subscribed to a hook
+pub(crate) struct EngineHookSubscriber
+{
+    name: String,
+    hook: EngineHook,
+    order: i32,
+    callback: EngineHookCallback,
+}
+
+impl<'a> EngineHookContext<'a>
+{
+    /// Get the hook being run
+    pub fn get_hook(&self) -> EngineHook
+    {
+        self.hook
+    }
+
+    /// Get the camera the frame is rendered from
+    pub fn get_camera(&self) -> &Camera
+    {
+        self.camera
+    }
+
+    /// Get the dimensions of the window being rendered to
+    pub fn get_window_dimensions(&self) -> (i32, i32)
+    {
+        self.window_dimensions
+    }
+
+    /// Finds the rectangle of the window an element covers, in pixels from the bottom left of the window
+    ///
+    /// `element` - the element to lay out
+    pub fn layout(&self, element: &UiElement) -> UiRect
+    {
+        element.layout(self.window_dimensions)
+    }
+
+    /// Get the render system being drawn, for the hooks run before and after a render system
+    pub fn get_render_system(&self) -> Option<RenderSystemIndex>
+    {
+        self.render_system
+    }
+}
+
+/// Subscribes a function to a hook, starting in the next frame. A subscriber with the same name as an
+/// existing subscriber replaces it
+///
+/// `name` - the name of the subscriber, used to unsubscribe it
+/// `hook` - the point of the frame to call the function at
+/// `order` - the position of the function among the subscribers of the hook; lower orders are called first
+/// `callback` - the function to call
+pub fn subscribe_engine_hook<A: Into<String>>(name: A, hook: EngineHook, order: i32, callback: EngineHookCallback)
+{
+    REQUESTED_ENGINE_HOOK_CHANGES.lock().push(EngineHookRequest::Subscribe(EngineHookSubscriber{ name: name.into(), hook, order, callback }));
+}
+
+/// Removes the subscriber with the given name
+///
+/// `name` - the name of the subscriber to remove
+pub fn unsubscribe_engine_hook<A: AsRef<str>>(name: A)
+{
+    REQUESTED_ENGINE_HOOK_CHANGES.lock().push(EngineHookRequest::Unsubscribe(name.as_ref().to_string()));
+}
+
+/// Applies any changes to the subscribers of hooks that were requested since the last frame
+pub(crate) fn apply_engine_hook_requests()
+{
+    let mut subscribers = ENGINE_HOOK_SUBSCRIBERS.lock();
+
+    for request in REQUESTED_ENGINE_HOOK_CHANGES.lock().drain(..)
+    {
+        match request
+        {
+            EngineHookRequest::Subscribe(subscriber) =>
+                {
+                    subscribers.retain(|x| x.name != subscriber.name);
+                    subscribers.push(subscriber);
+                },
+            EngineHookRequest::Unsubscribe(name) =>
+                {
+                    let number_subscribers = subscribers.len();
+                    subscribers.retain(|x| x.name != name);
+
+                    if number_subscribers == subscribers.len()
+                    {
+                        warn!("Unable to unsubscribe non-existent engine hook subscriber: {}", name);
+                    }
+                }
+        }
+    }
+
+    // Stable, so subscribers with the same order keep the order they subscribed in
+    subscribers.sort_by_key(|x| x.order);
+}
+
+/// Calls every subscriber of the hook, restoring the OpenGL state after each subscriber
+///
+/// `hook` - the hook that was reached
+/// `camera` - the camera the frame is rendered from
+/// `window_dimensions` - the dimensions of the window being rendered to
+/// `render_system` - the render system being drawn, for the hooks run before and after a render system
+pub(crate) fn run_engine_hook(hook: EngineHook, camera: &Camera, window_dimensions: (i32, i32), render_system: Option<RenderSystemIndex>)
+{
+    let mut subscribers = ENGINE_HOOK_SUBSCRIBERS.lock();
+
+    if subscribers.iter().all(|x| x.hook != hook)
+    {
+        return;
+    }
+
+    let context = EngineHookContext{ hook, camera, window_dimensions, render_system };
+
+    for subscriber in subscribers.iter_mut().filter(|x| x.hook == hook)
+    {
+        let _state_guard = GLStateGuard::new();
+        (subscriber.callback)(&context);
+    }
+}
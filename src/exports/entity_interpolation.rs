@@ -0,0 +1,207 @@
+use std::any::TypeId;
+use std::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use crate::exports::damage_decals::{damage_layer, DamageLayer};
+use crate::exports::movement_components::{PreviousTransformationMatrix, TransformationMatrix, WorldPosition};
+use crate::exports::pbr_material::{pbr_albedo, pbr_surface, PbrAlbedo, PbrSurface};
+use crate::exports::world_precision::{is_double_precision_positions_enabled, to_f64, to_render_space};
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+
+// By default the game logic is executed once per rendered frame. With a fixed logic timestep, the logic is
+// instead executed zero or more times per frame so that it always advances by the same amount of time. The
+// rendered frame then falls between two logic steps, and entities that moved in the latest step are drawn
+// between their previous and current transformation by the fraction of the step that has passed (the
+// frame alpha). The previous transformation is the PreviousTransformationMatrix written whenever an entity
+// moves, so the game logic is not changed by interpolation
+
+/// Most logic steps executed in a single frame. If a frame takes longer than this many steps, the game
+/// slows down rather than spending ever longer frames catching up
+const MAX_LOGIC_STEPS_PER_FRAME: u32 = 5;
+
+static ENTITY_INTERPOLATION: AtomicBool = AtomicBool::new(true);
+
+lazy_static!
+{
+    static ref FIXED_LOGIC_TIMESTEP: Mutex<Option<f32>> = Mutex::new(None);
+    static ref INTERPOLATION_ALPHA: Mutex<f32> = Mutex::new(1.0);
+}
+
+/// Specifies how much time every execution of the game logic advances by
+///
+/// `timestep` - the time of a logic step in seconds. If None, the logic is executed once per frame with the
+///              time the frame took
+pub fn set_fixed_logic_timestep(timestep: Option<f32>)
+{
+    *FIXED_LOGIC_TIMESTEP.lock() = timestep.filter(|x| *x > 0.0);
+}
+
+/// Get the time of a logic step in seconds, if the logic is executed with a fixed timestep
+pub fn fixed_logic_timestep() -> Option<f32>
+{
+    *FIXED_LOGIC_TIMESTEP.lock()
+}
+
+/// Specifies if entities are drawn between their previous and current transformation when the logic
+/// is executed with a fixed timestep. Enabled by default
+///
+/// `enabled` - true if the transformations of entities should be interpolated
+pub fn set_entity_interpolation(enabled: bool)
+{
+    ENTITY_INTERPOLATION.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks if entities are drawn between their previous and current transformation
+pub fn is_entity_interpolation_enabled() -> bool
+{
+    ENTITY_INTERPOLATION.load(Ordering::Relaxed)
+}
+
+/// Get the transformation the entity is drawn with this frame. None is returned if the entity does not
+/// have a transformation matrix
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to get the transformation of
+pub fn interpolated_transformation(ecs: &ECS, entity_id: EntityId) -> Option<TransformationMatrix>
+{
+    let current = ecs.get_copy::<TransformationMatrix>(entity_id)?;
+    let alpha = *INTERPOLATION_ALPHA.lock();
+
+    // Entities that did not move in the latest logic step are already where they should be drawn
+    if alpha >= 1.0 || !is_entity_interpolation_enabled() || !ecs.is_changed::<TransformationMatrix>(entity_id)
+    {
+        return Some(current);
+    }
+
+    let previous = match ecs.get_copy::<PreviousTransformationMatrix>(entity_id)
+    {
+        Some(i) => i,
+        None => return Some(current)
+    };
+
+    // Interpolating the matrices element wise is exact for translation and scale, and close enough for the
+    // small rotations made in a single logic step
+    let previous = previous.get_matrix();
+    Some(TransformationMatrix::new(previous + (current.get_matrix() - previous) * alpha))
+}
+
+/// Get the transformation the entity is drawn with this frame, with its translation relative to the render
+/// origin. The translation of entities with a WorldPosition is found in double precision
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to get the transformation of
+fn render_space_transformation(ecs: &ECS, entity_id: EntityId) -> TransformationMatrix
+{
+    let transformation = interpolated_transformation(ecs, entity_id).unwrap();
+
+    if !is_double_precision_positions_enabled()
+    {
+        return transformation;
+    }
+
+    let mut matrix = transformation.get_matrix();
+    let translation = match ecs.get_copy::<WorldPosition>(entity_id)
+    {
+        Some(world_position) =>
+            {
+                let alpha = *INTERPOLATION_ALPHA.lock() as f64;
+                let interpolate = alpha < 1.0 && is_entity_interpolation_enabled() && ecs.is_changed::<TransformationMatrix>(entity_id);
+
+                if interpolate
+                {
+                    let previous = world_position.get_previous_position();
+                    to_render_space(previous + (world_position.get_position() - previous) * alpha)
+                }
+                else
+                {
+                    to_render_space(world_position.get_position())
+                }
+            },
+        None => to_render_space(to_f64(matrix.column(3).xyz()))
+    };
+
+    matrix[(0, 3)] = translation.x;
+    matrix[(1, 3)] = translation.y;
+    matrix[(2, 3)] = translation.z;
+    TransformationMatrix::new(matrix)
+}
+
+/// Reads a component written into the instance data of an entity. Transformation matrices are
+/// interpolated and made relative to the render origin, damage layers are read from the damage masks and material instance data is read from the
+/// PbrMaterial of the entity; other components are read as they are. Used by the specify_type_ids macro
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to read the component of
+#[doc(hidden)]
+pub fn render_copy<'a, T: 'static + Copy + Serialize + Deserialize<'a>>(ecs: &ECS, entity_id: EntityId) -> T
+{
+    if TypeId::of::<T>() == TypeId::of::<TransformationMatrix>()
+    {
+        let transformation = render_space_transformation(ecs, entity_id);
+
+        // T is TransformationMatrix, checked above
+        return unsafe { std::mem::transmute_copy::<TransformationMatrix, T>(&transformation) };
+    }
+
+    // Damage is not a component; entities without damage use the first component of 0
+    if TypeId::of::<T>() == TypeId::of::<DamageLayer>()
+    {
+        let damage_layer = damage_layer(entity_id);
+
+        // T is DamageLayer, checked above
+        return unsafe { std::mem::transmute_copy::<DamageLayer, T>(&damage_layer) };
+    }
+
+    // Entities without a material use the default material
+    if TypeId::of::<T>() == TypeId::of::<PbrAlbedo>()
+    {
+        let albedo = pbr_albedo(ecs, entity_id);
+
+        // T is PbrAlbedo, checked above
+        return unsafe { std::mem::transmute_copy::<PbrAlbedo, T>(&albedo) };
+    }
+
+    if TypeId::of::<T>() == TypeId::of::<PbrSurface>()
+    {
+        let surface = pbr_surface(ecs, entity_id);
+
+        // T is PbrSurface, checked above
+        return unsafe { std::mem::transmute_copy::<PbrSurface, T>(&surface) };
+    }
+
+    ecs.get_copy::<T>(entity_id).unwrap()
+}
+
+/// Advances the time the logic has to catch up on by the time the frame took, returning the number of
+/// logic steps to execute this frame and the time each step advances by. The frame alpha used when
+/// drawing entities is updated for the time left over
+///
+/// `accumulator` - the time passed that the logic has not yet been executed for
+/// `delta_time` - the time the frame took in seconds
+pub(crate) fn advance_logic_clock(accumulator: &mut f32, delta_time: f32) -> (u32, f32)
+{
+    let timestep = match fixed_logic_timestep()
+    {
+        Some(i) => i,
+        None =>
+            {
+                *accumulator = 0.0;
+                *INTERPOLATION_ALPHA.lock() = 1.0;
+                return (1, delta_time);
+            }
+    };
+
+    *accumulator += delta_time;
+
+    let number_steps = ((*accumulator / timestep) as u32).min(MAX_LOGIC_STEPS_PER_FRAME);
+    *accumulator -= number_steps as f32 * timestep;
+
+    // Time that could not be caught up on is dropped
+    *accumulator = accumulator.min(timestep);
+
+    *INTERPOLATION_ALPHA.lock() = (*accumulator / timestep).min(1.0);
+
+    (number_steps, timestep)
+}
@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use tracing::warn;
 use crate::exports::light_components::FindLightType;
 use crate::exports::logic_components::CanCauseCollisions;
 use crate::exports::movement_components::*;
@@ -70,7 +71,7 @@ impl EntityTransformationBuilder
 
         if bounding_tree.add_entity(self.entity_id, &transformed_aabb, false, self.is_entity_static, self.light_type).is_err()
         {
-            eprintln!("Position {:?} is an invalid location", self.translation.unwrap().get_position());
+            warn!("Position {:?} is an invalid location", self.translation.unwrap().get_position());
         }
     }
 
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+// An environment map lights the scene from every direction, such as the sky and distant surroundings of an
+// outdoor scene. The map is an equirectangular HDR image that is converted into a cubemap and convolved on
+// the GPU once when it is loaded: into an irradiance map giving the diffuse light arriving at a surface,
+// and into a mip chain of prefiltered maps giving the reflections seen by increasingly rough surfaces. A
+// lookup table of the specular BRDF completes the split sum approximation. Render systems created with
+// environment lighting read these maps in their second pass for the ambient lighting of every fragment
+
+static ENVIRONMENT_LIGHTING_CHANGED: AtomicBool = AtomicBool::new(false);
+
+lazy_static!
+{
+    static ref ENVIRONMENT_LIGHTING: Mutex<Option<EnvironmentLighting>> = Mutex::new(None);
+}
+
+/// Controls the environment map lighting the scene and the resolution of the maps convolved from it
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentLighting
+{
+    /// Location of the equirectangular HDR image, such as a Radiance .hdr file
+    pub environment_map: PathBuf,
+    /// Scale applied to the diffuse light from the environment
+    pub diffuse_intensity: f32,
+    /// Scale applied to the reflections of the environment
+    pub specular_intensity: f32,
+    /// Width of each face of the cubemap the environment map is converted into
+    pub cube_resolution: u32,
+    /// Width of each face of the irradiance map. Irradiance changes slowly, so a low resolution suffices
+    pub irradiance_resolution: u32,
+    /// Width of each face of the most detailed prefiltered map, used by perfectly smooth surfaces
+    pub prefiltered_resolution: u32,
+}
+
+impl EnvironmentLighting
+{
+    /// Creates environment lighting from the given map with the default intensities and resolutions
+    ///
+    /// `environment_map` - location of the equirectangular HDR image
+    pub fn new<A: Into<PathBuf>>(environment_map: A) -> EnvironmentLighting
+    {
+        EnvironmentLighting
+        {
+            environment_map: environment_map.into(),
+            diffuse_intensity: 1.0,
+            specular_intensity: 1.0,
+            cube_resolution: 512,
+            irradiance_resolution: 32,
+            prefiltered_resolution: 128,
+        }
+    }
+}
+
+/// Sets the environment map lighting the scene. The maps are loaded and convolved at the start of the
+/// next frame, which blocks rendering for as long as it takes
+///
+/// `settings` - the environment lighting to use. If None, no environment lighting is applied
+pub fn set_environment_lighting(settings: Option<EnvironmentLighting>)
+{
+    *ENVIRONMENT_LIGHTING.lock() = settings.map(|x| EnvironmentLighting
+    {
+        diffuse_intensity: x.diffuse_intensity.max(0.0),
+        specular_intensity: x.specular_intensity.max(0.0),
+        cube_resolution: x.cube_resolution.clamp(16, 4096),
+        irradiance_resolution: x.irradiance_resolution.clamp(4, 256),
+        prefiltered_resolution: x.prefiltered_resolution.clamp(16, 2048),
+        ..x
+    });
+    ENVIRONMENT_LIGHTING_CHANGED.store(true, Ordering::Relaxed);
+}
+
+/// Get the environment lighting applied to the scene, if any
+pub fn environment_lighting() -> Option<EnvironmentLighting>
+{
+    ENVIRONMENT_LIGHTING.lock().clone()
+}
+
+/// Checks if the environment lighting changed since the last call, clearing the change
+pub(crate) fn take_environment_lighting_change() -> bool
+{
+    ENVIRONMENT_LIGHTING_CHANGED.swap(false, Ordering::Relaxed)
+}
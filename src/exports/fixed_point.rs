@@ -0,0 +1,400 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+use nalgebra_glm::{TVec3, vec3};
+use serde::{Serialize, Deserialize};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+/// Number of bits of a fixed point number used to represent the fraction
+const FRACTIONAL_BITS: u32 = 32;
+
+/// A number with a fixed number of fractional bits. Unlike floating point numbers, arithmetic on fixed
+/// point numbers gives identical results on every machine, which is required for lockstep networking
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct FixedPoint(i64);
+
+/// A vector of fixed point numbers
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FixedVec3
+{
+    pub x: FixedPoint,
+    pub y: FixedPoint,
+    pub z: FixedPoint,
+}
+
+/// A bounding volume with fixed point bounds, used to find collisions of entities moved in fixed point
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedAABB
+{
+    pub min: FixedVec3,
+    pub max: FixedVec3,
+}
+
+impl FixedPoint
+{
+    pub const ZERO: FixedPoint = FixedPoint(0);
+    pub const ONE: FixedPoint = FixedPoint(1 << FRACTIONAL_BITS);
+
+    /// Creates a fixed point number from the given integer
+    ///
+    /// `value` - the integer the fixed point number represents
+    pub fn from_int(value: i32) -> FixedPoint
+    {
+        FixedPoint((value as i64) << FRACTIONAL_BITS)
+    }
+
+    /// Creates the fixed point number closest to the fraction numerator / denominator, such as the length of
+    /// a fixed timestep
+    ///
+    /// `numerator` - the numerator of the fraction
+    /// `denominator` - the denominator of the fraction. Must not be zero
+    pub fn from_ratio(numerator: i32, denominator: i32) -> FixedPoint
+    {
+        FixedPoint::from_int(numerator) / FixedPoint::from_int(denominator)
+    }
+
+    /// Creates the fixed point number closest to the given floating point number
+    ///
+    /// `value` - the floating point number to convert
+    pub fn from_f32(value: f32) -> FixedPoint
+    {
+        debug_assert!(value.is_finite(), "Cannot convert a non-finite number to a fixed point number");
+
+        FixedPoint((value as f64 * (1u64 << FRACTIONAL_BITS) as f64).round() as i64)
+    }
+
+    /// Creates a fixed point number from its raw representation, such as one received over the network
+    ///
+    /// `raw` - the raw representation of the fixed point number
+    pub fn from_raw(raw: i64) -> FixedPoint
+    {
+        FixedPoint(raw)
+    }
+
+    /// Get the raw representation of the fixed point number
+    pub fn to_raw(&self) -> i64
+    {
+        self.0
+    }
+
+    /// Converts the fixed point number to the closest floating point number
+    pub fn to_f32(&self) -> f32
+    {
+        (self.0 as f64 / (1u64 << FRACTIONAL_BITS) as f64) as f32
+    }
+}
+
+impl FixedVec3
+{
+    /// Creates a fixed point vector from the given components
+    pub fn new(x: FixedPoint, y: FixedPoint, z: FixedPoint) -> FixedVec3
+    {
+        FixedVec3{ x, y, z }
+    }
+
+    /// Creates the fixed point vector closest to the given floating point vector
+    ///
+    /// `value` - the floating point vector to convert
+    pub fn from_vec3(value: TVec3<f32>) -> FixedVec3
+    {
+        FixedVec3::new(FixedPoint::from_f32(value.x), FixedPoint::from_f32(value.y), FixedPoint::from_f32(value.z))
+    }
+
+    /// Converts the fixed point vector to the closest floating point vector
+    pub fn to_vec3(&self) -> TVec3<f32>
+    {
+        vec3(self.x.to_f32(), self.y.to_f32(), self.z.to_f32())
+    }
+
+    /// Checks if all of the components of the vector are zero
+    pub fn is_zero(&self) -> bool
+    {
+        self.x == FixedPoint::ZERO && self.y == FixedPoint::ZERO && self.z == FixedPoint::ZERO
+    }
+}
+
+impl FixedAABB
+{
+    /// Creates the fixed point bounding volume closest to the given bounding volume
+    ///
+    /// `aabb` - the bounding volume to convert
+    pub fn from_aabb(aabb: &StaticAABB) -> FixedAABB
+    {
+        FixedAABB
+        {
+            min: FixedVec3::new(FixedPoint::from_f32(aabb.x_range.min), FixedPoint::from_f32(aabb.y_range.min), FixedPoint::from_f32(aabb.z_range.min)),
+            max: FixedVec3::new(FixedPoint::from_f32(aabb.x_range.max), FixedPoint::from_f32(aabb.y_range.max), FixedPoint::from_f32(aabb.z_range.max)),
+        }
+    }
+
+    /// Move the bounding volume in the given direction
+    ///
+    /// `move_vector` - vector specifying how much to move the volume in each dimension
+    pub fn translate(&mut self, move_vector: FixedVec3)
+    {
+        self.min += move_vector;
+        self.max += move_vector;
+    }
+
+    /// Same as StaticAABB::swept_intersect, but computed in fixed point so that every machine finds the
+    /// same time of impact. The time is the fraction of the displacement moved when the volumes first
+    /// touch, from 0 to 1. None is returned if the volumes never touch during the movement
+    ///
+    /// `other_aabb` - the volume to check for a collision with this one
+    /// `displacement` - vector specifying how much this volume moves in each dimension
+    pub fn swept_intersect(&self, other_aabb: &FixedAABB, displacement: FixedVec3) -> Option<FixedPoint>
+    {
+        let dimensions =
+            [
+                (self.min.x, self.max.x, other_aabb.min.x, other_aabb.max.x, displacement.x),
+                (self.min.y, self.max.y, other_aabb.min.y, other_aabb.max.y, displacement.y),
+                (self.min.z, self.max.z, other_aabb.min.z, other_aabb.max.z, displacement.z),
+            ];
+
+        let mut entry_time = FixedPoint::ZERO;
+        let mut exit_time = FixedPoint::ONE;
+
+        for (self_min, self_max, other_min, other_max, movement) in dimensions.iter().copied()
+        {
+            if movement == FixedPoint::ZERO
+            {
+                // Without movement in this dimension, the volumes must always overlap in it
+                if self_max < other_min || self_min > other_max
+                {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let (dimension_entry_time, dimension_exit_time) = if movement > FixedPoint::ZERO
+            {
+                ((other_min - self_max) / movement, (other_max - self_min) / movement)
+            }
+            else
+            {
+                ((other_max - self_min) / movement, (other_min - self_max) / movement)
+            };
+
+            entry_time = entry_time.max(dimension_entry_time);
+            exit_time = exit_time.min(dimension_exit_time);
+
+            if entry_time > exit_time
+            {
+                return None;
+            }
+        }
+
+        Some(entry_time)
+    }
+}
+
+impl Add for FixedPoint
+{
+    type Output = FixedPoint;
+
+    fn add(self, rhs: FixedPoint) -> FixedPoint
+    {
+        FixedPoint(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for FixedPoint
+{
+    type Output = FixedPoint;
+
+    fn sub(self, rhs: FixedPoint) -> FixedPoint
+    {
+        FixedPoint(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Mul for FixedPoint
+{
+    type Output = FixedPoint;
+
+    fn mul(self, rhs: FixedPoint) -> FixedPoint
+    {
+        // The product has twice the fractional bits, so it is computed at a larger width before being
+        // shifted back. The shift rounds towards negative infinity, which is the same on every machine
+        FixedPoint(((self.0 as i128 * rhs.0 as i128) >> FRACTIONAL_BITS) as i64)
+    }
+}
+
+impl Div for FixedPoint
+{
+    type Output = FixedPoint;
+
+    fn div(self, rhs: FixedPoint) -> FixedPoint
+    {
+        // The dividend is widened and shifted first so that the quotient keeps its fractional bits. The
+        // division rounds towards zero, which is the same on every machine
+        FixedPoint((((self.0 as i128) << FRACTIONAL_BITS) / rhs.0 as i128) as i64)
+    }
+}
+
+impl Neg for FixedPoint
+{
+    type Output = FixedPoint;
+
+    fn neg(self) -> FixedPoint
+    {
+        FixedPoint(self.0.wrapping_neg())
+    }
+}
+
+impl AddAssign for FixedPoint
+{
+    fn add_assign(&mut self, rhs: FixedPoint)
+    {
+        *self = *self + rhs;
+    }
+}
+
+impl Add for FixedVec3
+{
+    type Output = FixedVec3;
+
+    fn add(self, rhs: FixedVec3) -> FixedVec3
+    {
+        FixedVec3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for FixedVec3
+{
+    type Output = FixedVec3;
+
+    fn sub(self, rhs: FixedVec3) -> FixedVec3
+    {
+        FixedVec3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Neg for FixedVec3
+{
+    type Output = FixedVec3;
+
+    fn neg(self) -> FixedVec3
+    {
+        FixedVec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<FixedPoint> for FixedVec3
+{
+    type Output = FixedVec3;
+
+    fn mul(self, rhs: FixedPoint) -> FixedVec3
+    {
+        FixedVec3::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl AddAssign for FixedVec3
+{
+    fn add_assign(&mut self, rhs: FixedVec3)
+    {
+        *self = *self + rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use nalgebra_glm::vec3;
+    use crate::exports::fixed_point::{FixedAABB, FixedPoint, FixedVec3};
+    use crate::world::bounding_volumes::aabb::StaticAABB;
+    use crate::world::dimension::range::{XRange, YRange, ZRange};
+
+    fn unit_aabb_at(x: i32, y: i32, z: i32) -> FixedAABB
+    {
+        FixedAABB
+        {
+            min: FixedVec3::new(FixedPoint::from_int(x), FixedPoint::from_int(y), FixedPoint::from_int(z)),
+            max: FixedVec3::new(FixedPoint::from_int(x + 1), FixedPoint::from_int(y + 1), FixedPoint::from_int(z + 1)),
+        }
+    }
+
+    #[test]
+    fn arithmetic()
+    {
+        let two = FixedPoint::from_int(2);
+        let half = FixedPoint::from_f32(0.5);
+
+        assert_eq!(FixedPoint::from_f32(2.5), two + half);
+        assert_eq!(FixedPoint::from_f32(1.5), two - half);
+        assert_eq!(FixedPoint::from_f32(-1.5), half - two);
+        assert_eq!(FixedPoint::ONE, two * half);
+        assert_eq!(FixedPoint::from_int(4), two / half);
+        assert_eq!(FixedPoint::from_f32(-0.5), -half);
+        assert_eq!(FixedPoint::from_int(-3), FixedPoint::from_int(-6) * half);
+        assert_eq!(FixedPoint::from_int(-12), FixedPoint::from_int(-6) / half);
+
+        let mut sum = FixedPoint::ZERO;
+        sum += two;
+        sum += half;
+        assert_eq!(2.5, sum.to_f32());
+
+        let vector = FixedVec3::from_vec3(vec3(1.0, -2.0, 0.25));
+        assert_eq!(vec3(2.0, -4.0, 0.5), (vector * two).to_vec3());
+        assert_eq!(vec3(0.0, 0.0, 0.0), (vector - vector).to_vec3());
+        assert_eq!(vec3(-1.0, 2.0, -0.25), (-vector).to_vec3());
+        assert!((vector + -vector).is_zero());
+    }
+
+    #[test]
+    fn overflow_wraps()
+    {
+        let max = FixedPoint::from_raw(i64::MAX);
+        let min = FixedPoint::from_raw(i64::MIN);
+        let smallest = FixedPoint::from_raw(1);
+
+        // Wrapping is the same on every machine, unlike the panic of an overflow in debug builds
+        assert_eq!(min, max + smallest);
+        assert_eq!(max, min - smallest);
+        assert_eq!(min, -min);
+
+        // Products are computed at a larger width, so large intermediate values do not overflow
+        let large = FixedPoint::from_int(1 << 10);
+        assert_eq!(FixedPoint::from_int(1 << 20), large * large);
+        assert_eq!(large, large * large / large);
+    }
+
+    #[test]
+    fn rounding()
+    {
+        // Conversions round to the closest representable number
+        assert_eq!(1, FixedPoint::from_f32(0.3 / 4_294_967_296.0 * 2.0).to_raw());
+        assert_eq!(0, FixedPoint::from_f32(0.4 / 4_294_967_296.0).to_raw());
+        assert_eq!(-1, FixedPoint::from_f32(-0.6 / 4_294_967_296.0).to_raw());
+
+        // Products round towards negative infinity
+        let smallest = FixedPoint::from_raw(1);
+        let half = FixedPoint::from_f32(0.5);
+        assert_eq!(0, (smallest * half).to_raw());
+        assert_eq!(-1, (-smallest * half).to_raw());
+
+        // Quotients round towards zero
+        let three = FixedPoint::from_int(3);
+        assert_eq!(1431655765, (FixedPoint::ONE / three).to_raw());
+        assert_eq!(-1431655765, (-FixedPoint::ONE / three).to_raw());
+        assert_eq!(FixedPoint::ONE / three, FixedPoint::from_ratio(1, 3));
+    }
+
+    #[test]
+    fn fixed_swept_intersect()
+    {
+        let moving_aabb = unit_aabb_at(0, 0, 0);
+        let displacement = FixedVec3::new(FixedPoint::from_int(10), FixedPoint::ZERO, FixedPoint::ZERO);
+
+        assert_eq!(Some(FixedPoint::from_ratio(2, 5)), moving_aabb.swept_intersect(&unit_aabb_at(5, 0, 0), displacement));
+        assert_eq!(None, moving_aabb.swept_intersect(&unit_aabb_at(5, 3, 0), displacement));
+        assert_eq!(None, moving_aabb.swept_intersect(&unit_aabb_at(5, 0, 0), -displacement));
+        assert_eq!(Some(FixedPoint::ZERO), moving_aabb.swept_intersect(&unit_aabb_at(1, 0, 0), FixedVec3::default()));
+
+        let mut translated_aabb = FixedAABB::from_aabb(&StaticAABB::new(XRange::new(0.0, 1.0), YRange::new(0.0, 1.0), ZRange::new(0.0, 1.0)));
+        assert_eq!(moving_aabb, translated_aabb);
+
+        translated_aabb.translate(displacement);
+        assert_eq!(unit_aabb_at(10, 0, 0), translated_aabb);
+    }
+}
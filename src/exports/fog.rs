@@ -0,0 +1,101 @@
+use lazy_static::lazy_static;
+use nalgebra_glm::{TVec3, vec3};
+use parking_lot::Mutex;
+
+// Fog is drawn over the frame after the render systems drawing to the window have finished. The distance
+// from the camera to every pixel is ray marched at half the resolution of the window, accumulating fog that
+// is densest at the base height and thins out above it. Samples along the ray that the shadow maps show are
+// lit give light shafts, such as sunlight streaming between buildings. The world positions of pixels are
+// read from the first G-Buffer layout of the first enabled render system drawing to the window from the main
+// camera, which is expected to hold world positions as it does in the default render system
+
+lazy_static!
+{
+    static ref FOG_SETTINGS: Mutex<Option<FogSettings>> = Mutex::new(None);
+}
+
+/// Controls the volumetric fog and light shafts drawn over the frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FogSettings
+{
+    /// Colour of the fog where it is not lit by a light with a shadow map
+    pub colour: TVec3<f32>,
+    /// How much fog there is per unit of distance at the base height
+    pub density: f32,
+    /// Height at which the fog is at its given density
+    pub base_height: f32,
+    /// How quickly the fog thins out above the base height. A falloff of 0 gives uniform fog
+    pub height_falloff: f32,
+    /// Colour of the light scattered towards the camera by fog lit by a light with a shadow map
+    pub light_shaft_colour: TVec3<f32>,
+    /// How much light fog that is lit scatters towards the camera. A scattering of 0 disables light shafts
+    pub light_shaft_scattering: f32,
+    /// Farthest distance from the camera that fog is accumulated over
+    pub max_distance: f32,
+    /// Number of samples taken along each ray. More samples give smoother light shafts at a higher cost
+    pub steps: u32,
+}
+
+impl Default for FogSettings
+{
+    fn default() -> FogSettings
+    {
+        FogSettings
+        {
+            colour: vec3(0.5, 0.55, 0.6),
+            density: 0.02,
+            base_height: 0.0,
+            height_falloff: 0.05,
+            light_shaft_colour: vec3(1.0, 0.95, 0.85),
+            light_shaft_scattering: 0.5,
+            max_distance: 500.0,
+            steps: 32,
+        }
+    }
+}
+
+impl FogSettings
+{
+    /// Linearly interpolates between this and the other fog settings
+    ///
+    /// `other` - the settings to blend towards
+    /// `factor` - how much of the other settings to use, from 0 to 1
+    pub(crate) fn blend(&self, other: &FogSettings, factor: f32) -> FogSettings
+    {
+        let lerp = |from: f32, to: f32| from + (to - from) * factor;
+
+        FogSettings
+        {
+            colour: nalgebra_glm::lerp(&self.colour, &other.colour, factor),
+            density: lerp(self.density, other.density),
+            base_height: lerp(self.base_height, other.base_height),
+            height_falloff: lerp(self.height_falloff, other.height_falloff),
+            light_shaft_colour: nalgebra_glm::lerp(&self.light_shaft_colour, &other.light_shaft_colour, factor),
+            light_shaft_scattering: lerp(self.light_shaft_scattering, other.light_shaft_scattering),
+            max_distance: lerp(self.max_distance, other.max_distance),
+            steps: lerp(self.steps as f32, other.steps as f32).round() as u32,
+        }
+    }
+}
+
+/// Enables or disables the volumetric fog drawn over the frame
+///
+/// `settings` - how the fog looks. If None, no fog is drawn
+pub fn set_fog_settings(settings: Option<FogSettings>)
+{
+    *FOG_SETTINGS.lock() = settings.map(|x| FogSettings
+    {
+        density: x.density.max(0.0),
+        height_falloff: x.height_falloff.max(0.0),
+        light_shaft_scattering: x.light_shaft_scattering.max(0.0),
+        max_distance: x.max_distance.max(0.0),
+        steps: x.steps.clamp(1, 256),
+        ..x
+    });
+}
+
+/// Get how the volumetric fog looks, if it is enabled
+pub fn fog_settings() -> Option<FogSettings>
+{
+    *FOG_SETTINGS.lock()
+}
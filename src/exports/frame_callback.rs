@@ -0,0 +1,147 @@
+use tracing::warn;
+use crate::exports::accessibility::ui_scale;
+use crate::exports::camera_object::Camera;
+use crate::exports::ui_layout::{UiElement, UiRect};
+use crate::flows::shared_constants::REQUESTED_FRAME_CALLBACK_CHANGES;
+use crate::render_components::gl_state_guard::GLStateGuard;
+
+/// Function called on the render thread once per frame, after the engine has rendered the frame and
+/// before the window buffers are swapped. The OpenGL context is current when the function is called
+pub type FrameCallback = Box<dyn FnMut(&FrameCallbackContext) + Send>;
+
+// Frame callbacks allow OpenGL work that the render system builder cannot express yet. The state the engine
+// relies on, such as the bound program, frame buffer and viewport, is saved before each callback and restored
+// after it, so callbacks can change the state freely. Resources created by a callback are owned by the callback
+
+/// Information about the frame being rendered that is given to frame callbacks
+pub struct FrameCallbackContext<'a>
+{
+    camera: &'a Camera,
+    window_dimensions: (i32, i32),
+    delta_time: f32,
+}
+
+/// Changes to frame callbacks requested by the user, to be applied by the render thread
+pub(crate) enum FrameCallbackRequest
+{
+    Add(String, FrameCallback),
+    Remove(String),
+}
+
+/// The frame callbacks registered by the user, in the order they were registered
+pub(crate) struct FrameCallbacks
+{
+    callbacks: Vec<(String, FrameCallback)>,
+}
+
+impl<'a> FrameCallbackContext<'a>
+{
+    /// Get the camera the frame was rendered from
+    pub fn get_camera(&self) -> &Camera
+    {
+        self.camera
+    }
+
+    /// Get the dimensions of the window being rendered to
+    pub fn get_window_dimensions(&self) -> (i32, i32)
+    {
+        self.window_dimensions
+    }
+
+    /// Get the amount of time that has passed since the last frame in seconds
+    pub fn get_delta_time(&self) -> f32
+    {
+        self.delta_time
+    }
+
+    /// Get the multiplier that the size of anything drawn over the scene should be scaled by
+    pub fn get_ui_scale(&self) -> f32
+    {
+        ui_scale()
+    }
+
+    /// Finds the rectangle of the window an element covers, in pixels from the bottom left of the window
+    ///
+    /// `element` - the element to lay out
+    pub fn layout(&self, element: &UiElement) -> UiRect
+    {
+        element.layout(self.window_dimensions)
+    }
+}
+
+impl FrameCallbacks
+{
+    /// Creates a list of frame callbacks without any callbacks
+    pub fn new() -> FrameCallbacks
+    {
+        FrameCallbacks{ callbacks: Vec::new() }
+    }
+
+    /// Applies any changes to the frame callbacks that were requested since the last frame
+    pub fn apply_requests(&mut self)
+    {
+        for request in REQUESTED_FRAME_CALLBACK_CHANGES.lock().drain(..)
+        {
+            match request
+            {
+                FrameCallbackRequest::Add(name, callback) =>
+                    {
+                        match self.callbacks.iter_mut().find(|x| x.0 == name)
+                        {
+                            Some(i) => i.1 = callback,
+                            None => self.callbacks.push((name, callback))
+                        }
+                    },
+                FrameCallbackRequest::Remove(name) =>
+                    {
+                        let number_callbacks = self.callbacks.len();
+                        self.callbacks.retain(|x| x.0 != name);
+
+                        if number_callbacks == self.callbacks.len()
+                        {
+                            warn!("Unable to remove non-existent frame callback: {}", name);
+                        }
+                    }
+            }
+        }
+    }
+
+    /// Calls every frame callback, restoring the OpenGL state after each callback
+    ///
+    /// `camera` - the camera the frame was rendered from
+    /// `window_dimensions` - the dimensions of the window being rendered to
+    /// `delta_time` - the amount of time that has passed since the last frame in seconds
+    pub fn execute(&mut self, camera: &Camera, window_dimensions: (i32, i32), delta_time: f32)
+    {
+        if self.callbacks.is_empty()
+        {
+            return;
+        }
+
+        let context = FrameCallbackContext{ camera, window_dimensions, delta_time };
+
+        for (_, callback) in self.callbacks.iter_mut()
+        {
+            let _state_guard = GLStateGuard::new();
+            callback(&context);
+        }
+    }
+}
+
+/// Registers a function to be called on the render thread every frame, starting in the next frame. A callback
+/// with the same name as an existing callback replaces it
+///
+/// `name` - the name of the callback, used to remove it
+/// `callback` - the function to call every frame
+pub fn register_frame_callback<A: Into<String>>(name: A, callback: FrameCallback)
+{
+    REQUESTED_FRAME_CALLBACK_CHANGES.lock().push(FrameCallbackRequest::Add(name.into(), callback));
+}
+
+/// Removes the frame callback with the given name
+///
+/// `name` - the name of the callback to remove
+pub fn remove_frame_callback<A: AsRef<str>>(name: A)
+{
+    REQUESTED_FRAME_CALLBACK_CHANGES.lock().push(FrameCallbackRequest::Remove(name.as_ref().to_string()));
+}
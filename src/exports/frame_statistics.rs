@@ -0,0 +1,472 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use crate::exports::logic_components::RenderSystemIndex;
+
+/// The number of previous frames whose statistics are kept
+pub const FRAME_STATISTICS_HISTORY_LENGTH: usize = 120;
+
+// Draw calls are counted from within the draw functions, so the counters of the frame being rendered are
+// atomics rather than being stored behind the lock of the finished frames
+
+static FRAME_DRAW_CALLS: AtomicU32 = AtomicU32::new(0);
+static FRAME_INSTANCES_RENDERED: AtomicU64 = AtomicU64::new(0);
+static FRAME_BUFFER_BYTES_UPLOADED: AtomicUsize = AtomicUsize::new(0);
+static FRAME_VISIBLE_SECTIONS: AtomicUsize = AtomicUsize::new(0);
+static OVERLAY_VISIBLE: AtomicBool = AtomicBool::new(false);
+static CULLING_STATISTICS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static!
+{
+    static ref FINISHED_FRAMES: Mutex<VecDeque<FrameStatistics>> = Mutex::new(VecDeque::with_capacity(FRAME_STATISTICS_HISTORY_LENGTH));
+    static ref FRAME_FLOW_TIMINGS: Mutex<FlowTimings> = Mutex::new(FlowTimings::default());
+    static ref FRAME_GPU_TIMINGS: Mutex<(GpuTimings, Vec<(RenderSystemIndex, RenderSystemGpuTimings)>)> = Mutex::new((GpuTimings::default(), Vec::new()));
+    static ref FINISHED_FRAME_RENDER_SYSTEM_GPU_TIMINGS: Mutex<Vec<(RenderSystemIndex, RenderSystemGpuTimings)>> = Mutex::new(Vec::new());
+    static ref FRAME_CULLING: Mutex<Vec<(CullingPass, CullingStatistics)>> = Mutex::new(Vec::new());
+    static ref FINISHED_FRAME_CULLING: Mutex<Vec<(CullingPass, CullingStatistics)>> = Mutex::new(Vec::new());
+}
+
+/// Measurements of a rendered frame. Times are in milliseconds. The statistics of the previous frame can be
+/// read while drawing with DrawParam::frame_statistics, and from entity logic with latest_frame_statistics,
+/// which can be called from any thread
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameStatistics
+{
+    /// Time between the start of the frame and the start of the previous frame
+    pub frame_time: f32,
+    /// Time spent executing logic and submitting rendering commands
+    pub cpu_time: f32,
+    /// Time spent waiting for the GPU to present the frame, which includes waiting for vsync
+    pub gpu_wait_time: f32,
+    /// Number of world sections that were visible to the camera
+    pub visible_sections: usize,
+    /// Number of draw calls issued for the frame, including those for shadow maps and minimaps
+    pub draw_calls: u32,
+    /// Number of instances drawn by the render systems, including those drawn into shadow maps and minimaps.
+    /// Instances whose level of view is chosen on the GPU are not counted, as only the GPU knows how many are drawn
+    pub instances_rendered: u64,
+    /// Number of bytes written into mapped buffers for the GPU, such as instance data and uniforms
+    pub buffer_bytes_uploaded: usize,
+    /// Time spent in each part of the pipeline
+    pub flow_timings: FlowTimings,
+    /// Time the GPU spent rendering each part of a frame
+    pub gpu_timings: GpuTimings,
+}
+
+/// Time the GPU spent rendering each part of a frame, in milliseconds. The GPU is measured with timer queries
+/// that are read a few frames after they were issued so that reading them does not stall the CPU, so these
+/// times are those of a frame rendered a few frames before the frame the statistics are of. If the GPU times
+/// add up to more than the CPU time of the frame, the game is limited by the GPU rather than the CPU
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GpuTimings
+{
+    /// Time spent rendering shadow maps
+    pub shadow_maps: f32,
+    /// Time spent in the first pass of every render system, including those drawn into minimaps
+    pub first_passes: f32,
+    /// Time spent in the second pass of every render system, including those drawn into minimaps
+    pub second_passes: f32,
+    /// Time spent applying the post-processing stack
+    pub post_processing: f32,
+}
+
+/// Time the GPU spent rendering the passes of a render system, in milliseconds
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RenderSystemGpuTimings
+{
+    /// Time spent drawing the geometry of the render system, which includes choosing level of views on the GPU
+    pub first_pass: f32,
+    /// Time spent lighting the frame of the render system, which is zero for render systems without a second pass
+    pub second_pass: f32,
+}
+
+impl GpuTimings
+{
+    /// Get the time spent on every measured part of the frame
+    pub fn total(&self) -> f32
+    {
+        self.shadow_maps + self.first_passes + self.second_passes + self.post_processing
+    }
+}
+
+/// The parts of the pipeline that are timed for the frame statistics
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameFlow
+{
+    /// Finding the world sections visible to the camera and kept active for logic
+    Culling,
+    /// Executing the logic of the user entity with the input of the user
+    UserInput,
+    /// Executing every logic step of the frame, which includes the kinematics, collisions and entity logic
+    Logic,
+    /// Moving entities by their velocities, as part of logic
+    Kinematics,
+    /// Finding and resolving collisions, as part of logic
+    Collisions,
+    /// Executing the logic functions of entities, as part of logic
+    EntityLogic,
+    /// Sorting instances and submitting rendering commands
+    Render,
+}
+
+/// Time spent in each part of the pipeline during a frame, in milliseconds. With a fixed logic timestep, the
+/// logic times are the sum of every logic step of the frame
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FlowTimings
+{
+    pub culling: f32,
+    pub user_input: f32,
+    pub logic: f32,
+    pub kinematics: f32,
+    pub collisions: f32,
+    pub entity_logic: f32,
+    pub render: f32,
+}
+
+impl FlowTimings
+{
+    /// Get the time spent in a part of the pipeline, in milliseconds
+    ///
+    /// `flow` - the part of the pipeline to get the time of
+    pub fn time(&self, flow: FrameFlow) -> f32
+    {
+        match flow
+        {
+            FrameFlow::Culling => self.culling,
+            FrameFlow::UserInput => self.user_input,
+            FrameFlow::Logic => self.logic,
+            FrameFlow::Kinematics => self.kinematics,
+            FrameFlow::Collisions => self.collisions,
+            FrameFlow::EntityLogic => self.entity_logic,
+            FrameFlow::Render => self.render,
+        }
+    }
+
+    /// Get a mutable reference to the time spent in a part of the pipeline
+    ///
+    /// `flow` - the part of the pipeline to get the time of
+    fn time_mut(&mut self, flow: FrameFlow) -> &mut f32
+    {
+        match flow
+        {
+            FrameFlow::Culling => &mut self.culling,
+            FrameFlow::UserInput => &mut self.user_input,
+            FrameFlow::Logic => &mut self.logic,
+            FrameFlow::Kinematics => &mut self.kinematics,
+            FrameFlow::Collisions => &mut self.collisions,
+            FrameFlow::EntityLogic => &mut self.entity_logic,
+            FrameFlow::Render => &mut self.render,
+        }
+    }
+}
+
+/// The camera that world sections and entities were culled for
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CullingPass
+{
+    MainCamera,
+    /// All shadow maps rendered during the frame
+    ShadowMaps,
+    Minimap(String),
+    CameraView(String),
+}
+
+/// How many world sections and entities were tested for visibility from a camera, and what rejected those
+/// that are not drawn. Sections are tested against the view frustum and then against the draw distance;
+/// the entities of sections that pass both tests are drawn, except for those of large shared sections which
+/// are tested against the view frustum individually. The entities of sections rejected by the frustum are
+/// not counted, as they are culled without being looked at. Nothing is rejected by occlusion, as the world
+/// is not occlusion culled
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CullingStatistics
+{
+    /// Number of existing world sections within the draw distance of the camera along each axis
+    pub sections_tested: usize,
+    /// Number of world sections that passed every test
+    pub sections_accepted: usize,
+    pub sections_rejected_frustum: usize,
+    /// Number of world sections in the view frustum whose entities are all beyond the draw distance
+    pub sections_rejected_distance: usize,
+    /// Number of entities in the world sections that were in the view frustum
+    pub entities_tested: usize,
+    /// Number of entities that are drawn
+    pub entities_accepted: usize,
+    pub entities_rejected_frustum: usize,
+    pub entities_rejected_distance: usize,
+}
+
+impl CullingStatistics
+{
+    /// Adds the counts of the other statistics to these statistics
+    ///
+    /// `other` - the statistics to add
+    pub fn combine(&mut self, other: &CullingStatistics)
+    {
+        self.sections_tested += other.sections_tested;
+        self.sections_accepted += other.sections_accepted;
+        self.sections_rejected_frustum += other.sections_rejected_frustum;
+        self.sections_rejected_distance += other.sections_rejected_distance;
+        self.entities_tested += other.entities_tested;
+        self.entities_accepted += other.entities_accepted;
+        self.entities_rejected_frustum += other.entities_rejected_frustum;
+        self.entities_rejected_distance += other.entities_rejected_distance;
+    }
+}
+
+impl FrameStatistics
+{
+    /// Get the number of frames per second the frame would be rendered at
+    pub fn fps(&self) -> f32
+    {
+        if self.frame_time <= 0.0
+        {
+            return 0.0;
+        }
+
+        1000.0 / self.frame_time
+    }
+}
+
+/// Get the statistics of the most recently rendered frame. If no frame has been rendered, all values are zero
+pub fn latest_frame_statistics() -> FrameStatistics
+{
+    FINISHED_FRAMES.lock().back().copied().unwrap_or_default()
+}
+
+/// Get the statistics of the recently rendered frames, ordered from the oldest to the newest frame. At most
+/// FRAME_STATISTICS_HISTORY_LENGTH frames are returned
+pub fn frame_statistics_history() -> Vec<FrameStatistics>
+{
+    FINISHED_FRAMES.lock().iter().copied().collect()
+}
+
+/// Get the time the GPU spent rendering each render system, for the most recent frame whose timer queries
+/// have been read. Shadow maps are not included, as they are measured as a whole
+pub fn latest_render_system_gpu_timings() -> Vec<(RenderSystemIndex, RenderSystemGpuTimings)>
+{
+    FINISHED_FRAME_RENDER_SYSTEM_GPU_TIMINGS.lock().clone()
+}
+
+/// Shows or hides the statistics overlay drawn over the rendered scene. The overlay can also be toggled
+/// by pressing F3 while the game is running
+///
+/// `visible` - true if the overlay should be drawn
+pub fn set_statistics_overlay_visible(visible: bool)
+{
+    OVERLAY_VISIBLE.store(visible, Ordering::Relaxed);
+}
+
+/// Checks if the statistics overlay is drawn over the rendered scene
+pub fn is_statistics_overlay_visible() -> bool
+{
+    OVERLAY_VISIBLE.load(Ordering::Relaxed)
+}
+
+/// Enables or disables gathering culling statistics. Gathering them tests the bounding volumes of entities
+/// a second time, so it is disabled by default
+///
+/// `enabled` - true if culling statistics should be gathered
+pub fn set_culling_statistics_enabled(enabled: bool)
+{
+    CULLING_STATISTICS_ENABLED.store(enabled, Ordering::Relaxed);
+
+    if !enabled
+    {
+        FRAME_CULLING.lock().clear();
+        FINISHED_FRAME_CULLING.lock().clear();
+    }
+}
+
+/// Checks if culling statistics are gathered
+pub fn is_culling_statistics_enabled() -> bool
+{
+    CULLING_STATISTICS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Get the culling statistics of every camera rendered during the most recent frame, in the order they
+/// were rendered. Empty if culling statistics are not enabled
+pub fn latest_culling_statistics() -> Vec<(CullingPass, CullingStatistics)>
+{
+    FINISHED_FRAME_CULLING.lock().clone()
+}
+
+/// Adds to the culling statistics of the camera during the current frame
+///
+/// `pass` - the camera the world was culled for
+/// `statistics` - the result of culling the world
+pub(crate) fn record_culling(pass: CullingPass, statistics: CullingStatistics)
+{
+    let mut frame_culling = FRAME_CULLING.lock();
+
+    match frame_culling.iter_mut().find(|(x, _)| *x == pass)
+    {
+        Some((_, existing)) => existing.combine(&statistics),
+        None => frame_culling.push((pass, statistics))
+    }
+}
+
+/// Adds to the number of draw calls issued during the current frame
+///
+/// `count` - the number of draw calls that were issued
+pub(crate) fn record_draw_calls(count: u32)
+{
+    FRAME_DRAW_CALLS.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Adds to the number of instances drawn during the current frame
+///
+/// `count` - the number of instances that were drawn
+pub(crate) fn record_instances_rendered(count: u64)
+{
+    FRAME_INSTANCES_RENDERED.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Adds to the number of bytes written into mapped buffers during the current frame
+///
+/// `bytes` - the number of bytes that were written
+pub(crate) fn record_buffer_bytes_uploaded(bytes: usize)
+{
+    FRAME_BUFFER_BYTES_UPLOADED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Adds to the time spent in a part of the pipeline during the current frame
+///
+/// `flow` - the part of the pipeline that was executed
+/// `time` - the time spent executing it
+pub(crate) fn record_flow_time(flow: FrameFlow, time: Duration)
+{
+    *FRAME_FLOW_TIMINGS.lock().time_mut(flow) += time.as_secs_f32() * 1000.0;
+}
+
+/// Stores the GPU times of the passes of the frame, as read from their timer queries
+///
+/// `timings` - the time spent in each part of the frame
+/// `render_systems` - the time spent in the passes of each render system
+pub(crate) fn record_gpu_timings(timings: GpuTimings, render_systems: Vec<(RenderSystemIndex, RenderSystemGpuTimings)>)
+{
+    *FRAME_GPU_TIMINGS.lock() = (timings, render_systems);
+}
+
+/// Stores the number of world sections visible to the camera during the current frame
+///
+/// `count` - the number of visible world sections
+pub(crate) fn record_visible_sections(count: usize)
+{
+    FRAME_VISIBLE_SECTIONS.store(count, Ordering::Relaxed);
+}
+
+/// Stores the statistics of the current frame, and resets the counters for the next frame
+///
+/// `frame_time` - the time between the start of this frame and the previous frame, in seconds
+/// `cpu_time` - the time spent executing logic and submitting rendering commands, in seconds
+/// `gpu_wait_time` - the time spent waiting for the frame to be presented, in seconds
+pub(crate) fn end_frame(frame_time: f32, cpu_time: f32, gpu_wait_time: f32)
+{
+    let (gpu_timings, render_system_gpu_timings) = std::mem::take(&mut *FRAME_GPU_TIMINGS.lock());
+
+    let statistics = FrameStatistics
+    {
+        frame_time: frame_time * 1000.0,
+        cpu_time: cpu_time * 1000.0,
+        gpu_wait_time: gpu_wait_time * 1000.0,
+        visible_sections: FRAME_VISIBLE_SECTIONS.swap(0, Ordering::Relaxed),
+        draw_calls: FRAME_DRAW_CALLS.swap(0, Ordering::Relaxed),
+        instances_rendered: FRAME_INSTANCES_RENDERED.swap(0, Ordering::Relaxed),
+        buffer_bytes_uploaded: FRAME_BUFFER_BYTES_UPLOADED.swap(0, Ordering::Relaxed),
+        flow_timings: std::mem::take(&mut *FRAME_FLOW_TIMINGS.lock()),
+        gpu_timings,
+    };
+
+    *FINISHED_FRAME_RENDER_SYSTEM_GPU_TIMINGS.lock() = render_system_gpu_timings;
+
+    *FINISHED_FRAME_CULLING.lock() = std::mem::take(&mut *FRAME_CULLING.lock());
+
+    let mut finished_frames = FINISHED_FRAMES.lock();
+
+    if finished_frames.len() == FRAME_STATISTICS_HISTORY_LENGTH
+    {
+        finished_frames.pop_front();
+    }
+
+    finished_frames.push_back(statistics);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::exports::frame_statistics::{CullingStatistics, end_frame, FlowTimings, frame_statistics_history, FRAME_STATISTICS_HISTORY_LENGTH, FrameFlow, FrameStatistics, GpuTimings, latest_frame_statistics, record_draw_calls, record_flow_time};
+    use std::time::Duration;
+
+    #[test]
+    fn frames_per_second()
+    {
+        assert_eq!(0.0, FrameStatistics::default().fps());
+        assert_eq!(50.0, FrameStatistics{ frame_time: 20.0, ..FrameStatistics::default() }.fps());
+    }
+
+    #[test]
+    fn total_gpu_time()
+    {
+        let timings = GpuTimings{ shadow_maps: 1.0, first_passes: 2.0, second_passes: 3.0, post_processing: 4.0 };
+        assert_eq!(10.0, timings.total());
+    }
+
+    #[test]
+    fn flow_times()
+    {
+        let mut timings = FlowTimings::default();
+        *timings.time_mut(FrameFlow::Collisions) += 2.0;
+        *timings.time_mut(FrameFlow::Collisions) += 1.5;
+
+        assert_eq!(3.5, timings.time(FrameFlow::Collisions));
+        assert_eq!(3.5, timings.collisions);
+        assert_eq!(0.0, timings.time(FrameFlow::Kinematics));
+    }
+
+    #[test]
+    fn combine_culling_statistics()
+    {
+        let mut statistics = CullingStatistics{ sections_tested: 4, sections_accepted: 1, sections_rejected_frustum: 2, sections_rejected_distance: 1, ..CullingStatistics::default() };
+        statistics.combine(&CullingStatistics{ sections_tested: 1, sections_accepted: 1, entities_tested: 5, entities_accepted: 3, entities_rejected_frustum: 2, ..CullingStatistics::default() });
+
+        let expected = CullingStatistics
+        {
+            sections_tested: 5,
+            sections_accepted: 2,
+            sections_rejected_frustum: 2,
+            sections_rejected_distance: 1,
+            entities_tested: 5,
+            entities_accepted: 3,
+            entities_rejected_frustum: 2,
+            entities_rejected_distance: 0,
+        };
+
+        assert_eq!(expected, statistics);
+    }
+
+    #[test]
+    fn keep_recent_frames()
+    {
+        record_draw_calls(3);
+        record_draw_calls(2);
+        record_flow_time(FrameFlow::Render, Duration::from_millis(4));
+        end_frame(0.02, 0.015, 0.005);
+
+        let latest_frame = latest_frame_statistics();
+        assert_eq!(5, latest_frame.draw_calls);
+        assert_eq!(20.0, latest_frame.frame_time);
+        assert_eq!(4.0, latest_frame.flow_timings.render);
+
+        // Counters are reset for the next frame, and only the most recent frames are kept
+        for _ in 0..FRAME_STATISTICS_HISTORY_LENGTH
+        {
+            end_frame(0.01, 0.01, 0.0);
+        }
+
+        let history = frame_statistics_history();
+        assert_eq!(FRAME_STATISTICS_HISTORY_LENGTH, history.len());
+        assert!(history.iter().all(|x| x.draw_calls == 0 && x.frame_time == 10.0));
+    }
+}
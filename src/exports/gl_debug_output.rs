@@ -0,0 +1,375 @@
+use std::cell::RefCell;
+use std::ffi::{c_void, CStr, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+use tracing::{debug, error, info, warn};
+
+// OpenGL debug output reports errors, undefined behaviour and performance warnings from the driver as they
+// happen, rather than leaving them to be found with glGetError. Messages at or above the chosen severity are
+// given to the logger set by the user, or logged through tracing if there is none. Every severity is
+// reported by default, though notifications are logged at the debug level.
+//
+// Operations of the engine that commonly fail in ways OpenGL does not return an error for, such as generating
+// the shaders of render systems and mapping buffers, are wrapped in debug groups. Messages raised within them
+// say which operation raised them, and errors left in the error flag by those operations are reported even
+// when the driver does not give debug output, such as when the context is not a debug context
+
+lazy_static!
+{
+    static ref MINIMUM_SEVERITY: Mutex<Option<GlDebugSeverity>> = Mutex::new(Some(GlDebugSeverity::Notification));
+    static ref LOGGER: RwLock<Option<GlDebugLogger>> = RwLock::new(None);
+}
+
+static SETTINGS_CHANGED: AtomicBool = AtomicBool::new(true);
+
+thread_local!
+{
+    // The debug groups entered on this thread, and whether an error was reported within each of them
+    static DEBUG_GROUPS: RefCell<Vec<(String, bool)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Function given every debug message at or above the chosen severity. It is called from the render thread
+/// while the command that raised the message is executed, so it should return quickly
+pub type GlDebugLogger = Box<dyn Fn(&GlDebugMessage) + Send + Sync>;
+
+/// How important a debug message is, from least to most important
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum GlDebugSeverity
+{
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+/// What raised a debug message
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GlDebugSource
+{
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+/// What a debug message is about
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum GlDebugType
+{
+    Error,
+    DeprecatedBehaviour,
+    UndefinedBehaviour,
+    Portability,
+    Performance,
+    Marker,
+    PushGroup,
+    PopGroup,
+    Other,
+}
+
+/// A message reported by OpenGL
+#[derive(Clone, Debug)]
+pub struct GlDebugMessage
+{
+    pub source: GlDebugSource,
+    pub message_type: GlDebugType,
+    pub severity: GlDebugSeverity,
+    /// The ID the driver gave the message, or the error code for errors found in the error flag
+    pub id: u32,
+    pub message: String,
+    /// The operations of the engine that were being executed when the message was raised, from the outermost
+    /// to the innermost. Empty if the message was raised outside of those operations
+    pub context: Vec<String>,
+}
+
+impl GlDebugSeverity
+{
+    /// Get the OpenGL enum of the severity
+    fn to_gl(self) -> GLenum
+    {
+        match self
+        {
+            GlDebugSeverity::Notification => gl::DEBUG_SEVERITY_NOTIFICATION,
+            GlDebugSeverity::Low => gl::DEBUG_SEVERITY_LOW,
+            GlDebugSeverity::Medium => gl::DEBUG_SEVERITY_MEDIUM,
+            GlDebugSeverity::High => gl::DEBUG_SEVERITY_HIGH,
+        }
+    }
+
+    /// Get the severity of an OpenGL enum
+    ///
+    /// `severity` - the OpenGL enum of the severity
+    fn from_gl(severity: GLenum) -> GlDebugSeverity
+    {
+        match severity
+        {
+            gl::DEBUG_SEVERITY_HIGH => GlDebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => GlDebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW => GlDebugSeverity::Low,
+            _ => GlDebugSeverity::Notification
+        }
+    }
+}
+
+impl GlDebugSource
+{
+    /// Get the source of an OpenGL enum
+    ///
+    /// `source` - the OpenGL enum of the source
+    fn from_gl(source: GLenum) -> GlDebugSource
+    {
+        match source
+        {
+            gl::DEBUG_SOURCE_API => GlDebugSource::Api,
+            gl::DEBUG_SOURCE_WINDOW_SYSTEM => GlDebugSource::WindowSystem,
+            gl::DEBUG_SOURCE_SHADER_COMPILER => GlDebugSource::ShaderCompiler,
+            gl::DEBUG_SOURCE_THIRD_PARTY => GlDebugSource::ThirdParty,
+            gl::DEBUG_SOURCE_APPLICATION => GlDebugSource::Application,
+            _ => GlDebugSource::Other
+        }
+    }
+}
+
+impl GlDebugType
+{
+    /// Get the type of an OpenGL enum
+    ///
+    /// `message_type` - the OpenGL enum of the type
+    fn from_gl(message_type: GLenum) -> GlDebugType
+    {
+        match message_type
+        {
+            gl::DEBUG_TYPE_ERROR => GlDebugType::Error,
+            gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => GlDebugType::DeprecatedBehaviour,
+            gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => GlDebugType::UndefinedBehaviour,
+            gl::DEBUG_TYPE_PORTABILITY => GlDebugType::Portability,
+            gl::DEBUG_TYPE_PERFORMANCE => GlDebugType::Performance,
+            gl::DEBUG_TYPE_MARKER => GlDebugType::Marker,
+            gl::DEBUG_TYPE_PUSH_GROUP => GlDebugType::PushGroup,
+            gl::DEBUG_TYPE_POP_GROUP => GlDebugType::PopGroup,
+            _ => GlDebugType::Other
+        }
+    }
+}
+
+/// Enables or disables OpenGL debug output, starting from the next frame that is rendered
+///
+/// `minimum_severity` - the least important messages that are reported, or None to disable debug output
+pub fn set_gl_debug_output(minimum_severity: Option<GlDebugSeverity>)
+{
+    *MINIMUM_SEVERITY.lock() = minimum_severity;
+    SETTINGS_CHANGED.store(true, Ordering::Release);
+}
+
+/// Get the least important debug messages that are reported, if debug output is enabled
+pub fn gl_debug_output() -> Option<GlDebugSeverity>
+{
+    *MINIMUM_SEVERITY.lock()
+}
+
+/// Sets the function debug messages are given to
+///
+/// `logger` - the function to give messages to, or None to log messages through tracing
+pub fn set_gl_debug_logger(logger: Option<GlDebugLogger>)
+{
+    *LOGGER.write() = logger;
+}
+
+/// Enables debug output for a newly created OpenGL context with the current settings. The context must be current
+pub(crate) fn install_gl_debug_output()
+{
+    SETTINGS_CHANGED.store(true, Ordering::Release);
+    apply_gl_debug_output();
+}
+
+/// Applies the debug output settings changed since they were last applied. The OpenGL context must be current
+pub(crate) fn apply_gl_debug_output()
+{
+    if !SETTINGS_CHANGED.swap(false, Ordering::AcqRel) || !gl::DebugMessageCallback::is_loaded()
+    {
+        return;
+    }
+
+    let minimum_severity = match gl_debug_output()
+    {
+        Some(i) => i,
+        None =>
+            {
+                unsafe { gl::Disable(gl::DEBUG_OUTPUT); }
+                return;
+            }
+    };
+
+    unsafe
+        {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS); // makes sure errors are reported by the command raising them
+            gl::DebugMessageCallback(Some(gl_debug_output_callback), std::ptr::null());
+
+            for severity in [GlDebugSeverity::Notification, GlDebugSeverity::Low, GlDebugSeverity::Medium, GlDebugSeverity::High]
+            {
+                let enabled = if severity >= minimum_severity { gl::TRUE } else { gl::FALSE };
+                gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, severity.to_gl(), 0, std::ptr::null(), enabled);
+            }
+        }
+}
+
+/// Executes an operation within a debug group, so that debug messages raised by it say which operation raised
+/// them. Errors left in the error flag by the operation that were not already reported are reported once it
+/// finishes. The OpenGL context must be current
+///
+/// `name` - the description of the operation
+/// `operation` - the operation to execute
+pub(crate) fn with_gl_debug_group<R, F: FnOnce() -> R>(name: &str, operation: F) -> R
+{
+    // The error flag is cleared so that errors raised before the operation are not blamed on it
+    unsafe { while gl::GetError() != gl::NO_ERROR {} }
+
+    let group_name = CString::new(name).unwrap_or_default();
+    let debug_groups_supported = gl::PushDebugGroup::is_loaded();
+
+    if debug_groups_supported
+    {
+        unsafe { gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, -1, group_name.as_ptr()); }
+    }
+
+    DEBUG_GROUPS.with(|x| x.borrow_mut().push((name.to_string(), false)));
+
+    let result = operation();
+
+    let mut errors = Vec::new();
+    unsafe
+        {
+            loop
+            {
+                let error = gl::GetError();
+
+                if error == gl::NO_ERROR
+                {
+                    break;
+                }
+
+                errors.push(error);
+            }
+        }
+
+    let error_reported = DEBUG_GROUPS.with(|x| x.borrow().last().is_some_and(|x| x.1));
+
+    if !error_reported
+    {
+        for error in errors
+        {
+            report(GlDebugMessage
+            {
+                source: GlDebugSource::Api,
+                message_type: GlDebugType::Error,
+                severity: GlDebugSeverity::High,
+                id: error,
+                message: format!("{} was raised", gl_error_name(error)),
+                context: current_context(),
+            });
+        }
+    }
+
+    DEBUG_GROUPS.with(|x| x.borrow_mut().pop());
+
+    if debug_groups_supported
+    {
+        unsafe { gl::PopDebugGroup(); }
+    }
+
+    result
+}
+
+/// Get the operations of the engine being executed on this thread, from the outermost to the innermost
+fn current_context() -> Vec<String>
+{
+    DEBUG_GROUPS.with(|x| x.borrow().iter().map(|(name, _)| name.clone()).collect())
+}
+
+/// Gives a message to the logger of the user, or logs it through tracing if there is none. Messages less
+/// important than the chosen severity are discarded
+///
+/// `message` - the message to report
+fn report(message: GlDebugMessage)
+{
+    if gl_debug_output().is_none_or(|x| message.severity < x)
+    {
+        return;
+    }
+
+    if message.message_type == GlDebugType::Error
+    {
+        DEBUG_GROUPS.with(|x| x.borrow_mut().iter_mut().for_each(|x| x.1 = true));
+    }
+
+    if let Some(ref logger) = *LOGGER.read()
+    {
+        logger(&message);
+        return;
+    }
+
+    let context = message.context.join(" > ");
+
+    match message.severity
+    {
+        _ if message.message_type == GlDebugType::Error =>
+            error!(id = message.id, source = ?message.source, context = %context, "OpenGL error: {}", message.message),
+        GlDebugSeverity::High => error!(id = message.id, source = ?message.source, message_type = ?message.message_type, context = %context, "OpenGL: {}", message.message),
+        GlDebugSeverity::Medium => warn!(id = message.id, source = ?message.source, message_type = ?message.message_type, context = %context, "OpenGL: {}", message.message),
+        GlDebugSeverity::Low => info!(id = message.id, source = ?message.source, message_type = ?message.message_type, context = %context, "OpenGL: {}", message.message),
+        GlDebugSeverity::Notification => debug!(id = message.id, source = ?message.source, message_type = ?message.message_type, context = %context, "OpenGL: {}", message.message),
+    }
+}
+
+/// Get the name of an OpenGL error code
+///
+/// `error` - the error code
+fn gl_error_name(error: GLenum) -> String
+{
+    match error
+    {
+        gl::INVALID_ENUM => "GL_INVALID_ENUM".to_string(),
+        gl::INVALID_VALUE => "GL_INVALID_VALUE".to_string(),
+        gl::INVALID_OPERATION => "GL_INVALID_OPERATION".to_string(),
+        gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION".to_string(),
+        gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY".to_string(),
+        gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW".to_string(),
+        gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW".to_string(),
+        _ => format!("OpenGL error {:#x}", error)
+    }
+}
+
+extern "system" fn gl_debug_output_callback(source: GLenum, message_type: GLenum, id: GLuint, severity: GLenum, _length: GLsizei,
+                                            message: *const GLchar, _user_param: *mut c_void)
+{
+    let message_type = GlDebugType::from_gl(message_type);
+
+    // The debug groups of the engine are already given as the context of messages
+    if message_type == GlDebugType::PushGroup || message_type == GlDebugType::PopGroup
+    {
+        return;
+    }
+
+    let message = unsafe { CStr::from_ptr(message).to_string_lossy().into_owned() };
+    let gpu_vendor = unsafe { CStr::from_ptr(gl::GetString(gl::VENDOR) as *const i8).to_string_lossy().into_owned() };
+
+    if gpu_vendor.contains("Intel") && message.contains("API_ID_SYNC_FLUSH")
+    {
+        return;
+    }
+
+    report(GlDebugMessage
+    {
+        source: GlDebugSource::from_gl(source),
+        message_type,
+        severity: GlDebugSeverity::from_gl(severity),
+        id,
+        message,
+        context: current_context(),
+    });
+}
@@ -0,0 +1,32 @@
+use nalgebra_glm::TVec4;
+use serde::{Deserialize, Serialize};
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+
+// Small per-entity effects such as a damage flash, a team colour or how far an entity has dissolved only
+// need a few numbers in the shader. Rather than defining a component and a layout update function for each,
+// a render system created with instance parameters is given an instanced vec4 layout named instanceParams,
+// which the engine fills from the InstanceParams component of every entity it draws. What the four numbers
+// mean is up to the shaders of the render system. Static entities only show changed parameters once their
+// world section is next sorted, as the instance data of static entities is not rewritten every frame
+
+/// Four numbers written to the instanceParams layout of render systems created with instance parameters.
+/// Entities without this component write zeros
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InstanceParams(pub TVec4<f32>);
+
+/// Appends the instance parameters of an entity to the instance data being written for a render system
+///
+/// `ecs` - the ECS holding the entity
+/// `buffer_write_destination` - the instance data of the instanceParams layout
+/// `entity_id` - the entity being written
+pub(crate) fn write_instance_params(ecs: &ECS, buffer_write_destination: &mut Vec<u8>, entity_id: EntityId)
+{
+    let params = ecs.get_copy::<InstanceParams>(entity_id).map_or([0.0; 4], |x| [x.0.x, x.0.y, x.0.z, x.0.w]);
+
+    for param in params.iter()
+    {
+        buffer_write_destination.extend_from_slice(&param.to_ne_bytes());
+    }
+}
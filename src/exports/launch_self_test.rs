@@ -0,0 +1,85 @@
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::error;
+
+// Before any render system is created, the engine can render a small built-in scene into an offscreen
+// framebuffer and read it back. A driver or context that is misconfigured usually fails this in an obvious
+// way- nothing is drawn, depth is not written, or OpenGL reports an error- which is far easier to diagnose
+// than the same problem showing up once the world is loaded. The self-test is enabled by default in debug
+// builds only, and takes a few milliseconds
+
+lazy_static!
+{
+    static ref SELF_TEST_ENABLED: Mutex<bool> = Mutex::new(cfg!(debug_assertions));
+    static ref PANIC_ON_FAILURE: Mutex<bool> = Mutex::new(true);
+    static ref SELF_TEST_REPORT: Mutex<Option<SelfTestReport>> = Mutex::new(None);
+}
+
+/// The result of rendering the self-test scene
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelfTestReport
+{
+    /// Description of every invariant the rendered scene did not hold
+    pub failures: Vec<String>,
+    /// Time taken to render and check the scene, in milliseconds
+    pub time_taken: f32,
+}
+
+impl SelfTestReport
+{
+    /// Checks if the scene rendered as expected
+    pub fn passed(&self) -> bool
+    {
+        self.failures.is_empty()
+    }
+}
+
+/// Specifies if the self-test is run when the engine launches. Must be called before the engine is launched
+///
+/// `enabled` - true if the self-test should be run
+pub fn set_launch_self_test(enabled: bool)
+{
+    *SELF_TEST_ENABLED.lock() = enabled;
+}
+
+/// Checks if the self-test is run when the engine launches
+pub fn is_launch_self_test_enabled() -> bool
+{
+    *SELF_TEST_ENABLED.lock()
+}
+
+/// Specifies if the engine panics when the self-test fails. If false, the failures are printed and the
+/// engine continues to launch. Panicking is the default
+///
+/// `panic_on_failure` - true if a failed self-test should stop the engine
+pub fn set_panic_on_self_test_failure(panic_on_failure: bool)
+{
+    *PANIC_ON_FAILURE.lock() = panic_on_failure;
+}
+
+/// Get the result of the self-test run when the engine launched. None is returned if the self-test was
+/// not run
+pub fn launch_self_test_report() -> Option<SelfTestReport>
+{
+    SELF_TEST_REPORT.lock().clone()
+}
+
+/// Stores the result of the self-test, and reports any failures
+///
+/// `report` - the result of rendering the self-test scene
+pub(crate) fn record_self_test_report(report: SelfTestReport)
+{
+    if !report.passed()
+    {
+        let failures = report.failures.join("\n\t");
+
+        if *PANIC_ON_FAILURE.lock()
+        {
+            panic!("The launch self-test failed; the graphics driver or OpenGL context is likely misconfigured:\n\t{}", failures);
+        }
+
+        error!("The launch self-test failed; the graphics driver or OpenGL context is likely misconfigured:\n\t{}", failures);
+    }
+
+    *SELF_TEST_REPORT.lock() = Some(report);
+}
@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+// Lights that never move and the static entities they shine on give the same lighting every frame. When
+// light baking is enabled, the lighting from static lights is computed once for every world section that
+// only holds static entities, and render systems created with baked lighting read it from a texture
+// instead of evaluating the lights. Lights of any kind are not evaluated for fragments in a baked world
+// section, so dynamic lights do not affect static parts of the world
+
+static LIGHT_BAKE_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static!
+{
+    static ref LIGHT_BAKE_SETTINGS: Mutex<Option<LightBakeSettings>> = Mutex::new(None);
+}
+
+/// Controls how lighting is baked for static world sections
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightBakeSettings
+{
+    /// Number of lighting samples along each side of a world section. The samples of a world section are
+    /// interpolated between, so higher resolutions give sharper lighting at the cost of baking time and memory
+    pub resolution: u32,
+    /// If true, static entities between a light and a sample block the light. Bounding volumes are used to
+    /// find blocking entities, so shadows are blocky
+    pub shadows: bool,
+}
+
+impl Default for LightBakeSettings
+{
+    fn default() -> LightBakeSettings
+    {
+        LightBakeSettings{ resolution: 8, shadows: true }
+    }
+}
+
+/// Enables or disables light baking. The lighting is baked when the first frame is rendered, or loaded from
+/// the model folder if the static world has not changed since it was last baked
+///
+/// `settings` - how the lighting should be baked. If None, baked lighting is not used
+pub fn set_light_baking(settings: Option<LightBakeSettings>)
+{
+    *LIGHT_BAKE_SETTINGS.lock() = settings.map(|x| LightBakeSettings{ resolution: x.resolution.clamp(2, 64), ..x });
+    LIGHT_BAKE_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Get how lighting is baked, if light baking is enabled
+pub fn light_baking() -> Option<LightBakeSettings>
+{
+    *LIGHT_BAKE_SETTINGS.lock()
+}
+
+/// Requests the lighting to be baked again in the next frame, such as after static entities or lights
+/// were added to the world
+pub fn request_light_bake()
+{
+    LIGHT_BAKE_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Checks if the lighting should be baked, clearing the request
+pub(crate) fn take_light_bake_request() -> bool
+{
+    LIGHT_BAKE_REQUESTED.swap(false, Ordering::Relaxed)
+}
@@ -1,4 +1,4 @@
-use nalgebra_glm::{TVec3, TVec4};
+use nalgebra_glm::{TVec3, TVec4, vec4};
 use serde::{Serialize, Deserialize};
 
 pub struct DirectionLight;
@@ -23,6 +23,85 @@ pub struct LightInformation
     pub fov: Option<f32>,
 }
 
+/// The shape light is emitted from by an area light
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AreaLightShape
+{
+    Rectangle{ width: f32, height: f32 },
+    Disk{ radius: f32 },
+}
+
+/// Makes a spot light shine from a shape instead of from a single point, giving softer and more even lighting
+/// of nearby entities such as from a lit window or a ceiling panel. The shape lies in the plane spanned by the
+/// x and y axes of the transformation of the light, facing its z axis. The entity must also be a spot light
+/// with light information, which gives the colours and reach of the light
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AreaLight
+{
+    pub shape: AreaLightShape,
+    /// If false, only entities in front of the shape are lit
+    pub two_sided: bool,
+}
+
+/// Marks an entity as glowing geometry. If the entity is also a spot light, its light is emitted from the
+/// surface of the entity rather than from its centre, so that large glowing models such as stars light
+/// nearby entities evenly. The surface is approximated by the largest sphere fitting in the bounding box
+/// of the entity
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Emissive
+{
+    pub colour: TVec3<f32>,
+    /// Multiplier of the colour of the light given to nearby entities
+    pub strength: f32,
+}
+
+impl Emissive
+{
+    /// Creates the light information of a spot light matching the glow of the entity, fading out over
+    /// the given radius
+    ///
+    /// `radius` - the distance from the entity that it lights
+    pub fn light_information(&self, radius: f32) -> LightInformation
+    {
+        let radius = radius.max(f32::EPSILON);
+        let light_colour = self.colour * self.strength;
+
+        LightInformation
+        {
+            radius,
+            diffuse_colour: light_colour,
+            specular_colour: light_colour,
+            ambient_colour: vec4(self.colour.x, self.colour.y, self.colour.z, 0.25),
+            // Attenuation that fades most of the light by the edge of the radius
+            linear_coefficient: 4.5 / radius,
+            quadratic_coefficient: 75.0 / (radius * radius),
+            cutoff: None,
+            outer_cutoff: None,
+            direction: None,
+            fov: None
+        }
+    }
+}
+
+/// Controls whether a light is given a shadow map. Lights without this component cast shadows with the
+/// default priority. There are only six shadow maps, so when more lights need one the most important
+/// lights are given them, based on how bright they are, how near they are to the camera and their priority
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CastsShadows
+{
+    pub enabled: bool,
+    /// Multiplier of how important the light is when deciding which lights are given shadow maps
+    pub priority: f32,
+}
+
+impl Default for CastsShadows
+{
+    fn default() -> CastsShadows
+    {
+        CastsShadows{ enabled: true, priority: 1.0 }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct BorderOutline;
 
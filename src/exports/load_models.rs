@@ -1,11 +1,20 @@
 use std::path::PathBuf;
 use hashbrown::HashMap;
 use nalgebra_glm::TVec4;
+use crate::exports::accessibility::AccessibilitySettings;
+use crate::exports::benchmark::Benchmark;
 use crate::exports::camera_object::Camera;
-use crate::exports::logic_components::{CollisionLogic, EntityLogic, OutOfBoundsLogic, UserInputLogic};
-use crate::exports::rendering::LevelOfView;
+use crate::exports::logic_components::{CollisionLogic, EntityLogic, OutOfBoundsLogic, RenderSystemIndex, UserInputLogic};
+use crate::exports::model_import::ModelImportSettings;
+use crate::exports::panic_policy::PanicPolicy;
+use crate::exports::prefab::UserLoadPrefabInstances;
+#[cfg(feature = "scripting")]
+use crate::exports::scripted_logic::ScriptedLogic;
+use crate::exports::rendering::{LevelOfView, ScreenSpaceReflections, ShadowQuality};
+use crate::flows::shared_constants::{REQUESTED_DEBUG_RENDER_MODES, REQUESTED_DRAW_FUNCTION_REPLACEMENTS, REQUESTED_LIGHT_CONSTRAINTS, REQUESTED_RENDER_SYSTEM_TOGGLES};
 use crate::objects::ecs::{ECS, TypeIdentifier};
 use crate::objects::entity_id::EntityId;
+use crate::objects::system_scheduler::SystemScheduler;
 use crate::render_system::render_system::{InstancedLayoutWriteFunction, RenderSystem};
 use crate::render_system::system_information::DrawFunction;
 use crate::world::bounding_box_tree_v2::BoundingBoxTree;
@@ -13,28 +22,44 @@ use crate::world::bounding_volumes::aabb::StaticAABB;
 
 pub type AddInstanceFunction = fn(&mut ECS, Vec<EntityId>, &mut BoundingBoxTree, StaticAABB);
 pub type RegisterInstancesFunction = fn(&mut ECS);
+pub type CreateRenderSystemFunction = fn() -> RenderSystem;
 
 pub struct UserUploadInformation
 {
     pub window_resolution: (u32, u32),
     pub max_fps: i64,
+    pub fullscreen: bool,
+    pub accessibility: AccessibilitySettings,
     pub world_section_length: u32,
     pub initial_camera: Camera,
     pub render_systems: Vec<UserLoadRenderSystems>,
     pub load_models: Vec<UserLoadModelInfo>,
     pub load_instances: Vec<UserLoadModelInstances>,
+    pub load_prefabs: Vec<UserLoadPrefabInstances>,
     pub instance_logic: InstanceLogic,
     pub shadow_render_system_lov: Option<Vec<LevelOfView>>,
     pub shadow_draw_fn: DrawFunction,
     pub shadow_transparency_draw_fn: DrawFunction,
     pub shadow_light_draw_fn: DrawFunction,
     pub is_debugging: bool,
+    /// Folder the gameplay history is recorded to and replayed from. The debug logs folder is used if not set
+    pub capture_directory: Option<PathBuf>,
+    pub hot_reload_models: bool,
+    /// If set, the camera follows the path of the benchmark and the engine exits once it is finished
+    pub benchmark: Option<Benchmark>,
     pub model_texture_dir: PathBuf,
     pub user_collision_function: CollisionLogic,
     pub user_logic_function: EntityLogic,
     pub user_original_aabb: StaticAABB,
+    pub user_aabb_from_model: bool,
     pub user_input_functions: Vec<UserInputLogic>,
     pub register_instance_function: Vec<RegisterInstancesFunction>,
+    pub history_thread_panic_policy: PanicPolicy,
+    /// The render thread cannot be restarted, so PanicPolicy::RestartThread, or an escalation callback
+    /// returning PanicAction::RestartThread, exits the same as PanicPolicy::Exit
+    pub render_thread_panic_policy: PanicPolicy,
+    /// Sky drawn behind everything rendered to the window, without writing a render system for it
+    pub skybox: Option<Skybox>,
 }
 
 unsafe impl Send for UserUploadInformation {}
@@ -49,25 +74,53 @@ impl UserUploadInformation
         {
             window_resolution: (initial_camera.window_width as u32, initial_camera.window_height as u32),
             max_fps: 60,
+            fullscreen: false,
+            accessibility: AccessibilitySettings::default(),
             world_section_length: 64,
             initial_camera,
             render_systems: vec![],
             load_models: vec![],
             load_instances: vec![],
+            load_prefabs: vec![],
             instance_logic: InstanceLogic::new(),
             shadow_render_system_lov: None,
             shadow_draw_fn,
             shadow_light_draw_fn,
             shadow_transparency_draw_fn,
             is_debugging: false,
+            capture_directory: None,
+            hot_reload_models: false,
+            benchmark: None,
             model_texture_dir,
             user_collision_function,
             user_logic_function,
             user_original_aabb,
+            user_aabb_from_model: false,
             user_input_functions,
-            register_instance_function: Vec::new()
+            register_instance_function: Vec::new(),
+            history_thread_panic_policy: PanicPolicy::Exit,
+            render_thread_panic_policy: PanicPolicy::Exit,
+            skybox: None,
         }
     }
+
+    /// Adds a render system under the given name, returning the index that refers to it once the render
+    /// systems are created. Models are uploaded to a render system using its name
+    ///
+    /// `name` - the unique name of the render system
+    /// `render_system` - the render system to create
+    pub fn add_render_system<T: Into<String>>(&mut self, name: T, render_system: RenderSystemType) -> RenderSystemIndex
+    {
+        let render_system_name = name.into();
+
+        if self.render_systems.iter().any(|x| x.render_system_name == render_system_name)
+        {
+            panic!("A render system with the name {} was already added", render_system_name);
+        }
+
+        self.render_systems.push(UserLoadRenderSystems{ render_system, render_system_name });
+        RenderSystemIndex{ index: self.render_systems.len() - 1 }
+    }
 }
 
 pub struct InstanceLogic
@@ -76,7 +129,11 @@ pub struct InstanceLogic
     pub random_entity_logic: HashMap<TypeIdentifier, EntityLogic>,
     pub collision_logic: HashMap<TypeIdentifier, CollisionLogic>,
     pub random_collision_logic: HashMap<TypeIdentifier, CollisionLogic>,
-    pub out_of_bounds_logic: HashMap<TypeIdentifier, OutOfBoundsLogic>
+    pub out_of_bounds_logic: HashMap<TypeIdentifier, OutOfBoundsLogic>,
+    pub systems: SystemScheduler,
+    /// Logic given by script files, executed after the entity logic of the same entity type
+    #[cfg(feature = "scripting")]
+    pub scripted_logic: HashMap<TypeIdentifier, ScriptedLogic>,
 }
 
 impl InstanceLogic
@@ -89,11 +146,15 @@ impl InstanceLogic
             random_entity_logic: HashMap::default(),
             collision_logic: HashMap::default(),
             random_collision_logic: HashMap::default(),
-            out_of_bounds_logic: HashMap::default()
+            out_of_bounds_logic: HashMap::default(),
+            systems: SystemScheduler::new(),
+            #[cfg(feature = "scripting")]
+            scripted_logic: HashMap::default(),
         }
     }
 }
 
+#[derive(Copy, Clone, Eq, PartialEq)]
 pub struct MaxNumLights
 {
     pub directional: u16,
@@ -101,6 +162,87 @@ pub struct MaxNumLights
     pub spot: u16
 }
 
+/// Requests that the maximum number of lights used by a render system be changed. The change is
+/// applied at the start of the next frame that is rendered, regenerating the shaders of the render
+/// system that depend on the number of lights. Requests for render systems without a second render
+/// pass are rejected and logged, as their light buffers are not rebuilt
+///
+/// `render_system_index` - the index of the render system to change the light constraints of
+/// `max_num_lights` - the new maximum number of each type of light
+pub fn request_light_constraints_change(render_system_index: RenderSystemIndex, max_num_lights: MaxNumLights)
+{
+    REQUESTED_LIGHT_CONSTRAINTS.lock().push((render_system_index, max_num_lights));
+}
+
+/// Requests that a render system be enabled or disabled. A disabled render system does not sort the visible
+/// entities or draw them, both when rendering to the window and to minimaps, until it is enabled again. Shadows
+/// are rendered by a separate render system, so disabling a render system does not remove the shadows of
+/// the entities it draws. The change is applied at the start of the next frame that is rendered
+///
+/// `render_system_index` - the index of the render system to enable or disable
+/// `enabled` - true if the render system should render entities
+pub fn set_render_system_enabled(render_system_index: RenderSystemIndex, enabled: bool)
+{
+    REQUESTED_RENDER_SYSTEM_TOGGLES.lock().push((render_system_index, enabled));
+}
+
+/// The draw functions of a render system that can be replaced while the game runs
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DrawFunctionSlot
+{
+    /// Draws the models of the render system
+    Draw,
+    /// Draws the models that are light sources
+    LightSource,
+    /// Draws the transparent models, after the light sources and the sky box
+    Transparency,
+}
+
+/// Requests that a draw function of a render system be replaced, such as to try a different way of drawing
+/// while the game runs. The function is replaced at the start of the next frame that is rendered, so the frame
+/// being rendered when this is called finishes with the previous function. The draw functions of the render
+/// system that draws shadows cannot be replaced
+///
+/// `render_system_index` - the index of the render system to replace the draw function of
+/// `slot` - the draw function to replace
+/// `draw_function` - the function to draw with from the next frame
+pub fn replace_draw_function(render_system_index: RenderSystemIndex, slot: DrawFunctionSlot, draw_function: DrawFunction)
+{
+    REQUESTED_DRAW_FUNCTION_REPLACEMENTS.lock().push((render_system_index, slot, draw_function));
+}
+
+/// What a render system shows in place of its lit frame, to debug how its models are drawn
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DebugRenderMode
+{
+    /// The frame is drawn as usual
+    Shaded,
+    /// Models are drawn as the edges of their triangles, and then lit as usual
+    Wireframe,
+    /// The normals of the drawn fragments are shown as colours, without lighting
+    Normals,
+    /// Every pixel is coloured by how many times it was drawn over, from black for pixels that were not drawn
+    /// to white for pixels drawn over seven or more times
+    Overdraw,
+    /// The mipmap level sampled for the texture of every fragment is shown as a colour, from blue for the
+    /// full resolution level to red for the smallest levels
+    MipmapLevel,
+}
+
+/// Requests that a render system show a debug render mode in place of its lit frame. Modes other than the
+/// wireframe mode require the render system to use deferred rendering; the normals mode shows the second
+/// layout of the G-Buffer, and the mipmap level mode requires the first pass fragment shader to declare the
+/// debugMipmapLevel uniform, as the shaders of the default render systems do. Modes the render system cannot
+/// show are ignored. The change is applied at the start of the next frame that is rendered
+///
+/// `render_system_index` - the index of the render system to change the debug render mode of
+/// `mode` - what the render system shows
+pub fn set_debug_render_mode(render_system_index: RenderSystemIndex, mode: DebugRenderMode)
+{
+    REQUESTED_DEBUG_RENDER_MODES.lock().push((render_system_index, mode));
+}
+
+#[derive(Clone)]
 pub struct DefaultRenderSystemArgs
 {
     pub draw_function: DrawFunction,
@@ -113,12 +255,21 @@ pub struct DefaultRenderSystemArgs
     pub max_count_lights: MaxNumLights,
     pub no_light_source_cutoff: f32,
     pub default_diffuse_factor: f32,
+    pub shadow_quality: ShadowQuality,
+    pub clustered_lighting: bool,
+    pub gpu_level_of_views: bool,
+    pub gpu_culling: bool,
+    pub screen_space_reflections: Option<ScreenSpaceReflections>,
 }
 
+/// The render system to create. If the GL context is lost, such as after a GPU driver reset, render systems
+/// are created again from their arguments or creation function. A Custom render system cannot be created
+/// again, so the game is not able to recover from the loss of the context if one is used
 pub enum RenderSystemType
 {
     Default(DefaultRenderSystemArgs),
     Custom(RenderSystem),
+    CustomFunction(CreateRenderSystemFunction),
 }
 
 pub struct UserLoadRenderSystems
@@ -140,6 +291,7 @@ pub struct UserLoadModelInfo
     pub location: Vec<PathBuf>,
     pub custom_level_of_view: Option<Vec<UserLevelOfView>>,
     pub solid_colour_texture: Option<TVec4<u8>>,
+    pub import_settings: Option<ModelImportSettings>,
 }
 
 pub struct UserLoadModelInstances
@@ -149,6 +301,17 @@ pub struct UserLoadModelInstances
     pub upload_fn: AddInstanceFunction,
 }
 
+/// The images a skybox is created from
+#[derive(Clone, Debug)]
+pub enum Skybox
+{
+    /// Six images in the order right, left, top, bottom, front and back
+    CubeFaces(Vec<PathBuf>),
+    /// An equirectangular HDR image, converted into a cubemap with faces of the given width
+    Equirectangular{ image: PathBuf, resolution: u32 },
+}
+
+#[derive(Clone)]
 pub struct UserLoadSkyBoxModels
 {
     pub sky_box_name: String,
@@ -0,0 +1,199 @@
+use std::fs;
+use std::path::Path;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use crate::helper_things::environment::get_localization_folder;
+
+// Every language has a string table, a JSON file in the localization folder named after the language
+// (for example en.json) mapping keys to the text shown for that language:
+//
+//     { "menu.start": "Start Game", "hud.score": "Score: {score}" }
+//
+// Text is looked up by key in the current language. If the current language does not have the key, the
+// fallback language is used, and if that does not have it either, the key itself is returned so that
+// missing text is visible rather than blank. Changing the language increments the language generation,
+// which text that was already laid out can compare against to know when to look up its text again
+
+lazy_static!
+{
+    static ref LOCALIZATION: RwLock<Localization> = RwLock::new(Localization::new());
+}
+
+/// Reasons the localization could not be changed
+#[derive(Debug)]
+pub enum LocalizationError
+{
+    UnknownLanguage(String),
+    ReadError(String, std::io::Error),
+    InvalidStringTable(String, serde_json::Error),
+}
+
+struct Localization
+{
+    string_tables: HashMap<String, HashMap<String, String>>,
+    current_language: String,
+    fallback_language: String,
+    language_generation: u64,
+}
+
+impl Localization
+{
+    /// Creates a localization without any string tables, with English as the current and fallback language
+    fn new() -> Localization
+    {
+        Localization
+        {
+            string_tables: HashMap::default(),
+            current_language: "en".to_string(),
+            fallback_language: "en".to_string(),
+            language_generation: 0,
+        }
+    }
+
+    /// Finds the text for the key in the current language, then the fallback language
+    ///
+    /// `key` - the key of the text
+    fn lookup(&self, key: &str) -> Option<&String>
+    {
+        self.string_tables.get(&self.current_language).and_then(|x| x.get(key))
+            .or_else(|| self.string_tables.get(&self.fallback_language).and_then(|x| x.get(key)))
+    }
+}
+
+/// Loads the string tables of all languages in the localization folder of the asset folder
+pub fn load_string_tables() -> Result<(), LocalizationError>
+{
+    load_string_tables_from(get_localization_folder())
+}
+
+/// Loads the string tables of all languages in the given folder. Every JSON file in the folder is a string
+/// table, with the name of the file being the language. String tables that were already loaded for a
+/// language are replaced
+///
+/// `folder` - the folder holding the string tables
+pub fn load_string_tables_from<P: AsRef<Path>>(folder: P) -> Result<(), LocalizationError>
+{
+    let folder_name = folder.as_ref().to_string_lossy().to_string();
+    let entries = fs::read_dir(folder.as_ref()).map_err(|err| LocalizationError::ReadError(folder_name, err))?;
+
+    for entry in entries.filter_map(|x| x.ok())
+    {
+        let path = entry.path();
+
+        if path.extension().is_none_or(|x| x != "json")
+        {
+            continue;
+        }
+
+        let language = match path.file_stem()
+        {
+            Some(i) => i.to_string_lossy().to_string(),
+            None => continue
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|err| LocalizationError::ReadError(language.clone(), err))?;
+        add_string_table(&language, &contents)?;
+    }
+
+    Ok(())
+}
+
+/// Adds the string table of a language from its JSON contents, replacing any existing string table of
+/// the language
+///
+/// `language` - the language the string table is for
+/// `json` - the contents of the string table
+pub fn add_string_table(language: &str, json: &str) -> Result<(), LocalizationError>
+{
+    let string_table = serde_json::from_str::<HashMap<String, String>>(json)
+        .map_err(|err| LocalizationError::InvalidStringTable(language.to_string(), err))?;
+
+    let mut localization = LOCALIZATION.write();
+    localization.string_tables.insert(language.to_string(), string_table);
+
+    // Text of the current language may have changed
+    if localization.current_language == language
+    {
+        localization.language_generation += 1;
+    }
+
+    Ok(())
+}
+
+/// Changes the language text is shown in. Text that was already looked up has to be looked up again
+/// to be shown in the new language; see language_generation()
+///
+/// `language` - the language to change to. Its string table must have been loaded
+pub fn set_language(language: &str) -> Result<(), LocalizationError>
+{
+    let mut localization = LOCALIZATION.write();
+
+    if !localization.string_tables.contains_key(language)
+    {
+        return Err(LocalizationError::UnknownLanguage(language.to_string()));
+    }
+
+    if localization.current_language != language
+    {
+        localization.current_language = language.to_string();
+        localization.language_generation += 1;
+    }
+
+    Ok(())
+}
+
+/// Changes the language used when the current language does not have the text for a key
+///
+/// `language` - the language to fall back to
+pub fn set_fallback_language(language: &str)
+{
+    let mut localization = LOCALIZATION.write();
+    localization.fallback_language = language.to_string();
+    localization.language_generation += 1;
+}
+
+/// Get the language text is currently shown in
+pub fn current_language() -> String
+{
+    LOCALIZATION.read().current_language.clone()
+}
+
+/// Get the languages that have a string table loaded, sorted by name
+pub fn available_languages() -> Vec<String>
+{
+    let mut languages = LOCALIZATION.read().string_tables.keys().cloned().collect::<Vec<String>>();
+    languages.sort();
+    languages
+}
+
+/// Get a number that changes whenever the language or the text of the current language changes
+pub fn language_generation() -> u64
+{
+    LOCALIZATION.read().language_generation
+}
+
+/// Get the text of the key in the current language. The key itself is returned if no language has the text
+///
+/// `key` - the key of the text
+pub fn localize(key: &str) -> String
+{
+    LOCALIZATION.read().lookup(key).cloned().unwrap_or_else(|| key.to_string())
+}
+
+/// Get the text of the key in the current language, with every {name} in the text replaced by the value
+/// given for that name
+///
+/// `key` - the key of the text
+/// `arguments` - the names and the values that replace them
+pub fn localize_with(key: &str, arguments: &[(&str, &str)]) -> String
+{
+    let mut text = localize(key);
+
+    for (name, value) in arguments
+    {
+        text = text.replace(&format!("{{{}}}", name), value);
+    }
+
+    text
+}
@@ -0,0 +1,248 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Write};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+use tracing::{Event, Id, Metadata, Subscriber};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Record};
+use tracing::subscriber::Interest;
+
+// The engine logs through the tracing crate, so games choose how verbose the engine is and where its logs go by
+// installing their own subscriber, such as one from tracing-subscriber, before launching the render system. The
+// culling, user input, logic, render and shadow flows of every frame are wrapped in spans, so subscribers that
+// record spans can show how long each flow took and which flow a message was logged in.
+//
+// If no subscriber was installed when the render system is launched, the engine installs a simple one that
+// writes messages at or above the level set with set_engine_log_level, along with the spans they were logged in,
+// to the sink set with set_engine_log_sink. Until a sink is set, messages are written to stderr
+
+lazy_static!
+{
+    static ref ENGINE_LOG_LEVEL: RwLock<LevelFilter> = RwLock::new(LevelFilter::INFO);
+    static ref ENGINE_LOG_SINK: Mutex<Box<dyn io::Write + Send>> = Mutex::new(Box::new(io::stderr()));
+    // The name of every open span, and the number of handles to it
+    static ref OPEN_SPANS: Mutex<HashMap<u64, (&'static str, usize)>> = Mutex::new(HashMap::default());
+}
+
+static NEXT_SPAN_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local!
+{
+    // The spans entered on this thread, from the outermost to the innermost
+    static ENTERED_SPANS: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Sets the least important messages written by the subscriber the engine installs when the game has not
+/// installed one. Has no effect on subscribers installed by the game
+///
+/// `level` - the least important level written, or LevelFilter::OFF to write nothing
+pub fn set_engine_log_level(level: LevelFilter)
+{
+    *ENGINE_LOG_LEVEL.write() = level;
+}
+
+/// Get the least important messages written by the subscriber the engine installs
+pub fn engine_log_level() -> LevelFilter
+{
+    *ENGINE_LOG_LEVEL.read()
+}
+
+/// Sets where the subscriber the engine installs writes its messages, such as a file. Has no effect on
+/// subscribers installed by the game
+///
+/// `sink` - the destination of every message written from then on
+pub fn set_engine_log_sink(sink: Box<dyn io::Write + Send>)
+{
+    *ENGINE_LOG_SINK.lock() = sink;
+}
+
+/// Installs the engine subscriber as the global default, unless the game already installed a subscriber.
+/// Called when the render system is launched; games that log before then can call it earlier
+pub fn install_default_log_subscriber()
+{
+    if !tracing::dispatcher::has_been_set()
+    {
+        // Fails only if another thread installed a subscriber in the meantime, in which case that one is used
+        let _ = tracing::subscriber::set_global_default(EngineLogSubscriber);
+    }
+}
+
+/// Writes messages to the engine log sink, prefixed by the spans they were logged in
+struct EngineLogSubscriber;
+
+/// Formats the message and the other fields of an event
+#[derive(Default)]
+struct EventFormatter
+{
+    message: String,
+    fields: String,
+}
+
+impl Visit for EventFormatter
+{
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug)
+    {
+        if field.name() == "message"
+        {
+            let _ = write!(self.message, "{:?}", value);
+        }
+        else
+        {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str)
+    {
+        if field.name() == "message"
+        {
+            self.message.push_str(value);
+        }
+        else
+        {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+impl Subscriber for EngineLogSubscriber
+{
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest
+    {
+        // The level can change at any time, so whether a callsite is enabled is checked every time
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool
+    {
+        metadata.level() <= &engine_log_level()
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id
+    {
+        let id = NEXT_SPAN_ID.fetch_add(1, Ordering::Relaxed);
+        OPEN_SPANS.lock().insert(id, (span.metadata().name(), 1));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>)
+    {
+        let mut formatter = EventFormatter::default();
+        event.record(&mut formatter);
+
+        let spans = ENTERED_SPANS.with(|x|
+            {
+                let open_spans = OPEN_SPANS.lock();
+                x.borrow().iter().filter_map(|id| open_spans.get(id)).map(|(name, _)| *name).collect::<Vec<&str>>().join(":")
+            });
+
+        let metadata = event.metadata();
+        let spans = if spans.is_empty() { String::new() } else { format!(" {}", spans) };
+        let mut sink = ENGINE_LOG_SINK.lock();
+
+        // Nowhere is left to report a failed write, so the message is dropped
+        let _ = writeln!(sink, "{:>5} {}{}: {}{}", metadata.level(), metadata.target(), spans, formatter.message, formatter.fields);
+    }
+
+    fn enter(&self, span: &Id)
+    {
+        ENTERED_SPANS.with(|x| x.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id)
+    {
+        ENTERED_SPANS.with(|x|
+            {
+                let mut entered_spans = x.borrow_mut();
+
+                if let Some(index) = entered_spans.iter().rposition(|id| *id == span.into_u64())
+                {
+                    entered_spans.remove(index);
+                }
+            });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id
+    {
+        if let Some((_, handles)) = OPEN_SPANS.lock().get_mut(&span.into_u64())
+        {
+            *handles += 1;
+        }
+
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool
+    {
+        let mut open_spans = OPEN_SPANS.lock();
+
+        match open_spans.get_mut(&span.into_u64())
+        {
+            Some((_, handles)) if *handles > 1 =>
+                {
+                    *handles -= 1;
+                    false
+                },
+            Some(_) =>
+                {
+                    open_spans.remove(&span.into_u64());
+                    true
+                },
+            None => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::io;
+    use std::sync::Arc;
+    use parking_lot::Mutex;
+    use tracing::{info, info_span};
+    use crate::exports::logging::{EngineLogSubscriber, set_engine_log_sink};
+
+    /// Sink that keeps the written messages so they can be inspected
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer
+    {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+        {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()>
+        {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn messages_written_to_sink()
+    {
+        let buffer = SharedBuffer::default();
+        set_engine_log_sink(Box::new(buffer.clone()));
+
+        tracing::subscriber::with_default(EngineLogSubscriber, ||
+            {
+                let _span = info_span!("render_flow").entered();
+                info!(frame = 3, "Frame rendered");
+            });
+
+        set_engine_log_sink(Box::new(io::stderr()));
+
+        let written = String::from_utf8(buffer.0.lock().clone()).unwrap();
+        assert!(written.contains(" INFO render_engine::exports::logging::tests render_flow: Frame rendered frame=3\n"), "{}", written);
+    }
+}
@@ -1,5 +1,7 @@
+use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 use crate::exports::camera_object::Camera;
+use crate::flows::shared_constants::RENDER_SYSTEM_NAMES;
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::{EntityId, EntityIdRead};
 use crate::objects::entity_change_request::EntityChangeInformation;
@@ -11,11 +13,16 @@ type OtherEntity = EntityIdRead;
 type UserEntity = EntityId;
 type CurrentFrameECS = ECS;
 type ElapsedTime = f32;
+type TimeOfImpact = f32;
 
-type LogicFunction = fn(SelfEntity, &CurrentFrameECS, &BoundingBoxTree, ElapsedTime) -> Vec<EntityChangeInformation>;
-type CollisionFunction = fn(SelfEntity, OtherEntity, &CurrentFrameECS, &BoundingBoxTree) -> Vec<EntityChangeInformation>;
-type OutOfBoundsFunction = fn(SelfEntity, &mut CurrentFrameECS);
-type UserInputLogicFunction = fn(UserEntity, &ECS, &BoundingBoxTree, &mut Camera, &InputHistory, &CurrentFrameInput, ElapsedTime) -> Vec<EntityChangeInformation>;
+// Logic is stored as shared closures rather than function pointers so that the logic can capture
+// configuration, such as a speed for each type of entity. Logic is executed from multiple threads,
+// so anything captured must be safe to share between threads
+
+type LogicFunction = Arc<dyn Fn(SelfEntity, &CurrentFrameECS, &BoundingBoxTree, ElapsedTime) -> Vec<EntityChangeInformation> + Send + Sync>;
+type CollisionFunction = Arc<dyn Fn(SelfEntity, OtherEntity, TimeOfImpact, &CurrentFrameECS, &BoundingBoxTree) -> Vec<EntityChangeInformation> + Send + Sync>;
+type OutOfBoundsFunction = Arc<dyn Fn(SelfEntity, &mut CurrentFrameECS) + Send + Sync>;
+type UserInputLogicFunction = Arc<dyn Fn(UserEntity, &ECS, &BoundingBoxTree, &mut Camera, &InputHistory, &CurrentFrameInput, ElapsedTime) -> Vec<EntityChangeInformation> + Send + Sync>;
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct RenderSystemIndex
@@ -23,38 +30,185 @@ pub struct RenderSystemIndex
     pub index: usize
 }
 
-/// Note: if the LogicFunction will issue a DeleteRequest, then LogicFunction must return an EntityChangeInformation
+impl RenderSystemIndex
+{
+    /// Finds the render system registered with the given name. Prefer this over creating an index
+    /// directly, as the index of a render system changes if render systems are reordered. None is
+    /// returned if no render system has the name, including if the render systems have not been created
+    ///
+    /// `name` - the name the render system was registered with
+    pub fn from_name(name: &str) -> Option<RenderSystemIndex>
+    {
+        RENDER_SYSTEM_NAMES.lock().get(name).copied()
+    }
+}
+
+/// Note: if the LogicFunction will issue a DeleteRequest or DespawnRecursive, then LogicFunction must return an EntityChangeInformation
 /// with ONLY that Delete request.
-//#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct EntityLogic
 {
     pub logic: LogicFunction,
 }
 
-#[derive(Copy, Clone)]
+/// Note: the TimeOfImpact is the fraction, from 0 to 1, of the entities' most recent movement at which
+/// they first touched. A time of 0 means the entities were touching before they moved
+#[derive(Clone)]
 pub struct CollisionLogic
 {
     pub logic: CollisionFunction,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct OutOfBoundsLogic
 {
     pub logic: OutOfBoundsFunction
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct UserInputLogic
 {
     pub logic: UserInputLogicFunction
 }
 
+impl EntityLogic
+{
+    /// Creates the logic executed every frame for an entity
+    ///
+    /// `logic` - the function or closure to execute
+    pub fn new<F>(logic: F) -> EntityLogic
+        where F: Fn(SelfEntity, &CurrentFrameECS, &BoundingBoxTree, ElapsedTime) -> Vec<EntityChangeInformation> + Send + Sync + 'static
+    {
+        EntityLogic{ logic: Arc::new(logic) }
+    }
+}
+
+impl CollisionLogic
+{
+    /// Creates the logic executed when an entity collides with another entity
+    ///
+    /// `logic` - the function or closure to execute
+    pub fn new<F>(logic: F) -> CollisionLogic
+        where F: Fn(SelfEntity, OtherEntity, TimeOfImpact, &CurrentFrameECS, &BoundingBoxTree) -> Vec<EntityChangeInformation> + Send + Sync + 'static
+    {
+        CollisionLogic{ logic: Arc::new(logic) }
+    }
+}
+
+impl OutOfBoundsLogic
+{
+    /// Creates the logic executed when an entity leaves the game world
+    ///
+    /// `logic` - the function or closure to execute
+    pub fn new<F>(logic: F) -> OutOfBoundsLogic
+        where F: Fn(SelfEntity, &mut CurrentFrameECS) + Send + Sync + 'static
+    {
+        OutOfBoundsLogic{ logic: Arc::new(logic) }
+    }
+}
+
+impl UserInputLogic
+{
+    /// Creates the logic executed every frame with the input of the user
+    ///
+    /// `logic` - the function or closure to execute
+    pub fn new<F>(logic: F) -> UserInputLogic
+        where F: Fn(UserEntity, &ECS, &BoundingBoxTree, &mut Camera, &InputHistory, &CurrentFrameInput, ElapsedTime) -> Vec<EntityChangeInformation> + Send + Sync + 'static
+    {
+        UserInputLogic{ logic: Arc::new(logic) }
+    }
+}
+
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct IsOutOfBounds;
 
+/// The maximum number of children an entity can have in a transformation hierarchy
+pub const MAX_NUMBER_CHILD_ENTITIES: usize = 16;
+
+/// Note: an entity with a parent has its Position, Rotation and Scale relative to its parent. Its
+/// TransformationMatrix and StaticAABB are in world space, and are updated whenever the parent moves
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct ParentEntity{ pub entity: EntityId }
 
+/// The entities whose transformations are relative to this entity
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct ChildEntities
+{
+    entities: [Option<EntityId>; MAX_NUMBER_CHILD_ENTITIES]
+}
+
+impl ChildEntities
+{
+    /// Creates an empty list of child entities
+    pub fn new() -> ChildEntities
+    {
+        ChildEntities{ entities: [None; MAX_NUMBER_CHILD_ENTITIES] }
+    }
+
+    /// Adds the given entity as a child. False is returned if there is no space for another child
+    ///
+    /// `child` - the entity to add as a child
+    pub fn add(&mut self, child: EntityId) -> bool
+    {
+        if self.contains(child)
+        {
+            return true;
+        }
+
+        match self.entities.iter_mut().find(|x| x.is_none())
+        {
+            Some(i) =>
+                {
+                    *i = Some(child);
+                    true
+                },
+            None => false
+        }
+    }
+
+    /// Removes the given entity from the children, if it is a child
+    ///
+    /// `child` - the entity to remove
+    pub fn remove(&mut self, child: EntityId)
+    {
+        for x in self.entities.iter_mut()
+        {
+            if *x == Some(child)
+            {
+                *x = None;
+            }
+        }
+    }
+
+    /// Checks if the given entity is one of the children
+    ///
+    /// `child` - the entity to check for
+    pub fn contains(&self, child: EntityId) -> bool
+    {
+        self.entities.contains(&Some(child))
+    }
+
+    /// Checks if there are no children
+    pub fn is_empty(&self) -> bool
+    {
+        self.entities.iter().all(|x| x.is_none())
+    }
+
+    /// Get the child entities
+    pub fn get_entities(&self) -> Vec<EntityId>
+    {
+        self.entities.iter().filter_map(|x| *x).collect()
+    }
+}
+
+impl Default for ChildEntities
+{
+    fn default() -> Self
+    {
+        ChildEntities::new()
+    }
+}
+
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct CanCauseCollisions;
 
@@ -62,4 +216,4 @@ pub struct CanCauseCollisions;
 pub struct UserAlwaysCausesCollisions;
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
-pub struct AlwaysExecuteLogic;
\ No newline at end of file
+pub struct AlwaysExecuteLogic;
@@ -0,0 +1,270 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::warn;
+
+// Memory budgets limit how much video memory and memory the engine uses for each kind of data, so that the
+// engine degrades rather than running out of memory on smaller GPUs or during long sessions. The engine keeps
+// track of what it allocates for each category. When a category nears its budget the warning callbacks are
+// called, and once it goes over its budget they are called again. With the Reclaim policy, the engine also
+// frees or avoids memory of that category in a way that keeps the game running:
+//
+// - Textures: texture arrays created while over budget hold only their full resolution level, without mipmaps
+// - Buffers: only warned about, as instance and index buffers have the sizes given by their render systems
+// - History: the recorded history is restarted from the current state of the game, dropping recorded frames
+// - StaticSections: the instance data of static entities in the world sections that were visible the longest
+//   time ago is dropped, and sorted again when the world sections become visible
+//
+// No budgets are set by default. Memory is counted as the engine allocates it, so memory used by the driver
+// or by textures and buffers created outside of render systems is not counted
+
+lazy_static!
+{
+    static ref MEMORY_BUDGETS: Mutex<[Option<MemoryBudget>; NUMBER_MEMORY_CATEGORIES]> = Mutex::new([None; NUMBER_MEMORY_CATEGORIES]);
+    static ref WARNING_LEVELS: Mutex<[WarningLevel; NUMBER_MEMORY_CATEGORIES]> = Mutex::new([WarningLevel::Under; NUMBER_MEMORY_CATEGORIES]);
+    static ref WARNING_CALLBACKS: Mutex<Vec<MemoryWarningCallback>> = Mutex::new(Vec::new());
+}
+
+static MEMORY_USAGE: [AtomicUsize; NUMBER_MEMORY_CATEGORIES] = [AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0)];
+
+const NUMBER_MEMORY_CATEGORIES: usize = 4;
+
+/// Function called when a kind of memory nears or goes over its budget. It is called from the thread that
+/// allocated the memory, so it should return quickly
+pub type MemoryWarningCallback = fn(MemoryWarning);
+
+/// The kinds of memory that budgets can be set for
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MemoryCategory
+{
+    /// Video memory of the texture arrays of render systems
+    Textures,
+    /// Video memory of the instance and index buffers of render systems
+    Buffers,
+    /// Memory of the frames recorded by the history thread
+    History,
+    /// Memory of the instance data of static entities kept for the world sections they are in
+    StaticSections,
+}
+
+/// What the engine does when a kind of memory goes over its budget
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BudgetPolicy
+{
+    /// The warning callbacks are called, but memory is used as it would be without a budget
+    WarnOnly,
+    /// The warning callbacks are called, and the engine frees or avoids memory of the category
+    Reclaim,
+}
+
+/// A limit on how much memory of a kind the engine uses
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryBudget
+{
+    /// The number of bytes the memory should stay under
+    pub limit_bytes: usize,
+    /// Fraction of the limit at which the warning callbacks are first called
+    pub warning_fraction: f32,
+    /// What is done once the limit is passed
+    pub policy: BudgetPolicy,
+}
+
+/// Given to the warning callbacks when a kind of memory nears or goes over its budget
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryWarning
+{
+    pub category: MemoryCategory,
+    /// The number of bytes of the category in use
+    pub used_bytes: usize,
+    /// The budget of the category
+    pub budget: MemoryBudget,
+    /// True if the memory in use is over the limit of the budget, rather than only nearing it
+    pub over_budget: bool,
+}
+
+/// How close to its budget a kind of memory was the last time the warning callbacks were considered
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum WarningLevel
+{
+    Under,
+    Nearing,
+    Over,
+}
+
+impl MemoryCategory
+{
+    /// Get the index of the category into the arrays holding the state of every category
+    fn index(&self) -> usize
+    {
+        match *self
+        {
+            MemoryCategory::Textures => 0,
+            MemoryCategory::Buffers => 1,
+            MemoryCategory::History => 2,
+            MemoryCategory::StaticSections => 3,
+        }
+    }
+}
+
+impl MemoryBudget
+{
+    /// Creates a budget that warns at 90% of its limit and reclaims memory past its limit
+    ///
+    /// `limit_bytes` - the number of bytes the memory should stay under
+    pub fn new(limit_bytes: usize) -> MemoryBudget
+    {
+        MemoryBudget{ limit_bytes, warning_fraction: 0.9, policy: BudgetPolicy::Reclaim }
+    }
+
+    /// Sets the fraction of the limit at which the warning callbacks are first called
+    ///
+    /// `warning_fraction` - fraction of the limit, from 0 to 1
+    pub fn with_warning_fraction(mut self, warning_fraction: f32) -> Self
+    {
+        self.warning_fraction = warning_fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets what is done once the limit is passed
+    ///
+    /// `policy` - the policy of the budget
+    pub fn with_policy(mut self, policy: BudgetPolicy) -> Self
+    {
+        self.policy = policy;
+        self
+    }
+}
+
+/// Sets or removes the budget of a kind of memory. Memory already in use is checked against the new budget
+/// the next time memory of the kind is allocated
+///
+/// `category` - the kind of memory the budget is for
+/// `budget` - the budget of the memory, or None to remove the budget
+pub fn set_memory_budget(category: MemoryCategory, budget: Option<MemoryBudget>)
+{
+    MEMORY_BUDGETS.lock()[category.index()] = budget;
+    WARNING_LEVELS.lock()[category.index()] = WarningLevel::Under;
+}
+
+/// Get the budget of a kind of memory, if it has one
+///
+/// `category` - the kind of memory to get the budget of
+pub fn memory_budget(category: MemoryCategory) -> Option<MemoryBudget>
+{
+    MEMORY_BUDGETS.lock()[category.index()]
+}
+
+/// Get the number of bytes of a kind of memory that the engine is using
+///
+/// `category` - the kind of memory to get the usage of
+pub fn memory_usage(category: MemoryCategory) -> usize
+{
+    MEMORY_USAGE[category.index()].load(Ordering::Relaxed)
+}
+
+/// Adds a function that is called whenever a kind of memory nears or goes over its budget
+///
+/// `callback` - the function to call
+pub fn add_memory_warning_callback(callback: MemoryWarningCallback)
+{
+    WARNING_CALLBACKS.lock().push(callback);
+}
+
+/// Records that the engine allocated memory of a kind
+///
+/// `category` - the kind of memory allocated
+/// `bytes` - the number of bytes allocated
+pub(crate) fn record_allocation(category: MemoryCategory, bytes: usize)
+{
+    MEMORY_USAGE[category.index()].fetch_add(bytes, Ordering::Relaxed);
+    check_budget(category);
+}
+
+/// Records that the engine freed memory of a kind
+///
+/// `category` - the kind of memory freed
+/// `bytes` - the number of bytes freed
+pub(crate) fn record_release(category: MemoryCategory, bytes: usize)
+{
+    let _ = MEMORY_USAGE[category.index()].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| Some(x.saturating_sub(bytes)));
+    check_budget(category);
+}
+
+/// Records how much memory of a kind the engine is using, for memory that is measured rather than counted
+/// as it is allocated
+///
+/// `category` - the kind of memory measured
+/// `bytes` - the number of bytes in use
+pub(crate) fn record_usage(category: MemoryCategory, bytes: usize)
+{
+    MEMORY_USAGE[category.index()].store(bytes, Ordering::Relaxed);
+    check_budget(category);
+}
+
+/// Get the number of bytes of a kind of memory that the engine may still use before it goes over the budget,
+/// if the budget of the kind reclaims memory. None is returned if memory of the kind does not need to be
+/// reclaimed
+///
+/// `category` - the kind of memory to check
+pub(crate) fn reclaimable_room(category: MemoryCategory) -> Option<usize>
+{
+    let budget = memory_budget(category).filter(|x| x.policy == BudgetPolicy::Reclaim)?;
+    Some(budget.limit_bytes.saturating_sub(memory_usage(category)))
+}
+
+/// Checks if a kind of memory is over a budget that reclaims memory
+///
+/// `category` - the kind of memory to check
+pub(crate) fn needs_reclaiming(category: MemoryCategory) -> bool
+{
+    memory_budget(category).is_some_and(|x| x.policy == BudgetPolicy::Reclaim && memory_usage(category) > x.limit_bytes)
+}
+
+/// Calls the warning callbacks if a kind of memory became closer to its budget since they were last called
+///
+/// `category` - the kind of memory to check
+fn check_budget(category: MemoryCategory)
+{
+    let budget = match memory_budget(category)
+    {
+        Some(i) => i,
+        None => return
+    };
+
+    let used_bytes = memory_usage(category);
+
+    let level = if used_bytes > budget.limit_bytes
+    {
+        WarningLevel::Over
+    }
+    else if used_bytes as f64 >= budget.limit_bytes as f64 * budget.warning_fraction as f64
+    {
+        WarningLevel::Nearing
+    }
+    else
+    {
+        WarningLevel::Under
+    };
+
+    let previous_level = std::mem::replace(&mut WARNING_LEVELS.lock()[category.index()], level);
+
+    if level <= previous_level
+    {
+        return;
+    }
+
+    let warning = MemoryWarning{ category, used_bytes, budget, over_budget: level == WarningLevel::Over };
+
+    if warning.over_budget
+    {
+        warn!("{:?} memory is over its budget: {} bytes used of {}", category, used_bytes, budget.limit_bytes);
+    }
+
+    // The callbacks are copied so that they can set budgets or add callbacks themselves
+    let callbacks = WARNING_CALLBACKS.lock().clone();
+
+    for callback in callbacks
+    {
+        callback(warning);
+    }
+}
@@ -0,0 +1,125 @@
+use nalgebra_glm::{TVec2, TVec3, vec2, vec3};
+use crate::exports::camera_object::{Camera, CameraBuilder};
+use crate::exports::logic_components::RenderSystemIndex;
+use crate::flows::shared_constants::REQUESTED_MINIMAP_CHANGES;
+
+/// Specifies how often a minimap is rendered
+#[derive(Copy, Clone, Debug)]
+pub enum MinimapUpdateFrequency
+{
+    /// Only rendered when requested through request_minimap_update
+    OnDemand,
+    /// Rendered once every given number of frames, as well as when requested
+    EveryNumberFrames(u32),
+}
+
+/// Describes a top-down capture of a square region of the game world. The capture is stored in an FBO
+/// with the name of the minimap, accessible to the draw functions of the display render system. The
+/// FBO has one colour attachment holding the capture
+#[derive(Clone, Debug)]
+pub struct MinimapDescription
+{
+    pub name: String,
+    pub render_systems: Vec<RenderSystemIndex>,
+    pub display_render_system: RenderSystemIndex,
+    pub centre: TVec3<f32>,
+    pub half_length: f32,
+    pub update_frequency: MinimapUpdateFrequency,
+}
+
+/// Changes to minimaps requested by the user, to be applied by the render flow
+pub(crate) enum MinimapRequest
+{
+    Add(MinimapDescription),
+    Remove(String),
+    Update(String),
+}
+
+impl MinimapDescription
+{
+    /// Converts a position in the game world to the texture coordinates of that position in the minimap.
+    /// None is returned if the position is outside of the region the minimap captures
+    ///
+    /// `position` - the position in the game world to convert
+    pub fn world_to_map_uv(&self, position: TVec3<f32>) -> Option<TVec2<f32>>
+    {
+        let length = self.half_length * 2.0;
+
+        // The minimap is viewed from above with the negative z-axis pointing to the top of the texture
+        let u = (position.x - (self.centre.x - self.half_length)) / length;
+        let v = ((self.centre.z + self.half_length) - position.z) / length;
+
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v)
+        {
+            return None;
+        }
+
+        Some(vec2(u, v))
+    }
+
+    /// Converts texture coordinates of the minimap to the position in the game world they represent.
+    /// The returned position is at the height of the centre of the minimap
+    ///
+    /// `uv` - the texture coordinates to convert
+    pub fn map_uv_to_world(&self, uv: TVec2<f32>) -> TVec3<f32>
+    {
+        let length = self.half_length * 2.0;
+
+        vec3
+            (
+                self.centre.x - self.half_length + uv.x * length,
+                self.centre.y,
+                self.centre.z + self.half_length - uv.y * length
+            )
+    }
+
+    /// Creates the orthographic camera that looks down onto the region captured by the minimap
+    ///
+    /// `window_dimensions` - the dimensions of the texture the minimap is rendered to
+    pub(crate) fn create_camera(&self, window_dimensions: (i32, i32)) -> Camera
+    {
+        // Distance from the centre of the minimap to its corners. The camera is placed this far above
+        // the centre so that everything within this distance of the centre is captured
+        let reach = self.half_length * std::f32::consts::SQRT_2;
+
+        CameraBuilder::new(window_dimensions)
+            .as_orthographic()
+            .with_position(self.centre + vec3(0.0, reach, 0.0))
+            .with_direction(vec3(0.0, -1.0, 0.0))
+            .with_up_vector(vec3(0.0, 0.0, -1.0))
+            .with_left_ortho(-self.half_length)
+            .with_right_ortho(self.half_length)
+            .with_bottom_ortho(-self.half_length)
+            .with_top_ortho(self.half_length)
+            .with_near_ortho(0.1)
+            .with_far_ortho(reach * 2.0)
+            .with_far_draw_distance(reach * 2.0)
+            .build()
+    }
+}
+
+/// Adds a minimap that is rendered according to its update frequency. It is rendered for the first time
+/// in the next frame
+///
+/// `description` - the minimap to add
+pub fn add_minimap(description: MinimapDescription)
+{
+    debug_assert!(description.half_length > 0.0, "Minimap must capture a region with a positive length");
+    REQUESTED_MINIMAP_CHANGES.lock().push(MinimapRequest::Add(description));
+}
+
+/// Removes the minimap with the given name, along with its FBO
+///
+/// `name` - the name of the minimap to remove
+pub fn remove_minimap<A: AsRef<str>>(name: A)
+{
+    REQUESTED_MINIMAP_CHANGES.lock().push(MinimapRequest::Remove(name.as_ref().to_string()));
+}
+
+/// Requests that the minimap with the given name is rendered in the next frame
+///
+/// `name` - the name of the minimap to render
+pub fn request_minimap_update<A: AsRef<str>>(name: A)
+{
+    REQUESTED_MINIMAP_CHANGES.lock().push(MinimapRequest::Update(name.as_ref().to_string()));
+}
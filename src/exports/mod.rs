@@ -4,5 +4,57 @@ pub mod logic_components;
 pub mod rendering;
 pub mod entity_transformer;
 pub mod light_components;
+pub mod light_baking;
+pub mod environment_lighting;
+pub mod damage_decals;
+pub mod pbr_material;
 pub mod camera_object;
+pub mod camera_controller;
+pub mod camera_animation;
+pub mod camera_path;
+pub mod entity_interpolation;
 pub mod user_focused_entities;
+pub mod ambient_overrides;
+pub mod fog;
+pub mod world_labels;
+pub mod particles;
+pub mod reverb_zones;
+pub mod minimap;
+pub mod user_data;
+pub mod fixed_point;
+pub mod world_precision;
+pub mod panic_policy;
+pub mod frame_statistics;
+pub mod prefab;
+pub mod world_snapshot;
+pub mod model_import;
+pub mod component_reflection;
+pub mod frame_callback;
+pub mod command_line;
+pub mod localization;
+pub mod accessibility;
+pub mod text_shaping;
+pub mod state_hash;
+pub mod benchmark;
+pub mod section_length_tuning;
+pub mod startup_statistics;
+pub mod launch_self_test;
+pub mod instance_params;
+pub mod dissolve;
+pub mod transparency;
+pub mod uniform_inspector;
+pub mod engine_hooks;
+pub mod sprites;
+pub mod section_export;
+pub mod ui_layout;
+pub mod text_labels;
+pub mod debug_draw;
+pub mod world_query;
+pub mod world_section_overlay;
+pub mod memory_budget;
+pub mod gl_debug_output;
+pub mod logging;
+#[cfg(feature = "scripting")]
+pub mod scripted_logic;
+#[cfg(feature = "tooling_ui")]
+pub mod tooling_ui;
\ No newline at end of file
@@ -0,0 +1,311 @@
+use hashbrown::HashMap;
+use nalgebra_glm::{TVec3, TVec4, vec3};
+use crate::helper_things::aabb_helper_functions;
+use crate::models::model_definitions::MeshGeometry;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+/// Function adjusting the geometry of a mesh after the built-in import steps were applied
+pub type MeshImportFunction = fn(&mut ImportedMesh);
+
+/// Function adjusting a material of a model file, such as renaming its textures, before its textures are loaded
+pub type MaterialImportFunction = fn(&mut ImportedMaterial);
+
+// Tools used to create models disagree on the up axis and the unit of length, and often export duplicated
+// vertices. Rather than exporting the models again, the steps below adjust a model when it is loaded. The
+// built-in steps are applied in the order: axis conversion, scale normalization, vertex welding, followed by
+// the user functions in the order they were added
+
+/// Conversion between the up axis of the tool that created a model and the up axis of the engine (+Y)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AxisConversion
+{
+    /// The model was created with +Z being up
+    ZUpToYUp,
+    /// The model was created with +X being up
+    XUpToYUp,
+}
+
+/// Geometry of a mesh being loaded. The vertex attributes that the model file has are stored per vertex,
+/// with the indices referring to the vertices
+pub struct ImportedMesh
+{
+    pub vertices: Vec<TVec3<f32>>,
+    pub normals: Vec<TVec3<f32>>,
+    pub texture_coords: Vec<TVec4<f32>>,
+    pub indices: Vec<u32>,
+}
+
+/// Material of a model file as it was read. Texture names are relative to the texture directory of the
+/// model, and are empty if the material has no texture of that kind
+pub struct ImportedMaterial
+{
+    pub name: String,
+    pub ambient_texture: String,
+    pub diffuse_texture: String,
+    pub specular_texture: String,
+    pub normal_texture: String,
+    pub shininess_texture: String,
+    pub dissolve_texture: String,
+}
+
+/// Adjustments applied to a model when it is loaded from its file
+#[derive(Clone, Default)]
+pub struct ModelImportSettings
+{
+    axis_conversion: Option<AxisConversion>,
+    normalized_size: Option<f32>,
+    weld_tolerance: Option<f32>,
+    mesh_functions: Vec<MeshImportFunction>,
+    material_functions: Vec<MaterialImportFunction>,
+}
+
+impl ModelImportSettings
+{
+    /// Creates settings that load a model as it is stored in its file
+    pub fn new() -> ModelImportSettings
+    {
+        ModelImportSettings::default()
+    }
+
+    /// Rotates the model so that its up axis is the up axis of the engine
+    ///
+    /// `axis_conversion` - the up axis the model was created with
+    pub fn with_axis_conversion(&mut self, axis_conversion: AxisConversion) -> &mut Self
+    {
+        self.axis_conversion = Some(axis_conversion);
+        self
+    }
+
+    /// Uniformly scales the model so that the longest side of its bounding volume has the given length
+    ///
+    /// `size` - the length of the longest side of the scaled model
+    pub fn with_normalized_size(&mut self, size: f32) -> &mut Self
+    {
+        self.normalized_size = Some(size);
+        self
+    }
+
+    /// Merges vertices of a mesh that have the same position, normal and texture coordinates
+    ///
+    /// `tolerance` - the largest difference between components of vertices that are merged
+    pub fn with_vertex_welding(&mut self, tolerance: f32) -> &mut Self
+    {
+        self.weld_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Adds a function that adjusts every mesh of the model
+    ///
+    /// `function` - the function to call for every mesh
+    pub fn with_mesh_function(&mut self, function: MeshImportFunction) -> &mut Self
+    {
+        self.mesh_functions.push(function);
+        self
+    }
+
+    /// Adds a function that adjusts every material of the model before the textures of the materials are loaded
+    ///
+    /// `function` - the function to call for every material
+    pub fn with_material_function(&mut self, function: MaterialImportFunction) -> &mut Self
+    {
+        self.material_functions.push(function);
+        self
+    }
+
+    /// Applies the material functions to the materials read from a model file
+    ///
+    /// `materials` - the materials of the model being loaded
+    pub(crate) fn apply_to_materials(&self, materials: &mut Vec<tobj::Material>)
+    {
+        if self.material_functions.is_empty()
+        {
+            return;
+        }
+
+        for material in materials
+        {
+            let mut imported_material = ImportedMaterial
+            {
+                name: std::mem::take(&mut material.name),
+                ambient_texture: std::mem::take(&mut material.ambient_texture),
+                diffuse_texture: std::mem::take(&mut material.diffuse_texture),
+                specular_texture: std::mem::take(&mut material.specular_texture),
+                normal_texture: std::mem::take(&mut material.normal_texture),
+                shininess_texture: std::mem::take(&mut material.shininess_texture),
+                dissolve_texture: std::mem::take(&mut material.dissolve_texture),
+            };
+
+            for function in &self.material_functions
+            {
+                function(&mut imported_material);
+            }
+
+            material.name = imported_material.name;
+            material.ambient_texture = imported_material.ambient_texture;
+            material.diffuse_texture = imported_material.diffuse_texture;
+            material.specular_texture = imported_material.specular_texture;
+            material.normal_texture = imported_material.normal_texture;
+            material.shininess_texture = imported_material.shininess_texture;
+            material.dissolve_texture = imported_material.dissolve_texture;
+        }
+    }
+
+    /// Applies all of the adjustments to the meshes of a model read from a model file
+    ///
+    /// `meshes` - the meshes of the model being loaded
+    pub(crate) fn apply_to_meshes(&self, meshes: &mut [MeshGeometry])
+    {
+        if let Some(axis_conversion) = self.axis_conversion
+        {
+            for mesh in meshes.iter_mut()
+            {
+                mesh.vertices.iter_mut().for_each(|x| *x = ModelImportSettings::convert_axis(*x, axis_conversion));
+                mesh.normals.iter_mut().for_each(|x| *x = ModelImportSettings::convert_axis(*x, axis_conversion));
+            }
+        }
+
+        if let Some(size) = self.normalized_size
+        {
+            ModelImportSettings::normalize_size(meshes, size);
+        }
+
+        if let Some(tolerance) = self.weld_tolerance
+        {
+            meshes.iter_mut().for_each(|x| ModelImportSettings::weld_vertices(x, tolerance));
+        }
+
+        if self.mesh_functions.is_empty()
+        {
+            return;
+        }
+
+        for mesh in meshes.iter_mut()
+        {
+            let mut imported_mesh = ImportedMesh
+            {
+                vertices: std::mem::take(&mut mesh.vertices),
+                normals: std::mem::take(&mut mesh.normals),
+                texture_coords: std::mem::take(&mut mesh.texture_coords),
+                indices: std::mem::take(&mut mesh.indices),
+            };
+
+            for function in &self.mesh_functions
+            {
+                function(&mut imported_mesh);
+            }
+
+            // Every vertex of a mesh uses the same textures, so vertices added by the functions use them as well
+            if let Some(texture_location) = mesh.texture_location.first().cloned()
+            {
+                mesh.texture_location.resize(imported_mesh.vertices.len(), texture_location);
+            }
+
+            mesh.vertices = imported_mesh.vertices;
+            mesh.normals = imported_mesh.normals;
+            mesh.texture_coords = imported_mesh.texture_coords;
+            mesh.indices = imported_mesh.indices;
+        }
+    }
+
+    /// Rotates a vector from the up axis of the model to the up axis of the engine. The rotations keep the
+    /// handedness of the model, so that the winding order of its triangles is unchanged
+    ///
+    /// `vector` - the position or normal to rotate
+    /// `axis_conversion` - the up axis the model was created with
+    fn convert_axis(vector: TVec3<f32>, axis_conversion: AxisConversion) -> TVec3<f32>
+    {
+        match axis_conversion
+        {
+            AxisConversion::ZUpToYUp => vec3(vector.x, vector.z, -vector.y),
+            AxisConversion::XUpToYUp => vec3(-vector.y, vector.x, vector.z),
+        }
+    }
+
+    /// Scales all meshes so that the longest side of the bounding volume of the model has the given length
+    ///
+    /// `meshes` - the meshes of the model
+    /// `size` - the length of the longest side of the scaled model
+    fn normalize_size(meshes: &mut [MeshGeometry], size: f32)
+    {
+        let model_aabb = meshes.iter()
+            .fold(StaticAABB::point_aabb(), |aabb, x| aabb.combine_aabb(&aabb_helper_functions::calculate_aabb(&x.vertices)));
+
+        let longest_side = model_aabb.x_range.length().max(model_aabb.y_range.length()).max(model_aabb.z_range.length());
+
+        if longest_side <= 0.0
+        {
+            return;
+        }
+
+        let scale = size / longest_side;
+
+        for mesh in meshes.iter_mut()
+        {
+            mesh.vertices.iter_mut().for_each(|x| *x *= scale);
+        }
+    }
+
+    /// Merges the vertices of a mesh whose attributes are all within the tolerance of each other, and
+    /// updates the indices to refer to the merged vertices
+    ///
+    /// `mesh` - the mesh whose vertices are merged
+    /// `tolerance` - the largest difference between components of vertices that are merged
+    fn weld_vertices(mesh: &mut MeshGeometry, tolerance: f32)
+    {
+        // Vertices are snapped to a grid of the tolerance, so vertices within the tolerance of each other
+        // usually share a grid cell. Only vertices that share a cell are merged, which keeps welding linear
+        let cell_size = tolerance.max(f32::EPSILON);
+        let snap = |value: f32| (value / cell_size).round() as i64;
+
+        let mut welded_indexes: HashMap<Vec<i64>, u32> = HashMap::default();
+        let mut remapped_indexes = Vec::with_capacity(mesh.vertices.len());
+        let mut kept_vertices = Vec::new();
+
+        for index in 0..mesh.vertices.len()
+        {
+            let mut key = mesh.vertices[index].iter().map(|x| snap(*x)).collect::<Vec<i64>>();
+
+            if let Some(normal) = mesh.normals.get(index)
+            {
+                key.extend(normal.iter().map(|x| snap(*x)));
+            }
+
+            if let Some(texture_coord) = mesh.texture_coords.get(index)
+            {
+                key.extend(texture_coord.iter().map(|x| snap(*x)));
+            }
+
+            let welded_index = *welded_indexes.entry(key).or_insert_with(||
+                {
+                    kept_vertices.push(index);
+                    (kept_vertices.len() - 1) as u32
+                });
+
+            remapped_indexes.push(welded_index);
+        }
+
+        if kept_vertices.len() == mesh.vertices.len()
+        {
+            return;
+        }
+
+        mesh.indices.iter_mut().for_each(|x| *x = remapped_indexes[*x as usize]);
+
+        mesh.vertices = kept_vertices.iter().map(|x| mesh.vertices[*x]).collect();
+
+        if !mesh.normals.is_empty()
+        {
+            mesh.normals = kept_vertices.iter().filter_map(|x| mesh.normals.get(*x).copied()).collect();
+        }
+
+        if !mesh.texture_coords.is_empty()
+        {
+            mesh.texture_coords = kept_vertices.iter().filter_map(|x| mesh.texture_coords.get(*x).copied()).collect();
+        }
+
+        if !mesh.texture_location.is_empty()
+        {
+            mesh.texture_location = kept_vertices.iter().filter_map(|x| mesh.texture_location.get(*x).cloned()).collect();
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::ops::{AddAssign, Mul};
 
 use nalgebra_glm::{TVec3, TMat4x4, vec3};
 use serde::{Serialize, Deserialize};
+use crate::exports::fixed_point::FixedVec3;
 
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct HasMoved;
@@ -38,6 +39,39 @@ pub struct DynamicObject;
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct TransformationMatrix(TMat4x4<f32>);
 
+/// The transformation matrix an entity had before its most recent change in position, rotation or scale.
+/// Used to find the path an entity took so that collisions are not missed for fast moving entities
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct PreviousTransformationMatrix(TMat4x4<f32>);
+
+// *** Fixed point movement ***
+
+// Entities with a FixedPosition are moved using fixed point arithmetic so that their movement is identical
+// on every machine. Their Position is kept in sync with the fixed point position for rendering, and should
+// not be written directly. An entity should use either the fixed point or the floating point kinematic
+// components, not both
+
+/// Position of an entity in fixed point, along with its position before its most recent movement
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct FixedPosition(FixedVec3, FixedVec3);
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct FixedVelocity(FixedVec3);
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct FixedAcceleration(FixedVec3);
+
+// *** Double precision positions ***
+
+// Entities with a WorldPosition keep their position in double precision, for worlds too large for single
+// precision positions. Their Velocity moves the WorldPosition, and their Position is kept in sync with it
+// for world sections and collisions, so it should not be written directly. The WorldPosition is used when
+// drawing the entity relative to the render origin; see world_precision
+
+/// Position of an entity in double precision, along with its position before its most recent movement
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct WorldPosition(TVec3<f64>, TVec3<f64>);
+
 impl Default for Rotation
 {
     fn default() -> Self
@@ -207,6 +241,115 @@ impl TransformationMatrix
     }
 }
 
+impl PreviousTransformationMatrix
+{
+    pub fn new(matrix: TMat4x4<f32>) -> PreviousTransformationMatrix
+    {
+        for x in 0..16
+        {
+            debug_assert_ne!(matrix[x], f32::NAN, "Given matrix has a NaN number");
+        }
+        PreviousTransformationMatrix(matrix)
+    }
+
+    pub fn get_matrix(&self) -> TMat4x4<f32>
+    {
+        self.0
+    }
+}
+
+impl FixedPosition
+{
+    pub fn new(position: FixedVec3) -> FixedPosition
+    {
+        FixedPosition(position, position)
+    }
+
+    /// Creates the position reached by moving from this position by the given amount
+    ///
+    /// `displacement` - the amount to move by
+    pub fn moved_by(&self, displacement: FixedVec3) -> FixedPosition
+    {
+        FixedPosition(self.0 + displacement, self.0)
+    }
+
+    pub fn get_position(&self) -> FixedVec3
+    {
+        self.0
+    }
+
+    /// Get the amount the entity moved by in its most recent movement
+    pub fn get_displacement(&self) -> FixedVec3
+    {
+        self.0 - self.1
+    }
+
+    /// Converts the fixed point position into the floating point position used for rendering
+    pub fn to_position(&self) -> Position
+    {
+        Position::new(self.0.to_vec3())
+    }
+}
+
+impl WorldPosition
+{
+    pub fn new(position: TVec3<f64>) -> WorldPosition
+    {
+        WorldPosition(position, position)
+    }
+
+    /// Creates the position reached by moving from this position by the given amount
+    ///
+    /// `displacement` - the amount to move by
+    pub fn moved_by(&self, displacement: TVec3<f64>) -> WorldPosition
+    {
+        WorldPosition(self.0 + displacement, self.0)
+    }
+
+    pub fn get_position(&self) -> TVec3<f64>
+    {
+        self.0
+    }
+
+    /// Get the position of the entity before its most recent movement
+    pub fn get_previous_position(&self) -> TVec3<f64>
+    {
+        self.1
+    }
+
+    /// Converts the double precision position into the single precision position used by world sections
+    pub fn to_position(&self) -> Position
+    {
+        Position::new(vec3(self.0.x as f32, self.0.y as f32, self.0.z as f32))
+    }
+}
+
+impl FixedVelocity
+{
+    pub fn new(velocity: FixedVec3) -> FixedVelocity
+    {
+        FixedVelocity(velocity)
+    }
+
+    pub fn get_velocity(&self) -> FixedVec3
+    {
+        self.0
+    }
+}
+
+impl FixedAcceleration
+{
+    pub fn new(acceleration: FixedVec3) -> FixedAcceleration
+    {
+        FixedAcceleration(acceleration)
+    }
+
+    pub fn get_acceleration(&self) -> FixedVec3
+    {
+        self.0
+    }
+}
+
 macro_rules! implement_add_assign {
     ($target: ty, $($apply_to: ty),+) =>
     {
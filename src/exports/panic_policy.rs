@@ -0,0 +1,91 @@
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+lazy_static!
+{
+    static ref PANIC_MESSAGES: Mutex<HashMap<EngineThread, String>> = Mutex::new(HashMap::default());
+}
+
+/// The threads of the engine that a panic policy can be specified for
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum EngineThread
+{
+    History,
+    Render,
+}
+
+impl EngineThread
+{
+    /// Get the name the thread is spawned with, which identifies the thread a panic happened in
+    pub(crate) fn thread_name(&self) -> &'static str
+    {
+        match *self
+        {
+            EngineThread::History => "history",
+            EngineThread::Render => "render",
+        }
+    }
+
+    /// Get the engine thread spawned with the given name, if any
+    ///
+    /// `name` - the name of the thread
+    pub(crate) fn from_thread_name(name: &str) -> Option<EngineThread>
+    {
+        [EngineThread::History, EngineThread::Render].iter().copied().find(|x| x.thread_name() == name)
+    }
+}
+
+/// The action taken after a thread of the engine panics
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PanicAction
+{
+    /// The program exits
+    Exit,
+    /// The thread is started again. The history thread resumes recording from a snapshot of the current
+    /// state of the game. Restarting is currently only supported for the history thread; for other threads
+    /// this is the same as exiting
+    RestartThread,
+}
+
+/// Function that decides what to do after a thread of the engine panicked, given the panic message
+pub type PanicCallback = fn(EngineThread, &str) -> PanicAction;
+
+/// Specifies how the engine responds to one of its threads panicking
+#[derive(Copy, Clone)]
+pub enum PanicPolicy
+{
+    Exit,
+    /// Only supported by the history thread; the render thread exits instead
+    RestartThread,
+    Escalate(PanicCallback),
+}
+
+impl PanicPolicy
+{
+    /// Determines the action to take after the given thread panicked
+    ///
+    /// `thread` - the thread that panicked
+    pub(crate) fn resolve(&self, thread: EngineThread) -> PanicAction
+    {
+        match *self
+        {
+            PanicPolicy::Exit => PanicAction::Exit,
+            PanicPolicy::RestartThread => PanicAction::RestartThread,
+            PanicPolicy::Escalate(callback) =>
+                {
+                    let message = PANIC_MESSAGES.lock().remove(&thread).unwrap_or_default();
+                    callback(thread, &message)
+                }
+        }
+    }
+}
+
+/// Stores the message of a panic so that it can be passed to a panic callback
+///
+/// `thread` - the thread that panicked
+/// `message` - the description of the panic
+pub(crate) fn record_panic(thread: EngineThread, message: String)
+{
+    PANIC_MESSAGES.lock().insert(thread, message);
+}
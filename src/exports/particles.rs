@@ -0,0 +1,174 @@
+use nalgebra_glm::{TVec3, TVec4, vec3, vec4};
+use serde::{Serialize, Deserialize};
+use tracing::warn;
+
+// Particles are simulated and drawn entirely on the GPU. Every frame, the emitters of entities with a
+// ParticleEmitter are uploaded along with how many particles each one spawns that frame; a compute shader
+// then spawns and moves the particles, which are drawn over the frame as quads facing the camera after
+// the render systems drawing to the window have finished. Particles are hidden behind geometry using the
+// world positions of the frame, the same positions used by fog and world labels
+
+/// Most keys a particle curve can have
+pub const MAX_CURVE_KEYS: usize = 4;
+
+/// A value that changes over the lifetime of a particle, linearly interpolated between keys. The time of
+/// each key is the fraction of the lifetime of the particle, from 0 to 1
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParticleCurve
+{
+    times: [f32; MAX_CURVE_KEYS],
+    keys: [TVec4<f32>; MAX_CURVE_KEYS],
+    number_keys: usize,
+}
+
+impl ParticleCurve
+{
+    /// Creates a curve that has the same value over the lifetime of a particle
+    ///
+    /// `value` - the value of the curve
+    pub fn constant(value: TVec4<f32>) -> ParticleCurve
+    {
+        ParticleCurve{ times: [0.0; MAX_CURVE_KEYS], keys: [value; MAX_CURVE_KEYS], number_keys: 1 }
+    }
+
+    /// Creates a curve that changes from one value at the start of the lifetime of a particle to another
+    /// at its end
+    ///
+    /// `start` - the value when the particle spawns
+    /// `end` - the value when the particle dies
+    pub fn linear(start: TVec4<f32>, end: TVec4<f32>) -> ParticleCurve
+    {
+        ParticleCurve::constant(start).with_key(1.0, end)
+    }
+
+    /// Adds a key to the curve, replacing any key at the same time. If the curve already has the most
+    /// keys it can have, the key is not added
+    ///
+    /// `time` - the fraction of the lifetime of a particle the key is at, from 0 to 1
+    /// `value` - the value of the curve at the key
+    pub fn with_key(mut self, time: f32, value: TVec4<f32>) -> ParticleCurve
+    {
+        let time = time.clamp(0.0, 1.0);
+
+        if let Some(index) = self.times[..self.number_keys].iter().position(|x| *x == time)
+        {
+            self.keys[index] = value;
+            return self;
+        }
+
+        if self.number_keys == MAX_CURVE_KEYS
+        {
+            warn!("A particle curve can have at most {} keys; the key at {} is not added", MAX_CURVE_KEYS, time);
+            return self;
+        }
+
+        let index = self.times[..self.number_keys].iter().position(|x| *x > time).unwrap_or(self.number_keys);
+
+        for x in (index..self.number_keys).rev()
+        {
+            self.times[x + 1] = self.times[x];
+            self.keys[x + 1] = self.keys[x];
+        }
+
+        self.times[index] = time;
+        self.keys[index] = value;
+        self.number_keys += 1;
+        self
+    }
+
+    /// Finds the value of the curve at the given fraction of the lifetime of a particle
+    ///
+    /// `time` - the fraction of the lifetime of the particle, from 0 to 1
+    pub fn evaluate(&self, time: f32) -> TVec4<f32>
+    {
+        let (times, keys) = self.padded_keys();
+
+        if time <= times[0]
+        {
+            return keys[0];
+        }
+
+        for x in 0..MAX_CURVE_KEYS - 1
+        {
+            if time <= times[x + 1]
+            {
+                let span = (times[x + 1] - times[x]).max(0.0001);
+                return nalgebra_glm::lerp(&keys[x], &keys[x + 1], (time - times[x]) / span);
+            }
+        }
+
+        keys[MAX_CURVE_KEYS - 1]
+    }
+
+    /// Get the keys of the curve with the unused keys set to the last key, as the GPU always reads every key
+    pub(crate) fn padded_keys(&self) -> ([f32; MAX_CURVE_KEYS], [TVec4<f32>; MAX_CURVE_KEYS])
+    {
+        let mut times = self.times;
+        let mut keys = self.keys;
+        let last_key = self.number_keys.max(1) - 1;
+
+        for x in self.number_keys..MAX_CURVE_KEYS
+        {
+            times[x] = 1.0_f32.max(times[last_key]);
+            keys[x] = keys[last_key];
+        }
+
+        (times, keys)
+    }
+}
+
+/// Makes an entity emit particles from its position. The entity must have a position
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParticleEmitter
+{
+    /// Number of particles spawned per second
+    pub rate: f32,
+    /// Seconds a particle exists for after it is spawned
+    pub lifetime: f32,
+    /// Most particles of the emitter that exist at once. Once this many exist, new particles replace the oldest
+    pub max_particles: u32,
+    /// Velocity of particles over their lifetime, in the xyz components
+    pub velocity: ParticleCurve,
+    /// Largest speed randomly added to the velocity of each particle, in a random direction chosen when
+    /// the particle spawns
+    pub velocity_spread: f32,
+    /// Colour and opacity of particles over their lifetime
+    pub colour: ParticleCurve,
+    /// Width of particles in the world when they spawn and when they die
+    pub size: (f32, f32),
+    /// If true, particles add their colour to the frame instead of blending over it, such as for fire and sparks
+    pub additive: bool,
+    /// If false, no particles are spawned. Particles already spawned live out their lifetime
+    pub emitting: bool,
+}
+
+impl ParticleEmitter
+{
+    /// Creates an emitter of white particles rising slowly and fading out over two seconds
+    ///
+    /// `rate` - number of particles spawned per second
+    pub fn new(rate: f32) -> ParticleEmitter
+    {
+        ParticleEmitter
+        {
+            rate,
+            lifetime: 2.0,
+            max_particles: 256,
+            velocity: ParticleCurve::constant(vec4(0.0, 1.0, 0.0, 0.0)),
+            velocity_spread: 0.25,
+            colour: ParticleCurve::linear(vec4(1.0, 1.0, 1.0, 1.0), vec4(1.0, 1.0, 1.0, 0.0)),
+            size: (0.1, 0.1),
+            additive: false,
+            emitting: true,
+        }
+    }
+
+    /// Get the velocity of a particle, without the random spread, at the given fraction of its lifetime
+    ///
+    /// `time` - the fraction of the lifetime of the particle, from 0 to 1
+    pub fn velocity_at(&self, time: f32) -> TVec3<f32>
+    {
+        let velocity = self.velocity.evaluate(time);
+        vec3(velocity.x, velocity.y, velocity.z)
+    }
+}
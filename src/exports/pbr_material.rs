@@ -0,0 +1,106 @@
+use nalgebra_glm::{TVec4, vec4};
+use serde::{Deserialize, Serialize};
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+
+// Physically based materials describe a surface by how metallic and how rough it is rather than by
+// ad-hoc diffuse and specular colours, so the same material looks correct under any lighting. The maps of a
+// material are loaded with its model: the albedo map is the diffuse map of the .mtl file, the normal map its
+// normal map, the metallic and roughness maps are given with the map_Pm and map_Pr statements, and the
+// ambient occlusion map with map_Ka. Maps are only loaded into render systems created with the PBR shaders,
+// and every map of a material should be the same size as its albedo map as they share texture coordinates.
+// The PbrMaterial component of an entity scales the maps of its model, so entities sharing a model can look
+// different. Static entities only show a changed material once their world section is next sorted, as the
+// instance data of static entities is not rewritten every frame
+
+/// Scales the material maps of the model of an entity. Entities without this component use the default
+/// material: a dielectric that uses the albedo, normal, roughness and occlusion maps of its model as they are
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PbrMaterial
+{
+    /// Colour multiplied with the albedo map. If the model has no albedo map, this is the colour of the
+    /// surface
+    pub albedo: TVec4<f32>,
+    /// How metallic the surface is, from 0 to 1. Multiplied with the metallic map, so materials with a
+    /// metallic map usually set this to 1
+    pub metallic: f32,
+    /// How rough the surface is, from 0 to 1. Multiplied with the roughness map
+    pub roughness: f32,
+    /// How strongly the normal map bends the surface normal. 0 ignores the normal map
+    pub normal_strength: f32,
+    /// How strongly the ambient occlusion map darkens the ambient light, from 0 to 1
+    pub occlusion_strength: f32,
+}
+
+impl Default for PbrMaterial
+{
+    fn default() -> PbrMaterial
+    {
+        PbrMaterial
+        {
+            albedo: vec4(1.0, 1.0, 1.0, 1.0),
+            metallic: 0.0,
+            roughness: 1.0,
+            normal_strength: 1.0,
+            occlusion_strength: 1.0,
+        }
+    }
+}
+
+/// The per-instance data holding the albedo of a PbrMaterial
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PbrAlbedo([f32; 4]);
+
+/// The per-instance data holding the metallic, roughness, normal strength and occlusion strength of a
+/// PbrMaterial
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PbrSurface([f32; 4]);
+
+impl PbrMaterial
+{
+    /// Creates a material of a single colour, for models without material maps
+    ///
+    /// `albedo` - the colour of the surface
+    /// `metallic` - how metallic the surface is, from 0 to 1
+    /// `roughness` - how rough the surface is, from 0 to 1
+    pub fn new(albedo: TVec4<f32>, metallic: f32, roughness: f32) -> PbrMaterial
+    {
+        PbrMaterial{ albedo, metallic, roughness, ..PbrMaterial::default() }
+    }
+
+    fn albedo_instance_data(&self) -> PbrAlbedo
+    {
+        PbrAlbedo([self.albedo.x, self.albedo.y, self.albedo.z, self.albedo.w])
+    }
+
+    fn surface_instance_data(&self) -> PbrSurface
+    {
+        PbrSurface
+            ([
+                self.metallic.clamp(0.0, 1.0),
+                self.roughness.clamp(0.0, 1.0),
+                self.normal_strength.max(0.0),
+                self.occlusion_strength.clamp(0.0, 1.0)
+            ])
+    }
+}
+
+/// Gets the albedo instance data of an entity
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to get the instance data of
+pub(crate) fn pbr_albedo(ecs: &ECS, entity_id: EntityId) -> PbrAlbedo
+{
+    ecs.get_copy::<PbrMaterial>(entity_id).unwrap_or_default().albedo_instance_data()
+}
+
+/// Gets the surface instance data of an entity
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to get the instance data of
+pub(crate) fn pbr_surface(ecs: &ECS, entity_id: EntityId) -> PbrSurface
+{
+    ecs.get_copy::<PbrMaterial>(entity_id).unwrap_or_default().surface_instance_data()
+}
@@ -0,0 +1,217 @@
+use std::any::TypeId;
+use std::mem::size_of;
+use nalgebra_glm::TVec3;
+use serde::{Serialize, Deserialize};
+use crate::exports::entity_transformer::EntityTransformationBuilder;
+use crate::exports::light_components::FindLightType;
+use crate::exports::movement_components::{Position, Rotation, Scale};
+use crate::models::model_definitions::ModelId;
+use crate::objects::ecs::{ECS, TypeIdentifier};
+use crate::objects::entity_change_request::{EntityChangeInformation, EntityChangeRequest};
+use crate::objects::entity_id::EntityId;
+use crate::world::bounding_box_tree_v2::BoundingBoxTree;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+// A prefab spawned at runtime becomes an EntityChangeInformation::AddEntity, so spawning it is recorded
+// in the history like any other created entity. The AABB of the spawned entity is calculated from the
+// AABB of the prefab's model, transformed by the transform the prefab is spawned with
+
+/// Template of an entity, holding the model, entity type and components every entity spawned from the
+/// template starts with. The entity type determines the logic that is executed for the spawned entities
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Prefab
+{
+    model_name: String,
+    entity_type: TypeIdentifier,
+    is_static: bool,
+    can_cause_collision: bool,
+    light_type: Option<FindLightType>,
+    components: Vec<(TypeIdentifier, Vec<u8>)>,
+}
+
+/// Placement of an entity spawned from a prefab
+#[derive(Copy, Clone)]
+pub struct PrefabTransform
+{
+    pub translation: Position,
+    pub rotation: Option<Rotation>,
+    pub scale: Option<Scale>,
+}
+
+/// Instances of a prefab to spawn when the game is loaded
+pub struct UserLoadPrefabInstances
+{
+    pub prefab: Prefab,
+    pub transforms: Vec<PrefabTransform>,
+}
+
+impl Prefab
+{
+    /// Creates a prefab of a dynamic entity without any additional components
+    ///
+    /// `model_name` - the name of the model the spawned entities are instances of
+    /// `entity_type` - the type of the spawned entities
+    pub fn new<T: Into<String>>(model_name: T, entity_type: TypeIdentifier) -> Prefab
+    {
+        Prefab
+        {
+            model_name: model_name.into(),
+            entity_type,
+            is_static: false,
+            can_cause_collision: false,
+            light_type: None,
+            components: Vec::new(),
+        }
+    }
+
+    /// Adds a component that spawned entities start with. Adding a component of a type the prefab already
+    /// has replaces the previous value. The component type must be registered before the prefab is spawned
+    ///
+    /// `value` - the initial value of the component
+    pub fn with_component<T: 'static + Copy>(&mut self, value: T) -> &mut Self
+    {
+        let type_id = TypeIdentifier::from(TypeId::of::<T>());
+        let mut serialized_value: Vec<u8> = vec![0; size_of::<T>()];
+
+        unsafe
+            {
+                std::ptr::write_unaligned(serialized_value.as_mut_ptr() as *mut T, value);
+            }
+
+        self.components.retain(|(x, _)| *x != type_id);
+        self.components.push((type_id, serialized_value));
+        self
+    }
+
+    /// Adds a component, already converted into the bytes stored in the ECS, that spawned entities start with.
+    /// Adding a component of a type the prefab already has replaces the previous value
+    ///
+    /// `type_id` - the type of the component
+    /// `serialized_value` - the bytes of the initial value of the component
+    pub(crate) fn with_serialized_component(&mut self, type_id: TypeIdentifier, serialized_value: Vec<u8>) -> &mut Self
+    {
+        self.components.retain(|(x, _)| *x != type_id);
+        self.components.push((type_id, serialized_value));
+        self
+    }
+
+    /// Specifies if spawned entities are initially static
+    ///
+    /// `is_static` - true if the entities do not move when spawned
+    pub fn with_static(&mut self, is_static: bool) -> &mut Self
+    {
+        self.is_static = is_static;
+        self
+    }
+
+    /// Specifies that spawned entities can cause collisions with other entities
+    pub fn with_collisions(&mut self) -> &mut Self
+    {
+        self.can_cause_collision = true;
+        self
+    }
+
+    /// Specifies that spawned entities are lights. The light component itself is added through with_component
+    ///
+    /// `light_type` - the type of light the spawned entities are
+    pub fn with_light(&mut self, light_type: FindLightType) -> &mut Self
+    {
+        self.light_type = Some(light_type);
+        self
+    }
+
+    /// Get the name of the model the spawned entities are instances of
+    pub fn get_model_name(&self) -> &String
+    {
+        &self.model_name
+    }
+
+    /// Creates the change spawning an entity from this prefab. Return the change from a logic function
+    /// to have the entity created at the end of the frame
+    ///
+    /// `transform` - the placement of the spawned entity
+    pub fn spawn(&self, transform: &PrefabTransform) -> EntityChangeInformation
+    {
+        let entity_id = ECS::get_temporary_entity_id();
+        let mut component_request = EntityChangeRequest::new(entity_id);
+        component_request.type_id = self.components.clone();
+
+        EntityChangeInformation::AddEntity(self.model_name.clone(), self.entity_type, self.create_transformation(entity_id, transform), component_request)
+    }
+
+    /// Writes all of the components of this prefab to an already created entity and adds the entity to
+    /// the bounding box tree. The instance of the model must be registered separately
+    ///
+    /// `entity_id` - the entity being spawned
+    /// `transform` - the placement of the spawned entity
+    /// `model_id` - the ID of the prefab's model
+    /// `original_aabb` - the AABB of the prefab's model
+    /// `ecs` - the ECS holding the entity
+    /// `bounding_tree` - the tree the entity is added to
+    pub(crate) fn instantiate(&self, entity_id: EntityId, transform: &PrefabTransform, model_id: ModelId, original_aabb: StaticAABB, ecs: &mut ECS, bounding_tree: &mut BoundingBoxTree)
+    {
+        self.create_transformation(entity_id, transform).apply_choices(original_aabb, ecs, bounding_tree);
+
+        ecs.write_component::<ModelId>(entity_id, model_id);
+        ecs.write_entity_type(entity_id, self.entity_type);
+
+        for (type_id, serialized_value) in &self.components
+        {
+            unsafe
+                {
+                    ecs.write_component_serialized(entity_id, *type_id, serialized_value);
+                }
+        }
+    }
+
+    /// Creates the builder applying the transform and physical properties of this prefab to an entity
+    ///
+    /// `entity_id` - the entity being spawned
+    /// `transform` - the placement of the spawned entity
+    fn create_transformation(&self, entity_id: EntityId, transform: &PrefabTransform) -> EntityTransformationBuilder
+    {
+        let mut builder = EntityTransformationBuilder::new(entity_id, self.is_static, self.light_type, self.can_cause_collision);
+        builder.with_translation(transform.translation);
+
+        if let Some(rotation) = transform.rotation
+        {
+            builder.with_rotation(rotation);
+        }
+
+        if let Some(scale) = transform.scale
+        {
+            builder.with_scale(scale);
+        }
+
+        builder
+    }
+}
+
+impl PrefabTransform
+{
+    /// Creates a transform placing an entity at the given position without rotating or scaling it
+    ///
+    /// `position` - the position of the spawned entity
+    pub fn at(position: TVec3<f32>) -> PrefabTransform
+    {
+        PrefabTransform{ translation: Position::new(position), rotation: None, scale: None }
+    }
+
+    /// Rotates the spawned entity
+    ///
+    /// `rotation` - the rotation of the spawned entity
+    pub fn with_rotation(&mut self, rotation: Rotation) -> &mut Self
+    {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    /// Scales the spawned entity
+    ///
+    /// `scale` - the scale of the spawned entity
+    pub fn with_scale(&mut self, scale: Scale) -> &mut Self
+    {
+        self.scale = Some(scale);
+        self
+    }
+}
@@ -3,20 +3,34 @@ use std::ffi::{c_void, CString};
 use std::fmt::Debug;
 use std::mem::size_of;
 use hashbrown::HashMap;
+use lazy_static::lazy_static;
 use nalgebra_glm::{TMat4x4, TVec3, TVec4};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 use crate::exports::camera_object::Camera;
+use crate::exports::debug_draw::{debug_draw, DebugDraw};
+use crate::exports::frame_statistics::{FrameStatistics, latest_frame_statistics, record_draw_calls, record_instances_rendered};
+use crate::exports::uniform_inspector::write_cpu_value;
 use crate::flows::render_flow::{InstanceRange, ModelRenderingInformation};
 use crate::models::model_definitions::ModelId;
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
 use crate::render_components::frame_buffer::FBO;
+use crate::render_components::gpu_level_of_view_pass::IndirectDraws;
+use crate::render_components::indirect_draw_buffer::{DrawElementsIndirectCommand, IndirectDrawBuffer};
 use crate::render_components::mapped_buffer::MappedBuffer;
 use crate::render_system::render_pass_resources::UniformBufferInformation;
 use crate::render_system::render_system::{LevelOfViews, ModelNameLookupResult, UniformECS};
+use crate::render_system::system_information::TextureFormat;
 use crate::window::input_state::InputHistory;
 use crate::world::bounding_box_tree_v2::BoundingBoxTree;
 
+lazy_static!
+{
+    static ref POST_PROCESS_STACK: Mutex<Option<PostProcessStack>> = Mutex::new(None);
+}
+
 /// Holds information regarding what model to draw, and more specifically what instances of that
 /// model to draw
 pub struct ModelDrawCommand<A: AsRef<str>>
@@ -34,6 +48,205 @@ pub struct LevelOfView
     pub max_distance: f32,
 }
 
+/// How shadow maps are filtered when finding how much of a fragment is in shadow. Softer filtering
+/// hides the blocky edges of shadows at the cost of more shadow map reads per fragment
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShadowQuality
+{
+    /// A single read of the shadow map, giving hard edged shadows
+    Hard,
+    /// Percentage closer filtering over the 3x3 texels around the fragment
+    Pcf3x3,
+    /// Percentage closer filtering over the 5x5 texels around the fragment
+    Pcf5x5,
+    /// Percentage closer filtering with 16 reads spread over a disc, which gives smoother edges than a
+    /// grid with fewer reads
+    Poisson,
+    /// Percentage closer soft shadows: shadow edges become softer the further the shadow is from the
+    /// object casting it. The light size is the width of the light as a fraction of the shadow map
+    Pcss { light_size: f32 },
+}
+
+/// How screen space reflections are found. After the second render pass lights the frame, a ray is reflected
+/// off every lit fragment and marched through the positions of the G-Buffer; where it passes just behind
+/// a surface, the colour of that surface is blended over the fragment. Rougher surfaces reflect less
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScreenSpaceReflections
+{
+    /// The number of steps each reflected ray is marched. More steps find thinner reflected surfaces
+    pub steps: u32,
+    /// How far behind a surface, in world units, a ray can be and still reflect that surface
+    pub thickness: f32,
+    /// The fraction of the window, from its edges, over which reflections fade out, hiding where rays
+    /// leave the frame
+    pub fade: f32,
+    /// The furthest distance a reflected ray is marched, in world units
+    pub max_distance: f32,
+}
+
+impl Default for ScreenSpaceReflections
+{
+    fn default() -> Self
+    {
+        ScreenSpaceReflections{ steps: 32, thickness: 0.5, fade: 0.1, max_distance: 30.0 }
+    }
+}
+
+/// How the colours of the frame, which can be brighter than the window can show, are mapped into the
+/// range the window can show
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMappingOperator
+{
+    /// Divides each colour by one more than itself. Bright colours lose their saturation slowly
+    Reinhard,
+    /// Fits the filmic curve of the Academy Color Encoding System, giving more contrast than Reinhard
+    Aces,
+}
+
+/// An effect applied to the rendered frame before it is shown in the window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PostProcessEffect
+{
+    /// Bright parts of the frame bleed light into their surroundings. Pixels brighter than the threshold
+    /// are blurred at half the resolution of the window, and added back to the frame scaled by the intensity
+    Bloom { threshold: f32, intensity: f32, blur_passes: u32 },
+    /// Maps the colours of the frame into the range the window can show, after multiplying them by the exposure
+    ToneMapping { operator: ToneMappingOperator, exposure: f32 },
+    /// Raises the colours of the frame to the inverse of the gamma, converting them from linear colours
+    Gamma(f32),
+    /// Darkens the edges of the frame. The strength is how dark the corners become, and the radius is the
+    /// distance from the centre, as a fraction of the distance to the corners, that darkening starts at
+    Vignette { strength: f32, radius: f32 },
+    /// Fast approximate anti-aliasing, which smooths the edges found from the brightness of the frame. It is
+    /// best applied after tone mapping and gamma, as it expects colours the window can show
+    Fxaa,
+}
+
+/// The effects applied to the rendered frame, in the order they are applied
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostProcessStack
+{
+    effects: Vec<PostProcessEffect>,
+    scene_format: TextureFormat,
+}
+
+impl PostProcessStack
+{
+    /// Get the effects of the stack, in the order they are applied
+    pub fn effects(&self) -> &[PostProcessEffect]
+    {
+        &self.effects
+    }
+
+    /// Get the format of the frame buffer the frame is rendered into before the effects are applied
+    pub fn scene_format(&self) -> TextureFormat
+    {
+        self.scene_format
+    }
+}
+
+/// Creates a post-process stack, with effects applied in the order they are added. An effect can be
+/// added more than once
+#[derive(Default)]
+pub struct PostProcessBuilder
+{
+    effects: Vec<PostProcessEffect>,
+    scene_format: Option<TextureFormat>,
+}
+
+impl PostProcessBuilder
+{
+    pub fn new() -> PostProcessBuilder
+    {
+        PostProcessBuilder{ effects: Vec::new(), scene_format: None }
+    }
+
+    /// Changes the format of the frame buffer the frame is rendered into. By default this is RGBA16F; R11FG11FB10F
+    /// uses half the memory while still holding bright colours, and RGB10A2 avoids banding but cannot hold
+    /// colours brighter than the window can show, so it is best used without bloom or tone mapping
+    ///
+    /// `format` - the format of the frame buffer. Formats that do not hold colours are ignored
+    pub fn with_scene_format(mut self, format: TextureFormat) -> PostProcessBuilder
+    {
+        if !format.is_colour()
+        {
+            warn!("The frame must be rendered into a colour format, not {:?}; the default format is used", format);
+            return self;
+        }
+
+        self.scene_format = Some(format);
+        self
+    }
+
+    /// Adds bloom to the stack
+    ///
+    /// `threshold` - the brightness pixels must exceed to bloom
+    /// `intensity` - the multiplier of the blurred bright pixels added back to the frame
+    /// `blur_passes` - the number of times the bright pixels are blurred horizontally and vertically, from 1 to 16
+    pub fn with_bloom(mut self, threshold: f32, intensity: f32, blur_passes: u32) -> PostProcessBuilder
+    {
+        self.effects.push(PostProcessEffect::Bloom{ threshold: threshold.max(0.0), intensity: intensity.max(0.0), blur_passes: blur_passes.clamp(1, 16) });
+        self
+    }
+
+    /// Adds tone mapping to the stack
+    ///
+    /// `operator` - how colours are mapped into the range the window can show
+    /// `exposure` - the multiplier of the colours before they are mapped
+    pub fn with_tone_mapping(mut self, operator: ToneMappingOperator, exposure: f32) -> PostProcessBuilder
+    {
+        self.effects.push(PostProcessEffect::ToneMapping{ operator, exposure: exposure.max(0.0) });
+        self
+    }
+
+    /// Adds gamma correction to the stack
+    ///
+    /// `gamma` - the gamma of the window, usually 2.2. Values below 0.1 are treated as 0.1
+    pub fn with_gamma(mut self, gamma: f32) -> PostProcessBuilder
+    {
+        self.effects.push(PostProcessEffect::Gamma(gamma.max(0.1)));
+        self
+    }
+
+    /// Adds a vignette to the stack
+    ///
+    /// `strength` - how dark the corners of the frame become, from 0 to 1
+    /// `radius` - the distance from the centre darkening starts at, as a fraction of the distance to the corners
+    pub fn with_vignette(mut self, strength: f32, radius: f32) -> PostProcessBuilder
+    {
+        self.effects.push(PostProcessEffect::Vignette{ strength: strength.clamp(0.0, 1.0), radius: radius.clamp(0.0, 1.0) });
+        self
+    }
+
+    /// Adds fast approximate anti-aliasing to the stack
+    pub fn with_fxaa(mut self) -> PostProcessBuilder
+    {
+        self.effects.push(PostProcessEffect::Fxaa);
+        self
+    }
+
+    pub fn build(self) -> PostProcessStack
+    {
+        PostProcessStack{ effects: self.effects, scene_format: self.scene_format.unwrap_or(TextureFormat::RGBA16F) }
+    }
+}
+
+/// Changes the effects applied to the rendered frame. While a stack is set, the render systems drawing to
+/// the window render into a frame buffer that can hold colours brighter than the window can show, which
+/// the effects are applied to before the frame is shown. The changes are visible from the next rendered frame
+///
+/// `stack` - the effects to apply. If None or empty, the frame is rendered directly into the window
+pub fn set_post_process_stack(stack: Option<PostProcessStack>)
+{
+    *POST_PROCESS_STACK.lock() = stack.filter(|x| !x.effects.is_empty());
+}
+
+/// Get the effects applied to the rendered frame, if any are
+pub fn post_process_stack() -> Option<PostProcessStack>
+{
+    POST_PROCESS_STACK.lock().clone()
+}
+
 /// Holds variables required to execute a render function
 
 pub struct DrawParam<'a>
@@ -50,10 +263,30 @@ pub struct DrawParam<'a>
     input_history: &'a InputHistory,
     draw_fn_accessible_fbo: &'a mut HashMap<String, FBO>,
     rendering_skybox: bool,
+    indirect_draws: Option<&'a IndirectDraws>,
+    indirect_draw_buffer: Option<&'a mut IndirectDrawBuffer>,
 }
 
 impl<'a> DrawParam<'a>
 {
+    /// Makes models be drawn with the draw commands written when choosing level of views on the GPU,
+    /// rather than with the instance ranges written by the CPU
+    ///
+    /// `indirect_draws` - the draw commands of the instances of this frame
+    pub(crate) fn use_indirect_draws(&mut self, indirect_draws: &'a IndirectDraws)
+    {
+        self.indirect_draws = Some(indirect_draws);
+    }
+
+    /// Makes the instance ranges of every model drawn by one call of draw_model_with_sortable_index be drawn
+    /// with a single multi draw indirect call, rather than one instanced draw call per range and mesh
+    ///
+    /// `indirect_draw_buffer` - the buffer the draw commands are written to
+    pub(crate) fn use_indirect_draw_buffer(&mut self, indirect_draw_buffer: &'a mut IndirectDrawBuffer)
+    {
+        self.indirect_draw_buffer = Some(indirect_draw_buffer);
+    }
+
     pub fn toggle_rendering_skybox(&mut self, rendering_skybox: bool)
     {
         self.rendering_skybox = rendering_skybox;
@@ -153,6 +386,7 @@ impl<'a> DrawParam<'a>
         };
         let buffer = &mut self.uniforms.buffers[uniform_location.mapped_buffer_index];
         let write_info = buffer.wait_for_next_free_buffer(5_000_000).unwrap();
+        let cpu_values = &mut self.uniforms.cpu_values[uniform_location.mapped_buffer_index];
 
         let mut offset_bytes = uniform_location.offset_bytes;
 
@@ -164,14 +398,23 @@ impl<'a> DrawParam<'a>
                          uniform_name.as_ref(), expected_info.num_elements, data.len());
 
 
-        for (index, value) in data.into_iter().enumerate()
+        // Values set with the uniform inspector replace what the draw function writes
+        if let Some(value) = self.uniforms.overrides.get(uniform_name.as_ref())
+        {
+            value.write(write_info, cpu_values, uniform_location);
+        }
+        else
         {
-            MappedBuffer::write_single_serialized_value(write_info, value, offset_bytes, false);
+            for (index, value) in data.into_iter().enumerate()
+            {
+                write_cpu_value(cpu_values, &value, offset_bytes);
+                MappedBuffer::write_single_serialized_value(write_info, value, offset_bytes, false);
 
-            // (index + 1) as the offset_bytes will be used for the next iteration of the loop
-            offset_bytes = uniform_location.offset_bytes +  // Base offset in bytes for the uniform
-                (index + 1) as isize * uniform_location.sub_padding_bytes +  // Adjust byte count for padding
-                ((index + 1) * size_of::<T>()) as isize; // Adjust byte count assuming no padding
+                // (index + 1) as the offset_bytes will be used for the next iteration of the loop
+                offset_bytes = uniform_location.offset_bytes +  // Base offset in bytes for the uniform
+                    (index + 1) as isize * uniform_location.sub_padding_bytes +  // Adjust byte count for padding
+                    ((index + 1) * size_of::<T>()) as isize; // Adjust byte count assuming no padding
+            }
         }
 
         if self.uniforms.buffers_to_flush.iter().find(|x| **x == uniform_location.mapped_buffer_index).is_none()
@@ -259,6 +502,9 @@ impl<'a> DrawParam<'a>
                                                    mesh.vertex_offset);
                     }
             }
+
+            record_draw_calls(rendering_info.mesh_render_info.len() as u32);
+            record_instances_rendered(1);
         }
 
         self.set_fence_uniform_buffer();
@@ -314,8 +560,24 @@ impl<'a> DrawParam<'a>
 
     fn render_models<A: AsRef<str>>(&mut self, draw_commands: Vec<(ModelId, ModelDrawCommand<A>)>)
     {
+        // The draws of every model are gathered and issued together if a multi draw indirect buffer is available
+        let mut indirect_commands = Vec::new();
+        let mut indirect_instances = 0;
+
         for (model_id, command) in draw_commands
         {
+            // The level of view of each instance was chosen on the GPU, which also wrote the draw commands
+            // drawing every level of view of the model
+            if let Some(indirect_draws) = self.indirect_draws
+            {
+                for sortable_component_index in &command.component_indexes
+                {
+                    indirect_draws.draw(model_id, *sortable_component_index);
+                }
+
+                continue;
+            }
+
             // Iterate over all of the possible level of views, and for each one check if there are instances
             // that need to be rendered
 
@@ -363,6 +625,24 @@ impl<'a> DrawParam<'a>
 
                     for instances_to_render in render_ranges.iter().filter(|x| x.count != 0)
                     {
+                        if self.indirect_draw_buffer.is_some()
+                        {
+                            for mesh in &rendering_info.mesh_render_info
+                            {
+                                indirect_commands.push(DrawElementsIndirectCommand
+                                {
+                                    count: mesh.indice_count as u32,
+                                    instance_count: instances_to_render.count,
+                                    first_index: mesh.indice_offset as u32,
+                                    base_vertex: mesh.vertex_offset,
+                                    base_instance: instances_to_render.begin_instance
+                                });
+                            }
+
+                            indirect_instances += instances_to_render.count as u64;
+                            continue;
+                        }
+
                         for mesh in &rendering_info.mesh_render_info
                         {
                             unsafe
@@ -379,10 +659,19 @@ impl<'a> DrawParam<'a>
                                         );
                                 }
                         }
+
+                        record_draw_calls(rendering_info.mesh_render_info.len() as u32);
+                        record_instances_rendered(instances_to_render.count as u64);
                     }
                 }
             }
         }
+
+        if let Some(indirect_draw_buffer) = self.indirect_draw_buffer.as_mut()
+        {
+            indirect_draw_buffer.draw(&indirect_commands);
+            record_instances_rendered(indirect_instances);
+        }
     }
 
     /// Get the logical entities ECS
@@ -396,6 +685,20 @@ impl<'a> DrawParam<'a>
     {
         self.tree
     }
+
+    /// Get a handle to queue lines drawn over the frame once the render systems have finished, such as to
+    /// see the bounding volumes of the drawn entities
+    pub fn debug_draw(&self) -> DebugDraw
+    {
+        debug_draw()
+    }
+
+    /// Get the statistics of the previous frame, such as to draw fewer effects when frames take too long.
+    /// The statistics of the frame being drawn are only known once it is presented
+    pub fn frame_statistics(&self) -> FrameStatistics
+    {
+        latest_frame_statistics()
+    }
 }
 
 pub struct DrawBuilderParam<'a>
@@ -591,7 +894,9 @@ impl<'a> CreateDrawParam<'a>
             render_system: self.0.render_system.unwrap(),
             input_history: self.0.input_history.unwrap(),
             draw_fn_accessible_fbo: self.0.draw_fn_accessible_fbo.unwrap(),
-            rendering_skybox: self.0.initilally_rendering_skybox
+            rendering_skybox: self.0.initilally_rendering_skybox,
+            indirect_draws: None,
+            indirect_draw_buffer: None
         }
     }
 }
\ No newline at end of file
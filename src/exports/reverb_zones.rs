@@ -0,0 +1,207 @@
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use nalgebra_glm::TVec3;
+use parking_lot::Mutex;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+// The engine does not play audio itself. Reverb zones describe the acoustics of regions of the game world,
+// and the audio code of the game queries the parameters for the position of the listener (usually the
+// camera) each frame, passing them on to whatever audio library it uses
+
+lazy_static!
+{
+    static ref REVERB_ZONES: Mutex<ReverbZones> = Mutex::new(ReverbZones{ next_id: 0, zones: HashMap::default(), default_parameters: ReverbPreset::OpenSpace.parameters() });
+}
+
+/// The parameters of a reverb effect and of how much sound is muffled
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReverbParameters
+{
+    /// Time in seconds for the reverb to fade by 60dB
+    pub decay_time: f32,
+    /// Delay in seconds before the first reflections are heard
+    pub reflections_delay: f32,
+    /// How much of the output is reverberated sound, from 0 to 1
+    pub wet_mix: f32,
+    /// How much high frequencies are absorbed, from 0 (none) to 1 (fully muffled), such as by walls
+    /// between the listener and the outside
+    pub occlusion: f32,
+}
+
+/// Common acoustic environments
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReverbPreset
+{
+    OpenSpace,
+    SmallRoom,
+    Hall,
+    Cave,
+    Corridor,
+    Underwater,
+    Custom(ReverbParameters)
+}
+
+/// Identifies a reverb zone that was added, so that it can later be removed
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ReverbZoneId
+{
+    id: usize
+}
+
+/// Acoustics to use when the listener is within a region of the game world. Outside of the region, the
+/// zone is blended with the surrounding acoustics over the blend distance
+#[derive(Copy, Clone, Debug)]
+pub struct ReverbZone
+{
+    pub volume: StaticAABB,
+    pub blend_distance: f32,
+    pub preset: ReverbPreset,
+}
+
+struct ReverbZones
+{
+    next_id: usize,
+    zones: HashMap<ReverbZoneId, ReverbZone>,
+    default_parameters: ReverbParameters,
+}
+
+impl ReverbParameters
+{
+    /// Linearly interpolates between these and the other parameters
+    ///
+    /// `other` - the parameters to blend towards
+    /// `factor` - how much of the other parameters to use, from 0 to 1
+    fn blend(&self, other: &ReverbParameters, factor: f32) -> ReverbParameters
+    {
+        let lerp = |a: f32, b: f32| a + (b - a) * factor;
+
+        ReverbParameters
+        {
+            decay_time: lerp(self.decay_time, other.decay_time),
+            reflections_delay: lerp(self.reflections_delay, other.reflections_delay),
+            wet_mix: lerp(self.wet_mix, other.wet_mix),
+            occlusion: lerp(self.occlusion, other.occlusion),
+        }
+    }
+}
+
+impl ReverbPreset
+{
+    /// Get the reverb parameters of the preset
+    pub fn parameters(&self) -> ReverbParameters
+    {
+        let preset = |decay_time, reflections_delay, wet_mix, occlusion| ReverbParameters{ decay_time, reflections_delay, wet_mix, occlusion };
+
+        match *self
+        {
+            ReverbPreset::OpenSpace => preset(0.3, 0.0, 0.05, 0.0),
+            ReverbPreset::SmallRoom => preset(0.6, 0.005, 0.25, 0.2),
+            ReverbPreset::Hall => preset(2.5, 0.02, 0.4, 0.1),
+            ReverbPreset::Cave => preset(4.0, 0.03, 0.55, 0.3),
+            ReverbPreset::Corridor => preset(1.2, 0.01, 0.35, 0.2),
+            ReverbPreset::Underwater => preset(1.5, 0.007, 0.6, 0.8),
+            ReverbPreset::Custom(parameters) => parameters
+        }
+    }
+}
+
+impl ReverbZone
+{
+    /// Creates a reverb zone that applies within the given volume, such as the interior of a building
+    ///
+    /// `volume` - the region in which the zone fully applies
+    /// `blend_distance` - the distance outside of the volume over which the zone fades out
+    /// `preset` - the acoustics within the volume
+    pub fn new(volume: StaticAABB, blend_distance: f32, preset: ReverbPreset) -> ReverbZone
+    {
+        debug_assert!(blend_distance >= 0.0, "Reverb zone cannot have a negative blend distance");
+
+        ReverbZone{ volume, blend_distance, preset }
+    }
+
+    /// Creates a reverb zone that applies within the given world section
+    ///
+    /// `tree` - the bounding box tree the world section is a part of
+    /// `world_section` - the world section in which the zone fully applies
+    /// `blend_distance` - the distance outside of the world section over which the zone fades out
+    /// `preset` - the acoustics within the world section
+    pub fn for_world_section(tree: &BoundingBoxTree, world_section: UniqueWorldSectionId, blend_distance: f32, preset: ReverbPreset) -> ReverbZone
+    {
+        ReverbZone::new(tree.world_section_aabb(world_section), blend_distance, preset)
+    }
+
+    /// Finds how much the zone applies at the given position, from 0 to 1
+    ///
+    /// `position` - the position to find the influence of the zone at
+    fn influence(&self, position: TVec3<f32>) -> f32
+    {
+        let distance = self.volume.distance_to_point(position);
+
+        if self.blend_distance == 0.0
+        {
+            return if distance == 0.0 { 1.0 } else { 0.0 };
+        }
+
+        (1.0 - distance / self.blend_distance).max(0.0)
+    }
+}
+
+/// Adds a reverb zone, returning the id needed to remove it
+///
+/// `reverb_zone` - the zone to add
+pub fn add_reverb_zone(reverb_zone: ReverbZone) -> ReverbZoneId
+{
+    let mut reverb_zones = REVERB_ZONES.lock();
+
+    let id = ReverbZoneId{ id: reverb_zones.next_id };
+    reverb_zones.next_id += 1;
+    reverb_zones.zones.insert(id, reverb_zone);
+
+    id
+}
+
+/// Removes a previously added reverb zone. Removing a zone that does not exist has no effect
+///
+/// `id` - the id of the zone to remove
+pub fn remove_reverb_zone(id: ReverbZoneId)
+{
+    REVERB_ZONES.lock().zones.remove(&id);
+}
+
+/// Specifies the acoustics used where no reverb zone applies. Defaults to the open space preset
+///
+/// `preset` - the acoustics outside of all reverb zones
+pub fn set_default_reverb(preset: ReverbPreset)
+{
+    REVERB_ZONES.lock().default_parameters = preset.parameters();
+}
+
+/// Finds the reverb parameters for a listener at the given position. Zones are blended by size, from the
+/// largest to the smallest, so a room within a larger cave sounds like the room when the listener is in it
+///
+/// `listener_position` - the position of the listener, usually the camera
+pub fn reverb_at(listener_position: TVec3<f32>) -> ReverbParameters
+{
+    let reverb_zones = REVERB_ZONES.lock();
+
+    let volume_size = |x: &ReverbZone| x.volume.x_range.length() * x.volume.y_range.length() * x.volume.z_range.length();
+
+    // Ties in size are broken by the order the zones were added, so later zones take precedence
+    let mut applicable_zones = reverb_zones.zones.iter().collect::<Vec<(&ReverbZoneId, &ReverbZone)>>();
+    applicable_zones.sort_by(|a, b| volume_size(b.1).partial_cmp(&volume_size(a.1)).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(b.0)));
+
+    let mut blended_parameters = reverb_zones.default_parameters;
+
+    for (_, reverb_zone) in applicable_zones
+    {
+        let influence = reverb_zone.influence(listener_position);
+
+        if influence > 0.0
+        {
+            blended_parameters = blended_parameters.blend(&reverb_zone.preset.parameters(), influence);
+        }
+    }
+
+    blended_parameters
+}
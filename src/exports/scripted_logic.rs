@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use hashbrown::HashMap;
+use parking_lot::{Mutex, RwLock};
+use rhai::{AST, Dynamic, Engine, Scope};
+use tracing::{error, info, warn};
+use crate::exports::component_reflection::{edit_component, inspect_entity};
+use crate::objects::ecs::ECS;
+use crate::objects::entity_change_request::EntityChangeInformation;
+use crate::objects::entity_id::EntityId;
+
+/// How often the script file is checked for modifications
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Name of the function in the script that is called every frame for every entity using the script
+const FRAME_FUNCTION_NAME: &str = "on_frame";
+
+// Scripts are written in Rhai and replace the logic function of an entity type. The script defines
+// on_frame(ctx), where ctx gives access to the components of the entity that are registered for reflection,
+// read by their reflected name. The script cannot access the ECS directly; components it sets are returned
+// as changes, the same as the changes returned by a logic function:
+//
+//     fn on_frame(ctx) {
+//         let position = ctx.get("Position");
+//         position[1] += 2.0 * ctx.elapsed_time;
+//         ctx.set("Position", position);
+//     }
+//
+// The script file is compiled again when it is modified. If it no longer compiles, the previously compiled
+// script keeps being executed
+
+/// Logic of an entity type given by a script file
+pub struct ScriptedLogic
+{
+    location: PathBuf,
+    engine: Engine,
+    ast: RwLock<Option<AST>>,
+    last_modified: Mutex<(Option<SystemTime>, Instant)>,
+}
+
+/// The entity a script is executed for, given to the script as `ctx`
+#[derive(Clone)]
+struct ScriptContext
+{
+    entity_id: EntityId,
+    elapsed_time: f32,
+    components: Arc<HashMap<String, Dynamic>>,
+    changes: Arc<Mutex<Vec<(String, Dynamic)>>>,
+    delete_requested: Arc<Mutex<bool>>,
+}
+
+impl ScriptedLogic
+{
+    /// Loads the logic from the script file. If the script does not compile, the error is printed and the
+    /// entities using it do nothing until the script is fixed
+    ///
+    /// `location` - the location of the script file
+    pub fn new<P: Into<PathBuf>>(location: P) -> ScriptedLogic
+    {
+        let scripted_logic = ScriptedLogic
+        {
+            location: location.into(),
+            engine: ScriptedLogic::create_engine(),
+            ast: RwLock::new(None),
+            last_modified: Mutex::new((None, Instant::now())),
+        };
+
+        scripted_logic.last_modified.lock().0 = scripted_logic.modification_time();
+        scripted_logic.compile();
+        scripted_logic
+    }
+
+    /// Compiles the script file again if it was modified since it was last compiled
+    pub(crate) fn reload_if_changed(&self)
+    {
+        {
+            let mut last_modified = self.last_modified.lock();
+
+            if last_modified.1.elapsed() < RELOAD_CHECK_INTERVAL
+            {
+                return;
+            }
+
+            last_modified.1 = Instant::now();
+
+            let modification_time = self.modification_time();
+
+            if modification_time.is_none() || modification_time == last_modified.0
+            {
+                return;
+            }
+
+            last_modified.0 = modification_time;
+        }
+
+        if self.compile()
+        {
+            info!("Reloaded script {:?}", self.location);
+        }
+    }
+
+    /// Executes the script for the given entity, returning the changes the script requested
+    ///
+    /// `entity_id` - the entity the script is executed for
+    /// `ecs` - the ECS holding the entity
+    /// `elapsed_time` - the amount of time that has passed since the last frame in seconds
+    pub(crate) fn execute(&self, entity_id: EntityId, ecs: &ECS, elapsed_time: f32) -> Vec<EntityChangeInformation>
+    {
+        let ast = self.ast.read();
+
+        let ast = match *ast
+        {
+            Some(ref i) => i,
+            None => return Vec::new()
+        };
+
+        let components = inspect_entity(ecs, entity_id).into_iter()
+            .filter_map(|x| rhai::serde::to_dynamic(&x.value).ok().map(|value| (x.name.to_string(), value)))
+            .collect();
+
+        let context = ScriptContext
+        {
+            entity_id,
+            elapsed_time,
+            components: Arc::new(components),
+            changes: Arc::new(Mutex::new(Vec::new())),
+            delete_requested: Arc::new(Mutex::new(false)),
+        };
+
+        if let Err(err) = self.engine.call_fn::<Dynamic>(&mut Scope::new(), ast, FRAME_FUNCTION_NAME, (context.clone(), ))
+        {
+            error!("Script {:?} failed for entity {:?}: {}", self.location, entity_id, err);
+            return Vec::new();
+        }
+
+        let mut changes = Vec::new();
+
+        for (component_name, value) in context.changes.lock().drain(..)
+        {
+            let json_value = match rhai::serde::from_dynamic::<serde_json::Value>(&value)
+            {
+                Ok(i) => i,
+                Err(err) =>
+                    {
+                        warn!("Script {:?} set {} to an invalid value: {}", self.location, component_name, err);
+                        continue;
+                    }
+            };
+
+            match edit_component(entity_id, &component_name, json_value)
+            {
+                Ok(i) => changes.push(i),
+                Err(err) => warn!("Script {:?} could not set {}: {:?}", self.location, component_name, err)
+            }
+        }
+
+        if *context.delete_requested.lock()
+        {
+            changes.push(EntityChangeInformation::DeleteRequest(entity_id));
+        }
+
+        changes
+    }
+
+    /// Compiles the script file, keeping the previously compiled script if it fails. True is returned
+    /// if the script compiled
+    fn compile(&self) -> bool
+    {
+        match self.engine.compile_file(self.location.clone())
+        {
+            Ok(ast) =>
+                {
+                    *self.ast.write() = Some(ast);
+                    true
+                },
+            Err(err) =>
+                {
+                    error!("Failed to compile script {:?}: {}", self.location, err);
+                    false
+                }
+        }
+    }
+
+    /// Get the time the script file was last modified, if it can be read
+    fn modification_time(&self) -> Option<SystemTime>
+    {
+        fs::metadata(&self.location).and_then(|x| x.modified()).ok()
+    }
+
+    /// Creates the script engine with the functions scripts use to access their entity
+    fn create_engine() -> Engine
+    {
+        let mut engine = Engine::new();
+
+        engine.register_type_with_name::<ScriptContext>("Context")
+            .register_get("entity", |x: &mut ScriptContext| x.entity_id.get_entity_instance() as i64)
+            .register_get("elapsed_time", |x: &mut ScriptContext| x.elapsed_time as f64)
+            .register_fn("has", |x: &mut ScriptContext, component_name: &str| x.components.contains_key(component_name))
+            .register_fn("get", |x: &mut ScriptContext, component_name: &str| x.components.get(component_name).cloned().unwrap_or(Dynamic::UNIT))
+            .register_fn("set", |x: &mut ScriptContext, component_name: &str, value: Dynamic| x.changes.lock().push((component_name.to_string(), value)))
+            .register_fn("delete_self", |x: &mut ScriptContext| *x.delete_requested.lock() = true);
+
+        engine
+    }
+}
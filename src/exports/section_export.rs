@@ -0,0 +1,210 @@
+use std::any::TypeId;
+use std::fs;
+use std::path::Path;
+use hashbrown::HashMap;
+use nalgebra_glm::TVec3;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use crate::exports::component_reflection::{inspect_entity, reflected_entity_type, reflected_entity_type_name, ReflectionError, serialize_reflected_component};
+use crate::exports::light_components::FindLightType;
+use crate::exports::logic_components::CanCauseCollisions;
+use crate::exports::movement_components::{Position, Rotation, Scale, TransformationMatrix};
+use crate::exports::prefab::{Prefab, PrefabTransform};
+use crate::exports::world_snapshot::SnapshotError;
+use crate::models::model_definitions::{ModelId, OriginalAABB};
+use crate::objects::ecs::{ECS, TypeIdentifier};
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+// The entities of a selection of world sections can be exported into a standalone file and imported into
+// another world, such as to share prefabricated structures between worlds. Unlike a snapshot, nothing in
+// the file depends on the build of the game: models are referred to by name, and entity types and
+// components by the names they were registered for reflection with, with components stored as JSON.
+//
+// Importing spawns every entity of the file as an instance of a prefab, so the transformation and bounding
+// volume of each entity is created again at the position it is imported at. Only entities with a model are
+// exported, and relationships between entities, such as parents and children, are not kept
+
+/// Version of the layout of exported section files. Increase when the layout of the file changes
+pub const SECTION_EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Identifies a file as exported world sections
+const SECTION_EXPORT_MAGIC: &str = "RENGSECT";
+
+/// The entities of a selection of world sections
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SectionExport
+{
+    magic: String,
+    format_version: u32,
+    /// The world sections the entities were exported from
+    pub sections: Vec<UniqueWorldSectionId>,
+    pub entities: Vec<ExportedEntity>,
+}
+
+/// An entity of exported world sections
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportedEntity
+{
+    pub model_name: String,
+    /// The name the entity type was registered for reflection with
+    pub entity_type: String,
+    pub is_static: bool,
+    pub can_cause_collision: bool,
+    pub light_type: Option<FindLightType>,
+    pub position: Position,
+    pub rotation: Option<Rotation>,
+    pub scale: Option<Scale>,
+    /// The components registered for reflection, by name, other than those describing the placement and
+    /// model of the entity, which are created again when it is imported
+    pub components: Vec<(String, Value)>,
+}
+
+impl SectionExport
+{
+    /// Exports the entities with a model in the given world sections
+    ///
+    /// `sections` - the world sections to export
+    /// `ecs` - the ECS holding the entities
+    /// `bounding_box_tree` - the tree holding the world sections
+    /// `model_names` - the name of every uploaded model
+    pub(crate) fn from_world(sections: Vec<UniqueWorldSectionId>, ecs: &ECS, bounding_box_tree: &BoundingBoxTree, model_names: &[(String, ModelId)]) -> Result<SectionExport, SnapshotError>
+    {
+        let model_name_lookup = model_names.iter().map(|(name, model_id)| (*model_id, name)).collect::<HashMap<ModelId, &String>>();
+
+        let derived_components = [TypeId::of::<Position>(), TypeId::of::<Rotation>(), TypeId::of::<Scale>(), TypeId::of::<TransformationMatrix>(),
+                                  TypeId::of::<ModelId>(), TypeId::of::<StaticAABB>(), TypeId::of::<OriginalAABB>()]
+            .iter()
+            .map(|x| TypeIdentifier::from(*x))
+            .collect::<Vec<TypeIdentifier>>();
+
+        let user_entity = ecs.get_user_id_read();
+        let mut entities = Vec::new();
+
+        for entity_id in bounding_box_tree.get_section_entities(&sections)
+        {
+            if entity_id.get_entity_instance() == user_entity.get_entity_instance()
+            {
+                continue;
+            }
+
+            let model_name = match ecs.get_copy::<ModelId>(entity_id).and_then(|x| model_name_lookup.get(&x))
+            {
+                Some(i) => (*i).clone(),
+                None => continue
+            };
+
+            let position = match ecs.get_copy::<Position>(entity_id)
+            {
+                Some(i) => i,
+                None => continue
+            };
+
+            let entity_type = ecs.get_entity_type(entity_id)
+                .and_then(reflected_entity_type_name)
+                .ok_or_else(|| SnapshotError::UnknownEntityType(format!("entity {} has an entity type not registered for reflection", entity_id.get_entity_instance())))?;
+
+            let components = inspect_entity(ecs, entity_id).into_iter()
+                .filter(|x| !derived_components.contains(&x.type_id))
+                .map(|x| (x.name.to_string(), x.value))
+                .collect();
+
+            entities.push(ExportedEntity
+            {
+                model_name,
+                entity_type: entity_type.to_string(),
+                is_static: bounding_box_tree.is_entity_static(entity_id).unwrap_or(false),
+                can_cause_collision: ecs.get_ref::<CanCauseCollisions>(entity_id).is_some(),
+                light_type: bounding_box_tree.get_entity_light_type(entity_id),
+                position,
+                rotation: ecs.get_copy::<Rotation>(entity_id),
+                scale: ecs.get_copy::<Scale>(entity_id),
+                components,
+            });
+        }
+
+        Ok(SectionExport{ magic: SECTION_EXPORT_MAGIC.to_string(), format_version: SECTION_EXPORT_FORMAT_VERSION, sections, entities })
+    }
+
+    /// Creates the prefab of every exported entity, along with where it is spawned
+    ///
+    /// `offset` - the distance each entity is moved from where it was exported
+    pub(crate) fn to_prefabs(&self, offset: TVec3<f32>) -> Result<Vec<(Prefab, PrefabTransform)>, SnapshotError>
+    {
+        let mut prefabs = Vec::with_capacity(self.entities.len());
+
+        for entity in &self.entities
+        {
+            let entity_type = reflected_entity_type(&entity.entity_type).ok_or_else(|| SnapshotError::UnknownEntityType(entity.entity_type.clone()))?;
+
+            let mut prefab = Prefab::new(entity.model_name.clone(), entity_type);
+            prefab.with_static(entity.is_static);
+
+            if entity.can_cause_collision
+            {
+                prefab.with_collisions();
+            }
+
+            if let Some(light_type) = entity.light_type
+            {
+                prefab.with_light(light_type);
+            }
+
+            for (component_name, value) in &entity.components
+            {
+                let (type_id, serialized_value) = serialize_reflected_component(component_name, value.clone()).map_err(|err| match err
+                {
+                    ReflectionError::UnknownComponent(name) => SnapshotError::UnknownComponent(name),
+                    ReflectionError::InvalidValue(err) => SnapshotError::Corrupted(format!("invalid value of component {}: {}", component_name, err))
+                })?;
+
+                prefab.with_serialized_component(type_id, serialized_value);
+            }
+
+            let transform = PrefabTransform
+            {
+                translation: Position::new(entity.position.get_position() + offset),
+                rotation: entity.rotation,
+                scale: entity.scale,
+            };
+
+            prefabs.push((prefab, transform));
+        }
+
+        Ok(prefabs)
+    }
+
+    /// Writes the exported sections to the given file
+    ///
+    /// `path` - the location of the file to write
+    pub fn write_to_file(&self, path: &Path) -> Result<(), SnapshotError>
+    {
+        let contents = serde_json::to_string_pretty(self).map_err(|err| SnapshotError::Corrupted(err.to_string()))?;
+        fs::write(path, contents).map_err(SnapshotError::Io)
+    }
+
+    /// Reads exported sections from the given file
+    ///
+    /// `path` - the location of the file to read
+    pub fn read_from_file(path: &Path) -> Result<SectionExport, SnapshotError>
+    {
+        let contents = fs::read_to_string(path).map_err(SnapshotError::Io)?;
+
+        // The header is checked first, so that a file of another format is reported as such rather than as corrupted
+        let header: Value = serde_json::from_str(&contents).map_err(|_| SnapshotError::NotASnapshot)?;
+
+        if header.get("magic").and_then(|x| x.as_str()) != Some(SECTION_EXPORT_MAGIC)
+        {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        let format_version = header.get("format_version").and_then(|x| x.as_u64()).unwrap_or(0) as u32;
+
+        if format_version != SECTION_EXPORT_FORMAT_VERSION
+        {
+            return Err(SnapshotError::UnsupportedFormatVersion(format_version));
+        }
+
+        serde_json::from_value(header).map_err(|err| SnapshotError::Corrupted(err.to_string()))
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt::{Display, Formatter};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::warn;
+use crate::exports::frame_statistics::FrameStatistics;
+
+// The best world section length depends on how entities are spread through the scene: short sections cull
+// more precisely but give more sections to cull and sort, while long sections are cheap to cull but draw
+// more entities that cannot be seen. Tuning rebuilds the world sections of the loaded scene with each
+// candidate length in turn and measures the CPU time of frames rendered with it, as that is what the
+// section length affects. The scene should stay representative while tuning, for example by leaving the
+// camera in a typical spot or moving it along a camera path
+
+lazy_static!
+{
+    static ref SECTION_LENGTH_TUNING: Mutex<TuningState> = Mutex::new(TuningState{ requested: None, run: None, report: None });
+}
+
+/// Controls how the world section length is tuned
+#[derive(Clone, Debug, PartialEq)]
+pub struct SectionLengthTuning
+{
+    /// The world section lengths to measure. Lengths should be powers of two
+    pub candidate_lengths: Vec<u32>,
+    /// Number of frames rendered after rebuilding the world sections before frames are measured, giving
+    /// time for the render data of the rebuilt sections to be uploaded
+    pub warmup_frames: u32,
+    /// Number of frames measured for each length
+    pub measured_frames: u32,
+    /// If true, the world sections are rebuilt with the recommended length once tuning finishes. Otherwise
+    /// the length in use before tuning is restored
+    pub apply_recommendation: bool,
+}
+
+impl Default for SectionLengthTuning
+{
+    fn default() -> SectionLengthTuning
+    {
+        SectionLengthTuning
+        {
+            candidate_lengths: vec![16, 32, 64, 128, 256],
+            warmup_frames: 30,
+            measured_frames: 120,
+            apply_recommendation: false,
+        }
+    }
+}
+
+/// The cost of rendering the scene with a world section length. Times are in milliseconds
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SectionLengthMeasurement
+{
+    pub world_section_length: u32,
+    pub average_cpu_time: f32,
+    pub average_frame_time: f32,
+    pub average_visible_sections: f32,
+}
+
+/// Result of tuning the world section length
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SectionLengthReport
+{
+    /// The measurement of every candidate length, in the order they were measured
+    pub measurements: Vec<SectionLengthMeasurement>,
+    /// The world section length in use before tuning
+    pub original_length: u32,
+    /// The measured length with the lowest CPU time
+    pub recommended_length: u32,
+    /// True if the world sections were rebuilt with the recommended length
+    pub applied: bool,
+}
+
+/// Progress of tuning the world section length
+struct TuningRun
+{
+    settings: SectionLengthTuning,
+    original_length: u32,
+    candidate_index: usize,
+    frames_to_skip: u32,
+    frames: Vec<FrameStatistics>,
+    measurements: Vec<SectionLengthMeasurement>,
+}
+
+struct TuningState
+{
+    requested: Option<SectionLengthTuning>,
+    run: Option<TuningRun>,
+    report: Option<SectionLengthReport>,
+}
+
+/// Starts measuring the cost of rendering the scene with each of the candidate world section lengths. The
+/// world sections are rebuilt at the end of the frame. Tuning that is already in progress is restarted
+///
+/// `settings` - the lengths to measure and how long to measure them for
+pub fn start_section_length_tuning(settings: SectionLengthTuning)
+{
+    if settings.candidate_lengths.is_empty()
+    {
+        warn!("Unable to tune the world section length without any candidate lengths");
+        return;
+    }
+
+    SECTION_LENGTH_TUNING.lock().requested = Some(SectionLengthTuning
+    {
+        candidate_lengths: settings.candidate_lengths.iter().map(|x| (*x).max(1)).collect(),
+        measured_frames: settings.measured_frames.max(1),
+        ..settings
+    });
+}
+
+/// Checks if the world section length is being tuned
+pub fn is_tuning_section_length() -> bool
+{
+    let tuning_state = SECTION_LENGTH_TUNING.lock();
+    tuning_state.requested.is_some() || tuning_state.run.is_some()
+}
+
+/// Get the report of the most recently finished tuning of the world section length, if any has finished
+pub fn section_length_report() -> Option<SectionLengthReport>
+{
+    SECTION_LENGTH_TUNING.lock().report.clone()
+}
+
+/// Records the statistics of a rendered frame. Returns the world section length the world sections should
+/// be rebuilt with, if they need to be rebuilt. Once tuning finishes, the report is also returned
+///
+/// `statistics` - the statistics of the rendered frame
+/// `current_length` - the world section length in use
+pub(crate) fn record_tuning_frame(statistics: FrameStatistics, current_length: u32) -> (Option<u32>, Option<SectionLengthReport>)
+{
+    let mut tuning_state = SECTION_LENGTH_TUNING.lock();
+
+    if let Some(settings) = tuning_state.requested.take()
+    {
+        // A restarted tuning returns to the length in use before the previous tuning started
+        let original_length = tuning_state.run.as_ref().map_or(current_length, |x| x.original_length);
+        let first_length = settings.candidate_lengths[0];
+
+        tuning_state.run = Some(TuningRun
+        {
+            frames_to_skip: settings.warmup_frames,
+            settings,
+            original_length,
+            candidate_index: 0,
+            frames: Vec::new(),
+            measurements: Vec::new()
+        });
+
+        return (Some(first_length).filter(|x| *x != current_length), None);
+    }
+
+    let run = match tuning_state.run.as_mut()
+    {
+        Some(i) => i,
+        None => return (None, None)
+    };
+
+    if run.frames_to_skip != 0
+    {
+        run.frames_to_skip -= 1;
+        return (None, None);
+    }
+
+    run.frames.push(statistics);
+
+    if run.frames.len() < run.settings.measured_frames as usize
+    {
+        return (None, None);
+    }
+
+    let frame_count = run.frames.len() as f32;
+    let average = |value: &dyn Fn(&FrameStatistics) -> f32| run.frames.iter().map(value).sum::<f32>() / frame_count;
+
+    let measurement = SectionLengthMeasurement
+    {
+        world_section_length: current_length,
+        average_cpu_time: average(&|x| x.cpu_time),
+        average_frame_time: average(&|x| x.frame_time),
+        average_visible_sections: average(&|x| x.visible_sections as f32),
+    };
+
+    run.measurements.push(measurement);
+    run.frames.clear();
+    run.candidate_index += 1;
+
+    if let Some(next_length) = run.settings.candidate_lengths.get(run.candidate_index).copied()
+    {
+        run.frames_to_skip = run.settings.warmup_frames;
+        return (Some(next_length).filter(|x| *x != current_length), None);
+    }
+
+    let run = tuning_state.run.take().unwrap();
+
+    let recommended_length = run.measurements.iter()
+        .min_by(|a, b| a.average_cpu_time.partial_cmp(&b.average_cpu_time).unwrap_or(std::cmp::Ordering::Equal))
+        .map_or(run.original_length, |x| x.world_section_length);
+
+    let report = SectionLengthReport
+    {
+        measurements: run.measurements,
+        original_length: run.original_length,
+        recommended_length,
+        applied: run.settings.apply_recommendation,
+    };
+
+    tuning_state.report = Some(report.clone());
+
+    let final_length = if run.settings.apply_recommendation { recommended_length } else { run.original_length };
+    (Some(final_length).filter(|x| *x != current_length), Some(report))
+}
+
+impl Display for SectionLengthReport
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        writeln!(f, "World section length tuning results")?;
+
+        for x in &self.measurements
+        {
+            writeln!(f, "  Length {:>5}: {:.2}ms CPU time, {:.2}ms frame time, {:.1} visible sections",
+                     x.world_section_length, x.average_cpu_time, x.average_frame_time, x.average_visible_sections)?;
+        }
+
+        write!(f, "  Recommended length: {} (was {}{})", self.recommended_length, self.original_length, if self.applied { ", applied" } else { "" })
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+use lazy_static::lazy_static;
+use nalgebra_glm::{TVec3, TVec4, vec3, vec4};
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+
+// Sprites are textured quads drawn at the position of an entity with a SpriteRenderer, such as health bars,
+// impostors of distant models or dust, without a render system having to be written for them. They are
+// drawn over the frame after the render systems drawing to the window have finished, sorted from the
+// furthest to the nearest, with one instanced draw call for each run of sprites sharing a texture. As with
+// particles, sprites are hidden behind geometry using the world positions of the frame.
+//
+// Sprite textures are registered on any thread and loaded on the render thread the first time a sprite
+// using them is drawn
+
+lazy_static!
+{
+    static ref SPRITE_TEXTURES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+}
+
+/// Refers to an image sprites are drawn with, given by load_sprite_texture
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct SpriteTexture
+{
+    index: usize,
+}
+
+/// How a sprite is oriented
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BillboardMode
+{
+    /// The sprite always faces the camera, such as for dust and impostors seen from any direction
+    Spherical,
+    /// The sprite rotates about the world up axis to face the camera while staying upright, such as for trees
+    Cylindrical,
+    /// The sprite lies in the XY plane of its entity, rotated with the transformation matrix of the entity
+    Fixed,
+}
+
+/// The part of a texture a sprite is drawn with, in texture coordinates from the top left of the image
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteRegion
+{
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl SpriteRegion
+{
+    /// Creates a region covering the entire texture
+    pub fn full() -> SpriteRegion
+    {
+        SpriteRegion{ min: (0.0, 0.0), max: (1.0, 1.0) }
+    }
+
+    /// Creates the region of a cell of a sprite sheet whose cells are laid out in a grid, counted from the
+    /// top left of the sheet along each row
+    ///
+    /// `columns` - the number of cells in each row of the sheet
+    /// `rows` - the number of rows of the sheet
+    /// `cell` - the index of the cell
+    pub fn grid_cell(columns: u32, rows: u32, cell: u32) -> SpriteRegion
+    {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+        let column = (cell % columns) as f32;
+        let row = ((cell / columns) % rows) as f32;
+        let cell_size = (1.0 / columns as f32, 1.0 / rows as f32);
+
+        SpriteRegion
+        {
+            min: (column * cell_size.0, row * cell_size.1),
+            max: ((column + 1.0) * cell_size.0, (row + 1.0) * cell_size.1),
+        }
+    }
+}
+
+/// Draws a sprite at the position of an entity. The entity must have a position
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpriteRenderer
+{
+    /// The texture the sprite is drawn with
+    pub texture: SpriteTexture,
+    /// The part of the texture the sprite is drawn with
+    pub region: SpriteRegion,
+    /// Width and height of the sprite in the world
+    pub size: (f32, f32),
+    /// The point of the sprite placed at the position of the entity, from (0, 0) at its bottom left to
+    /// (1, 1) at its top right. A health bar shrinking towards its left has a pivot of (0, 0.5)
+    pub pivot: (f32, f32),
+    /// Multiplied with the colour and opacity of the texture
+    pub colour: TVec4<f32>,
+    /// Distance from the position of the entity to the pivot of the sprite, such as to place a health bar
+    /// above a model
+    pub offset: TVec3<f32>,
+    /// How the sprite is oriented
+    pub billboard: BillboardMode,
+    /// If true, the sprite is hidden where geometry is in front of it
+    pub occluded: bool,
+}
+
+impl SpriteRenderer
+{
+    /// Creates a sprite drawn with the entire texture, centred on its entity and facing the camera
+    ///
+    /// `texture` - the texture the sprite is drawn with
+    /// `size` - width and height of the sprite in the world
+    pub fn new(texture: SpriteTexture, size: (f32, f32)) -> SpriteRenderer
+    {
+        SpriteRenderer
+        {
+            texture,
+            region: SpriteRegion::full(),
+            size,
+            pivot: (0.5, 0.5),
+            colour: vec4(1.0, 1.0, 1.0, 1.0),
+            offset: vec3(0.0, 0.0, 0.0),
+            billboard: BillboardMode::Spherical,
+            occluded: true,
+        }
+    }
+}
+
+impl SpriteTexture
+{
+    /// Get the index of the texture among the registered sprite textures
+    pub(crate) fn index(&self) -> usize
+    {
+        self.index
+    }
+}
+
+/// Registers an image that sprites can be drawn with. Registering the same image again gives the same
+/// texture. The image is loaded the first time a sprite using it is drawn
+///
+/// `location` - the location of the image
+pub fn load_sprite_texture<A: Into<PathBuf>>(location: A) -> SpriteTexture
+{
+    let location = location.into();
+    let mut sprite_textures = SPRITE_TEXTURES.lock();
+
+    if let Some(index) = sprite_textures.iter().position(|x| *x == location)
+    {
+        return SpriteTexture{ index };
+    }
+
+    sprite_textures.push(location);
+    SpriteTexture{ index: sprite_textures.len() - 1 }
+}
+
+/// Get the locations of the registered sprite textures registered after the given number of textures
+///
+/// `number_known` - the number of textures that were already given
+pub(crate) fn new_sprite_textures(number_known: usize) -> Vec<PathBuf>
+{
+    SPRITE_TEXTURES.lock().iter().skip(number_known).cloned().collect()
+}
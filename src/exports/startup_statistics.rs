@@ -0,0 +1,40 @@
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+// Creating a render system has two parts: generating its shaders, which only needs the CPU, and creating its
+// OpenGL resources and compiling its shaders, which needs the GL context. The shaders of render systems
+// using the default render system are generated in parallel before any render system is created, so the
+// preparation time of those render systems overlap. Render systems given as a creation function are created
+// entirely on the render thread, so all of their time is counted as creation time
+
+lazy_static!
+{
+    static ref RENDER_SYSTEM_INIT_TIMES: Mutex<Vec<RenderSystemInitTime>> = Mutex::new(Vec::new());
+}
+
+/// How long it took to create a render system. Times are in milliseconds
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderSystemInitTime
+{
+    /// The name the render system was given when it was added
+    pub name: String,
+    /// Time spent generating the shaders of the render system off of the render thread
+    pub preparation_time: f32,
+    /// Time spent creating the OpenGL resources of the render system on the render thread
+    pub creation_time: f32,
+}
+
+/// Get how long it took to create each render system, in the order the render systems were added. If the
+/// render systems were created again after the GL context was lost, the times of doing so are returned
+pub fn render_system_init_times() -> Vec<RenderSystemInitTime>
+{
+    RENDER_SYSTEM_INIT_TIMES.lock().clone()
+}
+
+/// Stores how long it took to create each render system
+///
+/// `init_times` - the time taken to create each render system
+pub(crate) fn record_render_system_init_times(init_times: Vec<RenderSystemInitTime>)
+{
+    *RENDER_SYSTEM_INIT_TIMES.lock() = init_times;
+}
@@ -0,0 +1,228 @@
+use std::any::TypeId;
+use std::mem::size_of;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::error;
+use crate::objects::ecs::{ECS, TypeIdentifier};
+
+// The hash of the game state is recorded into the game history every few frames. Replaying the history
+// simulates the game again, and the hash of the replayed state is compared against the recorded hash at
+// the same point, so a client that changed its game state outside of the game logic produces a history
+// that does not verify. Only components registered for hashing are included, so that components that are
+// not relevant to gameplay, such as those used for rendering, do not cause false mismatches
+//
+// The hash is FNV-1a over the serialized components, which unlike the standard library hasher gives the
+// same result across builds and platforms. The components are serialized rather than hashed as they are stored,
+// as the padding bytes of a stored component can hold any value. Components are ordered by their registered name, as type IDs
+// differ between builds
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+lazy_static!
+{
+    static ref HASHED_COMPONENTS: Mutex<Vec<HashedComponent>> = Mutex::new(Vec::new());
+    static ref STATE_HASH_SETTINGS: Mutex<StateHashSettings> = Mutex::new(StateHashSettings{ interval: 0, frames_since_hash: 0 });
+    static ref VERIFICATION_RESULTS: Mutex<StateHashVerification> = Mutex::new(StateHashVerification::default());
+}
+
+#[derive(Copy, Clone)]
+struct HashedComponent
+{
+    name: &'static str,
+    type_id: TypeIdentifier,
+    size: usize,
+    serialize: fn(&[u8]) -> Option<Vec<u8>>,
+}
+
+struct StateHashSettings
+{
+    interval: u32,
+    frames_since_hash: u32,
+}
+
+/// Results of comparing the recorded hashes of the game state against the replayed game state
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateHashVerification
+{
+    /// Number of recorded hashes that matched the replayed state
+    pub matched: u64,
+    /// The frames of the replay at which the recorded hash did not match the replayed state
+    pub mismatched_frames: Vec<usize>,
+}
+
+impl StateHashVerification
+{
+    /// Checks if every recorded hash compared so far matched the replayed state
+    pub fn is_verified(&self) -> bool
+    {
+        self.mismatched_frames.is_empty()
+    }
+}
+
+/// Includes the component in the hash of the game state. Registering a type again replaces its name
+///
+/// `name` - the name of the component, which must be the same for all clients whose hashes are compared
+pub fn register_hashed_component<T: 'static + Copy + Serialize>(name: &'static str)
+{
+    let type_id = TypeIdentifier::from(TypeId::of::<T>());
+    let mut hashed_components = HASHED_COMPONENTS.lock();
+
+    hashed_components.retain(|x| x.type_id != type_id);
+    hashed_components.push(HashedComponent{ name, type_id, size: size_of::<T>(), serialize: serialized_component::<T> });
+    hashed_components.sort_by_key(|x| x.name);
+}
+
+/// Specifies how often the hash of the game state is recorded into the game history
+///
+/// `interval` - the number of frames between recorded hashes. If 0, no hashes are recorded
+pub fn set_state_hash_interval(interval: u32)
+{
+    let mut settings = STATE_HASH_SETTINGS.lock();
+    settings.interval = interval;
+    settings.frames_since_hash = 0;
+}
+
+/// Get the results of verifying the recorded hashes of the game state during a replay
+pub fn state_hash_verification() -> StateHashVerification
+{
+    VERIFICATION_RESULTS.lock().clone()
+}
+
+/// Calculates the hash of the components of all entities that are registered for hashing
+///
+/// `ecs` - the ECS holding the game state
+pub fn hash_game_state(ecs: &ECS) -> u64
+{
+    let mut hash = FNV_OFFSET_BASIS;
+
+    for component in HASHED_COMPONENTS.lock().iter()
+    {
+        if !ecs.is_type_registered(component.type_id)
+        {
+            continue;
+        }
+
+        hash = fnv_hash(hash, component.name.as_bytes());
+
+        // Entities are returned in order of their IDs, which are the same for every client
+        for entity in ecs.get_indexes_for_components(&[component.type_id])
+        {
+            if let Some(bytes) = ecs.get_component_bytes(entity, component.type_id, component.size).and_then(component.serialize)
+            {
+                hash = fnv_hash(hash, &entity.get_entity_instance().to_le_bytes());
+                hash = fnv_hash(hash, &bytes);
+            }
+        }
+    }
+
+    hash
+}
+
+/// Get the hash of the game state if it is due to be recorded this frame
+///
+/// `ecs` - the ECS holding the game state
+pub(crate) fn hash_if_due(ecs: &ECS) -> Option<u64>
+{
+    {
+        let mut settings = STATE_HASH_SETTINGS.lock();
+
+        if settings.interval == 0
+        {
+            return None;
+        }
+
+        settings.frames_since_hash += 1;
+
+        if settings.frames_since_hash < settings.interval
+        {
+            return None;
+        }
+
+        settings.frames_since_hash = 0;
+    }
+
+    Some(hash_game_state(ecs))
+}
+
+/// Compares a recorded hash against the hash of the replayed game state, recording the result
+///
+/// `ecs` - the ECS holding the replayed game state
+/// `recorded_hash` - the hash recorded at this point of the game history
+/// `frame_index` - the frame of the replay being compared
+pub(crate) fn verify_recorded_hash(ecs: &ECS, recorded_hash: u64, frame_index: usize)
+{
+    let replayed_hash = hash_game_state(ecs);
+    let mut results = VERIFICATION_RESULTS.lock();
+
+    if replayed_hash == recorded_hash
+    {
+        results.matched += 1;
+    }
+    else
+    {
+        error!("Game state hash mismatch at frame {}: recorded {:016x}, replayed {:016x}", frame_index, recorded_hash, replayed_hash);
+        results.mismatched_frames.push(frame_index);
+    }
+}
+
+/// Serializes a component from the bytes it is stored as in the ECS, leaving out any padding bytes
+///
+/// `bytes` - the bytes of the component
+fn serialized_component<T: Copy + Serialize>(bytes: &[u8]) -> Option<Vec<u8>>
+{
+    let value = unsafe
+        {
+            std::ptr::read_unaligned(bytes.as_ptr() as *const T)
+        };
+
+    bincode::serialize(&value).ok()
+}
+
+/// Adds the bytes to the running FNV-1a hash
+///
+/// `hash` - the hash of the bytes before these bytes
+/// `bytes` - the bytes to add to the hash
+fn fnv_hash(mut hash: u64, bytes: &[u8]) -> u64
+{
+    for byte in bytes
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serde::{Deserialize, Serialize};
+    use crate::exports::state_hash::serialized_component;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Serialize, Deserialize)]
+    struct PaddedComponent
+    {
+        flag: u8,
+        value: u32,
+    }
+
+    #[test]
+    fn padding_not_serialized()
+    {
+        // The three bytes after the flag are padding
+        let mut first_bytes = vec![1, 0, 0, 0];
+        first_bytes.extend_from_slice(&7_u32.to_ne_bytes());
+
+        let mut second_bytes = vec![1, 0xAB, 0xCD, 0xEF];
+        second_bytes.extend_from_slice(&7_u32.to_ne_bytes());
+
+        let first = serialized_component::<PaddedComponent>(&first_bytes).unwrap();
+        let second = serialized_component::<PaddedComponent>(&second_bytes).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(5, first.len());
+    }
+}
@@ -0,0 +1,154 @@
+use std::path::PathBuf;
+use lazy_static::lazy_static;
+use nalgebra_glm::{TVec3, TVec4, vec3, vec4};
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+use crate::exports::ui_layout::{Anchor, UiLength};
+
+// Text labels draw text with a TrueType font, either over the window, such as for scores and menus, or in
+// the world at the position of their entity, such as for signs and damage numbers. They are drawn in a UI
+// pass after post-processing, so effects such as bloom and colour grading do not change the text.
+//
+// The glyphs of a font are rasterized into an atlas on the render thread the first time they are drawn, at
+// the pixel size the font was loaded with. Text drawn larger than that size is magnified from the atlas, so
+// fonts should be loaded at the largest size they are drawn at. Fonts are registered on any thread and
+// loaded the first time a label using them is drawn
+
+lazy_static!
+{
+    static ref FONTS: Mutex<Vec<(PathBuf, f32)>> = Mutex::new(Vec::new());
+}
+
+/// Refers to a font text is drawn with, given by load_font
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct FontId
+{
+    index: usize,
+}
+
+/// How the lines of a label are aligned with each other
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TextAlignment
+{
+    Left,
+    Centre,
+    Right,
+}
+
+/// Where a label is drawn
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextPlacement
+{
+    /// The label is drawn over the window at the anchor of the safe area, regardless of where its entity is
+    Screen
+    {
+        anchor: Anchor,
+        /// Distance the label is moved from its anchor, as with UiElement::with_offset
+        offset: (UiLength, UiLength),
+    },
+    /// The label is drawn in the world facing the camera, with the bottom centre of the label at the
+    /// position of its entity. The entity must have a position
+    World
+    {
+        /// Distance from the position of the entity to the bottom centre of the label
+        offset: TVec3<f32>,
+        /// If true, the label is hidden where geometry is in front of it
+        occluded: bool,
+    },
+}
+
+/// Draws text with a TrueType font, either over the window or in the world
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextLabel
+{
+    /// The text to draw. New lines start a new line of the label below the previous one
+    pub text: String,
+    /// The font the text is drawn with
+    pub font: FontId,
+    /// Height of a line of text. For labels drawn over the window this is in pixels before they are
+    /// multiplied by the UI scale; for labels drawn in the world it is in world units
+    pub size: f32,
+    /// Colour of the text, including its opacity
+    pub colour: TVec4<f32>,
+    /// How the lines of the label are aligned with each other
+    pub alignment: TextAlignment,
+    /// Where the label is drawn
+    pub placement: TextPlacement,
+}
+
+impl TextLabel
+{
+    /// Creates a white label drawn over the window at the anchor of the safe area
+    ///
+    /// `text` - the text to draw
+    /// `font` - the font the text is drawn with
+    /// `size` - height of a line of text in pixels, before it is multiplied by the UI scale
+    /// `anchor` - the point of the safe area the label is placed at
+    pub fn screen<T: Into<String>>(text: T, font: FontId, size: f32, anchor: Anchor) -> TextLabel
+    {
+        TextLabel
+        {
+            text: text.into(),
+            font,
+            size,
+            colour: vec4(1.0, 1.0, 1.0, 1.0),
+            alignment: TextAlignment::Left,
+            placement: TextPlacement::Screen{ anchor, offset: (UiLength::Pixels(0.0), UiLength::Pixels(0.0)) },
+        }
+    }
+
+    /// Creates a white label drawn in the world above the position of its entity, hidden behind geometry
+    ///
+    /// `text` - the text to draw
+    /// `font` - the font the text is drawn with
+    /// `size` - height of a line of text in world units
+    pub fn world<T: Into<String>>(text: T, font: FontId, size: f32) -> TextLabel
+    {
+        TextLabel
+        {
+            text: text.into(),
+            font,
+            size,
+            colour: vec4(1.0, 1.0, 1.0, 1.0),
+            alignment: TextAlignment::Centre,
+            placement: TextPlacement::World{ offset: vec3(0.0, 0.0, 0.0), occluded: true },
+        }
+    }
+}
+
+impl FontId
+{
+    /// Get the index of the font among the registered fonts
+    pub(crate) fn index(&self) -> usize
+    {
+        self.index
+    }
+}
+
+/// Registers a TrueType or OpenType font that labels can be drawn with. Registering the same font at the
+/// same size again gives the same font. The font is loaded the first time a label using it is drawn
+///
+/// `location` - the location of the font file
+/// `pixel_size` - the height in pixels that the glyphs of the font are rasterized at
+pub fn load_font<A: Into<PathBuf>>(location: A, pixel_size: f32) -> FontId
+{
+    let location = location.into();
+    let pixel_size = pixel_size.max(1.0);
+    let mut fonts = FONTS.lock();
+
+    if let Some(index) = fonts.iter().position(|x| x.0 == location && x.1 == pixel_size)
+    {
+        return FontId{ index };
+    }
+
+    fonts.push((location, pixel_size));
+    FontId{ index: fonts.len() - 1 }
+}
+
+/// Get the location and pixel size of the fonts registered after the given number of fonts
+///
+/// `number_known` - the number of fonts that were already given
+pub(crate) fn new_fonts(number_known: usize) -> Vec<(PathBuf, f32)>
+{
+    FONTS.lock().iter().skip(number_known).cloned().collect()
+}
@@ -0,0 +1,167 @@
+use hashbrown::HashSet;
+
+/// Number of spaces a tab is laid out as
+const TAB_WIDTH: u32 = 4;
+
+/// Character shown for characters without a glyph, if the font has a glyph for it
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+/// Character shown for characters without a glyph when the font has no replacement character
+const LAST_RESORT_CHARACTER: char = '?';
+
+/// The characters that a font has glyphs for
+pub trait GlyphCoverage
+{
+    /// Checks if the font has a glyph for the character
+    ///
+    /// `character` - the character to check
+    fn has_glyph(&self, character: char) -> bool;
+}
+
+impl GlyphCoverage for HashSet<char>
+{
+    fn has_glyph(&self, character: char) -> bool
+    {
+        self.contains(&character)
+    }
+}
+
+impl<F: Fn(char) -> bool> GlyphCoverage for F
+{
+    fn has_glyph(&self, character: char) -> bool
+    {
+        self(character)
+    }
+}
+
+/// A glyph of laid out text. The line and column give the place of the glyph in a grid of
+/// characters; converting them to a position on the screen is left to the font
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ShapedGlyph
+{
+    /// The character to draw, which is a fallback if the font has no glyph for the original character
+    pub character: char,
+    /// The byte offset of the original character in the text
+    pub byte_offset: usize,
+    pub line: u32,
+    pub column: u32,
+    /// If true, the glyph is a mark drawn over the previous glyph, and shares its column
+    pub combining: bool,
+}
+
+/// Lays out UTF-8 text of scripts that are written left to right without joining characters, such as Latin,
+/// Greek and Cyrillic. Lines are broken at new lines, combining marks are placed on the character
+/// before them, and characters the font has no glyph for are replaced with a fallback
+///
+/// `text` - the text to lay out
+/// `coverage` - the characters the font has glyphs for
+pub fn shape_text<C: GlyphCoverage>(text: &str, coverage: &C) -> Vec<ShapedGlyph>
+{
+    let mut glyphs = Vec::with_capacity(text.len());
+    let mut line = 0;
+    let mut column = 0;
+    let mut previous_character = None;
+
+    for (byte_offset, character) in text.char_indices()
+    {
+        match character
+        {
+            '\n' =>
+                {
+                    // A \r\n line ending was already handled by the \r
+                    if previous_character != Some('\r')
+                    {
+                        line += 1;
+                        column = 0;
+                    }
+                },
+            '\r' =>
+                {
+                    line += 1;
+                    column = 0;
+                },
+            '\t' =>
+                {
+                    for _ in 0..TAB_WIDTH
+                    {
+                        glyphs.push(ShapedGlyph{ character: ' ', byte_offset, line, column, combining: false });
+                        column += 1;
+                    }
+                },
+            _ if is_combining_mark(character) =>
+                {
+                    // Marks without a glyph are dropped instead of drawing a fallback over the previous glyph
+                    if column != 0 && coverage.has_glyph(character)
+                    {
+                        glyphs.push(ShapedGlyph{ character, byte_offset, line, column: column - 1, combining: true });
+                    }
+                },
+            _ if character.is_control() || is_zero_width(character) => {},
+            _ =>
+                {
+                    glyphs.push(ShapedGlyph{ character: resolve_glyph(character, coverage), byte_offset, line, column, combining: false });
+                    column += 1;
+                }
+        }
+
+        previous_character = Some(character);
+    }
+
+    glyphs
+}
+
+/// Finds the character to draw for the given character. If the font has no glyph for it, a similar
+/// character is used if there is one, otherwise the replacement character
+///
+/// `character` - the character to draw
+/// `coverage` - the characters the font has glyphs for
+pub fn resolve_glyph<C: GlyphCoverage>(character: char, coverage: &C) -> char
+{
+    if coverage.has_glyph(character)
+    {
+        return character;
+    }
+
+    let similar_character = match character
+    {
+        '\u{00A0}' | '\u{2002}'..='\u{200A}' | '\u{202F}' => Some(' '),
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => Some('-'),
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => Some('\''),
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' | '\u{00AB}' | '\u{00BB}' => Some('"'),
+        '\u{2022}' | '\u{00B7}' => Some('*'),
+        _ => None
+    };
+
+    if let Some(i) = similar_character
+    {
+        if coverage.has_glyph(i)
+        {
+            return i;
+        }
+    }
+
+    if coverage.has_glyph(REPLACEMENT_CHARACTER)
+    {
+        REPLACEMENT_CHARACTER
+    }
+    else
+    {
+        LAST_RESORT_CHARACTER
+    }
+}
+
+/// Checks if the character is a mark that is drawn over the character before it
+///
+/// `character` - the character to check
+fn is_combining_mark(character: char) -> bool
+{
+    matches!(character, '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' | '\u{20D0}'..='\u{20FF}' | '\u{FE20}'..='\u{FE2F}')
+}
+
+/// Checks if the character takes up no space and is not drawn
+///
+/// `character` - the character to check
+fn is_zero_width(character: char) -> bool
+{
+    matches!(character, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
@@ -0,0 +1,287 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use egui::{Context, Event, Modifiers, PointerButton, Pos2, RawInput, Rect, Vec2};
+use glfw::{Action, Key, MouseButton, WindowEvent};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::error;
+use crate::exports::accessibility::ui_scale;
+use crate::render_components::tooling_ui_pass::ToolingUiPass;
+
+// The tooling UI lets games build debugging panels, entity inspectors and settings menus with egui without
+// leaving the engine. A function given by the user builds the interface on the render thread every frame, and
+// the interface is drawn over everything else in the window as the last pass of the frame. The interface is
+// given the same window events that are stored in the input state, including injected input, and is sized by
+// the UI scale of the accessibility settings.
+//
+// Input used by the interface is still given to the game. Logic that should ignore input while the user works
+// with the interface, such as moving the camera, can check tooling_ui_wants_pointer and tooling_ui_wants_keyboard.
+// Only available with the tooling_ui feature
+
+lazy_static!
+{
+    static ref REQUESTED_UI_BUILDER: Mutex<Option<Option<ToolingUiBuilder>>> = Mutex::new(None);
+    static ref UI_EVENTS: Mutex<Vec<WindowEvent>> = Mutex::new(Vec::new());
+}
+
+static UI_ENABLED: AtomicBool = AtomicBool::new(false);
+static WANTS_POINTER: AtomicBool = AtomicBool::new(false);
+static WANTS_KEYBOARD: AtomicBool = AtomicBool::new(false);
+
+/// Number of points the interface scrolls for every line scrolled with the mouse wheel
+const SCROLL_POINTS_PER_LINE: f32 = 50.0;
+
+/// Function that builds the tooling UI, called on the render thread once per frame
+pub type ToolingUiBuilder = Box<dyn FnMut(&Context) + Send>;
+
+/// Builds the tooling UI every frame and draws it over the window
+pub(crate) struct ToolingUi
+{
+    context: Context,
+    builder: Option<ToolingUiBuilder>,
+    pass: Option<ToolingUiPass>,
+    start_time: Instant,
+    pointer_position: Pos2,
+    modifiers: Modifiers,
+}
+
+/// Sets the function that builds the tooling UI every frame, starting in the next frame that is rendered
+///
+/// `builder` - the function building the interface, or None to stop drawing the interface
+pub fn set_tooling_ui(builder: Option<ToolingUiBuilder>)
+{
+    let enabled = builder.is_some();
+
+    UI_ENABLED.store(enabled, Ordering::Release);
+    *REQUESTED_UI_BUILDER.lock() = Some(builder);
+
+    if !enabled
+    {
+        UI_EVENTS.lock().clear();
+    }
+}
+
+/// Checks if the pointer was over the tooling UI or was being used by it in the last frame, in which case
+/// the game should usually ignore the mouse
+pub fn tooling_ui_wants_pointer() -> bool
+{
+    WANTS_POINTER.load(Ordering::Acquire)
+}
+
+/// Checks if the tooling UI was taking keyboard input in the last frame, such as when a text field has focus,
+/// in which case the game should usually ignore the keyboard
+pub fn tooling_ui_wants_keyboard() -> bool
+{
+    WANTS_KEYBOARD.load(Ordering::Acquire)
+}
+
+/// Stores a window event to be given to the tooling UI in the next frame it is built, if the interface is enabled
+///
+/// `event` - the event received by the window
+pub(crate) fn queue_window_event(event: &WindowEvent)
+{
+    if !UI_ENABLED.load(Ordering::Acquire)
+    {
+        return;
+    }
+
+    match event
+    {
+        WindowEvent::Key(..) | WindowEvent::Char(..) | WindowEvent::MouseButton(..) | WindowEvent::CursorPos(..) | WindowEvent::Scroll(..) =>
+            UI_EVENTS.lock().push(event.clone()),
+        _ => {}
+    }
+}
+
+impl ToolingUi
+{
+    /// Creates a tooling UI that draws nothing until a function building the interface is set
+    pub fn new() -> ToolingUi
+    {
+        ToolingUi
+        {
+            context: Context::default(),
+            builder: None,
+            pass: None,
+            start_time: Instant::now(),
+            pointer_position: Pos2::ZERO,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Builds the interface for this frame and draws it into the window. An OpenGL context must be current
+    ///
+    /// `window_dimensions` - the dimensions of the window
+    pub fn render(&mut self, window_dimensions: (i32, i32))
+    {
+        if let Some(builder) = REQUESTED_UI_BUILDER.lock().take()
+        {
+            self.builder = builder;
+        }
+
+        let events = std::mem::take(&mut *UI_EVENTS.lock());
+
+        if self.builder.is_none() || window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            WANTS_POINTER.store(false, Ordering::Release);
+            WANTS_KEYBOARD.store(false, Ordering::Release);
+            return;
+        }
+
+        // The pass must exist before the first frame is built, as that frame holds the font texture
+        if self.pass.is_none()
+        {
+            match ToolingUiPass::new()
+            {
+                Ok(i) => self.pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the tooling UI pass, disabling the tooling UI: {}", err);
+                        set_tooling_ui(None);
+                        return;
+                    }
+            }
+        }
+
+        let pixels_per_point = ui_scale().max(0.1);
+        let events = events.into_iter().filter_map(|x| self.convert_event(x, pixels_per_point)).collect::<Vec<Event>>();
+
+        let raw_input = RawInput
+        {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(window_dimensions.0 as f32, window_dimensions.1 as f32) / pixels_per_point)),
+            pixels_per_point: Some(pixels_per_point),
+            time: Some(self.start_time.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events,
+            ..RawInput::default()
+        };
+
+        let builder = self.builder.as_mut().unwrap();
+        let output = self.context.run(raw_input, |context| builder(context));
+
+        WANTS_POINTER.store(self.context.wants_pointer_input(), Ordering::Release);
+        WANTS_KEYBOARD.store(self.context.wants_keyboard_input(), Ordering::Release);
+
+        let meshes = self.context.tessellate(output.shapes);
+        self.pass.as_mut().unwrap().apply(&output.textures_delta, &meshes, pixels_per_point, window_dimensions);
+    }
+
+    /// Converts a window event into the event egui expects, in points rather than pixels
+    ///
+    /// `event` - the event received by the window
+    /// `pixels_per_point` - the number of pixels of the window in each point of the interface
+    fn convert_event(&mut self, event: WindowEvent, pixels_per_point: f32) -> Option<Event>
+    {
+        match event
+        {
+            WindowEvent::CursorPos(x, y) =>
+                {
+                    self.pointer_position = Pos2::new(x as f32 / pixels_per_point, y as f32 / pixels_per_point);
+                    Some(Event::PointerMoved(self.pointer_position))
+                },
+            WindowEvent::MouseButton(button, action, modifiers) =>
+                {
+                    self.modifiers = convert_modifiers(modifiers);
+
+                    let button = match button
+                    {
+                        MouseButton::Button1 => PointerButton::Primary,
+                        MouseButton::Button2 => PointerButton::Secondary,
+                        MouseButton::Button3 => PointerButton::Middle,
+                        _ => return None
+                    };
+
+                    Some(Event::PointerButton{ pos: self.pointer_position, button, pressed: action != Action::Release, modifiers: self.modifiers })
+                },
+            WindowEvent::Scroll(x, y) => Some(Event::Scroll(Vec2::new(x as f32, y as f32) * SCROLL_POINTS_PER_LINE)),
+            WindowEvent::Char(character) if !character.is_control() => Some(Event::Text(character.to_string())),
+            WindowEvent::Key(key, _, action, modifiers) =>
+                {
+                    self.modifiers = convert_modifiers(modifiers);
+                    convert_key(key).map(|key| Event::Key{ key, pressed: action != Action::Release, modifiers: self.modifiers })
+                },
+            _ => None
+        }
+    }
+}
+
+/// Converts the modifier keys held during a window event into those egui expects
+///
+/// `modifiers` - the modifier keys held
+fn convert_modifiers(modifiers: glfw::Modifiers) -> Modifiers
+{
+    let ctrl = modifiers.contains(glfw::Modifiers::Control);
+
+    Modifiers
+    {
+        alt: modifiers.contains(glfw::Modifiers::Alt),
+        ctrl,
+        shift: modifiers.contains(glfw::Modifiers::Shift),
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+/// Converts a key of the window into the key egui expects, if egui has such a key
+///
+/// `key` - the key of the window
+fn convert_key(key: Key) -> Option<egui::Key>
+{
+    let key = match key
+    {
+        Key::Down => egui::Key::ArrowDown,
+        Key::Left => egui::Key::ArrowLeft,
+        Key::Right => egui::Key::ArrowRight,
+        Key::Up => egui::Key::ArrowUp,
+        Key::Escape => egui::Key::Escape,
+        Key::Tab => egui::Key::Tab,
+        Key::Backspace => egui::Key::Backspace,
+        Key::Enter | Key::KpEnter => egui::Key::Enter,
+        Key::Space => egui::Key::Space,
+        Key::Insert => egui::Key::Insert,
+        Key::Delete => egui::Key::Delete,
+        Key::Home => egui::Key::Home,
+        Key::End => egui::Key::End,
+        Key::PageUp => egui::Key::PageUp,
+        Key::PageDown => egui::Key::PageDown,
+        Key::Num0 | Key::Kp0 => egui::Key::Num0,
+        Key::Num1 | Key::Kp1 => egui::Key::Num1,
+        Key::Num2 | Key::Kp2 => egui::Key::Num2,
+        Key::Num3 | Key::Kp3 => egui::Key::Num3,
+        Key::Num4 | Key::Kp4 => egui::Key::Num4,
+        Key::Num5 | Key::Kp5 => egui::Key::Num5,
+        Key::Num6 | Key::Kp6 => egui::Key::Num6,
+        Key::Num7 | Key::Kp7 => egui::Key::Num7,
+        Key::Num8 | Key::Kp8 => egui::Key::Num8,
+        Key::Num9 | Key::Kp9 => egui::Key::Num9,
+        Key::A => egui::Key::A,
+        Key::B => egui::Key::B,
+        Key::C => egui::Key::C,
+        Key::D => egui::Key::D,
+        Key::E => egui::Key::E,
+        Key::F => egui::Key::F,
+        Key::G => egui::Key::G,
+        Key::H => egui::Key::H,
+        Key::I => egui::Key::I,
+        Key::J => egui::Key::J,
+        Key::K => egui::Key::K,
+        Key::L => egui::Key::L,
+        Key::M => egui::Key::M,
+        Key::N => egui::Key::N,
+        Key::O => egui::Key::O,
+        Key::P => egui::Key::P,
+        Key::Q => egui::Key::Q,
+        Key::R => egui::Key::R,
+        Key::S => egui::Key::S,
+        Key::T => egui::Key::T,
+        Key::U => egui::Key::U,
+        Key::V => egui::Key::V,
+        Key::W => egui::Key::W,
+        Key::X => egui::Key::X,
+        Key::Y => egui::Key::Y,
+        Key::Z => egui::Key::Z,
+        _ => return None
+    };
+
+    Some(key)
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+// Blending an entity over the frame only looks right if whatever is behind it has already been drawn.
+// Every frame, the instances of each model that holds a Transparent entity are sorted from the furthest
+// to the nearest to the camera before they are uploaded, so that one draw of the model blends its
+// instances in the right order. Different models are still drawn in the order the draw functions draw
+// them, so transparent models are best drawn from the transparency draw function of a render system,
+// after everything opaque
+
+/// Marks an entity as blended over what is behind it, so that the instances of its model are drawn from
+/// back to front
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transparent;
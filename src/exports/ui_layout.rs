@@ -0,0 +1,272 @@
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+use crate::exports::accessibility::ui_scale;
+
+// Anything drawn over the scene, such as by frame callbacks or the statistics overlay, is positioned in
+// pixels of the window. Rather than working out pixel positions for every resolution, an element is placed
+// relative to an anchor of the window, such as its top right corner, with its offset and size given either
+// in pixels, which are multiplied by the UI scale, or as a percentage of the window. Elements are laid out
+// inside the safe area of the window, which is the window shrunk by the safe area insets, so that they are
+// not cut off by the edges of displays such as televisions with overscan.
+//
+// Laid out rectangles have their origin at the bottom left of the window, as OpenGL does, so they can be
+// passed directly to functions such as glViewport and glScissor
+
+lazy_static!
+{
+    static ref SAFE_AREA_INSETS: Mutex<SafeAreaInsets> = Mutex::new(SafeAreaInsets::default());
+}
+
+/// The point of the safe area an element is placed relative to. The same point of the element is placed at it,
+/// so an element anchored to the top right has its top right corner at the top right of the safe area
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Anchor
+{
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// A distance on the window
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UiLength
+{
+    /// Pixels of the window before they are multiplied by the UI scale
+    Pixels(f32),
+    /// Percentage, from 0 to 100, of the width or height of the area the length is measured in
+    Percent(f32),
+}
+
+/// Distance from each edge of the window that elements are kept away from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SafeAreaInsets
+{
+    pub left: UiLength,
+    pub right: UiLength,
+    pub top: UiLength,
+    pub bottom: UiLength,
+}
+
+/// A rectangle of the window in pixels, from the bottom left of the window
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct UiRect
+{
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Describes where an element drawn over the scene is placed, independent of the dimensions of the window
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UiElement
+{
+    anchor: Anchor,
+    size: (UiLength, UiLength),
+    offset: (UiLength, UiLength),
+    ignore_safe_area: bool,
+}
+
+impl Anchor
+{
+    /// Get how far along the width and height of an area the anchor is, from 0 at the bottom left to 1 at
+    /// the top right
+    fn factors(&self) -> (f32, f32)
+    {
+        match *self
+        {
+            Anchor::TopLeft => (0.0, 1.0),
+            Anchor::Top => (0.5, 1.0),
+            Anchor::TopRight => (1.0, 1.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Centre => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 0.0),
+            Anchor::Bottom => (0.5, 0.0),
+            Anchor::BottomRight => (1.0, 0.0),
+        }
+    }
+}
+
+impl UiLength
+{
+    /// Converts the length into pixels of the window
+    ///
+    /// `extent` - the width or height, in pixels, that a percentage is measured against
+    /// `scale` - the multiplier of lengths given in pixels
+    pub fn to_pixels(&self, extent: i32, scale: f32) -> f32
+    {
+        match *self
+        {
+            UiLength::Pixels(pixels) => pixels * scale,
+            UiLength::Percent(percent) => extent as f32 * percent / 100.0
+        }
+    }
+}
+
+impl Default for SafeAreaInsets
+{
+    fn default() -> Self
+    {
+        SafeAreaInsets::uniform(UiLength::Pixels(0.0))
+    }
+}
+
+impl SafeAreaInsets
+{
+    /// Creates insets that are the same distance from every edge of the window
+    ///
+    /// `inset` - the distance from each edge of the window
+    pub fn uniform(inset: UiLength) -> SafeAreaInsets
+    {
+        SafeAreaInsets{ left: inset, right: inset, top: inset, bottom: inset }
+    }
+}
+
+impl UiRect
+{
+    /// Get the position of the top of the rectangle measured from the top of the window, for use with
+    /// cursor positions, which start at the top left of the window
+    ///
+    /// `window_height` - the height of the window in pixels
+    pub fn top_from_window_top(&self, window_height: i32) -> i32
+    {
+        window_height - (self.y + self.height)
+    }
+
+    /// Checks if a point, measured from the bottom left of the window, is inside the rectangle
+    ///
+    /// `point` - the point to check
+    pub fn contains(&self, point: (i32, i32)) -> bool
+    {
+        point.0 >= self.x && point.0 < self.x + self.width && point.1 >= self.y && point.1 < self.y + self.height
+    }
+
+    /// Checks if a cursor position, measured from the top left of the window, is inside the rectangle
+    ///
+    /// `cursor_position` - the position of the cursor
+    /// `window_height` - the height of the window in pixels
+    pub fn contains_cursor(&self, cursor_position: (i32, i32), window_height: i32) -> bool
+    {
+        self.contains((cursor_position.0, window_height - 1 - cursor_position.1))
+    }
+}
+
+impl UiElement
+{
+    /// Creates an element placed at the anchor of the safe area, with no offset from it
+    ///
+    /// `anchor` - the point of the safe area the element is placed relative to
+    /// `width` - the width of the element. A percentage is of the width of the safe area
+    /// `height` - the height of the element. A percentage is of the height of the safe area
+    pub fn new(anchor: Anchor, width: UiLength, height: UiLength) -> UiElement
+    {
+        UiElement{ anchor, size: (width, height), offset: (UiLength::Pixels(0.0), UiLength::Pixels(0.0)), ignore_safe_area: false }
+    }
+
+    /// Moves the element away from its anchor. For anchors on an edge, positive offsets move the element
+    /// towards the centre of the safe area; for anchors centred along an axis, positive offsets move the
+    /// element right and up
+    ///
+    /// `x` - the horizontal offset. A percentage is of the width of the safe area
+    /// `y` - the vertical offset. A percentage is of the height of the safe area
+    pub fn with_offset(mut self, x: UiLength, y: UiLength) -> Self
+    {
+        self.offset = (x, y);
+        self
+    }
+
+    /// Places the element relative to the entire window instead of the safe area, such as for backgrounds
+    /// that should reach the edges of the display
+    ///
+    /// `ignore_safe_area` - true if the safe area insets are ignored
+    pub fn with_ignore_safe_area(mut self, ignore_safe_area: bool) -> Self
+    {
+        self.ignore_safe_area = ignore_safe_area;
+        self
+    }
+
+    /// Finds the rectangle of the window the element covers, using the current safe area insets and UI scale
+    ///
+    /// `window_dimensions` - the dimensions of the window in pixels
+    pub fn layout(&self, window_dimensions: (i32, i32)) -> UiRect
+    {
+        let area = if self.ignore_safe_area
+        {
+            UiRect{ x: 0, y: 0, width: window_dimensions.0, height: window_dimensions.1 }
+        }
+        else
+        {
+            safe_area(window_dimensions)
+        };
+
+        self.layout_in(area, ui_scale())
+    }
+
+    /// Finds the rectangle the element covers when placed inside the given area, such as to lay out elements
+    /// inside a panel
+    ///
+    /// `area` - the rectangle the element is placed inside
+    /// `scale` - the multiplier of lengths given in pixels
+    pub fn layout_in(&self, area: UiRect, scale: f32) -> UiRect
+    {
+        let (anchor_x, anchor_y) = self.anchor.factors();
+
+        let width = self.size.0.to_pixels(area.width, scale).max(0.0);
+        let height = self.size.1.to_pixels(area.height, scale).max(0.0);
+
+        // An edge anchor on the right or top flips the offset so that it always moves towards the centre
+        let offset_direction = |factor: f32| if factor > 0.5 { -1.0 } else { 1.0 };
+        let offset_x = self.offset.0.to_pixels(area.width, scale) * offset_direction(anchor_x);
+        let offset_y = self.offset.1.to_pixels(area.height, scale) * offset_direction(anchor_y);
+
+        let x = area.x as f32 + area.width as f32 * anchor_x - width * anchor_x + offset_x;
+        let y = area.y as f32 + area.height as f32 * anchor_y - height * anchor_y + offset_y;
+
+        UiRect{ x: x.round() as i32, y: y.round() as i32, width: width.round() as i32, height: height.round() as i32 }
+    }
+}
+
+/// Changes the distance from each edge of the window that elements are kept away from
+///
+/// `insets` - the distance from each edge of the window
+pub fn set_safe_area_insets(insets: SafeAreaInsets)
+{
+    *SAFE_AREA_INSETS.lock() = insets;
+}
+
+/// Get the distance from each edge of the window that elements are kept away from
+pub fn safe_area_insets() -> SafeAreaInsets
+{
+    *SAFE_AREA_INSETS.lock()
+}
+
+/// Finds the area of the window that elements are placed in, which is the window shrunk by the safe area insets.
+/// Inset pixels are multiplied by the UI scale
+///
+/// `window_dimensions` - the dimensions of the window in pixels
+pub fn safe_area(window_dimensions: (i32, i32)) -> UiRect
+{
+    let insets = safe_area_insets();
+    let scale = ui_scale();
+
+    let left = insets.left.to_pixels(window_dimensions.0, scale).max(0.0).round() as i32;
+    let right = insets.right.to_pixels(window_dimensions.0, scale).max(0.0).round() as i32;
+    let top = insets.top.to_pixels(window_dimensions.1, scale).max(0.0).round() as i32;
+    let bottom = insets.bottom.to_pixels(window_dimensions.1, scale).max(0.0).round() as i32;
+
+    UiRect
+    {
+        x: left,
+        y: bottom,
+        width: (window_dimensions.0 - left - right).max(0),
+        height: (window_dimensions.1 - top - bottom).max(0),
+    }
+}
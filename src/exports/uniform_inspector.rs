@@ -0,0 +1,419 @@
+use std::fmt;
+use std::mem::size_of;
+use std::ptr::copy_nonoverlapping;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use nalgebra_glm::{TMat4, TVec3, TVec4};
+use parking_lot::Mutex;
+use crate::exports::logic_components::RenderSystemIndex;
+use crate::render_components::mapped_buffer::{BufferWriteInfo, MappedBuffer};
+use crate::render_system::initialize_logic::UniformDataLocation;
+use crate::render_system::system_information::UniformType;
+
+// The uniform inspector lists the uniforms of every render system along with the value last written to
+// them, and lets those values be replaced while the engine runs, such as when tuning lighting constants.
+// Uniform buffers are only written by the CPU, so every render system keeps a copy of what its draw
+// functions wrote; while the inspector is enabled, that copy is read after each frame is rendered.
+//
+// An overridden uniform is given the override instead of the value written by the draw function, until
+// the override is removed. Overrides are checked against the type of the uniform when they are set, so
+// the inspector must have listed the uniform at least once.
+//
+// The same operations are available as text commands, for a debug console to forward to run_uniform_command:
+//
+//     list [render system]                      lists the uniforms, of every render system if none is given
+//     set <render system> <uniform> <values>    overrides a uniform, with the values separated by spaces or commas
+//     reset <render system> [uniform]           removes the override of the uniform, or of every uniform
+//
+// Render systems are given by the name they were registered with or by their index
+
+lazy_static!
+{
+    static ref UNIFORM_INSPECTOR_ENABLED: Mutex<bool> = Mutex::new(false);
+    static ref INSPECTED_UNIFORMS: Mutex<Vec<InspectedUniform>> = Mutex::new(Vec::new());
+    static ref UNIFORM_OVERRIDES: Mutex<HashMap<(RenderSystemIndex, String), UniformValue>> = Mutex::new(HashMap::new());
+}
+
+/// The value of a uniform, matching the type the uniform was declared with
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformValue
+{
+    Vec3(TVec3<f32>),
+    Mat4(TMat4<f32>),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+    UIntArray(Vec<u32>),
+    FloatArray(Vec<f32>),
+    Vec3Array(Vec<TVec3<f32>>),
+    Vec4Array(Vec<TVec4<f32>>),
+    Mat4Array(Vec<TMat4<f32>>),
+}
+
+/// A uniform of a render system, as listed by the inspector
+#[derive(Clone, Debug, PartialEq)]
+pub struct InspectedUniform
+{
+    pub render_system: RenderSystemIndex,
+    /// True if the uniform belongs to the second render pass of the render system
+    pub second_pass: bool,
+    pub name: String,
+    /// The value given to the uniform when the render system was last drawn
+    pub value: UniformValue,
+    /// True if the value is an override rather than the value written by the draw function
+    pub overridden: bool,
+}
+
+impl UniformValue
+{
+    /// Get the name of the type of the value, as it is declared in a shader
+    pub fn type_name(&self) -> String
+    {
+        match self
+        {
+            UniformValue::Vec3(_) => "vec3".to_string(),
+            UniformValue::Mat4(_) => "mat4".to_string(),
+            UniformValue::Int(_) => "int".to_string(),
+            UniformValue::UInt(_) => "uint".to_string(),
+            UniformValue::Float(_) => "float".to_string(),
+            UniformValue::UIntArray(i) => format!("uint[{}]", i.len()),
+            UniformValue::FloatArray(i) => format!("float[{}]", i.len()),
+            UniformValue::Vec3Array(i) => format!("vec3[{}]", i.len()),
+            UniformValue::Vec4Array(i) => format!("vec4[{}]", i.len()),
+            UniformValue::Mat4Array(i) => format!("mat4[{}]", i.len()),
+        }
+    }
+
+    /// Checks if the value has the same type as the given value, including the number of elements of arrays
+    ///
+    /// `other` - the value to compare the type of
+    pub fn same_type(&self, other: &UniformValue) -> bool
+    {
+        self.type_name() == other.type_name()
+    }
+
+    /// Reads a value of the same type as this value from text holding its components, separated by spaces
+    /// or commas. Matrices are given column by column
+    ///
+    /// `text` - the components of the value
+    pub fn parse_same_type(&self, text: &str) -> Result<UniformValue, String>
+    {
+        let components = text.split(|x: char| x.is_whitespace() || x == ',').filter(|x| !x.is_empty()).collect::<Vec<&str>>();
+        let expected_components = self.components().len();
+
+        if components.len() != expected_components
+        {
+            return Err(format!("A {} requires {} values, but {} were given", self.type_name(), expected_components, components.len()));
+        }
+
+        let parse_float = |x: &str| x.parse::<f32>().map_err(|_| format!("\"{}\" is not a number", x));
+        let parse_int = |x: &str| x.parse::<i32>().map_err(|_| format!("\"{}\" is not an integer", x));
+        let parse_uint = |x: &str| x.parse::<u32>().map_err(|_| format!("\"{}\" is not a non-negative integer", x));
+
+        let floats = || components.iter().map(|x| parse_float(x)).collect::<Result<Vec<f32>, String>>();
+        let vec3s = |x: Vec<f32>| x.chunks(3).map(TVec3::from_column_slice).collect::<Vec<TVec3<f32>>>();
+        let vec4s = |x: Vec<f32>| x.chunks(4).map(TVec4::from_column_slice).collect::<Vec<TVec4<f32>>>();
+        let mat4s = |x: Vec<f32>| x.chunks(16).map(TMat4::from_column_slice).collect::<Vec<TMat4<f32>>>();
+
+        let value = match self
+        {
+            UniformValue::Vec3(_) => UniformValue::Vec3(vec3s(floats()?)[0]),
+            UniformValue::Mat4(_) => UniformValue::Mat4(mat4s(floats()?)[0]),
+            UniformValue::Int(_) => UniformValue::Int(parse_int(components[0])?),
+            UniformValue::UInt(_) => UniformValue::UInt(parse_uint(components[0])?),
+            UniformValue::Float(_) => UniformValue::Float(parse_float(components[0])?),
+            UniformValue::UIntArray(_) => UniformValue::UIntArray(components.iter().map(|x| parse_uint(x)).collect::<Result<Vec<u32>, String>>()?),
+            UniformValue::FloatArray(_) => UniformValue::FloatArray(floats()?),
+            UniformValue::Vec3Array(_) => UniformValue::Vec3Array(vec3s(floats()?)),
+            UniformValue::Vec4Array(_) => UniformValue::Vec4Array(vec4s(floats()?)),
+            UniformValue::Mat4Array(_) => UniformValue::Mat4Array(mat4s(floats()?)),
+        };
+
+        Ok(value)
+    }
+
+    /// Get the components of the value, with matrices given column by column
+    fn components(&self) -> Vec<f32>
+    {
+        match self
+        {
+            UniformValue::Vec3(i) => i.as_slice().to_vec(),
+            UniformValue::Mat4(i) => i.as_slice().to_vec(),
+            UniformValue::Int(i) => vec![*i as f32],
+            UniformValue::UInt(i) => vec![*i as f32],
+            UniformValue::Float(i) => vec![*i],
+            UniformValue::UIntArray(i) => i.iter().map(|x| *x as f32).collect(),
+            UniformValue::FloatArray(i) => i.clone(),
+            UniformValue::Vec3Array(i) => i.iter().flat_map(|x| x.as_slice().to_vec()).collect(),
+            UniformValue::Vec4Array(i) => i.iter().flat_map(|x| x.as_slice().to_vec()).collect(),
+            UniformValue::Mat4Array(i) => i.iter().flat_map(|x| x.as_slice().to_vec()).collect(),
+        }
+    }
+
+    /// Reads the value of a uniform from the copy of the uniform buffer written by the CPU
+    ///
+    /// `uniform_type` - the type the uniform was declared with
+    /// `cpu_values` - the copy of the uniform buffer holding the uniform
+    /// `location` - where the uniform is stored in the uniform buffer
+    pub(crate) fn read(uniform_type: UniformType, cpu_values: &[u8], location: &UniformDataLocation) -> UniformValue
+    {
+        match uniform_type
+        {
+            UniformType::Vec3 => UniformValue::Vec3(read_elements(cpu_values, location, 1)[0]),
+            UniformType::Mat4x4Float => UniformValue::Mat4(read_elements(cpu_values, location, 1)[0]),
+            UniformType::Int => UniformValue::Int(read_elements(cpu_values, location, 1)[0]),
+            UniformType::UInt => UniformValue::UInt(read_elements(cpu_values, location, 1)[0]),
+            UniformType::Float => UniformValue::Float(read_elements(cpu_values, location, 1)[0]),
+            UniformType::UIntArray(i) => UniformValue::UIntArray(read_elements(cpu_values, location, i as usize)),
+            UniformType::FloatArray(i) => UniformValue::FloatArray(read_elements(cpu_values, location, i as usize)),
+            UniformType::Vec3Array(i) => UniformValue::Vec3Array(read_elements(cpu_values, location, i as usize)),
+            UniformType::Vec4Array(i) => UniformValue::Vec4Array(read_elements(cpu_values, location, i as usize)),
+            UniformType::Mat4Array(i) => UniformValue::Mat4Array(read_elements(cpu_values, location, i as usize)),
+        }
+    }
+
+    /// Writes the value into a uniform buffer and the copy of it kept by the CPU
+    ///
+    /// `write_info` - the uniform buffer to write to
+    /// `cpu_values` - the copy of the uniform buffer kept by the CPU
+    /// `location` - where the uniform is stored in the uniform buffer
+    pub(crate) fn write(&self, write_info: BufferWriteInfo, cpu_values: &mut [u8], location: &UniformDataLocation)
+    {
+        match self
+        {
+            UniformValue::Vec3(i) => write_elements(write_info, cpu_values, location, &[*i]),
+            UniformValue::Mat4(i) => write_elements(write_info, cpu_values, location, &[*i]),
+            UniformValue::Int(i) => write_elements(write_info, cpu_values, location, &[*i]),
+            UniformValue::UInt(i) => write_elements(write_info, cpu_values, location, &[*i]),
+            UniformValue::Float(i) => write_elements(write_info, cpu_values, location, &[*i]),
+            UniformValue::UIntArray(i) => write_elements(write_info, cpu_values, location, i),
+            UniformValue::FloatArray(i) => write_elements(write_info, cpu_values, location, i),
+            UniformValue::Vec3Array(i) => write_elements(write_info, cpu_values, location, i),
+            UniformValue::Vec4Array(i) => write_elements(write_info, cpu_values, location, i),
+            UniformValue::Mat4Array(i) => write_elements(write_info, cpu_values, location, i),
+        }
+    }
+}
+
+impl fmt::Display for UniformValue
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let components = match self
+        {
+            UniformValue::Int(i) => vec![i.to_string()],
+            UniformValue::UInt(i) => vec![i.to_string()],
+            UniformValue::UIntArray(i) => i.iter().map(|x| x.to_string()).collect(),
+            _ => self.components().iter().map(|x| x.to_string()).collect()
+        };
+
+        write!(f, "{}", components.join(" "))
+    }
+}
+
+/// Copies a value into the copy of a uniform buffer kept by the CPU. Values that do not fit are not copied
+///
+/// `cpu_values` - the copy of the uniform buffer
+/// `value` - the value to copy
+/// `offset_bytes` - offset in bytes from the start of the buffer to copy the value to
+pub(crate) fn write_cpu_value<T: 'static>(cpu_values: &mut [u8], value: &T, offset_bytes: isize)
+{
+    let offset_bytes = offset_bytes as usize;
+
+    if offset_bytes + size_of::<T>() > cpu_values.len()
+    {
+        return;
+    }
+
+    unsafe
+        {
+            copy_nonoverlapping(value as *const T as *const u8, cpu_values.as_mut_ptr().add(offset_bytes), size_of::<T>());
+        }
+}
+
+/// Reads the elements of a uniform from the copy of a uniform buffer kept by the CPU
+///
+/// `cpu_values` - the copy of the uniform buffer
+/// `location` - where the uniform is stored in the uniform buffer
+/// `number_elements` - the number of elements of the uniform
+fn read_elements<T: Copy + Default>(cpu_values: &[u8], location: &UniformDataLocation, number_elements: usize) -> Vec<T>
+{
+    (0..number_elements).map(|x|
+        {
+            let offset_bytes = element_offset::<T>(location, x);
+
+            if offset_bytes + size_of::<T>() > cpu_values.len()
+            {
+                return T::default();
+            }
+
+            unsafe { (cpu_values.as_ptr().add(offset_bytes) as *const T).read_unaligned() }
+        }).collect()
+}
+
+/// Writes the elements of a uniform into a uniform buffer and the copy of it kept by the CPU
+///
+/// `write_info` - the uniform buffer to write to
+/// `cpu_values` - the copy of the uniform buffer kept by the CPU
+/// `location` - where the uniform is stored in the uniform buffer
+/// `elements` - the elements of the uniform
+fn write_elements<T: 'static + Copy>(write_info: BufferWriteInfo, cpu_values: &mut [u8], location: &UniformDataLocation, elements: &[T])
+{
+    for (index, element) in elements.iter().enumerate()
+    {
+        let offset_bytes = element_offset::<T>(location, index) as isize;
+        write_cpu_value(cpu_values, element, offset_bytes);
+        MappedBuffer::write_single_serialized_value(write_info, *element, offset_bytes, false);
+    }
+}
+
+/// Get the offset in bytes of an element of a uniform, matching the padding used when writing uniforms
+///
+/// `location` - where the uniform is stored in the uniform buffer
+/// `index` - the index of the element
+fn element_offset<T>(location: &UniformDataLocation, index: usize) -> usize
+{
+    (location.offset_bytes + index as isize * (location.sub_padding_bytes + size_of::<T>() as isize)) as usize
+}
+
+/// Changes if the uniforms of the render systems are listed after each frame is rendered. Listing the
+/// uniforms copies every uniform of every render system, so the inspector should only be enabled when needed
+///
+/// `enable` - true to list the uniforms of the render systems
+pub fn set_uniform_inspector_enabled(enable: bool)
+{
+    *UNIFORM_INSPECTOR_ENABLED.lock() = enable;
+
+    if !enable
+    {
+        INSPECTED_UNIFORMS.lock().clear();
+    }
+}
+
+/// Checks if the uniforms of the render systems are listed after each frame is rendered
+pub fn is_uniform_inspector_enabled() -> bool
+{
+    *UNIFORM_INSPECTOR_ENABLED.lock()
+}
+
+/// Get the uniforms of every render system, as of the last rendered frame. Nothing is returned unless the
+/// inspector is enabled
+pub fn inspected_uniforms() -> Vec<InspectedUniform>
+{
+    INSPECTED_UNIFORMS.lock().clone()
+}
+
+/// Replaces the value written to a uniform of a render system by its draw functions. The change is visible
+/// from the next rendered frame
+///
+/// `render_system` - the render system holding the uniform
+/// `uniform_name` - the name of the uniform
+/// `value` - the value to give the uniform. It must have the type of the uniform, as listed by the inspector
+pub fn set_uniform_override(render_system: RenderSystemIndex, uniform_name: &str, value: UniformValue) -> Result<(), String>
+{
+    let inspected_uniform = INSPECTED_UNIFORMS.lock().iter()
+        .find(|x| x.render_system == render_system && x.name == uniform_name)
+        .cloned()
+        .ok_or(format!("Render system {} has no uniform \"{}\" listed by the inspector; enable the inspector and render a frame first",
+                       render_system.index, uniform_name))?;
+
+    if !inspected_uniform.value.same_type(&value)
+    {
+        return Err(format!("The uniform \"{}\" is a {}, but a {} was given", uniform_name, inspected_uniform.value.type_name(), value.type_name()));
+    }
+
+    UNIFORM_OVERRIDES.lock().insert((render_system, uniform_name.to_string()), value);
+    Ok(())
+}
+
+/// Removes overrides of the uniforms of a render system, returning them to the values written by its draw functions
+///
+/// `render_system` - the render system holding the uniforms
+/// `uniform_name` - the uniform to remove the override of. If None, the overrides of every uniform of the render system are removed
+pub fn clear_uniform_override(render_system: RenderSystemIndex, uniform_name: Option<&str>)
+{
+    UNIFORM_OVERRIDES.lock().retain(|(index, name), _| *index != render_system || uniform_name.is_some_and(|x| x != name));
+}
+
+/// Runs an inspector command, returning the text to show to the user. The commands are described at the top
+/// of this file. Listing uniforms enables the inspector, so the values are available from the next frame
+///
+/// `command` - the command to run
+pub fn run_uniform_command(command: &str) -> Result<String, String>
+{
+    let mut arguments = command.split_whitespace();
+
+    match arguments.next()
+    {
+        Some("list") =>
+            {
+                let render_system = arguments.next().map(parse_render_system).transpose()?;
+                set_uniform_inspector_enabled(true);
+
+                let lines = inspected_uniforms().into_iter()
+                    .filter(|x| render_system.is_none_or(|index| x.render_system == index))
+                    .map(|x| format!("{} {}{} {}: {}{}", x.render_system.index, if x.second_pass { "(second pass) " } else { "" }, x.value.type_name(), x.name, x.value,
+                                     if x.overridden { " (overridden)" } else { "" }))
+                    .collect::<Vec<String>>();
+
+                Ok(lines.join("\n"))
+            },
+        Some("set") =>
+            {
+                let render_system = parse_render_system(arguments.next().ok_or("A render system is required")?)?;
+                let uniform_name = arguments.next().ok_or("A uniform is required")?;
+
+                let current_value = INSPECTED_UNIFORMS.lock().iter()
+                    .find(|x| x.render_system == render_system && x.name == uniform_name)
+                    .map(|x| x.value.clone())
+                    .ok_or(format!("Render system {} has no uniform \"{}\" listed by the inspector", render_system.index, uniform_name))?;
+
+                let value = current_value.parse_same_type(&arguments.collect::<Vec<&str>>().join(" "))?;
+                set_uniform_override(render_system, uniform_name, value.clone())?;
+                Ok(format!("{} is now {}", uniform_name, value))
+            },
+        Some("reset") =>
+            {
+                let render_system = parse_render_system(arguments.next().ok_or("A render system is required")?)?;
+                let uniform_name = arguments.next();
+                clear_uniform_override(render_system, uniform_name);
+                Ok(format!("Removed the overrides of {}", uniform_name.unwrap_or("every uniform")))
+            },
+        Some(i) => Err(format!("Unknown uniform command: {}", i)),
+        None => Err("A uniform command is required: list, set or reset".to_string())
+    }
+}
+
+/// Finds the render system given in a command, either by its name or by its index
+///
+/// `argument` - the name or index of the render system
+fn parse_render_system(argument: &str) -> Result<RenderSystemIndex, String>
+{
+    if let Some(index) = RenderSystemIndex::from_name(argument)
+    {
+        return Ok(index);
+    }
+
+    argument.parse::<usize>()
+        .map(|index| RenderSystemIndex{ index })
+        .map_err(|_| format!("No render system is named \"{}\"", argument))
+}
+
+/// Get the overrides of the uniforms of a render system, by the name of the uniform
+///
+/// `render_system` - the render system to get the overrides of
+pub(crate) fn uniform_overrides(render_system: RenderSystemIndex) -> HashMap<String, UniformValue>
+{
+    UNIFORM_OVERRIDES.lock().iter()
+        .filter(|((index, _), _)| *index == render_system)
+        .map(|((_, name), value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Replaces the uniforms listed by the inspector
+///
+/// `uniforms` - the uniforms of every render system, as of the frame just rendered
+pub(crate) fn record_inspected_uniforms(uniforms: Vec<InspectedUniform>)
+{
+    *INSPECTED_UNIFORMS.lock() = uniforms;
+}
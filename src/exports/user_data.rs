@@ -0,0 +1,206 @@
+use std::any::TypeId;
+use std::mem::{align_of, size_of};
+use serde::{Serialize, Deserialize};
+use crate::objects::ecs::TypeIdentifier;
+
+/// The maximum number of bytes that can be stored in the user data of an entity
+pub const MAX_USER_DATA_BYTES: usize = 128;
+
+/// The largest alignment, in bytes, that a value stored in user data can require
+pub const MAX_USER_DATA_ALIGNMENT: usize = align_of::<u64>();
+
+const NUMBER_USER_DATA_WORDS: usize = MAX_USER_DATA_BYTES / size_of::<u64>();
+
+/// Raw bytes attached to an entity, allowing custom plain data to be stored without registering a
+/// separate component type for it. The bytes are stored as 8 byte words so that values are accessed in
+/// place rather than being copied out. The type of the stored value is remembered, and a value can only
+/// be read back as the type it was written as
+#[repr(C)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct UserData
+{
+    words: [u64; NUMBER_USER_DATA_WORDS],
+    length: usize,
+    type_id: Option<TypeIdentifier>,
+}
+
+impl UserData
+{
+    /// Creates user data that holds no value
+    pub fn new() -> UserData
+    {
+        UserData{ words: [0; NUMBER_USER_DATA_WORDS], length: 0, type_id: None }
+    }
+
+    /// Creates user data holding the given value
+    ///
+    /// `value` - the value to store in the user data
+    pub fn from_value<T: 'static + Copy>(value: T) -> UserData
+    {
+        let mut user_data = UserData::new();
+        user_data.write(value);
+        user_data
+    }
+
+    /// Stores the given value, replacing the current value of the user data. The value must fit within
+    /// MAX_USER_DATA_BYTES and not require an alignment larger than MAX_USER_DATA_ALIGNMENT
+    ///
+    /// `value` - the value to store in the user data
+    pub fn write<T: 'static + Copy>(&mut self, value: T)
+    {
+        if size_of::<T>() > MAX_USER_DATA_BYTES
+        {
+            panic!("Value of {} bytes does not fit in user data of {} bytes", size_of::<T>(), MAX_USER_DATA_BYTES);
+        }
+
+        if align_of::<T>() > MAX_USER_DATA_ALIGNMENT
+        {
+            panic!("Value with an alignment of {} bytes cannot be stored in user data", align_of::<T>());
+        }
+
+        self.words = [0; NUMBER_USER_DATA_WORDS];
+        self.length = size_of::<T>();
+        self.type_id = Some(TypeIdentifier::from(TypeId::of::<T>()));
+
+        // The user data itself may be stored at an address that is not aligned for the value
+        unsafe
+            {
+                std::ptr::write_unaligned(self.words.as_mut_ptr() as *mut T, value);
+            }
+    }
+
+    /// Get a copy of the stored value. None is returned if the stored value is not of the requested type.
+    /// Unlike get, the value can be read wherever the user data is stored
+    pub fn read<T: 'static + Copy>(&self) -> Option<T>
+    {
+        if !self.holds::<T>()
+        {
+            return None;
+        }
+
+        unsafe
+            {
+                Some(std::ptr::read_unaligned(self.words.as_ptr() as *const T))
+            }
+    }
+
+    /// Get a reference to the stored value. None is returned if the stored value is not of the requested
+    /// type, or if the user data is stored at an address that is not aligned for the type
+    pub fn get<T: 'static + Copy>(&self) -> Option<&T>
+    {
+        if !self.can_access::<T>()
+        {
+            return None;
+        }
+
+        unsafe
+            {
+                Some(&*(self.words.as_ptr() as *const T))
+            }
+    }
+
+    /// Get a mutable reference to the stored value. None is returned if the stored value is not of the
+    /// requested type, or if the user data is stored at an address that is not aligned for the type
+    pub fn get_mut<T: 'static + Copy>(&mut self) -> Option<&mut T>
+    {
+        if !self.can_access::<T>()
+        {
+            return None;
+        }
+
+        unsafe
+            {
+                Some(&mut *(self.words.as_mut_ptr() as *mut T))
+            }
+    }
+
+    /// Get the bytes of the stored value
+    pub fn as_bytes(&self) -> &[u8]
+    {
+        unsafe
+            {
+                std::slice::from_raw_parts(self.words.as_ptr() as *const u8, self.length)
+            }
+    }
+
+    /// Get the number of bytes of the stored value
+    pub fn len(&self) -> usize
+    {
+        self.length
+    }
+
+    /// Checks if no value is stored
+    pub fn is_empty(&self) -> bool
+    {
+        self.length == 0
+    }
+
+    /// Checks if the stored value was written as the given type
+    fn holds<T: 'static + Copy>(&self) -> bool
+    {
+        self.type_id == Some(TypeIdentifier::from(TypeId::of::<T>()))
+    }
+
+    /// Checks if the stored value can be accessed in place as the given type
+    fn can_access<T: 'static + Copy>(&self) -> bool
+    {
+        // Components are stored in a byte array in the ECS, so the alignment of the words is only
+        // guaranteed relative to the start of the user data
+        self.holds::<T>() && (self.words.as_ptr() as usize).is_multiple_of(align_of::<T>())
+    }
+}
+
+impl Default for UserData
+{
+    fn default() -> Self
+    {
+        UserData::new()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::exports::user_data::UserData;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Health
+    {
+        current: u32,
+        maximum: u32,
+    }
+
+    #[test]
+    fn round_trip_value()
+    {
+        let health = Health{ current: 5, maximum: 10 };
+        let mut user_data = UserData::from_value(health);
+
+        assert_eq!(8, user_data.len());
+        assert_eq!(Some(health), user_data.read::<Health>());
+        assert_eq!(Some(&health), user_data.get::<Health>());
+
+        user_data.get_mut::<Health>().unwrap().current = 7;
+        assert_eq!(Some(Health{ current: 7, maximum: 10 }), user_data.read::<Health>());
+
+        user_data.write(true);
+        assert_eq!(Some(true), user_data.read::<bool>());
+        assert_eq!(None, user_data.read::<Health>());
+    }
+
+    #[test]
+    fn reject_other_types()
+    {
+        let mut user_data = UserData::from_value(2_u32);
+
+        // Types of the same size as the stored value cannot reinterpret its bytes
+        assert_eq!(None, user_data.read::<f32>());
+        assert_eq!(None, user_data.read::<char>());
+        assert_eq!(None, user_data.get::<[u8; 4]>());
+        assert!(user_data.get_mut::<i32>().is_none());
+
+        assert_eq!(None, user_data.read::<bool>());
+        assert_eq!(None, UserData::new().read::<u32>());
+        assert!(UserData::new().is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use nalgebra_glm::{TVec4, vec4};
+use parking_lot::Mutex;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+// Labels are drawn over the frame after the render systems drawing to the window have finished, as quads
+// facing the camera that are centred above the bounding volume of their entity. Every glyph of every label
+// is drawn in one instanced draw call using the built-in font. Labels fade out with distance from the camera,
+// and are hidden behind geometry by comparing their distance to the camera with that of the world positions
+// stored in the first G-Buffer layout, the same positions used for fog
+
+lazy_static!
+{
+    static ref WORLD_LABELS: Mutex<HashMap<EntityId, WorldLabel>> = Mutex::new(HashMap::default());
+}
+
+/// Text drawn in the world above an entity, such as a name plate
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldLabel
+{
+    /// The text to draw. New lines start a new line of the label below the previous one
+    pub text: String,
+    /// Colour of the text, including its opacity when nearer than the fade start distance
+    pub colour: TVec4<f32>,
+    /// Height in the world of a line of text
+    pub line_height: f32,
+    /// Distance between the top of the bounding volume of the entity and the bottom of the label
+    pub offset: f32,
+    /// Distance from the camera at which the label starts to fade out
+    pub fade_start: f32,
+    /// Distance from the camera at which the label has faded out completely and is no longer drawn
+    pub fade_end: f32,
+    /// If true, the label is hidden where geometry is in front of it
+    pub occluded: bool,
+}
+
+impl WorldLabel
+{
+    /// Creates a white label that fades out between 50 and 75 units from the camera
+    ///
+    /// `text` - the text to draw
+    pub fn new<T: Into<String>>(text: T) -> WorldLabel
+    {
+        WorldLabel
+        {
+            text: text.into(),
+            colour: vec4(1.0, 1.0, 1.0, 1.0),
+            line_height: 0.5,
+            offset: 0.25,
+            fade_start: 50.0,
+            fade_end: 75.0,
+            occluded: true,
+        }
+    }
+
+    /// Finds how opaque the label is at the given distance from the camera, from 0 to 1
+    ///
+    /// `distance` - the distance from the camera to the label
+    pub fn fade(&self, distance: f32) -> f32
+    {
+        if distance <= self.fade_start
+        {
+            return 1.0;
+        }
+
+        if distance >= self.fade_end
+        {
+            return 0.0;
+        }
+
+        1.0 - (distance - self.fade_start) / (self.fade_end - self.fade_start)
+    }
+}
+
+/// Draws the label above the entity, replacing any label the entity already had. Labels of entities that
+/// are removed from the world are removed automatically
+///
+/// `entity_id` - the entity to draw the label above
+/// `label` - the label to draw
+pub fn set_world_label(entity_id: EntityId, label: WorldLabel)
+{
+    let label = WorldLabel
+    {
+        line_height: label.line_height.max(0.0),
+        fade_start: label.fade_start.max(0.0),
+        fade_end: label.fade_end.max(label.fade_start.max(0.0)),
+        ..label
+    };
+
+    WORLD_LABELS.lock().insert(entity_id, label);
+}
+
+/// Stops drawing the label of the entity
+///
+/// `entity_id` - the entity whose label is removed
+pub fn remove_world_label(entity_id: EntityId)
+{
+    WORLD_LABELS.lock().remove(&entity_id);
+}
+
+/// Get the label drawn above the entity, if it has one
+///
+/// `entity_id` - the entity to get the label of
+pub fn world_label(entity_id: EntityId) -> Option<WorldLabel>
+{
+    WORLD_LABELS.lock().get(&entity_id).cloned()
+}
+
+/// Get every label along with the bounding volume of its entity. Labels of entities that no longer
+/// exist are removed
+///
+/// `ecs` - the ECS holding the bounding volumes of the labelled entities
+pub(crate) fn labels_to_draw(ecs: &ECS) -> Vec<(StaticAABB, WorldLabel)>
+{
+    let mut world_labels = WORLD_LABELS.lock();
+    world_labels.retain(|entity_id, _| ecs.get_ref::<StaticAABB>(*entity_id).is_some());
+
+    world_labels.iter()
+        .filter_map(|(entity_id, label)| ecs.get_copy::<StaticAABB>(*entity_id).map(|x| (x, label.clone())))
+        .collect()
+}
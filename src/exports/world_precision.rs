@@ -0,0 +1,115 @@
+use lazy_static::lazy_static;
+use nalgebra_glm::{TMat4, TVec3, vec3};
+use parking_lot::Mutex;
+use crate::exports::movement_components::{Position, WorldPosition};
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+
+// Single precision positions lose precision far from the origin of the world, which shows up as vertices
+// jittering as the camera moves. With double precision positions enabled, entities can store their position
+// as a WorldPosition, and everything uploaded for rendering is made relative to a render origin near the
+// camera just before it is uploaded. The numbers given to the GPU are then small no matter where in the
+// world the camera is, while the world itself keeps its coordinates. The render origin is moved to the
+// camera whenever the camera gets too far from it; doing so rewrites the instance data of static entities.
+// The camera itself keeps a single precision position, and shadow maps are computed from single precision
+// light matrices, so both still lose precision very far from the world origin
+
+lazy_static!
+{
+    static ref RECENTRE_DISTANCE: Mutex<Option<f64>> = Mutex::new(None);
+    static ref RENDER_ORIGIN: Mutex<TVec3<f64>> = Mutex::new(vec3(0.0, 0.0, 0.0));
+}
+
+/// Specifies if positions are uploaded relative to a render origin near the camera, rather than relative
+/// to the origin of the world. Disabled by default
+///
+/// `recentre_distance` - how far the camera can move from the render origin before the render origin is
+///                       moved to the camera. None disables double precision positions
+pub fn set_double_precision_positions(recentre_distance: Option<f64>)
+{
+    *RECENTRE_DISTANCE.lock() = recentre_distance.filter(|x| *x > 0.0);
+}
+
+/// Checks if positions are uploaded relative to a render origin near the camera
+pub fn is_double_precision_positions_enabled() -> bool
+{
+    RECENTRE_DISTANCE.lock().is_some()
+}
+
+/// Get the point everything uploaded for rendering is relative to. This is the origin of the world if
+/// double precision positions are disabled
+pub fn render_origin() -> TVec3<f64>
+{
+    *RENDER_ORIGIN.lock()
+}
+
+/// Converts a position in the world into the position uploaded for rendering
+///
+/// `position` - the position in the world to convert
+pub fn to_render_space(position: TVec3<f64>) -> TVec3<f32>
+{
+    let relative_position = position - render_origin();
+    vec3(relative_position.x as f32, relative_position.y as f32, relative_position.z as f32)
+}
+
+/// Get the position of an entity relative to the render origin, using its WorldPosition if it has one.
+/// None is returned if the entity has no position
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to get the position of
+pub fn render_space_position(ecs: &ECS, entity_id: EntityId) -> Option<TVec3<f32>>
+{
+    if let Some(world_position) = ecs.get_copy::<WorldPosition>(entity_id)
+    {
+        return Some(to_render_space(world_position.get_position()));
+    }
+
+    ecs.get_copy::<Position>(entity_id).map(|x| to_render_space(to_f64(x.get_position())))
+}
+
+/// Converts a matrix taking positions in the world into light space into one taking positions relative to
+/// the render origin
+///
+/// `light_matrix` - the matrix to convert
+pub(crate) fn render_space_light_matrix(light_matrix: &TMat4<f32>) -> TMat4<f32>
+{
+    let origin = render_origin();
+    nalgebra_glm::translate(light_matrix, &vec3(origin.x as f32, origin.y as f32, origin.z as f32))
+}
+
+/// Moves the render origin to the camera if the camera is too far from it, or back to the origin of the
+/// world if double precision positions were disabled. Returns true if the render origin moved, in which
+/// case everything previously uploaded relative to it is out of date
+///
+/// `camera_position` - the position of the camera being rendered from
+pub(crate) fn update_render_origin(camera_position: TVec3<f32>) -> bool
+{
+    let new_origin = match *RECENTRE_DISTANCE.lock()
+    {
+        Some(recentre_distance) =>
+            {
+                let camera_position = to_f64(camera_position);
+
+                if nalgebra_glm::distance(&render_origin(), &camera_position) <= recentre_distance
+                {
+                    return false;
+                }
+
+                camera_position
+            },
+        None => vec3(0.0, 0.0, 0.0)
+    };
+
+    let mut render_origin = RENDER_ORIGIN.lock();
+    let moved = *render_origin != new_origin;
+    *render_origin = new_origin;
+    moved
+}
+
+/// Widens a single precision position into double precision
+///
+/// `position` - the position to widen
+pub fn to_f64(position: TVec3<f32>) -> TVec3<f64>
+{
+    vec3(position.x as f64, position.y as f64, position.z as f64)
+}
@@ -0,0 +1,194 @@
+use std::any::{Any, TypeId};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use nalgebra_glm::TVec3;
+use parking_lot::{Mutex, RwLock};
+use crate::exports::movement_components::{Position, WorldPosition};
+use crate::exports::world_precision::to_f64;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+
+// World queries let threads outside of the engine, such as audio, networking and analytics, read the state
+// of the world without touching the ECS while logic is changing it. At the end of every frame the positions
+// of the entities, along with the components registered with publish_component, are copied into a frame
+// state that is then published. Readers hold on to the state of a frame for as long as they need it, while
+// the next frame is written into a second state; once no reader holds the older state, it is reused for the
+// frame after that, so the two states take turns being read and written.
+//
+// Nothing is copied until a handle is requested with world_query, so games that do not read the world from
+// other threads do not pay for it. Entity IDs are those of the ECS, so an entity deleted after the frame was
+// published may have its ID reused by a new entity
+
+lazy_static!
+{
+    static ref PUBLISHED_FRAME: RwLock<Arc<FrameState>> = RwLock::new(Arc::new(FrameState::default()));
+    static ref SPARE_FRAME: Mutex<Option<FrameState>> = Mutex::new(None);
+    static ref PUBLISHED_COMPONENTS: Mutex<Vec<(TypeId, CopyComponentFunction)>> = Mutex::new(Vec::new());
+}
+
+static PUBLISHING_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Copies the instances of a component from the ECS into the state of a frame
+type CopyComponentFunction = fn(&ECS, &mut HashMap<TypeId, Box<dyn Any + Send + Sync>>);
+
+/// The state of the world at the end of a completed frame
+#[derive(Default)]
+pub struct FrameState
+{
+    frame: u64,
+    camera_position: TVec3<f32>,
+    positions: HashMap<EntityId, TVec3<f64>>,
+    components: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+/// Handle used to read the most recently completed frame from any thread
+#[derive(Copy, Clone, Debug)]
+pub struct WorldQuery;
+
+/// Get a handle to read the most recently completed frame. The state of the world is published starting
+/// with the frame that completes after the first handle is requested
+pub fn world_query() -> WorldQuery
+{
+    PUBLISHING_ENABLED.store(true, Ordering::Release);
+    WorldQuery
+}
+
+/// Copies every instance of the component into the published state of each frame, so that it can be read
+/// through world queries. Components that are not published cannot be read from other threads. The
+/// component must be registered with the ECS. Publishing the same component again has no effect
+pub fn publish_component<T: 'static + Clone + Send + Sync>()
+{
+    let mut published_components = PUBLISHED_COMPONENTS.lock();
+
+    if published_components.iter().all(|(type_id, _)| *type_id != TypeId::of::<T>())
+    {
+        published_components.push((TypeId::of::<T>(), copy_component::<T>));
+    }
+}
+
+impl WorldQuery
+{
+    /// Get the state of the most recently completed frame. The state does not change while it is held,
+    /// even as later frames complete; call this again to read a later frame
+    pub fn latest(&self) -> Arc<FrameState>
+    {
+        PUBLISHED_FRAME.read().clone()
+    }
+}
+
+impl FrameState
+{
+    /// Get the number of the frame, counting from one for the first published frame. No frame has been
+    /// published yet if this is zero
+    pub fn frame(&self) -> u64
+    {
+        self.frame
+    }
+
+    /// Get the position of the camera the frame was rendered from
+    pub fn camera_position(&self) -> TVec3<f32>
+    {
+        self.camera_position
+    }
+
+    /// Get the position of the entity, in double precision. None is returned if the entity had no position
+    ///
+    /// `entity_id` - the entity to get the position of
+    pub fn position(&self, entity_id: EntityId) -> Option<TVec3<f64>>
+    {
+        self.positions.get(&entity_id).copied()
+    }
+
+    /// Get every entity that had a position along with that position
+    pub fn positions(&self) -> impl Iterator<Item = (EntityId, TVec3<f64>)> + '_
+    {
+        self.positions.iter().map(|(entity_id, position)| (*entity_id, *position))
+    }
+
+    /// Get the entities whose position was within the given distance of a point
+    ///
+    /// `centre` - the point to find entities around
+    /// `radius` - the largest distance from the point an entity can be at
+    pub fn entities_within(&self, centre: TVec3<f64>, radius: f64) -> Vec<EntityId>
+    {
+        self.positions.iter()
+            .filter(|(_, position)| nalgebra_glm::distance2(*position, &centre) <= radius * radius)
+            .map(|(entity_id, _)| *entity_id)
+            .collect()
+    }
+
+    /// Get the component of the entity. None is returned if the entity did not have the component or if the
+    /// component is not published
+    ///
+    /// `entity_id` - the entity to get the component of
+    pub fn component<T: 'static>(&self, entity_id: EntityId) -> Option<&T>
+    {
+        self.instances::<T>().and_then(|x| x.get(&entity_id))
+    }
+
+    /// Get every entity that had the component along with its instance of the component. Nothing is
+    /// returned if the component is not published
+    pub fn entities_with<T: 'static>(&self) -> impl Iterator<Item = (EntityId, &T)>
+    {
+        self.instances::<T>().into_iter().flat_map(|x| x.iter().map(|(entity_id, component)| (*entity_id, component)))
+    }
+
+    /// Get the instances of the component copied into the frame
+    fn instances<T: 'static>(&self) -> Option<&HashMap<EntityId, T>>
+    {
+        self.components.get(&TypeId::of::<T>()).and_then(|x| x.downcast_ref::<HashMap<EntityId, T>>())
+    }
+}
+
+/// Copies every instance of the component in the ECS into the instances of the component of a frame,
+/// replacing those of the frame the instances were previously copied into
+///
+/// `ecs` - the ECS holding the components
+/// `components` - the instances of every published component of the frame
+fn copy_component<T: 'static + Clone + Send + Sync>(ecs: &ECS, components: &mut HashMap<TypeId, Box<dyn Any + Send + Sync>>)
+{
+    let instances = components.entry(TypeId::of::<T>())
+        .or_insert_with(|| Box::new(HashMap::<EntityId, T>::new()))
+        .downcast_mut::<HashMap<EntityId, T>>()
+        .unwrap();
+
+    instances.clear();
+    instances.extend(ecs.query_read::<(&T, )>().map(|(entity_id, (component, ))| (entity_id, component.clone())));
+}
+
+/// Publishes the state of the world at the end of a frame, if any world query handle was requested
+///
+/// `ecs` - the ECS holding the state of the world after the frame
+/// `camera_position` - the position of the camera the frame was rendered from
+pub(crate) fn publish_frame(ecs: &ECS, camera_position: TVec3<f32>)
+{
+    if !PUBLISHING_ENABLED.load(Ordering::Acquire)
+    {
+        return;
+    }
+
+    // The state of the frame before the previous one is reused if no reader holds it anymore, which keeps
+    // the allocations of its maps
+    let mut frame_state = SPARE_FRAME.lock().take().unwrap_or_default();
+
+    frame_state.frame = PUBLISHED_FRAME.read().frame + 1;
+    frame_state.camera_position = camera_position;
+
+    frame_state.positions.clear();
+    frame_state.positions.extend(ecs.query_read::<(&Position, )>().map(|(entity_id, (position, ))| (entity_id, to_f64(position.get_position()))));
+    frame_state.positions.extend(ecs.query_read::<(&WorldPosition, )>().map(|(entity_id, (position, ))| (entity_id, position.get_position())));
+
+    for (_, copy_component) in PUBLISHED_COMPONENTS.lock().iter()
+    {
+        copy_component(ecs, &mut frame_state.components);
+    }
+
+    let previous_frame = std::mem::replace(&mut *PUBLISHED_FRAME.write(), Arc::new(frame_state));
+
+    if let Ok(previous_frame) = Arc::try_unwrap(previous_frame)
+    {
+        *SPARE_FRAME.lock() = Some(previous_frame);
+    }
+}
@@ -0,0 +1,219 @@
+use hashbrown::HashSet;
+use lazy_static::lazy_static;
+use nalgebra_glm::{TVec4, vec4};
+use parking_lot::Mutex;
+use crate::exports::debug_draw::{debug_draw, DebugDraw};
+use crate::flows::visible_world_flow::CullResult;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, SharedWorldSectionId, UniqueWorldSectionId};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+// The world section overlay draws the world sections of the bounding box tree over the frame, using debug
+// drawing. Every unique world section holding entities is drawn as the area of the world it covers, and every
+// shared world section as the bounding volume of its entities, coloured by their level. Sections seen by the
+// camera are opaque, sections only kept active for logic near the camera are faded, and sections culled
+// this frame are faint.
+//
+// Sections that are referred to but do not exist in the tree, such as a visible section, a related section or
+// a shared section of a unique section that was removed, are drawn in red over all geometry. These are the
+// sections that cause "requested world section does not exist" panics when the entities of a section are
+// searched for
+
+lazy_static!
+{
+    static ref OVERLAY_SETTINGS: Mutex<Option<WorldSectionOverlay>> = Mutex::new(None);
+}
+
+/// Colours of the world sections of each level, repeating for levels past the last colour
+const LEVEL_COLOURS: [(f32, f32, f32); 6] = [(0.2, 0.9, 0.3), (0.2, 0.6, 1.0), (1.0, 0.85, 0.2), (0.8, 0.3, 1.0), (0.2, 0.9, 0.9), (1.0, 0.5, 0.2)];
+
+/// Opacity of sections seen by the camera, of sections only kept active for logic, and of culled sections
+const VISIBLE_OPACITY: f32 = 1.0;
+const LOGIC_ONLY_OPACITY: f32 = 0.5;
+const CULLED_OPACITY: f32 = 0.15;
+
+/// Controls which world sections the world section overlay draws
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WorldSectionOverlay
+{
+    /// If true, unique world sections are drawn
+    pub unique_sections: bool,
+    /// If true, shared world sections are drawn
+    pub shared_sections: bool,
+    /// If true, sections culled this frame are drawn; otherwise only sections that were not culled are drawn
+    pub culled_sections: bool,
+    /// If set, only sections of this level are drawn. Sections that do not exist are drawn regardless
+    pub level: Option<u16>,
+    /// If true, sections are hidden where geometry is in front of them
+    pub depth_tested: bool,
+}
+
+impl Default for WorldSectionOverlay
+{
+    fn default() -> WorldSectionOverlay
+    {
+        WorldSectionOverlay
+        {
+            unique_sections: true,
+            shared_sections: true,
+            culled_sections: true,
+            level: None,
+            depth_tested: false,
+        }
+    }
+}
+
+/// Enables or disables drawing the world sections of the bounding box tree over the frame
+///
+/// `overlay` - which world sections are drawn. If None, no world sections are drawn
+pub fn set_world_section_overlay(overlay: Option<WorldSectionOverlay>)
+{
+    *OVERLAY_SETTINGS.lock() = overlay;
+}
+
+/// Get which world sections are drawn over the frame, if the overlay is enabled
+pub fn world_section_overlay() -> Option<WorldSectionOverlay>
+{
+    *OVERLAY_SETTINGS.lock()
+}
+
+/// Queues the outlines of the world sections for the frame being rendered, if the overlay is enabled
+///
+/// `bounding_box_tree` - the tree holding the world sections
+/// `render_visible` - the world sections seen by the camera this frame
+/// `logic_visible` - the world sections kept active for logic this frame
+pub(crate) fn draw_world_section_overlay(bounding_box_tree: &BoundingBoxTree, render_visible: &CullResult, logic_visible: &CullResult)
+{
+    let settings = match world_section_overlay()
+    {
+        Some(i) => i,
+        None => return
+    };
+
+    let draw = if settings.depth_tested { debug_draw() } else { debug_draw().without_depth_test() };
+    let drawn_level = |level: u16| settings.level.is_none_or(|x| x == level);
+
+    let opacity = |sections: &mut dyn Iterator<Item = UniqueWorldSectionId>| -> Option<f32>
+        {
+            let mut opacity = if settings.culled_sections { Some(CULLED_OPACITY) } else { None };
+
+            for section in sections
+            {
+                if render_visible.visible_sections_map.contains(&section)
+                {
+                    return Some(VISIBLE_OPACITY);
+                }
+
+                if logic_visible.visible_sections_map.contains(&section)
+                {
+                    opacity = Some(LOGIC_ONLY_OPACITY);
+                }
+            }
+
+            opacity
+        };
+
+    if settings.unique_sections
+    {
+        for section in bounding_box_tree.stored_entities_indexes.keys().filter(|x| drawn_level(x.level()))
+        {
+            if let Some(opacity) = opacity(&mut std::iter::once(*section))
+            {
+                draw.draw_aabb(&bounding_box_tree.world_section_aabb(*section), level_colour(section.level(), opacity));
+            }
+        }
+    }
+
+    if settings.shared_sections
+    {
+        for (shared_section, shared_entities) in &bounding_box_tree.shared_section_indexes
+        {
+            let level = shared_section_level(shared_section);
+
+            if !level.is_some_and(drawn_level)
+            {
+                continue;
+            }
+
+            if let Some(opacity) = opacity(&mut shared_section.to_world_sections().iter().flatten().copied())
+            {
+                draw.draw_aabb(&shared_entities.aabb, level_colour(level.unwrap(), opacity));
+            }
+        }
+    }
+
+    draw_missing_sections(bounding_box_tree, render_visible, logic_visible);
+}
+
+/// Draws the world sections that are referred to but do not exist in the tree
+///
+/// `bounding_box_tree` - the tree holding the world sections
+/// `render_visible` - the world sections seen by the camera this frame
+/// `logic_visible` - the world sections kept active for logic this frame
+fn draw_missing_sections(bounding_box_tree: &BoundingBoxTree, render_visible: &CullResult, logic_visible: &CullResult)
+{
+    let draw = debug_draw().without_depth_test();
+    let missing_colour = vec4(1.0, 0.0, 0.0, 1.0);
+
+    let referred_unique = render_visible.visible_sections_vec.iter()
+        .chain(logic_visible.visible_sections_vec.iter())
+        .chain(bounding_box_tree.related_world_sections.keys())
+        .chain(bounding_box_tree.related_world_sections.values().flatten())
+        .chain(bounding_box_tree.reverse_shared_section_lookup.values().flatten())
+        .copied()
+        .filter(|x| !bounding_box_tree.is_section_in_existence(x))
+        .collect::<HashSet<UniqueWorldSectionId>>();
+
+    for section in referred_unique
+    {
+        draw.draw_aabb(&bounding_box_tree.world_section_aabb(section), missing_colour);
+    }
+
+    let referred_shared = bounding_box_tree.stored_entities_indexes.values()
+        .flat_map(|x| x.shared_sections_ids.iter())
+        .chain(bounding_box_tree.reverse_shared_section_lookup.keys())
+        .copied()
+        .filter(|x| !bounding_box_tree.shared_section_indexes.contains_key(x))
+        .collect::<HashSet<SharedWorldSectionId>>();
+
+    for shared_section in referred_shared
+    {
+        draw_shared_section_area(&draw, bounding_box_tree, &shared_section, missing_colour);
+    }
+}
+
+/// Draws the area of the world covered by the unique world sections making up a shared world section
+///
+/// `draw` - the debug drawing handle to queue the outline with
+/// `bounding_box_tree` - the tree holding the world sections
+/// `shared_section` - the shared world section to draw
+/// `colour` - the colour of the outline
+fn draw_shared_section_area(draw: &DebugDraw, bounding_box_tree: &BoundingBoxTree, shared_section: &SharedWorldSectionId, colour: TVec4<f32>)
+{
+    let area = shared_section.to_world_sections().iter()
+        .flatten()
+        .map(|x| bounding_box_tree.world_section_aabb(*x))
+        .fold(None, |area: Option<StaticAABB>, x| Some(area.map_or(x, |area| area.combine_aabb(&x))));
+
+    if let Some(area) = area
+    {
+        draw.draw_aabb(&area, colour);
+    }
+}
+
+/// Get the level of the unique world sections making up a shared world section
+///
+/// `shared_section` - the shared world section to get the level of
+fn shared_section_level(shared_section: &SharedWorldSectionId) -> Option<u16>
+{
+    shared_section.to_world_sections().iter().flatten().next().map(|x| x.level())
+}
+
+/// Get the colour world sections of a level are drawn with
+///
+/// `level` - the level of the world sections
+/// `opacity` - the opacity of the colour
+fn level_colour(level: u16, opacity: f32) -> TVec4<f32>
+{
+    let (r, g, b) = LEVEL_COLOURS[level as usize % LEVEL_COLOURS.len()];
+    vec4(r, g, b, opacity)
+}
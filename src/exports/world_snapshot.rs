@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use nalgebra_glm::TVec3;
+use serde::{Serialize, Deserialize};
+use tracing::error;
+use crate::exports::camera_object::Camera;
+use crate::flows::shared_constants::REQUESTED_SNAPSHOT_OPERATIONS;
+use crate::models::model_definitions::ModelId;
+use crate::objects::ecs::{ECS, TypeIdentifier};
+use crate::objects::resources::ResourceStorage;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
+
+/// Version of the layout of snapshot files. Increase when the layout of the header or of the engine
+/// structures stored in a snapshot changes
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Bytes at the start of every snapshot file, identifying it as a snapshot
+const SNAPSHOT_MAGIC: [u8; 8] = *b"RENGSNAP";
+
+// A snapshot file is a header followed by the snapshot itself, both encoded with bincode. The header
+// holds two versions: the format version describes the layout the engine wrote, while the game version is
+// set by the game and describes the layout of its components. When the game changes its components, it
+// increases its version and adds a migration converting snapshots of the previous version, so that save
+// games made with older versions of the game can still be loaded
+
+// Saving and loading are requested from anywhere, and are done by the render thread at the end of the
+// frame, when no logic is executing
+
+/// Function converting a snapshot of the game version it was registered for to the next game version
+pub type SnapshotMigration = fn(&mut WorldSnapshot);
+
+/// The state of the game stored in a snapshot
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot
+{
+    pub ecs: ECS,
+    // Resources are not serialized with the ECS. Storing them right after it keeps the same layout as
+    // snapshots written while the resources were the last field of the serialized ECS
+    pub(crate) resources: HashMap<TypeIdentifier, ResourceStorage>,
+    pub bounding_box_tree: BoundingBoxTree,
+    pub camera: Camera,
+    pub(crate) model_names: Vec<(String, ModelId)>,
+}
+
+/// Reasons a snapshot could not be saved or loaded
+#[derive(Debug)]
+pub enum SnapshotError
+{
+    Io(std::io::Error),
+    NotASnapshot,
+    UnsupportedFormatVersion(u32),
+    NewerGameVersion(u32),
+    MissingMigration(u32),
+    Corrupted(String),
+    UnknownModel(String),
+    UnknownComponent(String),
+    UnknownEntityType(String),
+}
+
+/// Stored at the start of a snapshot file
+#[derive(Serialize, Deserialize)]
+struct SnapshotHeader
+{
+    magic: [u8; 8],
+    format_version: u32,
+    game_version: u32,
+}
+
+/// Saving and loading of the game state
+pub struct World;
+
+/// An operation on a snapshot that is done at the end of the current frame
+pub(crate) enum SnapshotRequest
+{
+    Save(PathBuf),
+    Load(PathBuf),
+    ExportSections(Vec<UniqueWorldSectionId>, PathBuf),
+    ImportSections(PathBuf, TVec3<f32>),
+}
+
+/// The version of the game and the migrations from its previous versions
+struct GameVersionInformation
+{
+    game_version: u32,
+    migrations: BTreeMap<u32, SnapshotMigration>,
+}
+
+lazy_static!
+{
+    static ref GAME_VERSION_INFORMATION: Mutex<GameVersionInformation> = Mutex::new(GameVersionInformation{ game_version: 0, migrations: BTreeMap::new() });
+    static ref LAST_SNAPSHOT_RESULT: Mutex<Option<Result<PathBuf, SnapshotError>>> = Mutex::new(None);
+}
+
+impl World
+{
+    /// Saves the state of the game to the given file at the end of the current frame. Use
+    /// take_last_snapshot_result to find out if the save succeeded
+    ///
+    /// `path` - the location of the snapshot file to write
+    pub fn save_snapshot<P: Into<PathBuf>>(path: P)
+    {
+        REQUESTED_SNAPSHOT_OPERATIONS.lock().push(SnapshotRequest::Save(path.into()));
+    }
+
+    /// Replaces the state of the game with the state in the given file at the end of the current frame.
+    /// If the snapshot cannot be loaded, the game continues with its current state. Use
+    /// take_last_snapshot_result to find out if the load succeeded
+    ///
+    /// `path` - the location of the snapshot file to read
+    pub fn load_snapshot<P: Into<PathBuf>>(path: P)
+    {
+        REQUESTED_SNAPSHOT_OPERATIONS.lock().push(SnapshotRequest::Load(path.into()));
+    }
+
+    /// Exports the entities of the given world sections into a standalone file at the end of the current
+    /// frame, which can be imported into another world. Use take_last_snapshot_result to find out if the
+    /// export succeeded
+    ///
+    /// `sections` - the world sections to export
+    /// `path` - the location of the file to write
+    pub fn export_sections<P: Into<PathBuf>>(sections: Vec<UniqueWorldSectionId>, path: P)
+    {
+        REQUESTED_SNAPSHOT_OPERATIONS.lock().push(SnapshotRequest::ExportSections(sections, path.into()));
+    }
+
+    /// Adds the entities of a file of exported world sections to the world at the end of the current frame.
+    /// If any entity cannot be imported, no entity is added. Use take_last_snapshot_result to find out if
+    /// the import succeeded
+    ///
+    /// `path` - the location of the file to read
+    /// `offset` - the distance each entity is moved from where it was exported
+    pub fn import_sections<P: Into<PathBuf>>(path: P, offset: TVec3<f32>)
+    {
+        REQUESTED_SNAPSHOT_OPERATIONS.lock().push(SnapshotRequest::ImportSections(path.into(), offset));
+    }
+
+    /// Get the result of the most recently completed save or load, which is the path of the snapshot
+    /// file if it succeeded. None is returned if no operation completed since this was last called
+    pub fn take_last_snapshot_result() -> Option<Result<PathBuf, SnapshotError>>
+    {
+        LAST_SNAPSHOT_RESULT.lock().take()
+    }
+
+    /// Sets the version of the game written into saved snapshots. Increase the version whenever the
+    /// components of the game change in a way that requires older snapshots to be migrated
+    ///
+    /// `game_version` - the current version of the game
+    pub fn set_game_version(game_version: u32)
+    {
+        GAME_VERSION_INFORMATION.lock().game_version = game_version;
+    }
+
+    /// Adds the migration converting a snapshot saved by the given game version into a snapshot of the
+    /// next game version. Migrations are applied in order when loading a snapshot of an older game version,
+    /// which fails if the migration of any version in between was not added
+    ///
+    /// `from_game_version` - the game version of the snapshots the migration converts
+    /// `migration` - the function converting the snapshot
+    pub fn add_migration(from_game_version: u32, migration: SnapshotMigration)
+    {
+        GAME_VERSION_INFORMATION.lock().migrations.insert(from_game_version, migration);
+    }
+
+    /// Stores the result of a save or load so that it can be queried by the game
+    ///
+    /// `result` - the result of the operation
+    pub(crate) fn store_snapshot_result(result: Result<PathBuf, SnapshotError>)
+    {
+        if let Err(ref err) = result
+        {
+            error!("Snapshot operation failed: {:?}", err);
+        }
+
+        *LAST_SNAPSHOT_RESULT.lock() = Some(result);
+    }
+}
+
+impl WorldSnapshot
+{
+    /// Writes the snapshot to the given file, tagged with the current game version
+    ///
+    /// `path` - the location of the file to write
+    pub fn write_to_file(&self, path: &Path) -> Result<(), SnapshotError>
+    {
+        let header = SnapshotHeader
+        {
+            magic: SNAPSHOT_MAGIC,
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            game_version: GAME_VERSION_INFORMATION.lock().game_version,
+        };
+
+        let mut contents = bincode::serialize(&header).map_err(|err| SnapshotError::Corrupted(err.to_string()))?;
+        contents.append(&mut bincode::serialize(self).map_err(|err| SnapshotError::Corrupted(err.to_string()))?);
+
+        fs::write(path, contents).map_err(SnapshotError::Io)
+    }
+
+    /// Reads the snapshot stored in the given file, migrating it to the current game version if it was
+    /// saved by an older version of the game
+    ///
+    /// `path` - the location of the file to read
+    pub fn read_from_file(path: &Path) -> Result<WorldSnapshot, SnapshotError>
+    {
+        let contents = fs::read(path).map_err(SnapshotError::Io)?;
+
+        let header: SnapshotHeader = bincode::deserialize(&contents).map_err(|_| SnapshotError::NotASnapshot)?;
+
+        if header.magic != SNAPSHOT_MAGIC
+        {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        // When the format version changes, the conversion from the previous format belongs here
+        if header.format_version != SNAPSHOT_FORMAT_VERSION
+        {
+            return Err(SnapshotError::UnsupportedFormatVersion(header.format_version));
+        }
+
+        let header_size = bincode::serialized_size(&header).map_err(|err| SnapshotError::Corrupted(err.to_string()))? as usize;
+        let mut snapshot: WorldSnapshot = bincode::deserialize(&contents[header_size..]).map_err(|err| SnapshotError::Corrupted(err.to_string()))?;
+
+        // Migrations may change the version information, so it is not kept locked while they run
+        let (current_game_version, migrations) =
+            {
+                let version_information = GAME_VERSION_INFORMATION.lock();
+                (version_information.game_version, version_information.migrations.clone())
+            };
+
+        if header.game_version > current_game_version
+        {
+            return Err(SnapshotError::NewerGameVersion(header.game_version));
+        }
+
+        for game_version in header.game_version..current_game_version
+        {
+            match migrations.get(&game_version)
+            {
+                Some(migration) => migration(&mut snapshot),
+                None => return Err(SnapshotError::MissingMigration(game_version))
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use nalgebra_glm::{TVec3, vec3};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use crate::exports::light_baking::LightBakeSettings;
+use crate::exports::light_components::{FindLightType, LightInformation};
+use crate::exports::movement_components::Position;
+use crate::helper_things::environment::get_model_folder;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::world::bounding_box_tree_v2::BoundingBoxTree;
+use crate::world::bounding_volumes::ray::Ray;
+
+/// Layer of world sections holding dynamic entities. Fragments in these world sections are lit dynamically
+pub const DYNAMIC_SECTION_LAYER: i32 = -1;
+
+/// A world section found in the baked lighting, along with the lightmap layer holding its lighting
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct BakedSection
+{
+    pub level: u16,
+    pub offsets: (u16, u16, u16),
+    pub layer: i32,
+}
+
+/// The lighting baked for the static world sections. Every baked world section has resolution³ samples,
+/// stored as RGBA with x varying fastest, then z, then y
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BakedLighting
+{
+    input_hash: u64,
+    pub resolution: u32,
+    pub atomic_section_length: u32,
+    pub sections: Vec<BakedSection>,
+    pub number_layers: usize,
+    pub irradiance: Vec<[f32; 4]>,
+}
+
+/// A static light contributing to the baked lighting
+struct BakeLight
+{
+    entity_id: EntityId,
+    light_type: FindLightType,
+    position: TVec3<f32>,
+    information: LightInformation,
+}
+
+/// Get the location of the file the baked lighting is stored in, next to the models it was baked for
+fn baked_lighting_file() -> PathBuf
+{
+    get_model_folder().join("baked_lighting.bin")
+}
+
+/// Get the baked lighting for the static world. If the lighting stored in the model folder was baked from
+/// the same static world and settings it is used, otherwise the lighting is baked and stored
+///
+/// `tree` - the bounding box tree holding the world
+/// `ecs` - the ECS holding the lights and bounding volumes of entities
+/// `settings` - how the lighting is baked
+pub fn load_or_bake_lighting(tree: &BoundingBoxTree, ecs: &ECS, settings: LightBakeSettings) -> BakedLighting
+{
+    let lights = find_static_lights(tree, ecs);
+    let input_hash = hash_bake_inputs(tree, &lights, settings);
+
+    if let Ok(bytes) = fs::read(baked_lighting_file())
+    {
+        if let Ok(stored_lighting) = bincode::deserialize::<BakedLighting>(&bytes)
+        {
+            if stored_lighting.input_hash == input_hash
+            {
+                return stored_lighting;
+            }
+        }
+    }
+
+    let baked_lighting = bake_lighting(tree, ecs, &lights, settings, input_hash);
+
+    if let Err(err) = fs::write(baked_lighting_file(), bincode::serialize(&baked_lighting).unwrap())
+    {
+        error!("Failed to store baked lighting in {:?}: {}", baked_lighting_file(), err);
+    }
+
+    baked_lighting
+}
+
+/// Finds the lights that are static entities in the world
+///
+/// `tree` - the bounding box tree holding the world
+/// `ecs` - the ECS holding the lights
+fn find_static_lights(tree: &BoundingBoxTree, ecs: &ECS) -> Vec<BakeLight>
+{
+    let section_lights = tree.stored_entities_indexes.values().map(|x| &x.lights)
+        .chain(tree.shared_section_indexes.values().map(|x| &x.lights));
+
+    let mut lights = Vec::new();
+
+    for light_entities in section_lights
+    {
+        for light_type in [FindLightType::Directional, FindLightType::Point, FindLightType::Spot].iter()
+        {
+            for entity_id in light_entities.get_light_entities(*light_type)
+            {
+                if tree.is_entity_static(*entity_id) != Some(true)
+                {
+                    continue;
+                }
+
+                if let (Some(information), Some(position)) = (ecs.get_copy::<LightInformation>(*entity_id), ecs.get_ref::<Position>(*entity_id))
+                {
+                    lights.push(BakeLight{ entity_id: *entity_id, light_type: *light_type, position: position.get_position(), information });
+                }
+            }
+        }
+    }
+
+    // The order of the hash sets is not stable, and the hash of the inputs depends on the order of the lights
+    lights.sort_by_key(|x| x.entity_id);
+    lights
+}
+
+/// Hashes everything the baked lighting depends on, to determine if stored lighting can be reused
+///
+/// `tree` - the bounding box tree holding the world
+/// `lights` - the static lights of the world
+/// `settings` - how the lighting is baked
+fn hash_bake_inputs(tree: &BoundingBoxTree, lights: &[BakeLight], settings: LightBakeSettings) -> u64
+{
+    let mut hasher = DefaultHasher::new();
+
+    settings.resolution.hash(&mut hasher);
+    settings.shadows.hash(&mut hasher);
+    tree.atomic_world_section_length().hash(&mut hasher);
+
+    let mut sections = tree.stored_entities_indexes.iter()
+        .map(|(id, entities)| (id.level(), id.offsets(), entities.local_entities.len(), entities.static_entities.len()))
+        .collect::<Vec<(u16, (u16, u16, u16), usize, usize)>>();
+    sections.sort();
+    sections.hash(&mut hasher);
+
+    for light in lights
+    {
+        let info = &light.information;
+        let direction = info.direction.unwrap_or(vec3(0.0, 0.0, 0.0));
+
+        let values = [light.position.x, light.position.y, light.position.z, info.radius, info.diffuse_colour.x, info.diffuse_colour.y,
+            info.diffuse_colour.z, info.ambient_colour.x, info.ambient_colour.y, info.ambient_colour.z, info.ambient_colour.w,
+            info.linear_coefficient, info.quadratic_coefficient, info.cutoff.unwrap_or(0.0), info.outer_cutoff.unwrap_or(0.0),
+            direction.x, direction.y, direction.z];
+
+        (light.light_type as usize).hash(&mut hasher);
+        values.iter().for_each(|x| x.to_bits().hash(&mut hasher));
+    }
+
+    hasher.finish()
+}
+
+/// Computes the lighting of every world section that only holds static entities
+///
+/// `tree` - the bounding box tree holding the world
+/// `ecs` - the ECS holding the bounding volumes of entities
+/// `lights` - the static lights of the world
+/// `settings` - how the lighting is baked
+/// `input_hash` - the hash of the inputs of the baking
+fn bake_lighting(tree: &BoundingBoxTree, ecs: &ECS, lights: &[BakeLight], settings: LightBakeSettings, input_hash: u64) -> BakedLighting
+{
+    let mut sections = Vec::new();
+    let mut baked_section_ids = Vec::new();
+
+    for (section_id, entities) in &tree.stored_entities_indexes
+    {
+        if entities.local_entities.is_empty() && entities.static_entities.is_empty()
+        {
+            continue;
+        }
+
+        let layer = if entities.local_entities.is_empty()
+        {
+            baked_section_ids.push(*section_id);
+            (baked_section_ids.len() - 1) as i32
+        }
+        else
+        {
+            DYNAMIC_SECTION_LAYER
+        };
+
+        sections.push(BakedSection{ level: section_id.level(), offsets: section_id.offsets(), layer });
+    }
+
+    let resolution = settings.resolution as usize;
+
+    let irradiance = baked_section_ids.par_iter()
+        .flat_map_iter(|section_id|
+            {
+                let aabb = tree.world_section_aabb(*section_id);
+                let sample_spacing = aabb.x_range.length() / resolution as f32;
+
+                (0..resolution * resolution * resolution).map(move |index|
+                    {
+                        let x = index % resolution;
+                        let z = (index / resolution) % resolution;
+                        let y = index / (resolution * resolution);
+
+                        // Samples are at the centre of their part of the world section, matching the texel centres
+                        // the shader interpolates between
+                        let sample = vec3
+                            (
+                                aabb.x_range.min + (x as f32 + 0.5) * sample_spacing,
+                                aabb.y_range.min + (y as f32 + 0.5) * sample_spacing,
+                                aabb.z_range.min + (z as f32 + 0.5) * sample_spacing
+                            );
+
+                        let colour = lights.iter().fold(vec3(0.0, 0.0, 0.0), |acc, light| acc + light_contribution(tree, ecs, light, &sample, settings.shadows));
+                        [colour.x, colour.y, colour.z, 1.0]
+                    }).collect::<Vec<[f32; 4]>>()
+            })
+        .collect::<Vec<[f32; 4]>>();
+
+    BakedLighting
+    {
+        input_hash,
+        resolution: settings.resolution,
+        atomic_section_length: tree.atomic_world_section_length(),
+        sections,
+        number_layers: baked_section_ids.len(),
+        irradiance
+    }
+}
+
+/// Computes the light reaching the sample from the given light. The direction surfaces face is not known
+/// when baking, so surfaces are treated as facing the light
+///
+/// `tree` - the bounding box tree used to find entities blocking the light
+/// `ecs` - the ECS holding the bounding volumes of entities
+/// `light` - the light shining on the sample
+/// `sample` - the position the lighting is computed for
+/// `shadows` - true if entities can block the light
+fn light_contribution(tree: &BoundingBoxTree, ecs: &ECS, light: &BakeLight, sample: &TVec3<f32>, shadows: bool) -> TVec3<f32>
+{
+    let info = &light.information;
+    let ambient = info.ambient_colour.xyz() * info.ambient_colour.w;
+
+    let (light_direction, distance, intensity) = match light.light_type
+    {
+        FindLightType::Directional =>
+            {
+                let direction = info.direction.map(|x| nalgebra_glm::normalize(&x)).unwrap_or(vec3(0.0, -1.0, 0.0));
+                (-direction, tree.outline_length() as f32 * 2.0, 1.0)
+            },
+        FindLightType::Point =>
+            {
+                let distance = nalgebra_glm::distance(&light.position, sample);
+
+                if distance > info.radius || distance <= f32::EPSILON
+                {
+                    return vec3(0.0, 0.0, 0.0);
+                }
+
+                // Point lights shine in a cone between their cutoff and outer cutoff
+                let light_direction = (light.position - sample) / distance;
+                let angle = nalgebra_glm::dot(&-light_direction, &nalgebra_glm::normalize(&info.direction.unwrap_or(vec3(0.0, -1.0, 0.0))));
+                let cutoff = info.cutoff.unwrap_or(-1.0);
+                let outer_cutoff = info.outer_cutoff.unwrap_or(-1.0);
+                let intensity = ((angle - outer_cutoff) / (cutoff - outer_cutoff).max(f32::EPSILON)).clamp(0.0, 1.0);
+
+                (light_direction, distance, intensity)
+            },
+        FindLightType::Spot =>
+            {
+                let distance = nalgebra_glm::distance(&light.position, sample);
+
+                if distance > info.radius || distance <= f32::EPSILON
+                {
+                    return vec3(0.0, 0.0, 0.0);
+                }
+
+                ((light.position - sample) / distance, distance, 1.0)
+            }
+    };
+
+    let attenuation = match light.light_type
+    {
+        FindLightType::Directional => 1.0,
+        _ => 1.0 / (1.0 + info.linear_coefficient * distance + info.quadratic_coefficient * distance * distance)
+    };
+
+    if intensity > 0.0 && shadows && is_light_blocked(tree, ecs, light.entity_id, sample, &light_direction, distance)
+    {
+        return ambient * attenuation;
+    }
+
+    (ambient + info.diffuse_colour * intensity) * attenuation
+}
+
+/// Checks if a static entity is between the sample and the light. Entities whose bounding volume holds the
+/// sample are ignored, as the sample would otherwise always be in shadow of them
+///
+/// `tree` - the bounding box tree used to find entities blocking the light
+/// `ecs` - the ECS holding the bounding volumes of entities
+/// `light_entity` - the entity of the light, which does not block itself
+/// `sample` - the position the lighting is computed for
+/// `light_direction` - the direction from the sample to the light
+/// `distance` - the distance from the sample to the light
+fn is_light_blocked(tree: &BoundingBoxTree, ecs: &ECS, light_entity: EntityId, sample: &TVec3<f32>, light_direction: &TVec3<f32>, distance: f32) -> bool
+{
+    let ray = Ray::new(*sample, *light_direction);
+
+    tree.find_entities_along_ray(ecs, &ray, distance).iter()
+        .any(|(entity_id, hit_distance)| *entity_id != light_entity && *hit_distance > 0.0 && tree.is_entity_static(*entity_id) == Some(true))
+}
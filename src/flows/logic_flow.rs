@@ -10,14 +10,28 @@ use nalgebra_glm::{TVec3, vec3};
 use parking_lot::{Mutex, RwLock};
 use rayon::iter::ParallelIterator;
 use rayon::prelude::ParallelSliceMut;
+use tracing::warn;
 use crate::culling::logic_frustum_culler::LogicFrustumCuller;
 use crate::culling::render_frustum_culler::RenderFrustumCuller;
 use crate::culling::r#trait::TraversalDecider;
 use crate::exports::camera_object::{Camera, MovementFactor};
-use crate::exports::light_components::LightInformation;
+use crate::exports::light_components::{AreaLight, CastsShadows, Emissive, LightInformation};
 use crate::exports::load_models::{InstanceLogic, RegisterInstancesFunction};
-use crate::exports::logic_components::{UserAlwaysCausesCollisions, CanCauseCollisions, IsOutOfBounds, ParentEntity, RenderSystemIndex, UserInputLogic, AlwaysExecuteLogic};
-use crate::exports::movement_components::{Acceleration, AccelerationRotation, HasMoved, HasRotated, Position, Rotation, Scale, TransformationMatrix, Velocity, VelocityRotation};
+use crate::exports::logic_components::{UserAlwaysCausesCollisions, CanCauseCollisions, ChildEntities, IsOutOfBounds, ParentEntity, RenderSystemIndex, UserInputLogic, AlwaysExecuteLogic};
+use crate::exports::fixed_point::{FixedAABB, FixedPoint, FixedVec3};
+use crate::exports::component_reflection::register_reflected_component;
+use crate::exports::state_hash::{hash_if_due, register_hashed_component};
+use crate::exports::movement_components::{Acceleration, AccelerationRotation, FixedAcceleration, FixedPosition, FixedVelocity, HasMoved, HasRotated, Position, PreviousTransformationMatrix, Rotation, Scale, TransformationMatrix, Velocity, VelocityRotation, WorldPosition};
+use crate::exports::particles::ParticleEmitter;
+use crate::exports::sprites::SpriteRenderer;
+use crate::exports::text_labels::TextLabel;
+use crate::exports::instance_params::InstanceParams;
+use crate::exports::dissolve::{advance_dissolves, Dissolve};
+use crate::exports::transparency::Transparent;
+use crate::exports::pbr_material::PbrMaterial;
+use crate::exports::world_precision::to_f64;
+use crate::exports::user_data::UserData;
+use crate::exports::frame_statistics::{FrameFlow, record_flow_time};
 use crate::flows::render_flow::RenderFlow;
 use crate::flows::visible_world_flow::CullResult;
 use crate::helper_things::aabb_helper_functions;
@@ -33,6 +47,10 @@ use crate::threads::public_common_structures::FrameChange;
 use crate::window::input_state::{CurrentFrameInput, InputHistory};
 use crate::world::bounding_box_tree_v2::{BoundingBoxTree, SharedWorldSectionId, UniqueWorldSectionId, WorldSectionLookup};
 use crate::world::bounding_volumes::aabb::StaticAABB;
+use crate::world::visible_world_sections::VisibleWorldSections;
+
+/// Number of fixed timesteps entities moved in fixed point are advanced by each second
+const FIXED_TIMESTEPS_PER_SECOND: i32 = 60;
 
 lazy_static!
 {
@@ -41,6 +59,15 @@ lazy_static!
     static ref LOGIC_TIME_HISTORY: Mutex<TimeTakeHistory> = Mutex::new(TimeTakeHistory::new());
 }
 
+/// The path an entity took during its most recent movement
+#[derive(Copy, Clone)]
+struct SweptMovement
+{
+    previous_aabb: StaticAABB,
+    displacement: TVec3<f32>,
+    fixed_displacement: Option<FixedVec3>,
+}
+
 /// Represents the logic of the entities within the game, making sure their logic is executed
 /// as the game progresses.
 pub struct LogicFlow
@@ -48,10 +75,13 @@ pub struct LogicFlow
     pub ecs: ECS,
     last_accessed_time: Instant, // Keeps movement in units / second
 moved_entities: Mutex<Vec<EntityId>>,
+    recently_moved_entities: HashSet<EntityId>,
     expected_frame_changes: parking_lot::Mutex<Vec<FrameChange>>,
     random_frame_changes: parking_lot::Mutex<Vec<FrameChange>>,
     previous_camera_pos: TVec3<f32>,
     always_execute_entities: HashSet<EntityId>,
+    fixed_time_accumulator: FixedPoint,
+    fixed_time_steps: u32,
 
     pub instance_logic: InstanceLogic,
 }
@@ -91,8 +121,15 @@ impl LogicFlow
         ecs.register_type::<VelocityRotation>();
         ecs.register_type::<AccelerationRotation>();
 
+        ecs.register_type::<FixedPosition>();
+        ecs.register_type::<FixedVelocity>();
+        ecs.register_type::<FixedAcceleration>();
+
+        ecs.register_type::<WorldPosition>();
+
         ecs.register_type::<Scale>();
         ecs.register_type::<TransformationMatrix>();
+        ecs.register_type::<PreviousTransformationMatrix>();
 
         ecs.register_type::<ModelId>();
         ecs.register_type::<RenderSystemIndex>();
@@ -102,18 +139,37 @@ impl LogicFlow
 
         ecs.register_type::<IsOutOfBounds>();
         ecs.register_type::<ParentEntity>();
+        ecs.register_type::<ChildEntities>();
 
         ecs.register_type::<LightInformation>();
+        ecs.register_type::<AreaLight>();
+        ecs.register_type::<Emissive>();
+        ecs.register_type::<CastsShadows>();
+
+        ecs.register_type::<PbrMaterial>();
+
+        ecs.register_type::<ParticleEmitter>();
+        ecs.register_type::<SpriteRenderer>();
+        ecs.register_type::<TextLabel>();
+        ecs.register_type::<InstanceParams>();
+        ecs.register_type::<Dissolve>();
+        ecs.register_type::<Transparent>();
 
         ecs.register_type::<AlwaysExecuteLogic>();
 
         ecs.register_type::<MovementFactor>();
 
+        ecs.register_type::<UserData>();
+
         for x in register_instances
         {
             x(&mut ecs);
         }
 
+        LogicFlow::use_dense_storage(&mut ecs);
+        LogicFlow::register_reflected_components();
+        LogicFlow::register_hashed_components();
+
         let logic_flow = LogicFlow
         {
             /*
@@ -133,11 +189,14 @@ impl LogicFlow
             ecs,
             last_accessed_time: Instant::now(),
             moved_entities: Mutex::new(Vec::new()),
+            recently_moved_entities: HashSet::new(),
             expected_frame_changes: parking_lot::Mutex::new(Vec::new()),
             random_frame_changes: parking_lot::Mutex::new(Vec::new()),
             previous_camera_pos: vec3(0.0, 0.0, 0.0),
             instance_logic,
-            always_execute_entities: HashSet::new()
+            always_execute_entities: HashSet::new(),
+            fixed_time_accumulator: FixedPoint::ZERO,
+            fixed_time_steps: 0
         };
 
 
@@ -147,21 +206,86 @@ impl LogicFlow
     /// Creates a new logic flow from entity states that were loaded elsewhere in the program
     ///
     /// `ecs` - the state of entities to initialize the logic flow with
-    pub fn new_from_loaded_state(ecs: ECS, instance_logic: InstanceLogic) -> LogicFlow
+    pub fn new_from_loaded_state(mut ecs: ECS, instance_logic: InstanceLogic) -> LogicFlow
     {
+        LogicFlow::use_dense_storage(&mut ecs);
+        LogicFlow::register_hashed_components();
+
         LogicFlow
         {
             ecs,
             last_accessed_time: Instant::now(),
             moved_entities: Mutex::new(Vec::new()),
+            recently_moved_entities: HashSet::new(),
             expected_frame_changes: parking_lot::Mutex::new(Vec::new()),
             random_frame_changes: parking_lot::Mutex::new(Vec::new()),
             previous_camera_pos: vec3(0.0, 0.0, 0.0),
             instance_logic,
-            always_execute_entities: HashSet::new()
+            always_execute_entities: HashSet::new(),
+            fixed_time_accumulator: FixedPoint::ZERO,
+            fixed_time_steps: 0
         }
     }
 
+    /// Packs the components read for every rendered entity each frame, so that collecting the instance
+    /// data of the rendered entities accesses contiguous memory. Dense storage is not serialized, so this
+    /// is done for every created or loaded ECS
+    ///
+    /// `ecs` - the ECS holding the state of the entities
+    fn use_dense_storage(ecs: &mut ECS)
+    {
+        ecs.use_dense_storage::<TransformationMatrix>();
+        ecs.use_dense_storage::<ModelId>();
+    }
+
+    /// Allows the components of the engine to be inspected and edited by name
+    fn register_reflected_components()
+    {
+        register_reflected_component::<Position>("Position");
+        register_reflected_component::<Velocity>("Velocity");
+        register_reflected_component::<Acceleration>("Acceleration");
+        register_reflected_component::<Rotation>("Rotation");
+        register_reflected_component::<VelocityRotation>("VelocityRotation");
+        register_reflected_component::<AccelerationRotation>("AccelerationRotation");
+        register_reflected_component::<Scale>("Scale");
+        register_reflected_component::<TransformationMatrix>("TransformationMatrix");
+        register_reflected_component::<ModelId>("ModelId");
+        register_reflected_component::<StaticAABB>("StaticAABB");
+        register_reflected_component::<OriginalAABB>("OriginalAABB");
+        register_reflected_component::<PbrMaterial>("PbrMaterial");
+        register_reflected_component::<InstanceParams>("InstanceParams");
+        register_reflected_component::<Dissolve>("Dissolve");
+        register_reflected_component::<Transparent>("Transparent");
+    }
+
+    /// Includes the movement of entities in the hash of the game state recorded into history
+    fn register_hashed_components()
+    {
+        register_hashed_component::<Position>("Position");
+        register_hashed_component::<Velocity>("Velocity");
+        register_hashed_component::<Acceleration>("Acceleration");
+        register_hashed_component::<Rotation>("Rotation");
+        register_hashed_component::<VelocityRotation>("VelocityRotation");
+        register_hashed_component::<AccelerationRotation>("AccelerationRotation");
+        register_hashed_component::<Scale>("Scale");
+    }
+
+    /// Replaces the state of the entities, such as when a snapshot of the game is loaded. Information
+    /// about entities of the previous state is discarded
+    ///
+    /// `ecs` - the new state of the entities
+    pub fn replace_ecs(&mut self, mut ecs: ECS)
+    {
+        LogicFlow::use_dense_storage(&mut ecs);
+
+        self.ecs = ecs;
+        self.moved_entities.lock().clear();
+        self.recently_moved_entities.clear();
+        self.always_execute_entities.clear();
+        self.expected_frame_changes.lock().clear();
+        self.random_frame_changes.lock().clear();
+    }
+
     pub fn execute_user_input(&mut self, args: ExecutionArgs, input_functions: &Vec<UserInputLogic>)
     {
         let user_id = self.ecs.get_user_id();
@@ -185,6 +309,13 @@ impl LogicFlow
         {
             let mut change_history = self.random_frame_changes.lock();
 
+            // Recorded before the logic of this frame runs, so that a replay compares the hash before
+            // executing the same frame
+            if let Some(state_hash) = hash_if_due(&self.ecs)
+            {
+                change_history.push(FrameChange::StateHash(state_hash));
+            }
+
             change_history.push(FrameChange::DeltaTime(args.delta_time));
 
             if args.camera.get_view_matrix_changed()
@@ -243,6 +374,8 @@ impl LogicFlow
 
         self.handle_collisions(&args);
         self.update_logic(&active_world_sections, &args);
+        self.execute_systems(&args);
+        self.advance_dissolves(args.delta_time);
 
         // Add the updated user entity AABB to the bounding box tree
         args.bounding_box_tree.remove_entity(user_id);
@@ -259,6 +392,10 @@ impl LogicFlow
 
         self.expected_frame_changes.lock().clear();
 
+        // Components written and events sent from this point on belong to the next frame
+        self.ecs.advance_change_tick();
+        self.ecs.advance_events();
+
         let mut new_random_frame_changes = Vec::with_capacity(self.random_frame_changes.lock().len());
         swap(&mut new_random_frame_changes, &mut *self.random_frame_changes.lock());
         new_random_frame_changes
@@ -291,6 +428,7 @@ impl LogicFlow
                         model_bank_owner.write().remove_instance( model_index);
 
                         bounding_box_tree.remove_entity(entity);
+                        bounding_box_tree.remove_entity_from_groups(entity);
                         self.ecs.remove_entity(entity);
                     }
 
@@ -309,6 +447,18 @@ impl LogicFlow
     {
         self.reset_has_changed_component();
 
+        // Entities moved in fixed point advance by whole fixed timesteps, so that their movement does not
+        // depend on how long each frame took. Time not covered by a timestep is carried over to the next frame
+        let fixed_timestep = FixedPoint::from_ratio(1, FIXED_TIMESTEPS_PER_SECOND);
+        self.fixed_time_accumulator += FixedPoint::from_f32(args.delta_time);
+        self.fixed_time_steps = 0;
+
+        while self.fixed_time_accumulator >= fixed_timestep
+        {
+            self.fixed_time_accumulator = self.fixed_time_accumulator - fixed_timestep;
+            self.fixed_time_steps += 1;
+        }
+
         // Reserve required capacity for entities that will move; quick operation to perform that could reduce
         // many redundant re-allocations
         let type_id = [TypeIdentifier::from(TypeId::of::<Acceleration>())];
@@ -353,7 +503,10 @@ impl LogicFlow
                 }
             };
 
-        TimeTakeHistory::apply_to_function(&mut *POSITION_TIME_HISTORY.lock(), updated_kinematics_fn, affected_world_ids);
+        let mut time_history = POSITION_TIME_HISTORY.lock();
+        TimeTakeHistory::apply_to_function(&mut *time_history, updated_kinematics_fn, affected_world_ids);
+        record_flow_time(FrameFlow::Kinematics, time_history.last_time_taken());
+
         LogicFlow::apply_kinematics(&self, &self.always_execute_entities, args.delta_time);
     }
 
@@ -369,10 +522,50 @@ impl LogicFlow
         {
             let mut entity_moved = false;
 
+            // Same as the floating point kinematics below, but computed in fixed point with a fixed timestep
+            // so that the result is identical on every machine. The floating point position is derived from the result
+            if logic_flow.ecs.check_component_written_assume_registered::<FixedVelocity>(*entity)
+            {
+                let mut entity_change_request = EntityChangeRequest::new(*entity);
+                let fixed_timestep = FixedPoint::from_ratio(1, FIXED_TIMESTEPS_PER_SECOND);
+
+                let original_velocity = logic_flow.ecs.get_copy::<FixedVelocity>(*entity).unwrap().get_velocity();
+                let acceleration = logic_flow.ecs.get_copy::<FixedAcceleration>(*entity).map_or(FixedVec3::default(), |x| x.get_acceleration());
+
+                let mut velocity = original_velocity;
+                let mut displacement = FixedVec3::default();
+
+                for _ in 0..logic_flow.fixed_time_steps
+                {
+                    velocity += acceleration * fixed_timestep;
+                    displacement += velocity * fixed_timestep;
+                }
+
+                if velocity != original_velocity
+                {
+                    entity_change_request.add_new_change::<FixedVelocity>(FixedVelocity::new(velocity));
+                }
+
+                let position = logic_flow.ecs.get_copy::<FixedPosition>(*entity).unwrap();
+                if !displacement.is_zero()
+                {
+                    let position = position.moved_by(displacement);
+                    entity_change_request.add_new_change::<FixedPosition>(position);
+                    entity_change_request.add_new_change::<Position>(position.to_position());
+                    entity_change_request.add_new_change::<HasMoved>(HasMoved);
+                }
+
+                if entity_change_request.number_changes() != 0
+                {
+                    logic_flow.expected_frame_changes.lock().push(FrameChange::EntityChange(vec![EntityChangeInformation::ModifyRequest(entity_change_request)]));
+                }
+
+                entity_moved = true;
+            }
             // If an Entity has an acceleration component, then it has a velocity and position component.
             // The acceleration and velocity can be 0, but if one of components exist, then the other
             // one (including position) must exist as well
-            if logic_flow.ecs.check_component_written_assume_registered::<Velocity>(*entity)
+            else if logic_flow.ecs.check_component_written_assume_registered::<Velocity>(*entity)
             {
                 let mut entity_change_request = EntityChangeRequest::new(*entity);
 
@@ -393,8 +586,23 @@ impl LogicFlow
                 let mut position = logic_flow.ecs.get_copy::<Position>(*entity).unwrap();
                 if nalgebra_glm::length(&velocity.get_velocity()) != 0.0
                 {
-                    position += velocity * elapsed_time;
-                    entity_change_request.add_new_change::<Position>(position);
+                    // Entities with a double precision position are moved in double precision, with the
+                    // single precision position derived from the result
+                    match logic_flow.ecs.get_copy::<WorldPosition>(*entity)
+                    {
+                        Some(world_position) =>
+                            {
+                                let world_position = world_position.moved_by(to_f64(velocity.get_velocity() * elapsed_time));
+                                entity_change_request.add_new_change::<WorldPosition>(world_position);
+                                entity_change_request.add_new_change::<Position>(world_position.to_position());
+                            },
+                        None =>
+                            {
+                                position += velocity * elapsed_time;
+                                entity_change_request.add_new_change::<Position>(position);
+                            }
+                    }
+
                     entity_change_request.add_new_change::<HasMoved>(HasMoved);
                 }
 
@@ -472,6 +680,12 @@ impl LogicFlow
         let moved_entities = self.moved_entities.lock();
         let moved_entities_map: HashSet<&EntityId> = HashSet::from_iter(moved_entities.iter());
 
+        // An entity can move further than the thickness of another entity in a single frame. The path each
+        // moved entity took is used to find collisions that would be missed by only checking current positions
+        let swept_movements: HashMap<EntityId, SweptMovement> = moved_entities.iter()
+            .filter_map(|x| self.find_swept_movement(*x).map(|movement| (*x, movement)))
+            .collect();
+
         // Find world sections that moved entities are in
         let mut relevant_world_sections: HashMap<UniqueWorldSectionId, Vec<EntityId>> = HashMap::default();
 
@@ -499,6 +713,26 @@ impl LogicFlow
                         }
                     }
             }
+
+            // The entities that a fast moving entity passed through may be in world sections it is no longer in
+            if let Some(movement) = swept_movements.get(entity)
+            {
+                let swept_aabb = movement.previous_aabb.combine_aabb(self.ecs.get_ref::<StaticAABB>(*entity).unwrap());
+
+                for x in args.bounding_box_tree.find_all_unique_world_section_ids(&swept_aabb)
+                {
+                    if !args.bounding_box_tree.is_section_in_existence(&x)
+                    {
+                        continue;
+                    }
+
+                    let entities = relevant_world_sections.entry(x).or_insert_with(Vec::new);
+                    if !entities.contains(entity)
+                    {
+                        entities.push(*entity);
+                    }
+                }
+            }
         }
 
 
@@ -600,13 +834,13 @@ impl LogicFlow
             }).collect::<()>();
 
         // Apply collisions as required
-        let apply_collision_only_to_self = |this_entity: EntityId, other_entity: EntityId|
+        let apply_collision_only_to_self = |this_entity: EntityId, other_entity: EntityId, time_of_impact: f32|
             {
                 if let Some(this_entity_type) = self.ecs.get_entity_type(this_entity)
                 {
                     if let Some(collision_logic) = self.instance_logic.collision_logic.get(&this_entity_type)
                     {
-                        let changes = (collision_logic.logic)(this_entity, EntityIdRead::new(other_entity), &self.ecs, args.bounding_box_tree);
+                        let changes = (collision_logic.logic)(this_entity, EntityIdRead::new(other_entity), time_of_impact, &self.ecs, args.bounding_box_tree);
 
                         if !changes.is_empty()
                         {
@@ -631,27 +865,27 @@ impl LogicFlow
                                 continue;
                             }
 
-                            let other_entity_aabb = self.ecs.get_ref::<StaticAABB>(*other_entity).unwrap();
-                            if this_aabb.intersect(other_entity_aabb)
+                            if let Some(time_of_impact) = self.find_time_of_impact(&swept_movements, *moved_entity, &this_aabb, *other_entity)
                             {
-                                apply_collision_only_to_self(*moved_entity, *other_entity);
+                                apply_collision_only_to_self(*moved_entity, *other_entity, time_of_impact);
                             }
                         }
 
                         for other_entity in &x.relevant_both_collision_entities
                         {
-                            let other_entity_aabb = self.ecs.get_ref::<StaticAABB>(*other_entity).unwrap();
-                            if this_aabb.intersect(other_entity_aabb)
+                            if let Some(time_of_impact) = self.find_time_of_impact(&swept_movements, *moved_entity, &this_aabb, *other_entity)
                             {
-                                apply_collision_only_to_self(*moved_entity, *other_entity);
-                                apply_collision_only_to_self(*other_entity, *moved_entity);
+                                apply_collision_only_to_self(*moved_entity, *other_entity, time_of_impact);
+                                apply_collision_only_to_self(*other_entity, *moved_entity, time_of_impact);
                             }
                         }
                     }
                 }
             };
 
-        TimeTakeHistory::apply_to_function(&mut *COLLISION_TIME_HISTORY.lock(), collision_fn, &sorted_world_section_entities);
+        let mut time_history = COLLISION_TIME_HISTORY.lock();
+        TimeTakeHistory::apply_to_function(&mut *time_history, collision_fn, &sorted_world_section_entities);
+        record_flow_time(FrameFlow::Collisions, time_history.last_time_taken());
     }
 
     /// Performs the onFrame logic for each entity within the specified world sections. All changes to
@@ -663,6 +897,9 @@ impl LogicFlow
     {
         let processed_world_sections: Mutex<HashSet<SharedWorldSectionId>> = Mutex::new(HashSet::default());
 
+        #[cfg(feature = "scripting")]
+        self.instance_logic.scripted_logic.values().for_each(|x| x.reload_if_changed());
+
         let apply_entity_logic = |ecs: &ECS, entities: &HashSet::<EntityId>, elapsed_time: f32|
             {
                 for entity in entities
@@ -679,6 +916,17 @@ impl LogicFlow
                             }
                         }
 
+                        #[cfg(feature = "scripting")]
+                        if let Some(scripted_logic) = self.instance_logic.scripted_logic.get(&entity_type)
+                        {
+                            let changes = scripted_logic.execute(*entity, ecs, elapsed_time);
+
+                            if !changes.is_empty()
+                            {
+                                self.expected_frame_changes.lock().push(FrameChange::EntityChange(changes));
+                            }
+                        }
+
                         if let Some(entity_logic) = self.instance_logic.random_entity_logic.get(&entity_type)
                         {
                             let changes = (entity_logic.logic)(*entity, ecs, args.bounding_box_tree, elapsed_time);
@@ -729,10 +977,49 @@ impl LogicFlow
                 }
             };
 
-        TimeTakeHistory::apply_to_function(&mut *LOGIC_TIME_HISTORY.lock(), logic_fn,affected_world_ids);
+        let mut time_history = LOGIC_TIME_HISTORY.lock();
+        TimeTakeHistory::apply_to_function(&mut *time_history, logic_fn,affected_world_ids);
+        record_flow_time(FrameFlow::EntityLogic, time_history.last_time_taken());
+
         apply_entity_logic(&self.ecs, &self.always_execute_entities, args.delta_time);
     }
 
+    /// Executes the systems added by the user, storing the changes they requested to be applied along with
+    /// the changes requested by the logic of entities
+    ///
+    /// `args` - the required variables to execute a game frame logic
+    fn execute_systems(&mut self, args: &ExecutionArgs)
+    {
+        if self.instance_logic.systems.number_systems() == 0
+        {
+            return;
+        }
+
+        let visible_world_sections = VisibleWorldSections::new(&args.visible_world_sections, args.bounding_box_tree, args.camera.get_position());
+
+        for changes in self.instance_logic.systems.execute(&mut self.ecs, &visible_world_sections, args.delta_time)
+        {
+            if !changes.is_empty()
+            {
+                self.expected_frame_changes.lock().push(FrameChange::EntityChange(changes));
+            }
+        }
+    }
+
+    /// Advances the dissolve of every dissolving entity, deleting the entities that have finished dissolving
+    /// out of view
+    ///
+    /// `elapsed_time` - the time the frame advances by in seconds
+    fn advance_dissolves(&mut self, elapsed_time: f32)
+    {
+        let changes = advance_dissolves(&self.ecs, elapsed_time);
+
+        if !changes.is_empty()
+        {
+            self.expected_frame_changes.lock().push(FrameChange::EntityChange(changes));
+        }
+    }
+
     /// Updates the bounding box tree based off of the actions performed to an entity that resulted in its position being
     /// changed after the updating functions (movement, collision or on frame logic)
     ///
@@ -772,6 +1059,99 @@ impl LogicFlow
         apply_change(change_args, Some(changes));
     }
 
+    /// Finds the path the given entity took during its most recent movement. None is returned if the
+    /// entity did not move in the last frame
+    ///
+    /// `entity` - the entity to find the movement of
+    fn find_swept_movement(&self, entity: EntityId) -> Option<SweptMovement>
+    {
+        if !self.recently_moved_entities.contains(&entity)
+        {
+            return None;
+        }
+
+        let current_aabb = self.ecs.get_ref::<StaticAABB>(entity)?;
+
+        // The displacement of entities moved in fixed point is known exactly, so it is used directly rather
+        // than being derived from floating point transformations
+        if let Some(position) = self.ecs.get_ref::<FixedPosition>(entity)
+        {
+            let displacement = position.get_displacement().to_vec3();
+
+            if !self.ecs.check_component_written_assume_registered::<HasRotated>(entity)
+            {
+                let mut previous_aabb = *current_aabb;
+                previous_aabb.translate(-displacement);
+
+                return Some(SweptMovement{ previous_aabb, displacement, fixed_displacement: Some(position.get_displacement()) });
+            }
+        }
+
+        let previous_transformation = self.ecs.get_copy::<PreviousTransformationMatrix>(entity)?;
+        let previous_aabb = self.ecs.get_ref::<OriginalAABB>(entity)?.aabb.clone().apply_transformation(&previous_transformation.get_matrix());
+
+        Some(SweptMovement{ previous_aabb, displacement: current_aabb.centre() - previous_aabb.centre(), fixed_displacement: None })
+    }
+
+    /// Determines if the two entities collided during their most recent movement, returning the fraction
+    /// of the movement at which they first touched. Collisions are found using the relative movement of the
+    /// entities so that fast moving entities do not pass through other entities without colliding
+    ///
+    /// `swept_movements` - the movements of the entities that can cause collisions
+    /// `this_entity` - the entity that moved
+    /// `this_aabb` - the current bounding volume of the entity that moved
+    /// `other_entity` - the entity to check for a collision with
+    fn find_time_of_impact(&self, swept_movements: &HashMap<EntityId, SweptMovement>, this_entity: EntityId, this_aabb: &StaticAABB, other_entity: EntityId) -> Option<f32>
+    {
+        let other_aabb = self.ecs.get_ref::<StaticAABB>(other_entity).unwrap();
+
+        let this_movement = swept_movements.get(&this_entity).copied();
+        let other_movement = swept_movements.get(&other_entity).copied().or_else(|| self.find_swept_movement(other_entity));
+
+        if this_movement.is_some() || other_movement.is_some()
+        {
+            // Entities moved in fixed point, colliding with each other or with entities that did not move, find
+            // the time of impact in fixed point so that every machine reports the same time of impact
+            let this_fixed_displacement = this_movement.map_or(Some(FixedVec3::default()), |x| x.fixed_displacement);
+            let other_fixed_displacement = other_movement.map_or(Some(FixedVec3::default()), |x| x.fixed_displacement);
+
+            let time_of_impact = match (this_fixed_displacement, other_fixed_displacement)
+            {
+                (Some(this_displacement), Some(other_displacement)) =>
+                    {
+                        let mut this_previous_aabb = FixedAABB::from_aabb(this_aabb);
+                        this_previous_aabb.translate(-this_displacement);
+
+                        let mut other_previous_aabb = FixedAABB::from_aabb(other_aabb);
+                        other_previous_aabb.translate(-other_displacement);
+
+                        this_previous_aabb.swept_intersect(&other_previous_aabb, this_displacement - other_displacement).map(|x| x.to_f32())
+                    },
+                _ =>
+                    {
+                        let (this_previous_aabb, this_displacement) = this_movement.map_or((*this_aabb, vec3(0.0, 0.0, 0.0)), |x| (x.previous_aabb, x.displacement));
+                        let (other_previous_aabb, other_displacement) = other_movement.map_or((*other_aabb, vec3(0.0, 0.0, 0.0)), |x| (x.previous_aabb, x.displacement));
+
+                        this_previous_aabb.swept_intersect(&other_previous_aabb, this_displacement - other_displacement)
+                    }
+            };
+
+            if time_of_impact.is_some()
+            {
+                return time_of_impact;
+            }
+        }
+
+        // Movements that only rotate or scale an entity are not captured by the sweep, so the current
+        // bounding volumes are checked as well. Without any movement, the entities were already touching
+        if this_aabb.intersect(other_aabb)
+        {
+            return if this_movement.is_some() || other_movement.is_some() { Some(1.0) } else { Some(0.0) };
+        }
+
+        None
+    }
+
     /// Takes all entities that had a component indicating they moved or rotated in the previous frame
     /// and removes those components
     fn reset_has_changed_component(&mut self)
@@ -787,14 +1167,20 @@ impl LogicFlow
                 self.ecs.get_indexes_for_components(&type_id)
             };
 
+        // Entities that moved in the last frame have an up to date previous transformation, and so their
+        // movement can be swept when finding collisions
+        self.recently_moved_entities.clear();
+
         for x in entities_that_moved
         {
             self.ecs.remove_component::<HasMoved>(x);
+            self.recently_moved_entities.insert(x);
         }
 
         for x in entities_that_rotated
         {
             self.ecs.remove_component::<HasRotated>(x);
+            self.recently_moved_entities.insert(x);
         }
     }
 
@@ -831,7 +1217,7 @@ impl LogicFlow
                             self.always_execute_entities.insert(entity);
                         }
                     },
-                None => eprintln!("Unexpected entity for always exeute: {:?}", entity)
+                None => warn!("Unexpected entity for always exeute: {:?}", entity)
             }
         }
     }
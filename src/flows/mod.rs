@@ -1,6 +1,7 @@
 pub mod render_flow;
 mod logic_flow;
-mod visible_world_flow;
+pub(crate) mod visible_world_flow;
 pub mod pipeline;
 pub mod shadow_flow;
+pub(crate) mod light_bake_flow;
 pub mod shared_constants;
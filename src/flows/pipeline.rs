@@ -1,10 +1,21 @@
 use std::sync::Arc;
 use std::time::Instant;
+use hashbrown::HashMap;
 use nalgebra_glm::{TVec3, vec3, vec4};
 use parking_lot::RwLock;
+use tracing::{debug, error, info, info_span, trace};
+use crate::exports::camera_controller::CameraControllerArgs;
 use crate::exports::camera_object::{Camera, MovementFactor};
-use crate::exports::load_models::{AddInstanceFunction, InstanceLogic, RegisterInstancesFunction};
+use crate::exports::entity_interpolation::{advance_logic_clock, fixed_logic_timestep};
+use crate::exports::frame_statistics::{FrameFlow, record_flow_time};
+use crate::exports::load_models::{AddInstanceFunction, InstanceLogic, RegisterInstancesFunction, Skybox};
+use crate::exports::prefab::{Prefab, PrefabTransform};
+use crate::exports::section_export::SectionExport;
+use crate::exports::world_snapshot::{SnapshotError, WorldSnapshot};
+use crate::exports::world_query::publish_frame;
+use crate::exports::world_section_overlay::draw_world_section_overlay;
 use crate::exports::rendering::LevelOfView;
+use crate::exports::state_hash::verify_recorded_hash;
 use crate::flows::logic_flow::{ExecutionArgs, LogicFlow};
 use crate::flows::render_flow::{RenderArguments, RenderFlow};
 use crate::flows::shared_constants::WORLD_SECTION_LENGTH;
@@ -16,15 +27,16 @@ use crate::exports::logic_components::{UserAlwaysCausesCollisions, CanCauseColli
 use crate::exports::movement_components::{Acceleration, Position, TransformationMatrix, Velocity};
 use crate::exports::user_focused_entities::user_type_identifier;
 use crate::flows::visible_world_flow::{CullResult, VisibleWorldFlow};
-use crate::helper_things::entity_change_helpers::{apply_change, ChangeArgs};
+use crate::helper_things::entity_change_helpers::{apply_change, ChangeArgs, find_entity_light_type};
 use crate::helper_things::environment::get_model_folder;
 use crate::models::model_definitions::{ModelId, OriginalAABB};
+use crate::models::model_file_watcher::ModelFileWatcher;
 use crate::models::model_storage::{LoadModelInfo, ModelBankOwner};
 use crate::render_system::render_system::RenderSystem;
 use crate::render_system::system_information::DrawFunction;
 use crate::threads::public_common_structures::FrameChange;
 use crate::window::input_state::{CurrentFrameInput, InputHistory};
-use crate::world::bounding_box_tree_v2::BoundingBoxTree;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
 use crate::world::bounding_volumes::aabb::StaticAABB;
 
 type LastFrame = bool;
@@ -41,7 +53,9 @@ pub struct Pipeline
     frame_indexes: Vec<usize>,
     current_frame_index: usize,
     input_functions: Vec<UserInputLogic>,
-
+    uploaded_models: Vec<(ModelId, LoadModelInfo<String>)>,
+    model_file_watcher: Option<ModelFileWatcher>,
+    logic_time_accumulator: f32,
 }
 
 impl Pipeline
@@ -68,7 +82,10 @@ impl Pipeline
             debug_changes: Vec::new(),
             frame_indexes: Vec::new(),
             current_frame_index: 0,
-            input_functions
+            input_functions,
+            uploaded_models: Vec::new(),
+            model_file_watcher: None,
+            logic_time_accumulator: 0.0,
         }
     }
 
@@ -103,7 +120,10 @@ impl Pipeline
                 debug_changes: loaded_state.changes,
                 frame_indexes,
                 current_frame_index: 0,
-                input_functions
+                input_functions,
+                uploaded_models: Vec::new(),
+                model_file_watcher: None,
+                logic_time_accumulator: 0.0,
             },
             Arc::new(RwLock::new(loaded_state.camera))
         );
@@ -143,12 +163,14 @@ impl Pipeline
         self.logic_flow.ecs.write_component(entity, initial_user_movement);
     }
 
-    pub fn create_user_entity_instance(&mut self, render_system_index: RenderSystemIndex)
+    /// Uploads the model of the user and adds the user to the world
+    ///
+    /// `render_system_index` - the render system the model of the user is uploaded to
+    /// `aabb_from_model` - true if the bounding volume of the user is calculated from the model of the
+    ///                     user, replacing the bounding volume the user was registered with
+    pub fn create_user_entity_instance(&mut self, render_system_index: RenderSystemIndex, aabb_from_model: bool)
     {
         let entity = self.logic_flow.ecs.get_user_id();
-        let aabb = self.logic_flow.ecs.get_copy::<StaticAABB>(entity).unwrap();
-
-        self.bounding_box_tree.add_entity(entity, &aabb, false, false, None).unwrap();
 
         let user_load_info = LoadModelInfo
         {
@@ -164,10 +186,24 @@ impl Pipeline
             ],
             custom_level_of_view: None,
             model_texture_dir: Default::default(),
-            solid_colour_texture: Some(vec4(255, 255, 255, 0))
+            solid_colour_texture: Some(vec4(255, 255, 255, 0)),
+            import_settings: None,
         };
 
         let model_id = self.upload_model(user_load_info);
+
+        if aabb_from_model
+        {
+            let mut original_aabb = self.model_bank_owner.read().get_model_info(model_id).unwrap().aabb.aabb;
+            self.logic_flow.ecs.write_component::<OriginalAABB>(entity, OriginalAABB{ aabb: original_aabb });
+
+            original_aabb.translate(self.logic_flow.ecs.get_copy::<Position>(entity).unwrap().get_position());
+            self.logic_flow.ecs.write_component::<StaticAABB>(entity, original_aabb);
+        }
+
+        let aabb = self.logic_flow.ecs.get_copy::<StaticAABB>(entity).unwrap();
+        self.bounding_box_tree.add_entity(entity, &aabb, false, false, None).unwrap();
+
         self.logic_flow.ecs.write_component::<ModelId>(entity, model_id);
         self.model_bank_owner.write().register_instances(model_id, 1);
         self.bounding_box_tree.end_of_changes(&self.logic_flow.ecs);
@@ -176,8 +212,146 @@ impl Pipeline
     /// Uploads a new model to the pipeline. Afterwards, instances of the model can be created
     pub fn upload_model<T: Into<String> + Clone>(&mut self, model_info: LoadModelInfo<T>) -> ModelId
     {
-        let model_id = self.model_bank_owner.write().register_model(&model_info, &mut self.render_flow);
-        self.render_flow.register_model_with_render_system(model_info.model_name.into(), model_id, model_info.custom_level_of_view, true);
+        let model_info = LoadModelInfo
+        {
+            model_name: model_info.model_name.into(),
+            render_system_index: model_info.render_system_index,
+            location: model_info.location,
+            custom_level_of_view: model_info.custom_level_of_view,
+            model_texture_dir: model_info.model_texture_dir,
+            solid_colour_texture: model_info.solid_colour_texture,
+            import_settings: model_info.import_settings,
+        };
+
+        let model_id = self.register_model_with_render_flow(&model_info);
+
+        if let Some(ref mut model_file_watcher) = self.model_file_watcher
+        {
+            model_file_watcher.watch(model_id, &model_info);
+        }
+
+        self.uploaded_models.push((model_id, model_info));
+        model_id
+    }
+
+    /// Watches the files of all uploaded models, including models uploaded afterwards, so that changes to
+    /// the files are shown without restarting the game. The files are reloaded by reload_changed_models
+    pub fn enable_model_hot_reload(&mut self)
+    {
+        let mut model_file_watcher = ModelFileWatcher::new();
+
+        for (model_id, model_info) in &self.uploaded_models
+        {
+            model_file_watcher.watch(*model_id, model_info);
+        }
+
+        self.model_file_watcher = Some(model_file_watcher);
+    }
+
+    /// Sets the sky drawn behind everything rendered to the window
+    ///
+    /// `skybox` - the images to create the sky from, or None to stop drawing a sky
+    pub fn set_skybox(&mut self, skybox: Option<Skybox>)
+    {
+        self.render_flow.set_skybox(skybox);
+    }
+
+    /// Reloads the geometry of the models whose files were modified since they were last checked. The
+    /// reloaded models are uploaded again to the render systems, and their instances are kept. Nothing is
+    /// done if hot reloading of models is not enabled
+    pub fn reload_changed_models(&mut self)
+    {
+        let modified_models = match self.model_file_watcher
+        {
+            Some(ref mut model_file_watcher) => model_file_watcher.poll(),
+            None => return
+        };
+
+        for (model_id, level_of_view) in modified_models
+        {
+            // The ID of a removed model can be given to a later uploaded model, so the latest upload is used
+            let model_info = match self.uploaded_models.iter().rev().find(|(x, _)| *x == model_id)
+            {
+                Some((_, model_info)) => model_info,
+                None => continue
+            };
+
+            if self.model_bank_owner.write().reload_model(model_id, model_info, level_of_view, &mut self.render_flow)
+            {
+                info!("Reloaded model {} from {:?}", model_info.model_name, model_info.location[level_of_view]);
+            }
+        }
+    }
+
+    /// Recreates all of the rendering resources after the GL context was lost, such as after a GPU driver
+    /// reset. The models that were uploaded are uploaded again to the given render systems, while the
+    /// entities and the bounding box tree are kept as they are, allowing the game to resume rendering its
+    /// current state. The GL context being rendered to must already have been recreated
+    ///
+    /// `render_flow` - the render flow created from the recreated render systems, given in the same order
+    ///                 as they were originally given
+    pub fn recreate_render_resources(&mut self, mut render_flow: RenderFlow)
+    {
+        render_flow.set_skybox(self.render_flow.skybox());
+
+        for render_system_index in self.render_flow.disabled_render_systems()
+        {
+            render_flow.set_render_system_enabled(render_system_index, false);
+        }
+
+        // The FBOs of the camera views were lost along with the context, so they are created again
+        for description in self.render_flow.camera_view_descriptions()
+        {
+            render_flow.add_camera_view(description);
+        }
+
+        for (render_system_index, camera_view) in self.render_flow.assigned_camera_views()
+        {
+            render_flow.assign_camera_view(render_system_index, Some(camera_view));
+        }
+
+        for (render_system_index, slot, draw_function) in self.render_flow.replaced_draw_functions()
+        {
+            render_flow.replace_draw_function(render_system_index, slot, draw_function);
+        }
+
+        // The objects of the previous render flow were destroyed along with the lost context. Dropping it would
+        // delete the objects of the new context that were given the same names, so it is leaked instead
+        std::mem::forget(std::mem::replace(&mut self.render_flow, render_flow));
+
+        let number_render_systems = self.model_bank_owner.read().number_model_banks();
+        *self.model_bank_owner.write() = ModelBankOwner::new(number_render_systems);
+
+        // Models are uploaded in their original order so that they are given the same IDs as before, which the
+        // entities refer to
+        let uploaded_models = std::mem::take(&mut self.uploaded_models);
+        for (original_model_id, model_info) in &uploaded_models
+        {
+            let model_id = self.register_model_with_render_flow(model_info);
+            debug_assert_eq!(*original_model_id, model_id, "Model {} was given a different ID when uploaded again", model_info.model_name);
+        }
+        self.uploaded_models = uploaded_models;
+
+        let mut number_instances: HashMap<ModelId, u32> = HashMap::default();
+        for (_, (model_id, )) in self.logic_flow.ecs.query_read::<(&ModelId, )>()
+        {
+            *number_instances.entry(*model_id).or_insert(0) += 1;
+        }
+
+        for (model_id, count) in number_instances
+        {
+            self.model_bank_owner.write().register_instances(model_id, count);
+        }
+    }
+
+    /// Registers the given model with the model banks and the render systems, uploading its geometry
+    /// and textures
+    ///
+    /// `model_info` - the model information required to register the model
+    fn register_model_with_render_flow(&mut self, model_info: &LoadModelInfo<String>) -> ModelId
+    {
+        let model_id = self.model_bank_owner.write().register_model(model_info, &mut self.render_flow);
+        self.render_flow.register_model_with_render_system(model_info.model_name.clone(), model_id, model_info.custom_level_of_view.clone(), true);
         model_id
     }
 
@@ -207,6 +381,169 @@ impl Pipeline
         self.bounding_box_tree.end_of_changes(&self.logic_flow.ecs);
     }
 
+    /// Spawns entities from a prefab whose model has been uploaded, one entity for each given transform
+    ///
+    /// `prefab` - the template of the entities to spawn
+    /// `transforms` - the placement of each spawned entity
+    pub fn spawn_prefab_instances(&mut self, prefab: &Prefab, transforms: &Vec<PrefabTransform>)
+    {
+        let model_id = match self.model_bank_owner.read().lookup_model(prefab.get_model_name())
+        {
+            Some(i) => *i,
+            None => panic!("Unable to find a model with the name: {}", prefab.get_model_name())
+        };
+
+        let original_aabb = self.model_bank_owner.read().get_model_info(model_id).unwrap().aabb.aabb;
+
+        for transform in transforms
+        {
+            let entity = self.logic_flow.ecs.create_entity();
+            prefab.instantiate(entity, transform, model_id, original_aabb, &mut self.logic_flow.ecs, &mut self.bounding_box_tree);
+        }
+
+        self.model_bank_owner.write().register_instances(model_id, transforms.len() as u32);
+        self.bounding_box_tree.end_of_changes(&self.logic_flow.ecs);
+    }
+
+    /// Get the length of the smallest world sections the game world is divided into
+    pub fn world_section_length(&self) -> u32
+    {
+        self.bounding_box_tree.atomic_world_section_length()
+    }
+
+    /// Divides the game world into world sections of the given length, moving every entity into the new
+    /// world sections. The render data of entities is sorted again in the next frame
+    ///
+    /// `atomic_section_length` - the length of the smallest world sections
+    pub fn rebuild_world_sections(&mut self, atomic_section_length: u32)
+    {
+        let ecs = &self.logic_flow.ecs;
+        let out_of_bounds_logic = &self.logic_flow.instance_logic.out_of_bounds_logic;
+        let mut bounding_box_tree = BoundingBoxTree::new(self.bounding_box_tree.outline_length(), atomic_section_length);
+
+        for entity_id in self.bounding_box_tree.entities_index_lookup.keys()
+        {
+            let aabb = match ecs.get_ref::<StaticAABB>(*entity_id)
+            {
+                Some(i) => i,
+                None => continue
+            };
+
+            let is_static = self.bounding_box_tree.is_entity_static(*entity_id).unwrap_or(false);
+            let add_if_out_bounds = ecs.get_entity_type(*entity_id).is_some_and(|x| out_of_bounds_logic.contains_key(&x));
+
+            if bounding_box_tree.add_entity(*entity_id, aabb, add_if_out_bounds, is_static, find_entity_light_type(ecs, entity_id)).is_err()
+            {
+                error!("Failed to move entity {:?} into the rebuilt world sections", entity_id);
+            }
+        }
+
+        bounding_box_tree.end_of_changes(ecs);
+        bounding_box_tree.mark_all_static_unique_changed();
+
+        self.bounding_box_tree = bounding_box_tree;
+        *WORLD_SECTION_LENGTH.lock() = atomic_section_length;
+        self.render_flow.reset_entity_render_data();
+    }
+
+    /// Creates a snapshot of the current state of the game
+    ///
+    /// `camera` - the camera the game is rendered from
+    pub fn create_snapshot(&self, camera: &Camera) -> WorldSnapshot
+    {
+        WorldSnapshot
+        {
+            ecs: self.logic_flow.ecs.clone(),
+            resources: self.logic_flow.ecs.get_resources().clone(),
+            bounding_box_tree: self.bounding_box_tree.clone(),
+            camera: camera.clone(),
+            model_names: self.model_bank_owner.read().model_names(),
+        }
+    }
+
+    /// Replaces the state of the game with the state stored in the snapshot. The models of the entities in
+    /// the snapshot must have been uploaded, although they do not need to have the same IDs as when the
+    /// snapshot was created. The camera of the snapshot is returned
+    ///
+    /// `snapshot` - the state of the game to load
+    pub fn restore_snapshot(&mut self, mut snapshot: WorldSnapshot) -> Result<Camera, SnapshotError>
+    {
+        // Models are found by name, as the IDs of models depend on the order they were uploaded in
+        let mut model_id_lookup = HashMap::new();
+
+        for (model_name, snapshot_model_id) in &snapshot.model_names
+        {
+            if let Some(model_id) = self.model_bank_owner.read().lookup_model(model_name)
+            {
+                model_id_lookup.insert(*snapshot_model_id, *model_id);
+            }
+        }
+
+        for (entity, (model_id,)) in snapshot.ecs.query::<(&mut ModelId,)>()
+        {
+            match model_id_lookup.get(model_id)
+            {
+                Some(i) => *model_id = *i,
+                None =>
+                    {
+                        let model_name = snapshot.model_names.iter().find(|(_, x)| x == model_id).map(|(name, _)| name.clone());
+                        return Err(SnapshotError::UnknownModel(model_name.unwrap_or_else(|| format!("{:?} (entity {})", model_id, entity.get_entity_instance()))));
+                    }
+            }
+        }
+
+        let mut model_bank_owner = self.model_bank_owner.write();
+        model_bank_owner.reset_instance_counts();
+
+        for (_, (model_id,)) in snapshot.ecs.query_read::<(&ModelId,)>()
+        {
+            model_bank_owner.register_instances(*model_id, 1);
+        }
+
+        drop(model_bank_owner);
+
+        self.bounding_box_tree = snapshot.bounding_box_tree;
+        self.bounding_box_tree.mark_all_static_unique_changed();
+        *WORLD_SECTION_LENGTH.lock() = self.bounding_box_tree.atomic_world_section_length();
+
+        snapshot.ecs.set_resources(snapshot.resources);
+        self.logic_flow.replace_ecs(snapshot.ecs);
+        self.render_flow.reset_entity_render_data();
+
+        Ok(snapshot.camera)
+    }
+
+    /// Exports the entities with a model in the given world sections
+    ///
+    /// `sections` - the world sections to export
+    pub fn export_sections(&self, sections: Vec<UniqueWorldSectionId>) -> Result<SectionExport, SnapshotError>
+    {
+        SectionExport::from_world(sections, &self.logic_flow.ecs, &self.bounding_box_tree, &self.model_bank_owner.read().model_names())
+    }
+
+    /// Spawns the entities of exported world sections. The models of the entities must have been uploaded.
+    /// Either every entity is spawned or, if any entity cannot be spawned, none are. The number of spawned
+    /// entities is returned
+    ///
+    /// `section_export` - the exported world sections
+    /// `offset` - the distance each entity is moved from where it was exported
+    pub fn import_sections(&mut self, section_export: &SectionExport, offset: TVec3<f32>) -> Result<usize, SnapshotError>
+    {
+        let prefabs = section_export.to_prefabs(offset)?;
+
+        if let Some((prefab, _)) = prefabs.iter().find(|(x, _)| self.model_bank_owner.read().lookup_model(x.get_model_name()).is_none())
+        {
+            return Err(SnapshotError::UnknownModel(prefab.get_model_name().clone()));
+        }
+
+        for (prefab, transform) in &prefabs
+        {
+            self.spawn_prefab_instances(prefab, &vec![*transform]);
+        }
+
+        Ok(prefabs.len())
+    }
+
     /// Executes one iteration of the game pipeline. This means that entity logic is handled and the
     /// visible entities are rendered.
     pub fn execute(&mut self, camera: Arc<RwLock<Camera>>, delta_time: f32, input_history: &InputHistory, current_input: &CurrentFrameInput) -> Vec<FrameChange>
@@ -216,80 +553,150 @@ impl Pipeline
         let world_section_length = *WORLD_SECTION_LENGTH.lock() as f32;
 
         let camera = &mut camera.write();
+
+        let controller_args = CameraControllerArgs{ ecs: &self.logic_flow.ecs, input_history, current_input, delta_time };
+        camera.update_controller(&controller_args);
+        camera.update_effects(delta_time);
+
         let render_frustum_culler = RenderFrustumCuller::new(camera.get_projection_matrix() * camera.get_view_matrix());
         let logic_frustum_culler = LogicFrustumCuller::new(world_section_length, camera.get_position());
 
+        let culling_span = info_span!("culling").entered();
+        let culling_time_keeper = Instant::now();
+
         let mut logically_visible_world_sections =
             VisibleWorldFlow::find_visible_world_ids_entire_world(Arc::new(logic_frustum_culler.clone()), camera.get_position(), world_section_length * 2.0, &self.bounding_box_tree);
 
         let mut visible_world_sections=
             VisibleWorldFlow::find_visible_world_ids_frustum_aabb(Arc::new(render_frustum_culler.clone()), camera.get_position(), camera.get_far_draw_distance(), camera.get_direction(), &self.bounding_box_tree);
 
+        record_flow_time(FrameFlow::Culling, culling_time_keeper.elapsed());
+        drop(culling_span);
+
+        draw_world_section_overlay(&self.bounding_box_tree, &visible_world_sections, &logically_visible_world_sections);
         logically_visible_world_sections.extend(visible_world_sections);
         visible_world_sections = logically_visible_world_sections.clone();
 
-        let render_args = RenderArguments
+        // With a fixed logic timestep the frame is rendered after the logic steps, so that entities are
+        // drawn between their two most recent steps
+        let render_after_logic = fixed_logic_timestep().is_some();
+        let (number_logic_steps, logic_delta_time) = advance_logic_clock(&mut self.logic_time_accumulator, delta_time);
+
+        if !render_after_logic
         {
-            visible_world_sections,
-            bounding_box_tree: &self.bounding_box_tree,
-            ecs: &self.logic_flow.ecs,
-            camera: &*camera,
-            model_bank_owner: self.model_bank_owner.clone(),
-            input_history
-        };
-        self.render_flow.render(render_args);
+            self.render_frame(visible_world_sections.clone(), &*camera, input_history);
+        }
+
+        let mut frame_changes = Vec::new();
 
-        let execution_args = ExecutionArgs
+        for _ in 0..number_logic_steps
         {
-            visible_world_sections: CullResult::new(),
-            bounding_box_tree: &mut self.bounding_box_tree,
-            model_bank_owner: self.model_bank_owner.clone(),
-            delta_time,
-            camera: &mut *camera,
-            logic_frustum_culler: &logic_frustum_culler,
-            render_frustum_culler: &render_frustum_culler,
-            input_history,
-            current_input
-        };
-        self.logic_flow.execute_user_input(execution_args, &self.input_functions);
+            let execution_args = ExecutionArgs
+            {
+                visible_world_sections: CullResult::new(),
+                bounding_box_tree: &mut self.bounding_box_tree,
+                model_bank_owner: self.model_bank_owner.clone(),
+                delta_time: logic_delta_time,
+                camera: &mut *camera,
+                logic_frustum_culler: &logic_frustum_culler,
+                render_frustum_culler: &render_frustum_culler,
+                input_history,
+                current_input
+            };
+            let user_input_span = info_span!("user_input").entered();
+            let user_input_time_keeper = Instant::now();
+            self.logic_flow.execute_user_input(execution_args, &self.input_functions);
+            record_flow_time(FrameFlow::UserInput, user_input_time_keeper.elapsed());
+            drop(user_input_span);
+
+            let execution_args = ExecutionArgs
+            {
+                visible_world_sections: logically_visible_world_sections.clone(),
+                bounding_box_tree: &mut self.bounding_box_tree,
+                model_bank_owner: self.model_bank_owner.clone(),
+                delta_time: logic_delta_time,
+                camera: &mut *camera,
+                logic_frustum_culler: &logic_frustum_culler,
+                render_frustum_culler: &render_frustum_culler,
+                input_history,
+                current_input
+            };
+            let logic_span = info_span!("logic").entered();
+            let logic_time_keeper = Instant::now();
+            frame_changes.extend(self.logic_flow.execute_logic(execution_args, &mut self.render_flow));
+            record_flow_time(FrameFlow::Logic, logic_time_keeper.elapsed());
+            drop(logic_span);
+        }
 
-        let execution_args = ExecutionArgs
+        if render_after_logic
         {
-            visible_world_sections: logically_visible_world_sections,
-            bounding_box_tree: &mut self.bounding_box_tree,
-            model_bank_owner: self.model_bank_owner.clone(),
-            delta_time,
-            camera: &mut *camera,
-            logic_frustum_culler: &logic_frustum_culler,
-            render_frustum_culler: &render_frustum_culler,
-            input_history,
-            current_input
-        };
-        let frame_changes = self.logic_flow.execute_logic(execution_args, &mut self.render_flow);
+            self.render_frame(visible_world_sections, &*camera, input_history);
+        }
+
+        publish_frame(&self.logic_flow.ecs, camera.get_position());
 
         camera.reset_change_param();
         self.bounding_box_tree.clear_changed_static_unique();
 
-        println!("Time took: {}", instant.elapsed().as_millis());
+        trace!("Time took: {}", instant.elapsed().as_millis());
 
         frame_changes
     }
 
+    /// Renders the visible world sections from the point of view of the camera
+    ///
+    /// `visible_world_sections` - the world sections visible to the camera
+    /// `camera` - the camera to render from
+    /// `input_history` - the input the user has given in the current and previous frames
+    fn render_frame(&mut self, visible_world_sections: CullResult, camera: &Camera, input_history: &InputHistory)
+    {
+        let render_args = RenderArguments
+        {
+            visible_world_sections,
+            bounding_box_tree: &self.bounding_box_tree,
+            ecs: &self.logic_flow.ecs,
+            camera,
+            model_bank_owner: self.model_bank_owner.clone(),
+            input_history
+        };
+        let render_span = info_span!("render").entered();
+        let render_time_keeper = Instant::now();
+        self.render_flow.render(render_args);
+        record_flow_time(FrameFlow::Render, render_time_keeper.elapsed());
+        drop(render_span);
+    }
+
     /// Executes an iteration of the game by reading previous game history
     pub fn debug_execute(&mut self, custom_movement: bool, camera: Arc<RwLock<Camera>>, play: bool, execute_user_logic: bool, input_history: &InputHistory, current_input: &CurrentFrameInput, frame_time: f32) -> LastFrame
     {
         let world_section_length = *WORLD_SECTION_LENGTH.lock() as f32;
 
         let camera = &mut *camera.write();
+
+        // The recorded camera is used unless the user is moving the camera themselves
+        if custom_movement
+        {
+            let controller_args = CameraControllerArgs{ ecs: &self.logic_flow.ecs, input_history, current_input, delta_time: frame_time };
+            camera.update_controller(&controller_args);
+            camera.update_effects(frame_time);
+        }
+
         let logic_frustum_culler = LogicFrustumCuller::new(world_section_length, camera.get_position());
         let render_frustum_culler = RenderFrustumCuller::new(camera.get_projection_matrix() * camera.get_view_matrix());
 
+        let culling_span = info_span!("culling").entered();
+        let culling_time_keeper = Instant::now();
+
         let mut logically_visible_world_sections =
             VisibleWorldFlow::find_visible_world_ids_entire_world(Arc::new(logic_frustum_culler.clone()), camera.get_position(), world_section_length * 2.0, &self.bounding_box_tree);
 
         let mut visible_world_sections=
             VisibleWorldFlow::find_visible_world_ids_frustum_aabb(Arc::new(render_frustum_culler.clone()), camera.get_position(), camera.get_far_draw_distance(), camera.get_direction(), &self.bounding_box_tree);
 
+        record_flow_time(FrameFlow::Culling, culling_time_keeper.elapsed());
+        drop(culling_span);
+
+        draw_world_section_overlay(&self.bounding_box_tree, &visible_world_sections, &logically_visible_world_sections);
         logically_visible_world_sections.extend(visible_world_sections);
         visible_world_sections = logically_visible_world_sections.clone();
 
@@ -308,7 +715,11 @@ impl Pipeline
                 current_input
             };
 
+            let user_input_span = info_span!("user_input").entered();
+            let user_input_time_keeper = Instant::now();
             self.logic_flow.execute_user_input(execution_args, &self.input_functions);
+            record_flow_time(FrameFlow::UserInput, user_input_time_keeper.elapsed());
+            drop(user_input_span);
         }
 
         // Only play back history if the user has requested to do so, in order for the user to be able to
@@ -368,7 +779,11 @@ impl Pipeline
                                 current_input
                             };
 
+                            let logic_span = info_span!("logic").entered();
+                            let logic_time_keeper = Instant::now();
                             self.logic_flow.execute_logic(execution_args, &mut self.render_flow);
+                            record_flow_time(FrameFlow::Logic, logic_time_keeper.elapsed());
+                            drop(logic_span);
                         },
                     FrameChange::CameraStationary =>
                         {
@@ -385,7 +800,11 @@ impl Pipeline
                                 current_input
                             };
 
+                            let logic_span = info_span!("logic").entered();
+                            let logic_time_keeper = Instant::now();
                             self.logic_flow.execute_logic(execution_args, &mut self.render_flow);
+                            record_flow_time(FrameFlow::Logic, logic_time_keeper.elapsed());
+                            drop(logic_span);
                         }
                     FrameChange::DeltaTime(recorded_delta_time) =>
                         {
@@ -399,11 +818,15 @@ impl Pipeline
                         {
                             camera.account_window_change(dimensions);
                         },
-                    FrameChange::EndFrameChange => {}
+                    FrameChange::EndFrameChange => {},
+                    FrameChange::StateHash(recorded_hash) =>
+                        {
+                            verify_recorded_hash(&self.logic_flow.ecs, recorded_hash, self.current_frame_index);
+                        }
                 }
             }
 
-            println!("{}/{}", self.current_frame_index + 1, self.frame_indexes.len());
+            debug!("{}/{}", self.current_frame_index + 1, self.frame_indexes.len());
             self.current_frame_index += 1;
         }
 
@@ -416,7 +839,11 @@ impl Pipeline
             model_bank_owner: self.model_bank_owner.clone(),
             input_history
         };
+        let render_span = info_span!("render").entered();
+        let render_time_keeper = Instant::now();
         self.render_flow.render(render_args);
+        record_flow_time(FrameFlow::Render, render_time_keeper.elapsed());
+        drop(render_span);
         self.current_frame_index == self.frame_indexes.len() - 1
     }
 }
\ No newline at end of file
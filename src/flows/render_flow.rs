@@ -3,18 +3,45 @@ use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::mpsc::{Receiver, sync_channel, SyncSender};
+use std::time::Instant;
 use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
-use nalgebra_glm::{TMat4, TVec3, TVec4, vec4};
+use nalgebra_glm::{TMat4, TVec3, TVec4, vec3, vec4};
 use parking_lot::{Mutex, RwLock};
 use rayon::iter::ParallelIterator;
 use rayon::prelude::ParallelSlice;
-use crate::exports::camera_object::Camera;
+use tracing::{error, info_span, warn};
+use crate::culling::render_frustum_culler::RenderFrustumCuller;
+use crate::exports::ambient_overrides::blend_fog_overrides;
+use crate::exports::camera_object::{Camera, CameraViewDescription, CameraViewRequest};
+use crate::exports::damage_decals::take_changed_damage_masks;
+use crate::exports::engine_hooks::{EngineHook, run_engine_hook};
+use crate::exports::fog::{fog_settings, set_fog_settings};
+use crate::exports::frame_statistics::{CullingPass, GpuTimings, is_culling_statistics_enabled, record_culling, record_gpu_timings, record_visible_sections};
+use crate::exports::environment_lighting::{environment_lighting, take_environment_lighting_change};
+use crate::exports::light_baking::{light_baking, take_light_bake_request};
+use crate::exports::load_models::{DrawFunctionSlot, Skybox};
 use crate::exports::logic_components::RenderSystemIndex;
-use crate::exports::movement_components::TransformationMatrix;
-use crate::exports::rendering::LevelOfView;
+use crate::exports::memory_budget::{MemoryCategory, memory_budget, needs_reclaiming, record_usage};
+use crate::exports::minimap::{MinimapDescription, MinimapRequest, MinimapUpdateFrequency};
+use crate::exports::movement_components::{Position, TransformationMatrix};
+use crate::exports::rendering::{LevelOfView, post_process_stack, set_post_process_stack};
+use crate::exports::particles::ParticleEmitter;
+use crate::exports::sprites::SpriteRenderer;
+use crate::exports::debug_draw::take_debug_lines;
+use crate::exports::text_labels::TextLabel;
+#[cfg(feature = "tooling_ui")]
+use crate::exports::tooling_ui::ToolingUi;
+use crate::exports::instance_params::write_instance_params;
+use crate::exports::dissolve::write_dissolve;
+use crate::exports::transparency::Transparent;
+use crate::exports::uniform_inspector::{InspectedUniform, is_uniform_inspector_enabled, record_inspected_uniforms, uniform_overrides};
+use crate::exports::world_labels::labels_to_draw;
+use crate::exports::world_precision::{render_origin, render_space_light_matrix, render_space_position, to_render_space, update_render_origin};
+use crate::flows::light_bake_flow::load_or_bake_lighting;
 use crate::flows::shadow_flow;
 use crate::flows::shadow_flow::{CalculationArgs, ShadowFlow, ShadowMapLocation};
+use crate::flows::shared_constants::{ENTITY_SPHERE_CULLING, REQUESTED_CAMERA_VIEW_CHANGES, REQUESTED_DEBUG_RENDER_MODES, REQUESTED_DRAW_FUNCTION_REPLACEMENTS, REQUESTED_LIGHT_CONSTRAINTS, REQUESTED_MINIMAP_CHANGES, REQUESTED_RENDER_SYSTEM_TOGGLES, SUB_FRUSTUM_REFINEMENT_THRESHOLD};
 use crate::helper_things::aabb_helper_functions::distance_to_aabb;
 use crate::helper_things::cpu_usage_reducer::TimeTakeHistory;
 use crate::helper_things::environment::get_asset_folder;
@@ -22,15 +49,30 @@ use crate::models::model_definitions::{MeshGeometry, ModelId};
 use crate::models::model_storage::{ModelBank, ModelBankOwner};
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
+use crate::render_components::damage_masks::DamageMaskArray;
+use crate::render_components::dissolve_noise::DissolveNoise;
 use crate::render_components::frame_buffer::{AttachmentFormat, BindingTarget, FBO};
+use crate::render_components::gpu_level_of_view_pass::LevelOfViewGroup;
+use crate::render_components::gpu_timer::GpuTimer;
+use crate::render_components::environment_lighting::EnvironmentMaps;
+use crate::render_components::lightmap::Lightmap;
 use crate::render_components::mapped_buffer::{BufferWriteInfo, MappedBuffer};
+use crate::render_components::particle_pass::{PARTICLE_POSITION_TEXTURE_UNIT, ParticlePass, ParticleView};
+use crate::render_components::post_process_pass::PostProcessPass;
+use crate::render_components::skybox_pass::SkyboxPass;
+use crate::render_components::sprite_pass::{SPRITE_POSITION_TEXTURE_UNIT, SpriteInstance, SpritePass, SpriteView};
+use crate::render_components::debug_line_pass::{DEBUG_LINE_POSITION_TEXTURE_UNIT, DebugLinePass, DebugLineView};
+use crate::render_components::text_pass::{TEXT_POSITION_TEXTURE_UNIT, TextInstance, TextPass, TextView};
+use crate::render_components::volumetric_fog_pass::{FOG_POSITION_TEXTURE_UNIT, FOG_SHADOW_MAP_TEXTURE_UNIT, FogView, VolumetricFogPass};
+use crate::render_components::world_label_pass::{LABEL_POSITION_TEXTURE_UNIT, LabelView, WorldLabelPass};
 use crate::render_system::builder::{MaxLightConstraints, RenderSystemBuilder};
 use crate::render_system::render_system::{LevelOfViews, ModelUpdateFunction, NumberBytesChanged, RenderSystem, StartBufferChangedBytes, UploadedTextureLocation};
-use crate::render_system::system_information::{DrawFunction, DrawPreparationParameters, FragmentShaderInformation, GLSLVersion, IndiceInformation, LayoutInformation, LayoutInstance, LayoutType, LayoutUse, MagFilterOptions, MinFilterOptions, TextureFormat, TextureInformation, TextureWrap, Uniform, UniformBlock, UniformType, VertexShaderInformation};
+use crate::render_system::system_information::{DrawFunction, DrawPreparationParameters, FragmentShaderInformation, OutputFramebuffer, GLSLVersion, IndiceInformation, LayoutInformation, LayoutInstance, LayoutType, LayoutUse, MagFilterOptions, MinFilterOptions, TextureFormat, TextureInformation, TextureWrap, Uniform, UniformBlock, UniformType, VertexShaderInformation};
 use crate::{specify_model_geometry_layouts, specify_type_ids};
-use crate::flows::visible_world_flow::CullResult;
+use crate::flows::visible_world_flow::{CullResult, VisibleWorldFlow};
 use crate::window::input_state::InputHistory;
-use crate::world::bounding_box_tree_v2::{BoundingBoxTree, SharedWorldSectionId, UniqueWorldSectionId};
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, SharedWorldSectionEntities, SharedWorldSectionId, UniqueWorldSectionId};
+use crate::world::bounding_volumes::aabb::StaticAABB;
 
 lazy_static!
 {
@@ -111,6 +153,12 @@ struct WrittenInformation
 {
     number_entities: u32,
     layout_data: Vec<(u32, Vec<u8>)>,
+    // Position of every written entity, in the order they were written, so instances can be sorted by depth
+    positions: Vec<TVec3<f32>>,
+    // Radius around the position of every written entity holding its bounding volume, so instances can be
+    // culled on the GPU. Negative for entities without a bounding volume
+    bounding_radii: Vec<f32>,
+    has_transparent_entities: bool,
 }
 
 /// Stores the information needed to make the render system ready to render models after models
@@ -134,9 +182,16 @@ pub struct SortWorldSectionEntitiesParam<'a>
     bounding_box_tree: &'a BoundingBoxTree,
     unique_layout_indexes: Arc<Vec<u32>>,
     layout_update_function: fn(u32, &ECS, &mut Vec<u8>, EntityId),
+    instance_params_layout: Option<u32>,
+    dissolve_layout: Option<u32>,
+    gpu_level_of_views: bool,
     camera_position: TVec3<f32>,
     draw_distance: f32,
-    level_views: &'a LevelOfViews
+    level_views: &'a LevelOfViews,
+    frustum_culler: RenderFrustumCuller,
+    refinement_threshold: usize,
+    entity_sphere_culling: bool,
+    written_information_pool: &'a WrittenInformationPool
 }
 
 /// Variables required to sort entities in a specific world section(s)
@@ -156,6 +211,7 @@ struct AddEntitiesArgs<'a>
     local_sorted_data: &'a mut SortResult,
     distance_sphere: f32,
     sortable_index: SortableIndex,
+    cull_entities: bool,
 }
 
 /// Stores data for static entities
@@ -163,7 +219,47 @@ struct AddEntitiesArgs<'a>
 struct UniqueSectionData
 {
     world_data: HashMap<UniqueWorldSectionId, HashMap<ModelId, HashMap<SortableIndex, WrittenInformation>>>,
-    world_sections: HashSet::<UniqueWorldSectionId>
+    world_sections: HashSet::<UniqueWorldSectionId>,
+    render_origin_moved: bool,
+    // Bytes of instance data kept for each world section, and when each world section was last drawn, used
+    // to drop the data of the least recently drawn sections when over the memory budget
+    section_bytes: HashMap<UniqueWorldSectionId, usize>,
+    last_visible: HashMap<UniqueWorldSectionId, Instant>,
+    // World sections whose data was dropped, which are sorted again once they are visible
+    evicted_sections: HashSet::<UniqueWorldSectionId>,
+    // The data of the visible world sections combined the last time it was extracted, reused until the visible
+    // world sections, the models they are drawn with or the data of any world section changes
+    extracted: Option<ExtractedStaticData>,
+}
+
+/// The static entity data of the visible world sections, combined into the data to upload to a render system
+#[derive(Debug)]
+struct ExtractedStaticData
+{
+    // The visible world sections holding static entities, and the model every model of the section is drawn with
+    // if the level of views are chosen on the CPU
+    sections: Vec<(UniqueWorldSectionId, Option<HashMap<ModelId, ModelId>>)>,
+    data: Arc<SortResult>,
+}
+
+/// Keeps the instance data of previous frames once it is no longer needed, so that the vectors holding it are
+/// reused rather than allocated again every frame
+#[derive(Default)]
+struct WrittenInformationPool
+{
+    written_information: Mutex<Vec<WrittenInformation>>,
+}
+
+/// Most instance data kept by the pool; any more is freed
+const MAX_POOLED_WRITTEN_INFORMATION: usize = 4096;
+
+/// The instance data written for a single model and sortable index. The data of static entities is written right
+/// before that of active entities, so both are drawn with one draw call
+struct InstanceGroup<'a>
+{
+    model_id: ModelId,
+    sortable_index: SortableIndex,
+    parts: Vec<&'a WrittenInformation>,
 }
 
 impl UniqueSectionData
@@ -171,10 +267,170 @@ impl UniqueSectionData
     /// Create a new instance of UniqueSectionData with no static entity information
     fn new() -> UniqueSectionData
     {
-        UniqueSectionData { world_data: HashMap::default(), world_sections: HashSet::default() }
+        UniqueSectionData
+        {
+            world_data: HashMap::default(),
+            world_sections: HashSet::default(),
+            render_origin_moved: false,
+            section_bytes: HashMap::default(),
+            last_visible: HashMap::default(),
+            evicted_sections: HashSet::default(),
+            extracted: None
+        }
+    }
+}
+
+impl WrittenInformationPool
+{
+    /// Takes empty instance data holding a vector for each of the given layouts, reusing the vectors of
+    /// instance data given back to the pool if there are any
+    ///
+    /// `layout_indexes` - the indexes of the instanced layouts the data is written for
+    fn take(&self, layout_indexes: &[u32]) -> WrittenInformation
+    {
+        let mut written_information = self.take_empty(layout_indexes.len());
+
+        for ((layout_index, _), x) in written_information.layout_data.iter_mut().zip(layout_indexes.iter())
+        {
+            *layout_index = *x;
+        }
+
+        written_information
+    }
+
+    /// Copies the given instance data into vectors taken from the pool
+    ///
+    /// `source` - the instance data to copy
+    fn copy(&self, source: &WrittenInformation) -> WrittenInformation
+    {
+        let mut written_information = self.take_empty(source.layout_data.len());
+
+        for ((layout_index, data), (source_layout_index, source_data)) in written_information.layout_data.iter_mut().zip(source.layout_data.iter())
+        {
+            *layout_index = *source_layout_index;
+            data.extend_from_slice(source_data);
+        }
+
+        written_information.number_entities = source.number_entities;
+        written_information.positions.extend_from_slice(&source.positions);
+        written_information.bounding_radii.extend_from_slice(&source.bounding_radii);
+        written_information.has_transparent_entities = source.has_transparent_entities;
+        written_information
+    }
+
+    /// Gives instance data that is no longer needed back to the pool
+    ///
+    /// `written_information` - the instance data to reuse
+    fn recycle(&self, written_information: WrittenInformation)
+    {
+        let mut pool = self.written_information.lock();
+
+        if pool.len() < MAX_POOLED_WRITTEN_INFORMATION
+        {
+            pool.push(WrittenInformationPool::cleared(written_information));
+        }
+    }
+
+    /// Gives all of the given instance data back to the pool
+    ///
+    /// `sort_result` - the instance data to reuse
+    fn recycle_all(&self, sort_result: SortResult)
+    {
+        let mut pool = self.written_information.lock();
+
+        for (_, written_information) in sort_result.into_iter().flat_map(|(_, x)| x.into_iter())
+        {
+            if pool.len() >= MAX_POOLED_WRITTEN_INFORMATION
+            {
+                break;
+            }
+
+            pool.push(WrittenInformationPool::cleared(written_information));
+        }
+    }
+
+    /// Gives the given instance data back to the pool if nothing else holds it
+    ///
+    /// `sort_result` - the instance data to reuse
+    fn recycle_shared(&self, sort_result: Arc<SortResult>)
+    {
+        if let Ok(sort_result) = Arc::try_unwrap(sort_result)
+        {
+            self.recycle_all(sort_result);
+        }
+    }
+
+    /// Takes instance data without any entities from the pool, or creates it if the pool is empty
+    ///
+    /// `number_layouts` - the number of instanced layouts the data is written for
+    fn take_empty(&self, number_layouts: usize) -> WrittenInformation
+    {
+        let mut written_information = self.written_information.lock().pop().unwrap_or_else(||
+            {
+                WrittenInformation{ number_entities: 0, layout_data: Vec::new(), positions: Vec::new(), bounding_radii: Vec::new(), has_transparent_entities: false }
+            });
+
+        written_information.layout_data.resize_with(number_layouts, || (0, Vec::new()));
+        written_information
+    }
+
+    /// Removes the entities of the instance data, keeping the memory allocated for them
+    ///
+    /// `written_information` - the instance data to clear
+    fn cleared(mut written_information: WrittenInformation) -> WrittenInformation
+    {
+        for (_, data) in &mut written_information.layout_data
+        {
+            data.clear();
+        }
+
+        written_information.number_entities = 0;
+        written_information.positions.clear();
+        written_information.bounding_radii.clear();
+        written_information.has_transparent_entities = false;
+        written_information
+    }
+}
+
+/// Keeps track of when a minimap requested by the user needs to be rendered
+struct Minimap
+{
+    description: MinimapDescription,
+    dimensions: (i32, i32),
+    frames_since_update: u32,
+    update_requested: bool,
+}
+
+impl Minimap
+{
+    /// Determines if the minimap should be rendered in the current frame
+    fn is_update_due(&self) -> bool
+    {
+        match self.description.update_frequency
+        {
+            MinimapUpdateFrequency::OnDemand => self.update_requested,
+            MinimapUpdateFrequency::EveryNumberFrames(i) => self.update_requested || self.frames_since_update + 1 >= i
+        }
     }
 }
 
+/// A camera view requested by the user, along with the dimensions of the FBO it is rendered into
+struct CameraView
+{
+    description: CameraViewDescription,
+    dimensions: (i32, i32),
+}
+
+/// An FBO of a render system that the game world is rendered into, along with the render systems that render
+/// into it
+struct FboRenderTarget<'a>
+{
+    fbo_name: &'a str,
+    display_render_system: RenderSystemIndex,
+    render_systems: &'a [RenderSystemIndex],
+    dimensions: (i32, i32),
+}
+
 /// ************* Main Structure and Logic ***************
 
 /// Handles the logic of uploading the correct data to the appropriate render system
@@ -183,7 +439,11 @@ pub struct RenderFlow
     tx: SyncSender<UpdateModelInfo>,
     rx: Receiver<UpdateModelInfo>,
     render_systems: Vec<RenderSystem>,
+    disabled_render_systems: HashSet<usize>,
+    /// Draw functions replaced while the game runs, kept so that they can be given to recreated render systems
+    replaced_draw_functions: HashMap<(usize, DrawFunctionSlot), DrawFunction>,
     static_data_unique_section: Arc<RwLock<Vec<UniqueSectionData>>>,
+    written_information_pool: WrittenInformationPool,
 
     visible_direction_lights: HashSet::<EntityId>,
     visible_point_lights: HashSet::<EntityId>,
@@ -193,6 +453,26 @@ pub struct RenderFlow
     shadow_fbo: FBO,
     window_dimensions: (i32, i32),
     enable_shadow_rendering: bool,
+    minimaps: Vec<Minimap>,
+    camera_views: Vec<CameraView>,
+    render_system_cameras: HashMap<usize, String>,
+    lightmap: Lightmap,
+    environment_maps: EnvironmentMaps,
+    damage_masks: DamageMaskArray,
+    dissolve_noise: DissolveNoise,
+    volumetric_fog_pass: Option<VolumetricFogPass>,
+    world_label_pass: Option<WorldLabelPass>,
+    particle_pass: Option<ParticlePass>,
+    post_process_pass: Option<PostProcessPass>,
+    skybox: Option<Skybox>,
+    skybox_pass: Option<SkyboxPass>,
+    sprite_pass: Option<SpritePass>,
+    text_pass: Option<TextPass>,
+    debug_line_pass: Option<DebugLinePass>,
+    shadow_timer: GpuTimer,
+    post_processing_timer: GpuTimer,
+    #[cfg(feature = "tooling_ui")]
+    tooling_ui: ToolingUi,
 }
 
 impl RenderFlow
@@ -235,10 +515,14 @@ impl RenderFlow
         let shadow_fbo = FBO::new(vec![], Some(shadow_fbo_depth_texture), None, None).unwrap();
         unsafe{ gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1); }
 
-        RenderFlow{ tx, rx, render_systems, visible_direction_lights: HashSet::default(),
+        RenderFlow{ tx, rx, render_systems, disabled_render_systems: HashSet::default(), replaced_draw_functions: HashMap::default(), visible_direction_lights: HashSet::default(),
             visible_point_lights: HashSet::default(), visible_spot_lights: HashSet::default(),
-            shadow_flow: ShadowFlow::new(6), shadow_fbo, window_dimensions, enable_shadow_rendering,
-            static_data_unique_section: Arc::new(RwLock::new(static_data_unique_section)) }
+            shadow_flow: ShadowFlow::new(6), shadow_fbo, window_dimensions, enable_shadow_rendering, minimaps: Vec::new(),
+            camera_views: Vec::new(), render_system_cameras: HashMap::default(), lightmap: Lightmap::empty(), environment_maps: EnvironmentMaps::empty(), damage_masks: DamageMaskArray::new(), dissolve_noise: DissolveNoise::new(), volumetric_fog_pass: None, world_label_pass: None, particle_pass: None, post_process_pass: None, skybox: None, skybox_pass: None, sprite_pass: None, text_pass: None, debug_line_pass: None,
+            shadow_timer: GpuTimer::new(), post_processing_timer: GpuTimer::new(),
+            #[cfg(feature = "tooling_ui")]
+            tooling_ui: ToolingUi::new(),
+            static_data_unique_section: Arc::new(RwLock::new(static_data_unique_section)), written_information_pool: WrittenInformationPool::default() }
     }
 
     /// Updates render system to hold correct data for rendering and starts the drawing logic
@@ -246,6 +530,24 @@ impl RenderFlow
     /// `render_args` - structure containing the required variables for rendering
     pub fn render(&mut self, render_args: RenderArguments)
     {
+        let frame_start = Instant::now();
+
+        self.apply_requested_light_constraints();
+        self.apply_requested_render_system_toggles();
+        self.apply_requested_draw_function_replacements();
+        self.apply_requested_debug_render_modes();
+        self.apply_requested_minimap_changes();
+        self.apply_requested_camera_view_changes();
+        self.update_render_origin(render_args.camera);
+        self.release_unused_textures(&render_args.model_bank_owner);
+        self.update_baked_lighting(render_args.bounding_box_tree, render_args.ecs);
+        self.update_environment_lighting();
+        self.update_damage_masks(render_args.ecs);
+        self.prepare_post_processing();
+        self.apply_uniform_overrides();
+        record_visible_sections(render_args.visible_world_sections.visible_sections_vec.len());
+        RenderFlow::record_camera_culling(CullingPass::MainCamera, &render_args);
+
         let visible_sections_light = shadow_flow::find_nearby_world_sections_maps
             (
                 render_args.camera.get_position(),
@@ -268,6 +570,8 @@ impl RenderFlow
         {
             if let ShadowMapLocation::NewMapRequired(light_camera, light_visible_world, texture_array_index) = shadow_map_location
             {
+                let _shadows_span = info_span!("shadows").entered();
+
                 let upload_models = if render_args.model_bank_owner.write().any_models_changed_shadow_perspective()
                 {
                     render_args.model_bank_owner.write().clear_shadow_render_system_upload_flag();
@@ -298,10 +602,15 @@ impl RenderFlow
                     input_history: render_args.input_history
                 };
 
-                self.run_render_system(upload_models, self.get_shadow_render_system_index(), &render_args, &visible_sections_light);
+                RenderFlow::record_camera_culling(CullingPass::ShadowMaps, &render_args);
+                run_engine_hook(EngineHook::BeforeShadowPass, &light_camera, self.window_dimensions, None);
+                self.shadow_timer.begin();
+                self.run_render_system(upload_models, self.get_shadow_render_system_index(), &render_args, &visible_sections_light, self.window_output_framebuffer(), false);
+                self.shadow_timer.end();
+                run_engine_hook(EngineHook::AfterShadowPass, &light_camera, self.window_dimensions, None);
                 unsafe
                     {
-                        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                        gl::BindFramebuffer(gl::FRAMEBUFFER, self.window_output_framebuffer().framebuffer);
                         gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
                         gl::Viewport(0, 0, self.window_dimensions.0, self.window_dimensions.1);
                     }
@@ -310,235 +619,1506 @@ impl RenderFlow
 
         unsafe
             {
-                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.window_output_framebuffer().framebuffer);
                 gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
             }
 
         for index in 0..self.get_shadow_render_system_index()
         {
+            if self.disabled_render_systems.contains(&index)
+            {
+                self.sort_changed_static_entities(index, &render_args);
+                continue;
+            }
+
             self.render_systems[index].use_shader_program();
             self.render_systems[index].use_vao();
 
-            if render_args.model_bank_owner.read().require_reupload_models_user_render_system(index)
+            let upload_models = if render_args.model_bank_owner.read().require_reupload_models_user_render_system(index)
             {
-                let upload_models = Some(index..index + 1);
-                self.run_render_system(upload_models, index, &render_args, &visible_sections_light);
+                Some(index..index + 1)
             }
             else
             {
-                let upload_models = None;
-                self.run_render_system(upload_models, index, &render_args, &visible_sections_light);
+                None
+            };
+
+            // Render systems assigned a camera view render from the camera of that view instead of the main camera
+            let assigned_camera = self.render_system_cameras.get(&index)
+                .and_then(|x| self.camera_views.iter().find(|view| view.description.name == *x))
+                .map(|x| x.description.camera.clone());
+
+            run_engine_hook(EngineHook::BeforeRenderSystem, assigned_camera.as_ref().unwrap_or(render_args.camera), self.window_dimensions, Some(RenderSystemIndex{ index }));
+
+            match assigned_camera
+            {
+                Some(ref camera) =>
+                    {
+                        let camera_render_args = RenderFlow::create_camera_render_args(&render_args, camera);
+                        self.run_render_system(upload_models, index, &camera_render_args, &visible_sections_light, self.window_output_framebuffer(), true);
+                    },
+                None => self.run_render_system(upload_models, index, &render_args, &visible_sections_light, self.window_output_framebuffer(), true)
             }
 
+            run_engine_hook(EngineHook::AfterRenderSystem, assigned_camera.as_ref().unwrap_or(render_args.camera), self.window_dimensions, Some(RenderSystemIndex{ index }));
+
             render_args.model_bank_owner.write().clear_user_render_system_upload_flag(index);
         }
-    }
 
-    /// Updates the viewport to correspond with the new size of the rendering window
-    ///
-    /// `window_dimensions` - the resolution of the rendering window being rendered to
-    pub fn update_window_dimension(&mut self, window_dimensions: (i32, i32))
-    {
-        unsafe{ gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1); }
-        self.window_dimensions = window_dimensions;
+        self.render_skybox(&render_args);
+        self.render_sprites(&render_args);
+        self.render_particles(&render_args);
+        self.render_volumetric_fog(&render_args);
+        self.render_world_labels(&render_args);
+        self.apply_post_processing();
+        self.render_debug_lines(&render_args);
+        self.render_text_labels(&render_args);
+
+        // Models were uploaded when rendering to the window, so minimaps and camera views only need to upload instances
+        self.render_minimaps(&render_args, &visible_sections_light);
+        self.render_camera_views(&render_args, &visible_sections_light);
+        self.record_inspected_uniforms();
+        self.record_gpu_timings();
+
+        // Drawn last so that the interface is over everything else in the window
+        #[cfg(feature = "tooling_ui")]
+        self.tooling_ui.render(self.window_dimensions);
+
+        self.reclaim_static_section_memory(frame_start);
     }
 
-    /// Renders the visible scene with the provided render system
+    /// Records the memory used by the instance data of static entities, dropping the data of the world
+    /// sections drawn the longest time ago if the memory is over its budget. Sections drawn this frame are kept
     ///
-    /// `upload_models` - the indexes of render systems whose associated models should be uploaded to
-    ///                   the current render system being executed
-    /// `render_system_index` - the index of the render system to run
-    /// `render_args` - structure containing required variables for rendering
-    fn run_render_system(&mut self, upload_models: Option<Range<usize>>, render_system_index: usize, render_args: &RenderArguments, visible_sections_light: &CullResult)
+    /// `frame_start` - when the current frame started being rendered
+    fn reclaim_static_section_memory(&mut self, frame_start: Instant)
     {
-        let mut models_updated = false;
+        let mut static_data = self.static_data_unique_section.write();
+        let mut used_bytes = static_data.iter().flat_map(|x| x.section_bytes.values()).sum::<usize>();
+        record_usage(MemoryCategory::StaticSections, used_bytes);
 
-        // New model or existing model is no longer used, time to reupload models into render system
-        if let Some(model_bank_indexes) = upload_models
+        if !needs_reclaiming(MemoryCategory::StaticSections)
         {
-            // Create copies to move into new thread where models will be uploaded into render system buffers
-            let model_layout_indexes = self.render_systems[render_system_index].get_model_layout_indexes();
-            let model_layout_update_function = self.render_systems[render_system_index].get_model_layout_update_function();
-            let tx = self.tx.clone();
+            return;
+        }
 
-            let model_buffers = self.render_systems[render_system_index].get_model_mapped_buffers();
-            let indice_buffer = self.render_systems[render_system_index].get_indice_mapped_buffer();
+        let limit_bytes = memory_budget(MemoryCategory::StaticSections).map_or(usize::MAX, |x| x.limit_bytes);
 
-            let model_bank_owner = render_args.model_bank_owner.read();
-            let render_system_model_banks =
+        let mut candidates = static_data.iter()
+            .enumerate()
+            .flat_map(|(index, unique_sections)|
                 {
-                    let mut model_banks = Vec::new();
-                    for index in model_bank_indexes
-                    {
-                        model_banks.push(model_bank_owner.get_model_bank(RenderSystemIndex{ index }))
-                    }
-
-                    model_banks
-                };
-            RenderFlow::upload_models(&render_system_model_banks, model_buffers, indice_buffer, model_layout_update_function, model_layout_indexes, tx);
+                    unique_sections.section_bytes.iter()
+                        .map(move |(section, bytes)| (index, *section, *bytes, unique_sections.last_visible.get(section).copied()))
+                })
+            .filter(|(_, _, _, last_visible)| last_visible.is_none_or(|x| x < frame_start))
+            .collect::<Vec<(usize, UniqueWorldSectionId, usize, Option<Instant>)>>();
 
-            models_updated = true;
-        }
+        // Sections that were never drawn come first, as None is ordered before any time
+        candidates.sort_by_key(|(_, _, _, last_visible)| *last_visible);
 
-        // If there is no layout update functions, don't spend time going through the logic of updating
-        // mapped buffers
-        if let Some(layout_update_fn) = self.render_systems[render_system_index].get_instance_layout_update_function()
+        for (index, section, bytes, _) in candidates
         {
-            let num_unique_layouts = self.render_systems[render_system_index].get_instance_layout_indexes().len();
-
-            let sorting_param = SortWorldSectionEntitiesParam
+            if used_bytes <= limit_bytes
             {
-                visible_world_sections: &render_args.visible_world_sections,
-                ecs: render_args.ecs,
-                bounding_box_tree: render_args.bounding_box_tree,
-                unique_layout_indexes: Arc::new(self.render_systems[render_system_index].get_instance_layout_indexes()),
-                layout_update_function: layout_update_fn,
-                camera_position: render_args.camera.get_position(),
-                draw_distance: render_args.camera.get_far_draw_distance(),
-                level_views: &self.render_systems[render_system_index].level_of_views
-            };
+                break;
+            }
 
-            let static_data = RenderFlow::extract_static_data(&sorting_param, self.static_data_unique_section.clone(), render_system_index);
-            let sorted_data = RenderFlow::sort_world_section_active_entities(sorting_param);
+            let unique_sections = &mut static_data[index];
+            unique_sections.section_bytes.remove(&section);
+            unique_sections.last_visible.remove(&section);
+            unique_sections.evicted_sections.insert(section);
+            used_bytes -= bytes;
 
+            if let Some(world_data) = unique_sections.world_data.remove(&section)
             {
-                let mut sorted_data = sorted_data.lock();
-                let static_data = static_data.lock();
-                RenderFlow::append_written_information(&mut sorted_data, &static_data, None, num_unique_layouts);
+                self.written_information_pool.recycle_all(world_data);
             }
 
-            RenderFlow::upload_instance_data_to_render_system(&mut self.render_systems[render_system_index], &sorted_data.lock());
+            if let Some(extracted) = unique_sections.extracted.take()
+            {
+                self.written_information_pool.recycle_shared(extracted.data);
+            }
         }
 
-        if models_updated
+        record_usage(MemoryCategory::StaticSections, used_bytes);
+    }
+
+    /// Gives the render systems the uniform values set with the uniform inspector, which replace the values
+    /// written by their draw functions
+    fn apply_uniform_overrides(&mut self)
+    {
+        for index in 0..self.get_shadow_render_system_index()
         {
-            if let Ok(updated_model_info) = self.rx.recv()
+            self.render_systems[index].set_uniform_overrides(uniform_overrides(RenderSystemIndex{ index }));
+        }
+    }
+
+    /// Lists the uniforms of the render systems for the uniform inspector, if it is enabled
+    fn record_inspected_uniforms(&self)
+    {
+        if !is_uniform_inspector_enabled()
+        {
+            return;
+        }
+
+        let mut inspected_uniforms = Vec::new();
+
+        for index in 0..self.get_shadow_render_system_index()
+        {
+            let render_system = &self.render_systems[index];
+
+            inspected_uniforms.extend(render_system.inspect_uniforms().into_iter().map(|(second_pass, name, value)| InspectedUniform
             {
-                self.render_systems[render_system_index].flush_per_model_buffers(updated_model_info.flush_info);
-                self.render_systems[render_system_index].flush_indice_buffer(updated_model_info.indice_flush_info);
+                render_system: RenderSystemIndex{ index },
+                second_pass,
+                overridden: render_system.is_uniform_overridden(&name),
+                name,
+                value,
+            }));
+        }
 
-                // Have to synchronize the rendering information. The rendering information received
-                // from the thread that uploaded the model data contains offsets for the model geometry,
-                // while the rendering system rendering info has the correct instance range information
+        record_inspected_uniforms(inspected_uniforms);
+    }
 
-                self.render_systems[render_system_index].update_model_rendering_info(updated_model_info.updated_rendering_info);
+    /// Draws the sprites of entities over the frame rendered into the window, hidden behind the geometry of
+    /// the first enabled render system drawing to the window from the main camera
+    ///
+    /// `render_args` - structure containing the camera the frame was rendered from and the entities with sprites
+    fn render_sprites(&mut self, render_args: &RenderArguments)
+    {
+        let sprites = render_args.ecs.query_read::<(&SpriteRenderer, )>()
+            .filter_map(|(entity_id, (sprite, ))|
+                {
+                    render_space_position(render_args.ecs, entity_id).map(|position| SpriteInstance
+                    {
+                        sprite: *sprite,
+                        position,
+                        transformation: render_args.ecs.get_copy::<TransformationMatrix>(entity_id).map(|x| x.get_matrix())
+                    })
+                })
+            .collect::<Vec<SpriteInstance>>();
+
+        if sprites.is_empty()
+        {
+            return;
+        }
+
+        if self.sprite_pass.is_none()
+        {
+            match SpritePass::new()
+            {
+                Ok(i) => self.sprite_pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the sprite pass: {}", err);
+                        return;
+                    }
             }
         }
 
-        let matrices = self.shadow_flow.upload_matrices.iter().map(|x| *x).collect::<Vec<TMat4<f32>>>();
-        let indexes = self.shadow_flow.upload_indexes.iter().map(|x| *x).collect::<Vec<u32>>();
-        let view_matrices = self.shadow_flow.upload_view_matrices.iter().map(|x| *x).collect::<Vec<TMat4<f32>>>();
+        let view = SpriteView
+        {
+            camera_position: render_args.camera.get_render_position(),
+            view_matrix: render_args.camera.get_render_view_matrix(),
+            projection_matrix: render_args.camera.get_projection_matrix(),
+            positions_available: self.bind_window_positions(SPRITE_POSITION_TEXTURE_UNIT)
+        };
 
-        let draw_param = DrawPreparationParameters
+        let output_framebuffer = self.window_output_framebuffer();
+        self.sprite_pass.as_mut().unwrap().apply(&sprites, &view, output_framebuffer);
+    }
+
+    /// Draws the lines queued for debugging since the last frame into the window after post-processing, so that
+    /// they keep the colour they were given. Depth tested lines are hidden behind the geometry of the first enabled
+    /// render system drawing to the window from the main camera
+    ///
+    /// `render_args` - structure containing the camera the frame was rendered from
+    fn render_debug_lines(&mut self, render_args: &RenderArguments)
+    {
+        let lines = take_debug_lines();
+
+        if lines.is_empty()
         {
-            visible_sections_light: &visible_sections_light.visible_sections_map,
-            shadow_fbo: &mut self.shadow_fbo,
-            logical_entity_lookup: &HashMap::new(), // Deal with this later; have to be set in logical flow
-            logical_ecs: &render_args.ecs,
-            camera: render_args.camera,
-            input_history: render_args.input_history,
-            tree: render_args.bounding_box_tree,
+            return;
+        }
 
-            visible_directional_lights: &mut self.visible_direction_lights,
-            visible_point_lights: &mut self.visible_point_lights,
-            visible_spot_lights: &mut self.visible_spot_lights,
-            upload_matrices: &matrices,
-            upload_indexes: &indexes,
-            upload_view_matrices: &view_matrices
+        if self.debug_line_pass.is_none()
+        {
+            match DebugLinePass::new()
+            {
+                Ok(i) => self.debug_line_pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the debug line pass: {}", err);
+                        return;
+                    }
+            }
+        }
+
+        let view = DebugLineView
+        {
+            camera_position: render_args.camera.get_render_position(),
+            view_matrix: render_args.camera.get_render_view_matrix(),
+            projection_matrix: render_args.camera.get_projection_matrix(),
+            positions_available: self.bind_window_positions(DEBUG_LINE_POSITION_TEXTURE_UNIT)
         };
 
-        self.render_systems[render_system_index].draw(draw_param);
+        let output_framebuffer = OutputFramebuffer{ framebuffer: 0, dimensions: self.window_dimensions };
+        self.debug_line_pass.as_mut().unwrap().apply(&lines, &view, output_framebuffer);
     }
 
-    /// Accumulates all static entity rendering data into one data structure to be uploaded into vRAM
+    /// Draws the text labels of entities into the window after post-processing, so that the effects do not change
+    /// the text. Labels in the world are hidden behind the geometry of the first enabled render system drawing
+    /// to the window from the main camera
     ///
-    /// `sorting_param` - variables required to sort entity rendering data
-    /// `static_data` - structure holding static entity data
-    /// `render_system_index` - index of the render system static data is being uploaded to
-    fn extract_static_data(sorting_param: &SortWorldSectionEntitiesParam, static_data: Arc<RwLock<Vec<UniqueSectionData>>>, render_system_index: usize) -> Arc<Mutex<SortResult>>
+    /// `render_args` - structure containing the camera the frame was rendered from and the entities with labels
+    fn render_text_labels(&mut self, render_args: &RenderArguments)
     {
-        // If static entities changed in any of the visible world sections, then that data must be reloaded
-        RenderFlow::sort_world_section_static_entities(sorting_param, &mut static_data.write()[render_system_index]);
+        let labels = render_args.ecs.query_read::<(&TextLabel, )>()
+            .map(|(entity_id, (label, ))| TextInstance{ label: label.clone(), position: render_space_position(render_args.ecs, entity_id) })
+            .collect::<Vec<TextInstance>>();
 
-        let aggregated_sorted_data: Arc<Mutex<SortResult>> = Arc::new(Mutex::new(HashMap::default()));
-        let static_data_clone = static_data.clone();
-        let num_unique_layouts = sorting_param.unique_layout_indexes.len();
+        if labels.is_empty()
+        {
+            return;
+        }
 
-        let extract_fn = |chunks: &[UniqueWorldSectionId]|
+        if self.text_pass.is_none()
+        {
+            match TextPass::new()
             {
-                let mut local_static_data: SortResult = HashMap::default();
-
-                for world_section in chunks
-                {
-                    if let Some(write_info) = static_data_clone.read()[render_system_index].world_data.get(world_section)
+                Ok(i) => self.text_pass = Some(i),
+                Err(err) =>
                     {
-                        let distance_from_aabb = if let Some(unique_section) = sorting_param.bounding_box_tree.stored_entities_indexes.get(world_section)
-                        {
-                            distance_to_aabb(&unique_section.aabb, sorting_param.camera_position)
-                        }
-                        else
-                        {
-                            // This branch indicates there are static entities in a world section, but
-                            // that world section does not exist
-                            eprintln!("Failed to find world section: {:?}", *world_section);
+                        error!("Failed to create the text pass: {}", err);
+                        return;
+                    }
+            }
+        }
 
-                            debug_assert!(false);
-                            0.0
-                        };
+        let view = TextView
+        {
+            camera_position: render_args.camera.get_render_position(),
+            view_matrix: render_args.camera.get_render_view_matrix(),
+            projection_matrix: render_args.camera.get_projection_matrix(),
+            positions_available: self.bind_window_positions(TEXT_POSITION_TEXTURE_UNIT)
+        };
 
-                        if distance_from_aabb > sorting_param.draw_distance
-                        {
-                            continue;
-                        }
+        // Post-processing has finished, so the labels are drawn into the window itself
+        let output_framebuffer = OutputFramebuffer{ framebuffer: 0, dimensions: self.window_dimensions };
+        self.text_pass.as_mut().unwrap().apply(&labels, &view, output_framebuffer);
+    }
+
+    /// Simulates the particles of every particle emitter and draws them over the frame rendered into the
+    /// window, hidden behind the geometry of the first enabled render system drawing to the window from the
+    /// main camera
+    ///
+    /// `render_args` - structure containing the camera the frame was rendered from and the emitting entities
+    fn render_particles(&mut self, render_args: &RenderArguments)
+    {
+        let emitters = render_args.ecs.query_read::<(&ParticleEmitter, )>()
+            .filter_map(|(entity_id, (emitter, ))| render_space_position(render_args.ecs, entity_id).map(|x| (entity_id, x, *emitter)))
+            .collect::<Vec<(EntityId, TVec3<f32>, ParticleEmitter)>>();
 
-                        let mut translated_model_ids = HashMap::default();
+        // Once created, the pass keeps running without emitters so that the particles of removed emitters are killed
+        if emitters.is_empty() && self.particle_pass.is_none()
+        {
+            return;
+        }
+
+        if self.particle_pass.is_none()
+        {
+            match ParticlePass::new()
+            {
+                Ok(i) => self.particle_pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the particle pass: {}", err);
+                        return;
+                    }
+            }
+        }
+
+        let view = ParticleView
+        {
+            camera_position: render_args.camera.get_render_position(),
+            view_matrix: render_args.camera.get_render_view_matrix(),
+            projection_matrix: render_args.camera.get_projection_matrix(),
+            positions_available: self.bind_window_positions(PARTICLE_POSITION_TEXTURE_UNIT)
+        };
+
+        let output_framebuffer = self.window_output_framebuffer();
+        self.particle_pass.as_mut().unwrap().apply(&emitters, &view, output_framebuffer);
+    }
+
+    /// Sets the sky drawn behind the frame rendered into the window. The images of the sky are loaded the
+    /// next time a frame is rendered
+    ///
+    /// `skybox` - the images to create the sky from, or None to stop drawing a sky
+    pub fn set_skybox(&mut self, skybox: Option<Skybox>)
+    {
+        self.skybox = skybox;
+        self.skybox_pass = None;
+    }
+
+    /// Get the sky drawn behind the frame rendered into the window
+    pub fn skybox(&self) -> Option<Skybox>
+    {
+        self.skybox.clone()
+    }
+
+    /// Draws the sky behind the frame rendered into the window, if a skybox is set. It is drawn before the
+    /// effects drawn over the frame, such as particles and fog, so that they are drawn over the sky. The
+    /// depth of deferred render systems is copied from the first enabled render system drawing to the window
+    /// from the main camera
+    ///
+    /// `render_args` - structure containing the camera the frame was rendered from
+    fn render_skybox(&mut self, render_args: &RenderArguments)
+    {
+        let skybox = match self.skybox
+        {
+            Some(ref i) => i,
+            None => return
+        };
+
+        if self.skybox_pass.is_none()
+        {
+            match SkyboxPass::new(skybox)
+            {
+                Ok(i) => self.skybox_pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the skybox; no sky is drawn: {}", err);
+                        self.skybox = None;
+                        return;
+                    }
+            }
+        }
+
+        // Forward render systems write their depth into the output, while deferred render systems only write
+        // it into their G-Buffer
+        let depth_source = (0..self.get_shadow_render_system_index())
+            .find(|x| !self.disabled_render_systems.contains(x) && !self.render_system_cameras.contains_key(x));
+
+        let output_framebuffer = self.window_output_framebuffer();
+
+        if let Some(index) = depth_source
+        {
+            self.render_systems[index].blit_g_buffer_depth(output_framebuffer);
+        }
+
+        let view_matrix = render_args.camera.get_render_view_matrix();
+        let projection_matrix = render_args.camera.get_projection_matrix();
+        self.skybox_pass.as_mut().unwrap().apply(&view_matrix, &projection_matrix, output_framebuffer);
+    }
+
+    /// Draws the volumetric fog over the frame rendered into the window, if fog is enabled. The world
+    /// positions are read from the first enabled render system drawing to the window from the main camera
+    ///
+    /// `render_args` - structure containing the camera the frame was rendered from
+    fn render_volumetric_fog(&mut self, render_args: &RenderArguments)
+    {
+        // The camera may be within a region of the world with its own fog
+        let settings = match blend_fog_overrides(render_args.camera.get_position(), fog_settings())
+        {
+            Some(i) => i,
+            None =>
+                {
+                    self.volumetric_fog_pass = None;
+                    return;
+                }
+        };
+
+        if self.volumetric_fog_pass.is_none()
+        {
+            match VolumetricFogPass::new()
+            {
+                Ok(i) => self.volumetric_fog_pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the volumetric fog pass; fog is disabled: {}", err);
+                        set_fog_settings(None);
+                        return;
+                    }
+            }
+        }
+
+        if !self.bind_window_positions(FOG_POSITION_TEXTURE_UNIT)
+        {
+            return;
+        }
+
+        self.shadow_fbo.bind_depth_texture_to_specific_texture_unit(FOG_SHADOW_MAP_TEXTURE_UNIT);
+
+        let light_matrices = self.shadow_flow.upload_matrices.iter().map(render_space_light_matrix).collect::<Vec<TMat4<f32>>>();
+        let light_layers = self.shadow_flow.upload_indexes.iter().copied().collect::<Vec<u32>>();
+
+        let view = FogView
+        {
+            camera_position: render_args.camera.get_render_position(),
+            view_projection: render_args.camera.get_projection_matrix() * render_args.camera.get_render_view_matrix(),
+            light_matrices: &light_matrices,
+            light_layers: &light_layers
+        };
+
+        let output_framebuffer = self.window_output_framebuffer();
+        self.volumetric_fog_pass.as_mut().unwrap().apply(&settings, &view, output_framebuffer);
+    }
+
+    /// Draws the labels of entities over the frame rendered into the window, hidden behind the geometry
+    /// of the first enabled render system drawing to the window from the main camera
+    ///
+    /// `render_args` - structure containing the camera the frame was rendered from and the labelled entities
+    fn render_world_labels(&mut self, render_args: &RenderArguments)
+    {
+        let mut labels = labels_to_draw(render_args.ecs);
+
+        if labels.is_empty()
+        {
+            return;
+        }
+
+        if self.world_label_pass.is_none()
+        {
+            match WorldLabelPass::new()
+            {
+                Ok(i) => self.world_label_pass = Some(i),
+                Err(err) =>
+                    {
+                        error!("Failed to create the world label pass: {}", err);
+                        return;
+                    }
+            }
+        }
+
+        // Labels are hidden behind the positions of the rendered frame, which are relative to the render origin
+        let render_offset = to_render_space(vec3(0.0, 0.0, 0.0));
+        for (aabb, _) in &mut labels
+        {
+            aabb.translate(render_offset);
+        }
+
+        let view = LabelView
+        {
+            camera_position: render_args.camera.get_render_position(),
+            view_matrix: render_args.camera.get_render_view_matrix(),
+            projection_matrix: render_args.camera.get_projection_matrix(),
+            positions_available: self.bind_window_positions(LABEL_POSITION_TEXTURE_UNIT)
+        };
+
+        let output_framebuffer = self.window_output_framebuffer();
+        self.world_label_pass.as_mut().unwrap().apply(&labels, &view, output_framebuffer);
+    }
+
+    /// Binds the world positions of the frame rendered into the window to the given texture unit. The
+    /// positions are the first G-Buffer layout of the first enabled render system drawing to the window from
+    /// the main camera. Returns false if there is no such render system
+    ///
+    /// `texture_unit` - the texture unit to bind the world positions to
+    fn bind_window_positions(&mut self, texture_unit: u32) -> bool
+    {
+        let position_source = (0..self.get_shadow_render_system_index())
+            .find(|x| !self.disabled_render_systems.contains(x) && !self.render_system_cameras.contains_key(x));
+
+        match position_source
+        {
+            Some(index) => self.render_systems[index].bind_g_buffer_layout(0, texture_unit),
+            None => false
+        }
+    }
+
+    /// Get the output of render systems that render to the window. While post-processing is enabled, this
+    /// is the frame buffer the effects are applied to rather than the window
+    fn window_output_framebuffer(&self) -> OutputFramebuffer
+    {
+        let framebuffer = self.post_process_pass.as_ref().map_or(0, |x| x.scene_framebuffer());
+        OutputFramebuffer{ framebuffer, dimensions: self.window_dimensions }
+    }
+
+    /// Creates or removes the post-process pass depending on if a post-process stack is set, so that the
+    /// frame is rendered into the frame buffer the effects are applied to only when there are effects
+    fn prepare_post_processing(&mut self)
+    {
+        let scene_format = match post_process_stack()
+        {
+            Some(stack) => stack.scene_format(),
+            None =>
+                {
+                    self.post_process_pass = None;
+                    return;
+                }
+        };
+
+        let result = match self.post_process_pass.as_mut()
+        {
+            Some(post_process_pass) => post_process_pass.resize(self.window_dimensions, scene_format),
+            None => PostProcessPass::new(self.window_dimensions, scene_format).map(|x| self.post_process_pass = Some(x))
+        };
+
+        if let Err(err) = result
+        {
+            error!("Failed to create the post-process frame buffers; the frame is rendered without post-processing: {}", err);
+            set_post_process_stack(None);
+            self.post_process_pass = None;
+        }
+    }
+
+    /// Applies the post-process stack to the frame, drawing the result into the window. The window is then
+    /// bound, so that anything drawn afterwards, such as the overlay, is drawn over the processed frame
+    fn apply_post_processing(&mut self)
+    {
+        let (post_process_pass, stack) = match (self.post_process_pass.as_mut(), post_process_stack())
+        {
+            (Some(i), Some(stack)) => (i, stack),
+            _ => return
+        };
+
+        self.post_processing_timer.begin();
+        post_process_pass.apply(stack.effects());
+        self.post_processing_timer.end();
+        self.restore_window_framebuffer();
+    }
+
+    /// Reads the GPU times of the passes rendered a few frames ago and stores them in the frame statistics
+    fn record_gpu_timings(&mut self)
+    {
+        self.shadow_timer.end_frame();
+        self.post_processing_timer.end_frame();
+
+        let shadow_render_system_index = self.get_shadow_render_system_index();
+        let mut timings = GpuTimings{ shadow_maps: self.shadow_timer.latest_time(), post_processing: self.post_processing_timer.latest_time(), ..GpuTimings::default() };
+        let mut render_system_timings = Vec::new();
+
+        for (index, render_system) in self.render_systems.iter_mut().enumerate()
+        {
+            let render_system_timing = render_system.end_gpu_timer_frame();
+
+            // The shadow render system is measured as a whole by the shadow timer
+            if index == shadow_render_system_index
+            {
+                continue;
+            }
+
+            timings.first_passes += render_system_timing.first_pass;
+            timings.second_passes += render_system_timing.second_pass;
+            render_system_timings.push((RenderSystemIndex{ index }, render_system_timing));
+        }
+
+        record_gpu_timings(timings, render_system_timings);
+    }
+
+    /// Applies any changes to minimaps that were requested since the last frame
+    fn apply_requested_minimap_changes(&mut self)
+    {
+        let shadow_render_system_index = self.get_shadow_render_system_index();
+
+        for request in REQUESTED_MINIMAP_CHANGES.lock().drain(..)
+        {
+            match request
+            {
+                MinimapRequest::Add(description) =>
+                    {
+                        if description.display_render_system.index >= shadow_render_system_index
+                        {
+                            warn!("Unable to display minimap {} in non-existent render system: {}", description.name, description.display_render_system.index);
+                            continue;
+                        }
+
+                        self.remove_minimap(&description.name);
+
+                        let fbo = match RenderFlow::create_minimap_fbo(self.window_dimensions)
+                        {
+                            Ok(i) => i,
+                            Err(err) =>
+                                {
+                                    warn!("Unable to create FBO for minimap {}: {}", description.name, err);
+                                    continue;
+                                }
+                        };
+
+                        self.render_systems[description.display_render_system.index].add_accessible_fbo(description.name.clone(), fbo);
+                        self.minimaps.push(Minimap{ description, dimensions: self.window_dimensions, frames_since_update: 0, update_requested: true });
+                    },
+                MinimapRequest::Remove(name) => self.remove_minimap(&name),
+                MinimapRequest::Update(name) =>
+                    {
+                        match self.minimaps.iter_mut().find(|x| x.description.name == name)
+                        {
+                            Some(i) => i.update_requested = true,
+                            None => warn!("Unable to update non-existent minimap: {}", name)
+                        }
+                    }
+            }
+        }
+    }
+
+    /// Removes the minimap with the given name, if it exists, and deletes its FBO
+    ///
+    /// `name` - the name of the minimap to remove
+    fn remove_minimap(&mut self, name: &str)
+    {
+        if let Some(index) = self.minimaps.iter().position(|x| x.description.name == name)
+        {
+            let minimap = self.minimaps.remove(index);
+            self.render_systems[minimap.description.display_render_system.index].take_accessible_fbo(name);
+        }
+    }
+
+    /// Creates the FBO that a minimap is rendered into. The FBO is the same size as the G-Buffers of the
+    /// render systems, as they are written to the FBO in their entirety
+    ///
+    /// `dimensions` - the dimensions of the FBO
+    fn create_minimap_fbo(dimensions: (i32, i32)) -> Result<FBO, String>
+    {
+        let attachment_information = |sampler_name: &str, format: TextureFormat|
+            {
+                TextureInformation
+                {
+                    sampler_name: sampler_name.to_string(),
+                    number_mipmaps: 1,
+                    format,
+                    min_filter_options: MinFilterOptions::Linear,
+                    mag_filter_options: MagFilterOptions::Linear,
+                    wrap_s: TextureWrap::ClampToEdge,
+                    wrap_t: TextureWrap::ClampToEdge,
+                    width: dimensions.0,
+                    height: dimensions.1,
+                    number_textures: 1,
+                    border_color: None
+                }
+            };
+
+        FBO::new(vec![attachment_information("minimapTexture", TextureFormat::RGBA)], None, None,
+                 Some(attachment_information("minimapDepthStencil", TextureFormat::DepthStencil)))
+    }
+
+    /// Renders the minimaps that are due to be updated into their FBOs
+    ///
+    /// `render_args` - structure containing the required variables for rendering
+    /// `visible_sections_light` - the world sections that are near enough to the camera for lights to be uploaded
+    fn render_minimaps(&mut self, render_args: &RenderArguments, visible_sections_light: &CullResult)
+    {
+        let mut any_minimap_rendered = false;
+
+        for minimap_index in 0..self.minimaps.len()
+        {
+            if !self.minimaps[minimap_index].is_update_due()
+            {
+                self.minimaps[minimap_index].frames_since_update += 1;
+                continue;
+            }
+
+            let description = self.minimaps[minimap_index].description.clone();
+            let dimensions = self.minimaps[minimap_index].dimensions;
+            let minimap_camera = description.create_camera(dimensions);
+
+            let target = FboRenderTarget{ fbo_name: &description.name, display_render_system: description.display_render_system,
+                                          render_systems: &description.render_systems, dimensions };
+            self.render_into_fbo(target, &minimap_camera, render_args, visible_sections_light, CullingPass::Minimap(description.name.clone()));
+
+            self.minimaps[minimap_index].frames_since_update = 0;
+            self.minimaps[minimap_index].update_requested = false;
+            any_minimap_rendered = true;
+        }
+
+        if any_minimap_rendered
+        {
+            self.restore_window_framebuffer();
+        }
+    }
+
+    /// Renders every camera view into its FBO
+    ///
+    /// `render_args` - structure containing the required variables for rendering
+    /// `visible_sections_light` - the world sections that are near enough to the camera for lights to be uploaded
+    fn render_camera_views(&mut self, render_args: &RenderArguments, visible_sections_light: &CullResult)
+    {
+        if self.camera_views.is_empty()
+        {
+            return;
+        }
+
+        for view_index in 0..self.camera_views.len()
+        {
+            let description = self.camera_views[view_index].description.clone();
+            let dimensions = self.camera_views[view_index].dimensions;
+
+            let target = FboRenderTarget{ fbo_name: &description.name, display_render_system: description.display_render_system,
+                                          render_systems: &description.render_systems, dimensions };
+            self.render_into_fbo(target, &description.camera, render_args, visible_sections_light,
+                                 CullingPass::CameraView(description.name.clone()));
+        }
+
+        self.restore_window_framebuffer();
+    }
+
+    /// Renders the game world from the perspective of the given camera into the FBO with the given name that
+    /// is accessible to the draw functions of the display render system
+    ///
+    /// `target` - the FBO to render into and the render systems that render into it
+    /// `camera` - the camera to render from
+    /// `render_args` - structure containing the required variables for rendering
+    /// `visible_sections_light` - the world sections that are near enough to the camera for lights to be uploaded
+    /// `culling_pass` - what the culling statistics of the camera are recorded as
+    fn render_into_fbo(&mut self, target: FboRenderTarget, camera: &Camera, render_args: &RenderArguments, visible_sections_light: &CullResult,
+                       culling_pass: CullingPass)
+    {
+        let FboRenderTarget{ fbo_name, display_render_system, render_systems, dimensions } = target;
+        let shadow_render_system_index = self.get_shadow_render_system_index();
+
+        // The FBO is taken out of the display render system so that it can be rendered into while
+        // the render systems are borrowed
+        let mut fbo = match self.render_systems[display_render_system.index].take_accessible_fbo(fbo_name)
+        {
+            Some(i) => i,
+            None =>
+                {
+                    error!("Failed to find the FBO: {}", fbo_name);
+                    debug_assert!(false);
+                    return;
+                }
+        };
+
+        let camera_render_args = RenderFlow::create_camera_render_args(render_args, camera);
+        let output_framebuffer = OutputFramebuffer{ framebuffer: fbo.get_raw_resource(), dimensions };
+        RenderFlow::record_camera_culling(culling_pass, &camera_render_args);
+
+        fbo.bind_fbo(BindingTarget::DrawFrameBuffer);
+        unsafe
+            {
+                gl::Viewport(0, 0, dimensions.0, dimensions.1);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+            }
+
+        for render_system_index in render_systems
+        {
+            if render_system_index.index >= shadow_render_system_index
+            {
+                warn!("Unable to render non-existent render system {} into FBO {}", render_system_index.index, fbo_name);
+                continue;
+            }
+
+            if self.disabled_render_systems.contains(&render_system_index.index)
+            {
+                continue;
+            }
+
+            // Render systems without a second pass draw directly to whatever frame buffer is bound
+            fbo.bind_fbo(BindingTarget::DrawFrameBuffer);
+
+            self.render_systems[render_system_index.index].use_shader_program();
+            self.render_systems[render_system_index.index].use_vao();
+            self.run_render_system(None, render_system_index.index, &camera_render_args, visible_sections_light, output_framebuffer, false);
+        }
+
+        self.render_systems[display_render_system.index].add_accessible_fbo(fbo_name.to_string(), fbo);
+    }
+
+    /// Binds the window as the frame buffer being rendered to after rendering into FBOs
+    fn restore_window_framebuffer(&self)
+    {
+        unsafe
+            {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, self.window_dimensions.0, self.window_dimensions.1);
+            }
+    }
+
+    /// Creates the arguments to render the game world from the perspective of the given camera, with the
+    /// world sections visible to that camera
+    ///
+    /// `render_args` - the arguments used to render from the main camera
+    /// `camera` - the camera to render from
+    fn create_camera_render_args<'b>(render_args: &RenderArguments<'b>, camera: &'b Camera) -> RenderArguments<'b>
+    {
+        let render_frustum_culler = RenderFrustumCuller::new(camera.get_projection_matrix() * camera.get_view_matrix());
+        let visible_world_sections = VisibleWorldFlow::find_visible_world_ids_frustum_aabb(Arc::new(render_frustum_culler), camera.get_position(),
+                                                                                           camera.get_far_draw_distance(), camera.get_direction(), render_args.bounding_box_tree);
+
+        RenderArguments
+        {
+            visible_world_sections,
+            bounding_box_tree: render_args.bounding_box_tree,
+            ecs: render_args.ecs,
+            camera,
+            model_bank_owner: render_args.model_bank_owner.clone(),
+            input_history: render_args.input_history
+        }
+    }
+
+    /// Records how the world was culled for the camera of the render arguments, if culling statistics are enabled
+    ///
+    /// `culling_pass` - what the culling statistics of the camera are recorded as
+    /// `render_args` - structure containing the camera and the world sections visible to it
+    fn record_camera_culling(culling_pass: CullingPass, render_args: &RenderArguments)
+    {
+        if !is_culling_statistics_enabled()
+        {
+            return;
+        }
+
+        let camera = render_args.camera;
+        let frustum_culler = RenderFrustumCuller::new(camera.get_projection_matrix() * camera.get_view_matrix());
+        let statistics = VisibleWorldFlow::culling_statistics(&render_args.visible_world_sections, render_args.bounding_box_tree, &frustum_culler,
+                                                              camera.get_position(), camera.get_far_draw_distance(), *SUB_FRUSTUM_REFINEMENT_THRESHOLD.lock());
+
+        record_culling(culling_pass, statistics);
+    }
+
+    /// Moves the render origin if the camera moved too far from it. Everything uploaded relative to the
+    /// render origin that is not rewritten every frame is updated when it moves
+    ///
+    /// `camera` - the camera the frame is rendered from
+    fn update_render_origin(&mut self, camera: &Camera)
+    {
+        if !update_render_origin(camera.get_position())
+        {
+            return;
+        }
+
+        for unique_sections in self.static_data_unique_section.write().iter_mut()
+        {
+            unique_sections.render_origin_moved = true;
+        }
+
+        self.lightmap.set_render_origin(render_origin());
+    }
+
+    /// Applies any changes to camera views that were requested since the last frame
+    fn apply_requested_camera_view_changes(&mut self)
+    {
+        for request in REQUESTED_CAMERA_VIEW_CHANGES.lock().drain(..)
+        {
+            match request
+            {
+                CameraViewRequest::Add(description) =>
+                    {
+                        self.add_camera_view(description);
+                    },
+                CameraViewRequest::Remove(name) =>
+                    {
+                        self.remove_camera_view(&name);
+                        self.render_system_cameras.retain(|_, x| *x != name);
+                    },
+                CameraViewRequest::UpdateCamera(name, camera) =>
+                    {
+                        match self.camera_views.iter_mut().find(|x| x.description.name == name)
+                        {
+                            Some(i) => i.description.camera = camera,
+                            None => warn!("Unable to update non-existent camera view: {}", name)
+                        }
+                    },
+                CameraViewRequest::AssignRenderSystem(render_system_index, camera_view) =>
+                    {
+                        self.assign_camera_view(render_system_index, camera_view);
+                    }
+            }
+        }
+    }
+
+    /// Adds a camera view, creating the FBO it is rendered into. A camera view with the same name is replaced
+    ///
+    /// `description` - the description of the camera view to add
+    pub fn add_camera_view(&mut self, description: CameraViewDescription)
+    {
+        if description.display_render_system.index >= self.get_shadow_render_system_index()
+        {
+            warn!("Unable to display camera view {} in non-existent render system: {}", description.name, description.display_render_system.index);
+            return;
+        }
+
+        self.remove_camera_view(&description.name);
+
+        // Camera views are rendered into FBOs of the same kind as minimaps
+        let fbo = match RenderFlow::create_minimap_fbo(self.window_dimensions)
+        {
+            Ok(i) => i,
+            Err(err) =>
+                {
+                    warn!("Unable to create FBO for camera view {}: {}", description.name, err);
+                    return;
+                }
+        };
+
+        self.render_systems[description.display_render_system.index].add_accessible_fbo(description.name.clone(), fbo);
+        self.camera_views.push(CameraView{ description, dimensions: self.window_dimensions });
+    }
+
+    /// Assigns the camera of a camera view to a render system, which then renders the window from that camera
+    ///
+    /// `render_system_index` - the index of the render system to assign the camera to
+    /// `camera_view` - the name of the camera view whose camera is used, or None to use the main camera
+    pub fn assign_camera_view(&mut self, render_system_index: RenderSystemIndex, camera_view: Option<String>)
+    {
+        if render_system_index.index >= self.get_shadow_render_system_index()
+        {
+            warn!("Unable to assign a camera to non-existent render system: {}", render_system_index.index);
+            return;
+        }
+
+        match camera_view
+        {
+            Some(name) => { self.render_system_cameras.insert(render_system_index.index, name); },
+            None => { self.render_system_cameras.remove(&render_system_index.index); }
+        }
+    }
+
+    /// Get the descriptions of the camera views added while the game runs
+    pub fn camera_view_descriptions(&self) -> Vec<CameraViewDescription>
+    {
+        self.camera_views.iter().map(|x| x.description.clone()).collect()
+    }
+
+    /// Get the camera view assigned to each render system that does not render from the main camera
+    pub fn assigned_camera_views(&self) -> Vec<(RenderSystemIndex, String)>
+    {
+        self.render_system_cameras.iter().map(|(index, name)| (RenderSystemIndex{ index: *index }, name.clone())).collect()
+    }
+
+    /// Removes the camera view with the given name, if it exists, and deletes its FBO
+    ///
+    /// `name` - the name of the camera view to remove
+    fn remove_camera_view(&mut self, name: &str)
+    {
+        if let Some(index) = self.camera_views.iter().position(|x| x.description.name == name)
+        {
+            let camera_view = self.camera_views.remove(index);
+            self.render_systems[camera_view.description.display_render_system.index].take_accessible_fbo(name);
+        }
+    }
+
+    /// Applies any changes to the maximum number of lights that were requested since the last frame
+    fn apply_requested_light_constraints(&mut self)
+    {
+        // The shadow render system is not exposed to the user, and so cannot be the target of a request
+        let shadow_render_system_index = self.get_shadow_render_system_index();
+
+        for (render_system_index, max_num_lights) in REQUESTED_LIGHT_CONSTRAINTS.lock().drain(..)
+        {
+            if render_system_index.index >= shadow_render_system_index
+            {
+                warn!("Unable to change light constraints of non-existent render system: {}", render_system_index.index);
+                continue;
+            }
+
+            if let Err(err) = self.render_systems[render_system_index.index].change_light_constraints(max_num_lights)
+            {
+                warn!("Unable to change light constraints of render system {}: {}", render_system_index.index, err);
+            }
+        }
+    }
+
+    /// Enables or disables the render systems as requested since the last frame
+    fn apply_requested_render_system_toggles(&mut self)
+    {
+        // The shadow render system is not exposed to the user, and so cannot be the target of a request
+        let shadow_render_system_index = self.get_shadow_render_system_index();
+
+        for (render_system_index, enabled) in REQUESTED_RENDER_SYSTEM_TOGGLES.lock().drain(..)
+        {
+            if render_system_index.index >= shadow_render_system_index
+            {
+                warn!("Unable to enable or disable non-existent render system: {}", render_system_index.index);
+                continue;
+            }
+
+            self.set_render_system_enabled(render_system_index, enabled);
+        }
+    }
+
+    /// Enables or disables a render system. A disabled render system does not sort or draw its entities
+    ///
+    /// `render_system_index` - the index of the render system to enable or disable
+    /// `enabled` - true if the render system should render entities
+    pub fn set_render_system_enabled(&mut self, render_system_index: RenderSystemIndex, enabled: bool)
+    {
+        if enabled
+        {
+            self.disabled_render_systems.remove(&render_system_index.index);
+        }
+        else
+        {
+            self.disabled_render_systems.insert(render_system_index.index);
+        }
+    }
+
+    /// Get the render systems that were disabled while the game runs
+    pub fn disabled_render_systems(&self) -> Vec<RenderSystemIndex>
+    {
+        self.disabled_render_systems.iter().map(|x| RenderSystemIndex{ index: *x }).collect()
+    }
+
+    /// Replaces the draw functions of render systems as requested since the last frame. Requests are applied
+    /// before anything of the frame is drawn, so a frame is always drawn with one set of draw functions
+    fn apply_requested_draw_function_replacements(&mut self)
+    {
+        let requests = std::mem::take(&mut *REQUESTED_DRAW_FUNCTION_REPLACEMENTS.lock());
+
+        for (render_system_index, slot, draw_function) in requests
+        {
+            self.replace_draw_function(render_system_index, slot, draw_function);
+        }
+    }
+
+    /// Changes the debug render modes of render systems as requested since the last frame
+    fn apply_requested_debug_render_modes(&mut self)
+    {
+        // The shadow render system is not exposed to the user, and so cannot be the target of a request
+        let shadow_render_system_index = self.get_shadow_render_system_index();
+
+        for (render_system_index, mode) in REQUESTED_DEBUG_RENDER_MODES.lock().drain(..)
+        {
+            if render_system_index.index >= shadow_render_system_index
+            {
+                warn!("Unable to change the debug render mode of non-existent render system: {}", render_system_index.index);
+                continue;
+            }
+
+            self.render_systems[render_system_index.index].set_debug_render_mode(mode);
+        }
+    }
+
+    /// Replaces a draw function of a render system. The shadow render system is not exposed to the user, and
+    /// so its draw functions cannot be replaced
+    ///
+    /// `render_system_index` - the index of the render system to replace the draw function of
+    /// `slot` - the draw function to replace
+    /// `draw_function` - the function to draw with
+    pub fn replace_draw_function(&mut self, render_system_index: RenderSystemIndex, slot: DrawFunctionSlot, draw_function: DrawFunction)
+    {
+        if render_system_index.index >= self.get_shadow_render_system_index()
+        {
+            warn!("Unable to replace the draw function of non-existent render system: {}", render_system_index.index);
+            return;
+        }
+
+        self.render_systems[render_system_index.index].replace_draw_function(slot, draw_function.clone());
+        self.replaced_draw_functions.insert((render_system_index.index, slot), draw_function);
+    }
+
+    /// Get every draw function that was replaced while the game runs, along with the render system and slot
+    /// it replaced
+    pub fn replaced_draw_functions(&self) -> Vec<(RenderSystemIndex, DrawFunctionSlot, DrawFunction)>
+    {
+        self.replaced_draw_functions.iter()
+            .map(|((index, slot), draw_function)| (RenderSystemIndex{ index: *index }, *slot, draw_function.clone()))
+            .collect()
+    }
+
+    /// Updates the viewport to correspond with the new size of the rendering window
+    ///
+    /// `window_dimensions` - the resolution of the rendering window being rendered to
+    pub fn update_window_dimension(&mut self, window_dimensions: (i32, i32))
+    {
+        unsafe{ gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1); }
+
+        if window_dimensions != self.window_dimensions
+        {
+            self.window_dimensions = window_dimensions;
+            self.resize_minimap_fbos();
+        }
+    }
+
+    /// Recreates the FBOs of the minimaps and camera views at the size of the window, as the G-Buffers of the
+    /// render systems are written to them in their entirety. Each minimap is rendered again into its new FBO
+    fn resize_minimap_fbos(&mut self)
+    {
+        let window_dimensions = self.window_dimensions;
+
+        // A minimised window has no size; the FBOs are resized once it is restored
+        if window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        for minimap in &mut self.minimaps
+        {
+            match RenderFlow::create_minimap_fbo(window_dimensions)
+            {
+                Ok(fbo) =>
+                    {
+                        self.render_systems[minimap.description.display_render_system.index].add_accessible_fbo(minimap.description.name.clone(), fbo);
+                        minimap.dimensions = window_dimensions;
+                        minimap.update_requested = true;
+                    },
+                Err(err) => warn!("Unable to resize FBO for minimap {}: {}", minimap.description.name, err)
+            }
+        }
+
+        for camera_view in &mut self.camera_views
+        {
+            match RenderFlow::create_minimap_fbo(window_dimensions)
+            {
+                Ok(fbo) =>
+                    {
+                        self.render_systems[camera_view.description.display_render_system.index].add_accessible_fbo(camera_view.description.name.clone(), fbo);
+                        camera_view.dimensions = window_dimensions;
+                    },
+                Err(err) => warn!("Unable to resize FBO for camera view {}: {}", camera_view.description.name, err)
+            }
+        }
+    }
+
+    /// Renders the visible scene with the provided render system
+    ///
+    /// `upload_models` - the indexes of render systems whose associated models should be uploaded to
+    ///                   the current render system being executed
+    /// `render_system_index` - the index of the render system to run
+    /// `render_args` - structure containing required variables for rendering
+    /// `output_framebuffer` - the frame buffer the render system writes its final image to
+    /// `primary_view` - true if the render system is drawing the view it draws into the window every frame
+    fn run_render_system(&mut self, upload_models: Option<Range<usize>>, render_system_index: usize, render_args: &RenderArguments, visible_sections_light: &CullResult,
+                         output_framebuffer: OutputFramebuffer, primary_view: bool)
+    {
+        let mut models_updated = false;
+
+        // New model or existing model is no longer used, time to reupload models into render system
+        if let Some(model_bank_indexes) = upload_models
+        {
+            // Create copies to move into new thread where models will be uploaded into render system buffers
+            let model_layout_indexes = self.render_systems[render_system_index].get_model_layout_indexes();
+            let model_layout_update_function = self.render_systems[render_system_index].get_model_layout_update_function();
+            let tx = self.tx.clone();
+
+            let model_buffers = self.render_systems[render_system_index].get_model_mapped_buffers();
+            let indice_buffer = self.render_systems[render_system_index].get_indice_mapped_buffer();
+
+            let model_bank_owner = render_args.model_bank_owner.read();
+            let render_system_model_banks =
+                {
+                    let mut model_banks = Vec::new();
+                    for index in model_bank_indexes
+                    {
+                        model_banks.push(model_bank_owner.get_model_bank(RenderSystemIndex{ index }))
+                    }
+
+                    model_banks
+                };
+            RenderFlow::upload_models(&render_system_model_banks, model_buffers, indice_buffer, model_layout_update_function, model_layout_indexes, tx);
+
+            models_updated = true;
+        }
+
+        // If there is no layout update functions, don't spend time going through the logic of updating
+        // mapped buffers
+        if let Some(layout_update_fn) = self.render_systems[render_system_index].get_instance_layout_update_function()
+        {
+            let num_unique_layouts = self.render_systems[render_system_index].get_instance_layout_indexes().len();
+
+            // Render systems culling on the GPU check every instance there, so the entities of visible world
+            // sections are not checked one by one here
+            let gpu_culling = self.render_systems[render_system_index].uses_gpu_culling();
+
+            let sorting_param = SortWorldSectionEntitiesParam
+            {
+                visible_world_sections: &render_args.visible_world_sections,
+                ecs: render_args.ecs,
+                bounding_box_tree: render_args.bounding_box_tree,
+                unique_layout_indexes: Arc::new(self.render_systems[render_system_index].get_instance_layout_indexes()),
+                layout_update_function: layout_update_fn,
+                instance_params_layout: self.render_systems[render_system_index].get_instance_params_layout_index(),
+                dissolve_layout: self.render_systems[render_system_index].get_dissolve_layout_index(),
+                gpu_level_of_views: self.render_systems[render_system_index].uses_gpu_level_of_views(),
+                camera_position: render_args.camera.get_position(),
+                draw_distance: render_args.camera.get_far_draw_distance(),
+                level_views: &self.render_systems[render_system_index].level_of_views,
+                frustum_culler: RenderFrustumCuller::new(render_args.camera.get_projection_matrix() * render_args.camera.get_view_matrix()),
+                refinement_threshold: if gpu_culling { usize::MAX } else { *SUB_FRUSTUM_REFINEMENT_THRESHOLD.lock() },
+                entity_sphere_culling: !gpu_culling && *ENTITY_SPHERE_CULLING.lock(),
+                written_information_pool: &self.written_information_pool
+            };
+
+            let static_data = RenderFlow::extract_static_data(&sorting_param, self.static_data_unique_section.clone(), render_system_index);
+            let sorted_data = RenderFlow::sort_world_section_active_entities(sorting_param);
+
+            let mut sorted_data = sorted_data.lock();
+            RenderFlow::append_transparent_written_information(&mut sorted_data, &static_data, &self.written_information_pool, num_unique_layouts);
+            RenderFlow::sort_transparent_instances(&mut sorted_data, render_args.camera.get_position());
+
+            RenderFlow::upload_instance_data_to_render_system(&mut self.render_systems[render_system_index], &static_data, &sorted_data);
+            self.written_information_pool.recycle_all(std::mem::take(&mut *sorted_data));
+        }
+
+        if models_updated
+        {
+            if let Ok(updated_model_info) = self.rx.recv()
+            {
+                self.render_systems[render_system_index].flush_per_model_buffers(updated_model_info.flush_info);
+                self.render_systems[render_system_index].flush_indice_buffer(updated_model_info.indice_flush_info);
+
+                // Have to synchronize the rendering information. The rendering information received
+                // from the thread that uploaded the model data contains offsets for the model geometry,
+                // while the rendering system rendering info has the correct instance range information
+
+                self.render_systems[render_system_index].update_model_rendering_info(updated_model_info.updated_rendering_info);
+            }
+        }
+
+        let matrices = self.shadow_flow.upload_matrices.iter().map(|x| *x).collect::<Vec<TMat4<f32>>>();
+        let indexes = self.shadow_flow.upload_indexes.iter().map(|x| *x).collect::<Vec<u32>>();
+        let view_matrices = self.shadow_flow.upload_view_matrices.iter().map(|x| *x).collect::<Vec<TMat4<f32>>>();
+
+        let draw_param = DrawPreparationParameters
+        {
+            visible_sections_light: &visible_sections_light.visible_sections_map,
+            shadow_fbo: &mut self.shadow_fbo,
+            logical_entity_lookup: &HashMap::new(), // Deal with this later; have to be set in logical flow
+            logical_ecs: &render_args.ecs,
+            camera: render_args.camera,
+            input_history: render_args.input_history,
+            tree: render_args.bounding_box_tree,
+
+            visible_directional_lights: &mut self.visible_direction_lights,
+            visible_point_lights: &mut self.visible_point_lights,
+            visible_spot_lights: &mut self.visible_spot_lights,
+            upload_matrices: &matrices,
+            upload_indexes: &indexes,
+            upload_view_matrices: &view_matrices,
+
+            output_framebuffer,
+            primary_view
+        };
+
+        // Textures of earlier render systems may have been bound to the texture units of the lightmap,
+        // environment maps, damage masks and dissolve noise
+        self.lightmap.bind();
+        self.environment_maps.bind();
+        self.damage_masks.bind();
+        self.dissolve_noise.bind();
+        self.render_systems[render_system_index].draw(draw_param);
+    }
+
+    /// Bakes the lighting of static world sections, or removes it, if the light baking settings changed
+    /// or the lighting was requested to be baked again
+    ///
+    /// `tree` - the bounding box tree holding the world
+    /// `ecs` - the ECS holding the lights and bounding volumes of entities
+    fn update_baked_lighting(&mut self, tree: &BoundingBoxTree, ecs: &ECS)
+    {
+        // The world may not have been loaded yet in the first frames, in which case the request is kept
+        if tree.stored_entities_indexes.is_empty() || !take_light_bake_request()
+        {
+            return;
+        }
+
+        self.lightmap = match light_baking()
+        {
+            Some(settings) => Lightmap::new(&load_or_bake_lighting(tree, ecs, settings)),
+            None => Lightmap::empty()
+        };
+    }
+
+    /// Loads and convolves the environment map, or removes it, if the environment lighting changed
+    fn update_environment_lighting(&mut self)
+    {
+        if !take_environment_lighting_change()
+        {
+            return;
+        }
+
+        self.environment_maps = match environment_lighting()
+        {
+            Some(settings) => EnvironmentMaps::new(&settings).unwrap_or_else(|err|
+                {
+                    error!("Failed to create the environment lighting: {}", err);
+                    EnvironmentMaps::empty()
+                }),
+            None => EnvironmentMaps::empty()
+        };
+    }
+
+    /// Applies the hits on entities since the last frame and uploads the damage masks that changed
+    ///
+    /// `ecs` - the ECS holding the transformations of damaged entities
+    fn update_damage_masks(&mut self, ecs: &ECS)
+    {
+        for (layer, mask) in take_changed_damage_masks(ecs)
+        {
+            self.damage_masks.upload_layer(layer, &mask);
+        }
+    }
+
+    /// Sorts the static entities of the world sections that changed this frame without rendering them.
+    /// Static entities are only sorted when they change, so this keeps the static entity data of a disabled
+    /// render system correct for when it is enabled again
+    ///
+    /// `render_system_index` - the index of the disabled render system
+    /// `render_args` - structure containing required variables for rendering
+    fn sort_changed_static_entities(&mut self, render_system_index: usize, render_args: &RenderArguments)
+    {
+        if let Some(layout_update_fn) = self.render_systems[render_system_index].get_instance_layout_update_function()
+        {
+            let gpu_culling = self.render_systems[render_system_index].uses_gpu_culling();
+
+            let sorting_param = SortWorldSectionEntitiesParam
+            {
+                visible_world_sections: &render_args.visible_world_sections,
+                ecs: render_args.ecs,
+                bounding_box_tree: render_args.bounding_box_tree,
+                unique_layout_indexes: Arc::new(self.render_systems[render_system_index].get_instance_layout_indexes()),
+                layout_update_function: layout_update_fn,
+                instance_params_layout: self.render_systems[render_system_index].get_instance_params_layout_index(),
+                dissolve_layout: self.render_systems[render_system_index].get_dissolve_layout_index(),
+                gpu_level_of_views: self.render_systems[render_system_index].uses_gpu_level_of_views(),
+                camera_position: render_args.camera.get_position(),
+                draw_distance: render_args.camera.get_far_draw_distance(),
+                level_views: &self.render_systems[render_system_index].level_of_views,
+                frustum_culler: RenderFrustumCuller::new(render_args.camera.get_projection_matrix() * render_args.camera.get_view_matrix()),
+                refinement_threshold: if gpu_culling { usize::MAX } else { *SUB_FRUSTUM_REFINEMENT_THRESHOLD.lock() },
+                entity_sphere_culling: !gpu_culling && *ENTITY_SPHERE_CULLING.lock(),
+                written_information_pool: &self.written_information_pool
+            };
+
+            RenderFlow::sort_world_section_static_entities(&sorting_param, &mut self.static_data_unique_section.write()[render_system_index]);
+        }
+    }
+
+    /// Accumulates all static entity rendering data into one data structure to be uploaded into vRAM. The data
+    /// combined the previous time is reused if the same world sections are visible and drawn with the same models,
+    /// and none of their data changed
+    ///
+    /// `sorting_param` - variables required to sort entity rendering data
+    /// `static_data` - structure holding static entity data
+    /// `render_system_index` - index of the render system static data is being uploaded to
+    fn extract_static_data(sorting_param: &SortWorldSectionEntitiesParam, static_data: Arc<RwLock<Vec<UniqueSectionData>>>, render_system_index: usize) -> Arc<SortResult>
+    {
+        // If static entities changed in any of the visible world sections, then that data must be reloaded
+        {
+            let mut static_data = static_data.write();
+            let unique_sections = &mut static_data[render_system_index];
+            RenderFlow::sort_world_section_static_entities(sorting_param, unique_sections);
+
+            let now = Instant::now();
+            unique_sections.last_visible.extend(sorting_param.visible_world_sections.visible_sections_vec.iter().map(|x| (*x, now)));
+        }
+
+        let visible_sections = RenderFlow::visible_static_sections(sorting_param, &static_data.read()[render_system_index]);
+
+        if let Some(ref extracted) = static_data.read()[render_system_index].extracted
+        {
+            if extracted.sections == visible_sections
+            {
+                return extracted.data.clone();
+            }
+        }
 
-                        for (model_id, _) in write_info
-                        {
-                            let adjusted_model_id = match sorting_param.level_views.custom.get(&model_id)
-                            {
-                                Some(i) => ModelId::level_of_view_adjusted_model_index(*model_id, distance_from_aabb, i),
-                                None => ModelId::level_of_view_adjusted_model_index(*model_id, distance_from_aabb, &sorting_param.level_views.default),
-                            };
+        let aggregated_sorted_data: Mutex<SortResult> = Mutex::new(HashMap::default());
+        let num_unique_layouts = sorting_param.unique_layout_indexes.len();
 
-                            translated_model_ids.insert(*model_id, adjusted_model_id);
-                        }
+        let extract_fn = |chunks: &[(UniqueWorldSectionId, Option<HashMap<ModelId, ModelId>>)]|
+            {
+                let mut local_static_data: SortResult = HashMap::default();
+                let static_data = static_data.read();
 
-                        RenderFlow::append_written_information(&mut local_static_data, write_info, Some(translated_model_ids), num_unique_layouts);
+                for (world_section, translated_model_ids) in chunks
+                {
+                    if let Some(write_info) = static_data[render_system_index].world_data.get(world_section)
+                    {
+                        RenderFlow::append_written_information(&mut local_static_data, write_info, translated_model_ids.as_ref(), sorting_param.written_information_pool, num_unique_layouts);
                     }
                 }
 
                 let mut lock = aggregated_sorted_data.lock();
-                RenderFlow::append_written_information(&mut lock, &local_static_data, None, num_unique_layouts);
+                RenderFlow::merge_written_information(&mut lock, local_static_data, sorting_param.written_information_pool, num_unique_layouts);
             };
 
-        if sorting_param.visible_world_sections.visible_sections_vec.is_empty()
-        {
-            return aggregated_sorted_data;
-        }
-
-        if cfg!(debug_assertions)
+        if !visible_sections.is_empty()
         {
-            let chunk_size = sorting_param.visible_world_sections.visible_sections_vec.len();
+            if cfg!(debug_assertions)
+            {
+                extract_fn(&visible_sections);
+            }
+            else
+            {
+                let chunk_size = 25;
 
-            let _ = sorting_param.visible_world_sections.visible_sections_vec.chunks(chunk_size)
-                .map(|x|
+                let _ = visible_sections.par_chunks(chunk_size).map(|x|
                     {
                         extract_fn(x);
                     }).collect::<()>();
+            }
         }
-        else
+
+        let data = Arc::new(aggregated_sorted_data.into_inner());
+        let previous = static_data.write()[render_system_index].extracted.replace(ExtractedStaticData{ sections: visible_sections, data: data.clone() });
+
+        if let Some(previous) = previous
+        {
+            sorting_param.written_information_pool.recycle_shared(previous.data);
+        }
+
+        data
+    }
+
+    /// Finds the visible world sections holding static entities within the draw distance, and the model each of their
+    /// models is drawn with given the distance of the section from the camera
+    ///
+    /// `sorting_param` - variables required to sort entity rendering data
+    /// `unique_sections` - the data structure holding information for static entities
+    fn visible_static_sections(sorting_param: &SortWorldSectionEntitiesParam, unique_sections: &UniqueSectionData) -> Vec<(UniqueWorldSectionId, Option<HashMap<ModelId, ModelId>>)>
+    {
+        let mut visible_sections = Vec::new();
+
+        for world_section in &sorting_param.visible_world_sections.visible_sections_vec
         {
-            let chunk_size = 25;
+            if let Some(write_info) = unique_sections.world_data.get(world_section)
+            {
+                let distance_from_aabb = if let Some(unique_section) = sorting_param.bounding_box_tree.stored_entities_indexes.get(world_section)
+                {
+                    distance_to_aabb(&unique_section.aabb, sorting_param.camera_position)
+                }
+                else
+                {
+                    // This branch indicates there are static entities in a world section, but
+                    // that world section does not exist
+                    error!("Failed to find world section: {:?}", *world_section);
+
+                    debug_assert!(false);
+                    0.0
+                };
 
-            let _ = sorting_param.visible_world_sections.visible_sections_vec.par_chunks(chunk_size).map(|x|
+                if distance_from_aabb > sorting_param.draw_distance
                 {
-                    extract_fn(x);
-                }).collect::<()>();
-        };
+                    continue;
+                }
+
+                // The level of views of the instances are chosen when the render system is drawn
+                if sorting_param.gpu_level_of_views
+                {
+                    visible_sections.push((*world_section, None));
+                    continue;
+                }
+
+                let mut translated_model_ids = HashMap::default();
+
+                for (model_id, _) in write_info
+                {
+                    let adjusted_model_id = match sorting_param.level_views.custom.get(&model_id)
+                    {
+                        Some(i) => ModelId::level_of_view_adjusted_model_index(*model_id, distance_from_aabb, i),
+                        None => ModelId::level_of_view_adjusted_model_index(*model_id, distance_from_aabb, &sorting_param.level_views.default),
+                    };
+
+                    translated_model_ids.insert(*model_id, adjusted_model_id);
+                }
 
+                visible_sections.push((*world_section, Some(translated_model_ids)));
+            }
+        }
+
+        visible_sections
+    }
 
-        aggregated_sorted_data
+    /// Get the number of bytes of instance data held for the entities of a world section
+    ///
+    /// `world_data` - the instance data of the world section
+    fn written_information_bytes(world_data: &HashMap<ModelId, HashMap<SortableIndex, WrittenInformation>>) -> usize
+    {
+        world_data.values()
+            .flat_map(|x| x.values())
+            .map(|x| x.layout_data.iter().map(|(_, data)| data.len()).sum::<usize>() + x.positions.len() * size_of::<TVec3<f32>>() + x.bounding_radii.len() * size_of::<f32>())
+            .sum()
     }
 
     /// Finds any world sections where rendering information for static entities are out of data and
@@ -548,11 +2128,24 @@ impl RenderFlow
     /// `unique_sections` - the data structure holding information for static entities
     fn sort_world_section_static_entities(sorting_param: &SortWorldSectionEntitiesParam, unique_sections: &mut UniqueSectionData)
     {
-        if sorting_param.bounding_box_tree.get_changed_static_unique().is_empty()
+        let render_origin_moved = std::mem::replace(&mut unique_sections.render_origin_moved, false);
+
+        // Sections whose data was dropped to stay within the memory budget are sorted again once they are visible
+        let visible_evicted_world_sections = sorting_param.visible_world_sections.visible_sections_vec.iter()
+            .filter(|x| unique_sections.evicted_sections.contains(*x) && !sorting_param.bounding_box_tree.get_changed_static_unique().contains(*x))
+            .copied()
+            .collect::<Vec<UniqueWorldSectionId>>();
+
+        if sorting_param.bounding_box_tree.get_changed_static_unique().is_empty() && !render_origin_moved && visible_evicted_world_sections.is_empty()
         {
             return;
         }
 
+        if let Some(extracted) = unique_sections.extracted.take()
+        {
+            sorting_param.written_information_pool.recycle_shared(extracted.data);
+        }
+
         let processed_world_sections = Mutex::new(HashSet::default());
 
         let reupload_unique_world_sections = unique_sections.world_sections.intersection(sorting_param.bounding_box_tree.get_changed_static_unique())
@@ -570,7 +2163,21 @@ impl RenderFlow
             })
             .collect::<HashSet::<UniqueWorldSectionId>>();
 
-        for x in reupload_unique_world_sections.into_iter().chain(new_upload_unique_world_sections.into_iter())
+        // The instance data of static entities is relative to the render origin, so all of it is sorted again
+        // when the render origin moves
+        let moved_unique_world_sections = if render_origin_moved
+        {
+            unique_sections.world_data.keys()
+                .filter(|x| !sorting_param.bounding_box_tree.get_changed_static_unique().contains(*x))
+                .copied()
+                .collect::<Vec<UniqueWorldSectionId>>()
+        }
+        else
+        {
+            Vec::new()
+        };
+
+        for x in reupload_unique_world_sections.into_iter().chain(new_upload_unique_world_sections).chain(moved_unique_world_sections).chain(visible_evicted_world_sections)
         {
             let mut local_sorted_data = HashMap::default();
 
@@ -589,7 +2196,13 @@ impl RenderFlow
                 }
             }
 
-            unique_sections.world_data.insert(x, local_sorted_data);
+            unique_sections.evicted_sections.remove(&x);
+            unique_sections.section_bytes.insert(x, RenderFlow::written_information_bytes(&local_sorted_data));
+
+            if let Some(previous_data) = unique_sections.world_data.insert(x, local_sorted_data)
+            {
+                sorting_param.written_information_pool.recycle_all(previous_data);
+            }
         }
     }
 
@@ -619,7 +2232,7 @@ impl RenderFlow
                 // Time to append the sorted model layout data to the global equivalent
                 let mut global_sorted_data = sorted_data.lock();
 
-                RenderFlow::append_written_information(&mut global_sorted_data, &local_sorted_data, None, sorting_param.unique_layout_indexes.len());
+                RenderFlow::merge_written_information(&mut global_sorted_data, local_sorted_data, sorting_param.written_information_pool, sorting_param.unique_layout_indexes.len());
             };
 
         let mut active_world_sections = Vec::new();
@@ -652,17 +2265,19 @@ impl RenderFlow
         sorted_data
     }
 
-    /// Adds the information in the source to the target, effectively combining the rendering information
+    /// Adds the information in the source to the target, effectively combining the rendering information. The
+    /// source is copied into vectors taken from the pool wherever the target holds no data to extend
     ///
     /// `target` - the destination for all rendering data
     /// `source` - data to add to the target
     /// `model_id_translation` - optional translation of a source model id to a model id to store in the target
+    /// `pool` - the pool holding the vectors of instance data no longer used
     /// `num_unique_layout` - the number of layout data stored in the WrittenInformation in target and source
-    fn append_written_information(target: &mut SortResult, source: &SortResult, model_id_translation: Option<HashMap<ModelId, ModelId>>, num_unique_layouts: usize)
+    fn append_written_information(target: &mut SortResult, source: &SortResult, model_id_translation: Option<&HashMap<ModelId, ModelId>>, pool: &WrittenInformationPool, num_unique_layouts: usize)
     {
         for (model_id, local_model_data) in source
         {
-            let model_id = if let Some(ref translation) = model_id_translation
+            let model_id = if let Some(translation) = model_id_translation
             {
                 if let Some(adjusted_model_id) = translation.get(model_id)
                 {
@@ -681,37 +2296,72 @@ impl RenderFlow
                 *model_id
             };
 
+            let model_data = target.entry(model_id).or_insert_with(HashMap::default);
+
+            for (sortable_component_index, data) in local_model_data
+            {
+                match model_data.get_mut(sortable_component_index)
+                {
+                    Some(i) => RenderFlow::extend_written_information(i, data, num_unique_layouts),
+                    None => { model_data.insert(*sortable_component_index, pool.copy(data)); }
+                }
+            }
+        }
+    }
+
+    /// Moves the information in the source into the target. Data the target already holds for a model and
+    /// sortable index is extended in place, and the emptied source data is given back to the pool
+    ///
+    /// `target` - the destination for all rendering data
+    /// `source` - data to move into the target
+    /// `pool` - the pool holding the vectors of instance data no longer used
+    /// `num_unique_layout` - the number of layout data stored in the WrittenInformation in target and source
+    fn merge_written_information(target: &mut SortResult, source: SortResult, pool: &WrittenInformationPool, num_unique_layouts: usize)
+    {
+        for (model_id, local_model_data) in source
+        {
             match target.get_mut(&model_id)
             {
-                Some(i) =>
+                Some(model_data) =>
                     {
                         for (sortable_component_index, data) in local_model_data
                         {
-                            match i.get_mut(&sortable_component_index)
+                            match model_data.get_mut(&sortable_component_index)
                             {
-                                Some(j) =>
+                                Some(i) =>
                                     {
-                                        // Index of instanced layout vector data is the same in the global sorted data map
-                                        //  as it is in the local sorted data map
-                                        for index in 0..num_unique_layouts
-                                        {
-                                            j.layout_data[index].1.extend_from_slice(&data.layout_data[index].1);
-                                        }
-
-                                        j.number_entities += data.number_entities;
+                                        RenderFlow::extend_written_information(i, &data, num_unique_layouts);
+                                        pool.recycle(data);
                                     },
-                                None =>
-                                    {
-                                        i.insert(*sortable_component_index, data.clone());
-                                    }
+                                None => { model_data.insert(sortable_component_index, data); }
                             }
                         }
                     },
-                None => { target.insert(model_id, local_model_data.clone()); },
+                None => { target.insert(model_id, local_model_data); }
             }
         }
     }
 
+    /// Appends the entities of the source to the target
+    ///
+    /// `target` - the data to extend
+    /// `source` - the data to append
+    /// `num_unique_layout` - the number of layout data stored in the target and source
+    fn extend_written_information(target: &mut WrittenInformation, source: &WrittenInformation, num_unique_layouts: usize)
+    {
+        // Index of instanced layout vector data is the same in the global sorted data map
+        //  as it is in the local sorted data map
+        for index in 0..num_unique_layouts
+        {
+            target.layout_data[index].1.extend_from_slice(&source.layout_data[index].1);
+        }
+
+        target.number_entities += source.number_entities;
+        target.positions.extend_from_slice(&source.positions);
+        target.bounding_radii.extend_from_slice(&source.bounding_radii);
+        target.has_transparent_entities |= source.has_transparent_entities;
+    }
+
     /// Finds the data required to write to the vRAM for each model type in the given world sections
     ///
     /// `args` - structure holding variables required to perform the sorting
@@ -769,7 +2419,8 @@ impl RenderFlow
                             sorting_param: args.sorting_param,
                             local_sorted_data,
                             distance_sphere: distance_from_aabb,
-                            sortable_index: index
+                            sortable_index: index,
+                            cull_entities: false
                         };
 
                         RenderFlow::add_entities(entity_add_args, is_static);
@@ -785,7 +2436,8 @@ impl RenderFlow
                             sorting_param: args.sorting_param,
                             local_sorted_data,
                             distance_sphere: distance_from_aabb,
-                            sortable_index: index
+                            sortable_index: index,
+                            cull_entities: RenderFlow::section_partly_in_view(args.sorting_param, &all_section_entities.aabb)
                         };
 
                         RenderFlow::add_entities(entity_add_args, is_static);
@@ -834,14 +2486,24 @@ impl RenderFlow
                                         sorting_param: args.sorting_param,
                                         local_sorted_data,
                                         distance_sphere: distance_from_aabb,
-                                        sortable_index: index
+                                        sortable_index: index,
+                                        cull_entities: false
                                     };
 
                                     RenderFlow::add_entities(entity_add_args, is_static);
                                 }
                                 else
                                 {
-                                    let entities_with_sortable = i.entities.intersection(sortable_entities).into_iter().map(|x| *x).collect();
+                                    // A large shared section is visible if any part of it is, which would render all of
+                                    // its entities. Checking each entity removes those outside of the view. Static
+                                    // entities are not checked as they are sorted once, not every frame
+                                    let refine_entities = i.entities.len() > args.sorting_param.refinement_threshold;
+
+                                    let entities_with_sortable = i.entities.intersection(sortable_entities)
+                                        .into_iter()
+                                        .filter(|x| !refine_entities || RenderFlow::entity_in_view(args.sorting_param, i, **x))
+                                        .map(|x| *x)
+                                        .collect();
 
                                     let entity_add_args = AddEntitiesArgs
                                     {
@@ -849,7 +2511,8 @@ impl RenderFlow
                                         sorting_param: args.sorting_param,
                                         local_sorted_data,
                                         distance_sphere: distance_from_aabb,
-                                        sortable_index: index
+                                        sortable_index: index,
+                                        cull_entities: !refine_entities && RenderFlow::section_partly_in_view(args.sorting_param, &i.aabb)
                                     };
 
                                     RenderFlow::add_entities(entity_add_args, is_static);
@@ -865,19 +2528,89 @@ impl RenderFlow
         }
     }
 
+    /// Checks if the bounding volume of an entity in a shared section is in the view frustum. Entities
+    /// without a stored bounding volume are treated as visible
+    ///
+    /// `sorting_param` - variables required to sort entity rendering data
+    /// `shared_section` - the shared section holding the entity
+    /// `entity_id` - the entity to check
+    fn entity_in_view(sorting_param: &SortWorldSectionEntitiesParam, shared_section: &SharedWorldSectionEntities, entity_id: EntityId) -> bool
+    {
+        match shared_section.get_entity_aabb(entity_id)
+        {
+            Some(aabb) => sorting_param.frustum_culler.aabb_visible(aabb),
+            None => true
+        }
+    }
+
+    /// Checks if the bounding spheres of the entities in a world section should be checked against the view
+    /// frustum, which is only worth doing when the section is partly in view
+    ///
+    /// `sorting_param` - variables required to sort entity rendering data
+    /// `section_aabb` - the bounding volume of the world section
+    fn section_partly_in_view(sorting_param: &SortWorldSectionEntitiesParam, section_aabb: &StaticAABB) -> bool
+    {
+        sorting_param.entity_sphere_culling && !sorting_param.frustum_culler.aabb_fully_visible(section_aabb)
+    }
+
+    /// Checks if an active entity in a world section partly in view can be seen. The bounding volume of the
+    /// group the entity is a member of is checked first, so the members of a group entirely in or out of
+    /// view are not checked one by one
+    ///
+    /// `sorting_param` - variables required to sort entity rendering data
+    /// `entity` - the entity to check the visibility of
+    fn active_entity_visible(sorting_param: &SortWorldSectionEntitiesParam, entity: EntityId) -> bool
+    {
+        let tree = sorting_param.bounding_box_tree;
+
+        if let Some(group_aabb) = tree.get_entity_group(entity).and_then(|x| tree.get_group_aabb(x))
+        {
+            if !sorting_param.frustum_culler.aabb_visible(group_aabb)
+            {
+                return false;
+            }
+
+            if sorting_param.frustum_culler.aabb_fully_visible(group_aabb)
+            {
+                return true;
+            }
+        }
+
+        match tree.get_entity_bounding_sphere(entity)
+        {
+            Some(sphere) => sorting_param.frustum_culler.sphere_visible(sphere),
+            None => true
+        }
+    }
+
     /// Takes the entities provided and extracts their required data to be rendered
     ///
     /// `args` - variables to extract rendering data
     /// `is_static` - true is static entities were provided to this function
     fn add_entities(args: AddEntitiesArgs, is_static: bool)
     {
+        // The model ids of every entity are read each frame, so they are usually stored densely
+        let dense_model_ids = args.sorting_param.ecs.dense_components::<ModelId>();
+
         for entity in args.entities
         {
-            let model_id = args.sorting_param.ecs.get_copy::<ModelId>(*entity).unwrap();
-            let adjusted_model_id = if is_static
+            // Static entities are sorted once rather than every frame, so they are never culled here
+            if args.cull_entities && !is_static && !RenderFlow::active_entity_visible(args.sorting_param, *entity)
+            {
+                continue;
+            }
+
+            let model_id = match dense_model_ids
+            {
+                Some(ref model_ids) => *model_ids.get(*entity).unwrap(),
+                None => args.sorting_param.ecs.get_copy::<ModelId>(*entity).unwrap()
+            };
+
+            let adjusted_model_id = if is_static || args.sorting_param.gpu_level_of_views
             {
                 // Static entities are only uploaded once, so they should always have the base model id.
-                // When they are uploaded into vRAM, an appropriate model id will be decided
+                // When they are uploaded into vRAM, an appropriate model id will be decided. If the render
+                // system chooses level of views on the GPU, every instance has the base model id
                 model_id
             }
             else
@@ -900,16 +2633,7 @@ impl RenderFlow
                 Some(i) => i,
                 None =>
                     {
-                        let mut empty_written_information = WrittenInformation
-                        {
-                            number_entities: 0,
-                            layout_data: Vec::new()
-                        };
-
-                        for layout_index in args.sorting_param.unique_layout_indexes.iter()
-                        {
-                            empty_written_information.layout_data.push((*layout_index, Vec::new()));
-                        }
+                        let empty_written_information = args.sorting_param.written_information_pool.take(&args.sorting_param.unique_layout_indexes);
 
                         // Different entry for each sortable index, even if same model, allows for
                         // conditional rendering based off of sortable component by keeping track of
@@ -918,7 +2642,14 @@ impl RenderFlow
                     }
             };
 
+            let position = args.sorting_param.ecs.get_copy::<Position>(*entity).map_or(vec3(0.0, 0.0, 0.0), |x| x.get_position());
+            let bounding_radius = args.sorting_param.bounding_box_tree.get_entity_bounding_sphere(*entity)
+                .map_or(-1.0, |x| x.radius + nalgebra_glm::distance(&x.centre, &position));
+
             written_information.number_entities += 1;
+            written_information.positions.push(position);
+            written_information.bounding_radii.push(bounding_radius);
+            written_information.has_transparent_entities |= args.sorting_param.ecs.get_copy::<Transparent>(*entity).is_some();
 
             for (index, layout_index) in args.sorting_param.unique_layout_indexes.iter().enumerate()
             {
@@ -927,16 +2658,130 @@ impl RenderFlow
 
                 // This will append the current entity's instance information to the layout vector
                 let layout_vec = &mut written_information.layout_data[index].1;
-                (args.sorting_param.layout_update_function)(*layout_index, &args.sorting_param.ecs, layout_vec, *entity);
+
+                if args.sorting_param.instance_params_layout == Some(*layout_index)
+                {
+                    write_instance_params(args.sorting_param.ecs, layout_vec, *entity);
+                }
+                else if args.sorting_param.dissolve_layout == Some(*layout_index)
+                {
+                    write_dissolve(args.sorting_param.ecs, layout_vec, *entity);
+                }
+                else
+                {
+                    (args.sorting_param.layout_update_function)(*layout_index, &args.sorting_param.ecs, layout_vec, *entity);
+                }
+            }
+        }
+    }
+
+    /// Reorders the instances of every model holding a transparent entity from the furthest to the nearest
+    /// to the camera, so that blending them in one draw blends them in the right order
+    ///
+    /// `sorted_data` - the instance data of the visible models to reorder
+    /// `camera_position` - the position of the camera
+    fn sort_transparent_instances(sorted_data: &mut SortResult, camera_position: TVec3<f32>)
+    {
+        for written_information in sorted_data.values_mut().flat_map(|x| x.values_mut()).filter(|x| x.has_transparent_entities)
+        {
+            let number_entities = written_information.number_entities as usize;
+
+            if number_entities < 2 || written_information.positions.len() != number_entities || written_information.bounding_radii.len() != number_entities
+            {
+                continue;
+            }
+
+            let distances = written_information.positions.iter().map(|x| nalgebra_glm::distance2(x, &camera_position)).collect::<Vec<f32>>();
+            let mut order = (0..number_entities).collect::<Vec<usize>>();
+            order.sort_by(|a, b| distances[*b].partial_cmp(&distances[*a]).unwrap_or(std::cmp::Ordering::Equal));
+
+            for (_, layout_data) in written_information.layout_data.iter_mut()
+            {
+                let stride = layout_data.len() / number_entities;
+                let mut sorted_layout_data = Vec::with_capacity(layout_data.len());
+
+                for index in order.iter()
+                {
+                    sorted_layout_data.extend_from_slice(&layout_data[index * stride..(index + 1) * stride]);
+                }
+
+                *layout_data = sorted_layout_data;
+            }
+
+            written_information.positions = order.iter().map(|x| written_information.positions[*x]).collect();
+            written_information.bounding_radii = order.iter().map(|x| written_information.bounding_radii[*x]).collect();
+        }
+    }
+
+    /// Adds the static entity data holding transparent entities to the active entity data, so that the instances
+    /// of both are sorted by depth together. The static data is otherwise written straight from where it is kept
+    ///
+    /// `target` - the active entity data
+    /// `static_data` - the static entity data of the visible world sections
+    /// `pool` - the pool holding the vectors of instance data no longer used
+    /// `num_unique_layouts` - the number of layout data stored in the WrittenInformation in target and static data
+    fn append_transparent_written_information(target: &mut SortResult, static_data: &SortResult, pool: &WrittenInformationPool, num_unique_layouts: usize)
+    {
+        for (model_id, model_data) in static_data
+        {
+            for (sortable_index, data) in model_data.iter().filter(|(_, data)| data.has_transparent_entities)
+            {
+                let target_model_data = target.entry(*model_id).or_insert_with(HashMap::default);
+
+                match target_model_data.get_mut(sortable_index)
+                {
+                    Some(i) => RenderFlow::extend_written_information(i, data, num_unique_layouts),
+                    None => { target_model_data.insert(*sortable_index, pool.copy(data)); }
+                }
+            }
+        }
+    }
+
+    /// Groups the static and active entity data drawn with the same model and sortable index, in the order they are
+    /// written. Static data holding transparent entities is left out, as it was added to the active data
+    ///
+    /// `static_data` - the static entity data of the visible world sections
+    /// `active_data` - the active entity data of the visible world sections
+    fn instance_groups<'a>(static_data: &'a SortResult, active_data: &'a SortResult) -> Vec<InstanceGroup<'a>>
+    {
+        let mut groups = Vec::new();
+
+        for (model_id, model_data) in static_data
+        {
+            let active_model_data = active_data.get(model_id);
+
+            for (sortable_index, data) in model_data.iter().filter(|(_, data)| !data.has_transparent_entities)
+            {
+                let mut parts = vec![data];
+                parts.extend(active_model_data.and_then(|x| x.get(sortable_index)));
+                groups.push(InstanceGroup{ model_id: *model_id, sortable_index: *sortable_index, parts });
+            }
+        }
+
+        for (model_id, model_data) in active_data
+        {
+            let static_model_data = static_data.get(model_id);
+
+            for (sortable_index, data) in model_data
+            {
+                let written_with_static = static_model_data.and_then(|x| x.get(sortable_index)).is_some_and(|x| !x.has_transparent_entities);
+
+                if !written_with_static
+                {
+                    groups.push(InstanceGroup{ model_id: *model_id, sortable_index: *sortable_index, parts: vec![data] });
+                }
             }
         }
+
+        groups
     }
 
     /// Uploads the sorted world section entities into the appropriate buffers in the render system
     ///
     /// `render_system` - the render system to upload data to
-    /// `data_to_write` - the instance data for the visible models to upload to the given render system
-    fn upload_instance_data_to_render_system(render_system: &mut RenderSystem, data_to_write: &HashMap<ModelId, HashMap<SortableIndex, WrittenInformation>>)
+    /// `static_data` - the instance data of the static entities of the visible models
+    /// `active_data` - the instance data of the active entities of the visible models
+    fn upload_instance_data_to_render_system(render_system: &mut RenderSystem, static_data: &SortResult, active_data: &SortResult)
     {
         // Location and associate information to write data to
         let mapped_instance_buffers = render_system.get_instanced_mapped_buffers();
@@ -960,12 +2805,15 @@ impl RenderFlow
             }
         }
 
+        let instance_groups = RenderFlow::instance_groups(static_data, active_data);
+
         // Upload data for instances of a single model into render system
-        for (model_id, layout_info) in data_to_write.iter()
+        for group in &instance_groups
         {
-            let rendering_info = render_system.model_rendering_information.entry(*model_id).or_insert(ModelRenderingInformation::new());
+            let rendering_info = render_system.model_rendering_information.entry(group.model_id).or_insert(ModelRenderingInformation::new());
+            let mut number_entities = 0;
 
-            for (sortable_component_index, data) in layout_info
+            for data in &group.parts
             {
                 for (layout_index, layout_data) in data.layout_data.iter()
                 {
@@ -975,11 +2823,13 @@ impl RenderFlow
                         MappedBuffer::write_data_serialized(mapped_instance_buffers[buffer_index], layout_data, buffer_bytes_written[buffer_index], false);
                 }
 
-                let instance_range = InstanceRange{ begin_instance: total_entities_processed, count: data.number_entities };
-                rendering_info.instance_location.insert(*sortable_component_index, instance_range);
-
-                total_entities_processed += data.number_entities;
+                number_entities += data.number_entities;
             }
+
+            let instance_range = InstanceRange{ begin_instance: total_entities_processed, count: number_entities };
+            rendering_info.instance_location.insert(group.sortable_index, instance_range);
+
+            total_entities_processed += number_entities;
         }
 
         // Tell GPU to that new data in buffers is available
@@ -989,6 +2839,55 @@ impl RenderFlow
             flush_data_request.push((0, x));
         }
         render_system.flush_per_instance_buffers(flush_data_request);
+
+        if render_system.uses_gpu_level_of_views()
+        {
+            RenderFlow::set_level_of_view_instances(render_system, &instance_groups);
+        }
+    }
+
+    /// Gives the render system the instances written this frame, grouped the same way they were written,
+    /// so that it can choose their level of views on the GPU
+    ///
+    /// `render_system` - the render system the instance data was uploaded to
+    /// `instance_groups` - the instance data uploaded to the render system, in the order it was written
+    fn set_level_of_view_instances(render_system: &mut RenderSystem, instance_groups: &[InstanceGroup])
+    {
+        let mut groups = Vec::new();
+        let mut positions = Vec::new();
+
+        for group in instance_groups
+        {
+            let level_of_views = match render_system.level_of_views.custom.get(&group.model_id)
+            {
+                Some(i) => i.clone(),
+                None => render_system.level_of_views.default.clone()
+            };
+
+            let begin_instance = positions.len();
+
+            // Each part must have a position for every one of its instances for the groups to line up
+            // with the instances written. Instances without a bounding volume are never culled
+            for data in &group.parts
+            {
+                let begin_part = positions.len();
+                positions.extend(data.positions.iter().zip(data.bounding_radii.iter()).map(|(position, radius)| vec4(position.x, position.y, position.z, *radius)));
+                positions.resize(begin_part + data.number_entities as usize, vec4(0.0, 0.0, 0.0, -1.0));
+            }
+
+            groups.push(LevelOfViewGroup
+            {
+                model_id: group.model_id,
+                sortable_index: group.sortable_index,
+                begin_instance: begin_instance as u32,
+                count: (positions.len() - begin_instance) as u32,
+                level_of_views,
+                // Transparent instances were sorted by depth, which choosing the level of views must not undo
+                preserve_order: group.parts.iter().any(|x| x.has_transparent_entities)
+            });
+        }
+
+        render_system.set_level_of_view_instances(groups, positions);
     }
 
     /// Uploads the model data into the appropriate buffers for the current render system
@@ -1088,6 +2987,14 @@ impl RenderFlow
         self.render_systems[render_system_index.index].add_solid_colour_texture(colour)
     }
 
+    /// Checks if the given render system reads all of the material maps of models
+    ///
+    /// `render_system_index` - the index of the render system to check
+    pub fn uses_material_maps(&self, render_system_index: RenderSystemIndex) -> bool
+    {
+        self.render_systems[render_system_index.index].uses_material_maps()
+    }
+
     /// Uploads the given texture to the given render system, making it available for use when rendering
     ///
     /// `render_system_index` - the index of the render system to upload the texture to
@@ -1097,6 +3004,42 @@ impl RenderFlow
         self.render_systems[render_system_index.index].add_texture(texture_location)
     }
 
+    /// Uploads the given texture to the render system with the given name. None is returned if there is no
+    /// render system with that name
+    ///
+    /// `render_system_name` - the name of the render system to upload the texture to
+    /// `texture_location` - the location of the texture to upload
+    pub fn add_texture_by_name(&mut self, render_system_name: &str, texture_location: PathBuf) -> Option<UploadedTextureLocation>
+    {
+        RenderSystemIndex::from_name(render_system_name).map(|x| self.add_texture(x, texture_location))
+    }
+
+    /// Discards the rendering information kept for entities, such as after the entities were replaced by
+    /// loading a snapshot. The bounding box tree must mark all of its static sections as changed so that the
+    /// information of static entities is recreated
+    pub fn reset_entity_render_data(&mut self)
+    {
+        *self.static_data_unique_section.write() = (0..self.render_systems.len())
+            .map(|_| UniqueSectionData::new())
+            .collect::<Vec<UniqueSectionData>>();
+
+        self.visible_direction_lights.clear();
+        self.visible_point_lights.clear();
+        self.visible_spot_lights.clear();
+    }
+
+    /// Releases the textures of models that were removed since the last frame, freeing the texture array
+    /// layers of textures no longer used by any model
+    ///
+    /// `model_bank_owner` - owner of the models, which keeps track of the textures of removed models
+    fn release_unused_textures(&mut self, model_bank_owner: &RwLock<ModelBankOwner>)
+    {
+        for (render_system_index, texture) in model_bank_owner.write().take_released_textures()
+        {
+            self.render_systems[render_system_index.index].release_texture(texture);
+        }
+    }
+
     /// Find the index of the shadow render system
     fn get_shadow_render_system_index(&self) -> usize
     {
@@ -1147,6 +3090,7 @@ impl RenderFlow
                 shader_source: get_asset_folder().join("shaders/shadowFrag.glsl"),
                 uniforms: vec![],
                 include_shadow_maps: false,
+                include_light_storage_buffers: false,
                 include_error_textures: false,
                 textures: vec!
                 [
@@ -1183,4 +3127,101 @@ specify_model_geometry_layouts!(shadow_layout_update_fn,
                                 0, vertices);
 
 specify_type_ids!(shadow_instance_layout_fn,
-                1, TransformationMatrix);
\ No newline at end of file
+                1, TransformationMatrix);
+
+#[cfg(test)]
+mod tests
+{
+    use std::sync::Arc;
+    use hashbrown::{HashMap, HashSet};
+    use nalgebra_glm::{identity, vec3};
+    use crate::culling::render_frustum_culler::RenderFrustumCuller;
+    use crate::exports::logic_components::RenderSystemIndex;
+    use crate::flows::render_flow::{AddEntitiesArgs, RenderFlow, SortResult, SortWorldSectionEntitiesParam, WrittenInformationPool};
+    use crate::flows::visible_world_flow::CullResult;
+    use crate::models::model_definitions::ModelId;
+    use crate::objects::ecs::ECS;
+    use crate::objects::entity_id::EntityId;
+    use crate::render_system::render_system::LevelOfViews;
+    use crate::world::bounding_box_tree_v2::BoundingBoxTree;
+
+    fn write_entity_instance(_: u32, _: &ECS, layout: &mut Vec<u8>, entity_id: EntityId)
+    {
+        layout.extend_from_slice(&entity_id.get_entity_instance().to_ne_bytes());
+    }
+
+    #[test]
+    fn add_entities_reads_dense_model_ids()
+    {
+        let mut ecs = ECS::new();
+        ecs.register_type::<ModelId>();
+        ecs.use_dense_storage::<ModelId>();
+
+        let first_model = ModelId::new(0, RenderSystemIndex{ index: 0 });
+        let second_model = ModelId::new(1, RenderSystemIndex{ index: 0 });
+
+        let entities = (0..4).map(|_| ecs.create_entity()).collect::<Vec<EntityId>>();
+        ecs.write_component::<ModelId>(entities[0], first_model);
+        ecs.write_component::<ModelId>(entities[1], second_model);
+        ecs.write_component::<ModelId>(entities[2], first_model);
+        ecs.write_component::<ModelId>(entities[3], second_model);
+
+        // The model of the last entity takes the place of the removed model, so the dense order of the
+        // models no longer follows the order of the entities
+        ecs.remove_component::<ModelId>(entities[0]);
+        ecs.write_component::<ModelId>(entities[0], second_model);
+        assert_eq!(&[entities[3], entities[1], entities[2], entities[0]], ecs.dense_components::<ModelId>().unwrap().entities());
+
+        let cull_result = CullResult::new();
+        let bounding_box_tree = BoundingBoxTree::new(0, 0);
+        let level_views = LevelOfViews{ default: Vec::new(), custom: HashMap::default() };
+        let written_information_pool = WrittenInformationPool::default();
+
+        let sorting_param = SortWorldSectionEntitiesParam
+        {
+            visible_world_sections: &cull_result,
+            ecs: &ecs,
+            bounding_box_tree: &bounding_box_tree,
+            unique_layout_indexes: Arc::new(vec![4]),
+            layout_update_function: write_entity_instance,
+            instance_params_layout: None,
+            dissolve_layout: None,
+            gpu_level_of_views: true,
+            camera_position: vec3(0.0, 0.0, 0.0),
+            draw_distance: 100.0,
+            level_views: &level_views,
+            frustum_culler: RenderFrustumCuller::new(identity()),
+            refinement_threshold: 0,
+            entity_sphere_culling: false,
+            written_information_pool: &written_information_pool
+        };
+
+        let section_entities = entities.iter().copied().collect::<HashSet<EntityId>>();
+        let mut sorted_data = SortResult::default();
+
+        RenderFlow::add_entities(AddEntitiesArgs
+        {
+            entities: &section_entities,
+            sorting_param: &sorting_param,
+            local_sorted_data: &mut sorted_data,
+            distance_sphere: 0.0,
+            sortable_index: 0,
+            cull_entities: false
+        }, false);
+
+        let written_instances = |model_id: ModelId|
+            {
+                let written_information = &sorted_data[&model_id][&0];
+                let instances = written_information.layout_data[0].1.chunks(4)
+                    .map(|x| u32::from_ne_bytes([x[0], x[1], x[2], x[3]]))
+                    .collect::<HashSet<u32>>();
+
+                (written_information.number_entities, instances)
+            };
+
+        let instances_of = |indexes: &[usize]| indexes.iter().map(|x| entities[*x].get_entity_instance()).collect::<HashSet<u32>>();
+
+        assert_eq!((1, instances_of(&[2])), written_instances(first_model));
+        assert_eq!((3, instances_of(&[0, 1, 3])), written_instances(second_model));
+    }
+}
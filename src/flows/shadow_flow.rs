@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::iter::FromIterator;
 use std::sync::Arc;
@@ -6,8 +7,9 @@ use nalgebra_glm::{TMat4, TVec3, vec3};
 use crate::culling::render_frustum_culler::RenderFrustumCuller;
 use crate::culling::r#trait::TraversalDecider;
 use crate::exports::camera_object::{Camera, CameraBuilder};
-use crate::exports::light_components::{FindLightType, LightInformation};
+use crate::exports::light_components::{CastsShadows, FindLightType, LightInformation};
 use crate::exports::movement_components::Position;
+use crate::flows::shared_constants::SHADOW_CASTING_LIGHT_BUDGET;
 use crate::flows::visible_world_flow::{CullResult, VisibleWorldFlow};
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
@@ -15,6 +17,9 @@ use crate::world::bounding_box_tree_v2::{BoundingBoxTree, SharedWorldSectionId,
 use crate::world::bounding_volumes::aabb::StaticAABB;
 use crate::world::dimension::range::{XRange, YRange, ZRange};
 
+/// How many times more important a light must be than a light with shadow maps to take its shadow maps
+const SHADOW_EVICTION_MARGIN: f32 = 1.25;
+
 /// Handles the logic of determine what light source needs a shadow map and the information required
 /// to render the shadow map
 pub struct ShadowFlow
@@ -141,28 +146,12 @@ impl ShadowFlow
     {
         if current_light.is_none()
         {
-            if self.free_indexes.is_empty()
-            {
-                // Move on to next light source type; hopefully when round robin gets back to this
-                // light type there will a free index for the shadow map
-                self.current_light_type = ServicingLightType::PointLight(None);
-                return ShadowMapLocation::NoNewMapRequired;
-            }
+            current_light = self.find_next_light_to_have_shadow_map(args, FindLightType::Directional);
+            self.current_light_type = ServicingLightType::DirectionalLight(current_light);
 
-            // The provided functions to find a nearby visible light source of the given type is not used
-            // here as realistically there will not be many directional lights (and these lights should
-            // be visible for most of a given scene). Faster to just query all light sources of the
-            // directional type
-            let directional_lights = args.ecs.get_entities_with_sortable()[1];
-
-            for entity in directional_lights
+            if let Some(entity_id) = current_light
             {
-                if !self.directional_lights.contains_key(entity)
-                {
-                    current_light = Some(*entity);
-                    self.current_light_type = ServicingLightType::DirectionalLight(current_light);
-                    break;
-                }
+                self.directional_lights.insert(entity_id, ShadowMapIndex{ indexes: [None; 6] });
             }
         }
 
@@ -170,7 +159,20 @@ impl ShadowFlow
         {
             Some(entity_id) =>
                 {
-                    let free_index = self.free_indexes.pop_front().unwrap();
+                    // Move on to the next light source type once the light has its shadow map
+                    self.current_light_type = ServicingLightType::PointLight(None);
+
+                    let free_index = match self.free_indexes.pop_front()
+                    {
+                        Some(i) => i,
+                        None =>
+                            {
+                                self.directional_lights.remove(&entity_id);
+                                return ShadowMapLocation::NoNewMapRequired;
+                            }
+                    };
+
+                    self.directional_lights.get_mut(&entity_id).unwrap().indexes[0] = Some(free_index);
 
                     let position = args.ecs.get_copy::<Position>(entity_id).unwrap().get_position();
                     let light_information = args.ecs.get_copy::<LightInformation>(entity_id).unwrap();
@@ -225,14 +227,23 @@ impl ShadowFlow
         {
             Some(entity_id) =>
                 {
+                    // Move on to the next light source type once the light has its shadow map
+                    self.current_light_type = ServicingLightType::SpotLight(None);
+
                     // This is checked when finding id of light source to create map for,
                     // but check is done anyways for safety, just in case
                     let free_index = match self.free_indexes.pop_front()
                     {
                         Some(i) => i,
-                        None => return ShadowMapLocation::NoNewMapRequired
+                        None =>
+                            {
+                                self.point_lights.remove(&entity_id);
+                                return ShadowMapLocation::NoNewMapRequired;
+                            }
                     };
 
+                    self.point_lights.get_mut(&entity_id).unwrap().indexes[0] = Some(free_index);
+
                     let position = args.ecs.get_copy::<Position>(entity_id).unwrap().get_position();
                     let light_information = args.ecs.get_copy::<LightInformation>(entity_id).unwrap();
                     let window_size = (args.camera.window_width, args.camera.window_height);
@@ -271,9 +282,10 @@ impl ShadowFlow
             current_light = self.find_next_light_to_have_shadow_map(args, FindLightType::Spot);
             self.current_light_type = ServicingLightType::SpotLight(current_light);
 
+            // A spot light may already have some of its shadow maps if it did not have room for all of them
             if let Some(entity_id) = current_light
             {
-                self.spotlights.insert(entity_id, ShadowMapIndex{ indexes: [None; 6] });
+                self.spotlights.entry(entity_id).or_insert(ShadowMapIndex{ indexes: [None; 6] });
             }
         }
 
@@ -293,7 +305,11 @@ impl ShadowFlow
                                 let free_index = match self.free_indexes.pop_front()
                                 {
                                     Some(i) => i,
-                                    None => return ShadowMapLocation::NoNewMapRequired
+                                    None =>
+                                        {
+                                            self.current_light_type = ServicingLightType::DirectionalLight(None);
+                                            return ShadowMapLocation::NoNewMapRequired;
+                                        }
                                 };
                                 indexes.indexes[i] = Some(free_index);
 
@@ -357,94 +373,187 @@ impl ShadowFlow
         }
     }
 
-    /// Determines the id of the light source that should have a shadow map created for it
+    /// Determines the id of the light source that should have a shadow map created for it. The most important
+    /// light without a shadow map is chosen, evicting the shadow maps of less important lights if there is
+    /// no room for its shadow maps
     ///
     /// `args` - the structure containing variable required to create a shadow map
     /// `light_type` - the type of light for which a shadow map should be created for it
     fn find_next_light_to_have_shadow_map(&mut self, args: &CalculationArgs, light_type: FindLightType) -> Option<EntityId>
     {
-        // Quick check that could save a lot of work
-        if self.free_indexes.is_empty()
+        let nearby_light_sources = match light_type
         {
-            return None;
-        }
+            // Realistically there will not be many directional lights, and these lights should be visible for
+            // most of a given scene. Faster to just query all light sources of the directional type
+            FindLightType::Directional => args.ecs.get_entities_with_sortable()[FindLightType::Directional as usize].iter().copied().collect(),
+            // These lights include both that are visible and not visible to the camera; all lights within
+            // a given distance from the camera are included
+            _ => find_nearby_lights(&args.visible_sections_light.visible_sections_map, args.tree, light_type)
+        };
 
-        // These lights include both that are visible and not visible to the camera; all lights within
-        // a given distance from the camera are included
-        let nearby_light_sources = find_nearby_lights(&args.visible_sections_light.visible_sections_map, args.tree, light_type);
+        // Lights that are no longer nearby or no longer cast shadows give up their shadow maps
+        let released_lights = self.light_map(light_type).keys()
+            .filter(|x| !nearby_light_sources.contains(*x) || light_importance(args, **x, light_type).is_none())
+            .copied()
+            .collect::<Vec<EntityId>>();
 
-        let visible_lights = match light_type
+        for x in released_lights
         {
-            FindLightType::Directional => args.visible_direction_lights,
-            FindLightType::Point => args.visible_point_lights,
-            FindLightType::Spot => args.visible_spot_lights
-        };
+            self.release_shadow_maps(light_type, x);
+        }
 
-        let target_map = match light_type
+        let (candidate, candidate_importance) = nearby_light_sources.iter()
+            .filter(|x| !self.light_map(light_type).get(*x).is_some_and(|indexes| indexes.is_complete(light_type)))
+            .filter_map(|x| light_importance(args, *x, light_type).map(|importance| (*x, importance)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))?;
+
+        let (required_maps, has_shadow_maps) = match self.light_map(light_type).get(&candidate)
         {
-            FindLightType::Directional => &mut self.directional_lights,
-            FindLightType::Point => &mut self.point_lights,
-            FindLightType::Spot => &mut self.spotlights
+            Some(indexes) => (indexes.number_missing(light_type), true),
+            None => (ShadowMapIndex{ indexes: [None; 6] }.number_missing(light_type), false)
         };
 
-        // Remove lights that are no longer visible to the camera
-        let mut non_nearby_lights = Vec::new();
-        for entity in target_map.keys()
+        let light_budget = *SHADOW_CASTING_LIGHT_BUDGET.lock();
+
+        while self.free_indexes.len() < required_maps || (!has_shadow_maps && self.number_shadow_casting_lights() >= light_budget)
         {
-            // A loop is used as the .difference() method does not work between a set and a map
+            let (evicted_type, evicted_light, evicted_importance) = self.least_important_light(args, candidate)?;
 
-            if !nearby_light_sources.contains(entity)
+            // The margin keeps two lights of similar importance from repeatedly taking the shadow maps of each other
+            if evicted_importance * SHADOW_EVICTION_MARGIN >= candidate_importance
             {
-                non_nearby_lights.push(*entity);
+                return None;
             }
+
+            self.release_shadow_maps(evicted_type, evicted_light);
         }
 
-        for x in non_nearby_lights
+        Some(candidate)
+    }
+
+    /// Get the lights of the given type that have shadow maps
+    ///
+    /// `light_type` - the type of lights to get
+    fn light_map(&self, light_type: FindLightType) -> &HashMap<EntityId, ShadowMapIndex>
+    {
+        match light_type
         {
-            if let Some(indexes) = target_map.remove(&x)
-            {
-                for index in indexes.indexes.iter().filter_map(|x| *x)
-                {
-                    self.free_indexes.push_back(index);
-                }
-            }
+            FindLightType::Directional => &self.directional_lights,
+            FindLightType::Point => &self.point_lights,
+            FindLightType::Spot => &self.spotlights
         }
+    }
 
-        // Find if a visible light in currently being rendered; creating shadow for it is a priority
-        let priority_light =
-            {
-                let mut priority_light = None;
-                for x in visible_lights.iter()
-                {
-                    if !target_map.contains_key(x)
-                    {
-                        priority_light = Some(*x);
-                    }
-                }
+    /// Get the number of lights of any type that have shadow maps
+    fn number_shadow_casting_lights(&self) -> usize
+    {
+        self.directional_lights.len() + self.point_lights.len() + self.spotlights.len()
+    }
 
-                priority_light
-            };
+    /// Finds the least important light with shadow maps, other than the given light
+    ///
+    /// `args` - the structure containing the lights and the camera
+    /// `excluded_light` - the light that cannot be chosen
+    fn least_important_light(&self, args: &CalculationArgs, excluded_light: EntityId) -> Option<(FindLightType, EntityId, f32)>
+    {
+        [FindLightType::Directional, FindLightType::Point, FindLightType::Spot].iter()
+            .flat_map(|light_type| self.light_map(*light_type).keys().map(move |x| (*light_type, *x)))
+            .filter(|(_, x)| *x != excluded_light)
+            .map(|(light_type, x)| (light_type, x, light_importance(args, x, light_type).unwrap_or(0.0)))
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+    }
 
-        if priority_light.is_some()
+    /// Frees the shadow maps of the light so that they can be used by another light
+    ///
+    /// `light_type` - the type of the light
+    /// `entity_id` - the light whose shadow maps are freed
+    fn release_shadow_maps(&mut self, light_type: FindLightType, entity_id: EntityId)
+    {
+        let target_map = match light_type
         {
-            return priority_light;
-        }
-        else
+            FindLightType::Directional => &mut self.directional_lights,
+            FindLightType::Point => &mut self.point_lights,
+            FindLightType::Spot => &mut self.spotlights
+        };
+
+        if let Some(indexes) = target_map.remove(&entity_id)
         {
-            // If all visible light sources have shadow maps already, then choose a nearby offscreen
-            // light source. There is a chance it will be needed soon (such as if the camera rotates)
-            for x in nearby_light_sources
+            for index in indexes.indexes.iter().filter_map(|x| *x)
             {
-                if !visible_lights.contains(&x)
+                self.free_indexes.push_back(index);
+
+                // The freed shadow map will be overwritten, so its matrices must no longer be uploaded
+                if let Some(position) = self.upload_indexes.iter().position(|x| *x as usize == index)
                 {
-                    return Some(x);
+                    self.upload_indexes.remove(position);
+                    self.upload_matrices.remove(position);
+                    self.upload_view_matrices.remove(position);
                 }
             }
         }
+    }
+}
+
+impl ShadowMapIndex
+{
+    /// Get the number of shadow maps a light of the given type still needs
+    ///
+    /// `light_type` - the type of light the shadow maps are for
+    fn number_missing(&self, light_type: FindLightType) -> usize
+    {
+        match light_type
+        {
+            FindLightType::Spot => self.indexes.iter().filter(|x| x.is_none()).count(),
+            _ => self.indexes[0].is_none() as usize
+        }
+    }
+
+    /// Checks if a light of the given type has all of the shadow maps it needs
+    ///
+    /// `light_type` - the type of light the shadow maps are for
+    fn is_complete(&self, light_type: FindLightType) -> bool
+    {
+        self.number_missing(light_type) == 0
+    }
+}
 
-        // All required light sources have a shadow map
-        None
+/// Finds how important it is for the light to have a shadow map. Brighter lights with a larger reach that are
+/// near to the camera are more important, and lights shining on what the camera sees are preferred over
+/// offscreen lights that may only be needed soon. Returns None if the light does not cast shadows
+///
+/// `args` - the structure containing the lights and the camera
+/// `entity_id` - the light to find the importance of
+/// `light_type` - the type of the light
+fn light_importance(args: &CalculationArgs, entity_id: EntityId, light_type: FindLightType) -> Option<f32>
+{
+    let casts_shadows = args.ecs.get_copy::<CastsShadows>(entity_id).unwrap_or_default();
+
+    if !casts_shadows.enabled
+    {
+        return None;
     }
+
+    let light_information = args.ecs.get_copy::<LightInformation>(entity_id)?;
+
+    let visible_lights = match light_type
+    {
+        FindLightType::Directional => args.visible_direction_lights,
+        FindLightType::Point => args.visible_point_lights,
+        FindLightType::Spot => args.visible_spot_lights
+    };
+
+    // Directional lights light the scene regardless of where they are
+    let distance = match light_type
+    {
+        FindLightType::Directional => 0.0,
+        _ => args.ecs.get_copy::<Position>(entity_id).map_or(0.0, |x| nalgebra_glm::distance(&x.get_position(), &args.camera.get_position()))
+    };
+
+    let brightness = light_information.diffuse_colour.max().max(0.0);
+    let reach = light_information.radius.max(0.0) / (light_information.radius.max(0.0) + distance).max(f32::EPSILON);
+    let visibility = if visible_lights.contains(&entity_id) { 2.0 } else { 1.0 };
+
+    Some(brightness * reach * visibility * casts_shadows.priority.max(0.0))
 }
 
 /// Finds nearby light sources (relative to the camera) that are of the given type
@@ -1,7 +1,38 @@
+use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
+use crate::exports::camera_object::CameraViewRequest;
+use crate::exports::engine_hooks::EngineHookRequest;
+use crate::exports::frame_callback::FrameCallbackRequest;
+use crate::exports::load_models::{DebugRenderMode, DrawFunctionSlot, MaxNumLights};
+use crate::exports::logic_components::RenderSystemIndex;
+use crate::exports::minimap::MinimapRequest;
+use crate::exports::world_snapshot::SnapshotRequest;
+use crate::prelude::frustum::FrustumPadding;
+use crate::render_system::system_information::DrawFunction;
 
 lazy_static!
 {
   pub static ref WORLD_SECTION_LENGTH: Mutex<u32> = Mutex::new(32);
+  /// Number of entities a shared section must hold before its entities are individually checked against the
+  /// view frustum, rather than all being rendered when the section is visible
+  pub static ref SUB_FRUSTUM_REFINEMENT_THRESHOLD: Mutex<usize> = Mutex::new(64);
+  /// True if the bounding spheres of active entities in world sections partly in view are checked against
+  /// the view frustum before the entities are rendered
+  pub(crate) static ref ENTITY_SPHERE_CULLING: Mutex<bool> = Mutex::new(false);
+  /// Maximum number of lights given shadow maps at the same time. The least important lights lose their
+  /// shadow maps first when more lights need one
+  pub static ref SHADOW_CASTING_LIGHT_BUDGET: Mutex<usize> = Mutex::new(6);
+  /// Slack added around world sections when checking them against the view frustum
+  pub(crate) static ref FRUSTUM_PADDING: Mutex<FrustumPadding> = Mutex::new(FrustumPadding::default());
+  pub static ref REQUESTED_LIGHT_CONSTRAINTS: Mutex<Vec<(RenderSystemIndex, MaxNumLights)>> = Mutex::new(Vec::new());
+  pub(crate) static ref RENDER_SYSTEM_NAMES: Mutex<HashMap<String, RenderSystemIndex>> = Mutex::new(HashMap::default());
+  pub(crate) static ref REQUESTED_RENDER_SYSTEM_TOGGLES: Mutex<Vec<(RenderSystemIndex, bool)>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_DRAW_FUNCTION_REPLACEMENTS: Mutex<Vec<(RenderSystemIndex, DrawFunctionSlot, DrawFunction)>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_DEBUG_RENDER_MODES: Mutex<Vec<(RenderSystemIndex, DebugRenderMode)>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_MINIMAP_CHANGES: Mutex<Vec<MinimapRequest>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_CAMERA_VIEW_CHANGES: Mutex<Vec<CameraViewRequest>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_FRAME_CALLBACK_CHANGES: Mutex<Vec<FrameCallbackRequest>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_ENGINE_HOOK_CHANGES: Mutex<Vec<EngineHookRequest>> = Mutex::new(Vec::new());
+  pub(crate) static ref REQUESTED_SNAPSHOT_OPERATIONS: Mutex<Vec<SnapshotRequest>> = Mutex::new(Vec::new());
 }
\ No newline at end of file
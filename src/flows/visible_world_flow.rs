@@ -6,8 +6,11 @@ use parking_lot::Mutex;
 use rayon::iter::ParallelIterator;
 use rayon::prelude::ParallelSlice;
 use crate::culling::r#trait::TraversalDecider;
-use crate::flows::shared_constants::WORLD_SECTION_LENGTH;
-use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
+use crate::culling::render_frustum_culler::RenderFrustumCuller;
+use crate::exports::frame_statistics::CullingStatistics;
+use crate::flows::shared_constants::{FRUSTUM_PADDING, WORLD_SECTION_LENGTH};
+use crate::helper_things::aabb_helper_functions::distance_to_aabb;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, SharedWorldSectionId, UniqueWorldSectionId};
 use crate::world::bounding_volumes::aabb::StaticAABB;
 use crate::world::dimension::range::{XRange, YRange, ZRange};
 
@@ -19,26 +22,30 @@ pub struct CullResult
 {
     pub visible_sections_map: HashSet<UniqueWorldSectionId>,
     pub visible_sections_vec: Vec<UniqueWorldSectionId>,
+    /// Number of existing world sections that were tested against the frustum
+    pub sections_tested: usize,
 }
 
 impl CullResult
 {
     pub fn new() -> CullResult
     {
-        CullResult{ visible_sections_map: HashSet::default(), visible_sections_vec: Vec::new() }
+        CullResult{ visible_sections_map: HashSet::default(), visible_sections_vec: Vec::new(), sections_tested: 0 }
     }
 
     pub fn extend(&mut self, other: CullResult)
     {
         self.visible_sections_map.extend(other.visible_sections_map.into_iter());
         self.visible_sections_vec.extend(other.visible_sections_vec.into_iter());
+        self.sections_tested += other.sections_tested;
     }
 }
 
 impl VisibleWorldFlow
 {
-    pub fn find_visible_world_ids<T: TraversalDecider + Sync + Send>(frustum_culler: Arc<T>, bounding_tree: &BoundingBoxTree, world_aabb: StaticAABB) -> CullResult
+    pub fn find_visible_world_ids<T: TraversalDecider + Sync + Send>(frustum_culler: Arc<T>, pos: TVec3<f32>, bounding_tree: &BoundingBoxTree, world_aabb: StaticAABB) -> CullResult
     {
+        let frustum_padding = *FRUSTUM_PADDING.lock();
         let mut unique_world_sections=  vec![];
         let mut level = 0;
 
@@ -97,7 +104,14 @@ impl VisibleWorldFlow
 
                 for (id, aabb) in x
                 {
-                    if bounding_tree.is_section_in_existence(id) && frustum_culler.aabb_in_view(aabb)
+                    if !bounding_tree.is_section_in_existence(id)
+                    {
+                        continue;
+                    }
+
+                    local_visible_ids.sections_tested += 1;
+
+                    if frustum_culler.aabb_in_view(&frustum_padding.pad_aabb(aabb, pos))
                     {
                         local_visible_ids.visible_sections_map.insert(*id);
                         local_visible_ids.visible_sections_vec.push(*id);
@@ -125,13 +139,98 @@ impl VisibleWorldFlow
                 ZRange::new((centre_vec.z - half_draw).max(0.0), centre_vec.z + half_draw)
             );
 
-        VisibleWorldFlow::find_visible_world_ids(frustum_culler, bounding_tree, world_aabb)
+        VisibleWorldFlow::find_visible_world_ids(frustum_culler, pos, bounding_tree, world_aabb)
     }
 
     pub fn find_visible_world_ids_entire_world<T: TraversalDecider + Sync + Send>(frustum_culler: Arc<T>, pos: TVec3<f32>, draw: f32, bounding_tree: &BoundingBoxTree) -> CullResult
     {
         let world_aabb = VisibleWorldFlow::generate_original_culling_aabb(pos, draw);
-        VisibleWorldFlow::find_visible_world_ids(frustum_culler, bounding_tree, world_aabb)
+        VisibleWorldFlow::find_visible_world_ids(frustum_culler, pos, bounding_tree, world_aabb)
+    }
+
+    /// Counts the world sections and entities that were tested for visibility and what rejected them. This
+    /// repeats the distance and entity frustum tests done when sorting entities to be drawn, as sorting only
+    /// looks at static entities when they change
+    ///
+    /// `cull_result` - the world sections found to be in the view frustum
+    /// `bounding_tree` - the tree holding the world sections
+    /// `frustum_culler` - the view frustum of the camera
+    /// `pos` - the position of the camera
+    /// `draw` - the draw distance of the camera
+    /// `refinement_threshold` - number of entities above which entities of shared sections are tested individually
+    pub fn culling_statistics(cull_result: &CullResult, bounding_tree: &BoundingBoxTree, frustum_culler: &RenderFrustumCuller, pos: TVec3<f32>, draw: f32, refinement_threshold: usize) -> CullingStatistics
+    {
+        let mut statistics = CullingStatistics
+        {
+            sections_tested: cull_result.sections_tested,
+            sections_rejected_frustum: cull_result.sections_tested.saturating_sub(cull_result.visible_sections_vec.len()),
+            ..CullingStatistics::default()
+        };
+
+        let mut processed_shared_sections: HashSet<SharedWorldSectionId> = HashSet::default();
+
+        for world_section in &cull_result.visible_sections_vec
+        {
+            let unique_section = match bounding_tree.stored_entities_indexes.get(world_section)
+            {
+                Some(i) => i,
+                None => continue
+            };
+
+            let number_entities = unique_section.local_entities.len() + unique_section.static_entities.len();
+            statistics.entities_tested += number_entities;
+
+            if distance_to_aabb(&unique_section.aabb, pos) >= draw
+            {
+                statistics.sections_rejected_distance += 1;
+                statistics.entities_rejected_distance += number_entities;
+                continue;
+            }
+
+            statistics.sections_accepted += 1;
+            statistics.entities_accepted += number_entities;
+
+            for shared_section_id in &unique_section.shared_sections_ids
+            {
+                if !processed_shared_sections.insert(*shared_section_id)
+                {
+                    continue;
+                }
+
+                let shared_section = match bounding_tree.shared_section_indexes.get(shared_section_id)
+                {
+                    Some(i) => i,
+                    None => continue
+                };
+
+                let number_entities = shared_section.entities.len() + shared_section.static_entities.len();
+                statistics.entities_tested += number_entities;
+
+                if distance_to_aabb(&shared_section.aabb, pos) >= draw
+                {
+                    statistics.entities_rejected_distance += number_entities;
+                    continue;
+                }
+
+                // Matches the refinement done when sorting; static entities are never refined
+                let entities_rejected_frustum = if shared_section.entities.len() > refinement_threshold
+                {
+                    shared_section.entities.iter()
+                        .filter_map(|x| shared_section.get_entity_aabb(*x))
+                        .filter(|x| !frustum_culler.aabb_visible(x))
+                        .count()
+                }
+                else
+                {
+                    0
+                };
+
+                statistics.entities_rejected_frustum += entities_rejected_frustum;
+                statistics.entities_accepted += number_entities - entities_rejected_frustum;
+            }
+        }
+
+        statistics
     }
 
     pub fn generate_original_culling_aabb(pos: TVec3<f32>, draw: f32) -> StaticAABB
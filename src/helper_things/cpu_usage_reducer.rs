@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use rayon::iter::ParallelIterator;
 use rayon::prelude::ParallelSlice;
 
@@ -32,6 +32,7 @@ pub struct TimeTakeHistory
     one_thread: [TimeTaken; PREVIOUS_FRAMES],
     total_time_taken_micro_seconds: f32,
     time_passed: Instant,
+    last_time_taken: Duration,
 }
 
 impl TimeTakeHistory
@@ -46,7 +47,8 @@ impl TimeTakeHistory
         {
             one_thread: [assumed_previous_frame_time * MAX_ONE_THREAD_COEFFICIENT; PREVIOUS_FRAMES],
             total_time_taken_micro_seconds: assumed_previous_frame_time,
-            time_passed: Instant::now()
+            time_passed: Instant::now(),
+            last_time_taken: Duration::default(),
         }
     }
 
@@ -70,6 +72,7 @@ impl TimeTakeHistory
         {
             if number_elements_processed >= data.len()
             {
+                time_taken.end_frame();
                 return;
             }
 
@@ -116,16 +119,24 @@ impl TimeTakeHistory
     /// to this instance of the TimeTakenHistory structure (single thread + several threads)
     pub fn end_frame(&mut self)
     {
-        let time_passed = self.time_passed.elapsed().as_micros();
+        self.last_time_taken = self.time_passed.elapsed();
+        let time_passed = self.last_time_taken.as_micros();
 
         if time_passed == 0 // Platform does not support measuring micro seconds (unlikely all operations took 0 micro seconds)
         {
-            self.total_time_taken_micro_seconds = time_passed as f32;
+            self.total_time_taken_micro_seconds = 1000.0; // Default to 1ms, smallest amount of time every platform
+            // should be able to measure
         }
         else
         {
-            self.total_time_taken_micro_seconds = 1000.0; // Default to 1ms, smallest amount of time every platform
-            // should be able to measure
+            self.total_time_taken_micro_seconds = time_passed as f32;
         }
     }
+
+    /// Get how much time in total the last call of the function provided to this instance of the
+    /// TimeTakenHistory structure took, as measured rather than the time assumed when it could not be measured
+    pub fn last_time_taken(&self) -> Duration
+    {
+        self.last_time_taken
+    }
 }
\ No newline at end of file
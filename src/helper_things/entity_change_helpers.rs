@@ -1,10 +1,11 @@
 use std::any::TypeId;
 use std::time::Instant;
 use hashbrown::{HashMap, HashSet};
+use tracing::{error, trace, warn};
 use crate::exports::camera_object::Camera;
 use crate::exports::light_components::FindLightType;
-use crate::exports::logic_components::{IsOutOfBounds, OutOfBoundsLogic};
-use crate::exports::movement_components::{Position, Rotation, Scale, TransformationMatrix};
+use crate::exports::logic_components::{ChildEntities, IsOutOfBounds, OutOfBoundsLogic, ParentEntity};
+use crate::exports::movement_components::{Position, PreviousTransformationMatrix, Rotation, Scale, TransformationMatrix};
 use crate::flows::render_flow::RenderFlow;
 use crate::models::model_definitions::{ModelId, OriginalAABB};
 use crate::models::model_storage::ModelBankOwner;
@@ -64,7 +65,7 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
                                     },
                                     None =>
                                         {
-                                            eprintln!("Failed to get the model bank owner");
+                                            error!("Failed to get the model bank owner");
                                             debug_assert!(false);
                                             None
                                         }
@@ -88,20 +89,20 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
                                                 },
                                             None =>
                                                 {
-                                                    eprintln!("Failed to get the model information for model: {}", model_name);
+                                                    error!("Failed to get the model information for model: {}", model_name);
                                                     debug_assert!(false);
                                                 }
                                         },
                                         None =>
                                             {
-                                                eprintln!("Failed to get the model bank owner");
+                                                error!("Failed to get the model bank owner");
                                                 debug_assert!(false);
                                             }
                                     }
                                 }
                                 else
                                 {
-                                    eprintln!("Failed to get the model id for: {}", model_name);
+                                    error!("Failed to get the model id for: {}", model_name);
                                     debug_assert!(false);
                                 }
                             },
@@ -113,11 +114,25 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
                             {
                                 args.ecs.add_referenced_entity(*owner, *other);
                             },
+                        EntityChangeInformation::AddChildEntity(parent, child) =>
+                            {
+                                if add_child_entity(args.ecs, *parent, *child)
+                                {
+                                    // The child's transformation is now relative to the parent
+                                    kinematics_changed_entities.insert(*child);
+                                    only_translation_changed_entities.remove(child);
+                                }
+                            },
+                        EntityChangeInformation::AddGroupMember(group, member) =>
+                            {
+                                // The bounding volume of the group is found again once all changes are applied
+                                args.bounding_box_tree.add_group_member(*group, *member);
+                            },
                         EntityChangeInformation::MakeObjectStatic(ref entity_id) =>
                             {
                                 args.bounding_box_tree.remove_entity(*entity_id);
                                 let aabb = args.ecs.get_ref::<StaticAABB>(*entity_id).unwrap();
-                                let light_type = find_entity_light_type(&args, entity_id);
+                                let light_type = find_entity_light_type(args.ecs, entity_id);
 
                                 if let Err(_) = args.bounding_box_tree.add_entity(*entity_id, aabb, should_add_if_out_bounds(&args, *entity_id), true, light_type)
                                 {
@@ -128,7 +143,7 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
                             {
                                 args.bounding_box_tree.remove_entity(*entity_id);
                                 let aabb = args.ecs.get_ref::<StaticAABB>(*entity_id).unwrap();
-                                let light_type = find_entity_light_type(&args, entity_id);
+                                let light_type = find_entity_light_type(args.ecs, entity_id);
 
                                 if let Err(_) = args.bounding_box_tree.add_entity(*entity_id, aabb, should_add_if_out_bounds(&args, *entity_id), false, light_type)
                                 {
@@ -151,6 +166,18 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
                             {
                                 args.ecs.remove_referenced_entity(*owner, *other);
                             },
+                        EntityChangeInformation::RemoveChildEntity(parent, child) =>
+                            {
+                                remove_child_entity(args.ecs, *parent, *child);
+
+                                // The child's transformation is now relative to the world
+                                kinematics_changed_entities.insert(*child);
+                                only_translation_changed_entities.remove(child);
+                            },
+                        EntityChangeInformation::RemoveGroupMember(group, member) =>
+                            {
+                                args.bounding_box_tree.remove_group_member(*group, *member);
+                            },
                         EntityChangeInformation::ModifyRequest(ref change_request) =>
                             {
                                 apply_entity_change_requests(args.ecs, change_request, &mut kinematics_changed_entities, &mut only_translation_changed_entities, &mut deleted_changed_entities);
@@ -165,17 +192,25 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
                                 // a valid state, but redundant work was done. Those same changes after this branch
                                 // would be invalid
 
-                                let model_index = args.ecs.get_copy::<ModelId>(*entity_id).unwrap();
+                                delete_entity(&mut args, *entity_id, &mut kinematics_changed_entities, &mut only_translation_changed_entities, &mut deleted_changed_entities);
+                            },
+                        EntityChangeInformation::DespawnRecursive(ref entity_id) =>
+                            {
+                                // Same restrictions as for a delete request apply to every despawned entity
 
-                                if let Some(ref mut model_bank) = args.model_bank_owner
+                                for despawned_entity in args.ecs.get_owned_entities_recursive(*entity_id)
                                 {
-                                    model_bank.remove_instance( model_index);
+                                    delete_entity(&mut args, despawned_entity, &mut kinematics_changed_entities, &mut only_translation_changed_entities, &mut deleted_changed_entities);
+                                    args.ecs.clear_entity_relationships(despawned_entity);
                                 }
-
-                                args.bounding_box_tree.remove_entity(*entity_id);
-                                kinematics_changed_entities.remove(entity_id);
-                                deleted_changed_entities.insert(*entity_id);
-                                args.ecs.remove_entity(*entity_id);
+                            },
+                        EntityChangeInformation::SendEvent(type_id, ref event) =>
+                            {
+                                args.ecs.send_event_serialized(*type_id, event);
+                            }
+                        EntityChangeInformation::WriteResource(type_id, ref value) =>
+                            {
+                                args.ecs.write_resource_serialized(*type_id, value);
                             }
                     }
                 }
@@ -188,17 +223,47 @@ pub fn apply_change(mut args: ChangeArgs, mut changes: Option<&mut Vec<FrameChan
     args.bounding_box_tree.end_of_changes(&args.ecs);
 }
 
-fn find_entity_light_type(args: &ChangeArgs, entity_id: &EntityId) -> Option<FindLightType>
+/// Removes the entity from the ECS, the bounding box tree and the instances of its model
+///
+/// `args` - the variables required to apply changes requested for entities
+/// `entity_id` - the entity to delete
+/// `kinematics_changed_entities` - entities whose kinematics changed during this frame
+/// `only_translation_changed_entities` - entities whose translation only changed during this frame
+/// `deleted_changed_entities` - entities deleted during this frame
+fn delete_entity(args: &mut ChangeArgs, entity_id: EntityId, kinematics_changed_entities: &mut HashSet<EntityId>,
+                 only_translation_changed_entities: &mut HashSet<EntityId>, deleted_changed_entities: &mut HashSet<EntityId>)
+{
+    let model_index = args.ecs.get_copy::<ModelId>(entity_id).unwrap();
+
+    if let Some(ref mut model_bank) = args.model_bank_owner
+    {
+        model_bank.remove_instance( model_index);
+    }
+
+    args.bounding_box_tree.remove_entity(entity_id);
+    args.bounding_box_tree.remove_entity_from_groups(entity_id);
+    kinematics_changed_entities.remove(&entity_id);
+    only_translation_changed_entities.remove(&entity_id);
+    deleted_changed_entities.insert(entity_id);
+    detach_from_hierarchy(args.ecs, entity_id);
+    args.ecs.remove_entity(entity_id);
+}
+
+/// Finds the type of light the entity is, if it is a light
+///
+/// `ecs` - the ECS holding the entity
+/// `entity_id` - the entity to find the light type of
+pub(crate) fn find_entity_light_type(ecs: &ECS, entity_id: &EntityId) -> Option<FindLightType>
 {
-    if args.ecs.get_entities_with_sortable()[2].contains(entity_id)
+    if ecs.get_entities_with_sortable()[2].contains(entity_id)
     {
         Some(FindLightType::Point)
     }
-    else if args.ecs.get_entities_with_sortable()[3].contains(entity_id)
+    else if ecs.get_entities_with_sortable()[3].contains(entity_id)
     {
         Some(FindLightType::Spot)
     }
-    else if args.ecs.get_entities_with_sortable()[1].contains(entity_id)
+    else if ecs.get_entities_with_sortable()[1].contains(entity_id)
     {
         Some(FindLightType::Directional)
     }
@@ -209,15 +274,35 @@ fn find_entity_light_type(args: &ChangeArgs, entity_id: &EntityId) -> Option<Fin
 }
 
 /// Updates the entities that have had their kinematics information changed by updating their AABB
-/// based off of their new kinematic information
+/// based off of their new kinematic information. The new world sections of the moved entities are found
+/// in parallel, and the entities are then moved into them together
 ///
 /// `entities_moved` - the entities that have moved as a result of a change to their position, rotation
 ///                     or scale components
 /// `args` - the variables required to apply changes requested for entities
-pub fn update_aabb_after_kinematic_change(entities_moved: HashSet<EntityId>, only_translation_changed_entities: HashSet<EntityId>, args: &mut ChangeArgs)
+pub fn update_aabb_after_kinematic_change(mut entities_moved: HashSet<EntityId>, mut only_translation_changed_entities: HashSet<EntityId>, args: &mut ChangeArgs)
 {
     let time = Instant::now();
 
+    // The translation of an entity with a parent depends on the parent's rotation and scale, so its
+    // transformation cannot be found by only changing the translation
+    let child_entities = only_translation_changed_entities.iter()
+        .filter(|x| args.ecs.get_ref::<ParentEntity>(**x).is_some())
+        .copied()
+        .collect::<Vec<EntityId>>();
+
+    for x in child_entities
+    {
+        only_translation_changed_entities.remove(&x);
+        entities_moved.insert(x);
+    }
+
+    let changed_entities = entities_moved.iter().chain(only_translation_changed_entities.iter()).copied().collect::<HashSet<EntityId>>();
+
+    // The new bounding volume of every moved entity. An entity can be moved more than once, such as a child
+    // that moved and whose parent moved, in which case the last bounding volume is used
+    let mut moved_aabbs = HashMap::default();
+
     for entity_id in only_translation_changed_entities
     {
         let position = args.ecs.get_copy::<Position>(entity_id).unwrap();
@@ -226,6 +311,8 @@ pub fn update_aabb_after_kinematic_change(entities_moved: HashSet<EntityId>, onl
 
         {
             let mut transformation_matrix = args.ecs.get_ref_mut::<TransformationMatrix>(entity_id).unwrap().get_matrix();
+            args.ecs.write_component::<PreviousTransformationMatrix>(entity_id, PreviousTransformationMatrix::new(transformation_matrix));
+
             let mut column = nalgebra_glm::column(&transformation_matrix, 3);
             column.x = position.get_position().x;
             column.y = position.get_position().y;
@@ -235,30 +322,202 @@ pub fn update_aabb_after_kinematic_change(entities_moved: HashSet<EntityId>, onl
         }
 
         args.ecs.write_component::<StaticAABB>(entity_id, new_aabb);
-
-        update_entity_in_tree(args, entity_id, &new_aabb);
+        moved_aabbs.insert(entity_id, new_aabb);
     }
 
     for entity_id in entities_moved
     {
-        let position = args.ecs.get_ref::<Position>(entity_id).unwrap();
-        let rotation = args.ecs.get_copy::<Rotation>(entity_id).unwrap_or_else(|| Rotation::default());
-        let scale = args.ecs.get_copy::<Scale>(entity_id).unwrap_or_else(|| Scale::default());
+        let new_aabb = update_entity_transformation(args, entity_id);
+        moved_aabbs.insert(entity_id, new_aabb);
+    }
 
-        let mut transformation_matrix = nalgebra_glm::translate(&nalgebra_glm::identity(), &position.get_position());
-        transformation_matrix = nalgebra_glm::rotate(&transformation_matrix, rotation.get_rotation(), &rotation.get_rotation_axis());
-        transformation_matrix = nalgebra_glm::scale(&transformation_matrix, &scale.get_scale());
+    propagate_parent_transformations(&changed_entities, args, &mut moved_aabbs);
+    update_entities_in_tree(args, moved_aabbs);
 
-        let transformation_matrix = TransformationMatrix::new(transformation_matrix);
+    trace!("{}", time.elapsed().as_millis());
+}
 
-        let new_aabb = args.ecs.get_ref::<OriginalAABB>(entity_id).unwrap().aabb.clone().apply_transformation(&transformation_matrix.get_matrix());
-        args.ecs.write_component::<StaticAABB>(entity_id, new_aabb);
-        args.ecs.write_component::<TransformationMatrix>(entity_id, transformation_matrix);
+/// Computes the transformation matrix of the entity from its position, rotation and scale, composed
+/// with the transformation of its parent if it has one. The entity's AABB is updated accordingly and
+/// returned, so that the entity can be moved in the bounding box tree
+///
+/// `args` - the variables required to apply changes requested for entities
+/// `entity_id` - the entity to update the transformation of
+fn update_entity_transformation(args: &mut ChangeArgs, entity_id: EntityId) -> StaticAABB
+{
+    let position = args.ecs.get_ref::<Position>(entity_id).unwrap();
+    let rotation = args.ecs.get_copy::<Rotation>(entity_id).unwrap_or_else(|| Rotation::default());
+    let scale = args.ecs.get_copy::<Scale>(entity_id).unwrap_or_else(|| Scale::default());
+
+    let mut transformation_matrix = nalgebra_glm::translate(&nalgebra_glm::identity(), &position.get_position());
+    transformation_matrix = nalgebra_glm::rotate(&transformation_matrix, rotation.get_rotation(), &rotation.get_rotation_axis());
+    transformation_matrix = nalgebra_glm::scale(&transformation_matrix, &scale.get_scale());
+
+    if let Some(parent) = args.ecs.get_copy::<ParentEntity>(entity_id)
+    {
+        if let Some(parent_transformation) = args.ecs.get_copy::<TransformationMatrix>(parent.entity)
+        {
+            transformation_matrix = parent_transformation.get_matrix() * transformation_matrix;
+        }
+    }
+
+    let transformation_matrix = TransformationMatrix::new(transformation_matrix);
 
-        update_entity_in_tree(args, entity_id, &new_aabb);
+    if let Some(previous_transformation_matrix) = args.ecs.get_copy::<TransformationMatrix>(entity_id)
+    {
+        args.ecs.write_component::<PreviousTransformationMatrix>(entity_id, PreviousTransformationMatrix::new(previous_transformation_matrix.get_matrix()));
     }
 
-    println!("{}", time.elapsed().as_millis());
+    let new_aabb = args.ecs.get_ref::<OriginalAABB>(entity_id).unwrap().aabb.clone().apply_transformation(&transformation_matrix.get_matrix());
+    args.ecs.write_component::<StaticAABB>(entity_id, new_aabb);
+    args.ecs.write_component::<TransformationMatrix>(entity_id, transformation_matrix);
+
+    new_aabb
+}
+
+/// Updates the transformations of all of the descendants of the entities whose transformation changed,
+/// so that children follow their parents. Parents are updated before their children
+///
+/// `changed_entities` - the entities whose transformation changed
+/// `args` - the variables required to apply changes requested for entities
+/// `moved_aabbs` - the new bounding volume of every moved entity, which the descendants are added to
+fn propagate_parent_transformations(changed_entities: &HashSet<EntityId>, args: &mut ChangeArgs, moved_aabbs: &mut HashMap<EntityId, StaticAABB>)
+{
+    // Only the highest changed entity in each hierarchy needs to be propagated from, as doing so
+    // updates every changed entity below it
+    let is_ancestor_changed = |ecs: &ECS, entity_id: EntityId|
+        {
+            let mut current = ecs.get_copy::<ParentEntity>(entity_id);
+
+            while let Some(parent) = current
+            {
+                if changed_entities.contains(&parent.entity)
+                {
+                    return true;
+                }
+
+                current = ecs.get_copy::<ParentEntity>(parent.entity);
+            }
+
+            false
+        };
+
+    let hierarchy_roots = changed_entities.iter()
+        .filter(|x| args.ecs.get_ref::<ChildEntities>(**x).is_some() && !is_ancestor_changed(args.ecs, **x))
+        .copied()
+        .collect::<Vec<EntityId>>();
+
+    for root in hierarchy_roots
+    {
+        let mut pending_entities = args.ecs.get_ref::<ChildEntities>(root).unwrap().get_entities();
+
+        while let Some(entity_id) = pending_entities.pop()
+        {
+            if args.ecs.get_ref::<Position>(entity_id).is_none()
+            {
+                continue;
+            }
+
+            let new_aabb = update_entity_transformation(args, entity_id);
+            moved_aabbs.insert(entity_id, new_aabb);
+
+            if let Some(children) = args.ecs.get_ref::<ChildEntities>(entity_id)
+            {
+                pending_entities.extend(children.get_entities());
+            }
+        }
+    }
+}
+
+/// Makes the given entity a child of the parent entity. False is returned if the relationship
+/// could not be created
+///
+/// `ecs` - the ECS holding the entities
+/// `parent` - the entity that will become the parent
+/// `child` - the entity that will become the child
+fn add_child_entity(ecs: &mut ECS, parent: EntityId, child: EntityId) -> bool
+{
+    // A child cannot be an ancestor of its parent, as then neither's transformation could be found
+    let mut current = Some(parent);
+    while let Some(entity_id) = current
+    {
+        if entity_id == child
+        {
+            warn!("Cannot make entity {:?} a child of {:?} as it would create a cycle", child, parent);
+            debug_assert!(false);
+            return false;
+        }
+
+        current = ecs.get_copy::<ParentEntity>(entity_id).map(|x| x.entity);
+    }
+
+    let mut children = ecs.get_copy::<ChildEntities>(parent).unwrap_or_default();
+    if !children.add(child)
+    {
+        warn!("Entity {:?} cannot have any more children", parent);
+        debug_assert!(false);
+        return false;
+    }
+
+    if let Some(previous_parent) = ecs.get_copy::<ParentEntity>(child)
+    {
+        remove_child_entity(ecs, previous_parent.entity, child);
+    }
+
+    ecs.write_component::<ChildEntities>(parent, children);
+    ecs.write_component::<ParentEntity>(child, ParentEntity{ entity: parent });
+    true
+}
+
+/// Removes the parent-child relationship between the two entities, if there is one
+///
+/// `ecs` - the ECS holding the entities
+/// `parent` - the parent entity
+/// `child` - the child entity
+fn remove_child_entity(ecs: &mut ECS, parent: EntityId, child: EntityId)
+{
+    if let Some(mut children) = ecs.get_copy::<ChildEntities>(parent)
+    {
+        children.remove(child);
+
+        if children.is_empty()
+        {
+            ecs.remove_component::<ChildEntities>(parent);
+        }
+        else
+        {
+            ecs.write_component::<ChildEntities>(parent, children);
+        }
+    }
+
+    if let Some(current_parent) = ecs.get_copy::<ParentEntity>(child)
+    {
+        if current_parent.entity == parent
+        {
+            ecs.remove_component::<ParentEntity>(child);
+        }
+    }
+}
+
+/// Removes the entity from any transformation hierarchy it is a part of. Its children keep their
+/// relative position, rotation and scale, which are then relative to the world
+///
+/// `ecs` - the ECS holding the entities
+/// `entity_id` - the entity to remove from its hierarchy
+fn detach_from_hierarchy(ecs: &mut ECS, entity_id: EntityId)
+{
+    if let Some(parent) = ecs.get_copy::<ParentEntity>(entity_id)
+    {
+        remove_child_entity(ecs, parent.entity, entity_id);
+    }
+
+    if let Some(children) = ecs.get_copy::<ChildEntities>(entity_id)
+    {
+        for child in children.get_entities()
+        {
+            remove_child_entity(ecs, entity_id, child);
+        }
+    }
 }
 
 fn should_add_if_out_bounds(args: &ChangeArgs, entity_id: EntityId) -> bool
@@ -322,30 +581,56 @@ fn apply_entity_change_requests(ecs: &mut ECS, change_request: &EntityChangeRequ
     }
 }
 
-fn update_entity_in_tree(args: &mut ChangeArgs, entity_id: EntityId, aabb: &StaticAABB)
+/// Moves the entities into the world sections holding their new bounding volumes. Where each entity belongs
+/// is found for every entity in parallel, after which the tree is changed for every entity one after the other
+///
+/// `args` - the variables required to apply changes requested for entities
+/// `moved_aabbs` - the new bounding volume of every moved entity
+fn update_entities_in_tree(args: &mut ChangeArgs, moved_aabbs: HashMap<EntityId, StaticAABB>)
 {
-    // Every entity should have an entity type, but if it does not, have this check to prevent a crash
-    let add_if_out_bounds = should_add_if_out_bounds(&args, entity_id);
-    let light_type = find_entity_light_type(args, &entity_id);
+    let (moved_entities, placement_requests): (Vec<EntityId>, Vec<(StaticAABB, bool)>) = moved_aabbs.into_iter()
+        .map(|(entity_id, aabb)| (entity_id, (aabb, should_add_if_out_bounds(&args, entity_id))))
+        .unzip();
 
-    if args.bounding_box_tree.add_entity(entity_id,aabb, add_if_out_bounds, false, light_type).is_err()
+    let placements = args.bounding_box_tree.find_entity_placements(&placement_requests);
+
+    for ((entity_id, (_, add_if_out_bounds)), placement) in moved_entities.into_iter().zip(placement_requests).zip(placements)
     {
-        if add_if_out_bounds
+        match placement
         {
-            args.ecs.write_component::<IsOutOfBounds>(entity_id, IsOutOfBounds);
+            Some(placement) =>
+                {
+                    let light_type = find_entity_light_type(args.ecs, &entity_id);
+                    args.bounding_box_tree.place_entity(entity_id, placement, false, light_type);
+                },
+            None => handle_out_of_bounds_entity(args, entity_id, add_if_out_bounds)
         }
-        else
-        {
-            // If there is no logic to deal with out of bound entities in the next frame,
-            // then there is no known way to deal with it, so it is deleted
-            let model_index = args.ecs.get_copy::<ModelId>(entity_id).unwrap();
+    }
+}
 
-            if let Some(ref mut model_bank_owner) = args.model_bank_owner
-            {
-                model_bank_owner.remove_instance( model_index);
-            }
+/// Handles an entity that moved out of the game world. It is marked as out of bounds if there is logic to
+/// deal with it, and deleted otherwise
+///
+/// `args` - the variables required to apply changes requested for entities
+/// `entity_id` - the entity that moved out of bounds
+/// `add_if_out_bounds` - true if there is logic to deal with the entity being out of bounds
+fn handle_out_of_bounds_entity(args: &mut ChangeArgs, entity_id: EntityId, add_if_out_bounds: bool)
+{
+    if add_if_out_bounds
+    {
+        args.ecs.write_component::<IsOutOfBounds>(entity_id, IsOutOfBounds);
+    }
+    else
+    {
+        // If there is no logic to deal with out of bound entities in the next frame,
+        // then there is no known way to deal with it, so it is deleted
+        let model_index = args.ecs.get_copy::<ModelId>(entity_id).unwrap();
 
-            args.ecs.remove_entity(entity_id)
+        if let Some(ref mut model_bank_owner) = args.model_bank_owner
+        {
+            model_bank_owner.remove_instance( model_index);
         }
+
+        args.ecs.remove_entity(entity_id)
     }
 }
\ No newline at end of file
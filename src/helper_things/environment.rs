@@ -1,5 +1,8 @@
 use std::{env, fs};
 use std::path::{Path, PathBuf};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::debug;
 
 #[cfg(target_os = "linux")]
 use
@@ -19,13 +22,33 @@ pub fn get_model_folder() -> PathBuf
     get_asset_folder().join("models")
 }
 
+/// Get the location of the folder holding the string tables of each language
+pub fn get_localization_folder() -> PathBuf
+{
+    get_asset_folder().join("localization")
+}
+
+lazy_static!
+{
+    static ref DEBUG_LOGS_FOLDER_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+/// Changes the location of the debug / playback folder, such as to keep the gameplay history of
+/// different runs separate
+///
+/// `folder` - the folder to use as the debug / playback folder
+pub fn set_debug_logs_folder(folder: PathBuf)
+{
+    *DEBUG_LOGS_FOLDER_OVERRIDE.lock() = Some(folder);
+}
+
 /// Get the location of the debug / playback folder
 pub fn get_debug_logs_folder() -> PathBuf
 {
-    let path_directory = get_root_directory().join("debug_logs");
+    let path_directory = DEBUG_LOGS_FOLDER_OVERRIDE.lock().clone().unwrap_or_else(|| get_root_directory().join("debug_logs"));
     if !Path::exists(&*path_directory)
     {
-        fs::create_dir(path_directory.clone())
+        fs::create_dir_all(path_directory.clone())
             .unwrap_or_else(|e| panic!("Failed to create debug logs folder: {}", e));
     }
     path_directory
@@ -34,7 +57,7 @@ pub fn get_debug_logs_folder() -> PathBuf
 /// Get the location of the folders holding the generated shaders
 pub fn get_generated_shaders_folder() -> PathBuf
 {
-    println!("Attempting to find: {:?}", get_root_directory());
+    debug!("Attempting to find: {:?}", get_root_directory());
     let path_directory = get_root_directory().join("generated_shaders");
     if !Path::exists(&*path_directory)
     {
@@ -4,8 +4,12 @@ use std::{io, thread};
 use std::time::Duration;
 use lazy_static::lazy_static;
 use parking_lot::{Condvar, FairMutex, Mutex};
+use tracing::{error, warn};
+use crate::exports::accessibility::set_accessibility_settings;
 use crate::exports::load_models::UserUploadInformation;
-use crate::helper_things::environment::get_debug_logs_folder;
+use crate::exports::logging::install_default_log_subscriber;
+use crate::exports::panic_policy::{EngineThread, PanicAction, PanicPolicy, record_panic};
+use crate::helper_things::environment::{get_debug_logs_folder, set_debug_logs_folder};
 use crate::helper_things::game_loader::LoadParam;
 use crate::helper_things::round_robin_indexer::ArrayIndexer;
 use crate::threads::history_thread::{HistoryInputArgs, store_history, StoredHistoryState, write_to_disk};
@@ -42,22 +46,28 @@ pub type FrameVectors = Arc<[Mutex<ChangeHistory>; 2]>;
 
 pub fn launch_render_system(user_load_info: UserUploadInformation) {
 
+    install_default_log_subscriber();
+
     std::panic::set_hook(Box::new(|info|
         {
-            if let Some(error_location) = info.location()
+            // The thread is found from its name rather than where the panic happened, as the panic can
+            // happen in anything the thread calls
+            match thread::current().name().and_then(EngineThread::from_thread_name)
             {
-                if error_location.file().contains("history_thread")
-                {
-                    *HISTORY_THREAD_SUCCESS_COUNT.lock() = FAILURE_COUNT;
-                }
-
-                if error_location.file().contains("render_thread")
-                {
-                    *RENDER_THREAD_SUCCESS_COUNT.lock() = FAILURE_COUNT;
-                }
+                Some(EngineThread::History) =>
+                    {
+                        record_panic(EngineThread::History, info.to_string());
+                        *HISTORY_THREAD_SUCCESS_COUNT.lock() = FAILURE_COUNT;
+                    },
+                Some(EngineThread::Render) =>
+                    {
+                        record_panic(EngineThread::Render, info.to_string());
+                        *RENDER_THREAD_SUCCESS_COUNT.lock() = FAILURE_COUNT;
+                    },
+                None => {}
             }
 
-            println!("{}", info);
+            error!("{}", info);
         }));
 
     let frame_vectors = Arc::new(
@@ -75,6 +85,16 @@ pub fn launch_render_system(user_load_info: UserUploadInformation) {
 
     let debug = user_load_info.is_debugging;
 
+    set_accessibility_settings(user_load_info.accessibility);
+
+    if let Some(ref capture_directory) = user_load_info.capture_directory
+    {
+        set_debug_logs_folder(capture_directory.clone());
+    }
+
+    let history_thread_panic_policy = user_load_info.history_thread_panic_policy;
+    let render_thread_panic_policy = user_load_info.render_thread_panic_policy;
+
     if !debug
     {
         // *********************************************************************************************
@@ -90,10 +110,7 @@ pub fn launch_render_system(user_load_info: UserUploadInformation) {
             state: history_state.clone()
         };
 
-        thread::spawn(move ||
-            {
-                store_history(history_args);
-            });
+        spawn_history_thread(history_args);
 
         loop
         {
@@ -119,7 +136,7 @@ pub fn launch_render_system(user_load_info: UserUploadInformation) {
         state: history_state.clone(),
     };
 
-    let render_thread = thread::spawn(move ||
+    let render_thread = thread::Builder::new().name(EngineThread::Render.thread_name().to_string()).spawn(move ||
         {
             if debug
             {
@@ -136,7 +153,7 @@ pub fn launch_render_system(user_load_info: UserUploadInformation) {
             {
                 render_world(render_args, user_load_info, None);
             }
-        });
+        }).expect("Failed to spawn the render thread");
 
     loop
     {
@@ -165,6 +182,31 @@ pub fn launch_render_system(user_load_info: UserUploadInformation) {
                 break;
             }
 
+            if *RENDER_THREAD_SUCCESS_COUNT.lock() == FAILURE_COUNT
+            {
+                if apply_panic_policy(render_thread_panic_policy, EngineThread::Render) == PanicAction::RestartThread
+                {
+                    warn!("Restarting the render thread is not supported");
+                }
+
+                break;
+            }
+
+            if *HISTORY_THREAD_SUCCESS_COUNT.lock() == FAILURE_COUNT
+            {
+                match apply_panic_policy(history_thread_panic_policy, EngineThread::History)
+                {
+                    PanicAction::RestartThread =>
+                        {
+                            restart_history_thread(&frame_vectors, &history_condvar, &render_condvar, &history_state);
+                            history_count = *HISTORY_THREAD_SUCCESS_COUNT.lock();
+                            render_count = *RENDER_THREAD_SUCCESS_COUNT.lock();
+                            continue;
+                        },
+                    PanicAction::Exit => break
+                }
+            }
+
             match check_for_errors(history_count, render_count)
             {
                 WaitAction::Continue => {},
@@ -185,6 +227,58 @@ pub fn launch_render_system(user_load_info: UserUploadInformation) {
     }
 }
 
+/// Determines the action to take after a thread panicked, as specified by the thread's panic policy
+///
+/// `panic_policy` - the panic policy of the thread that panicked
+/// `thread` - the thread that panicked
+fn apply_panic_policy(panic_policy: PanicPolicy, thread: EngineThread) -> PanicAction
+{
+    let action = panic_policy.resolve(thread);
+    error!("{:?} thread panicked, action taken: {:?}", thread, action);
+    action
+}
+
+/// Launches a new history thread to replace one that panicked. The new thread records from a snapshot of
+/// the game state taken by the render thread, as the changes the previous thread was recording may be lost
+///
+/// `frame_vectors` - the structures holding the frame changes shared with the render thread
+/// `history_condvar` - condition variable the history thread waits on for new frame changes
+/// `render_condvar` - condition variable the render thread waits on for frame changes to be recorded
+/// `history_state` - the recorded history state
+fn restart_history_thread(frame_vectors: &FrameVectors, history_condvar: &Arc<Condvar>, render_condvar: &Arc<Condvar>, history_state: &Arc<Mutex<StoredHistoryState>>)
+{
+    let next_frame_vector_index =
+        {
+            let mut state = history_state.lock();
+            state.request_synchronization();
+            state.get_next_frame_vector_index()
+        };
+
+    let history_args = HistoryInputArgs
+    {
+        frame_vectors: frame_vectors.clone(),
+        indexer: ArrayIndexer::<2>::new(next_frame_vector_index),
+        history_condvar: history_condvar.clone(),
+        render_condvar: render_condvar.clone(),
+        state: history_state.clone()
+    };
+
+    *HISTORY_THREAD_SUCCESS_COUNT.lock() = 1;
+
+    spawn_history_thread(history_args);
+}
+
+/// Spawns the thread recording the history of the game, named so that its panics are handled by its panic policy
+///
+/// `history_args` - the structures the history thread shares with the other threads
+fn spawn_history_thread(history_args: HistoryInputArgs)
+{
+    thread::Builder::new().name(EngineThread::History.thread_name().to_string()).spawn(move ||
+        {
+            store_history(history_args);
+        }).expect("Failed to spawn the history thread");
+}
+
 fn wait_for_render_thread_to_launch(max_timeout_sec: u64) -> WaitAction
 {
     let mut time_waited_sec = 0;
@@ -268,12 +362,12 @@ fn check_for_errors(history_count: u64, render_count: u64) -> WaitAction
 
     return if error_history_thread
     {
-        println!("Error history thread");
+        error!("Error history thread");
         WaitAction::Quit
     }
     else if error_render_thread
     {
-        println!("Error render thread");
+        error!("Error render thread");
         WaitAction::Quit
     }
     else
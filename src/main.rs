@@ -1,11 +1,15 @@
+use std::sync::Arc;
 use nalgebra_glm::vec3;
 use render_engine::exports::camera_object::CameraBuilder;
+use render_engine::exports::command_line::apply_command_line_arguments;
 use render_engine::exports::load_models::UserUploadInformation;
+use render_engine::exports::logging::install_default_log_subscriber;
 use render_engine::exports::logic_components::{CollisionLogic, EntityLogic};
 use render_engine::exports::rendering::DrawParam;
 use render_engine::launch_render_system;
 use render_engine::world::bounding_volumes::aabb::StaticAABB;
 use render_engine::world::dimension::range::{XRange, YRange, ZRange};
+use tracing::warn;
 use crate::space_logic::helper_functionality::directory_lookup::get_model_texture_dir;
 use crate::space_logic::render_systems::render_system_setup::load_render_systems;
 use crate::space_logic::solar_system::asteroid::create_asteroid;
@@ -29,8 +33,8 @@ fn main()
         .with_far_draw_distance(draw_distance)
         .with_movement_speed_factor(60.0).build();
 
-    let user_collision_function = CollisionLogic{ logic: collision_logic };
-    let user_logic_function = EntityLogic{ logic: per_frame_logic };
+    let user_collision_function = CollisionLogic::new(collision_logic);
+    let user_logic_function = EntityLogic::new(per_frame_logic);
 
     let aabb_half_size = 5.0;
     let user_aabb = StaticAABB::new
@@ -40,7 +44,7 @@ fn main()
             ZRange::new(-aabb_half_size, aabb_half_size)
         );
 
-    let mut user_upload_information = UserUploadInformation::new(camera, shadow_draw_fn, shadow_light_draw_fn, shadow_transparency_draw_fn,
+    let mut user_upload_information = UserUploadInformation::new(camera, Arc::new(shadow_draw_fn), Arc::new(shadow_light_draw_fn), Arc::new(shadow_transparency_draw_fn),
                                                                  get_model_texture_dir(), user_collision_function, user_logic_function, user_aabb,
                                                                  create_user_logic());
     user_upload_information.max_fps = 60;
@@ -52,6 +56,14 @@ fn main()
 
     user_upload_information.is_debugging = false;
 
+    // Installed before the render system is launched so that problems with the arguments are logged
+    install_default_log_subscriber();
+
+    if let Err(err) = apply_command_line_arguments(&mut user_upload_information)
+    {
+        warn!("Ignoring command line arguments: {:?}", err);
+    }
+
     load_render_systems(&mut user_upload_information, draw_distance);
     create_star(&mut user_upload_information);
     create_asteroid(&mut user_upload_information);
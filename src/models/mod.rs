@@ -1,2 +1,3 @@
 pub mod model_definitions;
+pub mod model_file_watcher;
 pub mod model_storage;
\ No newline at end of file
@@ -1,5 +1,6 @@
-use nalgebra_glm::{TVec3, TVec4};
+use nalgebra_glm::{TVec3, TVec4, vec3};
 use serde::{Serialize, Deserialize};
+use tracing::warn;
 use crate::exports::logic_components::RenderSystemIndex;
 use crate::exports::rendering::LevelOfView;
 use crate::world::bounding_volumes::aabb::StaticAABB;
@@ -41,7 +42,7 @@ impl ModelId
                 },
             None =>
                 {
-                    eprintln!("Invalid distance ({}) specified for level of views: {:?}", distance, level_of_views);
+                    warn!("Invalid distance ({}) specified for level of views: {:?}", distance, level_of_views);
                     ModelId::apply_level_of_view(&mut id.model_index, NUMBER_MODEL_LEVEL_OF_VIEWS as u32 - 1);
                     id
                 }
@@ -57,6 +58,12 @@ impl ModelId
         // There are 8 possible level of views, which corresponds to an index of max 7
         *id |= level_of_view_index.min(NUMBER_MODEL_LEVEL_OF_VIEWS - 1) << 25;
     }
+
+    /// Get the model ID without the level of view index, which is the ID the model was registered with
+    pub fn without_level_of_view(&self) -> ModelId
+    {
+        ModelId{ model_index: self.model_index & ((1 << 25) - 1), render_system_index: self.render_system_index }
+    }
 }
 
 /// Holds rendering information used to render the model as well as interact with it logically
@@ -79,6 +86,9 @@ const DISSOLVE_INDEX: u128 = 1;
 const NORMAL_INDEX: u128 = 2;
 const SHININESS_INDEX: u128 = 3;
 const SPECULAR_INDEX: u128 = 4;
+const METALLIC_INDEX: u128 = 5;
+const ROUGHNESS_INDEX: u128 = 6;
+const OCCLUSION_INDEX: u128 = 7;
 
 const SIZE_TEXTURE_BITS: u128 = 16;
 const SIZE_TEXTURE_INDEX_OFFSET: u128 = 10;
@@ -93,8 +103,8 @@ macro_rules! texture_implement
            self.clear_array_index($texture_type);
            self.clear_index_offset($texture_type);
 
-           unsafe { *(self.data.as_mut_ptr() as *mut u128) |= (array_index as u128) << $texture_type * SIZE_TEXTURE_BITS  + SIZE_TEXTURE_INDEX_OFFSET; }
-           unsafe { *(self.data.as_mut_ptr() as *mut u128) |= (offset_index as u128) << $texture_type * SIZE_TEXTURE_BITS; }
+           self.modify_packed(|x| *x |= (array_index as u128) << $texture_type * SIZE_TEXTURE_BITS  + SIZE_TEXTURE_INDEX_OFFSET);
+           self.modify_packed(|x| *x |= (offset_index as u128) << $texture_type * SIZE_TEXTURE_BITS);
         }
     };
 }
@@ -116,6 +126,10 @@ impl TextureLocation
         texture_location.write_normal(0, 2);
         texture_location.write_shininess(0, 3);
         texture_location.write_specular(0, 4);
+
+        // The maps of physically based materials are optional, so they are left pointing to the start of
+        // the error texture array. Shaders treat a map in the error texture array as not being present and
+        // use the factors of the material instead
         texture_location
     }
 
@@ -124,6 +138,9 @@ impl TextureLocation
     texture_implement!(write_normal, NORMAL_INDEX);
     texture_implement!(write_shininess, SHININESS_INDEX);
     texture_implement!(write_specular, SPECULAR_INDEX);
+    texture_implement!(write_metallic, METALLIC_INDEX);
+    texture_implement!(write_roughness, ROUGHNESS_INDEX);
+    texture_implement!(write_occlusion, OCCLUSION_INDEX);
 
     /// Resets the array index of a texture type to 0, allowing future bitwise operations to write
     /// a new array index to be correct. This called only internally, in the write* functions implemented
@@ -133,7 +150,7 @@ impl TextureLocation
     fn clear_array_index(&mut self, array_offset: u128)
     {
         let clear_pattern = 0xFC00 as u128;
-        unsafe{ *(self.data.as_mut_ptr() as *mut u128) &= !(clear_pattern << array_offset * SIZE_TEXTURE_BITS) }
+        self.modify_packed(|x| *x &= !(clear_pattern << array_offset * SIZE_TEXTURE_BITS));
     }
 
     /// Resets the index offset of a texture type to 0, allowing future bitwise operations to write
@@ -144,7 +161,23 @@ impl TextureLocation
     fn clear_index_offset(&mut self, index_offset: u128)
     {
         let clear_pattern = 0x3FF;
-        unsafe{ *(self.data.as_mut_ptr() as *mut u128) &= !(clear_pattern << index_offset * SIZE_TEXTURE_BITS) }
+        self.modify_packed(|x| *x &= !(clear_pattern << index_offset * SIZE_TEXTURE_BITS));
+    }
+
+    /// Changes the texture locations as a single integer holding all of the texture types. The data is
+    /// only aligned to a u32, so it is read and written unaligned
+    ///
+    /// `modify` - the change to make to the integer holding the texture locations
+    fn modify_packed<F: FnOnce(&mut u128)>(&mut self, modify: F)
+    {
+        let packed_data = self.data.as_mut_ptr() as *mut u128;
+
+        unsafe
+            {
+                let mut packed = packed_data.read_unaligned();
+                modify(&mut packed);
+                packed_data.write_unaligned(packed);
+            }
     }
 }
 
@@ -157,6 +190,79 @@ pub struct MeshGeometry
     pub normals: Vec<TVec3<f32>>,
     pub texture_coords: Vec<TVec4<f32>>,
     pub texture_location: Vec<TextureLocation>,
+    pub tangents: Vec<TVec3<f32>>,
+    pub bitangents: Vec<TVec3<f32>>,
+}
+
+impl MeshGeometry
+{
+    /// Calculates the tangent and bitangent of every vertex from the texture coordinates of the triangles
+    /// using the vertex. The tangent points along increasing u and the bitangent along increasing v, both
+    /// perpendicular to the normal of the vertex. Meshes without normals are left without a tangent space
+    pub fn calculate_tangent_space(&mut self)
+    {
+        self.tangents.clear();
+        self.bitangents.clear();
+
+        if self.normals.len() != self.vertices.len() || self.texture_coords.len() != self.vertices.len()
+        {
+            return;
+        }
+
+        let mut tangents = vec![vec3(0.0, 0.0, 0.0); self.vertices.len()];
+        let mut bitangents = vec![vec3(0.0, 0.0, 0.0); self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3)
+        {
+            let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+
+            if a.max(b).max(c) >= self.vertices.len()
+            {
+                continue;
+            }
+
+            let first_edge = self.vertices[b] - self.vertices[a];
+            let second_edge = self.vertices[c] - self.vertices[a];
+            let first_uv = self.texture_coords[b].xy() - self.texture_coords[a].xy();
+            let second_uv = self.texture_coords[c].xy() - self.texture_coords[a].xy();
+
+            // Triangles whose texture coordinates do not span an area have no defined tangent direction
+            let determinant = first_uv.x * second_uv.y - second_uv.x * first_uv.y;
+            if determinant.abs() <= f32::EPSILON
+            {
+                continue;
+            }
+
+            let tangent = (first_edge * second_uv.y - second_edge * first_uv.y) / determinant;
+            let bitangent = (second_edge * first_uv.x - first_edge * second_uv.x) / determinant;
+
+            for index in [a, b, c]
+            {
+                tangents[index] += tangent;
+                bitangents[index] += bitangent;
+            }
+        }
+
+        for ((normal, tangent), bitangent) in self.normals.iter().zip(tangents.iter()).zip(bitangents.iter())
+        {
+            let normal = normal.normalize();
+            let mut orthogonal_tangent = tangent - normal * normal.dot(tangent);
+
+            // Vertices not used by a triangle with texture coordinates still need a tangent perpendicular to
+            // their normal, otherwise the tangent basis in the shaders is degenerate
+            if orthogonal_tangent.norm() <= f32::EPSILON
+            {
+                let axis = if normal.x.abs() < 0.9 { vec3(1.0, 0.0, 0.0) } else { vec3(0.0, 1.0, 0.0) };
+                orthogonal_tangent = axis - normal * normal.dot(&axis);
+            }
+
+            let orthogonal_tangent = orthogonal_tangent.normalize();
+            let handedness = if normal.cross(&orthogonal_tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+
+            self.tangents.push(orthogonal_tangent);
+            self.bitangents.push(normal.cross(&orthogonal_tangent) * handedness);
+        }
+    }
 }
 
 /// Collection of mesh rendering information to render a model
@@ -176,7 +282,9 @@ pub struct OriginalAABB
 #[cfg(test)]
 mod tests
 {
-    use crate::models::model_definitions::{TextureLocation, DIFFUSE_INDEX, DISSOLVE_INDEX, NORMAL_INDEX, SHININESS_INDEX, SPECULAR_INDEX};
+    use nalgebra_glm::{vec3, vec4};
+    use crate::models::model_definitions::{MeshGeometry, TextureLocation, DIFFUSE_INDEX, DISSOLVE_INDEX, NORMAL_INDEX, SHININESS_INDEX, SPECULAR_INDEX,
+                                              METALLIC_INDEX, ROUGHNESS_INDEX, OCCLUSION_INDEX};
 
     /// Finds the array index and index offset for one of the TextureLocation's array indexes.
     /// The returned values are (current_array_index, current_index_offset, other_array_index, other_index_offset).
@@ -347,4 +455,112 @@ mod tests
         assert_eq!(other_array, 0);
         assert_eq!(other_index, 0);
     }
+
+    #[test]
+    fn pack_unpack_metallic_texture()
+    {
+        let mut texture_location = TextureLocation::place_holder();
+        texture_location.write_metallic(2, 1000);
+
+        check_first_default_index_value(&texture_location);
+        check_second_default_index_value(&texture_location);
+        check_third_default_index_value(&texture_location);
+
+        let (array_index, index_offset, other_array, other_index) =
+            unpack_texture(texture_location.data[2],METALLIC_INDEX);
+
+        assert_eq!(array_index, 2);
+        assert_eq!(index_offset, 1000);
+        assert_eq!(other_array, 0);
+        assert_eq!(other_index, 4);
+    }
+
+    #[test]
+    fn pack_unpack_roughness_and_occlusion_textures()
+    {
+        let mut texture_location = TextureLocation::place_holder();
+        texture_location.write_roughness(1, 17);
+        texture_location.write_occlusion(63, 1023);
+
+        check_first_default_index_value(&texture_location);
+        check_second_default_index_value(&texture_location);
+        check_third_default_index_value(&texture_location);
+
+        let (array_index, index_offset, other_array, other_index) =
+            unpack_texture(texture_location.data[3],ROUGHNESS_INDEX);
+
+        assert_eq!(array_index, 1);
+        assert_eq!(index_offset, 17);
+        assert_eq!(other_array, 63);
+        assert_eq!(other_index, 1023);
+
+        // Writing the occlusion again clears the previous value rather than combining with it
+        texture_location.write_occlusion(4, 8);
+
+        let (array_index, index_offset, other_array, other_index) =
+            unpack_texture(texture_location.data[3],OCCLUSION_INDEX);
+
+        assert_eq!(array_index, 4);
+        assert_eq!(index_offset, 8);
+        assert_eq!(other_array, 1);
+        assert_eq!(other_index, 17);
+    }
+
+    /// Creates a quad in the XY plane facing along positive Z
+    ///
+    /// `max_u` - the u texture coordinate of the right side of the quad
+    fn create_quad(max_u: f32) -> MeshGeometry
+    {
+        MeshGeometry
+        {
+            vertices: vec![vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0), vec3(1.0, 1.0, 0.0), vec3(0.0, 1.0, 0.0)],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            normals: vec![vec3(0.0, 0.0, 1.0); 4],
+            texture_coords: vec![vec4(0.0, 0.0, 1.0, 1.0), vec4(max_u, 0.0, 1.0, 1.0), vec4(max_u, 1.0, 1.0, 1.0), vec4(0.0, 1.0, 1.0, 1.0)],
+            texture_location: vec![TextureLocation::place_holder(); 4],
+            tangents: Vec::new(),
+            bitangents: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn calculate_quad_tangent_space()
+    {
+        let mut mesh = create_quad(1.0);
+        mesh.calculate_tangent_space();
+
+        assert_eq!(mesh.tangents.len(), 4);
+        assert_eq!(mesh.bitangents.len(), 4);
+
+        for (tangent, bitangent) in mesh.tangents.iter().zip(mesh.bitangents.iter())
+        {
+            assert!((tangent - vec3(1.0, 0.0, 0.0)).norm() < 0.0001);
+            assert!((bitangent - vec3(0.0, 1.0, 0.0)).norm() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn mirrored_texture_coords_flip_tangent()
+    {
+        let mut mesh = create_quad(-1.0);
+        mesh.calculate_tangent_space();
+
+        // Mirroring u reverses the tangent while the bitangent still follows increasing v
+        for (tangent, bitangent) in mesh.tangents.iter().zip(mesh.bitangents.iter())
+        {
+            assert!((tangent - vec3(-1.0, 0.0, 0.0)).norm() < 0.0001);
+            assert!((bitangent - vec3(0.0, 1.0, 0.0)).norm() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn missing_normals_skip_tangent_space()
+    {
+        let mut mesh = create_quad(1.0);
+        mesh.normals.clear();
+        mesh.calculate_tangent_space();
+
+        assert!(mesh.tangents.is_empty());
+        assert!(mesh.bitangents.is_empty());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use crate::models::model_definitions::ModelId;
+use crate::models::model_storage::LoadModelInfo;
+
+/// How often the watched model files are checked for modifications
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Files are watched by comparing their modification times, which are read at most every POLL_INTERVAL to
+// keep the cost of watching many models low
+
+/// A model file being watched for modifications
+struct WatchedModelFile
+{
+    model_id: ModelId,
+    level_of_view: usize,
+    location: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+/// Watches the files of uploaded models and reports the models whose files changed
+pub struct ModelFileWatcher
+{
+    watched_files: Vec<WatchedModelFile>,
+    last_poll: Instant,
+}
+
+impl ModelFileWatcher
+{
+    /// Creates a watcher that is not watching any files
+    pub fn new() -> ModelFileWatcher
+    {
+        ModelFileWatcher{ watched_files: Vec::new(), last_poll: Instant::now() }
+    }
+
+    /// Watches the files of all level of views of the given model. If the model was already being
+    /// watched, its previous files are no longer watched
+    ///
+    /// `model_id` - the ID the model was registered with
+    /// `model_info` - the model information the model was registered with
+    pub fn watch<T: Into<String>>(&mut self, model_id: ModelId, model_info: &LoadModelInfo<T>)
+    {
+        self.watched_files.retain(|x| x.model_id != model_id);
+
+        for (level_of_view, location) in model_info.location.iter().enumerate()
+        {
+            self.watched_files.push(WatchedModelFile
+            {
+                model_id,
+                level_of_view,
+                location: location.clone(),
+                last_modified: ModelFileWatcher::modification_time(location),
+            });
+        }
+    }
+
+    /// Get the models and the level of views whose files were modified since they were last checked. An
+    /// empty list is returned if the files were checked recently
+    pub fn poll(&mut self) -> Vec<(ModelId, usize)>
+    {
+        if self.last_poll.elapsed() < POLL_INTERVAL
+        {
+            return Vec::new();
+        }
+
+        self.last_poll = Instant::now();
+
+        let mut modified_files = Vec::new();
+
+        for x in &mut self.watched_files
+        {
+            let last_modified = ModelFileWatcher::modification_time(&x.location);
+
+            // A file that cannot be read, such as while an editor is writing it, is checked again later
+            if last_modified.is_some() && last_modified != x.last_modified
+            {
+                x.last_modified = last_modified;
+                modified_files.push((x.model_id, x.level_of_view));
+            }
+        }
+
+        modified_files
+    }
+
+    /// Get the time the given file was last modified, if it can be read
+    ///
+    /// `location` - the file to check
+    fn modification_time(location: &PathBuf) -> Option<SystemTime>
+    {
+        fs::metadata(location).and_then(|x| x.modified()).ok()
+    }
+}
@@ -2,7 +2,9 @@ use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use hashbrown::HashMap;
 use nalgebra_glm::{TVec4, vec3, vec4};
+use tracing::{debug, error};
 use crate::exports::logic_components::RenderSystemIndex;
+use crate::exports::model_import::ModelImportSettings;
 use crate::exports::rendering::LevelOfView;
 use crate::flows::render_flow::RenderFlow;
 use crate::helper_things::aabb_helper_functions;
@@ -19,6 +21,8 @@ pub struct ModelBankOwner
     model_banks: Vec<ModelBank>,
     free_ids: Vec<ModelId>,
     number_models_loaded: usize,
+    model_textures: HashMap<ModelId, Vec<UploadedTextureLocation>>,
+    released_textures: Vec<(RenderSystemIndex, UploadedTextureLocation)>,
 }
 
 /// Holds uploaded models for a render system
@@ -37,7 +41,8 @@ pub struct LoadModelInfo<T: Into<String>>
     pub location: Vec<PathBuf>,
     pub custom_level_of_view: Option<Vec<LevelOfView>>,
     pub model_texture_dir: PathBuf,
-    pub solid_colour_texture: Option<TVec4<u8>>
+    pub solid_colour_texture: Option<TVec4<u8>>,
+    pub import_settings: Option<ModelImportSettings>,
 }
 
 /// This macro uploads different type of textures used by the model into the render system and creates
@@ -86,6 +91,58 @@ macro_rules! use_texture_type
     }};
 }
 
+/// The maps of a material that are uploaded to a render system. A map that is not uploaded is empty
+struct MaterialTextures
+{
+    diffuse_texture: String,
+    normal_texture: String,
+    metallic_texture: String,
+    roughness_texture: String,
+    occlusion_texture: String,
+}
+
+impl MaterialTextures
+{
+    /// Finds the maps of the material to upload. Render systems using physically based materials read the
+    /// normal, metallic, roughness and ambient occlusion maps as well as the diffuse map. The metallic and
+    /// roughness maps are given with the map_Pm and map_Pr statements of the .mtl file, and the ambient
+    /// occlusion map with map_Ka
+    ///
+    /// `material` - the material loaded from the .mtl file, with the texture directory already prepended
+    /// `material_maps` - true if all of the maps are read, rather than only the diffuse map
+    /// `texture_dir` - the directory holding the textures of the model
+    fn new(material: &tobj::Material, material_maps: bool, texture_dir: &PathBuf) -> MaterialTextures
+    {
+        if !material_maps
+        {
+            return MaterialTextures
+            {
+                diffuse_texture: material.diffuse_texture.clone(),
+                normal_texture: String::new(),
+                metallic_texture: String::new(),
+                roughness_texture: String::new(),
+                occlusion_texture: String::new(),
+            };
+        }
+
+        let unknown_texture = |name: &str|
+            {
+                let mut texture = material.unknown_param.get(name).cloned().unwrap_or_default();
+                append_texture_dir(&mut texture, texture_dir);
+                texture
+            };
+
+        MaterialTextures
+        {
+            diffuse_texture: material.diffuse_texture.clone(),
+            normal_texture: material.normal_texture.clone(),
+            metallic_texture: unknown_texture("map_Pm"),
+            roughness_texture: unknown_texture("map_Pr"),
+            occlusion_texture: material.ambient_texture.clone(),
+        }
+    }
+}
+
 fn append_texture_dir(texture: &mut String, texture_dir: &PathBuf)
 {
     if !texture.is_empty()
@@ -102,7 +159,21 @@ impl ModelBankOwner
     ///                          banks are created
     pub fn new(number_render_systems: usize) -> ModelBankOwner
     {
-        ModelBankOwner{ name_model_lookup: HashMap::default(), model_banks: (0..number_render_systems).into_iter().map(|_| ModelBank::new()).collect(), number_models_loaded: 0, free_ids: Vec::new() }
+        ModelBankOwner
+        {
+            name_model_lookup: HashMap::default(),
+            model_banks: (0..number_render_systems).into_iter().map(|_| ModelBank::new()).collect(),
+            number_models_loaded: 0,
+            free_ids: Vec::new(),
+            model_textures: HashMap::default(),
+            released_textures: Vec::new(),
+        }
+    }
+
+    /// Get the number of model banks, which is the number of render systems models can be uploaded to
+    pub fn number_model_banks(&self) -> usize
+    {
+        self.model_banks.len()
     }
 
     /// Get information about the stored model
@@ -113,15 +184,14 @@ impl ModelBankOwner
         self.model_banks[model_id.render_system_index.index].models.get(&model_id)
     }
 
-    fn upload_model_geometry_solid_texture<A: AsRef<Path> + Debug + Clone>(&mut self, location: A, render_system_index: u32, model_id: ModelId, render_flow: &mut RenderFlow, colour: TVec4<u8>)
+    fn upload_model_geometry_solid_texture<A: AsRef<Path> + Debug + Clone>(&mut self, location: A, render_system_index: u32, model_id: ModelId, render_flow: &mut RenderFlow, colour: TVec4<u8>, import_settings: &Option<ModelImportSettings>) -> Result<Vec<UploadedTextureLocation>, tobj::LoadError>
     {
         let uploaded_texture = render_flow.add_solid_colour_texture(RenderSystemIndex{ index: render_system_index as usize}, colour);
         let mut texture_location = TextureLocation::place_holder();
         texture_location.write_diffuse(uploaded_texture.array_index, uploaded_texture.index_offset);
-println!("Loaded: {:?}", location.as_ref());
-        let (mut models, _) = tobj::load_obj(location, true).unwrap();
+debug!("Loaded: {:?}", location.as_ref());
+        let (mut models, _) = tobj::load_obj(location, true)?;
         let mut model_geometry = Vec::new();
-        let mut model_aabb = StaticAABB::point_aabb();
 
         // Load and store all of the rendering information
         for x in models.iter_mut()
@@ -146,9 +216,6 @@ println!("Loaded: {:?}", location.as_ref());
                 normals.push(normal);
             }
 
-            // Combine all of the mesh AABB to find the overall bounding volume of the model
-            model_aabb = model_aabb.combine_aabb(&aabb_helper_functions::calculate_aabb(&vertices));
-
             model_geometry.push(MeshGeometry
             {
                 texture_location: vec![texture_location.clone(); vertices.len()],
@@ -156,11 +223,16 @@ println!("Loaded: {:?}", location.as_ref());
                 indices,
                 normals,
                 texture_coords,
+                tangents: Vec::new(),
+                bitangents: Vec::new(),
             });
 
         }
 
+        let model_aabb = ModelBankOwner::adjust_imported_geometry(&mut model_geometry, import_settings);
         self.model_banks[render_system_index as usize].add_model(model_id, ModelGeometry{ meshes: model_geometry }, model_aabb);
+
+        Ok(vec![uploaded_texture])
     }
 
     /// Upload model geometry and textures to the given render system
@@ -169,9 +241,17 @@ println!("Loaded: {:?}", location.as_ref());
     /// `render_system_index` - the index of the render system to upload the model to
     /// `model_id` - the ID of the model to upload
     /// `render_flow` - instance of render flow that owns the render systems
-    fn upload_model_geometry<A: AsRef<Path> + Debug + Clone>(&mut self, location: A, render_system_index: u32, model_id: ModelId, render_flow: &mut RenderFlow, texture_dir: &PathBuf)
+    /// `texture_dir` - the directory holding the textures of the model
+    /// `import_settings` - the adjustments to apply to the model after it is loaded
+    fn upload_model_geometry<A: AsRef<Path> + Debug + Clone>(&mut self, location: A, render_system_index: u32, model_id: ModelId, render_flow: &mut RenderFlow, texture_dir: &PathBuf,
+                                                             import_settings: &Option<ModelImportSettings>) -> Result<Vec<UploadedTextureLocation>, tobj::LoadError>
     {
-        let (mut models, mut materials) = tobj::load_obj(location, true).unwrap();
+        let (mut models, mut materials) = tobj::load_obj(location, true)?;
+
+        if let Some(import_settings) = import_settings
+        {
+            import_settings.apply_to_materials(&mut materials);
+        }
 
         for x in &mut materials
         {
@@ -183,17 +263,23 @@ println!("Loaded: {:?}", location.as_ref());
             append_texture_dir(&mut x.specular_texture, &texture_dir);
         }
 
+        let material_maps = render_flow.uses_material_maps(RenderSystemIndex{ index: render_system_index as usize });
+        let material_textures: Vec<MaterialTextures> = materials.iter().map(|x| MaterialTextures::new(x, material_maps, texture_dir)).collect();
+
         // Upload the textures to the render system and create the texture locations to index into
         // texture arrays in the shaders
         let (material_location, texture_location) =
 
-            // At time of writing, only diffuse textures are used. To add others, follow same pattern
-            // of input to macro as diffuse. For example:  dissolve_texture, write_dissolve
-            use_texture_type!(materials, render_flow, render_system_index,
-                         diffuse_texture, write_diffuse);
+            // At time of writing, dissolve, shininess and specular textures are not used. To add others,
+            // follow same pattern of input to macro as diffuse. For example:  dissolve_texture, write_dissolve
+            use_texture_type!(material_textures, render_flow, render_system_index,
+                         diffuse_texture, write_diffuse,
+                         normal_texture, write_normal,
+                         metallic_texture, write_metallic,
+                         roughness_texture, write_roughness,
+                         occlusion_texture, write_occlusion);
 
         let mut model_geometry = Vec::new();
-        let mut model_aabb = StaticAABB::point_aabb();
 
         // Load and store all of the rendering information
         for x in models.iter_mut()
@@ -248,9 +334,6 @@ println!("Loaded: {:?}", location.as_ref());
                 }
             }
 
-            // Combine all of the mesh AABB to find the overall bounding volume of the model
-            model_aabb = model_aabb.combine_aabb(&aabb_helper_functions::calculate_aabb(&vertices));
-
             model_geometry.push(MeshGeometry
             {
                 texture_location: vec![texture_location.clone(); vertices.len()],
@@ -258,11 +341,43 @@ println!("Loaded: {:?}", location.as_ref());
                 indices,
                 normals,
                 texture_coords,
+                tangents: Vec::new(),
+                bitangents: Vec::new(),
             });
 
         }
 
+        let model_aabb = ModelBankOwner::adjust_imported_geometry(&mut model_geometry, import_settings);
         self.model_banks[render_system_index as usize].add_model(model_id, ModelGeometry{ meshes: model_geometry }, model_aabb);
+
+        Ok(material_location.values()
+            .flat_map(|x| vec![x.diffuse_texture, x.normal_texture, x.metallic_texture, x.roughness_texture, x.occlusion_texture])
+            .flatten()
+            .collect())
+    }
+
+    /// Applies the import settings of a model to its loaded meshes, and calculates the bounding volume of
+    /// the adjusted model
+    ///
+    /// `model_geometry` - the meshes of the loaded model
+    /// `import_settings` - the adjustments to apply to the model
+    fn adjust_imported_geometry(model_geometry: &mut [MeshGeometry], import_settings: &Option<ModelImportSettings>) -> StaticAABB
+    {
+        if let Some(import_settings) = import_settings
+        {
+            import_settings.apply_to_meshes(model_geometry);
+        }
+
+        // Tangents depend on the final vertices and texture coordinates, so they are found after the import
+        // settings change the geometry
+        for mesh in model_geometry.iter_mut()
+        {
+            mesh.calculate_tangent_space();
+        }
+
+        // Combine all of the mesh AABB to find the overall bounding volume of the model
+        model_geometry.iter()
+            .fold(StaticAABB::point_aabb(), |model_aabb, x| model_aabb.combine_aabb(&aabb_helper_functions::calculate_aabb(&x.vertices)))
     }
 
     fn get_model_id(&mut self, render_system_index: RenderSystemIndex) -> ModelId
@@ -283,6 +398,25 @@ println!("Loaded: {:?}", location.as_ref());
         self.name_model_lookup.get(name)
     }
 
+    /// Get the names of the uploaded models along with their IDs
+    pub fn model_names(&self) -> Vec<(String, ModelId)>
+    {
+        self.name_model_lookup.iter().map(|(name, model_id)| (name.clone(), *model_id)).collect()
+    }
+
+    /// Sets the number of instances of every model to zero, without removing any model. Used when the
+    /// entities are replaced, after which the instances of the new entities are registered
+    pub fn reset_instance_counts(&mut self)
+    {
+        for model_bank in &mut self.model_banks
+        {
+            for model in model_bank.models.values_mut()
+            {
+                model.instance_count = 0;
+            }
+        }
+    }
+
     /// Create a model ID for the given model and upload its rendering information to the desired
     /// render system. After this call, instances of this model can be created
     ///
@@ -302,22 +436,9 @@ println!("Loaded: {:?}", location.as_ref());
         // Upload all of the rendering geometry for the different level of views
         for x in 0..model_info.location.len()
         {
-            let adjusted_model_id =
-                {
-                    let mut copy_model_id = base_model_id;
-                    ModelId::apply_level_of_view(&mut copy_model_id.model_index, x as u32);
-                    copy_model_id
-                };
-
-            if let Some(colour) = model_info.solid_colour_texture
-            {
-                self.upload_model_geometry_solid_texture(model_info.location[x].clone(), model_info.render_system_index.index as u32,
-                                                         adjusted_model_id, render_flow, colour);
-            }
-            else
+            if let Err(err) = self.upload_level_of_view(model_info, x, base_model_id, render_flow)
             {
-                self.upload_model_geometry(model_info.location[x].clone(), model_info.render_system_index.index as u32,
-                                           adjusted_model_id, render_flow, &model_info.model_texture_dir);
+                panic!("Failed to load the model {:?}: {:?}", model_info.location[x], err);
             }
         }
 
@@ -326,6 +447,67 @@ println!("Loaded: {:?}", location.as_ref());
         base_model_id
     }
 
+    /// Reloads the rendering information of one level of view of an uploaded model from its file, such as
+    /// after the file was modified. The instances of the model are kept. False is returned if the model no
+    /// longer exists or its file could not be loaded, in which case the model is left as it was
+    ///
+    /// `model_id` - the ID the model was registered with
+    /// `model_info` - the model information the model was registered with
+    /// `level_of_view` - the index of the level of view to reload
+    /// `render_flow` - owners of all of the render systems
+    pub fn reload_model<T: Into<String> + Clone>(&mut self, model_id: ModelId, model_info: &LoadModelInfo<T>, level_of_view: usize, render_flow: &mut RenderFlow) -> bool
+    {
+        if self.get_model_info(model_id).is_none()
+        {
+            return false;
+        }
+
+        if let Err(err) = self.upload_level_of_view(model_info, level_of_view, model_id, render_flow)
+        {
+            error!("Failed to reload the model {:?}: {:?}", model_info.location[level_of_view], err);
+            return false;
+        }
+
+        true
+    }
+
+    /// Uploads the rendering information of one level of view of a model to its render system. Textures
+    /// used by a previous upload of the same level of view are released
+    ///
+    /// `model_info` - the model information the model was registered with
+    /// `level_of_view` - the index of the level of view to upload
+    /// `base_model_id` - the ID the model was registered with
+    /// `render_flow` - owners of all of the render systems
+    fn upload_level_of_view<T: Into<String> + Clone>(&mut self, model_info: &LoadModelInfo<T>, level_of_view: usize, base_model_id: ModelId, render_flow: &mut RenderFlow) -> Result<(), tobj::LoadError>
+    {
+        let adjusted_model_id =
+            {
+                let mut copy_model_id = base_model_id;
+                ModelId::apply_level_of_view(&mut copy_model_id.model_index, level_of_view as u32);
+                copy_model_id
+            };
+
+        let uploaded_textures = if let Some(colour) = model_info.solid_colour_texture
+        {
+            self.upload_model_geometry_solid_texture(model_info.location[level_of_view].clone(), model_info.render_system_index.index as u32,
+                                                     adjusted_model_id, render_flow, colour, &model_info.import_settings)?
+        }
+        else
+        {
+            self.upload_model_geometry(model_info.location[level_of_view].clone(), model_info.render_system_index.index as u32,
+                                       adjusted_model_id, render_flow, &model_info.model_texture_dir, &model_info.import_settings)?
+        };
+
+        // The new textures were referenced before the previous textures are released, so textures used by
+        // both uploads are not freed
+        if let Some(previous_textures) = self.model_textures.insert(adjusted_model_id, uploaded_textures)
+        {
+            self.released_textures.extend(previous_textures.into_iter().map(|x| (base_model_id.render_system_index, x)));
+        }
+
+        Ok(())
+    }
+
     /// Determines if the models contained in the model bank associated with the given render system
     /// needs to be reuploaded
     ///
@@ -375,6 +557,14 @@ println!("Loaded: {:?}", location.as_ref());
         &self.model_banks[render_system_index.index]
     }
 
+    /// Gets the model bank for the render system with the given name
+    ///
+    /// `render_system_name` - the name of the render system that the returned model bank is associated with
+    pub fn get_model_bank_by_name(&self, render_system_name: &str) -> Option<&ModelBank>
+    {
+        RenderSystemIndex::from_name(render_system_name).map(|x| self.get_model_bank(x))
+    }
+
     /// Register instances of the given model
     ///
     /// `model_id` - the ID of the model to register instances
@@ -392,8 +582,29 @@ println!("Loaded: {:?}", location.as_ref());
         if self.model_banks[model_id.render_system_index.index].remove_instance(model_id)
         {
             self.free_ids.push(model_id);
+
+            // The textures of all level of views are released together once the model is removed
+            let released_textures = &mut self.released_textures;
+
+            self.model_textures.retain(|id, textures|
+                {
+                    if id.without_level_of_view() != model_id
+                    {
+                        return true;
+                    }
+
+                    released_textures.extend(textures.drain(..).map(|x| (model_id.render_system_index, x)));
+                    false
+                });
         }
     }
+
+    /// Get the textures of the models that were removed since this function was last called. The render
+    /// systems the textures were uploaded to must release them so that their storage can be reused
+    pub fn take_released_textures(&mut self) -> Vec<(RenderSystemIndex, UploadedTextureLocation)>
+    {
+        std::mem::take(&mut self.released_textures)
+    }
 }
 
 impl ModelBank
@@ -417,10 +628,13 @@ impl ModelBank
     /// `aabb` - the surrounding bounding volume of the model being added
     pub fn add_model(&mut self, model_id: ModelId, geometry: ModelGeometry, aabb: StaticAABB)
     {
+        // A model that is uploaded again, such as when its file is reloaded, keeps its instances
+        let instance_count = self.models.get(&model_id).map(|x| x.instance_count).unwrap_or(0);
+
         let model_information = ModelInformation
         {
             geometry,
-            instance_count: 0,
+            instance_count,
             aabb: OriginalAABB{ aabb }
         };
 
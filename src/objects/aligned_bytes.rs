@@ -0,0 +1,129 @@
+use serde::{Serialize, Serializer};
+
+/// Alignment of the start of AlignedBytes, which is enough for the values of any component type
+pub(crate) const BYTE_ALIGNMENT: usize = 16;
+
+#[repr(C, align(16))]
+#[derive(Copy, Clone)]
+struct AlignedChunk([u8; BYTE_ALIGNMENT]);
+
+/// Growable array of bytes whose start is aligned to BYTE_ALIGNMENT, so that values of any type stored at
+/// multiples of their size can be referenced in place
+#[derive(Clone, Default)]
+pub(crate) struct AlignedBytes
+{
+    chunks: Vec<AlignedChunk>,
+    len: usize,
+}
+
+impl AlignedBytes
+{
+    /// Creates an empty array of bytes
+    pub(crate) fn new() -> AlignedBytes
+    {
+        AlignedBytes{ chunks: Vec::new(), len: 0 }
+    }
+
+    /// Creates an array holding a copy of the given bytes
+    ///
+    /// `bytes` - the bytes to copy into the array
+    pub(crate) fn from_slice(bytes: &[u8]) -> AlignedBytes
+    {
+        let mut aligned_bytes = AlignedBytes::new();
+        aligned_bytes.extend_from_slice(bytes);
+        aligned_bytes
+    }
+
+    /// Get the number of bytes in the array
+    pub(crate) fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    /// Appends a copy of the given bytes to the end of the array
+    ///
+    /// `bytes` - the bytes to append
+    pub(crate) fn extend_from_slice(&mut self, bytes: &[u8])
+    {
+        let previous_len = self.len;
+        self.resize(previous_len + bytes.len());
+        self.as_mut_slice()[previous_len..].copy_from_slice(bytes);
+    }
+
+    /// Changes the number of bytes in the array. Added bytes are zero
+    ///
+    /// `len` - the new number of bytes
+    pub(crate) fn resize(&mut self, len: usize)
+    {
+        if len > self.len
+        {
+            // Bytes past the previous length may hold old values, which are not part of the array anymore
+            let previous_len = self.len;
+            self.chunks.resize(len.div_ceil(BYTE_ALIGNMENT), AlignedChunk([0; BYTE_ALIGNMENT]));
+            self.len = len;
+            self.as_mut_slice()[previous_len..].fill(0);
+        }
+        else
+        {
+            self.chunks.truncate(len.div_ceil(BYTE_ALIGNMENT));
+            self.len = len;
+        }
+    }
+
+    /// Get a pointer to the first byte
+    pub(crate) fn as_ptr(&self) -> *const u8
+    {
+        self.chunks.as_ptr() as *const u8
+    }
+
+    /// Get a mutable pointer to the first byte
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8
+    {
+        self.chunks.as_mut_ptr() as *mut u8
+    }
+
+    /// Get the bytes of the array
+    pub(crate) fn as_slice(&self) -> &[u8]
+    {
+        unsafe
+            {
+                std::slice::from_raw_parts(self.as_ptr(), self.len)
+            }
+    }
+
+    /// Get the bytes of the array mutably
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8]
+    {
+        unsafe
+            {
+                std::slice::from_raw_parts_mut(self.as_mut_ptr(), self.len)
+            }
+    }
+}
+
+// Serialized the same way as a vector of bytes, so that data serialized before the bytes were aligned can
+// still be read
+impl Serialize for AlignedBytes
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        serializer.collect_seq(self.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::objects::aligned_bytes::{AlignedBytes, BYTE_ALIGNMENT};
+
+    #[test]
+    fn serialized_as_byte_vector()
+    {
+        let bytes = (0..40).collect::<Vec<u8>>();
+        let aligned_bytes = AlignedBytes::from_slice(&bytes);
+
+        assert_eq!(0, aligned_bytes.as_ptr() as usize % BYTE_ALIGNMENT);
+        assert_eq!(bytes.as_slice(), aligned_bytes.as_slice());
+        assert_eq!(bincode::serialize(&bytes).unwrap(), bincode::serialize(&aligned_bytes).unwrap());
+    }
+}
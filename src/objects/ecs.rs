@@ -1,10 +1,19 @@
 use std::any::TypeId;
 use std::collections::BTreeSet;
+use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
 use std::ptr::copy_nonoverlapping;
 use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 use crate::exports::light_components::{DirectionLight, PointLight, SpotLight};
+use crate::exports::prefab::{Prefab, PrefabTransform};
 use crate::models::model_definitions::ModelId;
+use crate::objects::aligned_bytes::{AlignedBytes, BYTE_ALIGNMENT};
+use crate::objects::entity_change_request::EntityChangeInformation;
+use crate::objects::ecs_query::{ColumnEntities, Query, QueryChanges, QueryChangesIter, QueryColumn, QueryIter, ReadOnlyQuery};
+use crate::objects::events::{EventQueue, Events, record_event_registration, registered_event_queues};
+use crate::objects::resources::ResourceStorage;
 use crate::objects::entity_enforcers::ForceCreationEntity;
 use crate::objects::entity_id::{EntityId, EntityIdRead};
 use crate::objects::sorted_entities::EntityComponentOrganizer;
@@ -54,32 +63,82 @@ fn calculate_byte_bit_offset(component_index: usize) -> (usize, usize)
 
 // TODO: Parallelize disjoint writes
 
-const MAX_NUMBER_COMPONENTS: usize = 32;
+// The bitsets of all entities are stored one after another. Each bitset is made of parts that represent
+// 32 components each, with an entity using as many parts as are required for the registered components.
+// Parts are kept at 32 components so that an ECS with no more than 32 components is serialized the same
+// way as before more components were supported, allowing previously recorded histories to be loaded
+
+const MAX_NUMBER_COMPONENTS: usize = 128;
+
+const COMPONENTS_PER_BITSET_PART: usize = 32;
+
+type BitsetPart = [u8; num_bytes_for_components(COMPONENTS_PER_BITSET_PART)];
 
 /// An entity-component system. Stores all of the various components types and their values for an entity
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ECS
 {
     registered_types: Vec<IndexInformation>,
-    bitsets: Vec<[u8; num_bytes_for_components(MAX_NUMBER_COMPONENTS)]>,
+    bitsets: Vec<BitsetPart>,
     entity_model_lookup: HashMap<TypeIdentifier, HashSet::<EntityId>>,
     free_indexes: Vec<usize>,
     organizer: EntityComponentOrganizer,
     max_num_components: usize,
     user_entity_id: EntityId,
     owned_entities: HashMap<EntityId, HashSet<EntityId>>,
-    referenced_entities: HashMap<EntityId, HashSet<EntityIdRead>>
+    referenced_entities: HashMap<EntityId, HashSet<EntityIdRead>>,
+    // Resources were added after histories were first recorded, and so are stored outside of the ECS
+    // to keep the layout of recorded histories
+    #[serde(skip)]
+    resources: HashMap<TypeIdentifier, ResourceStorage>,
+    // Change ticks only matter to the frame being executed, and so are not part of the recorded history
+    #[serde(skip)]
+    change_tick: u64,
+    // Events are only readable for one frame, and sending them is recorded as a change. The queues of the
+    // registered events are created again when the ECS is loaded
+    #[serde(skip, default = "registered_event_queues")]
+    events: HashMap<TypeIdentifier, EventQueue>,
 }
 
 // Stores the actual values of components. To store all of these in the same vector in self.registered_types,
 // all of the values are serialized into bytes. The index into this byte array for an entity is stored using
-// a hashmap. The index is in bytes, NOT in type 'T'
+// a hashmap. The index is in bytes, NOT in type 'T'. Every value is stored at a multiple of its size from the
+// start of the byte array, which is aligned for any component type, so values are aligned for their type
+
+// Components that are read for most entities every frame, such as the transformation matrix, can opt into
+// dense storage. Their values are kept packed one after another without the holes left by removed values,
+// with the value removed being replaced by the last value, so that all values can be iterated as one slice.
+// Whether a component is dense is not serialized, keeping recorded histories loadable; it is chosen again
+// when the ECS is created or loaded
 
 // Deleted values are kept track of, and those free chunks of bytes are used for the next value that is written
 
+// The index of every value is also kept in a vector indexed by the entity instance, so that queries find the
+// values of an entity without hashing. The vector is derived from the hashmap, and so is rebuilt rather than
+// serialized when the ECS is loaded
+
 /// Stores the value of components and keeps track which value type 'T' is being stored
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "StoredIndexInformation")]
 struct IndexInformation
+{
+    type_id: TypeIdentifier,
+    instances: AlignedBytes,
+    free_space: Vec<isize>,
+    sparse_map: HashMap<EntityId, isize>,
+    // Index of the value of each entity, or NO_VALUE_INDEX if the entity has no value
+    #[serde(skip)]
+    entity_indexes: Vec<isize>,
+    // One more than the change tick the value of each entity was last written in, or zero if it was not written
+    #[serde(skip)]
+    change_ticks: Vec<u64>,
+    #[serde(skip)]
+    dense_storage: Option<DenseStorage>,
+}
+
+/// The serialized fields of IndexInformation, from which the fields that are not serialized are rebuilt
+#[derive(Deserialize)]
+struct StoredIndexInformation
 {
     type_id: TypeIdentifier,
     instances: Vec<u8>,
@@ -87,6 +146,25 @@ struct IndexInformation
     sparse_map: HashMap<EntityId, isize>,
 }
 
+/// Entity index of an entity that does not have a value of a component
+pub(crate) const NO_VALUE_INDEX: isize = -1;
+
+/// Order of the values of a component stored densely
+#[derive(Clone)]
+struct DenseStorage
+{
+    entities: Vec<EntityId>,
+    component_size: usize,
+}
+
+/// The values of a component stored densely, along with the entity each value belongs to
+pub struct DenseComponents<'a, T>
+{
+    entities: &'a [EntityId],
+    values: &'a [T],
+    entity_indexes: &'a [isize],
+}
+
 /// Serializable version of the standard library TypeId
 #[derive(Copy, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq, Serialize, Deserialize)]
 pub struct TypeIdentifier
@@ -109,6 +187,34 @@ impl From<TypeId> for TypeIdentifier
     }
 }
 
+/// Mutable access to a component value. The component is only marked as changed once the value is
+/// accessed mutably, so that borrowing a component to read it does not report it as changed
+pub struct ComponentMut<'a, T>
+{
+    value: &'a mut T,
+    change_tick: &'a mut u64,
+    current_change_tick: u64,
+}
+
+impl<'a, T> Deref for ComponentMut<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.value
+    }
+}
+
+impl<'a, T> DerefMut for ComponentMut<'a, T>
+{
+    fn deref_mut(&mut self) -> &mut T
+    {
+        *self.change_tick = self.current_change_tick + 1;
+        self.value
+    }
+}
+
 impl ECS
 {
     /// Creates a new Entity Component System
@@ -135,7 +241,10 @@ impl ECS
             max_num_components: MAX_NUMBER_COMPONENTS,
             user_entity_id: ECS::get_temporary_entity_id(),
             owned_entities: HashMap::default(),
-            referenced_entities: HashMap::default()
+            referenced_entities: HashMap::default(),
+            resources: HashMap::default(),
+            change_tick: 0,
+            events: HashMap::default(),
         };
         ecs.register_type::<TypeIdentifier>();
         ecs.user_entity_id = ecs.create_entity();
@@ -180,6 +289,64 @@ impl ECS
         }
     }
 
+    /// Get the given entity along with every entity it owns, directly or through the entities it owns. The
+    /// owning entity is first, and the owned entities of each entity are in order of their IDs so that the
+    /// result is the same every time it is requested
+    ///
+    /// `owning` - the entity at the top of the ownership hierarchy
+    pub fn get_owned_entities_recursive(&self, owning: EntityId) -> Vec<EntityId>
+    {
+        let mut entities = vec![owning];
+        let mut visited_entities: HashSet<EntityId> = HashSet::default();
+        visited_entities.insert(owning);
+
+        let mut index = 0;
+
+        while index < entities.len()
+        {
+            if let Some(owned) = self.owned_entities.get(&entities[index])
+            {
+                let mut owned = owned.iter().copied().collect::<Vec<EntityId>>();
+                owned.sort();
+
+                for x in owned
+                {
+                    // Guard against entities owning each other
+                    if visited_entities.insert(x)
+                    {
+                        entities.push(x);
+                    }
+                }
+            }
+
+            index += 1;
+        }
+
+        entities
+    }
+
+    /// Removes the ownership and references of the given entity, as well as the ownership and references
+    /// other entities have of it. Call when the entity is deleted so that no entity refers to it afterwards
+    ///
+    /// `entity_id` - the entity whose relationships are removed
+    pub fn clear_entity_relationships(&mut self, entity_id: EntityId)
+    {
+        self.owned_entities.remove(&entity_id);
+        self.referenced_entities.remove(&entity_id);
+
+        let entity_id_read = EntityIdRead::new(entity_id);
+
+        for owned in self.owned_entities.values_mut()
+        {
+            owned.remove(&entity_id);
+        }
+
+        for referenced in self.referenced_entities.values_mut()
+        {
+            referenced.remove(&entity_id_read);
+        }
+    }
+
     pub fn get_user_id_read(&self) -> EntityIdRead
     {
         EntityIdRead::new(self.user_entity_id)
@@ -272,7 +439,7 @@ impl ECS
 
             for entity_id in smallest_entity_set
             {
-                if self.registered_types[index].sparse_map.get(&entity_id).is_some()
+                if self.registered_types[index].value_index(entity_id).is_some()
                 {
                     refined_smallest_entity_set.insert(entity_id);
                 }
@@ -284,6 +451,360 @@ impl ECS
         smallest_entity_set
     }
 
+    /// Iterates over all entities that have every queried component, yielding the entity along with
+    /// references to those components. Components can be referenced both immutably and mutably, with
+    /// the mutably referenced components being marked as changed
+    ///
+    /// ```
+    ///  struct Position(u32);
+    ///  struct Velocity(u32);
+    ///  for (entity_id, (position, velocity)) in ecs.query::<(&Position, &mut Velocity)>()
+    ///  {
+    ///     velocity.0 += position.0;
+    ///  }
+    /// ```
+    pub fn query<'a, Q: Query<'a>>(&'a mut self) -> QueryIter<'a, Q>
+    {
+        let component_indexes = self.query_component_indexes(&Q::component_types());
+        let change_tick = self.change_tick;
+        let columns = self.query_columns();
+
+        // The ECS is borrowed mutably for as long as the returned iterator is in use
+        unsafe
+            {
+                ECS::query_from_columns(&columns, &component_indexes, change_tick)
+            }
+    }
+
+    /// Get the storage of every registered component type, in the order the types were registered. The
+    /// values and change ticks are given as pointers, so that queries of different component types can be
+    /// executed at the same time, such as by systems running in parallel, without each of them borrowing
+    /// the ECS mutably. The storage is resolved once, and then only shared
+    pub(crate) fn query_columns(&mut self) -> Vec<QueryColumn<'_>>
+    {
+        self.registered_types.iter_mut()
+            .map(|index_information|
+                {
+                    // The values and change ticks are written through while the indexes are only read, so
+                    // the pointers are taken before the rest of the storage is borrowed immutably
+                    let instances = index_information.instances.as_mut_ptr();
+                    let change_ticks = index_information.change_ticks.as_mut_ptr();
+                    let index_information: &IndexInformation = index_information;
+
+                    QueryColumn{ entity_indexes: &index_information.entity_indexes, entities: index_information.column_entities(), instances, change_ticks }
+                })
+            .collect()
+    }
+
+    /// Iterates over the given columns of the ECS, as returned by query_columns
+    ///
+    /// Safety: while the returned iterator is in use, no other access can be made to the mutably queried
+    /// component types, and no components or entities can be added or removed
+    ///
+    /// `columns` - the storage of every registered component type
+    /// `component_indexes` - the index of the column of each queried component, as found by query_component_indexes
+    /// `change_tick` - the change tick mutably queried components are marked with
+    pub(crate) unsafe fn query_from_columns<'a, Q: Query<'a>>(columns: &[QueryColumn<'a>], component_indexes: &[usize], change_tick: u64) -> QueryIter<'a, Q>
+    {
+        let columns = component_indexes.iter()
+            .zip(Q::component_types().iter())
+            .map(|(index, (_, mutable))|
+                {
+                    let mut column = columns[*index];
+
+                    if !*mutable
+                    {
+                        column.change_ticks = std::ptr::null_mut();
+                    }
+
+                    column
+                })
+            .collect();
+
+        QueryIter::new(columns, change_tick)
+    }
+
+    /// Iterates over all entities that have every queried component, yielding the entity along with
+    /// references to those components. Only immutable references can be queried
+    ///
+    /// ```
+    ///  struct Position(u32);
+    ///  struct Velocity(u32);
+    ///  for (entity_id, (position, velocity)) in ecs.query_read::<(&Position, &Velocity)>()
+    ///  {
+    ///     println!("{:?} {} {}", entity_id, position.0, velocity.0);
+    ///  }
+    /// ```
+    pub fn query_read<'a, Q: ReadOnlyQuery<'a>>(&'a self) -> QueryIter<'a, Q>
+    {
+        self.query_without_changes()
+    }
+
+    /// Iterates over all entities that have every queried component without requiring a mutable ECS, such as
+    /// in the logic of entities. Mutably queried components are copies; the change requests writing the
+    /// modified copies to the ECS are taken from the given changes once the iteration is done
+    ///
+    /// ```
+    ///  struct Position(u32);
+    ///  struct Velocity(u32);
+    ///  let mut velocities = QueryChanges::new();
+    ///  for (entity_id, (position, velocity)) in ecs.query_changes::<(&Position, &mut Velocity)>(&mut velocities)
+    ///  {
+    ///     velocity.0 += position.0;
+    ///  }
+    ///  changes.extend(velocities.take_changes());
+    /// ```
+    ///
+    /// `changes` - holds the copies of the mutably queried components
+    pub fn query_changes<'a, Q: Query<'a>>(&'a self, changes: &'a mut QueryChanges) -> QueryChangesIter<'a, Q>
+    {
+        changes.begin(self.query_without_changes())
+    }
+
+    /// Creates an iterator over the queried components that does not mark any component as changed. The
+    /// mutable references it hands out must not be used to modify the ECS
+    fn query_without_changes<'a, Q: Query<'a>>(&'a self) -> QueryIter<'a, Q>
+    {
+        let component_types = Q::component_types();
+        let component_indexes = self.query_component_indexes(&component_types);
+
+        let registered_types = &self.registered_types;
+        let columns = component_indexes.iter()
+            .map(|index|
+                {
+                    let index_information = &registered_types[*index];
+                    QueryColumn{ entity_indexes: &index_information.entity_indexes, entities: index_information.column_entities(),
+                                 instances: index_information.instances.as_ptr() as *mut u8, change_ticks: std::ptr::null_mut() }
+                })
+            .collect();
+
+        QueryIter::new(columns, self.change_tick)
+    }
+
+    /// Finds the indexes of the components in a query, ensuring that a component that is referenced
+    /// mutably is not referenced again in the same query
+    ///
+    /// `component_types` - the components of the query, along with if they are referenced mutably
+    pub(crate) fn query_component_indexes(&self, component_types: &[(TypeIdentifier, bool)]) -> Vec<usize>
+    {
+        let mut component_indexes = Vec::with_capacity(component_types.len());
+
+        for (type_id, mutable) in component_types
+        {
+            if *mutable && component_types.iter().filter(|(x, _)| *x == *type_id).count() > 1
+            {
+                panic!("The type {:?} is queried mutably more than once in the same query", type_id);
+            }
+
+            match self.registered_types.iter().position(|x| x.type_id == *type_id)
+            {
+                Some(index) => component_indexes.push(index),
+                None => panic!("The type {:?} was not registered!", type_id)
+            }
+        }
+
+        component_indexes
+    }
+
+    /// Get the current change tick. Components written from now on are marked with this tick
+    pub fn get_change_tick(&self) -> u64
+    {
+        self.change_tick
+    }
+
+    /// Starts a new change tick. Call once per frame after all of the logic of the frame has executed
+    pub fn advance_change_tick(&mut self)
+    {
+        self.change_tick += 1;
+    }
+
+    /// Get the entities whose component of the given type was written during the previous or the
+    /// current change tick. With one change tick per frame, these are the entities whose component was
+    /// written since the last frame
+    ///
+    /// ```
+    ///  struct Position(u32);
+    ///  let moved_entities = ecs.changed::<Position>();
+    /// ```
+    pub fn changed<T: 'static>(&self) -> BTreeSet<EntityId>
+    {
+        self.changed_since::<T>(self.change_tick.saturating_sub(1))
+    }
+
+    /// Get the entities whose component of the given type was written during or after the given change tick
+    ///
+    /// `change_tick` - the earliest change tick to report changes for
+    ///
+    /// ```
+    ///  struct Position(u32);
+    ///  let last_processed_tick = ecs.get_change_tick();
+    ///  // ... later frames ...
+    ///  let moved_entities = ecs.changed_since::<Position>(last_processed_tick);
+    /// ```
+    pub fn changed_since<T: 'static>(&self, change_tick: u64) -> BTreeSet<EntityId>
+    {
+        match self.index_of::<T>()
+        {
+            Some(index) => self.registered_types[index].change_ticks.iter()
+                .enumerate()
+                .filter(|(_, tick)| **tick > change_tick)
+                .map(|(entity_instance, _)| EntityId::new(entity_instance as u32, ForceCreationEntity))
+                .collect(),
+            None => panic!("The type {:?} was not registered!", TypeId::of::<T>())
+        }
+    }
+
+    /// Checks if the component of the given type was written for the entity during the previous or the
+    /// current change tick
+    ///
+    /// `entity_id` - the ID of the entity whose component to check
+    pub fn is_changed<T: 'static>(&self, entity_id: EntityId) -> bool
+    {
+        match self.index_of::<T>()
+        {
+            Some(index) => self.registered_types[index].change_ticks.get(entity_id.get_entity_instance() as usize)
+                .is_some_and(|tick| *tick > self.change_tick.saturating_sub(1)),
+            None => false
+        }
+    }
+
+    /// Register a type as an event. Afterwards, events of that type can be sent and read
+    ///
+    /// ```
+    ///  struct EntityDestroyed(EntityId);
+    ///  ecs.register_event::<EntityDestroyed>();
+    /// ```
+    pub fn register_event<T: 'static + Copy>(&mut self)
+    {
+        let type_id = TypeIdentifier::from(TypeId::of::<T>());
+
+        if self.events.contains_key(&type_id)
+        {
+            warn!("The event {:?} was already registered", TypeId::of::<T>());
+            return;
+        }
+
+        self.events.insert(type_id, EventQueue::new(size_of::<T>()));
+        record_event_registration(type_id, size_of::<T>());
+    }
+
+    /// Get the events of the given type that were sent during the previous frame
+    ///
+    /// ```
+    ///  struct EntityDestroyed(EntityId);
+    ///  for event in ecs.get_events::<EntityDestroyed>().iter()
+    ///  {
+    ///     println!("{:?}", event.0);
+    ///  }
+    /// ```
+    pub fn get_events<T: 'static + Copy>(&self) -> Events<'_, T>
+    {
+        match self.events.get(&TypeIdentifier::from(TypeId::of::<T>()))
+        {
+            Some(queue) => Events::new(queue),
+            None => panic!("The event {:?} was not registered!", TypeId::of::<T>())
+        }
+    }
+
+    /// Sends an event, given in its serialized form, that becomes readable in the next frame
+    ///
+    /// `type_id` - the type of the event
+    /// `event` - the serialized value of the event
+    pub fn send_event_serialized(&mut self, type_id: TypeIdentifier, event: &[u8])
+    {
+        match self.events.get_mut(&type_id)
+        {
+            Some(queue) => queue.send_serialized(event),
+            None => panic!("The event {:?} was not registered!", type_id)
+        }
+    }
+
+    /// Makes the events sent during the current frame readable, and drops the events of the previous
+    /// frame. Call once per frame after all of the changes of the frame have been applied
+    pub fn advance_events(&mut self)
+    {
+        for queue in self.events.values_mut()
+        {
+            queue.advance_frame();
+        }
+    }
+
+    /// Stores a resource, a value that exists once in the game rather than once per entity. If a resource
+    /// of the same type already exists, it is replaced. Resources are not serialized with the ECS; to
+    /// modify a resource during a frame so that it is recorded, use EntityChangeInformation::write_resource
+    ///
+    /// ```
+    ///  struct Score(u32);
+    ///  ecs.insert_resource(Score(0));
+    /// ```
+    pub fn insert_resource<T: 'static + Copy>(&mut self, value: T)
+    {
+        self.resources.insert(TypeIdentifier::from(TypeId::of::<T>()), ResourceStorage::from_value(value));
+    }
+
+    /// Get a reference to the resource of the given type, if it has been inserted
+    ///
+    /// ```
+    ///  struct Score(u32);
+    ///  let score = ecs.get_resource::<Score>().unwrap().0;
+    /// ```
+    pub fn get_resource<T: 'static + Copy>(&self) -> Option<&T>
+    {
+        self.resources.get(&TypeIdentifier::from(TypeId::of::<T>())).map(|x| x.get::<T>())
+    }
+
+    /// Get a mutable reference to the resource of the given type, if it has been inserted
+    pub fn get_resource_mut<T: 'static + Copy>(&mut self) -> Option<&mut T>
+    {
+        self.resources.get_mut(&TypeIdentifier::from(TypeId::of::<T>())).map(|x| x.get_mut::<T>())
+    }
+
+    /// Removes the resource of the given type, returning its value if it had been inserted
+    pub fn remove_resource<T: 'static + Copy>(&mut self) -> Option<T>
+    {
+        self.resources.remove(&TypeIdentifier::from(TypeId::of::<T>())).map(|x| *x.get::<T>())
+    }
+
+    /// Stores a resource given in its serialized form, replacing the existing resource of the same type
+    ///
+    /// `type_id` - the type of the resource
+    /// `value` - the serialized value of the resource
+    pub fn write_resource_serialized(&mut self, type_id: TypeIdentifier, value: &[u8])
+    {
+        self.resources.insert(type_id, ResourceStorage::from_serialized(value));
+    }
+
+    /// Get the changes that write the current value of every resource, so that the resources can be
+    /// recorded in a history
+    pub(crate) fn resource_changes(&self) -> Vec<EntityChangeInformation>
+    {
+        self.resources.iter().map(|(type_id, resource)| EntityChangeInformation::WriteResource(*type_id, resource.to_serialized())).collect()
+    }
+
+    /// Get all of the stored resources
+    pub(crate) fn get_resources(&self) -> &HashMap<TypeIdentifier, ResourceStorage>
+    {
+        &self.resources
+    }
+
+    /// Replaces all of the stored resources, such as with the resources stored in a snapshot
+    ///
+    /// `resources` - the resources to store
+    pub(crate) fn set_resources(&mut self, resources: HashMap<TypeIdentifier, ResourceStorage>)
+    {
+        self.resources = resources;
+    }
+
+    /// Creates the change spawning an entity from the given prefab. Return the change from a logic
+    /// function to have the entity created at the end of the frame
+    ///
+    /// `prefab` - the template of the entity to spawn
+    /// `transform` - the placement of the spawned entity
+    pub fn spawn_prefab(&self, prefab: &Prefab, transform: &PrefabTransform) -> EntityChangeInformation
+    {
+        prefab.spawn(transform)
+    }
+
     /// Get the entities that have the passed in Marker
     ///
     /// `marker` - the marker that the returned entity IDs should have
@@ -318,17 +839,77 @@ impl ECS
             panic!("Instance of ECS can only hold {} components", MAX_NUMBER_COMPONENTS);
         }
 
+        if std::mem::align_of::<T>() > BYTE_ALIGNMENT
+        {
+            panic!("The type {:?} is aligned to more than {} bytes, and cannot be a component", TypeId::of::<T>(), BYTE_ALIGNMENT);
+        }
+
         if self.index_of::<T>().is_none()
         {
+            let previous_parts_per_entity = self.bitset_parts_per_entity();
+
             self.registered_types.push(IndexInformation::new(TypeIdentifier::from(TypeId::of::<T>())));
 
+            // Entities that already exist need space in their bitset for the new component
+            if self.bitset_parts_per_entity() != previous_parts_per_entity
+            {
+                self.resize_bitsets(previous_parts_per_entity);
+            }
+
             return;
         }
 
         // If the component is already registered, there is no harm in not doing anything,
         // but probably better to make the issue visible
 
-        println!("The type {:?} was already registered", TypeId::of::<T>());
+        warn!("The type {:?} was already registered", TypeId::of::<T>());
+    }
+
+    /// Stores the values of a registered component packed one after another, allowing all of them to be
+    /// iterated as a slice with dense_components. Removing the component from an entity becomes slightly
+    /// more expensive, as the last value is moved into the place of the removed value
+    ///
+    /// ```
+    ///  ecs.register_type::<TransformationMatrix>();
+    ///  ecs.use_dense_storage::<TransformationMatrix>();
+    /// ```
+    pub fn use_dense_storage<T: 'static>(&mut self)
+    {
+        let component_size = size_of::<T>();
+
+        // Values of types without a size are not stored, so there is nothing to pack
+        if component_size == 0
+        {
+            return;
+        }
+
+        match self.index_of::<T>()
+        {
+            Some(index) => self.registered_types[index].make_dense(component_size),
+            None => panic!("Cannot use dense storage for the unregistered type {:?}", TypeId::of::<T>())
+        }
+    }
+
+    /// Get all of the values of a component using dense storage, along with the entity each value belongs
+    /// to. The order of the values changes when the component is removed from an entity. None is returned
+    /// if the component does not use dense storage
+    ///
+    /// ```
+    ///  let matrices = ecs.dense_components::<TransformationMatrix>().unwrap();
+    ///  let (entities, values) = (matrices.entities(), matrices.values());
+    /// ```
+    pub fn dense_components<T: 'static>(&self) -> Option<DenseComponents<'_, T>>
+    {
+        let index_information = &self.registered_types[self.index_of::<T>()?];
+        let dense_storage = index_information.dense_storage.as_ref()?;
+
+        // The values are packed from the start of the instances, which is aligned for every component type
+        let values = unsafe
+            {
+                std::slice::from_raw_parts(index_information.instances.as_ptr() as *const T, dense_storage.entities.len())
+            };
+
+        Some(DenseComponents{ entities: &dense_storage.entities, values, entity_indexes: &index_information.entity_indexes })
     }
 
     /// Checks if a component for an entity exists, which is true if that component has been written
@@ -349,18 +930,7 @@ impl ECS
     {
         if let Some(component_index) = self.index_of::<T>()
         {
-            let component_indexing_information = calculate_byte_bit_offset(component_index);
-
-            let bitset_ptr = &self.bitsets[entity_id.get_entity_instance() as usize][component_indexing_information.0];
-
-            if (*bitset_ptr >> component_indexing_information.1) & 0x1 == 1
-            {
-                return Ok(true);
-            }
-            else
-            {
-                return Ok(false);
-            }
+            return Ok(self.is_component_bit_set(entity_id, component_index));
         }
 
         Err(())
@@ -389,9 +959,13 @@ impl ECS
             Some(index) => EntityId::new(index as u32, ForceCreationEntity),
             None =>
                 {
-                    let entity_id = self.bitsets.len() as u32;
+                    let parts_per_entity = self.bitset_parts_per_entity();
+                    let entity_id = (self.bitsets.len() / parts_per_entity) as u32;
 
-                    self.bitsets.push([0; num_bytes_for_components(32)]);
+                    for _ in 0..parts_per_entity
+                    {
+                        self.bitsets.push([0; num_bytes_for_components(COMPONENTS_PER_BITSET_PART)]);
+                    }
 
                     EntityId::new(entity_id, ForceCreationEntity)
                 }
@@ -429,14 +1003,11 @@ impl ECS
     {
         if let Some(component_index) = self.registered_types.iter().position(|x| x.type_id == type_id)
         {
-            let component_indexing_information = calculate_byte_bit_offset(component_index);
-
-            let bitset_ptr = &mut self.bitsets[entity_id.get_entity_instance() as usize][component_indexing_information.0];
-
             // Mark the entity as having this type of component attached to it
-            *(bitset_ptr) |= 1 << component_indexing_information.1;
+            self.set_component_bit(entity_id, component_index, true);
 
             self.registered_types[component_index].write_serialized_data(entity_id, value);
+            self.registered_types[component_index].mark_changed(entity_id, self.change_tick);
         }
         else
         {
@@ -458,14 +1029,11 @@ impl ECS
     {
         if let Some(component_index) = self.index_of::<T>()
         {
-            let component_indexing_information = calculate_byte_bit_offset(component_index);
-
-            let bitset_ptr = &mut self.bitsets[entity_id.get_entity_instance() as usize][component_indexing_information.0];
-
             // Mark the entity as having this type of component attached to it
-            *(bitset_ptr) |= 1 << component_indexing_information.1;
+            self.set_component_bit(entity_id, component_index, true);
 
             self.registered_types[component_index].write_data(entity_id, value);
+            self.registered_types[component_index].mark_changed(entity_id, self.change_tick);
         }
         else
         {
@@ -532,17 +1100,11 @@ impl ECS
     /// `component_index` - the index of the component to remove
     fn remove_component_internal(&mut self, entity_id: EntityId, component_index: usize)
     {
-        let components_per_byte = 8;
-
-        let component_indexing_information = calculate_byte_bit_offset(component_index);
-
-        let bitset_ptr = &mut self.bitsets[entity_id.get_entity_instance() as usize][component_indexing_information.0];
-
-        if (*bitset_ptr >> component_indexing_information.1) & 0x1 == 1
+        if self.is_component_bit_set(entity_id, component_index)
         {
-            self.registered_types[component_indexing_information.0 * components_per_byte + component_indexing_information.1].remove_data(entity_id);
+            self.registered_types[component_index].remove_data(entity_id);
 
-            (*bitset_ptr) &= !(1 << component_indexing_information.1);
+            self.set_component_bit(entity_id, component_index, false);
         }
     }
 
@@ -564,25 +1126,20 @@ impl ECS
             return;
         }
 
-        // Have to iterate over the entire length of the bitset (in bytes) in order to remove all
-        // attached components
-        for x in 0..num_bytes_for_components(8)
+        // Have to check every registered component in order to remove all attached components
+        for component_index in 0..self.registered_types.len()
         {
-            let components_per_byte = 8;
-
-            for bit in 0..components_per_byte
+            if self.is_component_bit_set(entity_id, component_index)
             {
-                // Get a specific byte of a bitset, which represents a subset of the components.
-                // If a component was registered for the entity, then remove it.
-                if (self.bitsets[entity_id.get_entity_instance() as usize][x] >> bit) & 0x1 == 1
-                {
-                    self.registered_types[x * 8 + bit].remove_data(entity_id);
-                }
+                self.registered_types[component_index].remove_data(entity_id);
             }
         }
 
         // Easier to just clear the entire bitset, allowing it to be reused for a new entity
-        self.bitsets[entity_id.get_entity_instance() as usize] = [0; num_bytes_for_components(32)];
+        for part in self.entity_bitset_parts(entity_id)
+        {
+            self.bitsets[part] = [0; num_bytes_for_components(COMPONENTS_PER_BITSET_PART)];
+        }
 
         self.free_indexes.push(entity_id.get_entity_instance() as usize);
     }
@@ -607,20 +1164,7 @@ impl ECS
 
     pub fn is_entity_empty(&self, entity_id: EntityId) -> bool
     {
-        for x in 0..num_bytes_for_components(8)
-        {
-            let components_per_byte = 8;
-
-            for bit in 0..components_per_byte
-            {
-                if (self.bitsets[entity_id.get_entity_instance() as usize][x] >> bit) & 0x1 == 1
-                {
-                    return false;
-                }
-            }
-        }
-
-        return true;
+        self.entity_bitset_parts(entity_id).all(|part| self.bitsets[part].iter().all(|x| *x == 0))
     }
 
     /// Get a copy of component from a read-only reference to an entity
@@ -705,6 +1249,38 @@ impl ECS
         None
     }
 
+    /// Get the types of all of the components written for the entity, in the order the types were registered
+    ///
+    /// `entity_id` - the entity whose component types are returned
+    pub fn get_component_types(&self, entity_id: EntityId) -> Vec<TypeIdentifier>
+    {
+        (0..self.registered_types.len())
+            .filter(|x| self.is_component_bit_set(entity_id, *x))
+            .map(|x| self.registered_types[x].type_id)
+            .collect()
+    }
+
+    /// Get the bytes of the value of a component written for the entity
+    ///
+    /// `entity_id` - the entity whose component is read
+    /// `type_id` - the type of the component
+    /// `component_size` - the size of a value of the component, in bytes
+    pub(crate) fn get_component_bytes(&self, entity_id: EntityId, type_id: TypeIdentifier, component_size: usize) -> Option<&[u8]>
+    {
+        let index_information = self.registered_types.iter().find(|x| x.type_id == type_id)?;
+        let instance_index = index_information.value_index(entity_id)? as usize;
+
+        Some(&index_information.instances.as_slice()[instance_index..instance_index + component_size])
+    }
+
+    /// Checks if the type has been registered as a component
+    ///
+    /// `type_id` - the type to check
+    pub(crate) fn is_type_registered(&self, type_id: TypeIdentifier) -> bool
+    {
+        self.registered_types.iter().any(|x| x.type_id == type_id)
+    }
+
     /// Get a reference of a component
     ///
     /// `entity_id` - the Id of the entity associated with the desired component
@@ -712,9 +1288,12 @@ impl ECS
     /// ```
     /// struct Position(u32);
     /// let entity_id = 3;
-    /// let position_instance = ecs.get_ref_mut::<Position>(entity_id).unwrap();
+    /// let mut position_instance = ecs.get_ref_mut::<Position>(entity_id).unwrap();
+    /// position_instance.0 = 5;
     /// ```
-    pub fn get_ref_mut<'a, T: 'static + Serialize + Deserialize<'a>>(&mut self, entity_id: EntityId) -> Option<&mut T>
+    ///
+    /// Note: the component is marked as changed once it is accessed mutably through the returned value
+    pub fn get_ref_mut<'a, T: 'static + Serialize + Deserialize<'a>>(&mut self, entity_id: EntityId) -> Option<ComponentMut<'_, T>>
     {
         if let Some(index) = self.index_of::<T>()
         {
@@ -722,7 +1301,19 @@ impl ECS
             {
                 Ok(true) =>
                     {
-                        return Some(self.registered_types[index].get_ref_mut(entity_id));
+                        let current_change_tick = self.change_tick;
+                        let index_information = &mut self.registered_types[index];
+                        let entity_instance = entity_id.get_entity_instance() as usize;
+
+                        let instance_index = index_information.value_index(entity_id).unwrap();
+
+                        // The value and its change tick are stored in separate vectors, so both can be borrowed at once
+                        let value = unsafe
+                            {
+                                &mut *(index_information.instances.as_mut_ptr().offset(instance_index) as *mut T)
+                            };
+
+                        return Some(ComponentMut{ value, change_tick: &mut index_information.change_ticks[entity_instance], current_change_tick });
                     },
                 _ => return None
             }
@@ -731,6 +1322,88 @@ impl ECS
         None
     }
 
+    /// Get the number of bitset parts each entity uses to represent the registered components
+    fn bitset_parts_per_entity(&self) -> usize
+    {
+        self.registered_types.len().div_ceil(COMPONENTS_PER_BITSET_PART).max(1)
+    }
+
+    /// Get the indexes of the bitset parts that make up the bitset of the given entity
+    ///
+    /// `entity_id` - the ID of the entity whose bitset parts to get
+    fn entity_bitset_parts(&self, entity_id: EntityId) -> std::ops::Range<usize>
+    {
+        let parts_per_entity = self.bitset_parts_per_entity();
+        let first_part = entity_id.get_entity_instance() as usize * parts_per_entity;
+
+        first_part..first_part + parts_per_entity
+    }
+
+    /// Checks if the bit representing the given component is set in the bitset of an entity
+    ///
+    /// `entity_id` - the ID of the entity whose bitset to check
+    /// `component_index` - the index of the component to check for
+    fn is_component_bit_set(&self, entity_id: EntityId, component_index: usize) -> bool
+    {
+        let (part, byte, bit) = self.bitset_location(entity_id, component_index);
+
+        (self.bitsets[part][byte] >> bit) & 0x1 == 1
+    }
+
+    /// Sets or clears the bit representing the given component in the bitset of an entity
+    ///
+    /// `entity_id` - the ID of the entity whose bitset to modify
+    /// `component_index` - the index of the component to modify the bit of
+    /// `value` - true to set the bit, false to clear it
+    fn set_component_bit(&mut self, entity_id: EntityId, component_index: usize, value: bool)
+    {
+        let (part, byte, bit) = self.bitset_location(entity_id, component_index);
+
+        if value
+        {
+            self.bitsets[part][byte] |= 1 << bit;
+        }
+        else
+        {
+            self.bitsets[part][byte] &= !(1 << bit);
+        }
+    }
+
+    /// Finds the bitset part, and the byte and bit within it, that represent a component for an entity
+    ///
+    /// `entity_id` - the ID of the entity whose bitset to access
+    /// `component_index` - the index of the component to find the bit of
+    fn bitset_location(&self, entity_id: EntityId, component_index: usize) -> (usize, usize, usize)
+    {
+        let (byte, bit) = calculate_byte_bit_offset(component_index % COMPONENTS_PER_BITSET_PART);
+        let part = self.entity_bitset_parts(entity_id).start + component_index / COMPONENTS_PER_BITSET_PART;
+
+        (part, byte, bit)
+    }
+
+    /// Adds parts to the bitset of every entity after the number of parts per entity has grown
+    ///
+    /// `previous_parts_per_entity` - the number of parts each entity used before the growth
+    fn resize_bitsets(&mut self, previous_parts_per_entity: usize)
+    {
+        let parts_per_entity = self.bitset_parts_per_entity();
+        let number_entities = self.bitsets.len() / previous_parts_per_entity;
+
+        let mut bitsets = Vec::with_capacity(number_entities * parts_per_entity);
+
+        for entity_bitset in self.bitsets.chunks(previous_parts_per_entity)
+        {
+            bitsets.extend_from_slice(entity_bitset);
+
+            for _ in previous_parts_per_entity..parts_per_entity
+            {
+                bitsets.push([0; num_bytes_for_components(COMPONENTS_PER_BITSET_PART)]);
+            }
+        }
+
+        self.bitsets = bitsets;
+    }
+
     /// Gets the index of a component type
     ///
     /// ```
@@ -744,6 +1417,34 @@ impl ECS
     }
 }
 
+impl<'a, T> DenseComponents<'a, T>
+{
+    /// Get the entity each value belongs to, in the order of the values
+    pub fn entities(&self) -> &'a [EntityId]
+    {
+        self.entities
+    }
+
+    /// Get the values of the component, packed one after another
+    pub fn values(&self) -> &'a [T]
+    {
+        self.values
+    }
+
+    /// Get the value of the given entity, found in the packed values without looking up the entity
+    /// in the storage of the component
+    ///
+    /// `entity_id` - the entity whose value to get
+    pub fn get(&self, entity_id: EntityId) -> Option<&'a T>
+    {
+        match self.entity_indexes.get(entity_id.get_entity_instance() as usize)
+        {
+            Some(i) if *i != NO_VALUE_INDEX => self.values.get(*i as usize / size_of::<T>()),
+            _ => None
+        }
+    }
+}
+
 impl IndexInformation
 {
     /// Creates a new IndexInformation object
@@ -755,66 +1456,135 @@ impl IndexInformation
     /// ```
     fn new(type_id: TypeIdentifier) -> IndexInformation
     {
-        IndexInformation{ type_id, instances: Vec::new(), free_space: Vec::new(), sparse_map: HashMap::default() }
+        IndexInformation{ type_id, instances: AlignedBytes::new(), free_space: Vec::new(), sparse_map: HashMap::default(), entity_indexes: Vec::new(), change_ticks: Vec::new(), dense_storage: None }
     }
 
-    /// Get the index of the component in the appropriate vector [holding the component]
+    /// Get the entities that have a value, in the order of their values if the values are stored densely
+    fn column_entities(&self) -> ColumnEntities<'_>
+    {
+        match self.dense_storage
+        {
+            Some(ref dense_storage) => ColumnEntities::Dense(&dense_storage.entities),
+            None => ColumnEntities::Sparse(&self.sparse_map)
+        }
+    }
+
+    /// Get the index of the value of the entity in the instances vector, in bytes
     ///
-    /// If the entity does not have the component, None is returned.
+    /// `entity_id` - the entity whose value to find
+    fn value_index(&self, entity_id: EntityId) -> Option<isize>
+    {
+        match self.entity_indexes.get(entity_id.get_entity_instance() as usize)
+        {
+            Some(i) if *i != NO_VALUE_INDEX => Some(*i),
+            _ => None
+        }
+    }
+
+    /// Stores the index of the value of the entity in the instances vector
     ///
-    /// `entity_id` - the Id of the entity for which to get the index of a component
+    /// `entity_id` - the entity whose value is stored at the index
+    /// `index` - the index of the value, in bytes
+    fn set_value_index(&mut self, entity_id: EntityId, index: isize)
+    {
+        let entity_instance = entity_id.get_entity_instance() as usize;
+
+        if entity_instance >= self.entity_indexes.len()
+        {
+            self.entity_indexes.resize(entity_instance + 1, NO_VALUE_INDEX);
+            self.change_ticks.resize(entity_instance + 1, 0);
+        }
+
+        self.entity_indexes[entity_instance] = index;
+        self.sparse_map.insert(entity_id, index);
+    }
+
+    /// Forgets the index of the value of the entity, along with when the value was last changed. The
+    /// index the value was stored at is returned, if the entity had a value
     ///
-    /// ```
-    /// struct Position;
-    /// let index = indexInformation.get_index::<Position>(anEntity);
-    /// ```
-    fn get_index<T: 'static>(&self, entity_id: EntityId) -> Option<usize>
+    /// `entity_id` - the entity whose value is no longer stored
+    fn remove_value_index(&mut self, entity_id: EntityId) -> Option<isize>
     {
-        match self.sparse_map.get(&entity_id)
+        let entity_instance = entity_id.get_entity_instance() as usize;
+
+        if entity_instance < self.entity_indexes.len()
         {
-            Some(i) => Some((*i / std::mem::size_of::<T>() as isize) as usize),
-            None => None
+            self.entity_indexes[entity_instance] = NO_VALUE_INDEX;
+            self.change_ticks[entity_instance] = 0;
         }
+
+        self.sparse_map.remove(&entity_id)
     }
 
-    /// Get a reference to component data
+    /// Records that the value of the entity was written in the given change tick
     ///
-    /// `entity_id` - the Id of the entity associated with the desired component
+    /// `entity_id` - the entity whose value was written
+    /// `change_tick` - the change tick the value was written in
+    fn mark_changed(&mut self, entity_id: EntityId, change_tick: u64)
+    {
+        if let Some(tick) = self.change_ticks.get_mut(entity_id.get_entity_instance() as usize)
+        {
+            *tick = change_tick + 1;
+        }
+    }
+
+    /// Packs the stored values one after another, ordered by the entity they belong to, and keeps them
+    /// packed from now on
+    ///
+    /// `component_size` - the size of a value of the component, in bytes
+    fn make_dense(&mut self, component_size: usize)
+    {
+        let mut entities = self.sparse_map.keys().copied().collect::<Vec<EntityId>>();
+        entities.sort();
+
+        let mut instances = AlignedBytes::new();
+
+        for (index, entity_id) in entities.iter().enumerate()
+        {
+            let previous_index = self.sparse_map[entity_id] as usize;
+            instances.extend_from_slice(&self.instances.as_slice()[previous_index..previous_index + component_size]);
+            self.set_value_index(*entity_id, (index * component_size) as isize);
+        }
+
+        self.instances = instances;
+        self.free_space.clear();
+        self.dense_storage = Some(DenseStorage{ entities, component_size });
+    }
+
+    /// Get the index of the component in the appropriate vector [holding the component]
+    ///
+    /// If the entity does not have the component, None is returned.
+    ///
+    /// `entity_id` - the Id of the entity for which to get the index of a component
     ///
     /// ```
     /// struct Position;
-    /// let data = indexInformation.get_ref::<Position>(anEntity);
+    /// let index = indexInformation.get_index::<Position>(anEntity);
     /// ```
-    fn get_ref<T: 'static>(&self, entity_id: EntityId) -> &T
+    fn get_index<T: 'static>(&self, entity_id: EntityId) -> Option<usize>
     {
-        if let Some(instance_index) = self.sparse_map.get(&entity_id)
-        {
-            unsafe
-                {
-                    return &(*(self.instances.as_ptr().offset(*instance_index) as *const T));
-                }
-        }
-        else
+        match self.value_index(entity_id)
         {
-            panic!("Object ID {:?} is not stored for type: {:?}", entity_id,  TypeId::of::<T>());
+            Some(i) => Some((i / std::mem::size_of::<T>() as isize) as usize),
+            None => None
         }
     }
 
-    /// Get a mutable reference to component data
+    /// Get a reference to component data
     ///
     /// `entity_id` - the Id of the entity associated with the desired component
     ///
     /// ```
     /// struct Position;
-    /// let data = indexInformation.get_ref_mut::<Position>(anEntity);
+    /// let data = indexInformation.get_ref::<Position>(anEntity);
     /// ```
-    fn get_ref_mut<T: 'static>(&mut self, entity_id: EntityId) -> &mut T
+    fn get_ref<T: 'static>(&self, entity_id: EntityId) -> &T
     {
-        if let Some(instance_index) = self.sparse_map.get(&entity_id)
+        if let Some(instance_index) = self.value_index(entity_id)
         {
             unsafe
                 {
-                    return &mut (*(self.instances.as_mut_ptr().offset(*instance_index) as *mut T));
+                    return &(*(self.instances.as_ptr().offset(instance_index) as *const T));
                 }
         }
         else
@@ -833,12 +1603,34 @@ impl IndexInformation
     /// ```
     fn remove_data(&mut self, entity_id: EntityId)
     {
-        if let Some(instance_index) = self.sparse_map.get(&entity_id)
+        if let Some(component_size) = self.dense_storage.as_ref().map(|x| x.component_size)
         {
-            self.free_space.push(*instance_index);
+            if let Some(instance_index) = self.remove_value_index(entity_id)
+            {
+                // The last value is moved into the place of the removed value to keep the values packed
+                let removed_position = instance_index as usize / component_size;
+                let last_index = self.instances.len() - component_size;
+
+                let entities = &mut self.dense_storage.as_mut().unwrap().entities;
+                let moved_entity = *entities.last().unwrap();
+                entities.swap_remove(removed_position);
+
+                if instance_index as usize != last_index
+                {
+                    self.instances.as_mut_slice().copy_within(last_index.., instance_index as usize);
+                    self.set_value_index(moved_entity, instance_index);
+                }
+
+                self.instances.resize(last_index);
+            }
+
+            return;
         }
 
-        self.sparse_map.remove(&entity_id);
+        if let Some(instance_index) = self.remove_value_index(entity_id)
+        {
+            self.free_space.push(instance_index);
+        }
     }
 
     /// Write the value of a component for the given entity using the value's serialized form
@@ -848,11 +1640,11 @@ impl IndexInformation
     fn write_serialized_data(&mut self, entity_id: EntityId, value: &Vec<u8>)
     {
         // Space for the component already allocated; overwrite previous value
-        if let Some(instance_index) = self.sparse_map.get(&entity_id)
+        if let Some(instance_index) = self.value_index(entity_id)
         {
             unsafe
                 {
-                    copy_nonoverlapping(value.as_ptr(), self.instances.as_mut_ptr().offset(*instance_index), value.len());
+                    copy_nonoverlapping(value.as_ptr(), self.instances.as_mut_ptr().offset(instance_index), value.len());
                 }
         }
         else
@@ -867,22 +1659,24 @@ impl IndexInformation
                                 copy_nonoverlapping(value.as_ptr(), self.instances.as_mut_ptr().offset(index), value.len());
                             }
 
-                        self.sparse_map.insert(entity_id, index);
+                        self.set_value_index(entity_id, index);
                     },
                 // Create new space for the components value
                 None =>
                     {
                         let write_index = self.instances.len() as isize;
 
-                        self.sparse_map.insert(entity_id, write_index);
+                        self.set_value_index(entity_id, write_index);
 
-                        // Remember that the objects are serialized into bytes; therefore the number
-                        // of space the objects takes in bytes have to be allocated
-                        for _ in 0..value.len()
+                        if let Some(ref mut dense_storage) = self.dense_storage
                         {
-                            self.instances.push(0);
+                            dense_storage.entities.push(entity_id);
                         }
 
+                        // Remember that the objects are serialized into bytes; therefore the number
+                        // of space the objects takes in bytes have to be allocated
+                        self.instances.resize(self.instances.len() + value.len());
+
                         unsafe
                             {
                                 copy_nonoverlapping(value.as_ptr(), self.instances.as_mut_ptr().offset(write_index), value.len());
@@ -905,11 +1699,11 @@ impl IndexInformation
     fn write_data<T>(&mut self, entity_id: EntityId, value: T)
     {
         // Space for the component already allocated; overwrite previous value
-        if let Some(instance_index) = self.sparse_map.get(&entity_id)
+        if let Some(instance_index) = self.value_index(entity_id)
         {
             unsafe
                 {
-                    *(self.instances.as_ptr().offset(*instance_index) as *mut T) = value;
+                    *(self.instances.as_mut_ptr().offset(instance_index) as *mut T) = value;
                 }
         }
         else
@@ -921,28 +1715,30 @@ impl IndexInformation
                     {
                         unsafe
                             {
-                                *(self.instances.as_ptr().offset(index) as *mut T) = value;
+                                *(self.instances.as_mut_ptr().offset(index) as *mut T) = value;
                             }
 
-                        self.sparse_map.insert(entity_id, index);
+                        self.set_value_index(entity_id, index);
                     },
                 // Create new space for the components value
                 None =>
                     {
                         let write_index = self.instances.len() as isize;
 
-                        self.sparse_map.insert(entity_id, write_index);
+                        self.set_value_index(entity_id, write_index);
 
-                        // Remember that the objects are serialized into bytes; therefore the number
-                        // of space the objects takes in bytes have to be allocated
-                        for _ in 0.. std::mem::size_of::<T>()
+                        if let Some(ref mut dense_storage) = self.dense_storage
                         {
-                            self.instances.push(0);
+                            dense_storage.entities.push(entity_id);
                         }
 
+                        // Remember that the objects are serialized into bytes; therefore the number
+                        // of space the objects takes in bytes have to be allocated
+                        self.instances.resize(self.instances.len() + std::mem::size_of::<T>());
+
                         unsafe
                             {
-                                *(self.instances.as_ptr().offset(write_index) as *mut T) = value;
+                                *(self.instances.as_mut_ptr().offset(write_index) as *mut T) = value;
                             }
                     }
             }
@@ -950,16 +1746,36 @@ impl IndexInformation
     }
 }
 
+impl From<StoredIndexInformation> for IndexInformation
+{
+    fn from(stored: StoredIndexInformation) -> Self
+    {
+        let mut index_information = IndexInformation::new(stored.type_id);
+        index_information.instances = AlignedBytes::from_slice(&stored.instances);
+        index_information.free_space = stored.free_space;
+
+        for (entity_id, index) in stored.sparse_map
+        {
+            index_information.set_value_index(entity_id, index);
+        }
+
+        index_information
+    }
+}
+
 #[cfg(test)]
 mod tests
 {
     use super::ECS;
-    use super::{EntityId, IndexInformation};
+    use super::{EntityId, EntityIdRead, IndexInformation};
     use super::super::entity_enforcers::ForceCreationEntity;
     use std::any::TypeId;
     use std::fmt::Debug;
     use std::collections::BTreeSet;
     use crate::objects::ecs::TypeIdentifier;
+    use crate::objects::events::EventReader;
+    use crate::objects::ecs_query::QueryChanges;
+    use crate::objects::entity_change_request::EntityChangeInformation;
     use serde::{Serialize, Deserialize};
 
     #[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -977,13 +1793,17 @@ mod tests
     #[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
     struct Marker2;
 
+    #[repr(align(16))]
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+    struct AlignedValue(u8);
+
     fn check_getters_some<'a, T>(ecs: &mut ECS, entity_id: EntityId, mut value: T)
         where T:
         Copy + Clone + Debug + PartialEq + Eq + 'static + Serialize + Deserialize<'a>
     {
         assert_eq!(Some(value), ecs.get_copy::<T>(entity_id));
         assert_eq!(Some(&value), ecs.get_ref::<T>(entity_id));
-        assert_eq!(Some(&mut value), ecs.get_ref_mut::<T>(entity_id));
+        assert_eq!(Some(&mut value), ecs.get_ref_mut::<T>(entity_id).as_deref_mut());
     }
 
     fn check_getters_none<'a, T>(ecs: &mut ECS, entity_id: EntityId)
@@ -992,7 +1812,7 @@ mod tests
     {
         assert_eq!(None, ecs.get_copy::<T>(entity_id));
         assert_eq!(None, ecs.get_ref::<T>(entity_id));
-        assert_eq!(None, ecs.get_ref_mut::<T>(entity_id));
+        assert!(ecs.get_ref_mut::<T>(entity_id).is_none());
     }
 
     fn cast_value<T>(index_information: &IndexInformation, index: isize) -> &T
@@ -1361,8 +2181,433 @@ mod tests
         {
             assert_eq!(new_ecs.registered_types[x].type_id, ecs.registered_types[x].type_id);
             assert_eq!(new_ecs.registered_types[x].free_space, ecs.registered_types[x].free_space);
-            assert_eq!(new_ecs.registered_types[x].instances, ecs.registered_types[x].instances);
+            assert_eq!(new_ecs.registered_types[x].instances.as_slice(), ecs.registered_types[x].instances.as_slice());
             assert_eq!(new_ecs.registered_types[x].sparse_map, ecs.registered_types[x].sparse_map);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn query_components()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_type::<Position>();
+        ecs.register_type::<Velocity>();
+
+        let mut entities = Vec::new();
+
+        for x in 0..10
+        {
+            let entity = ecs.create_entity();
+            ecs.write_component::<Position>(entity, Position(x));
+
+            if x % 2 == 0
+            {
+                ecs.write_component::<Velocity>(entity, Velocity(x * 2));
+            }
+
+            entities.push(entity);
+        }
+
+        let mut queried_entities = BTreeSet::new();
+
+        for (entity_id, (position, velocity)) in ecs.query::<(&Position, &mut Velocity)>()
+        {
+            assert_eq!(velocity.0, position.0 * 2);
+            velocity.0 += position.0;
+            queried_entities.insert(entity_id);
+        }
+
+        let expected_entities = entities.iter().cloned().step_by(2).collect::<BTreeSet<EntityId>>();
+        assert_eq!(expected_entities, queried_entities);
+
+        for (x, entity) in entities.iter().enumerate().step_by(2)
+        {
+            assert_eq!(Some(Velocity(x as u32 * 3)), ecs.get_copy::<Velocity>(*entity));
+        }
+
+        assert_eq!(10, ecs.query_read::<(&Position,)>().count());
+    }
+
+    #[test]
+    fn query_changes_without_mutable_ecs()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_type::<Position>();
+        ecs.register_type::<Velocity>();
+
+        let mut entities = Vec::new();
+
+        for x in 0..10
+        {
+            let entity = ecs.create_entity();
+            ecs.write_component::<Position>(entity, Position(x));
+            ecs.write_component::<Velocity>(entity, Velocity(x));
+            entities.push(entity);
+        }
+
+        let mut velocities = QueryChanges::new();
+        let immutable_ecs = &ecs;
+
+        for (_, (position, velocity)) in immutable_ecs.query_changes::<(&Position, &mut Velocity)>(&mut velocities)
+        {
+            if position.0 % 2 == 0
+            {
+                velocity.0 += 100;
+            }
+        }
+
+        // The ECS is only changed once the changes are applied
+        assert!(entities.iter().all(|x| ecs.get_copy::<Velocity>(*x).unwrap().0 < 100));
+
+        let changes = velocities.take_changes();
+        assert_eq!(5, changes.len());
+        assert!(velocities.take_changes().is_empty());
+
+        for change in &changes
+        {
+            match change
+            {
+                EntityChangeInformation::ModifyRequest(request) =>
+                    {
+                        assert_eq!(1, request.number_changes());
+                        request.apply_changes(&mut ecs, 0);
+                    },
+                _ => panic!("Only modify requests are created by a query")
+            }
+        }
+
+        for (x, entity) in entities.iter().enumerate()
+        {
+            let expected_velocity = if x % 2 == 0 { x as u32 + 100 } else { x as u32 };
+            assert_eq!(Some(Velocity(expected_velocity)), ecs.get_copy::<Velocity>(*entity));
+        }
+    }
+
+    #[test]
+    fn query_after_removing_and_loading()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_type::<Position>();
+        ecs.register_type::<Velocity>();
+        ecs.use_dense_storage::<Velocity>();
+
+        let mut entities = Vec::new();
+
+        for x in 0..10
+        {
+            let entity = ecs.create_entity();
+            ecs.write_component::<Position>(entity, Position(x));
+            ecs.write_component::<Velocity>(entity, Velocity(x));
+            entities.push(entity);
+        }
+
+        ecs.remove_component::<Position>(entities[3]);
+        ecs.remove_component::<Velocity>(entities[0]);
+        ecs.remove_entity(entities[7]);
+
+        let check_query = |ecs: &ECS|
+            {
+                let queried_entities = ecs.query_read::<(&Position, &Velocity)>()
+                    .map(|(entity_id, (position, velocity))|
+                        {
+                            assert_eq!(position.0, velocity.0);
+                            entity_id
+                        })
+                    .collect::<BTreeSet<EntityId>>();
+
+                let expected_entities = entities.iter()
+                    .enumerate()
+                    .filter(|(x, _)| ![0, 3, 7].contains(x))
+                    .map(|(_, entity)| *entity)
+                    .collect::<BTreeSet<EntityId>>();
+
+                assert_eq!(expected_entities, queried_entities);
+            };
+
+        check_query(&ecs);
+
+        let serialized_ecs = bincode::serialize(&ecs).unwrap();
+        let loaded_ecs = bincode::deserialize::<ECS>(&serialized_ecs).unwrap();
+        check_query(&loaded_ecs);
+    }
+
+    #[test]
+    #[should_panic]
+    fn query_same_component_mutably_twice()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_type::<Position>();
+
+        let entity = ecs.create_entity();
+        ecs.write_component::<Position>(entity, Position(1));
+
+        ecs.query::<(&Position, &mut Position)>().count();
+    }
+
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+    struct NumberedComponent<const N: usize>(u32);
+
+    macro_rules! register_numbered_components
+    {
+        ($ecs: expr, $($number: literal),+) =>
+        {
+            $($ecs.register_type::<NumberedComponent<$number>>();)+
+        };
+    }
+
+    #[test]
+    fn register_more_than_32_components()
+    {
+        let mut ecs = ECS::new();
+
+        register_numbered_components!(ecs, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29);
+
+        // Entity is created before the bitsets need to grow, so its bitset has to be moved when they grow
+        let first_entity = ecs.create_entity();
+        ecs.write_component::<NumberedComponent<29>>(first_entity, NumberedComponent(29));
+
+        register_numbered_components!(ecs, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39);
+
+        let second_entity = ecs.create_entity();
+        ecs.write_component::<NumberedComponent<39>>(first_entity, NumberedComponent(39));
+        ecs.write_component::<NumberedComponent<35>>(second_entity, NumberedComponent(35));
+
+        check_getters_some::<NumberedComponent<29>>(&mut ecs, first_entity, NumberedComponent(29));
+        check_getters_some::<NumberedComponent<39>>(&mut ecs, first_entity, NumberedComponent(39));
+        check_getters_none::<NumberedComponent<35>>(&mut ecs, first_entity);
+
+        check_getters_some::<NumberedComponent<35>>(&mut ecs, second_entity, NumberedComponent(35));
+        check_getters_none::<NumberedComponent<29>>(&mut ecs, second_entity);
+        check_getters_none::<NumberedComponent<39>>(&mut ecs, second_entity);
+
+        ecs.remove_component::<NumberedComponent<39>>(first_entity);
+        check_getters_none::<NumberedComponent<39>>(&mut ecs, first_entity);
+        check_getters_some::<NumberedComponent<29>>(&mut ecs, first_entity, NumberedComponent(29));
+
+        ecs.remove_entity(second_entity);
+        assert!(ecs.is_entity_empty(second_entity));
+        assert!(!ecs.is_entity_empty(first_entity));
+    }
+
+    #[test]
+    fn detect_changed_components()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_type::<Position>();
+        ecs.register_type::<Velocity>();
+
+        let first_entity = ecs.create_entity();
+        let second_entity = ecs.create_entity();
+
+        ecs.write_component::<Position>(first_entity, Position(1));
+        ecs.write_component::<Position>(second_entity, Position(2));
+        ecs.write_component::<Velocity>(second_entity, Velocity(2));
+
+        ecs.advance_change_tick();
+
+        // Changes from the previous tick are still reported
+        assert_eq!(2, ecs.changed::<Position>().len());
+
+        ecs.advance_change_tick();
+        assert!(ecs.changed::<Position>().is_empty());
+
+        // Reading through a mutable borrow does not change the component
+        assert_eq!(2, ecs.get_ref_mut::<Position>(second_entity).unwrap().0);
+        assert!(ecs.changed::<Position>().is_empty());
+
+        ecs.get_ref_mut::<Position>(first_entity).unwrap().0 = 3;
+
+        for (_, (velocity,)) in ecs.query::<(&mut Velocity,)>()
+        {
+            velocity.0 += 1;
+        }
+
+        let mut expected_entities = BTreeSet::new();
+        expected_entities.insert(first_entity);
+        assert_eq!(expected_entities, ecs.changed::<Position>());
+        assert!(ecs.is_changed::<Velocity>(second_entity));
+        assert!(!ecs.is_changed::<Position>(second_entity));
+
+        ecs.remove_component::<Position>(first_entity);
+        assert!(ecs.changed::<Position>().is_empty());
+    }
+
+    #[test]
+    fn events_readable_next_frame()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_event::<Position>();
+
+        ecs.send_event_serialized(TypeIdentifier::from(TypeId::of::<Position>()), &vec![1, 0, 0, 0]);
+        assert!(ecs.get_events::<Position>().is_empty());
+
+        ecs.advance_events();
+        assert_eq!(vec![Position(1)], ecs.get_events::<Position>().iter().collect::<Vec<Position>>());
+
+        let mut reader = EventReader::<Position>::new();
+        assert_eq!(1, ecs.get_events::<Position>().read(&mut reader).count());
+        assert_eq!(0, ecs.get_events::<Position>().read(&mut reader).count());
+
+        ecs.advance_events();
+        assert!(ecs.get_events::<Position>().is_empty());
+    }
+
+    #[test]
+    fn events_registered_after_loading()
+    {
+        let mut ecs = ECS::new();
+
+        ecs.register_event::<Velocity>();
+
+        ecs.send_event_serialized(TypeIdentifier::from(TypeId::of::<Velocity>()), &vec![1, 0, 0, 0]);
+        ecs.advance_events();
+        assert_eq!(1, ecs.get_events::<Velocity>().len());
+
+        // Queued events are dropped when serialized, but the event remains registered
+        let mut loaded_ecs: ECS = bincode::deserialize(&bincode::serialize(&ecs).unwrap()).unwrap();
+        assert!(loaded_ecs.get_events::<Velocity>().is_empty());
+
+        loaded_ecs.send_event_serialized(TypeIdentifier::from(TypeId::of::<Velocity>()), &vec![2, 0, 0, 0]);
+        loaded_ecs.advance_events();
+        assert_eq!(vec![Velocity(2)], loaded_ecs.get_events::<Velocity>().iter().collect::<Vec<Velocity>>());
+    }
+
+    #[test]
+    fn insert_modify_resources()
+    {
+        let mut ecs = ECS::new();
+
+        assert_eq!(None, ecs.get_resource::<Position>());
+
+        ecs.insert_resource(Position(1));
+        ecs.insert_resource(Velocity(2));
+        assert_eq!(Some(&Position(1)), ecs.get_resource::<Position>());
+
+        ecs.get_resource_mut::<Position>().unwrap().0 = 3;
+        assert_eq!(Some(&Position(3)), ecs.get_resource::<Position>());
+
+        ecs.write_resource_serialized(TypeIdentifier::from(TypeId::of::<Velocity>()), &vec![4, 0, 0, 0]);
+        assert_eq!(Some(&Velocity(4)), ecs.get_resource::<Velocity>());
+
+        // Resources are not serialized with the ECS, but can be restored from the changes recording them
+        let mut loaded_ecs: ECS = bincode::deserialize(&bincode::serialize(&ecs).unwrap()).unwrap();
+        assert_eq!(None, loaded_ecs.get_resource::<Position>());
+
+        for change in ecs.resource_changes()
+        {
+            match change
+            {
+                EntityChangeInformation::WriteResource(type_id, value) => loaded_ecs.write_resource_serialized(type_id, &value),
+                _ => panic!("Resources are only recorded as resource writes")
+            }
+        }
+
+        assert_eq!(Some(&Position(3)), loaded_ecs.get_resource::<Position>());
+        assert_eq!(Some(&Velocity(4)), loaded_ecs.get_resource::<Velocity>());
+
+        assert_eq!(Some(Position(3)), ecs.remove_resource::<Position>());
+        assert_eq!(None, ecs.get_resource::<Position>());
+    }
+
+    #[test]
+    fn owned_entities_recursive()
+    {
+        let mut ecs = ECS::new();
+
+        let first_entity = ecs.create_entity();
+        let second_entity = ecs.create_entity();
+        let third_entity = ecs.create_entity();
+        let fourth_entity = ecs.create_entity();
+
+        ecs.add_owned_entity(first_entity, third_entity);
+        ecs.add_owned_entity(first_entity, second_entity);
+        ecs.add_owned_entity(second_entity, fourth_entity);
+        ecs.add_owned_entity(fourth_entity, first_entity);
+        ecs.add_referenced_entity(third_entity, EntityIdRead::new(second_entity));
+
+        assert_eq!(vec![first_entity, second_entity, third_entity, fourth_entity], ecs.get_owned_entities_recursive(first_entity));
+        assert_eq!(vec![fourth_entity, first_entity, second_entity, third_entity], ecs.get_owned_entities_recursive(fourth_entity));
+
+        ecs.clear_entity_relationships(second_entity);
+        assert!(ecs.get_owned_entities(second_entity).is_none());
+        assert!(!ecs.get_owned_entities(first_entity).unwrap().contains(&second_entity));
+        assert!(ecs.get_referenced_entities(third_entity).unwrap().is_empty());
+        assert_eq!(vec![first_entity, third_entity], ecs.get_owned_entities_recursive(first_entity));
+    }
+
+    #[test]
+    fn dense_storage_stays_packed()
+    {
+        let mut ecs = ECS::new();
+        ecs.register_type::<Position>();
+
+        let first_entity = ecs.create_entity();
+        let second_entity = ecs.create_entity();
+        let third_entity = ecs.create_entity();
+
+        ecs.write_component::<Position>(third_entity, Position(3));
+        ecs.write_component::<Position>(first_entity, Position(1));
+        ecs.write_component::<Position>(second_entity, Position(2));
+        ecs.remove_component::<Position>(first_entity);
+        assert!(ecs.dense_components::<Position>().is_none());
+
+        let dense_positions = |ecs: &ECS|
+            {
+                let positions = ecs.dense_components::<Position>().unwrap();
+                (positions.entities().to_vec(), positions.values().to_vec())
+            };
+
+        ecs.use_dense_storage::<Position>();
+        assert_eq!((vec![second_entity, third_entity], vec![Position(2), Position(3)]), dense_positions(&ecs));
+
+        ecs.write_component::<Position>(first_entity, Position(4));
+        ecs.remove_component::<Position>(second_entity);
+        assert_eq!((vec![first_entity, third_entity], vec![Position(4), Position(3)]), dense_positions(&ecs));
+        assert_eq!(Some(&Position(4)), ecs.dense_components::<Position>().unwrap().get(first_entity));
+        assert_eq!(None, ecs.dense_components::<Position>().unwrap().get(second_entity));
+        assert_eq!(Some(Position(4)), ecs.get_copy::<Position>(first_entity));
+        assert_eq!(Some(Position(3)), ecs.get_copy::<Position>(third_entity));
+
+        ecs.remove_entity(third_entity);
+        assert_eq!((vec![first_entity], vec![Position(4)]), dense_positions(&ecs));
+        assert_eq!(4, ecs.registered_types[ecs.index_of::<Position>().unwrap()].instances.len());
+    }
+
+    #[test]
+    fn dense_values_are_aligned()
+    {
+        let mut ecs = ECS::new();
+        ecs.register_type::<Position>();
+        ecs.register_type::<AlignedValue>();
+        ecs.use_dense_storage::<AlignedValue>();
+
+        let entities = (0..5).map(|_| ecs.create_entity()).collect::<Vec<EntityId>>();
+
+        for (index, entity) in entities.iter().enumerate()
+        {
+            ecs.write_component::<Position>(*entity, Position(index as u32));
+            ecs.write_component::<AlignedValue>(*entity, AlignedValue(index as u8));
+        }
+
+        ecs.remove_component::<AlignedValue>(entities[1]);
+
+        let values = ecs.dense_components::<AlignedValue>().unwrap();
+        assert_eq!(0, values.values().as_ptr() as usize % std::mem::align_of::<AlignedValue>());
+        assert_eq!(vec![AlignedValue(0), AlignedValue(4), AlignedValue(2), AlignedValue(3)], values.values().to_vec());
+        assert_eq!(Some(&AlignedValue(4)), values.get(entities[4]));
+
+        let serialized = bincode::serialize(&ecs).unwrap();
+        let mut loaded_ecs = bincode::deserialize::<ECS>(&serialized).unwrap();
+        loaded_ecs.use_dense_storage::<AlignedValue>();
+
+        let loaded_values = loaded_ecs.dense_components::<AlignedValue>().unwrap();
+        assert_eq!(0, loaded_values.values().as_ptr() as usize % std::mem::align_of::<AlignedValue>());
+        assert_eq!(Some(&AlignedValue(4)), loaded_values.get(entities[4]));
+    }
+}
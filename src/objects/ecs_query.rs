@@ -0,0 +1,480 @@
+use std::alloc::Layout;
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::ptr::copy_nonoverlapping;
+use hashbrown::HashMap;
+use crate::objects::aligned_bytes::{AlignedBytes, BYTE_ALIGNMENT};
+use crate::objects::ecs::{NO_VALUE_INDEX, TypeIdentifier};
+use crate::objects::entity_change_request::{EntityChangeInformation, EntityChangeRequest};
+use crate::objects::entity_id::EntityId;
+
+// A query is a tuple of component references, such as (&Position, &mut Velocity). Iterating a query walks
+// the entities of the component with the fewest entities once, in the order of their values if that
+// component is stored densely. The values of the other components of every entity are found by indexing the
+// columns of those components with the entity instance, avoiding any hashing or checks of the bitset of the
+// entity for every component that is accessed
+
+/// Column of the ECS holding all of the values of one component type that a query iterates over
+#[derive(Copy, Clone)]
+pub(crate) struct QueryColumn<'a>
+{
+    /// Index of the value of every entity in the instances, indexed by the entity instance
+    pub(crate) entity_indexes: &'a [isize],
+    pub(crate) entities: ColumnEntities<'a>,
+    pub(crate) instances: *mut u8,
+    /// Null if the component is not referenced mutably, in which case its change ticks are not updated.
+    /// Otherwise points to the change tick of every entity, indexed by the entity instance
+    pub(crate) change_ticks: *mut u64,
+}
+
+/// The entities that have a value in a column
+#[derive(Copy, Clone)]
+pub(crate) enum ColumnEntities<'a>
+{
+    /// The entities in the order of their values
+    Dense(&'a [EntityId]),
+    Sparse(&'a HashMap<EntityId, isize>),
+}
+
+/// Iterator over the entities of the column driving a query
+enum DrivingEntities<'a>
+{
+    Dense(std::slice::Iter<'a, EntityId>),
+    Sparse(hashbrown::hash_map::Keys<'a, EntityId, isize>),
+}
+
+/// A reference to a single component that can be a part of a query
+pub trait QueryComponent<'a>
+{
+    type Item: 'a;
+
+    /// Get the identifier of the component type being referenced
+    fn type_identifier() -> TypeIdentifier;
+
+    /// Get the size and alignment of the component type being referenced
+    fn component_layout() -> Layout;
+
+    /// Checks if the component is referenced mutably
+    fn is_mutable() -> bool;
+
+    /// Creates the reference to the component from a pointer to the start of its value
+    ///
+    /// `component` - pointer to the value of the component in the ECS
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a value of the component type that stays valid for the lifetime 'a, and
+    /// that is not referenced mutably anywhere else while the returned reference is in use
+    unsafe fn from_component_ptr(component: *mut u8) -> Self::Item;
+}
+
+/// Marks query components that only read their component
+pub trait ReadOnlyQueryComponent<'a>: QueryComponent<'a> {}
+
+/// A tuple of component references that are queried together
+pub trait Query<'a>
+{
+    type Item: 'a;
+
+    /// Get the identifiers of the component types being queried, along with if they are referenced mutably
+    fn component_types() -> Vec<(TypeIdentifier, bool)>;
+
+    /// Get the size and alignment of the component types being queried
+    fn component_layouts() -> Vec<Layout>;
+
+    /// Creates the references to the components of an entity
+    ///
+    /// `components` - pointers to the start of the values of the components, in the order of the query
+    ///
+    /// # Safety
+    ///
+    /// Every pointer must satisfy the requirements of QueryComponent::from_component_ptr for the
+    /// component type at the same position in the query
+    unsafe fn from_component_ptrs(components: &[*mut u8]) -> Self::Item;
+}
+
+/// Marks queries that only read their components, allowing them to be executed on an immutable ECS
+pub trait ReadOnlyQuery<'a>: Query<'a> {}
+
+impl<'a, T: 'static> QueryComponent<'a> for &'a T
+{
+    type Item = &'a T;
+
+    fn type_identifier() -> TypeIdentifier
+    {
+        TypeIdentifier::from(TypeId::of::<T>())
+    }
+
+    fn component_layout() -> Layout
+    {
+        Layout::new::<T>()
+    }
+
+    fn is_mutable() -> bool
+    {
+        false
+    }
+
+    unsafe fn from_component_ptr(component: *mut u8) -> Self::Item
+    {
+        &*(component as *const T)
+    }
+}
+
+impl<'a, T: 'static> ReadOnlyQueryComponent<'a> for &'a T {}
+
+impl<'a, T: 'static> QueryComponent<'a> for &'a mut T
+{
+    type Item = &'a mut T;
+
+    fn type_identifier() -> TypeIdentifier
+    {
+        TypeIdentifier::from(TypeId::of::<T>())
+    }
+
+    fn component_layout() -> Layout
+    {
+        Layout::new::<T>()
+    }
+
+    fn is_mutable() -> bool
+    {
+        true
+    }
+
+    unsafe fn from_component_ptr(component: *mut u8) -> Self::Item
+    {
+        &mut *(component as *mut T)
+    }
+}
+
+macro_rules! impl_query
+{
+    ($($component: ident, $index: tt),+) =>
+    {
+        impl<'a, $($component: QueryComponent<'a>),+> Query<'a> for ($($component,)+)
+        {
+            type Item = ($($component::Item,)+);
+
+            fn component_types() -> Vec<(TypeIdentifier, bool)>
+            {
+                vec![$(($component::type_identifier(), $component::is_mutable())),+]
+            }
+
+            fn component_layouts() -> Vec<Layout>
+            {
+                vec![$($component::component_layout()),+]
+            }
+
+            unsafe fn from_component_ptrs(components: &[*mut u8]) -> Self::Item
+            {
+                ($($component::from_component_ptr(components[$index]),)+)
+            }
+        }
+
+        impl<'a, $($component: ReadOnlyQueryComponent<'a>),+> ReadOnlyQuery<'a> for ($($component,)+) {}
+    };
+}
+
+impl_query!(A, 0);
+impl_query!(A, 0, B, 1);
+impl_query!(A, 0, B, 1, C, 2);
+impl_query!(A, 0, B, 1, C, 2, D, 3);
+impl_query!(A, 0, B, 1, C, 2, D, 3, E, 4);
+impl_query!(A, 0, B, 1, C, 2, D, 3, E, 4, F, 5);
+
+impl<'a> ColumnEntities<'a>
+{
+    /// Get the number of entities that have a value in the column
+    fn len(&self) -> usize
+    {
+        match self
+        {
+            ColumnEntities::Dense(entities) => entities.len(),
+            ColumnEntities::Sparse(sparse_map) => sparse_map.len()
+        }
+    }
+
+    /// Get an iterator over the entities that have a value in the column
+    fn iter(&self) -> DrivingEntities<'a>
+    {
+        match *self
+        {
+            ColumnEntities::Dense(entities) => DrivingEntities::Dense(entities.iter()),
+            ColumnEntities::Sparse(sparse_map) => DrivingEntities::Sparse(sparse_map.keys())
+        }
+    }
+}
+
+impl<'a> Iterator for DrivingEntities<'a>
+{
+    type Item = &'a EntityId;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        match self
+        {
+            DrivingEntities::Dense(entities) => entities.next(),
+            DrivingEntities::Sparse(entities) => entities.next()
+        }
+    }
+}
+
+/// Iterates over all entities that have every component of a query, yielding the entity along with
+/// references to its queried components
+pub struct QueryIter<'a, Q: Query<'a>>
+{
+    columns: Vec<QueryColumn<'a>>,
+    driving_entities: DrivingEntities<'a>,
+    component_ptrs: Vec<*mut u8>,
+    change_tick: u64,
+    _query: PhantomData<Q>,
+}
+
+impl<'a, Q: Query<'a>> QueryIter<'a, Q>
+{
+    /// Creates an iterator over the entities that have a value in all of the given columns
+    ///
+    /// `columns` - the storage of each queried component, in the order of the query
+    /// `change_tick` - the change tick to mark mutably referenced components with
+    pub(crate) fn new(columns: Vec<QueryColumn<'a>>, change_tick: u64) -> QueryIter<'a, Q>
+    {
+        // Walking the column with the fewest entities minimizes the number of entities checked in the other columns
+        let driving_entities = columns.iter()
+            .min_by_key(|column| column.entities.len())
+            .map(|column| column.entities.iter())
+            .unwrap();
+
+        let component_ptrs = vec![std::ptr::null_mut(); columns.len()];
+
+        QueryIter{ columns, driving_entities, component_ptrs, change_tick, _query: PhantomData }
+    }
+
+    /// Finds the next entity that has every queried component, storing pointers to the values of its
+    /// components in the order of the query
+    fn next_entity(&mut self) -> Option<EntityId>
+    {
+        'entities: for entity_id in &mut self.driving_entities
+        {
+            let entity_instance = entity_id.get_entity_instance() as usize;
+
+            for (index, column) in self.columns.iter().enumerate()
+            {
+                match column.entity_indexes.get(entity_instance)
+                {
+                    Some(i) if *i != NO_VALUE_INDEX => unsafe { self.component_ptrs[index] = column.instances.offset(*i) },
+                    _ => continue 'entities
+                }
+            }
+
+            // Change ticks are stored as one more than the tick the value was written in. Every entity with a
+            // value has a change tick, as the change ticks are kept the same length as the entity indexes
+            for column in self.columns.iter().filter(|x| !x.change_ticks.is_null())
+            {
+                unsafe
+                    {
+                        *column.change_ticks.add(entity_instance) = self.change_tick + 1;
+                    }
+            }
+
+            return Some(*entity_id);
+        }
+
+        None
+    }
+}
+
+impl<'a, Q: Query<'a>> Iterator for QueryIter<'a, Q>
+{
+    type Item = (EntityId, Q::Item);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let entity_id = self.next_entity()?;
+
+        // Each entity is visited once and the component types of a query are unique, so no two
+        // references handed out alias each other
+        unsafe
+            {
+                Some((entity_id, Q::from_component_ptrs(&self.component_ptrs)))
+            }
+    }
+}
+
+/// Copies of the mutably queried components of an ECS that can only be read, such as the one given to the
+/// logic of entities. Changes made to the copies are written to the ECS through change requests, which are
+/// applied along with the other changes returned by the logic
+///
+/// ```
+///  struct Position(u32);
+///  struct Velocity(u32);
+///  let mut velocities = QueryChanges::new();
+///  for (entity_id, (position, velocity)) in ecs.query_changes::<(&Position, &mut Velocity)>(&mut velocities)
+///  {
+///     velocity.0 += position.0;
+///  }
+///  changes.extend(velocities.take_changes());
+/// ```
+pub struct QueryChanges
+{
+    entities: Vec<EntityId>,
+    // The type, size and offset within the copied values of an entity of every mutably queried component
+    components: Vec<(TypeIdentifier, usize, usize)>,
+    entity_size: usize,
+    values: AlignedBytes,
+    original_values: Vec<u8>,
+}
+
+/// Iterates over the entities of a query on an immutable ECS, yielding references to the copies of the
+/// mutably queried components
+pub struct QueryChangesIter<'a, Q: Query<'a>>
+{
+    entities: &'a [EntityId],
+    component_ptrs: Vec<*mut u8>,
+    next_entity: usize,
+    _query: PhantomData<Q>,
+}
+
+impl QueryChanges
+{
+    /// Creates an empty set of changes, which can be reused by many queries
+    pub fn new() -> QueryChanges
+    {
+        QueryChanges{ entities: Vec::new(), components: Vec::new(), entity_size: 0, values: AlignedBytes::new(), original_values: Vec::new() }
+    }
+
+    /// Copies the mutably queried components of every entity the query matches, and creates the iterator
+    /// handing out references to the copies along with the other queried components. Any changes that were
+    /// not taken from a previous query are discarded
+    ///
+    /// `query` - the query over the immutable ECS, which does not modify any component
+    pub(crate) fn begin<'a, Q: Query<'a>>(&'a mut self, mut query: QueryIter<'a, Q>) -> QueryChangesIter<'a, Q>
+    {
+        let component_types = Q::component_types();
+        let component_layouts = Q::component_layouts();
+
+        // The copies of the components of an entity are placed one after another, aligned for their type
+        self.components.clear();
+        self.entity_size = 0;
+
+        for ((type_id, _), layout) in component_types.iter().zip(component_layouts.iter()).filter(|((_, mutable), _)| *mutable)
+        {
+            if layout.align() > BYTE_ALIGNMENT
+            {
+                panic!("The type {:?} is aligned to more than {} bytes, and cannot be queried mutably", type_id, BYTE_ALIGNMENT);
+            }
+
+            let offset = self.entity_size.next_multiple_of(layout.align());
+            self.components.push((*type_id, layout.size(), offset));
+            self.entity_size = offset + layout.size();
+        }
+
+        self.entity_size = self.entity_size.next_multiple_of(BYTE_ALIGNMENT);
+
+        self.entities.clear();
+        let mut component_ptrs = Vec::new();
+
+        while let Some(entity_id) = query.next_entity()
+        {
+            self.entities.push(entity_id);
+            component_ptrs.extend_from_slice(&query.component_ptrs);
+        }
+
+        self.values.resize(self.entities.len() * self.entity_size);
+
+        let mutable_columns = component_types.iter()
+            .enumerate()
+            .filter(|(_, (_, mutable))| *mutable)
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+
+        for (entity_index, entity_ptrs) in component_ptrs.chunks(component_types.len()).enumerate()
+        {
+            for (column, (_, size, offset)) in mutable_columns.iter().zip(self.components.iter())
+            {
+                let begin = entity_index * self.entity_size + offset;
+                unsafe
+                    {
+                        copy_nonoverlapping(entity_ptrs[*column], self.values.as_mut_slice()[begin..begin + size].as_mut_ptr(), *size);
+                    }
+            }
+        }
+
+        self.original_values.clear();
+        self.original_values.extend_from_slice(self.values.as_slice());
+
+        // The references handed out for the mutably queried components point to the copies
+        let values = self.values.as_mut_ptr();
+
+        for (entity_index, entity_ptrs) in component_ptrs.chunks_mut(component_types.len()).enumerate()
+        {
+            for (column, (_, _, offset)) in mutable_columns.iter().zip(self.components.iter())
+            {
+                unsafe
+                    {
+                        entity_ptrs[*column] = values.add(entity_index * self.entity_size + offset);
+                    }
+            }
+        }
+
+        QueryChangesIter{ entities: &self.entities, component_ptrs, next_entity: 0, _query: PhantomData }
+    }
+
+    /// Creates the change requests writing the modified copies of components to the ECS, and forgets the copies.
+    /// Only entities with a modified component have a change request, and only modified components are written
+    pub fn take_changes(&mut self) -> Vec<EntityChangeInformation>
+    {
+        let mut changes = Vec::new();
+
+        for (entity_index, entity_id) in self.entities.iter().enumerate()
+        {
+            let mut change_request = EntityChangeRequest::new(*entity_id);
+
+            for (type_id, size, offset) in &self.components
+            {
+                let begin = entity_index * self.entity_size + offset;
+                let value = &self.values.as_slice()[begin..begin + size];
+
+                if value != &self.original_values[begin..begin + size]
+                {
+                    change_request.type_id.push((*type_id, value.to_vec()));
+                }
+            }
+
+            if change_request.number_changes() != 0
+            {
+                changes.push(EntityChangeInformation::ModifyRequest(change_request));
+            }
+        }
+
+        self.entities.clear();
+        changes
+    }
+}
+
+impl Default for QueryChanges
+{
+    fn default() -> Self
+    {
+        QueryChanges::new()
+    }
+}
+
+impl<'a, Q: Query<'a>> Iterator for QueryChangesIter<'a, Q>
+{
+    type Item = (EntityId, Q::Item);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let entity_id = *self.entities.get(self.next_entity)?;
+        let number_components = self.component_ptrs.len() / self.entities.len();
+        let begin = self.next_entity * number_components;
+        self.next_entity += 1;
+
+        // Mutable references point to the copies of this entity, which no other item refers to
+        unsafe
+            {
+                Some((entity_id, Q::from_component_ptrs(&self.component_ptrs[begin..begin + number_components])))
+            }
+    }
+}
@@ -3,7 +3,7 @@ use std::mem::size_of;
 use serde::{Serialize, Deserialize};
 use crate::exports::entity_transformer::EntityTransformationBuilder;
 use crate::objects::ecs::{ECS, TypeIdentifier};
-use crate::objects::entity_id::{EntityId, OwnedEntity, ReferencedEntity, SelfEntity};
+use crate::objects::entity_id::{ChildEntity, EntityId, MemberEntity, OwnedEntity, ReferencedEntity, SelfEntity};
 
 /// Represents one of the possible operations that can be done to modify an entity.
 #[derive(Clone, Serialize, Deserialize)]
@@ -24,6 +24,49 @@ pub enum EntityChangeInformation
 
     MakeObjectStatic(EntityId),
     WakeUpRequest(EntityId),
+
+    SendEvent(TypeIdentifier, Vec<u8>),
+    WriteResource(TypeIdentifier, Vec<u8>),
+
+    // Recorded histories store the index of each variant, so new variants are only added at the end
+    AddChildEntity(SelfEntity, ChildEntity),
+    RemoveChildEntity(SelfEntity, ChildEntity),
+    DespawnRecursive(EntityId),
+    AddGroupMember(SelfEntity, MemberEntity),
+    RemoveGroupMember(SelfEntity, MemberEntity),
+}
+
+impl EntityChangeInformation
+{
+    /// Creates a change that sends the given event. The event can be read by all entities in the next frame
+    ///
+    /// `event` - the event to send
+    pub fn send_event<T: 'static + Copy>(event: T) -> EntityChangeInformation
+    {
+        let mut serialized_event: Vec<u8> = vec![0; size_of::<T>()];
+
+        unsafe
+            {
+                std::ptr::write_unaligned(serialized_event.as_mut_ptr() as *mut T, event);
+            }
+
+        EntityChangeInformation::SendEvent(TypeIdentifier::from(TypeId::of::<T>()), serialized_event)
+    }
+
+    /// Creates a change that sets the value of a resource, inserting the resource if it does not exist
+    ///
+    /// `value` - the new value of the resource
+    pub fn write_resource<T: 'static + Copy>(value: T) -> EntityChangeInformation
+    {
+        let mut serialized_value: Vec<u8> = vec![0; size_of::<T>()];
+
+        unsafe
+            {
+                std::ptr::write_unaligned(serialized_value.as_mut_ptr() as *mut T, value);
+            }
+
+        EntityChangeInformation::WriteResource(TypeIdentifier::from(TypeId::of::<T>()), serialized_value)
+    }
 }
 
 /// Required information to modify the value of a component for an entity. Component is automatically
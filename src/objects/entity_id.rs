@@ -11,6 +11,8 @@ pub struct EntityIdRead(u32);
 
 pub type SelfEntity = EntityId;
 pub type OwnedEntity = EntityId;
+pub type ChildEntity = EntityId;
+pub type MemberEntity = EntityId;
 pub type ReferencedEntity = EntityIdRead;
 
 impl EntityId
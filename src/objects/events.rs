@@ -0,0 +1,204 @@
+use std::marker::PhantomData;
+use std::mem::size_of;
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+use crate::objects::ecs::TypeIdentifier;
+
+// Events are sent through EntityChangeInformation::SendEvent, so that sending an event is recorded in the
+// history like any other change. Events sent during a frame are readable during the next frame, after
+// which they are dropped. This means the events a logic function sees do not depend on the order in which
+// the logic of entities executes within a frame
+
+// Similar to components, events are stored as bytes so that the events of all types can be stored together
+
+// Queued events are not serialized, as they are only readable for one frame. The registered event types are
+// remembered for the whole program instead, and a queue for each of them is created when an ECS is loaded,
+// so that a loaded history or snapshot can send and read the events registered by the game
+
+lazy_static!
+{
+    static ref REGISTERED_EVENTS: Mutex<HashMap<TypeIdentifier, usize>> = Mutex::new(HashMap::default());
+}
+
+/// Stores the events of one type that were sent during the previous and the current frame
+#[derive(Clone)]
+pub(crate) struct EventQueue
+{
+    event_size: usize,
+    previous_frame_events: Vec<u8>,
+    current_frame_events: Vec<u8>,
+    previous_frame_first_id: u64,
+    current_frame_first_id: u64,
+}
+
+/// Read access to the events of a type that were sent during the previous frame
+pub struct Events<'a, T: 'static + Copy>
+{
+    queue: &'a EventQueue,
+    _event: PhantomData<T>,
+}
+
+/// Cursor keeping track of which events a reader has already read. Store it in a component to have an
+/// entity only see each event once, even if its logic is executed several times in a frame
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct EventReader<T: 'static + Copy>
+{
+    next_event_id: u64,
+    _event: PhantomData<T>,
+}
+
+/// Remembers that the event type was registered, so that its queue is created for every ECS that is loaded
+///
+/// `type_id` - the type of the event
+/// `event_size` - the size in bytes of the event
+pub(crate) fn record_event_registration(type_id: TypeIdentifier, event_size: usize)
+{
+    REGISTERED_EVENTS.lock().insert(type_id, event_size);
+}
+
+/// Creates an empty queue for every event type registered in the program
+pub(crate) fn registered_event_queues() -> HashMap<TypeIdentifier, EventQueue>
+{
+    REGISTERED_EVENTS.lock().iter().map(|(type_id, event_size)| (*type_id, EventQueue::new(*event_size))).collect()
+}
+
+impl EventQueue
+{
+    /// Creates an empty queue for events of the given size
+    ///
+    /// `event_size` - the size in bytes of the events stored in the queue
+    pub(crate) fn new(event_size: usize) -> EventQueue
+    {
+        EventQueue
+        {
+            event_size,
+            previous_frame_events: Vec::new(),
+            current_frame_events: Vec::new(),
+            previous_frame_first_id: 0,
+            current_frame_first_id: 0,
+        }
+    }
+
+    /// Adds an event, in its serialized form, to the events of the current frame
+    ///
+    /// `event` - the bytes of the event
+    pub(crate) fn send_serialized(&mut self, event: &[u8])
+    {
+        debug_assert_eq!(event.len(), self.event_size, "Event of the wrong size sent to an event queue");
+        self.current_frame_events.extend_from_slice(event);
+    }
+
+    /// Makes the events of the current frame readable, and drops the events of the previous frame
+    pub(crate) fn advance_frame(&mut self)
+    {
+        self.previous_frame_first_id = self.current_frame_first_id;
+        self.current_frame_first_id += self.number_events(&self.current_frame_events) as u64;
+
+        self.previous_frame_events.clear();
+        std::mem::swap(&mut self.previous_frame_events, &mut self.current_frame_events);
+    }
+
+    /// Get the number of events stored in the given bytes
+    ///
+    /// `events` - the bytes holding the events
+    fn number_events(&self, events: &[u8]) -> usize
+    {
+        if self.event_size == 0
+        {
+            return 0;
+        }
+
+        events.len() / self.event_size
+    }
+}
+
+impl<'a, T: 'static + Copy> Events<'a, T>
+{
+    /// Creates read access to the events of the given queue
+    ///
+    /// `queue` - the queue holding the events of type T
+    pub(crate) fn new(queue: &'a EventQueue) -> Events<'a, T>
+    {
+        debug_assert_eq!(queue.event_size, size_of::<T>(), "Events read as the wrong type");
+        Events{ queue, _event: PhantomData }
+    }
+
+    /// Get the number of events sent during the previous frame
+    pub fn len(&self) -> usize
+    {
+        self.queue.number_events(&self.queue.previous_frame_events)
+    }
+
+    /// Checks if no events were sent during the previous frame
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    /// Get the events sent during the previous frame
+    pub fn iter(&self) -> impl Iterator<Item = T> + 'a
+    {
+        let queue = self.queue;
+        (0..self.len()).map(move |x| Events::<T>::event_at(queue, x))
+    }
+
+    /// Get the events sent during the previous frame that the reader has not yet read, and advances the
+    /// reader past them
+    ///
+    /// `reader` - the cursor of the reader
+    pub fn read(&self, reader: &mut EventReader<T>) -> impl Iterator<Item = T> + 'a
+    {
+        let queue = self.queue;
+        let first_unread = reader.next_event_id.max(queue.previous_frame_first_id) - queue.previous_frame_first_id;
+        let number_events = self.len();
+
+        reader.next_event_id = queue.previous_frame_first_id + number_events as u64;
+
+        (first_unread as usize..number_events).map(move |x| Events::<T>::event_at(queue, x))
+    }
+
+    /// Copies out the event at the given index of the previous frame's events
+    ///
+    /// `queue` - the queue holding the events
+    /// `index` - the index of the event to copy
+    fn event_at(queue: &EventQueue, index: usize) -> T
+    {
+        // Events are stored in a byte vector, so they are read unaligned
+        unsafe
+            {
+                std::ptr::read_unaligned(queue.previous_frame_events.as_ptr().add(index * queue.event_size) as *const T)
+            }
+    }
+}
+
+impl<T: 'static + Copy> EventReader<T>
+{
+    /// Creates a reader that has not read any events
+    pub fn new() -> EventReader<T>
+    {
+        EventReader{ next_event_id: 0, _event: PhantomData }
+    }
+}
+
+impl<T: 'static + Copy> Default for EventReader<T>
+{
+    fn default() -> Self
+    {
+        EventReader::new()
+    }
+}
+
+// Derives would require T to implement the traits, even though T is not stored
+
+impl<T: 'static + Copy> Clone for EventReader<T>
+{
+    fn clone(&self) -> Self
+    {
+        *self
+    }
+}
+
+impl<T: 'static + Copy> Copy for EventReader<T> {}
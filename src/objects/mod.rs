@@ -1,5 +1,10 @@
+mod aligned_bytes;
 pub mod ecs;
+pub mod ecs_query;
 pub mod entity_id;
 pub mod entity_change_request;
+pub mod events;
+pub(crate) mod resources;
 mod entity_enforcers;
 pub mod sorted_entities;
+pub mod system_scheduler;
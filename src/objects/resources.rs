@@ -0,0 +1,104 @@
+use std::mem::{align_of, size_of};
+use serde::{Serialize, Deserialize};
+
+// Resources are values that exist once in the game rather than once per entity, such as the score or the
+// elapsed time. They are modified during a frame through EntityChangeInformation::WriteResource so that the
+// modifications are replayed. Resources are not serialized with the ECS, keeping the layout of recorded
+// histories the same; a history instead records a change writing every resource, and a snapshot stores
+// the resources next to the ECS
+
+/// Stores the value of a single resource. Like components, the value is stored as bytes so that resources
+/// of all types can be stored together. The bytes are held in 8 byte words so that references to the value
+/// are always aligned
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct ResourceStorage
+{
+    words: Vec<u64>,
+    size: usize,
+}
+
+impl ResourceStorage
+{
+    /// Creates the storage for a resource from the serialized form of its value
+    ///
+    /// `value` - the bytes of the value of the resource
+    pub(crate) fn from_serialized(value: &[u8]) -> ResourceStorage
+    {
+        let mut words = vec![0; value.len().div_ceil(size_of::<u64>())];
+
+        unsafe
+            {
+                std::ptr::copy_nonoverlapping(value.as_ptr(), words.as_mut_ptr() as *mut u8, value.len());
+            }
+
+        ResourceStorage{ words, size: value.len() }
+    }
+
+    /// Creates the storage for a resource holding the given value
+    ///
+    /// `value` - the value of the resource
+    pub(crate) fn from_value<T: 'static + Copy>(value: T) -> ResourceStorage
+    {
+        ResourceStorage::check_alignment::<T>();
+
+        let mut storage = ResourceStorage{ words: vec![0; size_of::<T>().div_ceil(size_of::<u64>())], size: size_of::<T>() };
+
+        unsafe
+            {
+                *(storage.words.as_mut_ptr() as *mut T) = value;
+            }
+
+        storage
+    }
+
+    /// Get the value of the resource in its serialized form
+    pub(crate) fn to_serialized(&self) -> Vec<u8>
+    {
+        unsafe
+            {
+                std::slice::from_raw_parts(self.words.as_ptr() as *const u8, self.size).to_vec()
+            }
+    }
+
+    /// Get a reference to the value of the resource
+    pub(crate) fn get<T: 'static + Copy>(&self) -> &T
+    {
+        self.check_size::<T>();
+
+        unsafe
+            {
+                &*(self.words.as_ptr() as *const T)
+            }
+    }
+
+    /// Get a mutable reference to the value of the resource
+    pub(crate) fn get_mut<T: 'static + Copy>(&mut self) -> &mut T
+    {
+        self.check_size::<T>();
+
+        unsafe
+            {
+                &mut *(self.words.as_mut_ptr() as *mut T)
+            }
+    }
+
+    /// Ensures the stored value is the size of the type it is accessed as
+    fn check_size<T: 'static + Copy>(&self)
+    {
+        if self.size != size_of::<T>()
+        {
+            panic!("Resource of {} bytes accessed as a type of {} bytes", self.size, size_of::<T>());
+        }
+
+        ResourceStorage::check_alignment::<T>();
+    }
+
+    /// Ensures values of the given type can be stored in the words of a resource
+    fn check_alignment<T: 'static + Copy>()
+    {
+        if align_of::<T>() > align_of::<u64>()
+        {
+            panic!("Resource with an alignment of {} bytes cannot be stored in the ECS", align_of::<T>());
+        }
+    }
+}
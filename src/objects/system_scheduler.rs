@@ -0,0 +1,397 @@
+use std::any::TypeId;
+use hashbrown::HashSet;
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::objects::ecs::{ECS, TypeIdentifier};
+use crate::objects::ecs_query::{Query, QueryColumn, QueryIter};
+use crate::objects::entity_change_request::EntityChangeInformation;
+use crate::objects::entity_id::EntityId;
+use crate::world::visible_world_sections::VisibleWorldSections;
+
+type ElapsedTime = f32;
+
+/// Function executing the work of a system for all of the entities it is interested in. Changes that
+/// cannot be made through the components the system has access to, such as creating entities or moving
+/// an entity in the world, are returned and applied after all systems have finished executing
+pub type SystemFunction = fn(&mut SystemAccess, ElapsedTime) -> Vec<EntityChangeInformation>;
+
+// Systems declare the component types they read and write. Systems whose declarations do not conflict are
+// executed at the same time on the Rayon thread pool. Systems are placed into stages in the order they were
+// added; a system is placed in the stage after the last stage holding a system it conflicts with, so
+// conflicting systems always execute in the order they were added. This keeps the result of executing the
+// systems the same every time, which is required to replay the history of the game
+
+/// A system along with the component types it accesses
+pub struct SystemDescription
+{
+    name: String,
+    function: SystemFunction,
+    reads: HashSet<TypeIdentifier>,
+    writes: HashSet<TypeIdentifier>,
+}
+
+/// Access to the ECS given to an executing system, limited to the component types the system declared
+pub struct SystemAccess<'a>
+{
+    ecs: &'a ECS,
+    columns: SharedColumns<'a>,
+    description: &'a SystemDescription,
+    visible_world_sections: &'a VisibleWorldSections<'a>,
+}
+
+/// Holds all of the systems and executes them once per frame
+pub struct SystemScheduler
+{
+    systems: Vec<SystemDescription>,
+    stages: Vec<Vec<usize>>,
+    stages_outdated: bool,
+}
+
+/// Storage of the component types of the ECS, resolved before the systems execute, that can be shared
+/// between the threads executing systems
+#[derive(Copy, Clone)]
+struct SharedColumns<'a>(&'a [QueryColumn<'a>]);
+
+unsafe impl Send for SharedColumns<'_> {}
+unsafe impl Sync for SharedColumns<'_> {}
+
+impl SystemDescription
+{
+    /// Declares that the system reads the given component type
+    pub fn reads<T: 'static>(&mut self) -> &mut Self
+    {
+        self.reads.insert(TypeIdentifier::from(TypeId::of::<T>()));
+        self
+    }
+
+    /// Declares that the system writes the given component type. Writing a type allows it to be read as well
+    pub fn writes<T: 'static>(&mut self) -> &mut Self
+    {
+        self.writes.insert(TypeIdentifier::from(TypeId::of::<T>()));
+        self
+    }
+
+    /// Get the name of the system
+    pub fn get_name(&self) -> &str
+    {
+        &self.name
+    }
+
+    /// Checks if this system and the given system cannot be executed at the same time, which is the case
+    /// if either system writes a component type the other system accesses
+    ///
+    /// `other` - the system to check for conflicts with
+    fn conflicts_with(&self, other: &SystemDescription) -> bool
+    {
+        self.writes.iter().any(|x| other.reads.contains(x) || other.writes.contains(x)) ||
+            other.writes.iter().any(|x| self.reads.contains(x))
+    }
+
+    /// Checks if the system declared that it accesses the given component type
+    ///
+    /// `type_id` - the component type being accessed
+    /// `mutable` - true if the component type is written
+    fn can_access(&self, type_id: &TypeIdentifier, mutable: bool) -> bool
+    {
+        self.writes.contains(type_id) || (!mutable && self.reads.contains(type_id))
+    }
+}
+
+impl<'a> SystemAccess<'a>
+{
+    /// Iterates over all entities that have every queried component. Only the component types the system
+    /// declared can be queried, and only the declared written types can be referenced mutably
+    pub fn query<'b, Q: Query<'b>>(&'b mut self) -> QueryIter<'b, Q>
+    {
+        for (type_id, mutable) in Q::component_types()
+        {
+            if !self.description.can_access(&type_id, mutable)
+            {
+                panic!("The system {} did not declare {} access to the type {:?}", self.description.name, if mutable { "write" } else { "read" }, type_id);
+            }
+        }
+
+        let component_indexes = self.ecs.query_component_indexes(&Q::component_types());
+
+        // Systems executing at the same time do not access the same component types mutably, and cannot
+        // add or remove components
+        unsafe
+            {
+                ECS::query_from_columns(self.columns.0, &component_indexes, self.ecs.get_change_tick())
+            }
+    }
+
+    /// Get a reference to the component of the given entity. The component type must be declared as
+    /// being read or written by the system
+    ///
+    /// `entity_id` - the entity whose component is being read
+    pub fn get_ref<'b, T: 'static + Serialize + Deserialize<'b>>(&self, entity_id: EntityId) -> Option<&T>
+    {
+        let type_id = TypeIdentifier::from(TypeId::of::<T>());
+
+        if !self.description.can_access(&type_id, false)
+        {
+            panic!("The system {} did not declare read access to the type {:?}", self.description.name, type_id);
+        }
+
+        self.ecs.get_ref::<T>(entity_id)
+    }
+
+    /// Get the world sections that are visible this frame. These are the same world sections the renderer
+    /// draws, so features such as choosing ambient sounds can match what the user sees
+    pub fn get_visible_world_sections(&self) -> &VisibleWorldSections<'a>
+    {
+        self.visible_world_sections
+    }
+}
+
+impl SystemScheduler
+{
+    /// Creates a scheduler without any systems
+    pub fn new() -> SystemScheduler
+    {
+        SystemScheduler{ systems: Vec::new(), stages: Vec::new(), stages_outdated: false }
+    }
+
+    /// Adds a system that is executed every frame, after the logic of entities. The returned description
+    /// is used to declare the component types the system accesses
+    ///
+    /// ```
+    ///  scheduler.add_system("apply_drag", apply_drag)
+    ///     .reads::<Mass>()
+    ///     .writes::<Velocity>();
+    /// ```
+    ///
+    /// `name` - the name of the system, used when reporting errors
+    /// `function` - the function executing the system
+    pub fn add_system<T: Into<String>>(&mut self, name: T, function: SystemFunction) -> &mut SystemDescription
+    {
+        self.stages_outdated = true;
+
+        self.systems.push(SystemDescription{ name: name.into(), function, reads: HashSet::default(), writes: HashSet::default() });
+        self.systems.last_mut().unwrap()
+    }
+
+    /// Get the number of systems that were added
+    pub fn number_systems(&self) -> usize
+    {
+        self.systems.len()
+    }
+
+    /// Executes all of the systems, with systems that do not conflict being executed at the same time. The
+    /// changes returned by the systems are given in the order the systems were added
+    ///
+    /// `ecs` - the ECS the systems operate on
+    /// `visible_world_sections` - the world sections that are visible this frame
+    /// `elapsed_time` - the amount of time that has passed since the last frame in seconds
+    pub fn execute(&mut self, ecs: &mut ECS, visible_world_sections: &VisibleWorldSections, elapsed_time: f32) -> Vec<Vec<EntityChangeInformation>>
+    {
+        if self.stages_outdated
+        {
+            self.build_stages();
+        }
+
+        let mut changes: Vec<Vec<EntityChangeInformation>> = (0..self.systems.len()).map(|_| Vec::new()).collect();
+
+        // The storage of every component type is resolved before any system executes, so that the systems
+        // only share the ECS immutably rather than each of them borrowing it mutably
+        let ecs: *mut ECS = ecs;
+
+        let columns = unsafe
+            {
+                (*ecs).query_columns()
+            };
+
+        let ecs: &ECS = unsafe
+            {
+                &*ecs
+            };
+
+        let shared_columns = SharedColumns(&columns);
+
+        for stage in &self.stages
+        {
+            let stage_changes = stage.par_iter()
+                .map(|index|
+                    {
+                        let mut access = SystemAccess{ ecs, columns: shared_columns, description: &self.systems[*index], visible_world_sections };
+                        (*index, (self.systems[*index].function)(&mut access, elapsed_time))
+                    })
+                .collect::<Vec<(usize, Vec<EntityChangeInformation>)>>();
+
+            for (index, system_changes) in stage_changes
+            {
+                changes[index] = system_changes;
+            }
+        }
+
+        changes
+    }
+
+    /// Groups the systems into stages of systems that can be executed at the same time
+    fn build_stages(&mut self)
+    {
+        let mut system_stages: Vec<usize> = Vec::with_capacity(self.systems.len());
+        self.stages.clear();
+
+        for (index, system) in self.systems.iter().enumerate()
+        {
+            let stage = self.systems[..index].iter()
+                .zip(system_stages.iter())
+                .filter(|(x, _)| x.conflicts_with(system))
+                .map(|(_, stage)| *stage + 1)
+                .max()
+                .unwrap_or(0);
+
+            if stage == self.stages.len()
+            {
+                self.stages.push(Vec::new());
+            }
+
+            self.stages[stage].push(index);
+            system_stages.push(stage);
+        }
+
+        self.stages_outdated = false;
+    }
+}
+
+impl Default for SystemScheduler
+{
+    fn default() -> Self
+    {
+        SystemScheduler::new()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use nalgebra_glm::vec3;
+    use serde::{Serialize, Deserialize};
+    use crate::flows::visible_world_flow::CullResult;
+    use crate::objects::ecs::ECS;
+    use crate::objects::entity_change_request::EntityChangeInformation;
+    use crate::objects::system_scheduler::{SystemAccess, SystemScheduler};
+    use crate::world::bounding_box_tree_v2::BoundingBoxTree;
+    use crate::world::visible_world_sections::VisibleWorldSections;
+
+    #[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+    struct Position(u32);
+
+    #[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+    struct Velocity(u32);
+
+    #[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+    struct Mass(u32);
+
+    fn no_op(_: &mut SystemAccess, _: f32) -> Vec<EntityChangeInformation>
+    {
+        Vec::new()
+    }
+
+    fn apply_velocity(access: &mut SystemAccess, _: f32) -> Vec<EntityChangeInformation>
+    {
+        for (_, (position, velocity)) in access.query::<(&mut Position, &Velocity)>()
+        {
+            position.0 += velocity.0;
+        }
+
+        Vec::new()
+    }
+
+    fn double_mass(access: &mut SystemAccess, _: f32) -> Vec<EntityChangeInformation>
+    {
+        for (_, (mass,)) in access.query::<(&mut Mass,)>()
+        {
+            mass.0 *= 2;
+        }
+
+        Vec::new()
+    }
+
+    fn reset_velocity(access: &mut SystemAccess, _: f32) -> Vec<EntityChangeInformation>
+    {
+        for (_, (velocity,)) in access.query::<(&mut Velocity,)>()
+        {
+            velocity.0 = 0;
+        }
+
+        Vec::new()
+    }
+
+    #[test]
+    fn conflicts_between_systems()
+    {
+        let mut scheduler = SystemScheduler::new();
+        scheduler.add_system("reads_position", no_op).reads::<Position>();
+        scheduler.add_system("also_reads_position", no_op).reads::<Position>();
+        scheduler.add_system("writes_position", no_op).writes::<Position>();
+        scheduler.add_system("writes_velocity", no_op).writes::<Velocity>();
+
+        let systems = &scheduler.systems;
+
+        // Only reading the same type does not conflict
+        assert!(!systems[0].conflicts_with(&systems[1]));
+
+        // Reading and writing the same type conflicts, in either order
+        assert!(systems[0].conflicts_with(&systems[2]));
+        assert!(systems[2].conflicts_with(&systems[0]));
+
+        // Writing the same type conflicts
+        assert!(systems[2].conflicts_with(&systems[2]));
+
+        // Accessing different types does not conflict
+        assert!(!systems[2].conflicts_with(&systems[3]));
+        assert!(!systems[3].conflicts_with(&systems[0]));
+    }
+
+    #[test]
+    fn stages_keep_order_of_conflicting_systems()
+    {
+        let mut scheduler = SystemScheduler::new();
+        scheduler.add_system("apply_velocity", no_op).reads::<Velocity>().writes::<Position>();
+        scheduler.add_system("double_mass", no_op).writes::<Mass>();
+        scheduler.add_system("reset_velocity", no_op).writes::<Velocity>();
+        scheduler.add_system("read_position", no_op).reads::<Position>();
+        scheduler.add_system("read_mass", no_op).reads::<Mass>().reads::<Velocity>();
+
+        scheduler.build_stages();
+
+        // A system is placed after the last stage holding a system it conflicts with, even if an earlier
+        // stage has no conflicting systems
+        assert_eq!(vec![vec![0, 1], vec![2, 3], vec![4]], scheduler.stages);
+    }
+
+    #[test]
+    fn execute_systems_in_parallel()
+    {
+        let mut ecs = ECS::new();
+        ecs.register_type::<Position>();
+        ecs.register_type::<Velocity>();
+        ecs.register_type::<Mass>();
+
+        let entity = ecs.create_entity();
+        ecs.write_component::<Position>(entity, Position(1));
+        ecs.write_component::<Velocity>(entity, Velocity(2));
+        ecs.write_component::<Mass>(entity, Mass(3));
+
+        let mut scheduler = SystemScheduler::new();
+        scheduler.add_system("apply_velocity", apply_velocity).reads::<Velocity>().writes::<Position>();
+        scheduler.add_system("double_mass", double_mass).writes::<Mass>();
+        scheduler.add_system("reset_velocity", reset_velocity).writes::<Velocity>();
+
+        let cull_result = CullResult::new();
+        let bounding_box_tree = BoundingBoxTree::new(0, 0);
+        let visible_world_sections = VisibleWorldSections::new(&cull_result, &bounding_box_tree, vec3(0.0, 0.0, 0.0));
+
+        let changes = scheduler.execute(&mut ecs, &visible_world_sections, 0.0);
+        assert_eq!(3, changes.len());
+
+        // The velocity is reset only after it has been applied, as the systems conflict
+        assert_eq!(Some(Position(3)), ecs.get_copy::<Position>(entity));
+        assert_eq!(Some(Velocity(0)), ecs.get_copy::<Velocity>(entity));
+        assert_eq!(Some(Mass(6)), ecs.get_copy::<Mass>(entity));
+        assert!(ecs.is_changed::<Position>(entity));
+    }
+}
@@ -1,15 +1,17 @@
 use std::mem::size_of;
 use serde::{Serialize, Deserialize};
-use crate::exports::load_models::{MaxNumLights, UserLoadSkyBoxModels};
+use crate::exports::damage_decals::DamageLayer;
+use crate::exports::load_models::{DefaultRenderSystemArgs, MaxNumLights, UserLoadSkyBoxModels};
 use crate::exports::logic_components::RenderSystemIndex;
 use crate::exports::movement_components::TransformationMatrix;
-use crate::exports::rendering::LevelOfView;
+use crate::exports::rendering::{LevelOfView, ScreenSpaceReflections, ShadowQuality};
 use crate::helper_things::environment::{get_asset_folder, get_generated_shaders_folder};
 use crate::models::model_definitions::MeshGeometry;
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
 use crate::render_components::mapped_buffer::{BufferWriteInfo, MappedBuffer};
 use crate::render_system::builder::{MaxLightConstraints, RenderSystemBuilder};
+use crate::render_system::initialize_logic::PreparedRenderSystem;
 use crate::render_system::render_system::{InstancedLayoutWriteFunction, RenderSystem};
 use crate::render_system::system_information::*;
 use crate::specify_model_geometry_layouts;
@@ -17,6 +19,13 @@ use crate::specify_type_ids;
 
 pub const DEFAULT_RENDER_SYSTEM: RenderSystemIndex = RenderSystemIndex{ index: 0};
 
+/// Name the texture array of default render systems is shared under. A custom render system can share
+/// it by sharing a texture array with the same format, dimensions and capacity under this name
+pub const DEFAULT_SHARED_TEXTURE_ARRAY: &str = "defaultTextureArray";
+
+/// Name the solid colour texture array of default render systems is shared under
+pub const DEFAULT_SHARED_SOLID_COLOURS: &str = "defaultSolidColours";
+
 specify_model_geometry_layouts!(model_layout_update_fn,
                                 0, vertices,
                                 1, texture_coords,
@@ -24,7 +33,8 @@ specify_model_geometry_layouts!(model_layout_update_fn,
                                 3, normals);
 
 specify_type_ids!(instance_layout_fn,
-                  4, TransformationMatrix
+                  4, TransformationMatrix,
+                  8, DamageLayer
                    );
 
 pub fn create_default_render_system(draw_function: DrawFunction, light_draw_function: DrawFunction,
@@ -34,12 +44,46 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
                                     sky_boxes: Vec<UserLoadSkyBoxModels>,
                                     max_lights: MaxNumLights,
                                     no_light_source_cutoff: f32,
-                                    default_diffuse_factor: f32) -> RenderSystem
+                                    default_diffuse_factor: f32,
+                                    shadow_quality: ShadowQuality,
+                                    clustered_lighting: bool,
+                                    gpu_level_of_views: bool,
+                                    gpu_culling: bool,
+                                    screen_space_reflections: Option<ScreenSpaceReflections>) -> RenderSystem
+{
+    let default_render_system_args = DefaultRenderSystemArgs
+    {
+        draw_function,
+        draw_light_function: light_draw_function,
+        draw_transparency_function: transparency_draw_function,
+        instance_layout_update_fn,
+        level_of_views,
+        window_resolution,
+        sky_boxes,
+        max_count_lights: max_lights,
+        no_light_source_cutoff,
+        default_diffuse_factor,
+        shadow_quality,
+        clustered_lighting,
+        gpu_level_of_views,
+        gpu_culling,
+        screen_space_reflections
+    };
+
+    let prepared_render_system = prepare_default_render_system(&default_render_system_args);
+    finish_default_render_system(prepared_render_system.build(), default_render_system_args.sky_boxes)
+}
+
+/// Generates the shaders of the default render system without making any OpenGL calls. The render
+/// system built from the result is finished with finish_default_render_system
+///
+/// `args` - the arguments specifying the default render system to prepare
+pub fn prepare_default_render_system(args: &DefaultRenderSystemArgs) -> PreparedRenderSystem
 {
     // TODO: Why does a vec3 variable in uniform block that writes to an out variable not work.
     // TODO: Tested with a vec3 variable that changes skybox brightness
 
-    let mut render_system = RenderSystemBuilder::new()
+    RenderSystemBuilder::new()
         .with_constants(vec!
         [
             Constant::new(ConstantValue::UInt(6), "NUMBER_SHADOW_MAPS", vec![ConstantLocation::VertexShader, ConstantLocation::FragmentShader])
@@ -49,7 +93,7 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
             write_generated_shader: Some(get_generated_shaders_folder().join("first_pass_vertex.glsl").to_str().unwrap().to_string()),
             glsl_version: GLSLVersion::Core430,
             shader_source: get_asset_folder().join("shaders/first_pass_vertex.glsl"),
-            instance_layout_update_fn: Some(instance_layout_update_fn),
+            instance_layout_update_fn: Some(args.instance_layout_update_fn),
             model_layout_update_fn,
             indice_buffers: Some(IndiceInformation::new(1, 103100)),
             textures: vec![],
@@ -81,6 +125,7 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
                 LayoutInformation::new(LayoutType::Vec4Uint, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "layers"),
                 LayoutInformation::new(LayoutType::Vec3Float, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "normal"),
                 LayoutInformation::new(LayoutType::Mat4x4Float, LayoutInstance::Divisor1(2, 1_500_000), LayoutUse::PerInstance, "translation"),
+                LayoutInformation::new(LayoutType::Vec4Uint, LayoutInstance::Divisor1(2, 375_000), LayoutUse::PerInstance, "damageLayer"),
             ],
             out_variables: vec!
             [
@@ -94,6 +139,10 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
                 OutVariables::new(SharedVariableType::Vec4, "textureCoords", false, vec![SharedTarget::FragmentShader]),
                 OutVariables::new(SharedVariableType::UInt, "adjustBrightnessLightSource", true, vec![SharedTarget::FragmentShader]),
                 OutVariables::new(SharedVariableType::UInt, "drawingLightSource", true, vec![SharedTarget::FragmentShader]),
+                OutVariables::new(SharedVariableType::Vec3, "objectPosition", false, vec![SharedTarget::FragmentShader]),
+                OutVariables::new(SharedVariableType::UInt, "damageMaskLayer", true, vec![SharedTarget::FragmentShader]),
+                OutVariables::new(SharedVariableType::Vec4, "instanceTint", true, vec![SharedTarget::FragmentShader]),
+                OutVariables::new(SharedVariableType::Vec4, "dissolveState", true, vec![SharedTarget::FragmentShader]),
             ]
 
         })
@@ -101,10 +150,10 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
         {
             layouts: vec!
             [
-                FragLayoutInformation::new(LayoutType::Vec3Float, TextureFormat::RGBA16F, window_resolution, "gPosition"),
-                FragLayoutInformation::new(LayoutType::Vec3Float, TextureFormat::RGBA16F, window_resolution,"gNormal"),
-                FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA, window_resolution,"gAlbedoSpec"),
-                FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA16F, window_resolution,"gLightPosition")
+                FragLayoutInformation::new(LayoutType::Vec3Float, TextureFormat::RGBA32F, args.window_resolution, "gPosition"),
+                FragLayoutInformation::new(LayoutType::Vec3Float, TextureFormat::RGBA32F, args.window_resolution,"gNormal"),
+                FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA, args.window_resolution,"gAlbedoSpec"),
+                FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA32F, args.window_resolution,"gLightPosition")
             ],
             uniforms: vec![],
             out_variables: vec![],
@@ -147,6 +196,7 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
                 CubeMapInitInfo::new("skyBox")
             ],
             include_shadow_maps: false,
+            include_light_storage_buffers: false,
             include_error_textures: true,
         })
         .with_second_pass_vertex_shader()
@@ -157,37 +207,17 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
             write_generated_shader: Some(get_generated_shaders_folder().join("second_pass_frag.glsl").to_str().unwrap().to_string()),
             include_error_textures: false,
             include_shadow_maps: true,
+            include_light_storage_buffers: true,
             glsl_version: GLSLVersion::Core430,
             shader_source: get_asset_folder().join("shaders/second_pass_frag.glsl"),
             uniforms: vec!
             [
                 UniformBlock::new("LightSources", 4, vec!
                 [
+                    // The lights themselves are stored in the light storage buffers
                     Uniform::new("anyLightSourceVisible", UniformType::UInt),
-                    Uniform::new("directionLightDirection", UniformType::Vec3Array(max_lights.directional)),
-                    Uniform::new("directionLightDiffuseColour", UniformType::Vec3Array(max_lights.directional)),
-                    Uniform::new("directionLightSpecularColour", UniformType::Vec3Array(max_lights.directional)),
-                    Uniform::new("directionLightAmbientColour", UniformType::Vec4Array(max_lights.directional)),
                     Uniform::new("numberDirectionLights", UniformType::UInt),
-
-                    Uniform::new("spotLightPosition", UniformType::Vec3Array(max_lights.spot)),
-                    Uniform::new("spotLightDiffuseColour", UniformType::Vec3Array(max_lights.spot)),
-                    Uniform::new("spotLightSpecularColour", UniformType::Vec3Array(max_lights.spot)),
-                    Uniform::new("spotLightAmbientColour", UniformType::Vec4Array(max_lights.spot)),
-                    Uniform::new("spotLightLinearCoefficient", UniformType::FloatArray(max_lights.spot)),
-                    Uniform::new("spotLightQuadraticCoefficient", UniformType::FloatArray(max_lights.spot)),
-                    Uniform::new("spotLightRadius", UniformType::FloatArray(max_lights.spot)),
                     Uniform::new("numberSpotLights", UniformType::UInt),
-
-                    Uniform::new("pointLightPosition", UniformType::Vec3Array(max_lights.point)),
-                    Uniform::new("pointLightDirection", UniformType::Vec3Array(max_lights.point)),
-                    Uniform::new("pointLightDiffuseColour", UniformType::Vec3Array(max_lights.point)),
-                    Uniform::new("pointLightSpecularColour", UniformType::Vec3Array(max_lights.point)),
-                    Uniform::new("pointLightAmbientColour", UniformType::Vec4Array(max_lights.point)),
-                    Uniform::new("pointLightLinearCoefficient", UniformType::FloatArray(max_lights.point)),
-                    Uniform::new("pointLightQuadraticCoefficient", UniformType::FloatArray(max_lights.point)),
-                    Uniform::new("cutOff", UniformType::FloatArray(max_lights.point)),
-                    Uniform::new("outerCutoff", UniformType::FloatArray(max_lights.point)),
                     Uniform::new("numberPointLights", UniformType::UInt),
 
                     Uniform::new("cameraPosition", UniformType::Vec3),
@@ -207,14 +237,33 @@ pub fn create_default_render_system(draw_function: DrawFunction, light_draw_func
             textures: vec![],
             cubemaps: vec![],
         })
-        .with_draw_functions(draw_function, light_draw_function, transparency_draw_function)
-        .with_level_of_views(level_of_views)
+        .with_draw_functions(args.draw_function.clone(), args.draw_light_function.clone(), args.draw_transparency_function.clone())
+        .with_level_of_views(args.level_of_views.clone())
         .with_accessible_fbos(vec![])
         .apply_nearby_lights()
-        .with_light_constraints(MaxLightConstraints::Constraints(max_lights))
-        .with_no_light_diffuse_param(no_light_source_cutoff, default_diffuse_factor)
-        .build();
+        .with_light_constraints(MaxLightConstraints::Constraints(args.max_count_lights))
+        .with_no_light_diffuse_param(args.no_light_source_cutoff, args.default_diffuse_factor)
+        .with_shadow_quality(args.shadow_quality)
+        .with_clustered_lighting(args.clustered_lighting)
+        .with_baked_lighting(true)
+        .with_environment_lighting(true)
+        .with_damage_masks(true)
+        .with_instance_params(true)
+        .with_dissolve(true)
+        .with_gpu_level_of_views(args.gpu_level_of_views)
+        .with_gpu_culling(args.gpu_culling)
+        .with_screen_space_reflections(args.screen_space_reflections)
+        .with_shared_texture_array("textureArray", DEFAULT_SHARED_TEXTURE_ARRAY)
+        .with_shared_texture_array("solidColour", DEFAULT_SHARED_SOLID_COLOURS)
+        .prepare()
+}
 
+/// Uploads the sky boxes of the default render system once it has been built
+///
+/// `render_system` - the default render system built from the result of prepare_default_render_system
+/// `sky_boxes` - the sky boxes to upload
+pub fn finish_default_render_system(mut render_system: RenderSystem, sky_boxes: Vec<UserLoadSkyBoxModels>) -> RenderSystem
+{
     for x in sky_boxes
     {
         render_system.load_cubemap(x.sky_box_name, x.textures);
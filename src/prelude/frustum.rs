@@ -0,0 +1,211 @@
+use nalgebra_glm::{TMat4x4, TVec3, TVec4, vec4};
+use crate::exports::camera_object::Camera;
+use crate::flows::shared_constants::{ENTITY_SPHERE_CULLING, FRUSTUM_PADDING, SUB_FRUSTUM_REFINEMENT_THRESHOLD};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+use crate::world::bounding_volumes::bounding_sphere::BoundingSphere;
+use crate::world::dimension::range::{XRange, YRange, ZRange};
+
+/// The planes bounding a frustum
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrustumPlane
+{
+    Left = 0, // Indices used to index into an array of vectors representing plane normals
+    Right,
+    Bottom,
+    Top,
+    Near,
+    Far
+}
+
+/// The volume visible to a camera, bounded by six planes. This is the same volume the renderer uses
+/// to decide what to draw, so anything outside of it is not drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum
+{
+    plane_coefficients: [TVec4<f32>; 6],
+}
+
+/// Slack added around world sections when checking if they are visible, so that sections are rendered
+/// slightly before they enter the view. Without padding, turning the camera quickly can show sections
+/// appearing at the edges of the screen
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrustumPadding
+{
+    /// Distance every side of a section is extended by
+    pub distance: f32,
+    /// Angle in degrees the view is widened by. The slack this adds grows with the distance to the
+    /// camera, matching how far a section moves across the screen as the camera turns
+    pub angle: f32,
+}
+
+impl FrustumPadding
+{
+    /// Get the AABB extended by the padding
+    ///
+    /// `aabb` - the AABB to pad
+    /// `camera_position` - the position of the camera the AABB is checked against
+    pub(crate) fn pad_aabb(&self, aabb: &StaticAABB, camera_position: TVec3<f32>) -> StaticAABB
+    {
+        if self.distance <= 0.0 && self.angle <= 0.0
+        {
+            return *aabb;
+        }
+
+        let angle = self.angle.clamp(0.0, 89.0).to_radians();
+        let slack = self.distance.max(0.0) + aabb.distance_to_point(camera_position) * angle.tan();
+
+        StaticAABB::new
+            (
+                XRange::new(aabb.x_range.min - slack, aabb.x_range.max + slack),
+                YRange::new(aabb.y_range.min - slack, aabb.y_range.max + slack),
+                ZRange::new(aabb.z_range.min - slack, aabb.z_range.max + slack)
+            )
+    }
+}
+
+/// Changes the slack added around world sections when checking if they are visible. Larger padding
+/// reduces pop-in at the edges of the screen at the cost of rendering more sections
+///
+/// `padding` - the new frustum padding
+pub fn set_frustum_padding(padding: FrustumPadding)
+{
+    *FRUSTUM_PADDING.lock() = padding;
+}
+
+/// Get the slack added around world sections when checking if they are visible
+pub fn frustum_padding() -> FrustumPadding
+{
+    *FRUSTUM_PADDING.lock()
+}
+
+/// Changes if the bounding sphere of each active entity is checked against the view frustum before it is
+/// rendered, when the world section holding it is only partly in view. This removes entities outside of the
+/// view in sections at the edges of the screen, at the cost of a check per entity in those sections
+///
+/// `enable` - true to check the bounding spheres of entities
+pub fn set_entity_sphere_culling(enable: bool)
+{
+    *ENTITY_SPHERE_CULLING.lock() = enable;
+}
+
+/// Checks if the bounding spheres of entities are checked against the view frustum before they are rendered
+pub fn entity_sphere_culling() -> bool
+{
+    *ENTITY_SPHERE_CULLING.lock()
+}
+
+/// Changes the number of entities above which the entities of a shared world section are checked against the view
+/// frustum individually, rather than all being rendered when the section is visible
+///
+/// `threshold` - the number of entities, or usize::MAX to never check the entities individually
+pub fn set_sub_frustum_refinement_threshold(threshold: usize)
+{
+    *SUB_FRUSTUM_REFINEMENT_THRESHOLD.lock() = threshold;
+}
+
+/// Get the number of entities above which the entities of a shared world section are checked against the view frustum
+pub fn sub_frustum_refinement_threshold() -> usize
+{
+    *SUB_FRUSTUM_REFINEMENT_THRESHOLD.lock()
+}
+
+impl Frustum
+{
+    /// Creates the frustum of the given view projection matrix
+    ///
+    /// `view_projection_matrix` - the projection * view matrix of the camera
+    pub fn new(view_projection_matrix: TMat4x4<f32>) -> Frustum
+    {
+        let view_projection_matrix = nalgebra_glm::transpose(&view_projection_matrix);
+
+        let column = |x: usize|{ view_projection_matrix.column(x).into_owned() };
+
+        let normalize_coefficients = |x: TVec4<f32>|
+            {
+                let length = nalgebra_glm::length(&nalgebra_glm::vec4_to_vec3(&x));
+
+                x / length
+            };
+
+        let mut plane_coefficients = [vec4(0.0, 0.0, 0.0, 0.0); 6];
+
+        plane_coefficients[FrustumPlane::Left as usize] = normalize_coefficients(column(3) + column(0));
+        plane_coefficients[FrustumPlane::Right as usize] = normalize_coefficients(column(3) - column(0));
+        plane_coefficients[FrustumPlane::Bottom as usize] = normalize_coefficients(column(3) + column(1));
+        plane_coefficients[FrustumPlane::Top as usize] = normalize_coefficients(column(3) - column(1));
+        // The near plane passes through the camera rather than the near draw distance, so entities
+        // between the two are still considered visible
+        plane_coefficients[FrustumPlane::Near as usize] = normalize_coefficients(column(3));
+        plane_coefficients[FrustumPlane::Far as usize] = normalize_coefficients(column(3) - column(2));
+
+        Frustum{ plane_coefficients }
+    }
+
+    /// Creates the frustum of what the camera currently sees
+    ///
+    /// `camera` - the camera to get the frustum of
+    pub fn from_camera(camera: &Camera) -> Frustum
+    {
+        Frustum::new(camera.get_projection_matrix() * camera.get_view_matrix())
+    }
+
+    /// Get the coefficients (a, b, c, d) of the plane ax + by + cz + d = 0. The normal (a, b, c) is of
+    /// unit length and points into the frustum
+    ///
+    /// `plane` - the plane to get the coefficients of
+    pub fn plane(&self, plane: FrustumPlane) -> TVec4<f32>
+    {
+        self.plane_coefficients[plane as usize]
+    }
+
+    /// Get the coefficients of all planes, in the order of FrustumPlane
+    pub fn planes(&self) -> &[TVec4<f32>; 6]
+    {
+        &self.plane_coefficients
+    }
+
+    /// Checks if the point is inside the frustum
+    ///
+    /// `point` - the point to check
+    pub fn contains_point(&self, point: &TVec3<f32>) -> bool
+    {
+        self.plane_coefficients.iter().all(|x| Frustum::distance_to_plane(x, point) >= 0.0)
+    }
+
+    /// Checks if any part of the sphere is inside the frustum
+    ///
+    /// `sphere` - the sphere to check
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool
+    {
+        self.plane_coefficients.iter().all(|x| Frustum::distance_to_plane(x, &sphere.centre) >= -sphere.radius)
+    }
+
+    /// Checks if all of the AABB is inside the frustum
+    ///
+    /// `aabb` - the AABB to check
+    pub fn contains_aabb(&self, aabb: &StaticAABB) -> bool
+    {
+        aabb.get_aabb_points().iter().all(|x| self.contains_point(x))
+    }
+
+    /// Checks if any part of the AABB is inside the frustum. Large AABBs near the corners of the frustum
+    /// may be reported as intersecting when they are not, but an AABB inside the frustum is never
+    /// reported as outside of it
+    ///
+    /// `aabb` - the AABB to check
+    pub fn intersects_aabb(&self, aabb: &StaticAABB) -> bool
+    {
+        let aabb_points = aabb.get_aabb_points();
+
+        self.plane_coefficients.iter().all(|plane| aabb_points.iter().any(|point| Frustum::distance_to_plane(plane, point) >= 0.0))
+    }
+
+    /// Get the signed distance from the plane to the point, which is positive on the side the plane faces
+    ///
+    /// `plane` - the coefficients of the plane
+    /// `point` - the point to get the distance to
+    fn distance_to_plane(plane: &TVec4<f32>, point: &TVec3<f32>) -> f32
+    {
+        plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w
+    }
+}
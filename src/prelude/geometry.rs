@@ -0,0 +1,166 @@
+use nalgebra_glm::{TVec3, vec3};
+use crate::prelude::frustum::Frustum;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+use crate::world::bounding_volumes::ray::Ray;
+
+// Geometry queries against AABBs for use in game logic. These are the same tests the engine uses
+// internally, so results agree with what the engine considers visible or colliding
+
+/// How an AABB lies relative to a frustum
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrustumContainment
+{
+    Outside,
+    Intersecting,
+    Inside
+}
+
+/// Checks if the point is within the AABB, including its surface
+///
+/// `aabb` - the volume to check against
+/// `point` - the point to check
+pub fn aabb_contains_point(aabb: &StaticAABB, point: &TVec3<f32>) -> bool
+{
+    point.x >= aabb.x_range.min && point.x <= aabb.x_range.max &&
+        point.y >= aabb.y_range.min && point.y <= aabb.y_range.max &&
+        point.z >= aabb.z_range.min && point.z <= aabb.z_range.max
+}
+
+/// Get the point on or within the AABB that is closest to the given point. A point within the AABB
+/// is its own closest point
+///
+/// `aabb` - the volume to find the closest point on
+/// `point` - the point to find the closest point to
+pub fn closest_point_on_aabb(aabb: &StaticAABB, point: &TVec3<f32>) -> TVec3<f32>
+{
+    vec3
+        (
+            point.x.max(aabb.x_range.min).min(aabb.x_range.max),
+            point.y.max(aabb.y_range.min).min(aabb.y_range.max),
+            point.z.max(aabb.z_range.min).min(aabb.z_range.max)
+        )
+}
+
+/// Get the squared distance from the point to the closest point of the AABB. Cheaper than the distance
+/// when only comparing distances
+///
+/// `aabb` - the volume to find the distance to
+/// `point` - the point to find the distance from
+pub fn distance_squared_to_aabb(aabb: &StaticAABB, point: &TVec3<f32>) -> f32
+{
+    nalgebra_glm::distance2(&closest_point_on_aabb(aabb, point), point)
+}
+
+/// Get the distance from the point to the closest point of the AABB. The distance is zero if the point
+/// is within the AABB
+///
+/// `aabb` - the volume to find the distance to
+/// `point` - the point to find the distance from
+pub fn distance_to_aabb(aabb: &StaticAABB, point: &TVec3<f32>) -> f32
+{
+    distance_squared_to_aabb(aabb, point).sqrt()
+}
+
+/// Get the point on the line segment that is closest to the given point
+///
+/// `start` - the start of the line segment
+/// `end` - the end of the line segment
+/// `point` - the point to find the closest point to
+pub fn closest_point_on_segment(start: &TVec3<f32>, end: &TVec3<f32>, point: &TVec3<f32>) -> TVec3<f32>
+{
+    let segment = end - start;
+    let length_squared = nalgebra_glm::length2(&segment);
+
+    if length_squared <= f32::EPSILON
+    {
+        return *start;
+    }
+
+    let t = (nalgebra_glm::dot(&(point - start), &segment) / length_squared).clamp(0.0, 1.0);
+    start + segment * t
+}
+
+/// Finds the distance along the ray at which it first enters the AABB. If the origin of the ray is
+/// within the AABB then the distance is 0. None is returned if the ray misses the AABB
+///
+/// `ray` - the ray to check
+/// `aabb` - the volume to check for an intersection with the ray
+pub fn ray_aabb_intersection(ray: &Ray, aabb: &StaticAABB) -> Option<f32>
+{
+    ray.intersect_aabb(aabb)
+}
+
+/// Finds the point at which the line segment first enters the AABB, as the fraction (0 to 1) of the way
+/// from the start to the end of the segment. If the start is within the AABB then the fraction is 0.
+/// None is returned if the segment does not touch the AABB
+///
+/// `start` - the start of the line segment
+/// `end` - the end of the line segment
+/// `aabb` - the volume to check for an intersection with the segment
+pub fn segment_aabb_intersection(start: &TVec3<f32>, end: &TVec3<f32>, aabb: &StaticAABB) -> Option<f32>
+{
+    let length = nalgebra_glm::distance(start, end);
+
+    if length <= f32::EPSILON
+    {
+        return if aabb_contains_point(aabb, start) { Some(0.0) } else { None };
+    }
+
+    Ray::new(*start, end - start).intersect_aabb(aabb)
+        .filter(|x| *x <= length)
+        .map(|x| x / length)
+}
+
+/// Checks if any part of the AABB is inside the frustum. Large AABBs near the corners of the frustum
+/// may be reported as intersecting when they are not, but an AABB inside the frustum is never
+/// reported as outside of it
+///
+/// `aabb` - the volume to check
+/// `frustum` - the frustum to check against
+pub fn aabb_intersects_frustum(aabb: &StaticAABB, frustum: &Frustum) -> bool
+{
+    frustum.intersects_aabb(aabb)
+}
+
+/// Determines if the AABB is outside of, partially inside of, or completely inside of the frustum. As with
+/// aabb_intersects_frustum, AABBs near the corners of the frustum may be reported as intersecting when they
+/// are outside of it
+///
+/// `aabb` - the volume to check
+/// `frustum` - the frustum to check against
+pub fn aabb_frustum_containment(aabb: &StaticAABB, frustum: &Frustum) -> FrustumContainment
+{
+    let mut containment = FrustumContainment::Inside;
+
+    for plane in frustum.planes()
+    {
+        // The corners furthest along and furthest against the normal of the plane decide which side of it the AABB is on
+        let positive_corner = vec3
+            (
+                if plane.x >= 0.0 { aabb.x_range.max } else { aabb.x_range.min },
+                if plane.y >= 0.0 { aabb.y_range.max } else { aabb.y_range.min },
+                if plane.z >= 0.0 { aabb.z_range.max } else { aabb.z_range.min }
+            );
+
+        let negative_corner = vec3
+            (
+                if plane.x >= 0.0 { aabb.x_range.min } else { aabb.x_range.max },
+                if plane.y >= 0.0 { aabb.y_range.min } else { aabb.y_range.max },
+                if plane.z >= 0.0 { aabb.z_range.min } else { aabb.z_range.max }
+            );
+
+        let signed_distance = |point: &TVec3<f32>| plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w;
+
+        if signed_distance(&positive_corner) < 0.0
+        {
+            return FrustumContainment::Outside;
+        }
+
+        if signed_distance(&negative_corner) < 0.0
+        {
+            containment = FrustumContainment::Intersecting;
+        }
+    }
+
+    containment
+}
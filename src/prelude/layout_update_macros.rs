@@ -18,7 +18,7 @@ macro_rules! specify_type_ids
                                 buffer_write_destination.push(0);
                             }
                             *(buffer_write_destination.as_ptr().offset(write_index) as *mut $associated_type) =
-                             ecs.get_copy::<$associated_type>(entity_index).unwrap();
+                             $crate::exports::entity_interpolation::render_copy::<$associated_type>(ecs, entity_index);
                         }
                     },
                 )+
@@ -1,2 +1,5 @@
 pub mod layout_update_macros;
 pub mod default_render_system;
+pub mod pbr_render_system;
+pub mod frustum;
+pub mod geometry;
\ No newline at end of file
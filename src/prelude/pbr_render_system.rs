@@ -0,0 +1,254 @@
+use std::mem::size_of;
+use crate::exports::load_models::{MaxNumLights, UserLoadSkyBoxModels};
+use crate::exports::movement_components::TransformationMatrix;
+use crate::exports::pbr_material::{PbrAlbedo, PbrSurface};
+use crate::exports::rendering::{LevelOfView, ShadowQuality};
+use crate::helper_things::environment::{get_asset_folder, get_generated_shaders_folder};
+use crate::models::model_definitions::MeshGeometry;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::prelude::default_render_system::finish_default_render_system;
+use crate::render_components::mapped_buffer::{BufferWriteInfo, MappedBuffer};
+use crate::render_system::builder::{MaxLightConstraints, RenderSystemBuilder};
+use crate::render_system::render_system::RenderSystem;
+use crate::render_system::system_information::*;
+use crate::specify_model_geometry_layouts;
+use crate::specify_type_ids;
+
+specify_model_geometry_layouts!(pbr_model_layout_update_fn,
+                                0, vertices,
+                                1, texture_coords,
+                                2, texture_location,
+                                3, normals,
+                                4, tangents,
+                                5, bitangents);
+
+specify_type_ids!(pbr_instance_layout_fn,
+                  6, TransformationMatrix,
+                  10, PbrAlbedo,
+                  11, PbrSurface
+                   );
+
+/// Arguments specifying a render system that renders physically based materials
+#[derive(Clone)]
+pub struct PbrRenderSystemArgs
+{
+    pub draw_function: DrawFunction,
+    pub draw_light_function: DrawFunction,
+    pub draw_transparency_function: DrawFunction,
+    pub level_of_views: Vec<LevelOfView>,
+    pub window_resolution: (i32, i32),
+    pub sky_boxes: Vec<UserLoadSkyBoxModels>,
+    pub max_count_lights: MaxNumLights,
+    pub no_light_source_cutoff: f32,
+    pub default_diffuse_factor: f32,
+    pub shadow_quality: ShadowQuality,
+    pub clustered_lighting: bool,
+    /// The largest size of the material maps of the models of the render system
+    pub texture_resolution: (i32, i32),
+    /// The number of material maps that can be loaded into the render system
+    pub number_textures: i32,
+}
+
+/// Creates a render system that renders the physically based materials of its models, lit by the same
+/// lights as the default render system. The draw functions of the default render system can be used. Models
+/// uploaded to the render system have all of their material maps loaded; see the PbrMaterial component
+///
+/// `args` - the arguments specifying the render system to create
+pub fn create_pbr_render_system(args: PbrRenderSystemArgs) -> RenderSystem
+{
+    let render_system = RenderSystemBuilder::new()
+        .with_constants(vec!
+        [
+            Constant::new(ConstantValue::UInt(6), "NUMBER_SHADOW_MAPS", vec![ConstantLocation::VertexShader, ConstantLocation::FragmentShader])
+        ])
+        .with_pbr_shaders(args.window_resolution, args.texture_resolution, args.number_textures)
+        .with_second_pass_vertex_shader()
+        .with_pbr_second_pass_fragment_shader()
+        .with_draw_functions(args.draw_function, args.draw_light_function, args.draw_transparency_function)
+        .with_level_of_views(args.level_of_views)
+        .with_accessible_fbos(vec![])
+        .apply_nearby_lights()
+        .with_light_constraints(MaxLightConstraints::Constraints(args.max_count_lights))
+        .with_no_light_diffuse_param(args.no_light_source_cutoff, args.default_diffuse_factor)
+        .with_shadow_quality(args.shadow_quality)
+        .with_clustered_lighting(args.clustered_lighting)
+        .with_baked_lighting(true)
+        .with_environment_lighting(true)
+        .with_tangent_space(true)
+        .build();
+
+    // The sky box of the PBR shaders is controlled the same way as that of the default render system
+    finish_default_render_system(render_system, args.sky_boxes)
+}
+
+/// Creates the first pass vertex shader of a render system using the PBR shaders. The uniforms match those
+/// of the default render system, so the same draw functions can be used with both
+pub fn pbr_vertex_shader() -> VertexShaderInformation
+{
+    VertexShaderInformation
+    {
+        write_generated_shader: Some(get_generated_shaders_folder().join("pbr_first_pass_vertex.glsl").to_str().unwrap().to_string()),
+        glsl_version: GLSLVersion::Core430,
+        shader_source: get_asset_folder().join("shaders/pbr_first_pass_vertex.glsl"),
+        instance_layout_update_fn: Some(pbr_instance_layout_fn),
+        model_layout_update_fn: pbr_model_layout_update_fn,
+        indice_buffers: Some(IndiceInformation::new(1, 103100)),
+        textures: vec![],
+        cubemaps: vec![],
+        uniforms: vec!
+        [
+            UniformBlock::new("Matrices", 4, vec!
+            [
+                Uniform::new("projectionMatrix", UniformType::Mat4x4Float),
+                Uniform::new("viewMatrix", UniformType::Mat4x4Float),
+                Uniform::new("cameraLocation", UniformType::Vec3),
+                Uniform::new("renderingSkybox", UniformType::Int),
+                Uniform::new("drawOutline", UniformType::UInt),
+                Uniform::new("lightSource", UniformType::UInt),
+                Uniform::new("renderingLightSource", UniformType::UInt),
+            ]),
+
+            UniformBlock::new("LightMatrices", 4, vec!
+            [
+                Uniform::new("lightMatrices", UniformType::Mat4Array(6)),
+                Uniform::new("lightViewMatrices", UniformType::Mat4Array(6)),
+                Uniform::new("numberLightMatrices", UniformType::UInt),
+            ])
+        ],
+        layout_info: vec!
+        [
+            LayoutInformation::new(LayoutType::Vec3Float, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "aPos"),
+            LayoutInformation::new(LayoutType::Vec4Float, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "texCoords"),
+            LayoutInformation::new(LayoutType::Vec4Uint, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "layers"),
+            LayoutInformation::new(LayoutType::Vec3Float, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "normal"),
+            LayoutInformation::new(LayoutType::Tangent, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "tangent"),
+            LayoutInformation::new(LayoutType::Bitangent, LayoutInstance::Divisor0(1, 1_000_000), LayoutUse::PerModel, "bitangent"),
+            LayoutInformation::new(LayoutType::Mat4x4Float, LayoutInstance::Divisor1(2, 1_500_000), LayoutUse::PerInstance, "translation"),
+            LayoutInformation::new(LayoutType::Vec4Float, LayoutInstance::Divisor1(2, 375_000), LayoutUse::PerInstance, "pbrAlbedo"),
+            LayoutInformation::new(LayoutType::Vec4Float, LayoutInstance::Divisor1(2, 375_000), LayoutUse::PerInstance, "pbrSurface"),
+        ],
+        out_variables: vec!
+        [
+            OutVariables::new(SharedVariableType::Int, "useSkyboxTexture", true, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec3, "skyBoxTexCoords", false, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec3, "fragPosition", false, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec3, "normalizedVertexNormal", false, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec3, "cameraPosition", false, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec4Array(6), "lightFragPos", false, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::UVec4, "textureLayer", true, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec4, "textureCoords", false, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::UInt, "adjustBrightnessLightSource", true, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::UInt, "drawingLightSource", true, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec4, "materialAlbedo", true, vec![SharedTarget::FragmentShader]),
+            OutVariables::new(SharedVariableType::Vec4, "materialSurface", true, vec![SharedTarget::FragmentShader]),
+        ]
+    }
+}
+
+/// Creates the first pass fragment shader of a render system using the PBR shaders, which writes the
+/// material of every fragment into the g-buffer
+///
+/// `window_resolution` - the initial size of the g-buffer
+/// `texture_resolution` - the largest size of the material maps of the models of the render system
+/// `number_textures` - the number of material maps that can be loaded into the render system
+pub fn pbr_first_pass_fragment_shader(window_resolution: (i32, i32), texture_resolution: (i32, i32), number_textures: i32) -> FragmentShaderInformation
+{
+    FragmentShaderInformation
+    {
+        layouts: vec!
+        [
+            FragLayoutInformation::new(LayoutType::Vec3Float, TextureFormat::RGBA32F, window_resolution, "gPosition"),
+            FragLayoutInformation::new(LayoutType::Vec3Float, TextureFormat::RGBA32F, window_resolution,"gNormal"),
+            FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA, window_resolution,"gAlbedoSpec"),
+            FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA32F, window_resolution,"gLightPosition"),
+            // Metallic, roughness, ambient occlusion and 1 if the fragment is lit
+            FragLayoutInformation::new(LayoutType::Vec4Float, TextureFormat::RGBA, window_resolution,"gMaterial")
+        ],
+        uniforms: vec![],
+        out_variables: vec![],
+        write_generated_shader: Some(get_generated_shaders_folder().join("pbr_first_pass_frag.glsl").to_str().unwrap().to_string()),
+        glsl_version: GLSLVersion::Core430,
+        shader_source: get_asset_folder().join("shaders/pbr_first_pass_frag.glsl"),
+        textures: vec!
+        [
+            TextureInformation
+            {
+                sampler_name: "textureArray".to_string(),
+                number_mipmaps: 5,
+                format: TextureFormat::RGBA,
+                min_filter_options: MinFilterOptions::Linear,
+                mag_filter_options: MagFilterOptions::Linear,
+                wrap_s: TextureWrap::MirroredRepeat,
+                wrap_t: TextureWrap::MirroredRepeat,
+                width: texture_resolution.0,
+                height: texture_resolution.1,
+                number_textures,
+                border_color: None
+            },
+            TextureInformation
+            {
+                sampler_name: "solidColour".to_string(),
+                number_mipmaps: 1,
+                format: TextureFormat::RGBA,
+                min_filter_options: MinFilterOptions::Nearest,
+                mag_filter_options: MagFilterOptions::Nearest,
+                wrap_s: TextureWrap::ClampToEdge,
+                wrap_t: TextureWrap::ClampToEdge,
+                width: 1,
+                height: 1,
+                number_textures: 25,
+                border_color: None
+            }
+        ],
+        cubemaps: vec!
+        [
+            CubeMapInitInfo::new("skyBox")
+        ],
+        include_shadow_maps: false,
+        include_light_storage_buffers: false,
+        include_error_textures: true,
+    }
+}
+
+/// Creates the second pass fragment shader of a render system using the PBR shaders, which lights the
+/// materials in the g-buffer. The uniforms match those of the default render system
+pub fn pbr_second_pass_fragment_shader() -> FragmentShaderInformation
+{
+    FragmentShaderInformation
+    {
+        layouts: vec![],
+        out_variables: vec![OutVariables::new(SharedVariableType::Vec4, "FragColor", false, vec![])],
+        write_generated_shader: Some(get_generated_shaders_folder().join("pbr_second_pass_frag.glsl").to_str().unwrap().to_string()),
+        include_error_textures: false,
+        include_shadow_maps: true,
+        include_light_storage_buffers: true,
+        glsl_version: GLSLVersion::Core430,
+        shader_source: get_asset_folder().join("shaders/pbr_second_pass_frag.glsl"),
+        uniforms: vec!
+        [
+            UniformBlock::new("LightSources", 4, vec!
+            [
+                // The lights themselves are stored in the light storage buffers
+                Uniform::new("anyLightSourceVisible", UniformType::UInt),
+                Uniform::new("numberDirectionLights", UniformType::UInt),
+                Uniform::new("numberSpotLights", UniformType::UInt),
+                Uniform::new("numberPointLights", UniformType::UInt),
+
+                Uniform::new("cameraPosition", UniformType::Vec3),
+                Uniform::new("fragDrawOutline", UniformType::UInt),
+                Uniform::new("noLightSourceCutoff", UniformType::Float),
+                Uniform::new("defaultDiffuseFactor", UniformType::Float),
+                Uniform::new("renderSkybox", UniformType::UInt)
+            ]),
+
+            UniformBlock::new("LightIndexes", 4, vec!
+            [
+                Uniform::new("lightIndexes", UniformType::UIntArray(6)),
+                Uniform::new("numberLightIndexes", UniformType::UInt)
+            ]),
+        ],
+        textures: vec![],
+        cubemaps: vec![],
+    }
+}
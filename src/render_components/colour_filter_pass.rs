@@ -0,0 +1,136 @@
+use std::ffi::CString;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+
+/// Texture unit the copy of the rendered frame is bound to when filtering it
+const SCENE_TEXTURE_UNIT: u32 = 0;
+
+/// Applies a colour matrix to every pixel of the frame rendered into the window. The frame is copied into
+/// a texture, which is then drawn back into the window with the matrix applied
+pub struct ColourFilterPass
+{
+    shader_program: ShaderProgram,
+    vao: VAO,
+    scene_texture: u32,
+    scene_texture_dimensions: (i32, i32),
+    colour_matrix_location: i32,
+}
+
+impl ColourFilterPass
+{
+    /// Creates the resources required to filter the frame. An OpenGL context must be current
+    pub fn new() -> Result<ColourFilterPass, String>
+    {
+        let version = "#version 430 core\n";
+        let shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/colour_filter_frag.glsl"), Some(version), None)?,
+        ];
+
+        let shader_program = ShaderProgram::new(&shaders)?;
+
+        let (colour_matrix_location, scene_texture_location) = unsafe
+            {
+                let colour_matrix_name = CString::new("colourMatrix").unwrap();
+                let scene_texture_name = CString::new("sceneTexture").unwrap();
+
+                (gl::GetUniformLocation(shader_program.shader_program, colour_matrix_name.as_ptr()),
+                 gl::GetUniformLocation(shader_program.shader_program, scene_texture_name.as_ptr()))
+            };
+
+        unsafe
+            {
+                gl::ProgramUniform1i(shader_program.shader_program, scene_texture_location, SCENE_TEXTURE_UNIT as i32);
+            }
+
+        Ok(ColourFilterPass{ shader_program, vao: VAO::new(), scene_texture: 0, scene_texture_dimensions: (0, 0), colour_matrix_location })
+    }
+
+    /// Applies the colour matrix to the frame rendered into the window. The OpenGL state is the same
+    /// after this call as before it
+    ///
+    /// `colour_matrix` - the matrix to multiply the colour of every pixel by, in column major order
+    /// `window_dimensions` - the dimensions of the window
+    pub fn apply(&mut self, colour_matrix: &[f32; 9], window_dimensions: (i32, i32))
+    {
+        if window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        self.resize_scene_texture(window_dimensions);
+
+        unsafe
+            {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::CopyTextureSubImage2D(self.scene_texture, 0, 0, 0, 0, 0, window_dimensions.0, window_dimensions.1);
+
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.shader_program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                gl::UniformMatrix3fv(self.colour_matrix_location, 1, gl::FALSE, colour_matrix.as_ptr());
+                gl::BindTextureUnit(SCENE_TEXTURE_UNIT, self.scene_texture);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                gl::BindTextureUnit(SCENE_TEXTURE_UNIT, 0);
+            }
+    }
+
+    /// Recreates the texture the frame is copied into if the window dimensions changed
+    ///
+    /// `window_dimensions` - the dimensions of the window
+    fn resize_scene_texture(&mut self, window_dimensions: (i32, i32))
+    {
+        if self.scene_texture != 0 && self.scene_texture_dimensions == window_dimensions
+        {
+            return;
+        }
+
+        unsafe
+            {
+                if self.scene_texture != 0
+                {
+                    gl::DeleteTextures(1, &self.scene_texture);
+                }
+
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut self.scene_texture);
+                gl::TextureStorage2D(self.scene_texture, 1, gl::RGBA8, window_dimensions.0, window_dimensions.1);
+                gl::TextureParameteri(self.scene_texture, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                gl::TextureParameteri(self.scene_texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl::TextureParameteri(self.scene_texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(self.scene_texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+
+        self.scene_texture_dimensions = window_dimensions;
+    }
+}
+
+impl Drop for ColourFilterPass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                if self.scene_texture != 0
+                {
+                    gl::DeleteTextures(1, &self.scene_texture);
+                }
+
+                gl::DeleteProgram(self.shader_program.shader_program);
+            }
+    }
+}
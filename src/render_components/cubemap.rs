@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use hashbrown::HashSet;
 use stb_image::stb_image::bindgen::{stbi_image_free, stbi_load};
 use crate::helper_things::environment::path_to_bytes;
+use crate::render_components::environment_lighting::equirectangular_to_cubemap;
 
 /// Represents a cubemap, holding the resource and logic to create and use one
 pub struct CubeMap
@@ -37,12 +38,29 @@ impl CubeMap
         CubeMap{ buffer, binding_point }
     }
 
+    /// Creates a cubemap from an equirectangular HDR image. This is a blocking operation
+    ///
+    /// `environment_map` - the location of the equirectangular image
+    /// `resolution` - the width of each face of the cubemap
+    /// `binding_point` - the sampler binding point for the cubemap
+    pub fn from_equirectangular(environment_map: &PathBuf, resolution: u32, binding_point: u32) -> Result<CubeMap, String>
+    {
+        let buffer = equirectangular_to_cubemap(environment_map, resolution.max(1) as i32, 1)?;
+        Ok(CubeMap{ buffer, binding_point })
+    }
+
     /// Binds the cube map texture to the Texture Cube Map target
     pub fn bind(&mut self)
     {
         unsafe { gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.buffer); }
     }
 
+    /// Binds the cube map texture to the texture unit of its sampler binding point
+    pub fn bind_to_binding_point(&self)
+    {
+        unsafe { gl::BindTextureUnit(self.binding_point, self.buffer); }
+    }
+
     /// Uploads the given textures to the cube map. This is a blocking operation.
     /// There must be 6 textures to load, all of the same format, in the following order:
     ///
@@ -124,3 +142,11 @@ impl CubeMap
         Ok(CubeMapUploadResult::Success)
     }
 }
+
+impl Drop for CubeMap
+{
+    fn drop(&mut self)
+    {
+        unsafe { gl::DeleteTextures(1, &self.buffer); }
+    }
+}
@@ -0,0 +1,96 @@
+use std::ffi::c_void;
+use crate::exports::damage_decals::{DAMAGE_MASK_HEIGHT, DAMAGE_MASK_WIDTH, MAX_DAMAGED_ENTITIES};
+
+/// The texture unit the damage masks are bound to, below the texture unit of the lightmap
+pub const DAMAGE_MASK_TEXTURE_UNIT: u32 = 14;
+
+/// GLSL declarations of the damage masks and the function to apply them. The mask maps directions from the
+/// centre of the model, matching the mapping used when hits are accumulated into the mask
+pub const DAMAGE_MASK_DECLARATIONS: &str =
+"#define DAMAGE_MASKS
+
+layout (binding = 14) uniform sampler2DArray damageMasks;
+
+const vec3 SCORCH_COLOUR = vec3(0.05, 0.04, 0.03);
+const float PI = 3.14159265;
+
+vec3 applyDamage(vec3 colour, uint damageLayer, vec3 objectPosition)
+{
+\tif(damageLayer == 0u || dot(objectPosition, objectPosition) == 0.0)
+\t{
+\t\treturn colour;
+\t}
+
+\tvec3 direction = normalize(objectPosition);
+\tvec2 maskCoords = vec2(atan(direction.z, direction.x) / (2.0 * PI) + 0.5, asin(clamp(direction.y, -1.0, 1.0)) / PI + 0.5);
+\tfloat scorch = texture(damageMasks, vec3(maskCoords, float(damageLayer - 1u))).r;
+
+\treturn mix(colour, SCORCH_COLOUR, scorch * 0.9);
+}
+";
+
+/// The damage masks of all damaged entities, stored as the layers of a texture array
+pub struct DamageMaskArray
+{
+    texture: u32,
+}
+
+impl DamageMaskArray
+{
+    /// Creates the texture array with a layer for every entity that can be damaged at once
+    pub fn new() -> DamageMaskArray
+    {
+        let mut texture: u32 = 0;
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_2D_ARRAY, 1, &mut texture);
+                gl::TextureStorage3D(texture, 1, gl::R8, DAMAGE_MASK_WIDTH as i32, DAMAGE_MASK_HEIGHT as i32, MAX_DAMAGED_ENTITIES as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                // The mask wraps around the model horizontally
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+
+        DamageMaskArray{ texture }
+    }
+
+    /// Replaces the contents of a layer with the given mask
+    ///
+    /// `layer` - the layer of the damaged entity
+    /// `mask` - the scorch of every texel of the mask, stored row by row from the bottom of the model
+    pub fn upload_layer(&mut self, layer: usize, mask: &[u8])
+    {
+        debug_assert_eq!(mask.len(), DAMAGE_MASK_WIDTH * DAMAGE_MASK_HEIGHT);
+
+        unsafe
+            {
+                // Rows of the mask are not a multiple of four bytes wide for every mask size
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl::TextureSubImage3D(self.texture, 0, 0, 0, layer as i32, DAMAGE_MASK_WIDTH as i32, DAMAGE_MASK_HEIGHT as i32, 1,
+                                      gl::RED, gl::UNSIGNED_BYTE, mask.as_ptr() as *const c_void);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            }
+    }
+
+    /// Binds the damage masks for first render passes to sample
+    pub fn bind(&self)
+    {
+        unsafe
+            {
+                gl::BindTextureUnit(DAMAGE_MASK_TEXTURE_UNIT, self.texture);
+            }
+    }
+}
+
+impl Drop for DamageMaskArray
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteTextures(1, &self.texture);
+            }
+    }
+}
@@ -0,0 +1,159 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use nalgebra_glm::{TMat4, TVec3};
+use crate::exports::debug_draw::DebugLine;
+use crate::exports::world_precision::{to_f64, to_render_space};
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the G-Buffer layout holding world positions is bound to when drawing debug lines
+pub const DEBUG_LINE_POSITION_TEXTURE_UNIT: u32 = 0;
+
+/// Shader storage binding point of the vertices of the lines, after the one used by text
+const DEBUG_LINE_BINDING_POINT: u32 = 20;
+
+/// The view of the scene the lines are drawn for
+pub struct DebugLineView
+{
+    pub camera_position: TVec3<f32>,
+    pub view_matrix: TMat4<f32>,
+    pub projection_matrix: TMat4<f32>,
+    /// If false, no world positions are bound and every line is drawn over all geometry
+    pub positions_available: bool,
+}
+
+/// A vertex of a line, laid out as the DebugVertex struct of the debug line vertex shader
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DebugVertex
+{
+    position_depth_tested: [f32; 4],
+    colour: [f32; 4],
+}
+
+/// Locations of the uniforms of the debug line shaders
+struct DebugLineUniforms
+{
+    view_projection: i32,
+    camera_position: i32,
+    window_dimensions: i32,
+    positions_available: i32,
+}
+
+/// Draws the lines queued for debugging into the window in one draw call
+pub struct DebugLinePass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    uniforms: DebugLineUniforms,
+    vertex_buffer: u32,
+}
+
+impl DebugLinePass
+{
+    /// Creates the resources required to draw debug lines. An OpenGL context must be current
+    pub fn new() -> Result<DebugLinePass, String>
+    {
+        let version = "#version 430 core\n";
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/debug_line_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/debug_line_frag.glsl"), Some(version), None)?,
+        ])?;
+
+        let uniforms = DebugLineUniforms
+        {
+            view_projection: program.uniform_location("viewProjection"),
+            camera_position: program.uniform_location("cameraPosition"),
+            window_dimensions: program.uniform_location("windowDimensions"),
+            positions_available: program.uniform_location("positionsAvailable"),
+        };
+
+        let mut vertex_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("gPosition"), DEBUG_LINE_POSITION_TEXTURE_UNIT as i32);
+                gl::CreateBuffers(1, &mut vertex_buffer);
+            }
+
+        Ok(DebugLinePass{ program, vao: VAO::new(), uniforms, vertex_buffer })
+    }
+
+    /// Draws the lines into the window. If world positions are available, they must be bound to their texture
+    /// unit. The OpenGL state is the same after this call as before it
+    ///
+    /// `lines` - the lines to draw, in world space
+    /// `view` - the view of the scene the lines are drawn for
+    /// `output_framebuffer` - the frame buffer the lines are drawn into
+    pub fn apply(&mut self, lines: &[DebugLine], view: &DebugLineView, output_framebuffer: OutputFramebuffer)
+    {
+        let window_dimensions = output_framebuffer.dimensions;
+
+        if lines.is_empty() || window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        // Lines are given in world space, while the camera and the world positions of the frame are relative to the render origin
+        let mut vertices = Vec::with_capacity(lines.len() * 2);
+
+        for line in lines
+        {
+            let depth_tested = if line.depth_tested { 1.0 } else { 0.0 };
+            let colour = [line.colour.x, line.colour.y, line.colour.z, line.colour.w];
+
+            for point in &[line.start, line.end]
+            {
+                let position = to_render_space(to_f64(*point));
+                vertices.push(DebugVertex{ position_depth_tested: [position.x, position.y, position.z, depth_tested], colour });
+            }
+        }
+
+        let _state_guard = GLStateGuard::new();
+        let view_projection = view.projection_matrix * view.view_matrix;
+
+        unsafe
+            {
+                gl::NamedBufferData(self.vertex_buffer, (vertices.len() * size_of::<DebugVertex>()) as isize, vertices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, DEBUG_LINE_BINDING_POINT, self.vertex_buffer);
+
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                gl::UniformMatrix4fv(self.uniforms.view_projection, 1, gl::FALSE, view_projection.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_position, 1, view.camera_position.as_ptr());
+                gl::Uniform2f(self.uniforms.window_dimensions, window_dimensions.0 as f32, window_dimensions.1 as f32);
+                gl::Uniform1i(self.uniforms.positions_available, view.positions_available as i32);
+                gl::DrawArrays(gl::LINES, 0, vertices.len() as i32);
+            }
+    }
+}
+
+impl Drop for DebugLinePass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteBuffers(1, &self.vertex_buffer);
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
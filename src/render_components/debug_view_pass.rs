@@ -0,0 +1,204 @@
+use crate::exports::frame_statistics::record_draw_calls;
+use crate::exports::load_models::DebugRenderMode;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::frame_buffer::FBO;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the G-Buffer layout being shown is bound to
+const G_BUFFER_TEXTURE_UNIT: u32 = 0;
+
+/// Index of the G-Buffer layout holding normals in the first pass of the default render systems
+const NORMAL_LAYOUT_INDEX: usize = 1;
+
+/// Index of the G-Buffer layout holding the colour of fragments in the first pass of the default render systems
+const ALBEDO_LAYOUT_INDEX: usize = 2;
+
+/// Colour of pixels drawn the given number of times, with the last colour used for pixels drawn that many
+/// times or more
+const OVERDRAW_COLOURS: [[f32; 4]; 8] =
+[
+    [0.0, 0.0, 0.0, 1.0],
+    [0.0, 0.0, 0.6, 1.0],
+    [0.0, 0.5, 1.0, 1.0],
+    [0.0, 0.8, 0.3, 1.0],
+    [0.9, 0.9, 0.0, 1.0],
+    [1.0, 0.5, 0.0, 1.0],
+    [1.0, 0.0, 0.0, 1.0],
+    [1.0, 1.0, 1.0, 1.0],
+];
+
+/// Values of the viewMode uniform of the debug view shader
+const VIEW_MODE_COLOUR: i32 = 0;
+const VIEW_MODE_NORMALS: i32 = 1;
+const VIEW_MODE_HEAT: i32 = 2;
+
+/// Locations of the uniforms of the debug view shader
+struct DebugViewUniforms
+{
+    view_mode: i32,
+    heat_colour: i32,
+}
+
+/// Draws what a debug render mode shows in place of the lit frame of a render system, using the G-Buffer
+/// written by its first pass
+pub struct DebugViewPass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    uniforms: DebugViewUniforms,
+}
+
+impl DebugViewPass
+{
+    /// Creates the resources required to draw debug views. An OpenGL context must be current
+    pub fn new() -> Result<DebugViewPass, String>
+    {
+        let version = "#version 430 core\n";
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/debug_view_frag.glsl"), Some(version), None)?,
+        ])?;
+
+        let uniforms = DebugViewUniforms
+        {
+            view_mode: program.uniform_location("viewMode"),
+            heat_colour: program.uniform_location("heatColour"),
+        };
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("gBufferLayout"), G_BUFFER_TEXTURE_UNIT as i32);
+            }
+
+        Ok(DebugViewPass{ program, vao: VAO::new(), uniforms })
+    }
+
+    /// Checks if the debug render mode can be shown from the given G-Buffer
+    ///
+    /// `mode` - the debug render mode to check
+    /// `g_buffer` - the G-Buffer written by the first pass of the render system
+    pub fn supports(mode: DebugRenderMode, g_buffer: &FBO) -> bool
+    {
+        match mode
+        {
+            DebugRenderMode::Shaded | DebugRenderMode::Wireframe | DebugRenderMode::Overdraw => true,
+            DebugRenderMode::Normals => g_buffer.has_colour_texture(NORMAL_LAYOUT_INDEX),
+            DebugRenderMode::MipmapLevel => g_buffer.has_colour_texture(ALBEDO_LAYOUT_INDEX),
+        }
+    }
+
+    /// Draws the debug view into the output frame buffer, replacing the lit frame of the render system. For
+    /// the overdraw mode, the stencil of the G-Buffer must hold the number of times each pixel was drawn. The
+    /// OpenGL state is the same after this call as before it, other than the stencil of the output frame buffer
+    ///
+    /// `mode` - the debug render mode to show
+    /// `g_buffer` - the G-Buffer written by the first pass of the render system
+    /// `output_framebuffer` - the frame buffer the debug view is drawn into
+    pub fn apply(&mut self, mode: DebugRenderMode, g_buffer: &mut FBO, output_framebuffer: OutputFramebuffer)
+    {
+        let (width, height) = output_framebuffer.dimensions;
+
+        if width <= 0 || height <= 0
+        {
+            return;
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        unsafe
+            {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, width, height);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+
+        match mode
+        {
+            DebugRenderMode::Normals =>
+                {
+                    g_buffer.bind_colour_texture_to_specific_texture_unit(NORMAL_LAYOUT_INDEX, G_BUFFER_TEXTURE_UNIT);
+                    self.draw_fullscreen(VIEW_MODE_NORMALS, [0.0; 4]);
+                },
+            DebugRenderMode::MipmapLevel =>
+                {
+                    g_buffer.bind_colour_texture_to_specific_texture_unit(ALBEDO_LAYOUT_INDEX, G_BUFFER_TEXTURE_UNIT);
+                    self.draw_fullscreen(VIEW_MODE_COLOUR, [0.0; 4]);
+                },
+            DebugRenderMode::Overdraw => self.draw_overdraw(g_buffer, output_framebuffer),
+            DebugRenderMode::Shaded | DebugRenderMode::Wireframe => {}
+        }
+    }
+
+    /// Colours every pixel by the number of times it was drawn, which is held in the stencil of the G-Buffer.
+    /// Each number of times is drawn separately, with the stencil test only passing for pixels drawn that
+    /// many times
+    ///
+    /// `g_buffer` - the G-Buffer written by the first pass of the render system
+    /// `output_framebuffer` - the frame buffer the debug view is drawn into
+    fn draw_overdraw(&mut self, g_buffer: &FBO, output_framebuffer: OutputFramebuffer)
+    {
+        let (width, height) = output_framebuffer.dimensions;
+        let last_count = OVERDRAW_COLOURS.len() - 1;
+
+        unsafe
+            {
+                gl::BlitNamedFramebuffer(g_buffer.get_raw_resource(), output_framebuffer.framebuffer, 0, 0, width, height,
+                                         0, 0, width, height, gl::STENCIL_BUFFER_BIT, gl::NEAREST);
+
+                gl::Enable(gl::STENCIL_TEST);
+                gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+            }
+
+        for (count, colour) in OVERDRAW_COLOURS.iter().enumerate()
+        {
+            // The reference is compared against the stencil, so LEQUAL passes for pixels drawn at least that many times
+            let function = if count == last_count { gl::LEQUAL } else { gl::EQUAL };
+            unsafe { gl::StencilFunc(function, count as i32, 0xFF); }
+            self.draw_fullscreen(VIEW_MODE_HEAT, *colour);
+        }
+
+        unsafe
+            {
+                gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+            }
+    }
+
+    /// Draws a triangle covering the output frame buffer
+    ///
+    /// `view_mode` - what the shader shows
+    /// `heat_colour` - the colour drawn when the shader fills with a single colour
+    fn draw_fullscreen(&self, view_mode: i32, heat_colour: [f32; 4])
+    {
+        unsafe
+            {
+                gl::Uniform1i(self.uniforms.view_mode, view_mode);
+                gl::Uniform4fv(self.uniforms.heat_colour, 1, heat_colour.as_ptr());
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            }
+
+        record_draw_calls(1);
+    }
+}
+
+impl Drop for DebugViewPass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
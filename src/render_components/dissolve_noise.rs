@@ -0,0 +1,102 @@
+use std::ffi::c_void;
+
+/// The texture unit the dissolve noise is bound to, below the texture units of the environment maps
+pub const DISSOLVE_NOISE_TEXTURE_UNIT: u32 = 10;
+
+/// Number of texels along each side of the dissolve noise
+const DISSOLVE_NOISE_SIZE: usize = 32;
+
+/// GLSL declarations of the dissolve noise and the function to apply a dissolve. The noise is sampled with
+/// the position in the model, so every model dissolves the same way regardless of its UV layout
+pub const DISSOLVE_DECLARATIONS: &str =
+"#define DISSOLVE
+
+layout (binding = 10) uniform sampler3D dissolveNoise;
+
+const float DISSOLVE_EDGE_WIDTH = 0.06;
+
+vec3 applyDissolve(vec3 colour, vec4 dissolve, vec3 objectPosition)
+{
+\tif(dissolve.x <= 0.0)
+\t{
+\t\treturn colour;
+\t}
+
+\tfloat noise = texture(dissolveNoise, objectPosition * 0.8).r * 0.65 + texture(dissolveNoise, objectPosition * 2.3).r * 0.35;
+
+\tif(noise < dissolve.x)
+\t{
+\t\tdiscard;
+\t}
+
+\treturn mix(dissolve.yzw, colour, smoothstep(dissolve.x, dissolve.x + DISSOLVE_EDGE_WIDTH, noise));
+}
+";
+
+/// A tiling 3D noise texture sampled by render systems created with dissolve support
+pub struct DissolveNoise
+{
+    texture: u32,
+}
+
+impl DissolveNoise
+{
+    /// Creates the noise texture. The noise is the same every time it is created
+    pub fn new() -> DissolveNoise
+    {
+        let noise = (0..DISSOLVE_NOISE_SIZE * DISSOLVE_NOISE_SIZE * DISSOLVE_NOISE_SIZE).map(|x| hash_texel(x as u32)).collect::<Vec<u8>>();
+        let mut texture: u32 = 0;
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_3D, 1, &mut texture);
+                gl::TextureStorage3D(texture, 1, gl::R8, DISSOLVE_NOISE_SIZE as i32, DISSOLVE_NOISE_SIZE as i32, DISSOLVE_NOISE_SIZE as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_R, gl::REPEAT as i32);
+
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl::TextureSubImage3D(texture, 0, 0, 0, 0, DISSOLVE_NOISE_SIZE as i32, DISSOLVE_NOISE_SIZE as i32, DISSOLVE_NOISE_SIZE as i32,
+                                      gl::RED, gl::UNSIGNED_BYTE, noise.as_ptr() as *const c_void);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            }
+
+        DissolveNoise{ texture }
+    }
+
+    /// Binds the dissolve noise for first render passes to sample
+    pub fn bind(&self)
+    {
+        unsafe
+            {
+                gl::BindTextureUnit(DISSOLVE_NOISE_TEXTURE_UNIT, self.texture);
+            }
+    }
+}
+
+impl Drop for DissolveNoise
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteTextures(1, &self.texture);
+            }
+    }
+}
+
+/// Finds the noise value of a texel by hashing its index
+///
+/// `index` - the index of the texel in the noise texture
+fn hash_texel(index: u32) -> u8
+{
+    let mut hash = index.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x846C_A68B);
+    hash ^= hash >> 16;
+    (hash >> 24) as u8
+}
@@ -0,0 +1,435 @@
+use std::ffi::{c_void, CString};
+use std::path::PathBuf;
+use stb_image::stb_image::bindgen::{stbi_image_free, stbi_loadf, stbi_set_flip_vertically_on_load};
+use crate::exports::environment_lighting::EnvironmentLighting;
+use crate::helper_things::environment::{get_asset_folder, path_to_bytes};
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::mapped_buffer::BindingPoint;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+
+/// The texture unit the BRDF lookup table is bound to, below the texture units of the damage masks and
+/// lightmap
+pub const BRDF_LUT_TEXTURE_UNIT: u32 = 11;
+
+/// The texture unit the irradiance map is bound to
+pub const IRRADIANCE_TEXTURE_UNIT: u32 = 12;
+
+/// The texture unit the prefiltered environment maps are bound to
+pub const PREFILTERED_TEXTURE_UNIT: u32 = 13;
+
+/// The binding point of the storage buffer holding the environment lighting parameters, following the
+/// binding point of the label glyphs
+pub const ENVIRONMENT_PARAMS_BINDING: BindingPoint = 7;
+
+/// Width and height of the BRDF lookup table
+const BRDF_LUT_RESOLUTION: i32 = 512;
+
+/// Most mip levels of the prefiltered environment maps. Each level is prefiltered for a higher roughness,
+/// with the last level used by fully rough surfaces
+const MAX_PREFILTERED_MIP_LEVELS: i32 = 5;
+
+/// Texture unit the source texture of a convolution is bound to while the maps are created
+const SOURCE_TEXTURE_UNIT: u32 = 0;
+
+/// GLSL function prepended to the convolution shaders giving the direction through a texel of the cubemap
+/// face being rendered, following the face orientations of OpenGL cubemaps
+const CUBE_FACE_DIRECTION: &str =
+"uniform int cubeFace;
+
+vec3 cubeFaceDirection(vec2 faceCoords)
+{
+\tvec2 st = faceCoords * 2.0 - 1.0;
+
+\tswitch(cubeFace)
+\t{
+\t\tcase 0: return normalize(vec3(1.0, -st.y, -st.x));
+\t\tcase 1: return normalize(vec3(-1.0, -st.y, st.x));
+\t\tcase 2: return normalize(vec3(st.x, 1.0, st.y));
+\t\tcase 3: return normalize(vec3(st.x, -1.0, -st.y));
+\t\tcase 4: return normalize(vec3(st.x, -st.y, 1.0));
+\t\tdefault: return normalize(vec3(-st.x, -st.y, -1.0));
+\t}
+}
+";
+
+/// GLSL declarations of the environment maps and the function giving the ambient lighting from them using
+/// the split sum approximation
+pub const ENVIRONMENT_LIGHTING_DECLARATIONS: &str =
+"#define ENVIRONMENT_LIGHTING
+
+layout (binding = 11) uniform sampler2D brdfLut;
+layout (binding = 12) uniform samplerCube irradianceMap;
+layout (binding = 13) uniform samplerCube prefilteredMap;
+
+layout (std430, binding = 7) readonly buffer EnvironmentLightingParams
+{
+\tvec4 environmentLightingParams; // Diffuse intensity, specular intensity, highest prefiltered mip level, 1 if a map is loaded
+};
+
+bool environmentLighting(vec3 fragPosition, vec3 viewPosition, vec3 objectNormal, vec3 albedo, float roughness, float metallic, out vec3 ambient)
+{
+\tambient = vec3(0.0);
+
+\tif(environmentLightingParams.w == 0.0 || dot(objectNormal, objectNormal) == 0.0)
+\t{
+\t\treturn false;
+\t}
+
+\tvec3 normal = normalize(objectNormal);
+\tvec3 view = normalize(viewPosition - fragPosition);
+\tvec3 reflection = reflect(-view, normal);
+\tfloat normalDotView = max(dot(normal, view), 0.0);
+
+\t// Fresnel-Schlick, accounting for rough surfaces reflecting less at grazing angles
+\tvec3 reflectance = mix(vec3(0.04), albedo, metallic);
+\tvec3 fresnel = reflectance + (max(vec3(1.0 - roughness), reflectance) - reflectance) * pow(1.0 - normalDotView, 5.0);
+\tvec3 diffuseFactor = (1.0 - fresnel) * (1.0 - metallic);
+
+\tvec3 diffuse = texture(irradianceMap, normal).rgb * albedo;
+\tvec3 prefiltered = textureLod(prefilteredMap, reflection, roughness * environmentLightingParams.z).rgb;
+\tvec2 brdf = texture(brdfLut, vec2(normalDotView, roughness)).rg;
+\tvec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+
+\tambient = diffuseFactor * diffuse * environmentLightingParams.x + specular * environmentLightingParams.y;
+\treturn true;
+}
+";
+
+/// The maps convolved from an environment map, used for the ambient lighting of second render passes
+pub struct EnvironmentMaps
+{
+    irradiance_map: u32,
+    prefiltered_map: u32,
+    brdf_lut: u32,
+    params_buffer: u32,
+}
+
+/// Creates a program rendering a fullscreen triangle with the given fragment shader, which has the function
+/// giving the direction through a cubemap texel prepended to it
+///
+/// `frag_shader` - the name of the fragment shader in the shader asset folder
+fn create_convolution_program(frag_shader: &str) -> Result<ShaderProgram, String>
+{
+    let version = "#version 430 core\n";
+    let shaders = vec!
+    [
+        ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+        ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders").join(frag_shader), Some(version.to_string() + CUBE_FACE_DIRECTION), None)?,
+    ];
+
+    ShaderProgram::new(&shaders)
+}
+
+/// Creates a cubemap with floating point faces that can be rendered into
+///
+/// `resolution` - the width of each face
+/// `mip_levels` - the number of mip levels of the cubemap
+fn create_cubemap(resolution: i32, mip_levels: i32) -> u32
+{
+    let mut cubemap: u32 = 0;
+
+    unsafe
+        {
+            gl::CreateTextures(gl::TEXTURE_CUBE_MAP, 1, &mut cubemap);
+            gl::TextureStorage2D(cubemap, mip_levels, gl::RGB16F, resolution, resolution);
+            gl::TextureParameteri(cubemap, gl::TEXTURE_MIN_FILTER, if mip_levels > 1 { gl::LINEAR_MIPMAP_LINEAR } else { gl::LINEAR } as i32);
+            gl::TextureParameteri(cubemap, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(cubemap, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(cubemap, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(cubemap, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as i32);
+        }
+
+    cubemap
+}
+
+/// Renders the bound program into every face of a mip level of the cubemap
+///
+/// `program` - the program rendering a face, which must be in use
+/// `fbo` - the framebuffer the faces are attached to when rendered into
+/// `cubemap` - the cubemap to render into
+/// `mip_level` - the mip level of the cubemap to render into
+/// `resolution` - the width of each face at the mip level
+fn render_cube_faces(program: &ShaderProgram, fbo: u32, cubemap: u32, mip_level: i32, resolution: i32)
+{
+    let cube_face_location = program.uniform_location("cubeFace");
+
+    unsafe
+        {
+            gl::Viewport(0, 0, resolution, resolution);
+
+            for face in 0..6
+            {
+                gl::NamedFramebufferTextureLayer(fbo, gl::COLOR_ATTACHMENT0, cubemap, mip_level, face);
+                gl::Uniform1i(cube_face_location, face);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            }
+        }
+}
+
+/// Loads an equirectangular HDR image and renders it into a new floating point cubemap, returning the
+/// cubemap. Only the first mip level is rendered into. This is a blocking operation that requires an
+/// OpenGL context to be current
+///
+/// `environment_map` - the location of the equirectangular image
+/// `resolution` - the width of each face of the cubemap
+/// `mip_levels` - the number of mip levels of the cubemap
+pub fn equirectangular_to_cubemap(environment_map: &PathBuf, resolution: i32, mip_levels: i32) -> Result<u32, String>
+{
+    let mut equirectangular_program = create_convolution_program("equirectangular_to_cube_frag.glsl")?;
+    let equirectangular_map = load_equirectangular_map(environment_map)?;
+
+    let _state_guard = GLStateGuard::new();
+
+    let cubemap = create_cubemap(resolution, mip_levels);
+    let mut fbo: u32 = 0;
+    let mut vao = VAO::new();
+
+    unsafe
+        {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::STENCIL_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+            gl::Disable(gl::SCISSOR_TEST);
+
+            gl::CreateFramebuffers(1, &mut fbo);
+            gl::NamedFramebufferDrawBuffer(fbo, gl::COLOR_ATTACHMENT0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        }
+
+    vao.bind();
+    equirectangular_program.use_shader_program();
+
+    unsafe
+        {
+            gl::Uniform1i(equirectangular_program.uniform_location("equirectangularMap"), SOURCE_TEXTURE_UNIT as i32);
+            gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, equirectangular_map);
+        }
+
+    render_cube_faces(&equirectangular_program, fbo, cubemap, 0, resolution);
+
+    unsafe
+        {
+            gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, 0);
+            gl::DeleteFramebuffers(1, &fbo);
+            gl::DeleteTextures(1, &equirectangular_map);
+            gl::DeleteProgram(equirectangular_program.shader_program);
+        }
+
+    Ok(cubemap)
+}
+
+/// Loads the equirectangular HDR image into a floating point texture
+///
+/// `environment_map` - the location of the image
+fn load_equirectangular_map(environment_map: &PathBuf) -> Result<u32, String>
+{
+    let mut width = 0;
+    let mut height = 0;
+    let mut nr_channels = 0;
+
+    // Texture arrays load images flipped, and the flag is shared by all loads, so it is set explicitly
+    // to give the orientation the conversion shader expects
+    let image_data = unsafe
+        {
+            let texture_cstring = CString::new(path_to_bytes(environment_map.clone())).unwrap();
+            stbi_set_flip_vertically_on_load(1);
+            stbi_loadf(texture_cstring.as_ptr(), &mut width, &mut height, &mut nr_channels, 3)
+        };
+
+    if image_data.is_null()
+    {
+        return Err(format!("Failed to load the environment map {:?}", environment_map));
+    }
+
+    let mut texture: u32 = 0;
+
+    unsafe
+        {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+            gl::TextureStorage2D(texture, 1, gl::RGB16F, width, height);
+            gl::TextureSubImage2D(texture, 0, 0, 0, width, height, gl::RGB, gl::FLOAT, image_data as *const c_void);
+            gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            stbi_image_free(image_data as *mut c_void);
+        }
+
+    Ok(texture)
+}
+
+impl EnvironmentMaps
+{
+    /// Creates maps that give no environment lighting, so that shaders reading them fall back to the
+    /// lighting they use without an environment map
+    pub fn empty() -> EnvironmentMaps
+    {
+        let black = [0.0_f32; 4];
+
+        let irradiance_map = create_cubemap(1, 1);
+        let prefiltered_map = create_cubemap(1, 1);
+        let mut brdf_lut: u32 = 0;
+
+        unsafe
+            {
+                for face in 0..6
+                {
+                    gl::TextureSubImage3D(irradiance_map, 0, 0, 0, face, 1, 1, 1, gl::RGB, gl::FLOAT, black.as_ptr() as *const c_void);
+                    gl::TextureSubImage3D(prefiltered_map, 0, 0, 0, face, 1, 1, 1, gl::RGB, gl::FLOAT, black.as_ptr() as *const c_void);
+                }
+
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut brdf_lut);
+                gl::TextureStorage2D(brdf_lut, 1, gl::RG16F, 1, 1);
+                gl::TextureSubImage2D(brdf_lut, 0, 0, 0, 1, 1, gl::RG, gl::FLOAT, black.as_ptr() as *const c_void);
+            }
+
+        EnvironmentMaps{ irradiance_map, prefiltered_map, brdf_lut, params_buffer: EnvironmentMaps::create_params_buffer([0.0; 4]) }
+    }
+
+    /// Loads the environment map and convolves it into the maps used for lighting. This is a blocking
+    /// operation that requires an OpenGL context to be current
+    ///
+    /// `settings` - the environment map to load and the resolutions of the maps
+    pub fn new(settings: &EnvironmentLighting) -> Result<EnvironmentMaps, String>
+    {
+        let mut irradiance_program = create_convolution_program("irradiance_convolution_frag.glsl")?;
+        let mut prefilter_program = create_convolution_program("prefilter_environment_frag.glsl")?;
+        let mut brdf_program = create_convolution_program("brdf_lut_frag.glsl")?;
+
+        let cube_resolution = settings.cube_resolution as i32;
+        let cube_mip_levels = (cube_resolution as f32).log2() as i32 + 1;
+        let irradiance_resolution = settings.irradiance_resolution as i32;
+        let prefiltered_resolution = settings.prefiltered_resolution as i32;
+        let prefiltered_mip_levels = ((prefiltered_resolution as f32).log2() as i32 + 1).min(MAX_PREFILTERED_MIP_LEVELS);
+
+        // The environment is converted into a cubemap first so that the convolutions sample it by direction.
+        // Its mip chain is used by the prefiltering to sample the environment at a matching level of detail
+        let environment_map = equirectangular_to_cubemap(&settings.environment_map, cube_resolution, cube_mip_levels)?;
+
+        let _state_guard = GLStateGuard::new();
+
+        let irradiance_map = create_cubemap(irradiance_resolution, 1);
+        let prefiltered_map = create_cubemap(prefiltered_resolution, prefiltered_mip_levels);
+        let mut brdf_lut: u32 = 0;
+        let mut fbo: u32 = 0;
+        let mut vao = VAO::new();
+
+        unsafe
+            {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+
+                gl::CreateFramebuffers(1, &mut fbo);
+                gl::NamedFramebufferDrawBuffer(fbo, gl::COLOR_ATTACHMENT0);
+                gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            }
+
+        vao.bind();
+        irradiance_program.use_shader_program();
+
+        unsafe
+            {
+                gl::GenerateTextureMipmap(environment_map);
+                gl::Uniform1i(irradiance_program.uniform_location("environmentMap"), SOURCE_TEXTURE_UNIT as i32);
+                gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, environment_map);
+            }
+
+        render_cube_faces(&irradiance_program, fbo, irradiance_map, 0, irradiance_resolution);
+
+        prefilter_program.use_shader_program();
+
+        unsafe
+            {
+                gl::Uniform1i(prefilter_program.uniform_location("environmentMap"), SOURCE_TEXTURE_UNIT as i32);
+                gl::Uniform1f(prefilter_program.uniform_location("environmentResolution"), cube_resolution as f32);
+            }
+
+        // Each mip level is prefiltered for a higher roughness, so shaders pick the level by roughness
+        let roughness_location = prefilter_program.uniform_location("roughness");
+
+        for mip_level in 0..prefiltered_mip_levels
+        {
+            let roughness = mip_level as f32 / (prefiltered_mip_levels - 1).max(1) as f32;
+            unsafe { gl::Uniform1f(roughness_location, roughness); }
+
+            render_cube_faces(&prefilter_program, fbo, prefiltered_map, mip_level, (prefiltered_resolution >> mip_level).max(1));
+        }
+
+        brdf_program.use_shader_program();
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut brdf_lut);
+                gl::TextureStorage2D(brdf_lut, 1, gl::RG16F, BRDF_LUT_RESOLUTION, BRDF_LUT_RESOLUTION);
+                gl::TextureParameteri(brdf_lut, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(brdf_lut, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(brdf_lut, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(brdf_lut, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+                gl::NamedFramebufferTexture(fbo, gl::COLOR_ATTACHMENT0, brdf_lut, 0);
+                gl::Viewport(0, 0, BRDF_LUT_RESOLUTION, BRDF_LUT_RESOLUTION);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+                gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, 0);
+                gl::DeleteFramebuffers(1, &fbo);
+                gl::DeleteTextures(1, &environment_map);
+
+                for program in &[irradiance_program, prefilter_program, brdf_program]
+                {
+                    gl::DeleteProgram(program.shader_program);
+                }
+            }
+
+        let params = [settings.diffuse_intensity, settings.specular_intensity, (prefiltered_mip_levels - 1) as f32, 1.0];
+        Ok(EnvironmentMaps{ irradiance_map, prefiltered_map, brdf_lut, params_buffer: EnvironmentMaps::create_params_buffer(params) })
+    }
+
+    /// Binds the environment maps and their parameters for second render passes to sample
+    pub fn bind(&self)
+    {
+        unsafe
+            {
+                gl::BindTextureUnit(BRDF_LUT_TEXTURE_UNIT, self.brdf_lut);
+                gl::BindTextureUnit(IRRADIANCE_TEXTURE_UNIT, self.irradiance_map);
+                gl::BindTextureUnit(PREFILTERED_TEXTURE_UNIT, self.prefiltered_map);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, ENVIRONMENT_PARAMS_BINDING, self.params_buffer);
+            }
+    }
+
+    /// Creates the storage buffer holding the parameters read by the environment lighting function
+    ///
+    /// `params` - the diffuse intensity, specular intensity, highest prefiltered mip level and whether a
+    ///            map is loaded
+    fn create_params_buffer(params: [f32; 4]) -> u32
+    {
+        let mut params_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::CreateBuffers(1, &mut params_buffer);
+                gl::NamedBufferStorage(params_buffer, std::mem::size_of::<[f32; 4]>() as isize, params.as_ptr() as *const c_void, 0);
+            }
+
+        params_buffer
+    }
+}
+
+impl Drop for EnvironmentMaps
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteTextures(1, &self.irradiance_map);
+                gl::DeleteTextures(1, &self.prefiltered_map);
+                gl::DeleteTextures(1, &self.brdf_lut);
+                gl::DeleteBuffers(1, &self.params_buffer);
+            }
+    }
+}
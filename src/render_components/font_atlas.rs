@@ -0,0 +1,205 @@
+use std::ffi::c_void;
+use std::fs;
+use std::path::PathBuf;
+use fontdue::{Font, FontSettings};
+use hashbrown::HashMap;
+use tracing::warn;
+use crate::exports::text_shaping::GlyphCoverage;
+use crate::render_components::glyph_atlas::GlyphRect;
+
+/// Width and height of the atlas texture, in texels
+const ATLAS_SIZE: usize = 1024;
+
+/// Texels left empty around every glyph, so that filtering does not bleed neighbouring glyphs into each other
+const GLYPH_PADDING: usize = 1;
+
+/// A glyph rasterized into the atlas. Distances are in pixels at the size the font was loaded at
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasGlyph
+{
+    /// The area of the atlas holding the glyph, from its top left to its bottom right
+    pub rect: GlyphRect,
+    /// Width and height of the glyph. Glyphs without an outline, such as spaces, have no size
+    pub size: (f32, f32),
+    /// Distance from the pen position on the baseline to the bottom left of the glyph
+    pub bearing: (f32, f32),
+    /// Distance the pen moves after the glyph is drawn
+    pub advance: f32,
+}
+
+/// Texture holding the glyphs of a TrueType font. Glyphs are rasterized into the texture the first time
+/// they are requested, filling it in rows from its top left
+pub struct FontAtlas
+{
+    font: Font,
+    pixel_size: f32,
+    texture: u32,
+    glyphs: HashMap<char, AtlasGlyph>,
+    /// Where the next glyph is placed in the current row of the atlas
+    cursor: (usize, usize),
+    row_height: usize,
+    line_height: f32,
+    descent: f32,
+    reported_full: bool,
+}
+
+impl FontAtlas
+{
+    /// Loads the font and creates an empty atlas for it. An OpenGL context must be current
+    ///
+    /// `location` - the location of the font file
+    /// `pixel_size` - the height in pixels that glyphs are rasterized at
+    pub fn new(location: &PathBuf, pixel_size: f32) -> Result<FontAtlas, String>
+    {
+        let bytes = fs::read(location).map_err(|err| format!("Failed to read the font {:?}: {}", location, err))?;
+        let font = Font::from_bytes(bytes, FontSettings{ scale: pixel_size, ..FontSettings::default() })
+            .map_err(|err| format!("Failed to load the font {:?}: {}", location, err))?;
+
+        let (line_height, descent) = match font.horizontal_line_metrics(pixel_size)
+        {
+            Some(i) => (i.new_line_size, -i.descent),
+            None => (pixel_size, 0.0)
+        };
+
+        let empty_texels = vec![0_u8; ATLAS_SIZE * ATLAS_SIZE];
+        let mut texture: u32 = 0;
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+                gl::TextureStorage2D(texture, 1, gl::R8, ATLAS_SIZE as i32, ATLAS_SIZE as i32);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl::TextureSubImage2D(texture, 0, 0, 0, ATLAS_SIZE as i32, ATLAS_SIZE as i32, gl::RED, gl::UNSIGNED_BYTE, empty_texels.as_ptr() as *const c_void);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+
+        Ok(FontAtlas
+        {
+            font,
+            pixel_size,
+            texture,
+            glyphs: HashMap::default(),
+            cursor: (GLYPH_PADDING, GLYPH_PADDING),
+            row_height: 0,
+            line_height,
+            descent,
+            reported_full: false,
+        })
+    }
+
+    /// Get the distance between the baselines of two lines of text, in pixels
+    pub fn line_height(&self) -> f32
+    {
+        self.line_height
+    }
+
+    /// Get the distance from the baseline to the lowest point of any glyph, in pixels
+    pub fn descent(&self) -> f32
+    {
+        self.descent
+    }
+
+    /// Get the adjustment of the distance between two characters drawn next to each other, in pixels
+    ///
+    /// `left` - the character drawn first
+    /// `right` - the character drawn after it
+    pub fn kerning(&self, left: char, right: char) -> f32
+    {
+        self.font.horizontal_kern(left, right, self.pixel_size).unwrap_or(0.0)
+    }
+
+    /// Get the glyph of the character, rasterizing it into the atlas if it was not requested before. Nothing
+    /// is returned if the atlas has no room left for the glyph
+    ///
+    /// `character` - the character to get the glyph of
+    pub fn glyph(&mut self, character: char) -> Option<AtlasGlyph>
+    {
+        if let Some(glyph) = self.glyphs.get(&character)
+        {
+            return Some(*glyph);
+        }
+
+        let (metrics, bitmap) = self.font.rasterize(character, self.pixel_size);
+
+        if metrics.width + GLYPH_PADDING > ATLAS_SIZE - self.cursor.0
+        {
+            self.cursor = (GLYPH_PADDING, self.cursor.1 + self.row_height + GLYPH_PADDING);
+            self.row_height = 0;
+        }
+
+        if metrics.height + GLYPH_PADDING > ATLAS_SIZE - self.cursor.1
+        {
+            if !self.reported_full
+            {
+                warn!("The font atlas is full; characters not drawn before are not drawn. Load the font at a smaller size");
+                self.reported_full = true;
+            }
+
+            return None;
+        }
+
+        if metrics.width != 0 && metrics.height != 0
+        {
+            // The rows of the bitmap are from the top of the glyph, so the top of the glyph is at the lowest row of its area
+            unsafe
+                {
+                    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                    gl::TextureSubImage2D(self.texture, 0, self.cursor.0 as i32, self.cursor.1 as i32, metrics.width as i32, metrics.height as i32,
+                                          gl::RED, gl::UNSIGNED_BYTE, bitmap.as_ptr() as *const c_void);
+                    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                }
+        }
+
+        let glyph = AtlasGlyph
+        {
+            rect: GlyphRect
+            {
+                min: (self.cursor.0 as f32 / ATLAS_SIZE as f32, self.cursor.1 as f32 / ATLAS_SIZE as f32),
+                max: ((self.cursor.0 + metrics.width) as f32 / ATLAS_SIZE as f32, (self.cursor.1 + metrics.height) as f32 / ATLAS_SIZE as f32),
+            },
+            size: (metrics.width as f32, metrics.height as f32),
+            bearing: (metrics.xmin as f32, metrics.ymin as f32),
+            advance: metrics.advance_width,
+        };
+
+        self.cursor.0 += metrics.width + GLYPH_PADDING;
+        self.row_height = self.row_height.max(metrics.height);
+        self.glyphs.insert(character, glyph);
+
+        Some(glyph)
+    }
+
+    /// Binds the atlas to the given texture unit
+    ///
+    /// `texture_unit` - the texture unit to bind the atlas to
+    pub fn bind(&self, texture_unit: u32)
+    {
+        unsafe
+            {
+                gl::BindTextureUnit(texture_unit, self.texture);
+            }
+    }
+}
+
+impl GlyphCoverage for FontAtlas
+{
+    fn has_glyph(&self, character: char) -> bool
+    {
+        self.font.lookup_glyph_index(character) != 0
+    }
+}
+
+impl Drop for FontAtlas
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteTextures(1, &self.texture);
+            }
+    }
+}
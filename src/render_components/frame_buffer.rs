@@ -1,6 +1,6 @@
 use std::env;
 use crate::render_components::texture_array::TextureArray;
-use crate::render_system::system_information::TextureInformation;
+use crate::render_system::system_information::{TextureFormat, TextureInformation};
 
 const MIN_NUMBER_COLOUR_ATTACHMENTS: usize = 8;
 
@@ -15,17 +15,38 @@ pub struct FBO
     no_colour_attachments: bool,
 }
 
-/// Possible formats of attachments to the FBO
+/// Possible formats of attachments to the FBO. RGB is any colour attachment with 8 bits per channel; the
+/// other colour attachments hold colours with more precision, or brighter than the window can show
 #[derive(Copy, Clone)]
 #[repr(u32)]
 pub enum AttachmentFormat
 {
     RGB = gl::RGB,
+    RGBA16F = gl::RGBA16F,
+    RGB10A2 = gl::RGB10_A2,
+    R11FG11FB10F = gl::R11F_G11F_B10F,
     DepthAttachment = gl::DEPTH_COMPONENT,
     StencilAttachment = gl::STENCIL_INDEX,
     DepthAndStencilAttachment = gl::DEPTH24_STENCIL8,
 }
 
+impl AttachmentFormat
+{
+    /// Get the format of a colour attachment whose texture has the given format
+    ///
+    /// `format` - the format of the texture of the colour attachment
+    pub fn colour_attachment(format: TextureFormat) -> AttachmentFormat
+    {
+        match format
+        {
+            TextureFormat::RGBA16F | TextureFormat::RGBA32F => AttachmentFormat::RGBA16F,
+            TextureFormat::RGB10A2 => AttachmentFormat::RGB10A2,
+            TextureFormat::R11FG11FB10F => AttachmentFormat::R11FG11FB10F,
+            _ => AttachmentFormat::RGB
+        }
+    }
+}
+
 /// Targets that the FBO can be bound to
 #[repr(u32)]
 
@@ -59,7 +80,8 @@ impl FBO
         let mut colour_attachments = vec![];
         for (index, x) in colour_attachment.into_iter().enumerate()
         {
-            FBO::setup_attachment_internal(x, AttachmentFormat::RGB,  Some(index as u32), fbo, &mut colour_texture[index]);
+            let attachment_format = AttachmentFormat::colour_attachment(x.format);
+            FBO::setup_attachment_internal(x, attachment_format, Some(index as u32), fbo, &mut colour_texture[index]);
             colour_attachments.push(gl::COLOR_ATTACHMENT0 + index as u32);
         }
 
@@ -138,6 +160,12 @@ impl FBO
             }
     }
 
+    /// Get the underlying OpenGL frame buffer object
+    pub fn get_raw_resource(&self) -> u32
+    {
+        self.fbo
+    }
+
     /// Bind the texture holding the depth information to the sampler binding point given
     ///
     /// `binding_point` - the binding point to bind the depth attachment texture to
@@ -160,6 +188,32 @@ impl FBO
         }
     }
 
+    /// Checks if the FBO has a texture for the colour attachment
+    ///
+    /// `attachment_index` - the index of the colour attachment to check
+    pub fn has_colour_texture(&self, attachment_index: usize) -> bool
+    {
+        matches!(self.colour_texture.get(attachment_index), Some(Some(_)))
+    }
+
+    /// Binds a single colour attachment texture to the sampler binding point given. Returns false if the
+    /// FBO does not have the colour attachment
+    ///
+    /// `attachment_index` - the index of the colour attachment to bind
+    /// `binding_point` - the binding point to bind the colour attachment texture to
+    pub fn bind_colour_texture_to_specific_texture_unit(&mut self, attachment_index: usize, binding_point: u32) -> bool
+    {
+        match self.colour_texture.get_mut(attachment_index)
+        {
+            Some(Some(texture)) =>
+                {
+                    texture.bind_to_specific_texture_unit(binding_point);
+                    true
+                },
+            _ => false
+        }
+    }
+
     /// Marks a specific layer within a texture layer used as an attachment as the storage for
     /// rendering operations
     ///
@@ -205,7 +259,7 @@ impl FBO
             {
                 match format
                 {
-                    AttachmentFormat::RGB =>
+                    AttachmentFormat::RGB | AttachmentFormat::RGBA16F | AttachmentFormat::RGB10A2 | AttachmentFormat::R11FG11FB10F =>
                         {
                             let attachment_index = colour_index.unwrap_or(0);
                             gl::NamedFramebufferTextureLayer(fbo, gl::COLOR_ATTACHMENT0 + attachment_index, texture_array.get_raw_resource(), 0, 0)
@@ -0,0 +1,95 @@
+use gl::types::{GLboolean, GLenum, GLint};
+
+/// Capabilities whose enabled state is saved by the guard
+const SAVED_CAPABILITIES: [GLenum; 5] = [gl::BLEND, gl::DEPTH_TEST, gl::CULL_FACE, gl::SCISSOR_TEST, gl::STENCIL_TEST];
+
+/// Saves the OpenGL state the engine relies on when created, and restores it when dropped. This allows
+/// code outside of the engine to change the state without affecting the rendering of later frames
+pub struct GLStateGuard
+{
+    program: GLint,
+    vertex_array: GLint,
+    draw_framebuffer: GLint,
+    read_framebuffer: GLint,
+    array_buffer: GLint,
+    active_texture: GLint,
+    viewport: [GLint; 4],
+    capabilities: [GLboolean; SAVED_CAPABILITIES.len()],
+    depth_mask: GLboolean,
+    blend_source: GLint,
+    blend_destination: GLint,
+}
+
+impl GLStateGuard
+{
+    /// Saves the current OpenGL state. A context must be current on the calling thread
+    pub fn new() -> GLStateGuard
+    {
+        let mut guard = GLStateGuard
+        {
+            program: 0,
+            vertex_array: 0,
+            draw_framebuffer: 0,
+            read_framebuffer: 0,
+            array_buffer: 0,
+            active_texture: 0,
+            viewport: [0; 4],
+            capabilities: [gl::FALSE; SAVED_CAPABILITIES.len()],
+            depth_mask: gl::TRUE,
+            blend_source: 0,
+            blend_destination: 0,
+        };
+
+        unsafe
+            {
+                gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut guard.program);
+                gl::GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut guard.vertex_array);
+                gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut guard.draw_framebuffer);
+                gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut guard.read_framebuffer);
+                gl::GetIntegerv(gl::ARRAY_BUFFER_BINDING, &mut guard.array_buffer);
+                gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut guard.active_texture);
+                gl::GetIntegerv(gl::VIEWPORT, guard.viewport.as_mut_ptr());
+                gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut guard.depth_mask);
+                gl::GetIntegerv(gl::BLEND_SRC_RGB, &mut guard.blend_source);
+                gl::GetIntegerv(gl::BLEND_DST_RGB, &mut guard.blend_destination);
+
+                for (index, capability) in SAVED_CAPABILITIES.iter().enumerate()
+                {
+                    guard.capabilities[index] = gl::IsEnabled(*capability);
+                }
+            }
+
+        guard
+    }
+}
+
+impl Drop for GLStateGuard
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::UseProgram(self.program as u32);
+                gl::BindVertexArray(self.vertex_array as u32);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.draw_framebuffer as u32);
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.read_framebuffer as u32);
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.array_buffer as u32);
+                gl::ActiveTexture(self.active_texture as u32);
+                gl::Viewport(self.viewport[0], self.viewport[1], self.viewport[2], self.viewport[3]);
+                gl::DepthMask(self.depth_mask);
+                gl::BlendFunc(self.blend_source as u32, self.blend_destination as u32);
+
+                for (index, capability) in SAVED_CAPABILITIES.iter().enumerate()
+                {
+                    if self.capabilities[index] == gl::TRUE
+                    {
+                        gl::Enable(*capability);
+                    }
+                    else
+                    {
+                        gl::Disable(*capability);
+                    }
+                }
+            }
+    }
+}
@@ -0,0 +1,215 @@
+use std::ffi::c_void;
+use crate::exports::text_shaping::GlyphCoverage;
+
+/// Width of every glyph of the built-in font, in texels
+pub const GLYPH_WIDTH: usize = 5;
+
+/// Height of every glyph of the built-in font, in texels
+pub const GLYPH_HEIGHT: usize = 7;
+
+/// Distance between the start of one column of text and the next, in texels
+pub const GLYPH_ADVANCE: usize = 6;
+
+/// Distance between the bottom of one line of text and the next, in texels
+pub const LINE_ADVANCE: usize = 9;
+
+/// Number of glyphs stored in each row of the atlas
+const GLYPHS_PER_ROW: usize = 16;
+
+/// Size of the cell each glyph is stored in. The extra texel on the right and bottom of every glyph keeps
+/// filtering from bleeding neighbouring glyphs into each other
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// The built-in font. Every glyph is stored as its rows from the top, with the leftmost texel of a row in
+/// the highest of the five used bits. Lowercase letters are drawn with the uppercase glyphs
+const FONT_GLYPHS: [(char, [u8; GLYPH_HEIGHT]); 61] =
+[
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x04]),
+    ('"', [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00]),
+    ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('&', [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D]),
+    ('\'', [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('*', [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    (';', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08]),
+    ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('@', [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E]),
+    ('A', [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('[', [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E]),
+    (']', [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+];
+
+/// The area of the atlas holding a glyph, in texture coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlyphRect
+{
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+/// Texture holding the glyphs of the built-in font, used to draw text without loading a font file
+pub struct GlyphAtlas
+{
+    texture: u32,
+}
+
+/// Finds the index of the glyph drawn for the character in the built-in font
+///
+/// `character` - the character to find the glyph of
+fn glyph_index(character: char) -> Option<usize>
+{
+    let character = character.to_ascii_uppercase();
+    FONT_GLYPHS.iter().position(|(x, _)| *x == character)
+}
+
+/// Get the dimensions of the atlas texture, in texels
+fn atlas_dimensions() -> (usize, usize)
+{
+    let rows = FONT_GLYPHS.len().div_ceil(GLYPHS_PER_ROW);
+    (GLYPHS_PER_ROW * CELL_WIDTH, rows * CELL_HEIGHT)
+}
+
+impl GlyphAtlas
+{
+    /// Creates the atlas texture from the built-in font. An OpenGL context must be current
+    pub fn new() -> GlyphAtlas
+    {
+        let (width, height) = atlas_dimensions();
+        let mut texels = vec![0_u8; width * height];
+
+        for (index, (_, rows)) in FONT_GLYPHS.iter().enumerate()
+        {
+            let cell_x = (index % GLYPHS_PER_ROW) * CELL_WIDTH;
+            let cell_y = (index / GLYPHS_PER_ROW) * CELL_HEIGHT;
+
+            for (row_index, row) in rows.iter().enumerate()
+            {
+                // Textures are stored from the bottom row up, while the rows of the glyphs are from the top
+                let y = cell_y + CELL_HEIGHT - 1 - row_index;
+
+                for column in 0..GLYPH_WIDTH
+                {
+                    if row >> (GLYPH_WIDTH - 1 - column) & 1 == 1
+                    {
+                        texels[y * width + cell_x + column] = 255;
+                    }
+                }
+            }
+        }
+
+        let mut texture: u32 = 0;
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+                gl::TextureStorage2D(texture, 1, gl::R8, width as i32, height as i32);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl::TextureSubImage2D(texture, 0, 0, 0, width as i32, height as i32, gl::RED, gl::UNSIGNED_BYTE, texels.as_ptr() as *const c_void);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                // The font is a bitmap font, so it is kept sharp when magnified
+                gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+
+        GlyphAtlas{ texture }
+    }
+
+    /// Get the area of the atlas holding the glyph of the character, if the font has a glyph for it
+    ///
+    /// `character` - the character to find the glyph of
+    pub fn glyph_rect(&self, character: char) -> Option<GlyphRect>
+    {
+        let index = glyph_index(character)?;
+        let (width, height) = atlas_dimensions();
+
+        let cell_x = (index % GLYPHS_PER_ROW) * CELL_WIDTH;
+        let cell_y = (index / GLYPHS_PER_ROW) * CELL_HEIGHT + 1;
+
+        Some(GlyphRect
+        {
+            min: (cell_x as f32 / width as f32, cell_y as f32 / height as f32),
+            max: ((cell_x + GLYPH_WIDTH) as f32 / width as f32, (cell_y + GLYPH_HEIGHT) as f32 / height as f32),
+        })
+    }
+
+    /// Binds the atlas to the given texture unit
+    ///
+    /// `texture_unit` - the texture unit to bind the atlas to
+    pub fn bind(&self, texture_unit: u32)
+    {
+        unsafe
+            {
+                gl::BindTextureUnit(texture_unit, self.texture);
+            }
+    }
+}
+
+impl GlyphCoverage for GlyphAtlas
+{
+    fn has_glyph(&self, character: char) -> bool
+    {
+        glyph_index(character).is_some()
+    }
+}
+
+impl Drop for GlyphAtlas
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteTextures(1, &self.texture);
+            }
+    }
+}
@@ -0,0 +1,466 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use hashbrown::HashMap;
+use nalgebra_glm::{TMat4, TVec3, TVec4};
+use crate::exports::frame_statistics::record_draw_calls;
+use crate::exports::rendering::LevelOfView;
+use crate::flows::render_flow::ModelRenderingInformation;
+use crate::helper_things::environment::get_asset_folder;
+use crate::models::model_definitions::{ModelId, NUMBER_MODEL_LEVEL_OF_VIEWS};
+use crate::render_components::hi_z_buffer::{HI_Z_TEXTURE_UNIT, HiZBuffer};
+use crate::render_components::indirect_draw_buffer::DrawElementsIndirectCommand;
+use crate::render_components::mapped_buffer::MappedBuffer;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+
+/// Most per-instance layouts a render system choosing level of views on the GPU can have
+pub const MAX_INSTANCE_LAYOUTS: usize = 16;
+
+/// Shader storage binding point of the instance positions, after those used by particles. The pass uses
+/// the binding points from this one up to the binding point of the draw command buckets
+const POSITION_BINDING_POINT: u32 = 10;
+const PLACEMENT_BINDING_POINT: u32 = 11;
+const GROUP_BINDING_POINT: u32 = 12;
+const BUCKET_COUNT_BINDING_POINT: u32 = 13;
+const SOURCE_BINDING_POINT: u32 = 14;
+const DESTINATION_BINDING_POINT: u32 = 15;
+const COMMAND_BINDING_POINT: u32 = 16;
+const COMMAND_BUCKET_BINDING_POINT: u32 = 17;
+
+/// Number of instances or draw commands processed by each work group of the compute shader
+const WORK_GROUP_SIZE: u32 = 64;
+
+/// A per-instance layout of a render system, as the pass copies it
+#[derive(Copy, Clone, Debug)]
+pub struct InstanceLayoutDescription
+{
+    /// The first layout location, which is also the first vertex buffer binding point of the layout
+    pub layout_index: u32,
+    /// Number of layout locations the layout uses, each with its own binding point
+    pub number_locations: u32,
+    /// Size in bytes of the layout for one instance
+    pub stride: u32,
+    /// Size in bytes of the buffer holding the layout
+    pub capacity: isize,
+}
+
+/// The instances of one model with one sortable index, whose level of views are chosen together
+pub struct LevelOfViewGroup
+{
+    pub model_id: ModelId,
+    pub sortable_index: usize,
+    pub begin_instance: u32,
+    pub count: u32,
+    pub level_of_views: Vec<LevelOfView>,
+    /// True if the instances are drawn in the order they were written, such as transparent instances
+    /// sorted from the furthest to the nearest to the camera
+    pub preserve_order: bool,
+}
+
+/// What the instances are culled against when they are culled on the GPU
+pub struct InstanceCulling<'a>
+{
+    /// The view projection matrix of the camera the instances are drawn from, in world space
+    pub view_projection: TMat4<f32>,
+    /// The depth of the previous frame drawn from the camera, if instances hidden behind it are culled
+    pub occluders: Option<&'a HiZBuffer>,
+}
+
+/// An instance, laid out as the Instance struct of the compute shader
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuInstance
+{
+    position: [f32; 4],
+    bounding_radius: f32,
+    padding: [f32; 3],
+}
+
+/// A group, laid out as the LevelOfViewGroup struct of the compute shader
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuGroup
+{
+    range: [u32; 4],
+    max_distances: [f32; 8],
+}
+
+/// The indirect draw commands of every group, written by the pass for the draw functions to issue
+pub struct IndirectDraws
+{
+    command_buffer: u32,
+    // The first command and number of commands of each model and sortable index
+    draw_ranges: HashMap<(ModelId, usize), (usize, usize)>,
+}
+
+impl IndirectDraws
+{
+    /// Draws every level of view of the instances of a model with the given sortable index, in one indirect
+    /// draw call. The render system VAO and shader program must be bound
+    ///
+    /// `model_id` - the ID of the model to draw, without a level of view
+    /// `sortable_index` - the sortable index of the instances to draw
+    pub fn draw(&self, model_id: ModelId, sortable_index: usize)
+    {
+        let (first_command, number_commands) = match self.draw_ranges.get(&(model_id, sortable_index))
+        {
+            Some(i) if i.1 != 0 => *i,
+            _ => return
+        };
+
+        unsafe
+            {
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.command_buffer);
+                gl::MultiDrawElementsIndirect(gl::TRIANGLES, gl::UNSIGNED_INT, (first_command * size_of::<DrawElementsIndirectCommand>()) as *const c_void,
+                                              number_commands as i32, size_of::<DrawElementsIndirectCommand>() as i32);
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+            }
+
+        record_draw_calls(1);
+    }
+}
+
+/// Locations of the uniforms of the compute shader
+struct LevelOfViewUniforms
+{
+    stage: i32,
+    number_items: i32,
+    camera_position: i32,
+    number_layouts: i32,
+    instance_layouts: i32,
+    cull_instances: i32,
+    frustum_planes: i32,
+    occlusion_culling: i32,
+    occluder_view_projection: i32,
+    hi_z_size: i32,
+    hi_z_levels: i32,
+}
+
+/// Chooses the level of view of every instance of a render system on the GPU. The instances written by the
+/// CPU are grouped by model and sortable index only; a compute shader finds the distance of each instance
+/// to the camera and copies it into the instances of its level of view, then writes the indirect draw
+/// commands drawing each level of view. When culling is requested, instances outside of the view frustum or
+/// hidden behind the previous frame are left out while copying, so the instances drawn are compacted on the GPU
+pub struct GpuLevelOfViewPass
+{
+    program: ShaderProgram,
+    uniforms: LevelOfViewUniforms,
+    layouts: Vec<InstanceLayoutDescription>,
+    // Offset in bytes of each layout in the source and destination buffers
+    layout_offsets: Vec<isize>,
+    source_buffer: u32,
+    destination_buffer: u32,
+    position_buffer: u32,
+    placement_buffer: u32,
+    group_buffer: u32,
+    bucket_count_buffer: u32,
+    command_bucket_buffer: u32,
+    indirect_draws: IndirectDraws,
+}
+
+impl GpuLevelOfViewPass
+{
+    /// Creates the resources required to choose level of views on the GPU. An OpenGL context must be current
+    ///
+    /// `layouts` - the per-instance layouts of the render system, in the order of its instance buffers
+    pub fn new(layouts: Vec<InstanceLayoutDescription>) -> Result<GpuLevelOfViewPass, String>
+    {
+        if layouts.len() > MAX_INSTANCE_LAYOUTS
+        {
+            return Err(format!("Choosing level of views on the GPU supports at most {} per-instance layouts; the render system has {}", MAX_INSTANCE_LAYOUTS, layouts.len()));
+        }
+
+        if let Some(layout) = layouts.iter().find(|x| x.stride % 4 != 0)
+        {
+            return Err(format!("The per-instance layout at location {} is not a multiple of four bytes, so cannot be copied on the GPU", layout.layout_index));
+        }
+
+        let prefix = format!("#version 430 core\n#define MAX_INSTANCE_LAYOUTS {}\n", MAX_INSTANCE_LAYOUTS);
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::COMPUTE_SHADER, get_asset_folder().join("shaders/gpu_level_of_view_compute.glsl"), Some(prefix), None)?,
+        ])?;
+
+        let uniforms = LevelOfViewUniforms
+        {
+            stage: program.uniform_location("stage"),
+            number_items: program.uniform_location("numberItems"),
+            camera_position: program.uniform_location("cameraPosition"),
+            number_layouts: program.uniform_location("numberLayouts"),
+            instance_layouts: program.uniform_location("instanceLayouts"),
+            cull_instances: program.uniform_location("cullInstances"),
+            frustum_planes: program.uniform_location("frustumPlanes"),
+            occlusion_culling: program.uniform_location("occlusionCulling"),
+            occluder_view_projection: program.uniform_location("occluderViewProjection"),
+            hi_z_size: program.uniform_location("hiZSize"),
+            hi_z_levels: program.uniform_location("hiZLevels"),
+        };
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("hiZBuffer"), HI_Z_TEXTURE_UNIT as i32);
+            }
+
+        let mut layout_offsets = Vec::with_capacity(layouts.len());
+        let mut total_size = 0;
+
+        for layout in &layouts
+        {
+            layout_offsets.push(total_size);
+            total_size += layout.capacity;
+        }
+
+        let mut buffers = [0_u32; 8];
+
+        unsafe
+            {
+                gl::CreateBuffers(buffers.len() as i32, buffers.as_mut_ptr());
+                gl::NamedBufferStorage(buffers[0], total_size.max(4), std::ptr::null(), 0);
+                gl::NamedBufferStorage(buffers[1], total_size.max(4), std::ptr::null(), 0);
+            }
+
+        Ok(GpuLevelOfViewPass
+        {
+            program,
+            uniforms,
+            layouts,
+            layout_offsets,
+            source_buffer: buffers[0],
+            destination_buffer: buffers[1],
+            position_buffer: buffers[2],
+            placement_buffer: buffers[3],
+            group_buffer: buffers[4],
+            bucket_count_buffer: buffers[5],
+            command_bucket_buffer: buffers[6],
+            indirect_draws: IndirectDraws{ command_buffer: buffers[7], draw_ranges: HashMap::default() },
+        })
+    }
+
+    /// Chooses the level of view of every instance written this frame and writes the draw commands drawing
+    /// them, leaving out culled instances if culling is requested. The instance buffers must have been flushed
+    ///
+    /// `groups` - the instances of each model and sortable index, in the order they were written
+    /// `positions` - the position of every instance written in xyz, and the radius around it holding its bounding
+    ///               volume in w, in the order they were written. A negative radius means the instance is never culled
+    /// `instance_buffers` - the buffers the per-instance layouts were written to
+    /// `model_rendering_information` - the geometry of every level of view of the models of the render system
+    /// `camera_position` - the position of the camera the level of views are chosen for
+    /// `culling` - what the instances are culled against, or None to draw every instance
+    pub fn select_level_of_views(&mut self, groups: &[LevelOfViewGroup], positions: &[TVec4<f32>], instance_buffers: &[MappedBuffer],
+                                 model_rendering_information: &HashMap<ModelId, ModelRenderingInformation>, camera_position: TVec3<f32>,
+                                 culling: Option<InstanceCulling>)
+    {
+        self.indirect_draws.draw_ranges.clear();
+
+        let number_instances = positions.len();
+
+        if number_instances == 0 || groups.is_empty()
+        {
+            return;
+        }
+
+        let mut gpu_positions = Vec::with_capacity(number_instances);
+        let mut gpu_groups = Vec::with_capacity(groups.len());
+        let mut commands = Vec::new();
+        let mut command_buckets = Vec::new();
+
+        for (group_index, group) in groups.iter().enumerate()
+        {
+            let number_level_of_views = group.level_of_views.len().max(1).min(NUMBER_MODEL_LEVEL_OF_VIEWS as usize);
+            let mut max_distances = [f32::MAX; 8];
+
+            for (index, level_of_view) in group.level_of_views.iter().take(number_level_of_views).enumerate()
+            {
+                max_distances[index] = level_of_view.max_distance;
+            }
+
+            gpu_groups.push(GpuGroup{ range: [group.begin_instance, group.count, number_level_of_views as u32, group.preserve_order as u32], max_distances });
+
+            let group_bits = f32::from_bits(group_index as u32);
+            let group_end = (group.begin_instance + group.count) as usize;
+            for position in &positions[group.begin_instance as usize..group_end.min(number_instances)]
+            {
+                gpu_positions.push(GpuInstance{ position: [position.x, position.y, position.z, group_bits], bounding_radius: position.w, padding: [0.0; 3] });
+            }
+
+            let first_command = commands.len();
+
+            for level_of_view in 0..number_level_of_views
+            {
+                let mut adjusted_model_id = group.model_id;
+                ModelId::apply_level_of_view(&mut adjusted_model_id.model_index, level_of_view as u32);
+
+                if let Some(rendering_info) = model_rendering_information.get(&adjusted_model_id)
+                {
+                    for mesh in &rendering_info.mesh_render_info
+                    {
+                        commands.push(DrawElementsIndirectCommand
+                        {
+                            count: mesh.indice_count as u32,
+                            instance_count: 0,
+                            first_index: mesh.indice_offset as u32,
+                            base_vertex: mesh.vertex_offset,
+                            base_instance: 0
+                        });
+                        command_buckets.push([group_index as u32, level_of_view as u32]);
+                    }
+                }
+            }
+
+            self.indirect_draws.draw_ranges.insert((group.model_id, group.sortable_index), (first_command, commands.len() - first_command));
+        }
+
+        // Instances without a position are placed in the first group; this only happens if the positions and
+        // instances written disagree
+        gpu_positions.resize(number_instances, GpuInstance{ position: [0.0, 0.0, 0.0, f32::from_bits(0)], bounding_radius: -1.0, padding: [0.0; 3] });
+
+        let instance_layouts = self.layouts.iter().zip(self.layout_offsets.iter())
+            .flat_map(|(layout, offset)| vec![(*offset / 4) as u32, layout.stride / 4])
+            .collect::<Vec<u32>>();
+
+        unsafe
+            {
+                // The instances were written through a persistent mapping this frame
+                gl::MemoryBarrier(gl::CLIENT_MAPPED_BUFFER_BARRIER_BIT);
+
+                for ((layout, offset), instance_buffer) in self.layouts.iter().zip(self.layout_offsets.iter()).zip(instance_buffers.iter())
+                {
+                    let size = (layout.stride as isize * number_instances as isize).min(layout.capacity);
+                    gl::CopyNamedBufferSubData(instance_buffer.current_buffer(), self.source_buffer, 0, *offset, size);
+                }
+
+                gl::NamedBufferData(self.position_buffer, (gpu_positions.len() * size_of::<GpuInstance>()) as isize, gpu_positions.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::NamedBufferData(self.placement_buffer, (number_instances * size_of::<u32>()) as isize, std::ptr::null(), gl::STREAM_DRAW);
+                gl::NamedBufferData(self.group_buffer, (gpu_groups.len() * size_of::<GpuGroup>()) as isize, gpu_groups.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::NamedBufferData(self.bucket_count_buffer, (groups.len() * NUMBER_MODEL_LEVEL_OF_VIEWS as usize * size_of::<u32>()) as isize, std::ptr::null(), gl::STREAM_DRAW);
+                gl::ClearNamedBufferData(self.bucket_count_buffer, gl::R32UI, gl::RED_INTEGER, gl::UNSIGNED_INT, std::ptr::null());
+                gl::NamedBufferData(self.indirect_draws.command_buffer, (commands.len().max(1) * size_of::<DrawElementsIndirectCommand>()) as isize, commands.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::NamedBufferData(self.command_bucket_buffer, (command_buckets.len().max(1) * size_of::<[u32; 2]>()) as isize, command_buckets.as_ptr() as *const c_void, gl::STREAM_DRAW);
+
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, POSITION_BINDING_POINT, self.position_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, PLACEMENT_BINDING_POINT, self.placement_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, GROUP_BINDING_POINT, self.group_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, BUCKET_COUNT_BINDING_POINT, self.bucket_count_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, SOURCE_BINDING_POINT, self.source_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, DESTINATION_BINDING_POINT, self.destination_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, COMMAND_BINDING_POINT, self.indirect_draws.command_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, COMMAND_BUCKET_BINDING_POINT, self.command_bucket_buffer);
+
+                let mut previous_program = 0;
+                gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+
+                self.program.use_shader_program();
+                gl::Uniform3fv(self.uniforms.camera_position, 1, camera_position.as_ptr());
+                gl::Uniform1ui(self.uniforms.number_layouts, self.layouts.len() as u32);
+                gl::Uniform2uiv(self.uniforms.instance_layouts, self.layouts.len() as i32, instance_layouts.as_ptr());
+                self.set_culling_uniforms(culling);
+
+                for (stage, number_items) in [(0, number_instances), (1, number_instances), (2, commands.len())].iter()
+                {
+                    if *number_items == 0
+                    {
+                        continue;
+                    }
+
+                    gl::Uniform1ui(self.uniforms.stage, *stage);
+                    gl::Uniform1ui(self.uniforms.number_items, *number_items as u32);
+                    gl::DispatchCompute((*number_items as u32).div_ceil(WORK_GROUP_SIZE), 1, 1);
+                    gl::MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+                }
+
+                gl::MemoryBarrier(gl::COMMAND_BARRIER_BIT | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+                gl::UseProgram(previous_program as u32);
+            }
+    }
+
+    /// Sets the uniforms deciding which instances are culled. The compute shader must be in use
+    ///
+    /// `culling` - what the instances are culled against, or None to draw every instance
+    fn set_culling_uniforms(&self, culling: Option<InstanceCulling>)
+    {
+        let culling = match culling
+        {
+            Some(i) => i,
+            None =>
+                {
+                    unsafe { gl::Uniform1i(self.uniforms.cull_instances, 0); }
+                    return;
+                }
+        };
+
+        let frustum_planes = frustum_planes(&culling.view_projection);
+
+        // The Hi-Z buffer is only bound if it was built from a frame
+        let occluders = culling.occluders.and_then(|x| x.bind().map(|view_projection| (x, view_projection)));
+
+        unsafe
+            {
+                gl::Uniform1i(self.uniforms.cull_instances, 1);
+                gl::Uniform4fv(self.uniforms.frustum_planes, 6, frustum_planes.as_ptr() as *const f32);
+                gl::Uniform1i(self.uniforms.occlusion_culling, occluders.is_some() as i32);
+
+                if let Some((hi_z_buffer, view_projection)) = occluders
+                {
+                    let (width, height) = hi_z_buffer.dimensions();
+                    gl::UniformMatrix4fv(self.uniforms.occluder_view_projection, 1, gl::FALSE, view_projection.as_ptr());
+                    gl::Uniform2f(self.uniforms.hi_z_size, width as f32, height as f32);
+                    gl::Uniform1f(self.uniforms.hi_z_levels, hi_z_buffer.number_levels() as f32);
+                }
+            }
+    }
+
+    /// Makes the bound VAO read the per-instance layouts from the instances sorted by level of view, rather
+    /// than from the instance buffers written by the CPU
+    pub fn bind_instance_buffers(&self)
+    {
+        unsafe
+            {
+                for (layout, offset) in self.layouts.iter().zip(self.layout_offsets.iter())
+                {
+                    for location in layout.layout_index..layout.layout_index + layout.number_locations
+                    {
+                        gl::BindVertexBuffer(location, self.destination_buffer, *offset, layout.stride as i32);
+                    }
+                }
+            }
+    }
+
+    /// Get the draw commands written for the instances of this frame
+    pub fn indirect_draws(&self) -> &IndirectDraws
+    {
+        &self.indirect_draws
+    }
+}
+
+impl Drop for GpuLevelOfViewPass
+{
+    fn drop(&mut self)
+    {
+        let buffers = [self.source_buffer, self.destination_buffer, self.position_buffer, self.placement_buffer, self.group_buffer,
+                       self.bucket_count_buffer, self.command_bucket_buffer, self.indirect_draws.command_buffer];
+
+        unsafe
+            {
+                gl::DeleteBuffers(buffers.len() as i32, buffers.as_ptr());
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
+
+/// Finds the planes of the view frustum of a view projection matrix, with their normals pointing into the frustum
+///
+/// `view_projection` - the view projection matrix of the camera
+fn frustum_planes(view_projection: &TMat4<f32>) -> [[f32; 4]; 6]
+{
+    let row = |index: usize| view_projection.row(index).transpose();
+    let planes = [row(3) + row(0), row(3) - row(0), row(3) + row(1), row(3) - row(1), row(3) + row(2), row(3) - row(2)];
+
+    let mut normalized_planes = [[0.0; 4]; 6];
+
+    for (normalized_plane, plane) in normalized_planes.iter_mut().zip(planes.iter())
+    {
+        let length = plane.xyz().magnitude().max(f32::EPSILON);
+        *normalized_plane = [plane.x / length, plane.y / length, plane.z / length, plane.w / length];
+    }
+
+    normalized_planes
+}
@@ -0,0 +1,136 @@
+use tracing::warn;
+
+/// Number of frames the queries of a frame are kept before their results are read. The GPU has usually
+/// finished a frame this long after it was submitted, so reading the results does not wait for it
+const QUERY_LATENCY_FRAMES: usize = 4;
+
+/// Measures how long the GPU spends executing the commands issued between calls of begin and end, using
+/// timestamp queries. A pass can be timed several times in a frame, such as once for the window and once for
+/// every minimap, in which case the times are added together. Results are read QUERY_LATENCY_FRAMES frames
+/// after they were issued, so the latest time is that of a frame rendered a few frames ago
+pub struct GpuTimer
+{
+    // The start and end timestamp queries issued during each of the frames in flight
+    frames: [Vec<(u32, u32)>; QUERY_LATENCY_FRAMES],
+    free_queries: Vec<u32>,
+    current_frame: usize,
+    open_query: Option<u32>,
+    latest_time: f32,
+}
+
+impl GpuTimer
+{
+    /// Creates a timer without any queries. Queries are created as they are needed
+    pub fn new() -> GpuTimer
+    {
+        GpuTimer
+        {
+            frames: Default::default(),
+            free_queries: Vec::new(),
+            current_frame: 0,
+            open_query: None,
+            latest_time: 0.0,
+        }
+    }
+
+    /// Starts timing the commands issued from now on. An OpenGL context must be current
+    pub fn begin(&mut self)
+    {
+        if self.open_query.is_some()
+        {
+            warn!("A GPU timer was started while it was already timing; the earlier start is used");
+            return;
+        }
+
+        let query = self.take_query();
+        unsafe { gl::QueryCounter(query, gl::TIMESTAMP); }
+        self.open_query = Some(query);
+    }
+
+    /// Stops timing the commands issued since begin was called
+    pub fn end(&mut self)
+    {
+        let start_query = match self.open_query.take()
+        {
+            Some(i) => i,
+            None => return
+        };
+
+        let end_query = self.take_query();
+        unsafe { gl::QueryCounter(end_query, gl::TIMESTAMP); }
+        self.frames[self.current_frame].push((start_query, end_query));
+    }
+
+    /// Moves on to the next frame, reading the results of the oldest frame in flight. Call once per frame,
+    /// after every pass timed by this timer was issued
+    pub fn end_frame(&mut self)
+    {
+        if let Some(start_query) = self.open_query.take()
+        {
+            warn!("A GPU timer was not stopped before the end of the frame; the unfinished measurement is discarded");
+            self.free_queries.push(start_query);
+        }
+
+        self.current_frame = (self.current_frame + 1) % QUERY_LATENCY_FRAMES;
+
+        let oldest_queries = std::mem::take(&mut self.frames[self.current_frame]);
+        let mut elapsed_nanoseconds = 0;
+
+        for (start_query, end_query) in oldest_queries
+        {
+            let (mut start, mut end) = (0_u64, 0_u64);
+
+            unsafe
+                {
+                    gl::GetQueryObjectui64v(start_query, gl::QUERY_RESULT, &mut start);
+                    gl::GetQueryObjectui64v(end_query, gl::QUERY_RESULT, &mut end);
+                }
+
+            elapsed_nanoseconds += end.saturating_sub(start);
+            self.free_queries.push(start_query);
+            self.free_queries.push(end_query);
+        }
+
+        self.latest_time = elapsed_nanoseconds as f32 / 1_000_000.0;
+    }
+
+    /// Get the time the GPU spent on the timed commands of the most recent frame whose results were read,
+    /// in milliseconds
+    pub fn latest_time(&self) -> f32
+    {
+        self.latest_time
+    }
+
+    /// Get a query that is not in use, creating one if there is none
+    fn take_query(&mut self) -> u32
+    {
+        match self.free_queries.pop()
+        {
+            Some(i) => i,
+            None =>
+                {
+                    let mut query = 0;
+                    unsafe { gl::CreateQueries(gl::TIMESTAMP, 1, &mut query); }
+                    query
+                }
+        }
+    }
+}
+
+impl Drop for GpuTimer
+{
+    fn drop(&mut self)
+    {
+        let queries = self.frames.iter()
+            .flatten()
+            .flat_map(|x| [x.0, x.1])
+            .chain(self.free_queries.iter().copied())
+            .chain(self.open_query)
+            .collect::<Vec<u32>>();
+
+        unsafe
+            {
+                gl::DeleteQueries(queries.len() as i32, queries.as_ptr());
+            }
+    }
+}
@@ -0,0 +1,213 @@
+use nalgebra_glm::TMat4;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+
+/// Texture unit the depth of the frame is bound to while the first level is written, and the Hi-Z buffer is
+/// bound to while instances are culled. This is after the units of the lightmap and the other textures
+/// bound for every render system
+pub const HI_Z_TEXTURE_UNIT: u32 = 16;
+
+/// Number of texels of a level written by each work group of the compute shader, in each direction
+const WORK_GROUP_SIZE: i32 = 8;
+
+/// Locations of the uniforms of the compute shader
+struct HiZUniforms
+{
+    level: i32,
+    source_size: i32,
+    destination_size: i32,
+}
+
+/// Holds the furthest depth of every region of a frame at every level of detail, so that whether a bounding
+/// volume is hidden behind what was drawn can be found by reading a few texels. It is built from the depth
+/// of a frame after its opaque entities are drawn, and used to cull the instances of the next frame
+pub struct HiZBuffer
+{
+    program: ShaderProgram,
+    uniforms: HiZUniforms,
+    framebuffer: u32,
+    depth_texture: u32,
+    hi_z_texture: u32,
+    dimensions: (i32, i32),
+    number_levels: i32,
+    // The view projection matrix of the frame the buffer was built from, if it was built
+    view_projection: Option<TMat4<f32>>,
+}
+
+impl HiZBuffer
+{
+    /// Creates the resources required to build a Hi-Z buffer. The textures are created when the buffer is
+    /// first built, as their size is that of the frame. An OpenGL context must be current
+    pub fn new() -> Result<HiZBuffer, String>
+    {
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::COMPUTE_SHADER, get_asset_folder().join("shaders/hi_z_compute.glsl"), Some("#version 430 core\n"), None)?,
+        ])?;
+
+        let uniforms = HiZUniforms
+        {
+            level: program.uniform_location("level"),
+            source_size: program.uniform_location("sourceSize"),
+            destination_size: program.uniform_location("destinationSize"),
+        };
+
+        let mut framebuffer = 0;
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("depthMap"), HI_Z_TEXTURE_UNIT as i32);
+                gl::CreateFramebuffers(1, &mut framebuffer);
+
+                // The frame buffer only holds depth
+                gl::NamedFramebufferDrawBuffer(framebuffer, gl::NONE);
+                gl::NamedFramebufferReadBuffer(framebuffer, gl::NONE);
+            }
+
+        Ok(HiZBuffer{ program, uniforms, framebuffer, depth_texture: 0, hi_z_texture: 0, dimensions: (0, 0), number_levels: 0, view_projection: None })
+    }
+
+    /// Builds the Hi-Z buffer from the depth of the given frame buffer. The depth is copied first, so the
+    /// frame buffer can be drawn to again afterwards. The OpenGL state is the same after this call as before it,
+    /// other than the bound image units
+    ///
+    /// `source_framebuffer` - the frame buffer holding the depth of the frame, with a 24 bit depth and 8 bit stencil attachment
+    /// `dimensions` - the dimensions of the frame in the frame buffer
+    /// `view_projection` - the view projection matrix the frame was drawn with, in world space
+    pub fn build(&mut self, source_framebuffer: u32, dimensions: (i32, i32), view_projection: TMat4<f32>)
+    {
+        if dimensions.0 <= 0 || dimensions.1 <= 0
+        {
+            self.view_projection = None;
+            return;
+        }
+
+        if dimensions != self.dimensions
+        {
+            self.create_textures(dimensions);
+        }
+
+        unsafe
+            {
+                gl::BlitNamedFramebuffer(source_framebuffer, self.framebuffer, 0, 0, dimensions.0, dimensions.1,
+                                         0, 0, dimensions.0, dimensions.1, gl::DEPTH_BUFFER_BIT, gl::NEAREST);
+
+                let mut previous_program = 0;
+                gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+
+                self.program.use_shader_program();
+                gl::BindTextureUnit(HI_Z_TEXTURE_UNIT, self.depth_texture);
+
+                for level in 0..self.number_levels
+                {
+                    let source_level = (level - 1).max(0);
+                    let source_size = HiZBuffer::level_size(dimensions, source_level);
+                    let destination_size = HiZBuffer::level_size(dimensions, level);
+
+                    gl::BindImageTexture(0, self.hi_z_texture, source_level, gl::FALSE, 0, gl::READ_ONLY, gl::R32F);
+                    gl::BindImageTexture(1, self.hi_z_texture, level, gl::FALSE, 0, gl::WRITE_ONLY, gl::R32F);
+                    gl::Uniform1i(self.uniforms.level, level);
+                    gl::Uniform2i(self.uniforms.source_size, source_size.0, source_size.1);
+                    gl::Uniform2i(self.uniforms.destination_size, destination_size.0, destination_size.1);
+                    gl::DispatchCompute(((destination_size.0 + WORK_GROUP_SIZE - 1) / WORK_GROUP_SIZE) as u32, ((destination_size.1 + WORK_GROUP_SIZE - 1) / WORK_GROUP_SIZE) as u32, 1);
+                    gl::MemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+                }
+
+                gl::MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+                gl::BindTextureUnit(HI_Z_TEXTURE_UNIT, 0);
+                gl::UseProgram(previous_program as u32);
+            }
+
+        self.view_projection = Some(view_projection);
+    }
+
+    /// Binds the Hi-Z buffer to its texture unit. Returns the view projection matrix of the frame it was built
+    /// from, or None without binding anything if it was not built
+    pub fn bind(&self) -> Option<TMat4<f32>>
+    {
+        let view_projection = self.view_projection?;
+        unsafe { gl::BindTextureUnit(HI_Z_TEXTURE_UNIT, self.hi_z_texture); }
+        Some(view_projection)
+    }
+
+    /// Get the dimensions of the first level of the Hi-Z buffer, which are those of the frame it was built from
+    pub fn dimensions(&self) -> (i32, i32)
+    {
+        self.dimensions
+    }
+
+    /// Get the number of levels of the Hi-Z buffer
+    pub fn number_levels(&self) -> i32
+    {
+        self.number_levels
+    }
+
+    /// Creates the textures holding the depth of the frame and the levels of the Hi-Z buffer, replacing any
+    /// created for frames of another size
+    ///
+    /// `dimensions` - the dimensions of the frame
+    fn create_textures(&mut self, dimensions: (i32, i32))
+    {
+        self.delete_textures();
+
+        self.dimensions = dimensions;
+        self.number_levels = 32 - (dimensions.0.max(dimensions.1) as u32).leading_zeros() as i32;
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut self.depth_texture);
+                gl::TextureStorage2D(self.depth_texture, 1, gl::DEPTH24_STENCIL8, dimensions.0, dimensions.1);
+                gl::NamedFramebufferTexture(self.framebuffer, gl::DEPTH_STENCIL_ATTACHMENT, self.depth_texture, 0);
+
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut self.hi_z_texture);
+                gl::TextureStorage2D(self.hi_z_texture, self.number_levels, gl::R32F, dimensions.0, dimensions.1);
+                gl::TextureParameteri(self.hi_z_texture, gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_NEAREST as i32);
+                gl::TextureParameteri(self.hi_z_texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                gl::TextureParameteri(self.hi_z_texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(self.hi_z_texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+    }
+
+    /// Deletes the textures of the Hi-Z buffer, if they were created
+    fn delete_textures(&mut self)
+    {
+        unsafe
+            {
+                if self.depth_texture != 0
+                {
+                    gl::DeleteTextures(1, &self.depth_texture);
+                }
+
+                if self.hi_z_texture != 0
+                {
+                    gl::DeleteTextures(1, &self.hi_z_texture);
+                }
+            }
+
+        self.depth_texture = 0;
+        self.hi_z_texture = 0;
+    }
+
+    /// Get the dimensions of a level of the Hi-Z buffer
+    ///
+    /// `dimensions` - the dimensions of the first level
+    /// `level` - the level to get the dimensions of
+    fn level_size(dimensions: (i32, i32), level: i32) -> (i32, i32)
+    {
+        ((dimensions.0 >> level).max(1), (dimensions.1 >> level).max(1))
+    }
+}
+
+impl Drop for HiZBuffer
+{
+    fn drop(&mut self)
+    {
+        self.delete_textures();
+
+        unsafe
+            {
+                gl::DeleteFramebuffers(1, &self.framebuffer);
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
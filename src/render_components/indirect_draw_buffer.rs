@@ -0,0 +1,88 @@
+use std::ffi::c_void;
+use std::mem::{size_of, size_of_val};
+use crate::exports::frame_statistics::record_draw_calls;
+
+/// Number of draw commands the buffer holds when it is first written to
+const INITIAL_CAPACITY_COMMANDS: usize = 1024;
+
+/// An indirect draw command, laid out as OpenGL reads it
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct DrawElementsIndirectCommand
+{
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+
+/// Issues the draws of many models and instance ranges in a single indirect draw call. The commands of each call
+/// are written after those of the previous call, so commands the GPU may still be reading are never overwritten;
+/// once the buffer is full its storage is orphaned and writing starts again from the beginning
+pub struct IndirectDrawBuffer
+{
+    buffer: u32,
+    capacity_commands: usize,
+    next_command: usize,
+}
+
+impl IndirectDrawBuffer
+{
+    /// Creates an indirect draw buffer without any storage. The storage is created when commands are first drawn
+    pub fn new() -> IndirectDrawBuffer
+    {
+        IndirectDrawBuffer{ buffer: 0, capacity_commands: 0, next_command: 0 }
+    }
+
+    /// Draws the given commands with one indirect draw call. The render system VAO and shader program must be bound
+    ///
+    /// `commands` - the draw commands to issue
+    pub fn draw(&mut self, commands: &[DrawElementsIndirectCommand])
+    {
+        if commands.is_empty()
+        {
+            return;
+        }
+
+        if self.next_command + commands.len() > self.capacity_commands
+        {
+            self.capacity_commands = self.capacity_commands.max(INITIAL_CAPACITY_COMMANDS).max(commands.len().next_power_of_two());
+            self.next_command = 0;
+
+            unsafe
+                {
+                    if self.buffer == 0
+                    {
+                        gl::CreateBuffers(1, &mut self.buffer);
+                    }
+
+                    gl::NamedBufferData(self.buffer, (self.capacity_commands * size_of::<DrawElementsIndirectCommand>()) as isize, std::ptr::null(), gl::STREAM_DRAW);
+                }
+        }
+
+        let offset = self.next_command * size_of::<DrawElementsIndirectCommand>();
+
+        unsafe
+            {
+                gl::NamedBufferSubData(self.buffer, offset as isize, size_of_val(commands) as isize, commands.as_ptr() as *const c_void);
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.buffer);
+                gl::MultiDrawElementsIndirect(gl::TRIANGLES, gl::UNSIGNED_INT, offset as *const c_void, commands.len() as i32, size_of::<DrawElementsIndirectCommand>() as i32);
+                gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+            }
+
+        self.next_command += commands.len();
+        record_draw_calls(1);
+    }
+}
+
+impl Drop for IndirectDrawBuffer
+{
+    fn drop(&mut self)
+    {
+        if self.buffer != 0
+        {
+            unsafe { gl::DeleteBuffers(1, &self.buffer); }
+        }
+    }
+}
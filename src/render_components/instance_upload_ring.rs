@@ -0,0 +1,172 @@
+use gl::types::GLsync;
+use tracing::{debug, warn};
+use crate::render_components::mapped_buffer::{BufferType, BufferWriteInfo, MappedBuffer};
+use crate::render_system::system_information::{NumberBuffers, SizeBufferBytes};
+
+/// Number of frames of instance data that can be in flight before the ring has to grow
+pub const FRAMES_IN_FLIGHT: usize = 3;
+
+/// Number of frames the ring grows to before waiting for the GPU instead. Only reached if the GPU is far
+/// behind the CPU, such as when it stopped responding
+const MAX_FRAMES: usize = 8;
+
+/// Nanoseconds to wait for the oldest frame once the ring cannot grow anymore
+const FULL_RING_TIMEOUT: u64 = 1_000_000_000;
+
+/// The per-instance buffers written during a single frame, and the fence signalled once the draws reading them
+/// have finished
+struct RingFrame
+{
+    buffers: Vec<MappedBuffer>,
+    fence: Option<GLsync>,
+}
+
+/// Holds the per-instance layouts of a render system in a ring of frames. Every upload writes into a frame whose
+/// previous draws the GPU already finished, so writing instance data never waits on draws that are in flight; if
+/// the GPU has not finished with any frame, a new frame is added to the ring rather than waiting
+pub struct InstanceUploadRing
+{
+    frames: Vec<RingFrame>,
+    layouts: Vec<(SizeBufferBytes, BufferType)>,
+    current_frame: usize,
+    is_frame_acquired: bool,
+}
+
+impl InstanceUploadRing
+{
+    /// Creates a ring holding the given per-instance layouts
+    ///
+    /// `layouts` - the size in bytes and the binding information of every per-instance layout, in layout order
+    /// `number_frames` - the number of frames the ring starts with. At least FRAMES_IN_FLIGHT frames are created
+    pub fn new(layouts: Vec<(SizeBufferBytes, BufferType)>, number_frames: NumberBuffers) -> InstanceUploadRing
+    {
+        let mut ring = InstanceUploadRing{ frames: Vec::new(), layouts, current_frame: 0, is_frame_acquired: false };
+
+        for _ in 0..number_frames.max(FRAMES_IN_FLIGHT)
+        {
+            let frame = ring.create_frame();
+            ring.frames.push(frame);
+        }
+
+        ring
+    }
+
+    /// Starts writing the instance data of a frame, returning where to write each layout. The frame is the one
+    /// after the previously written frame if the GPU finished drawing it, otherwise a new frame. Calling this again
+    /// before the frame is finished returns the same frame
+    pub fn begin_frame(&mut self) -> Vec<BufferWriteInfo>
+    {
+        if !self.is_frame_acquired
+        {
+            let next_frame = (self.current_frame + 1) % self.frames.len();
+
+            // Frames are finished by the GPU in the order they were drawn, so if the oldest frame is still being
+            // read, so is every other frame
+            if !self.is_frame_free(next_frame)
+            {
+                if self.frames.len() < MAX_FRAMES
+                {
+                    debug!("Instance data of every frame is still in use by the GPU; adding frame {} to the upload ring", self.frames.len() + 1);
+                    let frame = self.create_frame();
+                    self.frames.insert(next_frame, frame);
+                }
+                else
+                {
+                    warn!("Instance upload ring holds {} frames that are all in use by the GPU; waiting for the oldest frame", MAX_FRAMES);
+                    self.wait_for_frame(next_frame);
+                }
+            }
+
+            self.current_frame = next_frame;
+            self.is_frame_acquired = true;
+        }
+
+        self.frames[self.current_frame].buffers.iter().map(|x| x.write_info()).collect()
+    }
+
+    /// Flushes the data written into the frame being written, and binds its buffers so that they are drawn from
+    ///
+    /// `data_changed_range` - the range of bytes written to each layout, in layout order
+    pub fn flush(&mut self, data_changed_range: Vec<(isize, isize)>)
+    {
+        for (buffer, (start_byte_changed, number_bytes_changed)) in self.frames[self.current_frame].buffers.iter_mut().zip(data_changed_range)
+        {
+            buffer.mark_buffer_updates_finish(start_byte_changed, number_bytes_changed);
+        }
+    }
+
+    /// Get the buffers of the frame most recently written, in layout order
+    pub fn current_buffers(&self) -> &[MappedBuffer]
+    {
+        &self.frames[self.current_frame].buffers
+    }
+
+    /// Finishes the frame most recently written. This must be called right after the draw operations that read it,
+    /// and is called after every draw, as a frame can be drawn more than once
+    pub fn end_frame(&mut self)
+    {
+        let frame = &mut self.frames[self.current_frame];
+
+        unsafe
+            {
+                if let Some(fence) = frame.fence
+                {
+                    gl::DeleteSync(fence);
+                }
+
+                frame.fence = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+            }
+
+        self.is_frame_acquired = false;
+    }
+
+    /// Creates the buffers of a single frame
+    fn create_frame(&self) -> RingFrame
+    {
+        let buffers = self.layouts.iter().map(|(size_buffer_bytes, buffer_type)| MappedBuffer::new(*size_buffer_bytes, buffer_type.clone(), 1)).collect();
+        RingFrame{ buffers, fence: None }
+    }
+
+    /// Checks if the GPU finished the draws reading the given frame, without waiting
+    ///
+    /// `frame_index` - the index of the frame to check
+    fn is_frame_free(&self, frame_index: usize) -> bool
+    {
+        match self.frames[frame_index].fence
+        {
+            Some(fence) =>
+                {
+                    let fence_result = unsafe { gl::ClientWaitSync(fence, 0, 0) };
+                    fence_result == gl::ALREADY_SIGNALED || fence_result == gl::CONDITION_SATISFIED
+                },
+            None => true
+        }
+    }
+
+    /// Blocks until the GPU finished the draws reading the given frame
+    ///
+    /// `frame_index` - the index of the frame to wait for
+    fn wait_for_frame(&self, frame_index: usize)
+    {
+        if let Some(fence) = self.frames[frame_index].fence
+        {
+            let fence_result = unsafe { gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, FULL_RING_TIMEOUT) };
+
+            if fence_result == gl::TIMEOUT_EXPIRED || fence_result == gl::WAIT_FAILED
+            {
+                panic!("Instance data of the upload ring never stopped being used by the GPU");
+            }
+        }
+    }
+}
+
+impl Drop for InstanceUploadRing
+{
+    fn drop(&mut self)
+    {
+        for fence in self.frames.iter().filter_map(|x| x.fence)
+        {
+            unsafe { gl::DeleteSync(fence); }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::mem::size_of;
+use nalgebra_glm::{TMat4x4, TVec3, TVec4, vec3, vec4};
+use crate::exports::camera_object::Camera;
+use crate::render_components::light_storage_buffer::LightStorageBuffer;
+use crate::render_components::mapped_buffer::{BindingPoint, BufferType, MappedBuffer};
+
+/// The binding points of the cluster buffers, which follow the binding points of the light storage buffers
+pub const LIGHT_CLUSTER_BINDING: BindingPoint = 3;
+pub const CLUSTER_LIGHT_INDEX_BINDING: BindingPoint = 4;
+
+/// The number of clusters the view frustum is divided into along each axis. The depth slices grow
+/// exponentially with distance, so that clusters far from the camera are not much smaller on screen than
+/// clusters close to it
+pub const CLUSTER_GRID_X: usize = 16;
+pub const CLUSTER_GRID_Y: usize = 9;
+pub const CLUSTER_GRID_Z: usize = 24;
+
+const NUMBER_CLUSTERS: usize = CLUSTER_GRID_X * CLUSTER_GRID_Y * CLUSTER_GRID_Z;
+
+/// GLSL declarations of the cluster buffers and the function to find the cluster of a fragment. The
+/// cluster of a fragment holds the offset and count into the cluster light index list of the point
+/// lights and then the spot lights affecting that fragment
+pub const LIGHT_CLUSTER_DECLARATIONS: &str =
+"#define CLUSTERED_LIGHTING
+
+const uint CLUSTER_GRID_X = 16;
+const uint CLUSTER_GRID_Y = 9;
+const uint CLUSTER_GRID_Z = 24;
+
+layout (std430, binding = 3) readonly buffer LightClusters
+{
+\tmat4 clusterViewMatrix;
+\tmat4 clusterProjectionMatrix;
+\tvec4 clusterDepthParams; // Near, far, log(far / near), unused
+\tuvec4 lightClusters[]; // Point light offset, point light count, spot light offset, spot light count
+};
+
+layout (std430, binding = 4) readonly buffer ClusterLightIndexes
+{
+\tuint clusterLightIndexes[];
+};
+
+uvec4 findLightCluster(vec3 worldPosition)
+{
+\tvec4 viewPosition = clusterViewMatrix * vec4(worldPosition, 1.0);
+\tvec4 clipPosition = clusterProjectionMatrix * viewPosition;
+\tvec2 screenPosition = clamp(clipPosition.xy / clipPosition.w * 0.5 + 0.5, 0.0, 0.9999);
+
+\tfloat depth = clamp(-viewPosition.z, clusterDepthParams.x, clusterDepthParams.y);
+\tuint slice = min(uint(log(depth / clusterDepthParams.x) / clusterDepthParams.z * CLUSTER_GRID_Z), CLUSTER_GRID_Z - 1);
+
+\tuint tileX = uint(screenPosition.x * CLUSTER_GRID_X);
+\tuint tileY = uint(screenPosition.y * CLUSTER_GRID_Y);
+
+\treturn lightClusters[tileX + tileY * CLUSTER_GRID_X + slice * CLUSTER_GRID_X * CLUSTER_GRID_Y];
+}
+";
+
+/// The volume a positioned light affects, used to determine which clusters it is binned into
+#[derive(Copy, Clone, Debug)]
+pub struct LightVolume
+{
+    pub position: TVec3<f32>,
+    pub radius: f32,
+}
+
+/// Layout of the start of the cluster buffer, before the list of clusters
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LightClusterHeader
+{
+    view_matrix: TMat4x4<f32>,
+    projection_matrix: TMat4x4<f32>,
+    depth_params: TVec4<f32>,
+}
+
+/// The bounds of a cluster in view space
+#[derive(Copy, Clone)]
+struct ClusterBounds
+{
+    min: TVec3<f32>,
+    max: TVec3<f32>,
+}
+
+/// Divides the view frustum into a grid of clusters and stores which lights affect each of them, so that
+/// a fragment only evaluates the lights near it rather than every visible light
+pub struct LightClusters
+{
+    cluster_buffer: MappedBuffer,
+    light_indexes: LightStorageBuffer,
+    cluster_bounds: Vec<ClusterBounds>,
+    bounds_projection: TMat4x4<f32>,
+    written_this_frame: bool,
+}
+
+impl LightClusters
+{
+    /// Creates the buffers holding the clusters and the lights within them
+    ///
+    /// `number_buffers` - the number of buffers to use in a round-robin fashion to prevent stalling
+    pub fn new(number_buffers: usize) -> LightClusters
+    {
+        let size_buffer_bytes = size_of::<LightClusterHeader>() + size_of::<[u32; 4]>() * NUMBER_CLUSTERS;
+
+        LightClusters
+        {
+            cluster_buffer: MappedBuffer::new(size_buffer_bytes as isize, BufferType::ShaderStorageArray(LIGHT_CLUSTER_BINDING), number_buffers),
+            light_indexes: LightStorageBuffer::new(CLUSTER_LIGHT_INDEX_BINDING, size_of::<u32>(), number_buffers),
+            cluster_bounds: Vec::new(),
+            bounds_projection: nalgebra_glm::zero(),
+            written_this_frame: false
+        }
+    }
+
+    /// Bins the lights into the clusters of the camera's view and uploads the result. The lights must be in
+    /// the same order as they were written to their light storage buffers
+    ///
+    /// `camera` - the camera the scene is being rendered with
+    /// `point_lights` - the volumes of the point lights written to the point light storage buffer
+    /// `spot_lights` - the volumes of the spot lights written to the spot light storage buffer
+    pub fn upload(&mut self, camera: &Camera, point_lights: &[LightVolume], spot_lights: &[LightVolume])
+    {
+        let view_matrix = camera.get_render_view_matrix();
+        let projection_matrix = camera.get_projection_matrix();
+        let near = camera.get_near_draw_distance().max(0.001);
+        let far = camera.get_far_draw_distance().max(near + 0.001);
+
+        // The bounds of the clusters only depend on the projection, which rarely changes
+        if projection_matrix != self.bounds_projection
+        {
+            self.cluster_bounds = LightClusters::compute_cluster_bounds(&projection_matrix, near, far);
+            self.bounds_projection = projection_matrix;
+        }
+
+        let point_clusters = self.bin_lights(&view_matrix, near, far, point_lights);
+        let spot_clusters = self.bin_lights(&view_matrix, near, far, spot_lights);
+
+        let mut clusters = Vec::with_capacity(NUMBER_CLUSTERS);
+        let mut indexes = Vec::new();
+
+        for (point_lights, spot_lights) in point_clusters.iter().zip(spot_clusters.iter())
+        {
+            let point_offset = indexes.len() as u32;
+            indexes.extend(point_lights.iter());
+
+            let spot_offset = indexes.len() as u32;
+            indexes.extend(spot_lights.iter());
+
+            clusters.push([point_offset, point_lights.len() as u32, spot_offset, spot_lights.len() as u32]);
+        }
+
+        // An empty index list still needs a buffer bound for the shader to read from
+        if indexes.is_empty()
+        {
+            indexes.push(0);
+        }
+
+        let header = LightClusterHeader
+        {
+            view_matrix,
+            projection_matrix,
+            depth_params: vec4(near, far, (far / near).ln(), 0.0)
+        };
+
+        let write_info = self.cluster_buffer.wait_for_next_free_buffer(1_000_000).unwrap();
+        let header_bytes = MappedBuffer::write_single_serialized_value(write_info, header, 0, true);
+        let cluster_bytes = MappedBuffer::write_data_serialized(write_info, &clusters, header_bytes, true);
+        self.cluster_buffer.mark_buffer_updates_finish(0, header_bytes + cluster_bytes);
+        self.written_this_frame = true;
+
+        self.light_indexes.upload(&indexes);
+    }
+
+    /// Sets the fences of the cluster buffers if they were written to. Call this after the draw call using the buffers
+    pub fn set_fences(&mut self)
+    {
+        if self.written_this_frame
+        {
+            self.cluster_buffer.set_fence();
+            self.written_this_frame = false;
+        }
+
+        self.light_indexes.set_fence();
+    }
+
+    /// Finds the indexes of the lights affecting every cluster
+    ///
+    /// `view_matrix` - the view matrix of the camera
+    /// `near` - the near draw distance of the camera
+    /// `far` - the far draw distance of the camera
+    /// `lights` - the volumes of the lights to bin
+    fn bin_lights(&self, view_matrix: &TMat4x4<f32>, near: f32, far: f32, lights: &[LightVolume]) -> Vec<Vec<u32>>
+    {
+        let mut clusters = vec![Vec::new(); NUMBER_CLUSTERS];
+
+        for (index, light) in lights.iter().enumerate()
+        {
+            let view_position = (view_matrix * vec4(light.position.x, light.position.y, light.position.z, 1.0)).xyz();
+            let depth = -view_position.z;
+
+            if depth + light.radius < near || depth - light.radius > far
+            {
+                continue;
+            }
+
+            // Only the depth slices the light reaches have to be checked
+            let first_slice = depth_slice(depth - light.radius, near, far);
+            let last_slice = depth_slice(depth + light.radius, near, far);
+
+            for slice in first_slice..=last_slice
+            {
+                let slice_start = slice * CLUSTER_GRID_X * CLUSTER_GRID_Y;
+
+                let slice_range = slice_start..slice_start + CLUSTER_GRID_X * CLUSTER_GRID_Y;
+
+                for (cluster, bounds) in clusters[slice_range.clone()].iter_mut().zip(&self.cluster_bounds[slice_range])
+                {
+                    let closest_point = nalgebra_glm::clamp_vec(&view_position, &bounds.min, &bounds.max);
+
+                    if nalgebra_glm::distance2(&closest_point, &view_position) <= light.radius * light.radius
+                    {
+                        cluster.push(index as u32);
+                    }
+                }
+            }
+        }
+
+        clusters
+    }
+
+    /// Computes the view space bounds of every cluster, in the order the clusters are stored in
+    ///
+    /// `projection_matrix` - the projection matrix of the camera
+    /// `near` - the near draw distance of the camera
+    /// `far` - the far draw distance of the camera
+    fn compute_cluster_bounds(projection_matrix: &TMat4x4<f32>, near: f32, far: f32) -> Vec<ClusterBounds>
+    {
+        let mut cluster_bounds = Vec::with_capacity(NUMBER_CLUSTERS);
+
+        // A point at the given depth and normalized device coordinates, for a perspective projection
+        let view_point = |ndc_x: f32, ndc_y: f32, depth: f32|
+            {
+                vec3(ndc_x * depth / projection_matrix[(0, 0)], ndc_y * depth / projection_matrix[(1, 1)], -depth)
+            };
+
+        for z in 0..CLUSTER_GRID_Z
+        {
+            let slice_near = near * (far / near).powf(z as f32 / CLUSTER_GRID_Z as f32);
+            let slice_far = near * (far / near).powf((z + 1) as f32 / CLUSTER_GRID_Z as f32);
+
+            for y in 0..CLUSTER_GRID_Y
+            {
+                let ndc_min_y = y as f32 / CLUSTER_GRID_Y as f32 * 2.0 - 1.0;
+                let ndc_max_y = (y + 1) as f32 / CLUSTER_GRID_Y as f32 * 2.0 - 1.0;
+
+                for x in 0..CLUSTER_GRID_X
+                {
+                    let ndc_min_x = x as f32 / CLUSTER_GRID_X as f32 * 2.0 - 1.0;
+                    let ndc_max_x = (x + 1) as f32 / CLUSTER_GRID_X as f32 * 2.0 - 1.0;
+
+                    let corners =
+                        [
+                            view_point(ndc_min_x, ndc_min_y, slice_near), view_point(ndc_max_x, ndc_max_y, slice_near),
+                            view_point(ndc_min_x, ndc_min_y, slice_far), view_point(ndc_max_x, ndc_max_y, slice_far),
+                        ];
+
+                    let min = corners.iter().fold(corners[0], |acc, x| nalgebra_glm::min2(&acc, x));
+                    let max = corners.iter().fold(corners[0], |acc, x| nalgebra_glm::max2(&acc, x));
+
+                    cluster_bounds.push(ClusterBounds{ min, max });
+                }
+            }
+        }
+
+        cluster_bounds
+    }
+}
+
+/// Get the depth slice containing the given distance from the camera, clamped to the slices of the grid
+///
+/// `depth` - the distance along the view direction from the camera
+/// `near` - the near draw distance of the camera
+/// `far` - the far draw distance of the camera
+fn depth_slice(depth: f32, near: f32, far: f32) -> usize
+{
+    let depth = depth.max(near).min(far);
+    let slice = ((depth / near).ln() / (far / near).ln() * CLUSTER_GRID_Z as f32) as usize;
+
+    slice.min(CLUSTER_GRID_Z - 1)
+}
@@ -0,0 +1,261 @@
+use std::mem::size_of;
+use nalgebra_glm::TVec4;
+use crate::render_components::light_clusters::{LightClusters, LightVolume};
+use crate::render_components::mapped_buffer::{BindingPoint, BufferType, MappedBuffer};
+
+/// The binding points of the light storage buffers. These are separate from the uniform block binding
+/// points, and so can start at zero
+pub const DIRECTION_LIGHT_STORAGE_BINDING: BindingPoint = 0;
+pub const POINT_LIGHT_STORAGE_BINDING: BindingPoint = 1;
+pub const SPOT_LIGHT_STORAGE_BINDING: BindingPoint = 2;
+
+/// The number of lights of each type that space is initially reserved for
+const INITIAL_LIGHT_CAPACITY: usize = 16;
+
+/// GLSL declarations of the light storage buffers. The structures only use vec4s so that the std430
+/// layout matches the layout of the Rust structures below without any padding
+pub const LIGHT_STORAGE_DECLARATIONS: &str =
+"struct DirectionLight
+{
+\tvec4 direction;
+\tvec4 diffuseColour;
+\tvec4 specularColour;
+\tvec4 ambientColour;
+};
+
+struct PointLight
+{
+\tvec4 position;
+\tvec4 direction;
+\tvec4 diffuseColour;
+\tvec4 specularColour;
+\tvec4 ambientColour;
+\tvec4 attenuation; // Linear coefficient, quadratic coefficient, cutoff, outer cutoff
+};
+
+struct SpotLight
+{
+\tvec4 position; // Position, radius
+\tvec4 diffuseColour;
+\tvec4 specularColour;
+\tvec4 ambientColour;
+\tvec4 attenuation; // Linear coefficient, quadratic coefficient, two sided (1 if true), unused
+\tvec4 shapeRight; // Half extent along the right axis of an area light, shape
+\tvec4 shapeUp; // Half extent along the up axis of an area light, radius of an emissive sphere
+};
+
+layout (std430, binding = 0) readonly buffer DirectionLights
+{
+\tDirectionLight directionLights[];
+};
+
+layout (std430, binding = 1) readonly buffer PointLights
+{
+\tPointLight pointLights[];
+};
+
+layout (std430, binding = 2) readonly buffer SpotLights
+{
+\tSpotLight spotLights[];
+};
+
+// Finds the point of the spot light closest to the fragment, which the light is treated as shining from.
+// Returns false if the fragment is behind a one sided area light
+bool closestSpotLightPoint(SpotLight light, vec3 fragPosition, out vec3 lightPoint)
+{
+\tlightPoint = light.position.xyz;
+\tuint shape = uint(light.shapeRight.w);
+\tvec3 toFrag = fragPosition - light.position.xyz;
+
+\tif(shape == 0u)
+\t{
+\t\treturn true;
+\t}
+
+\tif(shape == 3u)
+\t{
+\t\tfloat distanceToCentre = length(toFrag);
+\t\tlightPoint += distanceToCentre > light.shapeUp.w ? toFrag / distanceToCentre * light.shapeUp.w : toFrag;
+\t\treturn true;
+\t}
+
+\tfloat halfWidth = length(light.shapeRight.xyz);
+\tfloat halfHeight = length(light.shapeUp.xyz);
+\tvec3 right = light.shapeRight.xyz / halfWidth;
+\tvec3 up = light.shapeUp.xyz / halfHeight;
+
+\tif(dot(toFrag, cross(right, up)) <= 0.0 && light.attenuation.z == 0.0)
+\t{
+\t\treturn false;
+\t}
+
+\tvec2 local = vec2(dot(toFrag, right), dot(toFrag, up));
+
+\tif(shape == 1u)
+\t{
+\t\tlocal = clamp(local, -vec2(halfWidth, halfHeight), vec2(halfWidth, halfHeight));
+\t}
+\telse if(length(local) > halfWidth)
+\t{
+\t\tlocal *= halfWidth / length(local);
+\t}
+
+\tlightPoint += right * local.x + up * local.y;
+\treturn true;
+}
+";
+
+/// Shapes a spot light can shine from, stored in the w component of the shape right vector
+pub const SPOT_LIGHT_SHAPE_POINT: f32 = 0.0;
+pub const SPOT_LIGHT_SHAPE_RECTANGLE: f32 = 1.0;
+pub const SPOT_LIGHT_SHAPE_DISK: f32 = 2.0;
+pub const SPOT_LIGHT_SHAPE_SPHERE: f32 = 3.0;
+
+/// Layout of a directional light within the directional light storage buffer
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct DirectionLightStorage
+{
+    pub direction: TVec4<f32>,
+    pub diffuse_colour: TVec4<f32>,
+    pub specular_colour: TVec4<f32>,
+    pub ambient_colour: TVec4<f32>,
+}
+
+/// Layout of a point light within the point light storage buffer
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct PointLightStorage
+{
+    pub position: TVec4<f32>,
+    pub direction: TVec4<f32>,
+    pub diffuse_colour: TVec4<f32>,
+    pub specular_colour: TVec4<f32>,
+    pub ambient_colour: TVec4<f32>,
+    pub attenuation: TVec4<f32>,
+}
+
+/// Layout of a spot light within the spot light storage buffer
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SpotLightStorage
+{
+    pub position: TVec4<f32>,
+    pub diffuse_colour: TVec4<f32>,
+    pub specular_colour: TVec4<f32>,
+    pub ambient_colour: TVec4<f32>,
+    pub attenuation: TVec4<f32>,
+    pub shape_right: TVec4<f32>,
+    pub shape_up: TVec4<f32>,
+}
+
+/// A shader storage buffer holding a list of lights. The buffer grows when more lights are uploaded
+/// than there is space for; as the shader declares an unsized array, the shader does not need to change
+pub struct LightStorageBuffer
+{
+    buffer: MappedBuffer,
+    binding_point: BindingPoint,
+    number_buffers: usize,
+    element_size_bytes: usize,
+    capacity: usize,
+    written_this_frame: bool,
+    light_volumes: Vec<LightVolume>,
+}
+
+impl LightStorageBuffer
+{
+    /// Creates a new light storage buffer with space for a default number of lights
+    ///
+    /// `binding_point` - the shader storage binding point the buffer is bound to
+    /// `element_size_bytes` - the size of a single light in the buffer
+    /// `number_buffers` - the number of buffers to use in a round-robin fashion to prevent stalling
+    pub fn new(binding_point: BindingPoint, element_size_bytes: usize, number_buffers: usize) -> LightStorageBuffer
+    {
+        let buffer = MappedBuffer::new((element_size_bytes * INITIAL_LIGHT_CAPACITY) as isize, BufferType::ShaderStorageArray(binding_point), number_buffers);
+        LightStorageBuffer{ buffer, binding_point, number_buffers, element_size_bytes, capacity: INITIAL_LIGHT_CAPACITY, written_this_frame: false, light_volumes: Vec::new() }
+    }
+
+    /// Writes the given lights to the buffer and binds it, growing the buffer if required
+    ///
+    /// `lights` - the lights to write to the buffer
+    pub fn upload<T: 'static + Copy>(&mut self, lights: &[T])
+    {
+        debug_assert_eq!(size_of::<T>(), self.element_size_bytes, "Incorrect light structure written to light storage buffer");
+
+        if lights.len() > self.capacity
+        {
+            self.capacity = lights.len().next_power_of_two();
+            self.buffer = MappedBuffer::new((self.element_size_bytes * self.capacity) as isize, BufferType::ShaderStorageArray(self.binding_point), self.number_buffers);
+        }
+
+        let write_info = self.buffer.wait_for_next_free_buffer(1_000_000).unwrap();
+        let bytes_written = MappedBuffer::write_data_serialized(write_info, lights, 0, true);
+        self.buffer.mark_buffer_updates_finish(0, bytes_written);
+        self.written_this_frame = true;
+    }
+
+    /// Stores the volumes of the lights written to the buffer, in the order they were written, for the
+    /// lights to be binned into clusters
+    ///
+    /// `light_volumes` - the volumes of the uploaded lights
+    pub fn set_light_volumes(&mut self, light_volumes: Vec<LightVolume>)
+    {
+        self.light_volumes = light_volumes;
+    }
+
+    /// Get the volumes of the lights last written to the buffer
+    pub fn light_volumes(&self) -> &[LightVolume]
+    {
+        &self.light_volumes
+    }
+
+    /// Sets the fence for the buffer if it was written to. Call this after the draw call using the buffer
+    pub fn set_fence(&mut self)
+    {
+        if self.written_this_frame
+        {
+            self.buffer.set_fence();
+            self.written_this_frame = false;
+        }
+    }
+}
+
+/// Holds the storage buffers for each type of light
+pub struct LightStorageBuffers
+{
+    pub directional: LightStorageBuffer,
+    pub point: LightStorageBuffer,
+    pub spot: LightStorageBuffer,
+    pub clusters: Option<LightClusters>,
+}
+
+impl LightStorageBuffers
+{
+    /// Creates the storage buffers for all types of lights
+    ///
+    /// `number_buffers` - the number of buffers each light type uses to prevent stalling
+    /// `clustered_lighting` - true if the lights are also binned into clusters of the view frustum
+    pub fn new(number_buffers: usize, clustered_lighting: bool) -> LightStorageBuffers
+    {
+        LightStorageBuffers
+        {
+            directional: LightStorageBuffer::new(DIRECTION_LIGHT_STORAGE_BINDING, size_of::<DirectionLightStorage>(), number_buffers),
+            point: LightStorageBuffer::new(POINT_LIGHT_STORAGE_BINDING, size_of::<PointLightStorage>(), number_buffers),
+            spot: LightStorageBuffer::new(SPOT_LIGHT_STORAGE_BINDING, size_of::<SpotLightStorage>(), number_buffers),
+            clusters: if clustered_lighting { Some(LightClusters::new(number_buffers)) } else { None },
+        }
+    }
+
+    /// Sets the fences of the light storage buffers that were written to this frame
+    pub fn set_fences(&mut self)
+    {
+        self.directional.set_fence();
+        self.point.set_fence();
+        self.spot.set_fence();
+
+        if let Some(ref mut clusters) = self.clusters
+        {
+            clusters.set_fences();
+        }
+    }
+}
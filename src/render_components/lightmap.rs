@@ -0,0 +1,213 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use nalgebra_glm::TVec3;
+use crate::exports::world_precision::render_origin;
+use crate::flows::light_bake_flow::BakedLighting;
+use crate::render_components::mapped_buffer::BindingPoint;
+
+/// The texture unit the lightmap is bound to. This is above the texture units used by the G-Buffer and
+/// shadow maps of second render passes
+pub const LIGHTMAP_TEXTURE_UNIT: u32 = 15;
+
+/// The binding point of the storage buffer listing the world sections found in the baked lighting,
+/// following the binding points of the light cluster buffers
+pub const BAKED_SECTION_BINDING: BindingPoint = 5;
+
+/// GLSL declarations of the lightmap and the function to sample it. The baked world sections are sorted by
+/// level and then offsets, so the world section around a fragment is found with a binary search per level
+pub const BAKED_LIGHTING_DECLARATIONS: &str =
+"#define BAKED_LIGHTING
+
+layout (binding = 15) uniform sampler2DArray lightmap;
+
+layout (std430, binding = 5) readonly buffer BakedSections
+{
+\tuvec4 bakedLightingParams; // Atomic section length, resolution, number of sections, max level
+\tvec4 bakedLightingOrigin; // The render origin fragment positions are relative to
+\tuvec4 bakedSections[]; // Level << 16 | x, y << 16 | z, layer + 1 (0 if dynamic), unused
+};
+
+int findBakedLayer(uint level, uvec3 offsets)
+{
+\tuvec2 key = uvec2(level << 16 | offsets.x, offsets.y << 16 | offsets.z);
+\tint low = 0;
+\tint high = int(bakedLightingParams.z) - 1;
+
+\twhile(low <= high)
+\t{
+\t\tint middle = (low + high) / 2;
+\t\tuvec2 middleKey = bakedSections[middle].xy;
+
+\t\tif(middleKey == key)
+\t\t{
+\t\t\treturn int(bakedSections[middle].z) - 1;
+\t\t}
+
+\t\tif(middleKey.x < key.x || (middleKey.x == key.x && middleKey.y < key.y))
+\t\t{
+\t\t\tlow = middle + 1;
+\t\t}
+\t\telse
+\t\t{
+\t\t\thigh = middle - 1;
+\t\t}
+\t}
+
+\treturn -2;
+}
+
+bool sampleBakedLighting(vec3 position, out vec3 irradiance)
+{
+\tirradiance = vec3(0.0);
+\tvec3 worldPosition = position + bakedLightingOrigin.xyz;
+
+\tif(bakedLightingParams.z == 0u || any(lessThan(worldPosition, vec3(0.0))))
+\t{
+\t\treturn false;
+\t}
+
+\t// The smallest world section holding entities around the fragment decides how it is lit
+\tfor(uint level = 0u; level <= bakedLightingParams.w; ++level)
+\t{
+\t\tfloat sectionLength = float(bakedLightingParams.x << level);
+\t\tuvec3 offsets = uvec3(worldPosition / sectionLength);
+\t\tint layer = findBakedLayer(level, offsets);
+
+\t\tif(layer == -2)
+\t\t{
+\t\t\tcontinue;
+\t\t}
+
+\t\tif(layer == -1)
+\t\t{
+\t\t\treturn false;
+\t\t}
+
+\t\tfloat resolution = float(bakedLightingParams.y);
+\t\tvec3 local = clamp((worldPosition / sectionLength - vec3(offsets)) * resolution - 0.5, 0.0, resolution - 1.0);
+
+\t\t// Each row of the layer holds one y slice, with the z slices placed side by side; the z slices
+\t\t// are interpolated between manually so the filtering does not blend neighbouring slices
+\t\tfloat zSlice = floor(local.z);
+\t\tfloat nextZSlice = min(zSlice + 1.0, resolution - 1.0);
+\t\tvec2 textureSize = vec2(resolution * resolution, resolution);
+
+\t\tvec3 first = texture(lightmap, vec3((vec2(local.x + zSlice * resolution, local.y) + 0.5) / textureSize, layer)).rgb;
+\t\tvec3 second = texture(lightmap, vec3((vec2(local.x + nextZSlice * resolution, local.y) + 0.5) / textureSize, layer)).rgb;
+
+\t\tirradiance = mix(first, second, local.z - zSlice);
+\t\treturn true;
+\t}
+
+\treturn false;
+}
+";
+
+/// The baked lighting of static world sections uploaded to the GPU
+pub struct Lightmap
+{
+    texture: u32,
+    section_buffer: u32,
+}
+
+impl Lightmap
+{
+    /// Creates a lightmap without any baked world sections, so that shaders sampling the lightmap always
+    /// use dynamic lighting
+    pub fn empty() -> Lightmap
+    {
+        Lightmap::new(&BakedLighting::default())
+    }
+
+    /// Uploads the baked lighting
+    ///
+    /// `baked_lighting` - the lighting of the static world sections
+    pub fn new(baked_lighting: &BakedLighting) -> Lightmap
+    {
+        let resolution = baked_lighting.resolution.max(1) as i32;
+        let number_layers = baked_lighting.number_layers.max(1) as i32;
+
+        let mut sections = baked_lighting.sections.iter()
+            .map(|x| [(x.level as u32) << 16 | x.offsets.0 as u32, (x.offsets.1 as u32) << 16 | x.offsets.2 as u32, (x.layer + 1) as u32, 0])
+            .collect::<Vec<[u32; 4]>>();
+        sections.sort();
+
+        let max_level = baked_lighting.sections.iter().map(|x| x.level as u32).max().unwrap_or(0);
+        let params = [baked_lighting.atomic_section_length, resolution as u32, sections.len() as u32, max_level];
+
+        let mut texture: u32 = 0;
+        let mut section_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::CreateTextures(gl::TEXTURE_2D_ARRAY, 1, &mut texture);
+                gl::TextureStorage3D(texture, 1, gl::RGBA16F, resolution * resolution, resolution, number_layers);
+                gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+                if !baked_lighting.irradiance.is_empty()
+                {
+                    // The samples are stored with x varying fastest, then z, then y, which is the row major
+                    // order of a layer with the z slices side by side
+                    gl::TextureSubImage3D(texture, 0, 0, 0, 0, resolution * resolution, resolution, baked_lighting.number_layers as i32,
+                                          gl::RGBA, gl::FLOAT, baked_lighting.irradiance.as_ptr() as *const c_void);
+                }
+
+                let size_buffer_bytes = size_of::<[u32; 4]>() * (sections.len() + 2);
+                let mut buffer_data = Vec::with_capacity(sections.len() + 2);
+                buffer_data.push(params);
+                buffer_data.push(Lightmap::origin_data(render_origin()));
+                buffer_data.extend(sections);
+
+                gl::CreateBuffers(1, &mut section_buffer);
+                gl::NamedBufferStorage(section_buffer, size_buffer_bytes as isize, buffer_data.as_ptr() as *const c_void, gl::DYNAMIC_STORAGE_BIT);
+            }
+
+        Lightmap{ texture, section_buffer }
+    }
+
+    /// Updates the render origin that the positions sampling the lightmap are relative to
+    ///
+    /// `origin` - the new render origin
+    pub fn set_render_origin(&self, origin: TVec3<f64>)
+    {
+        let origin_data = Lightmap::origin_data(origin);
+
+        unsafe
+            {
+                gl::NamedBufferSubData(self.section_buffer, size_of::<[u32; 4]>() as isize, size_of::<[u32; 4]>() as isize, origin_data.as_ptr() as *const c_void);
+            }
+    }
+
+    /// Packs the render origin into the layout of the storage buffer
+    ///
+    /// `origin` - the render origin to pack
+    fn origin_data(origin: TVec3<f64>) -> [u32; 4]
+    {
+        [(origin.x as f32).to_bits(), (origin.y as f32).to_bits(), (origin.z as f32).to_bits(), 0]
+    }
+
+    /// Binds the lightmap and the list of baked world sections for second render passes to sample
+    pub fn bind(&self)
+    {
+        unsafe
+            {
+                gl::BindTextureUnit(LIGHTMAP_TEXTURE_UNIT, self.texture);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, BAKED_SECTION_BINDING, self.section_buffer);
+            }
+    }
+}
+
+impl Drop for Lightmap
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteTextures(1, &self.texture);
+                gl::DeleteBuffers(1, &self.section_buffer);
+            }
+    }
+}
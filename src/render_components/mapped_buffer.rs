@@ -2,6 +2,10 @@ use std::ffi::c_void;
 use std::mem::size_of;
 use std::ptr::{copy_nonoverlapping, null};
 use gl::types::GLsync;
+use tracing::warn;
+use crate::exports::frame_statistics::record_buffer_bytes_uploaded;
+use crate::exports::gl_debug_output::with_gl_debug_group;
+use crate::exports::memory_budget::{MemoryCategory, record_allocation, record_release};
 
 /// A buffer that supports updating data within itself without causing GPU stalls
 pub struct MappedBuffer
@@ -27,6 +31,7 @@ pub struct BufferWriteInfo
 pub type BindingPoint = u32;
 
 /// Specifies the binding information for the current buffer
+#[derive(Clone)]
 pub struct BindingInformation
 {
     binding_point: BindingPoint,
@@ -37,11 +42,13 @@ pub struct BindingInformation
 /// Specifies if the current buffer is to be used for indices
 // This is needed because if the buffer is for indices, then an explicit bind to the ELEMENT_ARRAY_BUFFER
 // target is required for the VAO to use indices
+#[derive(Clone)]
 pub enum BufferType
 {
     IndiceArray,
     NonIndiceArray(Vec<BindingInformation>),
     UniformBufferArray(BindingPoint),
+    ShaderStorageArray(BindingPoint),
 }
 
 /// Represents possible errors that can occur when waiting for a buffer to be available for writing
@@ -89,21 +96,33 @@ impl MappedBuffer
             gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_UNSYNCHRONIZED_BIT | gl::MAP_FLUSH_EXPLICIT_BIT
         };
 
-        unsafe
+        with_gl_debug_group(&format!("creating and mapping {} buffers of {} bytes", number_buffers, size_buffer_bytes), ||
             {
-                for _ in 0..number_buffers
-                {
-                    let mut new_buffer: u32 = 0;
+                unsafe
+                    {
+                        for _ in 0..number_buffers
+                        {
+                            let mut new_buffer: u32 = 0;
 
-                    gl::CreateBuffers(1, &mut new_buffer);
-                    gl::NamedBufferStorage(new_buffer, size_buffer_bytes, null(), buffer_bitmap);
+                            gl::CreateBuffers(1, &mut new_buffer);
+                            gl::NamedBufferStorage(new_buffer, size_buffer_bytes, null(), buffer_bitmap);
 
-                    buffer.push(new_buffer);
-                    ptr.push( gl::MapNamedBufferRange(new_buffer, 0, size_buffer_bytes, ptr_bitmap) );
-                    fence.push(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) );
+                            buffer.push(new_buffer);
+                            ptr.push( gl::MapNamedBufferRange(new_buffer, 0, size_buffer_bytes, ptr_bitmap) );
+                            fence.push(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) );
 
-                }
-            }
+                        }
+                    }
+            });
+
+        // Writing through a buffer that failed to map would write to a null pointer. Empty buffers are never
+        // written to, and cannot be mapped
+        if size_buffer_bytes > 0 && ptr.iter().any(|x| x.is_null())
+        {
+            panic!("Unable to map a buffer of {} bytes; see the OpenGL debug output for the cause", size_buffer_bytes);
+        }
+
+        record_allocation(MemoryCategory::Buffers, size_buffer_bytes.max(0) as usize * number_buffers);
 
         MappedBuffer{ buffer, ptr, fence, current_instance_buffer_index: 0, buffer_type, number_buffers, size_buffer_bytes, is_fence_set: true }
     }
@@ -158,6 +177,13 @@ impl MappedBuffer
         Ok(BufferWriteInfo{ ptr: self.ptr[self.current_instance_buffer_index], size_buffer_bytes: self.size_buffer_bytes} )
     }
 
+    /// Get the information required to write to the current buffer without waiting for it to be free. Only
+    /// for buffers whose synchronization is done by the caller
+    pub fn write_info(&self) -> BufferWriteInfo
+    {
+        BufferWriteInfo{ ptr: self.ptr[self.current_instance_buffer_index], size_buffer_bytes: self.size_buffer_bytes }
+    }
+
     /// Write data to the buffer without any type safety checks
     ///
     /// `write_information` - information required to write to a mapped buffer
@@ -176,7 +202,7 @@ impl MappedBuffer
             }
             else
             {
-                eprintln!("Attempting to write {} bytes of data into buffer of {} bytes large with byte offset {}", bytes_to_write, write_information.size_buffer_bytes, offset_count);
+                warn!("Attempting to write {} bytes of data into buffer of {} bytes large with byte offset {}", bytes_to_write, write_information.size_buffer_bytes, offset_count);
             }
         }
 
@@ -185,6 +211,8 @@ impl MappedBuffer
                 copy_nonoverlapping(data.as_ptr() as *const u8, (write_information.ptr as *mut u8).offset(offset_count), bytes_to_write.min(write_information.size_buffer_bytes as usize));
             }
 
+        record_buffer_bytes_uploaded(bytes_to_write.min(write_information.size_buffer_bytes as usize));
+
         (size_type * data.len()) as isize
     }
 
@@ -263,11 +291,24 @@ impl MappedBuffer
                         {
                             gl::BindBufferBase(gl::UNIFORM_BUFFER, binding_point, self.buffer[self.current_instance_buffer_index])
                         }
+                },
+            BufferType::ShaderStorageArray(binding_point) =>
+                {
+                    unsafe
+                        {
+                            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding_point, self.buffer[self.current_instance_buffer_index])
+                        }
                 }
 
         }
     }
 
+    /// Get the OpenGL buffer currently written to and bound
+    pub fn current_buffer(&self) -> u32
+    {
+        self.buffer[self.current_instance_buffer_index]
+    }
+
     /// Updates the fences for the buffer. This must be called right after the draw operations that use
     /// the buffer
     pub fn set_fence(&mut self)
@@ -280,6 +321,25 @@ impl MappedBuffer
     }
 }
 
+impl Drop for MappedBuffer
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                for (buffer, fence) in self.buffer.iter().zip(self.fence.iter())
+                {
+                    gl::UnmapNamedBuffer(*buffer);
+                    gl::DeleteSync(*fence);
+                }
+
+                gl::DeleteBuffers(self.buffer.len() as i32, self.buffer.as_ptr());
+            }
+
+        record_release(MemoryCategory::Buffers, self.size_buffer_bytes.max(0) as usize * self.number_buffers);
+    }
+}
+
 impl BindingInformation
 {
     /// Creates a new structure of binding information
@@ -3,4 +3,33 @@ pub mod mapped_buffer;
 pub mod vao;
 pub mod texture_array;
 pub mod cubemap;
-pub mod frame_buffer;
\ No newline at end of file
+pub mod frame_buffer;
+pub mod light_storage_buffer;
+pub mod light_clusters;
+pub mod lightmap;
+pub mod environment_lighting;
+pub mod damage_masks;
+pub mod gl_state_guard;
+pub mod shared_texture_arrays;
+pub mod colour_filter_pass;
+pub mod volumetric_fog_pass;
+pub mod glyph_atlas;
+pub mod world_label_pass;
+pub mod particle_pass;
+pub mod self_test_pass;
+pub mod dissolve_noise;
+pub mod gpu_level_of_view_pass;
+pub mod post_process_pass;
+pub mod screen_space_reflection_pass;
+pub mod skybox_pass;
+pub mod sprite_pass;
+pub mod font_atlas;
+pub mod text_pass;
+pub mod debug_line_pass;
+pub mod debug_view_pass;
+pub mod gpu_timer;
+pub mod hi_z_buffer;
+pub mod indirect_draw_buffer;
+pub mod instance_upload_ring;
+#[cfg(feature = "tooling_ui")]
+pub mod tooling_ui_pass;
\ No newline at end of file
@@ -0,0 +1,448 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use std::time::Instant;
+use hashbrown::HashMap;
+use nalgebra_glm::{TMat4, TVec3, vec3};
+use tracing::warn;
+use crate::exports::particles::{MAX_CURVE_KEYS, ParticleEmitter};
+use crate::exports::world_precision::render_origin;
+use crate::helper_things::environment::get_asset_folder;
+use crate::objects::entity_id::EntityId;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the G-Buffer layout holding world positions is bound to when drawing particles
+pub const PARTICLE_POSITION_TEXTURE_UNIT: u32 = 0;
+
+/// Shader storage binding point of the particles, after those used by world labels and environment lighting
+const PARTICLE_BINDING_POINT: u32 = 8;
+
+/// Shader storage binding point of the emitters
+const EMITTER_BINDING_POINT: u32 = 9;
+
+/// Most particles that exist at once, across all emitters
+const MAX_PARTICLES: u32 = 65536;
+
+/// Number of particles simulated by each work group of the simulation shader
+const SIMULATION_GROUP_SIZE: u32 = 64;
+
+/// Size in bytes of a particle, laid out as the Particle struct of the particle shaders
+const PARTICLE_SIZE: usize = 48;
+
+/// Longest time simulated in one frame, so that a stall does not launch every particle far away
+const MAX_SIMULATION_STEP: f32 = 0.1;
+
+/// Structs, buffers and functions shared by the particle shaders
+const PARTICLE_DECLARATIONS: &str =
+"struct Particle
+{
+    // xyz is the position relative to the render origin, w is the age in seconds
+    vec4 positionAge;
+    // xyz is the random velocity added to the velocity of the emitter, w is the lifetime in seconds
+    vec4 spreadLifetime;
+    // x is the index of the emitter the particle belongs to
+    uvec4 emitter;
+};
+
+struct ParticleEmitter
+{
+    // xyz is the position relative to the render origin, w is the largest random speed added to particles
+    vec4 positionSpread;
+    // x is the lifetime, y and z the size when spawned and when dying, w is 1 if particles are additive
+    vec4 lifetimeSize;
+    // x is the first particle of the emitter, y the number of particles, z the first particle to spawn and w the number to spawn
+    uvec4 slots;
+    vec4 velocityTimes;
+    vec4 velocityKeys[4];
+    vec4 colourTimes;
+    vec4 colourKeys[4];
+};
+
+layout (std430, binding = 8) buffer Particles
+{
+    Particle particles[];
+};
+
+layout (std430, binding = 9) readonly buffer ParticleEmitters
+{
+    ParticleEmitter emitters[];
+};
+
+vec4 evaluateCurve(vec4 times, vec4 keys[4], float time)
+{
+    if(time <= times[0])
+    {
+        return keys[0];
+    }
+
+    for(int i = 0; i < 3; ++i)
+    {
+        if(time <= times[i + 1])
+        {
+            float span = max(times[i + 1] - times[i], 0.0001);
+            return mix(keys[i], keys[i + 1], (time - times[i]) / span);
+        }
+    }
+
+    return keys[3];
+}
+";
+
+/// The view of the scene the particles are drawn for
+pub struct ParticleView
+{
+    pub camera_position: TVec3<f32>,
+    pub view_matrix: TMat4<f32>,
+    pub projection_matrix: TMat4<f32>,
+    /// If false, no world positions are bound and particles are drawn over all geometry
+    pub positions_available: bool,
+}
+
+/// An emitter, laid out as the ParticleEmitter struct of the particle shaders
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuEmitter
+{
+    position_spread: [f32; 4],
+    lifetime_size: [f32; 4],
+    slots: [u32; 4],
+    velocity_times: [f32; MAX_CURVE_KEYS],
+    velocity_keys: [[f32; 4]; MAX_CURVE_KEYS],
+    colour_times: [f32; MAX_CURVE_KEYS],
+    colour_keys: [[f32; 4]; MAX_CURVE_KEYS],
+}
+
+/// The particles of the particle buffer given to an emitter. Particles are spawned into the slots in order,
+/// replacing the oldest particle once every slot has been used
+struct EmitterSlots
+{
+    offset: u32,
+    max_particles: u32,
+    next_spawn: u32,
+    spawn_accumulator: f32,
+}
+
+/// Locations of the uniforms of the particle shaders
+struct ParticleUniforms
+{
+    delta_time: i32,
+    origin_shift: i32,
+    frame_seed: i32,
+    view_projection: i32,
+    camera_right: i32,
+    camera_up: i32,
+    camera_position: i32,
+    window_dimensions: i32,
+    positions_available: i32,
+}
+
+/// Simulates particles in a compute shader and draws them into the window as quads facing the camera, with
+/// every particle drawn in one instanced draw call
+pub struct ParticlePass
+{
+    simulation_program: ShaderProgram,
+    draw_program: ShaderProgram,
+    vao: VAO,
+    uniforms: ParticleUniforms,
+    particle_buffer: u32,
+    emitter_buffer: u32,
+    emitter_slots: HashMap<EntityId, EmitterSlots>,
+    used_slots: u32,
+    previous_render_origin: TVec3<f64>,
+    last_simulation: Instant,
+    frame_seed: u32,
+    reported_full: bool,
+}
+
+impl ParticlePass
+{
+    /// Creates the resources required to simulate and draw particles. An OpenGL context must be current
+    pub fn new() -> Result<ParticlePass, String>
+    {
+        let version = "#version 430 core\n";
+        let declarations = format!("{}{}", version, PARTICLE_DECLARATIONS);
+
+        let simulation_program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::COMPUTE_SHADER, get_asset_folder().join("shaders/particle_simulation_compute.glsl"), Some(declarations.clone()), None)?,
+        ])?;
+
+        let draw_program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/particle_vertex.glsl"), Some(declarations), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/particle_frag.glsl"), Some(version), None)?,
+        ])?;
+
+        let uniforms = ParticleUniforms
+        {
+            delta_time: simulation_program.uniform_location("deltaTime"),
+            origin_shift: simulation_program.uniform_location("originShift"),
+            frame_seed: simulation_program.uniform_location("frameSeed"),
+            view_projection: draw_program.uniform_location("viewProjection"),
+            camera_right: draw_program.uniform_location("cameraRight"),
+            camera_up: draw_program.uniform_location("cameraUp"),
+            camera_position: draw_program.uniform_location("cameraPosition"),
+            window_dimensions: draw_program.uniform_location("windowDimensions"),
+            positions_available: draw_program.uniform_location("positionsAvailable"),
+        };
+
+        let mut particle_buffer: u32 = 0;
+        let mut emitter_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::ProgramUniform1i(draw_program.shader_program, draw_program.uniform_location("gPosition"), PARTICLE_POSITION_TEXTURE_UNIT as i32);
+
+                // A particle of all zeros has a lifetime of zero, and so is dead
+                gl::CreateBuffers(1, &mut particle_buffer);
+                gl::NamedBufferStorage(particle_buffer, (MAX_PARTICLES as usize * PARTICLE_SIZE) as isize, std::ptr::null(), gl::DYNAMIC_STORAGE_BIT);
+                gl::ClearNamedBufferData(particle_buffer, gl::R32UI, gl::RED_INTEGER, gl::UNSIGNED_INT, std::ptr::null());
+
+                gl::CreateBuffers(1, &mut emitter_buffer);
+            }
+
+        Ok(ParticlePass
+        {
+            simulation_program,
+            draw_program,
+            vao: VAO::new(),
+            uniforms,
+            particle_buffer,
+            emitter_buffer,
+            emitter_slots: HashMap::new(),
+            used_slots: 0,
+            previous_render_origin: render_origin(),
+            last_simulation: Instant::now(),
+            frame_seed: 0,
+            reported_full: false,
+        })
+    }
+
+    /// Spawns and moves the particles of the emitters, then draws every particle into the window. If world
+    /// positions are available, they must be bound to their texture unit. The OpenGL state is the same after
+    /// this call as before it
+    ///
+    /// `emitters` - every emitter, along with its entity and its position relative to the render origin
+    /// `view` - the view of the scene the particles are drawn for
+    /// `output_framebuffer` - the frame buffer the frame was rendered into, which is drawn over
+    pub fn apply(&mut self, emitters: &[(EntityId, TVec3<f32>, ParticleEmitter)], view: &ParticleView, output_framebuffer: OutputFramebuffer)
+    {
+        let window_dimensions = output_framebuffer.dimensions;
+
+        let delta_time = self.last_simulation.elapsed().as_secs_f32().min(MAX_SIMULATION_STEP);
+        self.last_simulation = Instant::now();
+
+        self.allocate_slots(emitters);
+
+        let gpu_emitters = self.create_gpu_emitters(emitters, delta_time);
+
+        if self.used_slots == 0 || gpu_emitters.is_empty() || window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        // Particles are stored relative to the render origin, so they are moved with it
+        let current_render_origin = render_origin();
+        let shift = self.previous_render_origin - current_render_origin;
+        let origin_shift = vec3(shift.x as f32, shift.y as f32, shift.z as f32);
+        self.previous_render_origin = current_render_origin;
+        self.frame_seed = self.frame_seed.wrapping_add(1);
+
+        let _state_guard = GLStateGuard::new();
+
+        let largest_emitter = gpu_emitters.iter().map(|x| x.slots[1]).max().unwrap_or(0);
+
+        unsafe
+            {
+                gl::NamedBufferData(self.emitter_buffer, (gpu_emitters.len() * size_of::<GpuEmitter>()) as isize, gpu_emitters.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, PARTICLE_BINDING_POINT, self.particle_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, EMITTER_BINDING_POINT, self.emitter_buffer);
+            }
+
+        self.simulation_program.use_shader_program();
+
+        unsafe
+            {
+                gl::Uniform1f(self.uniforms.delta_time, delta_time);
+                gl::Uniform3fv(self.uniforms.origin_shift, 1, origin_shift.as_ptr());
+                gl::Uniform1ui(self.uniforms.frame_seed, self.frame_seed);
+                gl::DispatchCompute(largest_emitter.div_ceil(SIMULATION_GROUP_SIZE), gpu_emitters.len() as u32, 1);
+                gl::MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+            }
+
+        let view_projection = view.projection_matrix * view.view_matrix;
+        let camera_right = vec3(view.view_matrix[(0, 0)], view.view_matrix[(0, 1)], view.view_matrix[(0, 2)]);
+        let camera_up = vec3(view.view_matrix[(1, 0)], view.view_matrix[(1, 1)], view.view_matrix[(1, 2)]);
+
+        unsafe
+            {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Enable(gl::BLEND);
+                // Colours are premultiplied by their opacity; additive particles have an opacity of zero
+                gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.draw_program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                gl::UniformMatrix4fv(self.uniforms.view_projection, 1, gl::FALSE, view_projection.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_right, 1, camera_right.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_up, 1, camera_up.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_position, 1, view.camera_position.as_ptr());
+                gl::Uniform2f(self.uniforms.window_dimensions, window_dimensions.0 as f32, window_dimensions.1 as f32);
+                gl::Uniform1i(self.uniforms.positions_available, view.positions_available as i32);
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, self.used_slots as i32);
+            }
+    }
+
+    /// Gives each emitter its own particles of the particle buffer. The particles of emitters that were removed,
+    /// or whose most particles changed, are killed. When the buffer has no room left for a new emitter, every
+    /// particle is killed and the particles are given out again from the start of the buffer
+    ///
+    /// `emitters` - every emitter, along with its entity
+    fn allocate_slots(&mut self, emitters: &[(EntityId, TVec3<f32>, ParticleEmitter)])
+    {
+        let mut removed_slots = Vec::new();
+
+        self.emitter_slots.retain(|entity_id, slots|
+            {
+                let keep = emitters.iter().any(|(x, _, emitter)| x == entity_id && emitter.max_particles == slots.max_particles);
+
+                if !keep
+                {
+                    removed_slots.push((slots.offset, slots.max_particles));
+                }
+
+                keep
+            });
+
+        for (offset, max_particles) in removed_slots
+        {
+            self.kill_particles(offset, max_particles);
+        }
+
+        if self.emitter_slots.is_empty()
+        {
+            self.used_slots = 0;
+        }
+
+        let required_slots = emitters.iter()
+            .filter(|(entity_id, _, _)| !self.emitter_slots.contains_key(entity_id))
+            .map(|(_, _, emitter)| emitter.max_particles)
+            .sum::<u32>();
+
+        if required_slots != 0 && self.used_slots + required_slots > MAX_PARTICLES
+        {
+            self.emitter_slots.clear();
+            self.kill_particles(0, MAX_PARTICLES);
+            self.used_slots = 0;
+        }
+
+        for (entity_id, _, emitter) in emitters
+        {
+            if self.emitter_slots.contains_key(entity_id) || emitter.max_particles == 0
+            {
+                continue;
+            }
+
+            if self.used_slots + emitter.max_particles > MAX_PARTICLES
+            {
+                if !self.reported_full
+                {
+                    warn!("There is no room for the particles of every emitter; at most {} particles can exist at once", MAX_PARTICLES);
+                    self.reported_full = true;
+                }
+
+                continue;
+            }
+
+            self.emitter_slots.insert(*entity_id, EmitterSlots{ offset: self.used_slots, max_particles: emitter.max_particles, next_spawn: 0, spawn_accumulator: 0.0 });
+            self.used_slots += emitter.max_particles;
+        }
+    }
+
+    /// Creates the information about every emitter given particles that the particle shaders read, including
+    /// which of its particles are spawned this frame
+    ///
+    /// `emitters` - every emitter, along with its entity and its position relative to the render origin
+    /// `delta_time` - the time being simulated, in seconds
+    fn create_gpu_emitters(&mut self, emitters: &[(EntityId, TVec3<f32>, ParticleEmitter)], delta_time: f32) -> Vec<GpuEmitter>
+    {
+        let mut gpu_emitters = Vec::with_capacity(emitters.len());
+
+        for (entity_id, position, emitter) in emitters
+        {
+            let slots = match self.emitter_slots.get_mut(entity_id)
+            {
+                Some(i) => i,
+                None => continue
+            };
+
+            let mut spawn_count = 0;
+
+            if emitter.emitting && emitter.rate > 0.0 && emitter.lifetime > 0.0
+            {
+                slots.spawn_accumulator += emitter.rate * delta_time;
+                spawn_count = (slots.spawn_accumulator.floor() as u32).min(slots.max_particles);
+                slots.spawn_accumulator = slots.spawn_accumulator.fract();
+            }
+
+            let spawn_start = slots.next_spawn;
+            slots.next_spawn = (slots.next_spawn + spawn_count) % slots.max_particles;
+
+            let (velocity_times, velocity_keys) = emitter.velocity.padded_keys();
+            let (colour_times, colour_keys) = emitter.colour.padded_keys();
+            let additive = if emitter.additive { 1.0 } else { 0.0 };
+
+            gpu_emitters.push(GpuEmitter
+            {
+                position_spread: [position.x, position.y, position.z, emitter.velocity_spread.max(0.0)],
+                lifetime_size: [emitter.lifetime.max(0.0), emitter.size.0.max(0.0), emitter.size.1.max(0.0), additive],
+                slots: [slots.offset, slots.max_particles, spawn_start, spawn_count],
+                velocity_times,
+                velocity_keys: velocity_keys.map(|x| [x.x, x.y, x.z, x.w]),
+                colour_times,
+                colour_keys: colour_keys.map(|x| [x.x, x.y, x.z, x.w]),
+            });
+        }
+
+        gpu_emitters
+    }
+
+    /// Kills the particles in the given range of the particle buffer
+    ///
+    /// `offset` - the first particle to kill
+    /// `count` - the number of particles to kill
+    fn kill_particles(&self, offset: u32, count: u32)
+    {
+        unsafe
+            {
+                gl::ClearNamedBufferSubData(self.particle_buffer, gl::R32UI, offset as isize * PARTICLE_SIZE as isize, count as isize * PARTICLE_SIZE as isize,
+                                            gl::RED_INTEGER, gl::UNSIGNED_INT, std::ptr::null());
+            }
+    }
+}
+
+impl Drop for ParticlePass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteBuffers(1, &self.particle_buffer);
+                gl::DeleteBuffers(1, &self.emitter_buffer);
+                gl::DeleteProgram(self.simulation_program.shader_program);
+                gl::DeleteProgram(self.draw_program.shader_program);
+            }
+    }
+}
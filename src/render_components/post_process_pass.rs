@@ -0,0 +1,344 @@
+use crate::exports::rendering::{PostProcessEffect, ToneMappingOperator};
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::TextureFormat;
+
+/// Texture unit the image an effect reads is bound to
+const SOURCE_TEXTURE_UNIT: u32 = 0;
+
+/// Texture unit the blurred bright pixels are bound to when bloom is added to the frame
+const BLOOM_TEXTURE_UNIT: u32 = 1;
+
+/// The effects drawn by the post-process fragment shader, matching the constants it declares
+const EFFECT_COPY: i32 = 0;
+const EFFECT_BRIGHT_PASS: i32 = 1;
+const EFFECT_BLUR: i32 = 2;
+const EFFECT_BLOOM_COMPOSITE: i32 = 3;
+const EFFECT_TONE_MAP: i32 = 4;
+const EFFECT_GAMMA: i32 = 5;
+const EFFECT_VIGNETTE: i32 = 6;
+const EFFECT_FXAA: i32 = 7;
+
+/// Locations of the uniforms of the post-process shader
+struct PostProcessUniforms
+{
+    effect: i32,
+    parameters: i32,
+    texel_size: i32,
+}
+
+/// Where an effect draws to
+#[derive(Copy, Clone)]
+struct EffectTarget
+{
+    framebuffer: u32,
+    dimensions: (i32, i32),
+}
+
+/// Applies the post-process stack to the frame. While the pass exists, the render systems drawing to the
+/// window render into its scene frame buffer, whose colours are half floats by default and so can be brighter
+/// than the window can show. Each effect then reads the result of the previous one, with the last effect
+/// drawing into the window
+pub struct PostProcessPass
+{
+    shader_program: ShaderProgram,
+    vao: VAO,
+    uniforms: PostProcessUniforms,
+    dimensions: (i32, i32),
+    scene_format: TextureFormat,
+    scene_framebuffer: u32,
+    scene_texture: u32,
+    scene_depth_stencil_texture: u32,
+    // Effects alternate between writing to each of these, reading what the previous effect wrote
+    effect_framebuffers: [u32; 2],
+    effect_textures: [u32; 2],
+    // The bright pixels of the frame are blurred between these, at half the resolution of the frame
+    bloom_framebuffers: [u32; 2],
+    bloom_textures: [u32; 2],
+}
+
+impl PostProcessPass
+{
+    /// Creates the resources required to apply the post-process stack. An OpenGL context must be current
+    ///
+    /// `dimensions` - the dimensions of the window
+    /// `scene_format` - the format of the frame buffer the frame is rendered into
+    pub fn new(dimensions: (i32, i32), scene_format: TextureFormat) -> Result<PostProcessPass, String>
+    {
+        let version = "#version 430 core\n";
+        let shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/post_process_frag.glsl"), Some(version), None)?,
+        ];
+
+        let shader_program = ShaderProgram::new(&shaders)?;
+
+        let uniforms = PostProcessUniforms
+        {
+            effect: shader_program.uniform_location("effect"),
+            parameters: shader_program.uniform_location("parameters"),
+            texel_size: shader_program.uniform_location("texelSize"),
+        };
+
+        unsafe
+            {
+                gl::ProgramUniform1i(shader_program.shader_program, shader_program.uniform_location("sourceTexture"), SOURCE_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(shader_program.shader_program, shader_program.uniform_location("bloomTexture"), BLOOM_TEXTURE_UNIT as i32);
+            }
+
+        let mut post_process_pass = PostProcessPass
+        {
+            shader_program,
+            vao: VAO::new(),
+            uniforms,
+            dimensions: (0, 0),
+            scene_format,
+            scene_framebuffer: 0,
+            scene_texture: 0,
+            scene_depth_stencil_texture: 0,
+            effect_framebuffers: [0; 2],
+            effect_textures: [0; 2],
+            bloom_framebuffers: [0; 2],
+            bloom_textures: [0; 2],
+        };
+
+        post_process_pass.resize(dimensions, scene_format)?;
+        Ok(post_process_pass)
+    }
+
+    /// Get the frame buffer the render systems drawing to the window render into
+    pub fn scene_framebuffer(&self) -> u32
+    {
+        self.scene_framebuffer
+    }
+
+    /// Recreates the frame buffers of the pass if the window dimensions or the format of the scene changed
+    ///
+    /// `dimensions` - the dimensions of the window
+    /// `scene_format` - the format of the frame buffer the frame is rendered into
+    pub fn resize(&mut self, dimensions: (i32, i32), scene_format: TextureFormat) -> Result<(), String>
+    {
+        let dimensions = (dimensions.0.max(1), dimensions.1.max(1));
+
+        if self.scene_framebuffer != 0 && self.dimensions == dimensions && self.scene_format == scene_format
+        {
+            return Ok(());
+        }
+
+        self.delete_framebuffers();
+
+        let bloom_dimensions = ((dimensions.0 / 2).max(1), (dimensions.1 / 2).max(1));
+
+        self.scene_texture = create_colour_texture(dimensions, scene_format);
+        self.scene_depth_stencil_texture = unsafe
+            {
+                let mut texture = 0;
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+                gl::TextureStorage2D(texture, 1, gl::DEPTH24_STENCIL8, dimensions.0, dimensions.1);
+                texture
+            };
+        self.scene_framebuffer = create_framebuffer(self.scene_texture, Some(self.scene_depth_stencil_texture))?;
+
+        for index in 0..2
+        {
+            self.effect_textures[index] = create_colour_texture(dimensions, TextureFormat::RGBA16F);
+            self.effect_framebuffers[index] = create_framebuffer(self.effect_textures[index], None)?;
+
+            self.bloom_textures[index] = create_colour_texture(bloom_dimensions, TextureFormat::RGBA16F);
+            self.bloom_framebuffers[index] = create_framebuffer(self.bloom_textures[index], None)?;
+        }
+
+        self.dimensions = dimensions;
+        self.scene_format = scene_format;
+        Ok(())
+    }
+
+    /// Applies the effects to the frame rendered into the scene frame buffer, drawing the result into the
+    /// window. The OpenGL state is the same after this call as before it
+    ///
+    /// `effects` - the effects to apply, in order
+    pub fn apply(&mut self, effects: &[PostProcessEffect])
+    {
+        let _state_guard = GLStateGuard::new();
+
+        unsafe
+            {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+
+        self.shader_program.use_shader_program();
+        self.vao.bind();
+
+        let window = EffectTarget{ framebuffer: 0, dimensions: self.dimensions };
+
+        if effects.is_empty()
+        {
+            self.draw_effect(EFFECT_COPY, self.scene_texture, [0.0; 4], window);
+            return;
+        }
+
+        let mut source = self.scene_texture;
+        let mut target_index = 0;
+
+        for (index, effect) in effects.iter().enumerate()
+        {
+            let target = if index == effects.len() - 1
+            {
+                window
+            }
+            else
+            {
+                EffectTarget{ framebuffer: self.effect_framebuffers[target_index], dimensions: self.dimensions }
+            };
+
+            match *effect
+            {
+                PostProcessEffect::Bloom { threshold, intensity, blur_passes } =>
+                    {
+                        self.blur_bright_pixels(source, threshold, blur_passes);
+
+                        unsafe{ gl::BindTextureUnit(BLOOM_TEXTURE_UNIT, self.bloom_textures[0]); }
+                        self.draw_effect(EFFECT_BLOOM_COMPOSITE, source, [intensity, 0.0, 0.0, 0.0], target);
+                        unsafe{ gl::BindTextureUnit(BLOOM_TEXTURE_UNIT, 0); }
+                    },
+                PostProcessEffect::ToneMapping { operator, exposure } =>
+                    {
+                        let aces = if operator == ToneMappingOperator::Aces { 1.0 } else { 0.0 };
+                        self.draw_effect(EFFECT_TONE_MAP, source, [exposure, aces, 0.0, 0.0], target);
+                    },
+                PostProcessEffect::Gamma(gamma) => self.draw_effect(EFFECT_GAMMA, source, [gamma, 0.0, 0.0, 0.0], target),
+                PostProcessEffect::Vignette { strength, radius } => self.draw_effect(EFFECT_VIGNETTE, source, [strength, radius, 0.0, 0.0], target),
+                PostProcessEffect::Fxaa => self.draw_effect(EFFECT_FXAA, source, [0.0; 4], target),
+            }
+
+            source = self.effect_textures[target_index];
+            target_index = 1 - target_index;
+        }
+
+        unsafe{ gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, 0); }
+    }
+
+    /// Extracts the pixels brighter than the threshold into the first bloom texture and blurs them
+    ///
+    /// `source` - the texture holding the frame
+    /// `threshold` - the brightness pixels must exceed to bloom
+    /// `blur_passes` - the number of times the bright pixels are blurred horizontally and vertically
+    fn blur_bright_pixels(&self, source: u32, threshold: f32, blur_passes: u32)
+    {
+        let bloom_dimensions = ((self.dimensions.0 / 2).max(1), (self.dimensions.1 / 2).max(1));
+        let bloom_target = |index: usize| EffectTarget{ framebuffer: self.bloom_framebuffers[index], dimensions: bloom_dimensions };
+
+        self.draw_effect(EFFECT_BRIGHT_PASS, source, [threshold, 0.0, 0.0, 0.0], bloom_target(0));
+
+        for _ in 0..blur_passes
+        {
+            self.draw_effect(EFFECT_BLUR, self.bloom_textures[0], [1.0, 0.0, 0.0, 0.0], bloom_target(1));
+            self.draw_effect(EFFECT_BLUR, self.bloom_textures[1], [0.0, 1.0, 0.0, 0.0], bloom_target(0));
+        }
+    }
+
+    /// Draws one effect over the entire target, reading from the given texture
+    ///
+    /// `effect` - the effect to draw
+    /// `source` - the texture the effect reads
+    /// `parameters` - the parameters of the effect
+    /// `target` - where the effect draws to
+    fn draw_effect(&self, effect: i32, source: u32, parameters: [f32; 4], target: EffectTarget)
+    {
+        // Only bloom textures are smaller than the frame, and only bloom textures are read when drawing into them
+        let source_dimensions = if target.dimensions == self.dimensions || effect == EFFECT_BRIGHT_PASS { self.dimensions } else { target.dimensions };
+
+        unsafe
+            {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, target.framebuffer);
+                gl::Viewport(0, 0, target.dimensions.0, target.dimensions.1);
+
+                gl::Uniform1i(self.uniforms.effect, effect);
+                gl::Uniform4fv(self.uniforms.parameters, 1, parameters.as_ptr());
+                gl::Uniform2f(self.uniforms.texel_size, 1.0 / source_dimensions.0 as f32, 1.0 / source_dimensions.1 as f32);
+                gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, source);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            }
+    }
+
+    /// Deletes the frame buffers and textures of the pass
+    fn delete_framebuffers(&mut self)
+    {
+        let framebuffers = [self.scene_framebuffer, self.effect_framebuffers[0], self.effect_framebuffers[1], self.bloom_framebuffers[0], self.bloom_framebuffers[1]];
+        let textures = [self.scene_texture, self.scene_depth_stencil_texture, self.effect_textures[0], self.effect_textures[1], self.bloom_textures[0], self.bloom_textures[1]];
+
+        unsafe
+            {
+                gl::DeleteFramebuffers(framebuffers.len() as i32, framebuffers.as_ptr());
+                gl::DeleteTextures(textures.len() as i32, textures.as_ptr());
+            }
+
+        self.scene_framebuffer = 0;
+    }
+}
+
+/// Creates a colour texture that is sampled linearly and clamped to its edges
+///
+/// `dimensions` - the dimensions of the texture
+/// `format` - the format of the texture
+fn create_colour_texture(dimensions: (i32, i32), format: TextureFormat) -> u32
+{
+    let mut texture = 0;
+
+    unsafe
+        {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+            gl::TextureStorage2D(texture, 1, format as u32, dimensions.0, dimensions.1);
+            gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        }
+
+    texture
+}
+
+/// Creates a frame buffer drawing into the given textures
+///
+/// `colour_texture` - the texture colours are written to
+/// `depth_stencil_texture` - the texture depth and stencil values are written to, if any
+fn create_framebuffer(colour_texture: u32, depth_stencil_texture: Option<u32>) -> Result<u32, String>
+{
+    let mut framebuffer = 0;
+
+    unsafe
+        {
+            gl::CreateFramebuffers(1, &mut framebuffer);
+            gl::NamedFramebufferTexture(framebuffer, gl::COLOR_ATTACHMENT0, colour_texture, 0);
+
+            if let Some(depth_stencil_texture) = depth_stencil_texture
+            {
+                gl::NamedFramebufferTexture(framebuffer, gl::DEPTH_STENCIL_ATTACHMENT, depth_stencil_texture, 0);
+            }
+
+            let status = gl::CheckNamedFramebufferStatus(framebuffer, gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE
+            {
+                gl::DeleteFramebuffers(1, &framebuffer);
+                return Err(format!("The post-process frame buffer is incomplete, with status {:#x}", status));
+            }
+        }
+
+    Ok(framebuffer)
+}
+
+impl Drop for PostProcessPass
+{
+    fn drop(&mut self)
+    {
+        self.delete_framebuffers();
+        unsafe{ gl::DeleteProgram(self.shader_program.shader_program); }
+    }
+}
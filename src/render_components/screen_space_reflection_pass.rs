@@ -0,0 +1,266 @@
+use nalgebra_glm::{TMat4, TVec3};
+use crate::exports::rendering::ScreenSpaceReflections;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture units the G-Buffer layouts are bound to by the second render pass, matching their layout index
+const POSITION_TEXTURE_UNIT: u32 = 0;
+const NORMAL_TEXTURE_UNIT: u32 = 1;
+const MATERIAL_TEXTURE_UNIT: u32 = 4;
+
+/// Texture unit the copy of the lit frame is bound to when ray marching, and the reflections are bound to
+/// when blending them over the frame. Unused by the G-Buffer layouts of either prelude render system
+const SOURCE_TEXTURE_UNIT: u32 = 5;
+
+/// Locations of the uniforms of the ray marching shader
+struct MarchUniforms
+{
+    view_matrix: i32,
+    projection_matrix: i32,
+    camera_position: i32,
+    use_material_roughness: i32,
+    steps: i32,
+    thickness: i32,
+    edge_fade: i32,
+    max_distance: i32,
+}
+
+/// The view of the scene reflections are found for
+pub struct ReflectionView
+{
+    pub camera_position: TVec3<f32>,
+    pub view_matrix: TMat4<f32>,
+    pub projection_matrix: TMat4<f32>,
+}
+
+/// Draws screen space reflections over the frame lit by the second render pass of a render system. The lit
+/// frame is copied so that rays can read it, the reflections are ray marched into an intermediate frame
+/// buffer, and are then blended over the frame by how smooth each surface is
+pub struct ScreenSpaceReflectionPass
+{
+    settings: ScreenSpaceReflections,
+    march_program: ShaderProgram,
+    composite_program: ShaderProgram,
+    vao: VAO,
+    march_uniforms: MarchUniforms,
+    dimensions: (i32, i32),
+    scene_framebuffer: u32,
+    scene_texture: u32,
+    reflection_framebuffer: u32,
+    reflection_texture: u32,
+}
+
+impl ScreenSpaceReflectionPass
+{
+    /// Creates the resources required to draw the reflections. An OpenGL context must be current
+    ///
+    /// `settings` - how the reflections are ray marched
+    pub fn new(settings: ScreenSpaceReflections) -> Result<ScreenSpaceReflectionPass, String>
+    {
+        let version = "#version 430 core\n";
+        let march_shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/screen_space_reflection_frag.glsl"), Some(version), None)?,
+        ];
+
+        let composite_shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/reflection_composite_frag.glsl"), Some(version), None)?,
+        ];
+
+        let march_program = ShaderProgram::new(&march_shaders)?;
+        let composite_program = ShaderProgram::new(&composite_shaders)?;
+
+        let march_uniforms = MarchUniforms
+        {
+            view_matrix: march_program.uniform_location("viewMatrix"),
+            projection_matrix: march_program.uniform_location("projectionMatrix"),
+            camera_position: march_program.uniform_location("cameraPosition"),
+            use_material_roughness: march_program.uniform_location("useMaterialRoughness"),
+            steps: march_program.uniform_location("steps"),
+            thickness: march_program.uniform_location("thickness"),
+            edge_fade: march_program.uniform_location("edgeFade"),
+            max_distance: march_program.uniform_location("maxDistance"),
+        };
+
+        unsafe
+            {
+                gl::ProgramUniform1i(march_program.shader_program, march_program.uniform_location("gPosition"), POSITION_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(march_program.shader_program, march_program.uniform_location("gNormal"), NORMAL_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(march_program.shader_program, march_program.uniform_location("gMaterial"), MATERIAL_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(march_program.shader_program, march_program.uniform_location("sceneTexture"), SOURCE_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(composite_program.shader_program, composite_program.uniform_location("reflectionTexture"), SOURCE_TEXTURE_UNIT as i32);
+            }
+
+        Ok(ScreenSpaceReflectionPass
+        {
+            settings,
+            march_program,
+            composite_program,
+            vao: VAO::new(),
+            march_uniforms,
+            dimensions: (0, 0),
+            scene_framebuffer: 0,
+            scene_texture: 0,
+            reflection_framebuffer: 0,
+            reflection_texture: 0,
+        })
+    }
+
+    /// Ray marches the reflections of the frame lit by the second render pass and blends them over it. The
+    /// G-Buffer layouts must be bound to the texture units of their layout index. The OpenGL state is the
+    /// same after this call as before it
+    ///
+    /// `view` - the view of the scene the reflections are found for
+    /// `material_roughness` - true if the G-Buffer holds the roughness of each fragment in the second
+    ///                        component of its fifth layout
+    /// `output_framebuffer` - the frame buffer the second render pass drew into, which is drawn over
+    pub fn apply(&mut self, view: &ReflectionView, material_roughness: bool, output_framebuffer: OutputFramebuffer)
+    {
+        let dimensions = output_framebuffer.dimensions;
+
+        if dimensions.0 <= 0 || dimensions.1 <= 0
+        {
+            return;
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        self.resize(dimensions);
+
+        unsafe
+            {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+
+                // Rays cannot read the frame while the reflections are drawn over it, so they read a copy
+                gl::BlitNamedFramebuffer(output_framebuffer.framebuffer, self.scene_framebuffer, 0, 0, dimensions.0, dimensions.1,
+                                         0, 0, dimensions.0, dimensions.1, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.reflection_framebuffer);
+                gl::Viewport(0, 0, dimensions.0, dimensions.1);
+            }
+
+        self.march_program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                let uniforms = &self.march_uniforms;
+                gl::UniformMatrix4fv(uniforms.view_matrix, 1, gl::FALSE, view.view_matrix.as_ptr());
+                gl::UniformMatrix4fv(uniforms.projection_matrix, 1, gl::FALSE, view.projection_matrix.as_ptr());
+                gl::Uniform3fv(uniforms.camera_position, 1, view.camera_position.as_ptr());
+                gl::Uniform1i(uniforms.use_material_roughness, material_roughness as i32);
+                gl::Uniform1i(uniforms.steps, self.settings.steps.max(1) as i32);
+                gl::Uniform1f(uniforms.thickness, self.settings.thickness.max(0.0));
+                gl::Uniform1f(uniforms.edge_fade, self.settings.fade.clamp(0.0, 0.5));
+                gl::Uniform1f(uniforms.max_distance, self.settings.max_distance.max(0.0));
+                gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, self.scene_texture);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+                // The alpha of the reflections is how much of them is seen, leaving the alpha of the frame unchanged
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Enable(gl::BLEND);
+                gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+            }
+
+        self.composite_program.use_shader_program();
+
+        unsafe
+            {
+                gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, self.reflection_texture);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                gl::BindTextureUnit(SOURCE_TEXTURE_UNIT, 0);
+            }
+    }
+
+    /// Recreates the frame buffers of the pass if the dimensions of the frame changed
+    ///
+    /// `dimensions` - the dimensions of the frame
+    fn resize(&mut self, dimensions: (i32, i32))
+    {
+        if self.scene_framebuffer != 0 && self.dimensions == dimensions
+        {
+            return;
+        }
+
+        self.delete_framebuffers();
+
+        let (scene_framebuffer, scene_texture) = create_colour_framebuffer(dimensions);
+        let (reflection_framebuffer, reflection_texture) = create_colour_framebuffer(dimensions);
+
+        self.scene_framebuffer = scene_framebuffer;
+        self.scene_texture = scene_texture;
+        self.reflection_framebuffer = reflection_framebuffer;
+        self.reflection_texture = reflection_texture;
+        self.dimensions = dimensions;
+    }
+
+    /// Deletes the frame buffers and textures of the pass
+    fn delete_framebuffers(&mut self)
+    {
+        if self.scene_framebuffer == 0
+        {
+            return;
+        }
+
+        let framebuffers = [self.scene_framebuffer, self.reflection_framebuffer];
+        let textures = [self.scene_texture, self.reflection_texture];
+
+        unsafe
+            {
+                gl::DeleteFramebuffers(framebuffers.len() as i32, framebuffers.as_ptr());
+                gl::DeleteTextures(textures.len() as i32, textures.as_ptr());
+            }
+
+        self.scene_framebuffer = 0;
+    }
+}
+
+/// Creates a frame buffer drawing into a half float colour texture that is sampled linearly and clamped
+/// to its edges, returning the frame buffer and the texture
+///
+/// `dimensions` - the dimensions of the texture
+fn create_colour_framebuffer(dimensions: (i32, i32)) -> (u32, u32)
+{
+    let mut framebuffer = 0;
+    let mut texture = 0;
+
+    unsafe
+        {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+            gl::TextureStorage2D(texture, 1, gl::RGBA16F, dimensions.0, dimensions.1);
+            gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            gl::CreateFramebuffers(1, &mut framebuffer);
+            gl::NamedFramebufferTexture(framebuffer, gl::COLOR_ATTACHMENT0, texture, 0);
+            gl::NamedFramebufferDrawBuffer(framebuffer, gl::COLOR_ATTACHMENT0);
+        }
+
+    (framebuffer, texture)
+}
+
+impl Drop for ScreenSpaceReflectionPass
+{
+    fn drop(&mut self)
+    {
+        self.delete_framebuffers();
+
+        unsafe
+            {
+                gl::DeleteProgram(self.march_program.shader_program);
+                gl::DeleteProgram(self.composite_program.shader_program);
+            }
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Instant;
+use crate::exports::launch_self_test::SelfTestReport;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+
+/// Dimensions of the offscreen framebuffer the self-test scene is rendered into
+const SELF_TEST_DIMENSIONS: i32 = 64;
+
+/// Colour of the triangle in front, as written by the self-test fragment shader
+const FRONT_COLOUR: [u8; 3] = [255, 128, 64];
+
+/// Depth the triangle in front is drawn at, once mapped into the depth range
+const FRONT_DEPTH: f32 = 0.5;
+
+/// Largest difference allowed between a read back colour channel and the expected value
+const COLOUR_TOLERANCE: u8 = 2;
+
+/// Largest difference allowed between a read back depth and the expected value
+const DEPTH_TOLERANCE: f32 = 0.01;
+
+/// Most errors read from OpenGL at once. A lost context can keep reporting an error, so reading is bounded
+const MAX_REPORTED_ERRORS: usize = 16;
+
+/// Renders the self-test scene into an offscreen framebuffer for one frame, and checks that the result
+/// is what any working driver would produce. An OpenGL context must be current, and the OpenGL state is
+/// the same after this call as before it
+pub fn run_self_test() -> SelfTestReport
+{
+    let start = Instant::now();
+    let mut failures = Vec::new();
+
+    let _state_guard = GLStateGuard::new();
+
+    // Errors raised before the self-test are not caused by it
+    drain_gl_errors();
+
+    let shader_program = match create_shader_program()
+    {
+        Ok(i) => i,
+        Err(err) =>
+            {
+                failures.push(format!("Failed to create the self-test shader program: {}", err));
+                return SelfTestReport{ failures, time_taken: start.elapsed().as_secs_f32() * 1000.0 };
+            }
+    };
+
+    let mut previous_clear_colour = [0.0_f32; 4];
+    let mut previous_clear_depth = 1.0_f64;
+    let mut framebuffer = 0;
+    let mut colour_texture = 0;
+    let mut depth_texture = 0;
+    let mut vao = 0;
+
+    let pixel_count = (SELF_TEST_DIMENSIONS * SELF_TEST_DIMENSIONS) as usize;
+    let mut colours = vec![0_u8; pixel_count * 4];
+    let mut depths = vec![0.0_f32; pixel_count];
+
+    unsafe
+        {
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, previous_clear_colour.as_mut_ptr());
+            gl::GetDoublev(gl::DEPTH_CLEAR_VALUE, &mut previous_clear_depth);
+
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut colour_texture);
+            gl::TextureStorage2D(colour_texture, 1, gl::RGBA8, SELF_TEST_DIMENSIONS, SELF_TEST_DIMENSIONS);
+
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut depth_texture);
+            gl::TextureStorage2D(depth_texture, 1, gl::DEPTH_COMPONENT24, SELF_TEST_DIMENSIONS, SELF_TEST_DIMENSIONS);
+
+            gl::CreateFramebuffers(1, &mut framebuffer);
+            gl::NamedFramebufferTexture(framebuffer, gl::COLOR_ATTACHMENT0, colour_texture, 0);
+            gl::NamedFramebufferTexture(framebuffer, gl::DEPTH_ATTACHMENT, depth_texture, 0);
+
+            let framebuffer_status = gl::CheckNamedFramebufferStatus(framebuffer, gl::FRAMEBUFFER);
+            if framebuffer_status != gl::FRAMEBUFFER_COMPLETE
+            {
+                failures.push(format!("The offscreen framebuffer is incomplete, with status {:#x}", framebuffer_status));
+            }
+            else
+            {
+                gl::GenVertexArrays(1, &mut vao);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+                gl::Viewport(0, 0, SELF_TEST_DIMENSIONS, SELF_TEST_DIMENSIONS);
+
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::LESS);
+                gl::DepthMask(gl::TRUE);
+
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                gl::ClearDepth(1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+                gl::UseProgram(shader_program.shader_program);
+                gl::BindVertexArray(vao);
+                gl::DrawArraysInstanced(gl::TRIANGLES, 0, 3, 2);
+
+                gl::GetTextureImage(colour_texture, 0, gl::RGBA, gl::UNSIGNED_BYTE, colours.len() as i32, colours.as_mut_ptr() as *mut _);
+                gl::GetTextureImage(depth_texture, 0, gl::DEPTH_COMPONENT, gl::FLOAT, (depths.len() * std::mem::size_of::<f32>()) as i32, depths.as_mut_ptr() as *mut _);
+
+                check_rendered_scene(&colours, &depths, &mut failures);
+            }
+
+            for error in (0..MAX_REPORTED_ERRORS).map(|_| gl::GetError()).take_while(|x| *x != gl::NO_ERROR)
+            {
+                failures.push(format!("OpenGL raised the error {:#x}", error));
+            }
+
+            gl::ClearColor(previous_clear_colour[0], previous_clear_colour[1], previous_clear_colour[2], previous_clear_colour[3]);
+            gl::ClearDepth(previous_clear_depth);
+
+            if vao != 0
+            {
+                gl::DeleteVertexArrays(1, &vao);
+            }
+
+            gl::DeleteFramebuffers(1, &framebuffer);
+            gl::DeleteTextures(1, &colour_texture);
+            gl::DeleteTextures(1, &depth_texture);
+            gl::DeleteProgram(shader_program.shader_program);
+        }
+
+    SelfTestReport{ failures, time_taken: start.elapsed().as_secs_f32() * 1000.0 }
+}
+
+/// Creates the shader program that draws the self-test scene
+fn create_shader_program() -> Result<ShaderProgram, String>
+{
+    let version = "#version 430 core\n";
+    let shaders = vec!
+    [
+        ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/self_test_vertex.glsl"), Some(version), None)?,
+        ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/self_test_frag.glsl"), Some(version), None)?,
+    ];
+
+    ShaderProgram::new(&shaders)
+}
+
+/// Checks the invariants of the rendered self-test scene. The centre of the framebuffer is covered by both
+/// triangles, and so must hold the colour and depth of the triangle in front. The corners are covered by
+/// neither, and so must still hold the cleared values
+///
+/// `colours` - the RGBA colour of every pixel in the framebuffer
+/// `depths` - the depth of every pixel in the framebuffer
+/// `failures` - the list to add the invariants that do not hold to
+fn check_rendered_scene(colours: &[u8], depths: &[f32], failures: &mut Vec<String>)
+{
+    let centre = ((SELF_TEST_DIMENSIONS / 2) * SELF_TEST_DIMENSIONS + SELF_TEST_DIMENSIONS / 2) as usize;
+    let corner = 0;
+
+    let centre_colour = &colours[centre * 4..centre * 4 + 3];
+    let corner_colour = &colours[corner * 4..corner * 4 + 3];
+
+    if centre_colour.iter().all(|x| *x == 0)
+    {
+        failures.push("Nothing was drawn; the rendered scene is black".to_string());
+    }
+    else if centre_colour.iter().zip(FRONT_COLOUR.iter()).any(|(x, y)| x.abs_diff(*y) > COLOUR_TOLERANCE)
+    {
+        failures.push(format!("The centre of the scene is {:?} rather than {:?}; the depth test did not keep the triangle in front", centre_colour, FRONT_COLOUR));
+    }
+
+    if depths[centre] >= 1.0
+    {
+        failures.push("No depth was written; the centre of the scene holds the cleared depth".to_string());
+    }
+    else if (depths[centre] - FRONT_DEPTH).abs() > DEPTH_TOLERANCE
+    {
+        failures.push(format!("The depth at the centre of the scene is {} rather than {}", depths[centre], FRONT_DEPTH));
+    }
+
+    if corner_colour.iter().any(|x| *x != 0) || depths[corner] != 1.0
+    {
+        failures.push(format!("The corner of the scene was drawn over, holding the colour {:?} and depth {}; clearing the framebuffer failed", corner_colour, depths[corner]));
+    }
+}
+
+/// Removes the errors OpenGL has recorded so far
+fn drain_gl_errors()
+{
+    unsafe
+        {
+            for _ in 0..MAX_REPORTED_ERRORS
+            {
+                if gl::GetError() == gl::NO_ERROR
+                {
+                    break;
+                }
+            }
+        }
+}
@@ -4,6 +4,7 @@ use std::fs::File;
 use std::io::{BufReader, Read, Write};
 use std::path::Path;
 use gl::types::GLenum;
+use crate::exports::gl_debug_output::with_gl_debug_group;
 
 /// Representation of a shader program used in a render system
 pub struct ShaderProgram
@@ -26,20 +27,23 @@ impl ShaderProgram
     /// `shaders` - the information required to create shaders for the shader program
     pub fn new(shaders: &Vec<ShaderInitInformation>) -> Result<ShaderProgram, String>
     {
-        let shaders =
+        with_gl_debug_group("compiling and linking a shader program", ||
             {
-                let mut created_shaders = Vec::new();
-                for x in shaders
-                {
-                    created_shaders.push(ShaderProgram::create_shader(x.shader_type, x.source.clone())?)
-                }
+                let shaders =
+                    {
+                        let mut created_shaders = Vec::new();
+                        for x in shaders
+                        {
+                            created_shaders.push(ShaderProgram::create_shader(x.shader_type, x.source.clone())?)
+                        }
 
-                created_shaders
-            };
+                        created_shaders
+                    };
 
-        let shader_program = ShaderProgram::create_from_shaders(shaders)?;
+                let shader_program = ShaderProgram::create_from_shaders(shaders)?;
 
-        Ok( ShaderProgram{ shader_program } )
+                Ok( ShaderProgram{ shader_program } )
+            })
     }
 
     /// Uses the shader program; binds the shader program
@@ -48,6 +52,15 @@ impl ShaderProgram
         unsafe{ gl::UseProgram(self.shader_program) }
     }
 
+    /// Finds the location of a uniform in the shader program
+    ///
+    /// `name` - the name of the uniform
+    pub fn uniform_location(&self, name: &str) -> i32
+    {
+        let name = CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(self.shader_program, name.as_ptr()) }
+    }
+
     /// Creates a shader program with the required information
     ///
     /// `shader_type` - the type of shader being created
@@ -218,7 +231,7 @@ impl ShaderInitInformation
 
         let total_shader_source = if let Some(append) = append_contents
         {
-            append.into() + &file_contents
+            append.into() + file_contents.as_str()
         }
         else
         {
@@ -0,0 +1,51 @@
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tracing::warn;
+use crate::render_components::texture_array::TextureArray;
+use crate::render_system::system_information::TextureInformation;
+
+// Texture arrays that render systems share, by the name they are shared under. The first render system to
+// use a name creates the texture array; later render systems bind the same texture array to their own
+// binding point. The arrays belong to the OpenGL context they were created in, so they are forgotten
+// whenever the render systems are recreated
+
+lazy_static!
+{
+    static ref SHARED_TEXTURE_ARRAYS: Mutex<HashMap<String, TextureArray>> = Mutex::new(HashMap::new());
+}
+
+/// Get the texture array shared under the given name, creating it if no render system has used the name
+/// yet. If the existing texture array cannot hold the textures described by the given information, a
+/// texture array that is not shared is created instead
+///
+/// `shared_name` - the name the texture array is shared under
+/// `texture_array_info` - the information describing the texture array the render system requires
+/// `binding_point` - the sampler binding point of the texture array in the render system
+pub fn share_texture_array(shared_name: &str, texture_array_info: &TextureInformation, binding_point: u32) -> TextureArray
+{
+    let mut shared_texture_arrays = SHARED_TEXTURE_ARRAYS.lock();
+
+    if let Some(texture_array) = shared_texture_arrays.get(shared_name)
+    {
+        if texture_array.is_compatible(texture_array_info)
+        {
+            return texture_array.share(binding_point);
+        }
+
+        warn!("The texture array \"{}\" cannot be shared under \"{}\"; its format, dimensions or capacity differ from the shared texture array",
+                  texture_array_info.sampler_name, shared_name);
+
+        return TextureArray::new(texture_array_info.clone(), 1, binding_point);
+    }
+
+    let texture_array = TextureArray::new(texture_array_info.clone(), 1, binding_point);
+    shared_texture_arrays.insert(shared_name.to_string(), texture_array.share(binding_point));
+    texture_array
+}
+
+/// Forgets every shared texture array. Called before the render systems are created in a new OpenGL context
+pub(crate) fn clear_shared_texture_arrays()
+{
+    SHARED_TEXTURE_ARRAYS.lock().clear();
+}
@@ -0,0 +1,119 @@
+use nalgebra_glm::TMat4;
+use crate::exports::load_models::Skybox;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::cubemap::CubeMap;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the cubemap of the sky is bound to while the sky is drawn
+const SKYBOX_TEXTURE_UNIT: u32 = 0;
+
+/// Draws a cubemap behind the frame rendered into the window. The sky is drawn on the far plane with a
+/// depth test of equal, so it only covers the fragments that nothing was drawn to
+pub struct SkyboxPass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    cubemap: CubeMap,
+    inverse_view_projection_location: i32,
+}
+
+impl SkyboxPass
+{
+    /// Loads the images of the sky into a cubemap and creates the resources required to draw it. This is a
+    /// blocking operation that requires an OpenGL context to be current
+    ///
+    /// `skybox` - the images to create the sky from
+    pub fn new(skybox: &Skybox) -> Result<SkyboxPass, String>
+    {
+        let version = "#version 430 core\n";
+        let shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/skybox_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/skybox_frag.glsl"), Some(version), None)?,
+        ];
+
+        let program = ShaderProgram::new(&shaders)?;
+
+        let _state_guard = GLStateGuard::new();
+
+        let cubemap = match skybox
+        {
+            Skybox::CubeFaces(faces) =>
+                {
+                    if faces.len() != 6
+                    {
+                        return Err(format!("A skybox requires 6 faces, but {} were given", faces.len()));
+                    }
+
+                    let mut cubemap = CubeMap::new(SKYBOX_TEXTURE_UNIT);
+
+                    if let Err(err) = cubemap.upload_texture_sequentially(faces.clone())
+                    {
+                        return Err(format!("Failed to load the skybox faces: {:?}", err));
+                    }
+
+                    cubemap
+                },
+            Skybox::Equirectangular { image, resolution } => CubeMap::from_equirectangular(image, *resolution, SKYBOX_TEXTURE_UNIT)?
+        };
+
+        unsafe { gl::ProgramUniform1i(program.shader_program, program.uniform_location("skybox"), SKYBOX_TEXTURE_UNIT as i32); }
+
+        let inverse_view_projection_location = program.uniform_location("inverseViewProjection");
+        Ok(SkyboxPass{ program, vao: VAO::new(), cubemap, inverse_view_projection_location })
+    }
+
+    /// Draws the sky into the fragments of the output that hold the cleared depth. The output must hold the
+    /// depth of the frame. The OpenGL state is the same after this call as before it
+    ///
+    /// `view_matrix` - the view matrix of the camera the frame was rendered from
+    /// `projection_matrix` - the projection matrix of the camera the frame was rendered from
+    /// `output_framebuffer` - the frame buffer the frame was rendered into
+    pub fn apply(&mut self, view_matrix: &TMat4<f32>, projection_matrix: &TMat4<f32>, output_framebuffer: OutputFramebuffer)
+    {
+        let _state_guard = GLStateGuard::new();
+
+        // Only the rotation of the camera is kept, so the sky appears infinitely far away
+        let rotation = nalgebra_glm::mat3_to_mat4(&nalgebra_glm::mat4_to_mat3(view_matrix));
+        let inverse_view_projection = nalgebra_glm::inverse(&(projection_matrix * rotation));
+
+        unsafe
+            {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, output_framebuffer.dimensions.0, output_framebuffer.dimensions.1);
+
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::EQUAL);
+                gl::DepthMask(gl::FALSE);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+        self.cubemap.bind_to_binding_point();
+
+        unsafe
+            {
+                gl::UniformMatrix4fv(self.inverse_view_projection_location, 1, gl::FALSE, inverse_view_projection.as_ptr());
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                gl::BindTextureUnit(SKYBOX_TEXTURE_UNIT, 0);
+
+                // The depth function is not saved by the guard, so the one the engine renders with is restored
+                gl::DepthFunc(gl::LESS);
+            }
+    }
+}
+
+impl Drop for SkyboxPass
+{
+    fn drop(&mut self)
+    {
+        unsafe { gl::DeleteProgram(self.program.shader_program); }
+    }
+}
@@ -0,0 +1,330 @@
+use std::ffi::{c_void, CString};
+use std::mem::size_of;
+use std::path::PathBuf;
+use nalgebra_glm::{TMat4, TVec3, vec3};
+use stb_image::stb_image::bindgen::{stbi_image_free, stbi_load, stbi_set_flip_vertically_on_load};
+use tracing::warn;
+use crate::exports::sprites::{BillboardMode, new_sprite_textures, SpriteRenderer};
+use crate::helper_things::environment::{get_asset_folder, path_to_bytes};
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the G-Buffer layout holding world positions is bound to when drawing sprites
+pub const SPRITE_POSITION_TEXTURE_UNIT: u32 = 0;
+
+/// Texture unit the texture of a run of sprites is bound to while it is drawn
+const SPRITE_TEXTURE_UNIT: u32 = 1;
+
+/// Shader storage binding point of the sprites, after those used by the GPU level of view pass
+const SPRITE_BINDING_POINT: u32 = 18;
+
+/// A sprite to draw, along with where it is in the world
+pub struct SpriteInstance
+{
+    pub sprite: SpriteRenderer,
+    /// The position of the entity relative to the render origin
+    pub position: TVec3<f32>,
+    /// The transformation matrix of the entity, which orients fixed sprites
+    pub transformation: Option<TMat4<f32>>,
+}
+
+/// The view of the scene the sprites are drawn for
+pub struct SpriteView
+{
+    pub camera_position: TVec3<f32>,
+    pub view_matrix: TMat4<f32>,
+    pub projection_matrix: TMat4<f32>,
+    /// If false, no world positions are bound and sprites are drawn over all geometry
+    pub positions_available: bool,
+}
+
+/// A sprite, laid out as the Sprite struct of the sprite vertex shader
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuSprite
+{
+    pivot_occluded: [f32; 4],
+    right_pivot: [f32; 4],
+    up_pivot: [f32; 4],
+    region: [f32; 4],
+    colour: [f32; 4],
+}
+
+/// Locations of the uniforms of the sprite shaders
+struct SpriteUniforms
+{
+    view_projection: i32,
+    first_sprite: i32,
+    camera_position: i32,
+    window_dimensions: i32,
+    positions_available: i32,
+}
+
+/// Draws the sprites of entities into the window, with one instanced draw call for each run of sprites
+/// sharing a texture
+pub struct SpritePass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    uniforms: SpriteUniforms,
+    sprite_buffer: u32,
+    /// The texture of each registered sprite texture, or 0 if it failed to load
+    textures: Vec<u32>,
+}
+
+impl SpritePass
+{
+    /// Creates the resources required to draw sprites. An OpenGL context must be current
+    pub fn new() -> Result<SpritePass, String>
+    {
+        let version = "#version 430 core\n";
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/sprite_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/sprite_frag.glsl"), Some(version), None)?,
+        ])?;
+
+        let uniforms = SpriteUniforms
+        {
+            view_projection: program.uniform_location("viewProjection"),
+            first_sprite: program.uniform_location("firstSprite"),
+            camera_position: program.uniform_location("cameraPosition"),
+            window_dimensions: program.uniform_location("windowDimensions"),
+            positions_available: program.uniform_location("positionsAvailable"),
+        };
+
+        let mut sprite_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("gPosition"), SPRITE_POSITION_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("spriteTexture"), SPRITE_TEXTURE_UNIT as i32);
+                gl::CreateBuffers(1, &mut sprite_buffer);
+            }
+
+        Ok(SpritePass{ program, vao: VAO::new(), uniforms, sprite_buffer, textures: Vec::new() })
+    }
+
+    /// Draws the sprites into the window, from the furthest to the nearest. If world positions are available,
+    /// they must be bound to their texture unit. The OpenGL state is the same after this call as before it
+    ///
+    /// `sprites` - every sprite to draw
+    /// `view` - the view of the scene the sprites are drawn for
+    /// `output_framebuffer` - the frame buffer the frame was rendered into, which is drawn over
+    pub fn apply(&mut self, sprites: &[SpriteInstance], view: &SpriteView, output_framebuffer: OutputFramebuffer)
+    {
+        let window_dimensions = output_framebuffer.dimensions;
+
+        if window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        self.load_new_textures();
+
+        let mut sprites = sprites.iter()
+            .filter(|x| self.textures.get(x.sprite.texture.index()).is_some_and(|x| *x != 0))
+            .filter(|x| x.sprite.size.0 > 0.0 && x.sprite.size.1 > 0.0)
+            .map(|x| (nalgebra_glm::distance2(&(x.position + x.sprite.offset), &view.camera_position), x))
+            .collect::<Vec<(f32, &SpriteInstance)>>();
+
+        if sprites.is_empty()
+        {
+            return;
+        }
+
+        // Drawn from the furthest to the nearest so that sprites blend over those behind them
+        sprites.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let gpu_sprites = sprites.iter().map(|(_, x)| SpritePass::create_gpu_sprite(x, view)).collect::<Vec<GpuSprite>>();
+
+        let _state_guard = GLStateGuard::new();
+
+        unsafe
+            {
+                gl::NamedBufferData(self.sprite_buffer, (gpu_sprites.len() * size_of::<GpuSprite>()) as isize, gpu_sprites.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, SPRITE_BINDING_POINT, self.sprite_buffer);
+
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+
+        let view_projection = view.projection_matrix * view.view_matrix;
+
+        unsafe
+            {
+                gl::UniformMatrix4fv(self.uniforms.view_projection, 1, gl::FALSE, view_projection.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_position, 1, view.camera_position.as_ptr());
+                gl::Uniform2f(self.uniforms.window_dimensions, window_dimensions.0 as f32, window_dimensions.1 as f32);
+                gl::Uniform1i(self.uniforms.positions_available, view.positions_available as i32);
+            }
+
+        let mut run_start = 0;
+
+        while run_start < sprites.len()
+        {
+            let texture_index = sprites[run_start].1.sprite.texture.index();
+            let run_length = sprites[run_start..].iter().take_while(|(_, x)| x.sprite.texture.index() == texture_index).count();
+
+            unsafe
+                {
+                    gl::BindTextureUnit(SPRITE_TEXTURE_UNIT, self.textures[texture_index]);
+                    gl::Uniform1i(self.uniforms.first_sprite, run_start as i32);
+                    gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, run_length as i32);
+                }
+
+            run_start += run_length;
+        }
+
+        unsafe { gl::BindTextureUnit(SPRITE_TEXTURE_UNIT, 0); }
+    }
+
+    /// Creates the information about the sprite that the sprite shaders read, orienting it by its billboard mode
+    ///
+    /// `instance` - the sprite to draw
+    /// `view` - the view of the scene the sprites are drawn for
+    fn create_gpu_sprite(instance: &SpriteInstance, view: &SpriteView) -> GpuSprite
+    {
+        let sprite = &instance.sprite;
+        let pivot = instance.position + sprite.offset;
+        let camera_right = vec3(view.view_matrix[(0, 0)], view.view_matrix[(0, 1)], view.view_matrix[(0, 2)]);
+        let camera_up = vec3(view.view_matrix[(1, 0)], view.view_matrix[(1, 1)], view.view_matrix[(1, 2)]);
+
+        let (right, up) = match sprite.billboard
+        {
+            BillboardMode::Spherical => (camera_right, camera_up),
+            BillboardMode::Cylindrical =>
+                {
+                    let up = vec3(0.0, 1.0, 0.0);
+                    let to_camera = view.camera_position - pivot;
+                    let right = nalgebra_glm::cross(&up, &to_camera);
+
+                    // Looking straight down or up at the sprite, it is turned to the camera as well as it can be
+                    if nalgebra_glm::length2(&right) > f32::EPSILON
+                    {
+                        (nalgebra_glm::normalize(&right), up)
+                    }
+                    else
+                    {
+                        (camera_right, up)
+                    }
+                },
+            BillboardMode::Fixed =>
+                {
+                    match instance.transformation
+                    {
+                        Some(matrix) =>
+                            {
+                                let right = vec3(matrix[(0, 0)], matrix[(1, 0)], matrix[(2, 0)]);
+                                let up = vec3(matrix[(0, 1)], matrix[(1, 1)], matrix[(2, 1)]);
+                                (nalgebra_glm::normalize(&right), nalgebra_glm::normalize(&up))
+                            },
+                        None => (vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0))
+                    }
+                }
+        };
+
+        let right = right * sprite.size.0;
+        let up = up * sprite.size.1;
+        let occluded = if sprite.occluded { 1.0 } else { 0.0 };
+
+        GpuSprite
+        {
+            pivot_occluded: [pivot.x, pivot.y, pivot.z, occluded],
+            right_pivot: [right.x, right.y, right.z, sprite.pivot.0],
+            up_pivot: [up.x, up.y, up.z, sprite.pivot.1],
+            region: [sprite.region.min.0, sprite.region.min.1, sprite.region.max.0, sprite.region.max.1],
+            colour: [sprite.colour.x, sprite.colour.y, sprite.colour.z, sprite.colour.w],
+        }
+    }
+
+    /// Loads the sprite textures registered since the last frame
+    fn load_new_textures(&mut self)
+    {
+        for location in new_sprite_textures(self.textures.len())
+        {
+            let texture = match load_texture(&location)
+            {
+                Ok(i) => i,
+                Err(err) =>
+                    {
+                        warn!("{}; sprites using it are not drawn", err);
+                        0
+                    }
+            };
+
+            self.textures.push(texture);
+        }
+    }
+}
+
+/// Loads the image into a texture with mipmaps, keeping its top row at the top of the texture
+///
+/// `location` - the location of the image
+fn load_texture(location: &PathBuf) -> Result<u32, String>
+{
+    let mut width = 0;
+    let mut height = 0;
+    let mut nr_channels = 0;
+
+    // The flag is shared by all loads, so it is set explicitly to keep regions relative to the top left
+    let image_data = unsafe
+        {
+            let texture_cstring = CString::new(path_to_bytes(location.clone())).unwrap();
+            stbi_set_flip_vertically_on_load(0);
+            stbi_load(texture_cstring.as_ptr(), &mut width, &mut height, &mut nr_channels, 4)
+        };
+
+    if image_data.is_null()
+    {
+        return Err(format!("Failed to load the sprite texture {:?}", location));
+    }
+
+    let mip_levels = (width.max(height) as f32).log2() as i32 + 1;
+    let mut texture: u32 = 0;
+
+    unsafe
+        {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+            gl::TextureStorage2D(texture, mip_levels, gl::RGBA8, width, height);
+            gl::TextureSubImage2D(texture, 0, 0, 0, width, height, gl::RGBA, gl::UNSIGNED_BYTE, image_data as *const c_void);
+            gl::GenerateTextureMipmap(texture);
+            gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            stbi_image_free(image_data as *mut c_void);
+        }
+
+    Ok(texture)
+}
+
+impl Drop for SpritePass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                for texture in self.textures.iter().filter(|x| **x != 0)
+                {
+                    gl::DeleteTextures(1, texture);
+                }
+
+                gl::DeleteBuffers(1, &self.sprite_buffer);
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
@@ -0,0 +1,415 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use nalgebra_glm::{TMat4, TVec3, vec3};
+use tracing::warn;
+use crate::exports::accessibility::ui_scale;
+use crate::exports::text_labels::{new_fonts, TextAlignment, TextLabel, TextPlacement};
+use crate::exports::text_shaping::shape_text;
+use crate::exports::ui_layout::{UiElement, UiLength};
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::font_atlas::FontAtlas;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the G-Buffer layout holding world positions is bound to when drawing text
+pub const TEXT_POSITION_TEXTURE_UNIT: u32 = 1;
+
+/// Texture unit the atlas of a run of glyphs is bound to while it is drawn
+const TEXT_ATLAS_TEXTURE_UNIT: u32 = 0;
+
+/// Shader storage binding point of the glyphs drawn, after the one used by sprites
+const TEXT_GLYPH_BINDING_POINT: u32 = 19;
+
+/// How a glyph is placed, matching the modes of the text vertex shader
+const MODE_SCREEN: f32 = 0.0;
+const MODE_WORLD: f32 = 1.0;
+const MODE_WORLD_OCCLUDED: f32 = 2.0;
+
+/// A label to draw, along with where its entity is in the world
+pub struct TextInstance
+{
+    pub label: TextLabel,
+    /// The position of the entity relative to the render origin, if it has one
+    pub position: Option<TVec3<f32>>,
+}
+
+/// The view of the scene the text is drawn for
+pub struct TextView
+{
+    pub camera_position: TVec3<f32>,
+    pub view_matrix: TMat4<f32>,
+    pub projection_matrix: TMat4<f32>,
+    /// If false, no world positions are bound and text in the world is drawn over all geometry
+    pub positions_available: bool,
+}
+
+/// A glyph, laid out as the TextGlyph struct of the text vertex shader
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct GpuGlyph
+{
+    origin_mode: [f32; 4],
+    offset_size: [f32; 4],
+    atlas_rect: [f32; 4],
+    colour: [f32; 4],
+}
+
+/// A glyph of laid out text, in pixels of the font from the bottom left of the text
+struct PlacedGlyph
+{
+    position: (f32, f32),
+    size: (f32, f32),
+    atlas_rect: [f32; 4],
+}
+
+/// Locations of the uniforms of the text shaders
+struct TextUniforms
+{
+    view_projection: i32,
+    camera_right: i32,
+    camera_up: i32,
+    camera_position: i32,
+    window_dimensions: i32,
+    positions_available: i32,
+    first_glyph: i32,
+}
+
+/// Draws the text labels of entities over the window, with one instanced draw call for each run of glyphs
+/// sharing a font
+pub struct TextPass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    uniforms: TextUniforms,
+    glyph_buffer: u32,
+    /// The atlas of each registered font, or None if it failed to load
+    fonts: Vec<Option<FontAtlas>>,
+}
+
+impl TextPass
+{
+    /// Creates the resources required to draw text. An OpenGL context must be current
+    pub fn new() -> Result<TextPass, String>
+    {
+        let version = "#version 430 core\n";
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/text_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/text_frag.glsl"), Some(version), None)?,
+        ])?;
+
+        let uniforms = TextUniforms
+        {
+            view_projection: program.uniform_location("viewProjection"),
+            camera_right: program.uniform_location("cameraRight"),
+            camera_up: program.uniform_location("cameraUp"),
+            camera_position: program.uniform_location("cameraPosition"),
+            window_dimensions: program.uniform_location("windowDimensions"),
+            positions_available: program.uniform_location("positionsAvailable"),
+            first_glyph: program.uniform_location("firstGlyph"),
+        };
+
+        let mut glyph_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("fontAtlas"), TEXT_ATLAS_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("gPosition"), TEXT_POSITION_TEXTURE_UNIT as i32);
+                gl::CreateBuffers(1, &mut glyph_buffer);
+            }
+
+        Ok(TextPass{ program, vao: VAO::new(), uniforms, glyph_buffer, fonts: Vec::new() })
+    }
+
+    /// Draws the labels into the window. Labels in the world are drawn first, from the furthest to the nearest,
+    /// followed by labels over the window in the order they are given. If world positions are available, they
+    /// must be bound to their texture unit. The OpenGL state is the same after this call as before it
+    ///
+    /// `labels` - every label to draw
+    /// `view` - the view of the scene the labels are drawn for
+    /// `output_framebuffer` - the frame buffer the labels are drawn into
+    pub fn apply(&mut self, labels: &[TextInstance], view: &TextView, output_framebuffer: OutputFramebuffer)
+    {
+        let window_dimensions = output_framebuffer.dimensions;
+
+        if window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        self.load_new_fonts();
+
+        let mut world_labels = labels.iter()
+            .filter_map(|x| match (x.label.placement, x.position)
+            {
+                (TextPlacement::World { offset, .. }, Some(position)) => Some((nalgebra_glm::distance2(&(position + offset), &view.camera_position), x)),
+                _ => None
+            })
+            .collect::<Vec<(f32, &TextInstance)>>();
+
+        world_labels.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let screen_labels = labels.iter().filter(|x| matches!(x.label.placement, TextPlacement::Screen { .. }));
+        let ordered_labels = world_labels.into_iter().map(|(_, x)| x).chain(screen_labels);
+
+        // Consecutive labels sharing a font are drawn together, so each run is the font and the number of its glyphs
+        let mut glyphs = Vec::new();
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+
+        for instance in ordered_labels
+        {
+            let font_index = instance.label.font.index();
+            let number_glyphs = glyphs.len();
+
+            self.create_gpu_glyphs(instance, window_dimensions, &mut glyphs);
+
+            let added_glyphs = glyphs.len() - number_glyphs;
+
+            match runs.last_mut()
+            {
+                Some(run) if run.0 == font_index => run.1 += added_glyphs,
+                _ if added_glyphs != 0 => runs.push((font_index, added_glyphs)),
+                _ => {}
+            }
+        }
+
+        if glyphs.is_empty()
+        {
+            return;
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        let view_projection = view.projection_matrix * view.view_matrix;
+        let camera_right = vec3(view.view_matrix[(0, 0)], view.view_matrix[(0, 1)], view.view_matrix[(0, 2)]);
+        let camera_up = vec3(view.view_matrix[(1, 0)], view.view_matrix[(1, 1)], view.view_matrix[(1, 2)]);
+
+        unsafe
+            {
+                gl::NamedBufferData(self.glyph_buffer, (glyphs.len() * size_of::<GpuGlyph>()) as isize, glyphs.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, TEXT_GLYPH_BINDING_POINT, self.glyph_buffer);
+
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                gl::UniformMatrix4fv(self.uniforms.view_projection, 1, gl::FALSE, view_projection.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_right, 1, camera_right.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_up, 1, camera_up.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_position, 1, view.camera_position.as_ptr());
+                gl::Uniform2f(self.uniforms.window_dimensions, window_dimensions.0 as f32, window_dimensions.1 as f32);
+                gl::Uniform1i(self.uniforms.positions_available, view.positions_available as i32);
+            }
+
+        let mut first_glyph = 0;
+
+        for (font_index, number_glyphs) in runs
+        {
+            if let Some(Some(atlas)) = self.fonts.get(font_index)
+            {
+                atlas.bind(TEXT_ATLAS_TEXTURE_UNIT);
+
+                unsafe
+                    {
+                        gl::Uniform1i(self.uniforms.first_glyph, first_glyph as i32);
+                        gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, number_glyphs as i32);
+                    }
+            }
+
+            first_glyph += number_glyphs;
+        }
+
+        unsafe { gl::BindTextureUnit(TEXT_ATLAS_TEXTURE_UNIT, 0); }
+    }
+
+    /// Lays out the label and adds its glyphs to those that are drawn
+    ///
+    /// `instance` - the label to lay out
+    /// `window_dimensions` - the dimensions of the window in pixels
+    /// `glyphs` - the glyphs that are drawn
+    fn create_gpu_glyphs(&mut self, instance: &TextInstance, window_dimensions: (i32, i32), glyphs: &mut Vec<GpuGlyph>)
+    {
+        let label = &instance.label;
+
+        let atlas = match self.fonts.get_mut(label.font.index())
+        {
+            Some(Some(i)) => i,
+            _ => return
+        };
+
+        if label.text.is_empty() || label.size <= 0.0 || label.colour.w <= 0.0
+        {
+            return;
+        }
+
+        let (placed_glyphs, text_size) = layout_text(atlas, &label.text, label.alignment);
+        let colour = [label.colour.x, label.colour.y, label.colour.z, label.colour.w];
+
+        match label.placement
+        {
+            TextPlacement::Screen { anchor, offset } =>
+                {
+                    let size_scale = label.size / atlas.line_height();
+
+                    let rect = UiElement::new(anchor, UiLength::Pixels(text_size.0 * size_scale), UiLength::Pixels(text_size.1 * size_scale))
+                        .with_offset(offset.0, offset.1)
+                        .layout(window_dimensions);
+
+                    let scale = size_scale * ui_scale();
+
+                    for x in placed_glyphs
+                    {
+                        glyphs.push(GpuGlyph
+                        {
+                            origin_mode: [rect.x as f32, rect.y as f32, 0.0, MODE_SCREEN],
+                            offset_size: [x.position.0 * scale, x.position.1 * scale, x.size.0 * scale, x.size.1 * scale],
+                            atlas_rect: x.atlas_rect,
+                            colour
+                        });
+                    }
+                },
+            TextPlacement::World { offset, occluded } =>
+                {
+                    let origin = match instance.position
+                    {
+                        Some(i) => i + offset,
+                        None => return
+                    };
+
+                    let scale = label.size / atlas.line_height();
+                    let mode = if occluded { MODE_WORLD_OCCLUDED } else { MODE_WORLD };
+
+                    // The label faces the camera with the bottom centre of the text at its origin
+                    for x in placed_glyphs
+                    {
+                        glyphs.push(GpuGlyph
+                        {
+                            origin_mode: [origin.x, origin.y, origin.z, mode],
+                            offset_size: [(x.position.0 - text_size.0 * 0.5) * scale, x.position.1 * scale, x.size.0 * scale, x.size.1 * scale],
+                            atlas_rect: x.atlas_rect,
+                            colour
+                        });
+                    }
+                }
+        }
+    }
+
+    /// Loads the fonts registered since the last frame
+    fn load_new_fonts(&mut self)
+    {
+        for (location, pixel_size) in new_fonts(self.fonts.len())
+        {
+            let atlas = match FontAtlas::new(&location, pixel_size)
+            {
+                Ok(i) => Some(i),
+                Err(err) =>
+                    {
+                        warn!("{}; labels using it are not drawn", err);
+                        None
+                    }
+            };
+
+            self.fonts.push(atlas);
+        }
+    }
+}
+
+/// Lays out the glyphs of the text in pixels of the font, returning the glyphs and the width and height of
+/// the text. Lines are aligned within the width of the longest line
+///
+/// `atlas` - the atlas of the font the text is drawn with
+/// `text` - the text to lay out
+/// `alignment` - how the lines of the text are aligned with each other
+fn layout_text(atlas: &mut FontAtlas, text: &str, alignment: TextAlignment) -> (Vec<PlacedGlyph>, (f32, f32))
+{
+    let shaped_glyphs = shape_text(text, atlas);
+    let number_lines = shaped_glyphs.iter().map(|x| x.line + 1).max().unwrap_or(0);
+    let line_height = atlas.line_height();
+
+    let mut placed_glyphs = Vec::with_capacity(shaped_glyphs.len());
+    let mut line_widths = vec![0.0_f32; number_lines as usize];
+    let mut glyph_lines = Vec::with_capacity(shaped_glyphs.len());
+    let mut pen = 0.0;
+    let mut previous: Option<(u32, char)> = None;
+
+    for shaped_glyph in shaped_glyphs
+    {
+        let glyph = match atlas.glyph(shaped_glyph.character)
+        {
+            Some(i) => i,
+            None => continue
+        };
+
+        match previous
+        {
+            Some((line, character)) if line == shaped_glyph.line => pen += atlas.kerning(character, shaped_glyph.character),
+            _ => pen = 0.0
+        }
+
+        // Combining marks are positioned by the font relative to the end of the glyph they are placed on
+        let baseline = (number_lines - 1 - shaped_glyph.line) as f32 * line_height + atlas.descent();
+
+        if glyph.size.0 > 0.0 && glyph.size.1 > 0.0
+        {
+            placed_glyphs.push(PlacedGlyph
+            {
+                position: (pen + glyph.bearing.0, baseline + glyph.bearing.1),
+                size: glyph.size,
+                atlas_rect: [glyph.rect.min.0, glyph.rect.min.1, glyph.rect.max.0, glyph.rect.max.1],
+            });
+
+            glyph_lines.push(shaped_glyph.line);
+        }
+
+        if !shaped_glyph.combining
+        {
+            pen += glyph.advance;
+        }
+
+        let line_width = &mut line_widths[shaped_glyph.line as usize];
+        *line_width = line_width.max(pen);
+        previous = Some((shaped_glyph.line, shaped_glyph.character));
+    }
+
+    let text_width = line_widths.iter().cloned().fold(0.0, f32::max);
+
+    let alignment_factor = match alignment
+    {
+        TextAlignment::Left => 0.0,
+        TextAlignment::Centre => 0.5,
+        TextAlignment::Right => 1.0
+    };
+
+    for (glyph, line) in placed_glyphs.iter_mut().zip(glyph_lines)
+    {
+        glyph.position.0 += (text_width - line_widths[line as usize]) * alignment_factor;
+    }
+
+    (placed_glyphs, (text_width, number_lines as f32 * line_height))
+}
+
+impl Drop for TextPass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteBuffers(1, &self.glyph_buffer);
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
@@ -1,8 +1,15 @@
+use std::collections::hash_map::DefaultHasher;
 use std::ffi::{c_void, CString};
+use std::hash::{Hash, Hasher};
 use std::mem::size_of;
 use std::path::PathBuf;
 use std::ptr::copy_nonoverlapping;
+use std::sync::Arc;
+use hashbrown::HashMap;
+use parking_lot::Mutex;
 use stb_image::stb_image::bindgen::{stbi_image_free, stbi_load, stbi_set_flip_vertically_on_load};
+use tracing::warn;
+use crate::exports::memory_budget::{MemoryCategory, reclaimable_room, record_allocation};
 use crate::helper_things::environment::path_to_bytes;
 use crate::render_system::system_information::{TextureFormat, TextureInformation};
 
@@ -13,11 +20,31 @@ pub struct TextureArray
 {
     buffers: Vec<u32>,
     texture_array_info: TextureInformation,
-    number_textures_held: i32,
+    layers: Arc<Mutex<TextureArrayLayers>>,
     current_buffer_index: usize,
     binding_point: u32,
 }
 
+/// Keeps track of which layers of a texture array hold a texture. When a texture array is shared between
+/// render systems, this is shared as well, so that each texture is held once no matter how many render
+/// systems use it
+struct TextureArrayLayers
+{
+    number_textures_held: i32,
+    free_layers: Vec<i32>,
+    layer_users: HashMap<i32, u32>,
+    contents: HashMap<TextureContents, (i32, f32, f32)>,
+}
+
+/// Identifies what a layer of a texture array holds, so that a texture can be found in the array without
+/// being uploaded again
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextureContents
+{
+    Image(u64),
+    SolidColour([u8; 4]),
+}
+
 /// Possible result of uploading a texture. This enum contains both success and error values;
 /// return result uses Option to differentiate between success and failure.
 #[derive(Debug)]
@@ -47,10 +74,19 @@ impl TextureArray
     /// `texture_array_info` - the information specifying information about the texture array to create
     /// `number_buffers` - the number of round-robin buffers to use for the texture array
     /// `binding_point` - the sampler binding point that this texture array should bind to
-    pub fn new(texture_array_info: TextureInformation, number_buffers: usize, binding_point: u32) -> TextureArray
+    pub fn new(mut texture_array_info: TextureInformation, number_buffers: usize, binding_point: u32) -> TextureArray
     {
         let mut buffers = Vec::with_capacity(number_buffers);
 
+        // Without mipmaps the array takes up three quarters of the memory, at the cost of aliasing in the distance
+        if texture_array_info.number_mipmaps > 1 && reclaimable_room(MemoryCategory::Textures).is_some_and(|x| x < TextureArray::size_bytes(&texture_array_info, number_buffers))
+        {
+            warn!("Creating the texture array \"{}\" without mipmaps to stay within the texture memory budget", texture_array_info.sampler_name);
+            texture_array_info.number_mipmaps = 1;
+        }
+
+        record_allocation(MemoryCategory::Textures, TextureArray::size_bytes(&texture_array_info, number_buffers));
+
         unsafe
             {
                 for _ in 0..number_buffers
@@ -76,7 +112,63 @@ impl TextureArray
                 }
             }
 
-        TextureArray{ buffers, texture_array_info, number_textures_held: 0, current_buffer_index: 0, binding_point }
+        let layers = TextureArrayLayers{ number_textures_held: 0, free_layers: Vec::new(), layer_users: HashMap::new(), contents: HashMap::new() };
+        TextureArray{ buffers, texture_array_info, layers: Arc::new(Mutex::new(layers)), current_buffer_index: 0, binding_point }
+    }
+
+    /// Get the number of bytes of video memory the texture arrays described by the information take up
+    ///
+    /// `texture_array_info` - the information of the texture arrays
+    /// `number_buffers` - the number of round-robin buffers created for the texture array
+    fn size_bytes(texture_array_info: &TextureInformation, number_buffers: usize) -> usize
+    {
+        let layer_bytes = (0..texture_array_info.number_mipmaps.max(1))
+            .map(|level| (texture_array_info.width >> level).max(1) as usize * (texture_array_info.height >> level).max(1) as usize)
+            .sum::<usize>() * texture_array_info.format.bytes_per_texel();
+
+        layer_bytes * texture_array_info.number_textures.max(0) as usize * number_buffers
+    }
+
+    /// Creates a texture array that holds the same textures as this one, but is bound to a different
+    /// sampler binding point. Textures added to or removed from either array affect both
+    ///
+    /// `binding_point` - the sampler binding point that the new texture array should bind to
+    pub fn share(&self, binding_point: u32) -> TextureArray
+    {
+        TextureArray
+        {
+            buffers: self.buffers.clone(),
+            texture_array_info: self.texture_array_info.clone(),
+            layers: self.layers.clone(),
+            current_buffer_index: self.current_buffer_index,
+            binding_point
+        }
+    }
+
+    /// Checks if a texture array with the given information can hold the same textures as this one
+    ///
+    /// `texture_array_info` - the information of the other texture array
+    pub fn is_compatible(&self, texture_array_info: &TextureInformation) -> bool
+    {
+        self.texture_array_info.format == texture_array_info.format &&
+            self.texture_array_info.width == texture_array_info.width &&
+            self.texture_array_info.height == texture_array_info.height &&
+            self.texture_array_info.number_textures == texture_array_info.number_textures &&
+            self.texture_array_info.number_mipmaps == texture_array_info.number_mipmaps
+    }
+
+    /// Finds a texture with the given contents that is already held by the texture array, such as one
+    /// added through another render system sharing the array. The found texture is marked as used by the
+    /// caller, and must be removed once the caller no longer uses it. Returns the layer holding the texture,
+    /// and how much its U and V coordinates need to be scaled by
+    ///
+    /// `contents` - the contents of the texture to find
+    pub fn find_texture(&mut self, contents: TextureContents) -> Option<(i32, f32, f32)>
+    {
+        let mut layers = self.layers.lock();
+        let found_texture = layers.contents.get(&contents).copied()?;
+        *layers.layer_users.entry(found_texture.0).or_insert(0) += 1;
+        Some(found_texture)
     }
 
     /// Adds a texture that is a single colour to a layer of the texture array
@@ -84,27 +176,27 @@ impl TextureArray
     /// `colour` - the colour the texture layer should have
     pub fn add_texture_solid_colour(&mut self, colour: [u8; 4]) -> i32
     {
-        if self.number_textures_held == self.texture_array_info.number_textures
+        if self.is_full()
         {
             // TODO: Should this be a panic or default to not enough storage for texture colour, like
             // TODO: when calling add_texture_sequentially_from_file_stbi?
-            eprintln!("Not enough storage when adding solid colour texture. Max amount: {}", self.texture_array_info.number_textures);
+            warn!("Not enough storage when adding solid colour texture. Max amount: {}", self.texture_array_info.number_textures);
         }
 
         let pixels_required = self.texture_array_info.width * self.texture_array_info.height;
         let pixel_data = vec![colour; pixels_required as usize];
+        let layer = self.take_layer(TextureContents::SolidColour(colour), 1.0, 1.0);
 
         unsafe
             {
                 gl::TextureSubImage3D(self.buffers[self.current_buffer_index],
                                       0,
-                                      0, 0, self.number_textures_held,
+                                      0, 0, layer,
                                       self.texture_array_info.width, self.texture_array_info.height, 1,
                                       gl::RGBA, gl::UNSIGNED_BYTE, pixel_data.as_ptr() as *const c_void);
             }
 
-        self.number_textures_held += 1;
-        self.number_textures_held - 1
+        layer
     }
 
     /// Adds a texture to the array, blocking the calling thread.
@@ -118,10 +210,11 @@ impl TextureArray
     /// texture coordinates U and V coordinates respectfully
     ///
     /// `texture_properties` - the properties of the texture to upload
+    /// `content_hash` - the hash of the contents of the texture, from TextureProperties::content_hash
 
-    pub fn add_texture_sequentially_from_file_stbi(&mut self, texture_properties: &TextureProperties) -> Result<TextureUploadResult, TextureUploadResult>
+    pub fn add_texture_sequentially_from_file_stbi(&mut self, texture_properties: &TextureProperties, content_hash: u64) -> Result<TextureUploadResult, TextureUploadResult>
     {
-        if self.number_textures_held == self.texture_array_info.number_textures
+        if self.is_full()
         {
             return Err(TextureUploadResult::TextureArrayFull);
         }
@@ -152,37 +245,86 @@ impl TextureArray
                 unsafe{ copy_nonoverlapping(texture_properties.image_data.offset(source_offset), pixels.as_mut_ptr().offset(destination_offset), number_bytes_to_copy) }
             }
 
+            let resize_factor_width = texture_properties.width as f32 / self.texture_array_info.width as f32;
+            let resize_factor_height = texture_properties.height as f32 / self.texture_array_info.height as f32;
+            let layer = self.take_layer(TextureContents::Image(content_hash), resize_factor_width, resize_factor_height);
+
             unsafe
                 {
                     gl::TextureSubImage3D(self.buffers[self.current_buffer_index],
                                           0,
-                                          0, 0, self.number_textures_held,
+                                          0, 0, layer,
                                           self.texture_array_info.width, self.texture_array_info.height, 1,
                                           pixel_format, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
                 }
 
-            let resize_factor_width = texture_properties.width as f32 / self.texture_array_info.width as f32;
-            let resize_factor_height = texture_properties.height as f32 / self.texture_array_info.height as f32;
+            return Ok(TextureUploadResult::SuccessWithResize(layer, resize_factor_width, resize_factor_height));
+        }
 
-            self.number_textures_held += 1;
+        let layer = self.take_layer(TextureContents::Image(content_hash), 0.0, 0.0);
 
-            return Ok(TextureUploadResult::SuccessWithResize(self.number_textures_held - 1, resize_factor_width, resize_factor_height));
-        }
-        else
+        unsafe
+            {
+                gl::TextureSubImage3D(self.buffers[self.current_buffer_index],
+                                      0,
+                                      0, 0, layer,
+                                      self.texture_array_info.width, self.texture_array_info.height, 1,
+                                      pixel_format, gl::UNSIGNED_BYTE, texture_properties.image_data as *const c_void);
+            }
+
+        Ok(TextureUploadResult::Success(layer))
+    }
+
+    /// Marks the layer of the texture array as unused by the caller. Once no texture array sharing the layer
+    /// uses it, a later texture can be uploaded into it. The contents of the layer are left as they are until
+    /// they are overwritten
+    ///
+    /// `layer` - the layer holding the texture that is no longer used
+    pub fn remove_texture(&mut self, layer: i32)
+    {
+        let mut layers = self.layers.lock();
+        debug_assert!(layer < layers.number_textures_held && !layers.free_layers.contains(&layer), "Texture layer {} is not in use", layer);
+
+        let users = layers.layer_users.entry(layer).or_insert(1);
+        *users -= 1;
+
+        if *users == 0
         {
-            self.number_textures_held += 1;
+            layers.layer_users.remove(&layer);
+            layers.contents.retain(|_, x| x.0 != layer);
+            layers.free_layers.push(layer);
+        }
+    }
 
-            unsafe
+    /// Checks if every layer of the texture array holds a texture that is in use
+    fn is_full(&self) -> bool
+    {
+        let layers = self.layers.lock();
+        layers.free_layers.is_empty() && layers.number_textures_held == self.texture_array_info.number_textures
+    }
+
+    /// Get the layer to upload the next texture into, reusing a layer of a removed texture if there is one
+    ///
+    /// `contents` - the contents of the texture that will be uploaded into the layer
+    /// `scale_x` - how much the texture's U coordinates need to be scaled by
+    /// `scale_y` - how much the texture's V coordinates need to be scaled by
+    fn take_layer(&mut self, contents: TextureContents, scale_x: f32, scale_y: f32) -> i32
+    {
+        let mut layers = self.layers.lock();
+
+        let layer = match layers.free_layers.pop()
+        {
+            Some(i) => i,
+            None =>
                 {
-                    gl::TextureSubImage3D(self.buffers[self.current_buffer_index],
-                                          0,
-                                          0, 0, self.number_textures_held,
-                                          self.texture_array_info.width, self.texture_array_info.height, 1,
-                                          pixel_format, gl::UNSIGNED_BYTE, texture_properties.image_data as *const c_void);
+                    layers.number_textures_held += 1;
+                    layers.number_textures_held - 1
                 }
-        }
+        };
 
-        Ok(TextureUploadResult::Success(self.number_textures_held - 1))
+        layers.layer_users.insert(layer, 1);
+        layers.contents.insert(contents, (layer, scale_x, scale_y));
+        layer
     }
 
     /// Binds the texture array to the texture unit specified in the array constructor
@@ -223,7 +365,7 @@ impl TextureArray
     /// `texture_properties` - the properties of the texture to upload to
     pub fn query_wasted_space(&self, texture_properties: &TextureProperties) -> Result<usize, ()>
     {
-        if self.number_textures_held == self.texture_array_info.number_textures
+        if self.is_full()
         {
             return Err(());
         }
@@ -287,6 +429,22 @@ impl TextureProperties
 
         TextureProperties { width, height, nr_channels, image_data }
     }
+
+    /// Calculates a hash of the dimensions and pixels of the texture, used to find textures with the same
+    /// contents that are stored in different files
+    pub fn content_hash(&self) -> u64
+    {
+        let mut hasher = DefaultHasher::new();
+        (self.width, self.height, self.nr_channels).hash(&mut hasher);
+
+        let pixels = unsafe
+            {
+                std::slice::from_raw_parts(self.image_data, (self.width * self.height * self.nr_channels) as usize)
+            };
+
+        pixels.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 impl Drop for TextureProperties
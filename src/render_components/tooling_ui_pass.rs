@@ -0,0 +1,247 @@
+use std::ffi::{c_void, CString};
+use std::mem::size_of;
+use egui::{ClippedMesh, ImageData, TexturesDelta, TextureId};
+use egui::epaint::Vertex;
+use hashbrown::HashMap;
+use tracing::warn;
+use crate::exports::frame_statistics::record_draw_calls;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+
+/// Texture unit the texture of the mesh being drawn is bound to
+const UI_TEXTURE_UNIT: u32 = 0;
+
+/// Shader storage binding points of the vertices and indices of the meshes, after the one used by debug lines
+const UI_VERTEX_BINDING_POINT: u32 = 21;
+const UI_INDEX_BINDING_POINT: u32 = 22;
+
+/// Draws the meshes of the tooling UI over the window. Textures created by egui are kept until egui frees
+/// them; user textures are OpenGL texture names given to egui as TextureId::User
+pub struct ToolingUiPass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    screen_size_location: i32,
+    vertex_buffer: u32,
+    index_buffer: u32,
+    textures: HashMap<TextureId, u32>,
+}
+
+impl ToolingUiPass
+{
+    /// Creates the resources required to draw the tooling UI. An OpenGL context must be current
+    pub fn new() -> Result<ToolingUiPass, String>
+    {
+        let version = "#version 430 core\n";
+        let program = ShaderProgram::new(&vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/tooling_ui_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/tooling_ui_frag.glsl"), Some(version), None)?,
+        ])?;
+
+        let mut buffers = [0; 2];
+
+        let screen_size_location = unsafe
+            {
+                let screen_size_name = CString::new("screenSize").unwrap();
+                let texture_name = CString::new("uiTexture").unwrap();
+
+                gl::ProgramUniform1i(program.shader_program, gl::GetUniformLocation(program.shader_program, texture_name.as_ptr()), UI_TEXTURE_UNIT as i32);
+                gl::CreateBuffers(2, buffers.as_mut_ptr());
+                gl::GetUniformLocation(program.shader_program, screen_size_name.as_ptr())
+            };
+
+        Ok(ToolingUiPass{ program, vao: VAO::new(), screen_size_location, vertex_buffer: buffers[0], index_buffer: buffers[1], textures: HashMap::default() })
+    }
+
+    /// Updates the textures changed by egui and draws the meshes into the window. The OpenGL state is the
+    /// same after this call as before it
+    ///
+    /// `textures_delta` - the textures egui created, changed or freed this frame
+    /// `meshes` - the meshes of the interface, in points
+    /// `pixels_per_point` - the number of pixels of the window in each point of the interface
+    /// `window_dimensions` - the dimensions of the window
+    pub fn apply(&mut self, textures_delta: &TexturesDelta, meshes: &[ClippedMesh], pixels_per_point: f32, window_dimensions: (i32, i32))
+    {
+        for (texture_id, image_delta) in &textures_delta.set
+        {
+            self.update_texture(*texture_id, &image_delta.image, image_delta.pos);
+        }
+
+        self.draw_meshes(meshes, pixels_per_point, window_dimensions);
+
+        for texture_id in &textures_delta.free
+        {
+            if let Some(texture) = self.textures.remove(texture_id)
+            {
+                unsafe { gl::DeleteTextures(1, &texture); }
+            }
+        }
+    }
+
+    /// Draws the meshes with their clip rectangles. The vertices and indices of every mesh are uploaded together,
+    /// with the indices of each mesh offset to the first of its vertices, so each mesh is one draw call
+    ///
+    /// `meshes` - the meshes of the interface, in points
+    /// `pixels_per_point` - the number of pixels of the window in each point of the interface
+    /// `window_dimensions` - the dimensions of the window
+    fn draw_meshes(&mut self, meshes: &[ClippedMesh], pixels_per_point: f32, window_dimensions: (i32, i32))
+    {
+        let meshes = meshes.iter().filter(|x| !x.1.indices.is_empty()).collect::<Vec<&ClippedMesh>>();
+
+        if meshes.is_empty()
+        {
+            return;
+        }
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+
+        for ClippedMesh(_, mesh) in &meshes
+        {
+            let first_vertex = vertices.len() as u32;
+            vertices.extend_from_slice(&mesh.vertices);
+            indices.extend(mesh.indices.iter().map(|x| x + first_vertex));
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        unsafe
+            {
+                gl::NamedBufferData(self.vertex_buffer, (vertices.len() * size_of::<Vertex>()) as isize, vertices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::NamedBufferData(self.index_buffer, (indices.len() * size_of::<u32>()) as isize, indices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, UI_VERTEX_BINDING_POINT, self.vertex_buffer);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, UI_INDEX_BINDING_POINT, self.index_buffer);
+
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Enable(gl::SCISSOR_TEST);
+                gl::Enable(gl::BLEND);
+
+                // The colours of egui have premultiplied alpha
+                gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                gl::Uniform2f(self.screen_size_location, window_dimensions.0 as f32 / pixels_per_point, window_dimensions.1 as f32 / pixels_per_point);
+            }
+
+        let mut first_index = 0;
+
+        for ClippedMesh(clip_rect, mesh) in &meshes
+        {
+            // The clip rectangle is in points from the top left of the window, while the scissor is in pixels
+            // from the bottom left
+            let min_x = (clip_rect.min.x * pixels_per_point).round().max(0.0) as i32;
+            let min_y = (clip_rect.min.y * pixels_per_point).round().max(0.0) as i32;
+            let max_x = ((clip_rect.max.x * pixels_per_point).round() as i32).min(window_dimensions.0);
+            let max_y = ((clip_rect.max.y * pixels_per_point).round() as i32).min(window_dimensions.1);
+
+            if max_x > min_x && max_y > min_y
+            {
+                let texture = match mesh.texture_id
+                {
+                    TextureId::User(texture) => texture as u32,
+                    texture_id => self.textures.get(&texture_id).copied().unwrap_or(0)
+                };
+
+                unsafe
+                    {
+                        gl::Scissor(min_x, window_dimensions.1 - max_y, max_x - min_x, max_y - min_y);
+                        gl::BindTextureUnit(UI_TEXTURE_UNIT, texture);
+                        gl::DrawArrays(gl::TRIANGLES, first_index, mesh.indices.len() as i32);
+                    }
+
+                record_draw_calls(1);
+            }
+
+            first_index += mesh.indices.len() as i32;
+        }
+
+        unsafe { gl::BindTextureUnit(UI_TEXTURE_UNIT, 0); }
+    }
+
+    /// Creates or changes a texture created by egui
+    ///
+    /// `texture_id` - the texture to create or change
+    /// `image` - the new contents of the texture, or of the region of it being changed
+    /// `position` - the top left corner of the region being changed, or None if the whole texture is replaced
+    fn update_texture(&mut self, texture_id: TextureId, image: &ImageData, position: Option<[usize; 2]>)
+    {
+        // The font texture only holds coverage, which is turned into white with that alpha
+        let (size, pixels) = match image
+        {
+            ImageData::Color(image) => (image.size, image.pixels.iter().flat_map(|x| x.to_array()).collect::<Vec<u8>>()),
+            ImageData::Alpha(image) => (image.size, image.srgba_pixels(1.0).flat_map(|x| x.to_array()).collect::<Vec<u8>>())
+        };
+
+        let (width, height) = (size[0] as i32, size[1] as i32);
+
+        unsafe
+            {
+                let texture = match position
+                {
+                    Some(_) => match self.textures.get(&texture_id)
+                    {
+                        Some(texture) => *texture,
+                        None =>
+                            {
+                                warn!("Unable to update non-existent tooling UI texture: {:?}", texture_id);
+                                return;
+                            }
+                    },
+                    None =>
+                        {
+                            if let Some(texture) = self.textures.remove(&texture_id)
+                            {
+                                gl::DeleteTextures(1, &texture);
+                            }
+
+                            let mut texture = 0;
+                            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+                            gl::TextureStorage2D(texture, 1, gl::RGBA8, width, height);
+                            gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                            gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                            self.textures.insert(texture_id, texture);
+                            texture
+                        }
+                };
+
+                let [x, y] = position.unwrap_or([0, 0]);
+
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl::TextureSubImage2D(texture, 0, x as i32, y as i32, width, height, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const c_void);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            }
+    }
+}
+
+impl Drop for ToolingUiPass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                for texture in self.textures.values()
+                {
+                    gl::DeleteTextures(1, texture);
+                }
+
+                gl::DeleteBuffers(1, &self.vertex_buffer);
+                gl::DeleteBuffers(1, &self.index_buffer);
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
@@ -0,0 +1,243 @@
+use nalgebra_glm::{TMat4, TVec3};
+use crate::exports::fog::FogSettings;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+
+/// Texture unit the G-Buffer layout holding world positions is bound to when ray marching
+pub const FOG_POSITION_TEXTURE_UNIT: u32 = 0;
+
+/// Texture unit the shadow maps are bound to when ray marching
+pub const FOG_SHADOW_MAP_TEXTURE_UNIT: u32 = 1;
+
+/// Texture unit the ray marched fog is bound to when compositing it over the frame
+const FOG_TEXTURE_UNIT: u32 = 0;
+
+/// Most shadow maps the ray march tests samples against, matching the shadow maps of the shadow flow
+const MAX_FOG_SHADOW_MAPS: usize = 6;
+
+/// The view of the scene the fog is ray marched through
+pub struct FogView<'a>
+{
+    pub camera_position: TVec3<f32>,
+    pub view_projection: TMat4<f32>,
+    /// The matrices transforming world positions into the space of each shadow map
+    pub light_matrices: &'a [TMat4<f32>],
+    /// The layer of the shadow map texture array of each light matrix
+    pub light_layers: &'a [u32],
+}
+
+/// Locations of the uniforms of the ray marching shader
+struct MarchUniforms
+{
+    inverse_view_projection: i32,
+    camera_position: i32,
+    light_matrices: i32,
+    light_layers: i32,
+    number_light_matrices: i32,
+    fog_colour: i32,
+    fog_density: i32,
+    fog_base_height: i32,
+    fog_height_falloff: i32,
+    light_shaft_colour: i32,
+    light_shaft_scattering: i32,
+    max_distance: i32,
+    steps: i32,
+}
+
+/// Draws volumetric fog and light shafts over the frame rendered into the window. The fog is ray marched
+/// into a half resolution texture, which is then blended over the frame
+pub struct VolumetricFogPass
+{
+    march_program: ShaderProgram,
+    composite_program: ShaderProgram,
+    vao: VAO,
+    march_uniforms: MarchUniforms,
+    fog_fbo: u32,
+    fog_texture: u32,
+    fog_texture_dimensions: (i32, i32),
+}
+
+impl VolumetricFogPass
+{
+    /// Creates the resources required to draw the fog. An OpenGL context must be current
+    pub fn new() -> Result<VolumetricFogPass, String>
+    {
+        let version = "#version 430 core\n";
+        let march_shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/volumetric_fog_frag.glsl"), Some(version), None)?,
+        ];
+
+        let composite_shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/colour_filter_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/fog_composite_frag.glsl"), Some(version), None)?,
+        ];
+
+        let march_program = ShaderProgram::new(&march_shaders)?;
+        let composite_program = ShaderProgram::new(&composite_shaders)?;
+
+        let march_uniforms = MarchUniforms
+        {
+            inverse_view_projection: march_program.uniform_location("inverseViewProjection"),
+            camera_position: march_program.uniform_location("cameraPosition"),
+            light_matrices: march_program.uniform_location("lightMatrices"),
+            light_layers: march_program.uniform_location("lightLayers"),
+            number_light_matrices: march_program.uniform_location("numberLightMatrices"),
+            fog_colour: march_program.uniform_location("fogColour"),
+            fog_density: march_program.uniform_location("fogDensity"),
+            fog_base_height: march_program.uniform_location("fogBaseHeight"),
+            fog_height_falloff: march_program.uniform_location("fogHeightFalloff"),
+            light_shaft_colour: march_program.uniform_location("lightShaftColour"),
+            light_shaft_scattering: march_program.uniform_location("lightShaftScattering"),
+            max_distance: march_program.uniform_location("maxDistance"),
+            steps: march_program.uniform_location("steps"),
+        };
+
+        unsafe
+            {
+                gl::ProgramUniform1i(march_program.shader_program, march_program.uniform_location("gPosition"), FOG_POSITION_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(march_program.shader_program, march_program.uniform_location("shadowMaps"), FOG_SHADOW_MAP_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(composite_program.shader_program, composite_program.uniform_location("fogTexture"), FOG_TEXTURE_UNIT as i32);
+            }
+
+        Ok(VolumetricFogPass{ march_program, composite_program, vao: VAO::new(), march_uniforms, fog_fbo: 0, fog_texture: 0, fog_texture_dimensions: (0, 0) })
+    }
+
+    /// Ray marches the fog and blends it over the frame rendered into the window. The G-Buffer positions and
+    /// shadow maps must be bound to their texture units. The OpenGL state is the same after this call as
+    /// before it
+    ///
+    /// `settings` - how the fog looks
+    /// `view` - the view of the scene the fog is drawn for
+    /// `output_framebuffer` - the frame buffer the frame was rendered into, which is drawn over
+    pub fn apply(&mut self, settings: &FogSettings, view: &FogView, output_framebuffer: OutputFramebuffer)
+    {
+        let window_dimensions = output_framebuffer.dimensions;
+
+        if window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        let fog_dimensions = ((window_dimensions.0 / 2).max(1), (window_dimensions.1 / 2).max(1));
+        self.resize_fog_texture(fog_dimensions);
+
+        let number_light_matrices = view.light_matrices.len().min(view.light_layers.len()).min(MAX_FOG_SHADOW_MAPS);
+        let light_matrices = view.light_matrices.iter().take(number_light_matrices).flat_map(|x| x.as_slice().to_vec()).collect::<Vec<f32>>();
+        let light_layers = view.light_layers.iter().take(number_light_matrices).map(|x| *x as i32).collect::<Vec<i32>>();
+        let inverse_view_projection = nalgebra_glm::inverse(&view.view_projection);
+
+        unsafe
+            {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.fog_fbo);
+                gl::Viewport(0, 0, fog_dimensions.0, fog_dimensions.1);
+            }
+
+        self.march_program.use_shader_program();
+        self.vao.bind();
+
+        unsafe
+            {
+                let uniforms = &self.march_uniforms;
+                gl::UniformMatrix4fv(uniforms.inverse_view_projection, 1, gl::FALSE, inverse_view_projection.as_ptr());
+                gl::Uniform3fv(uniforms.camera_position, 1, view.camera_position.as_ptr());
+
+                if number_light_matrices != 0
+                {
+                    gl::UniformMatrix4fv(uniforms.light_matrices, number_light_matrices as i32, gl::FALSE, light_matrices.as_ptr());
+                    gl::Uniform1iv(uniforms.light_layers, number_light_matrices as i32, light_layers.as_ptr());
+                }
+
+                gl::Uniform1i(uniforms.number_light_matrices, number_light_matrices as i32);
+                gl::Uniform3fv(uniforms.fog_colour, 1, settings.colour.as_ptr());
+                gl::Uniform1f(uniforms.fog_density, settings.density);
+                gl::Uniform1f(uniforms.fog_base_height, settings.base_height);
+                gl::Uniform1f(uniforms.fog_height_falloff, settings.height_falloff);
+                gl::Uniform3fv(uniforms.light_shaft_colour, 1, settings.light_shaft_colour.as_ptr());
+                gl::Uniform1f(uniforms.light_shaft_scattering, settings.light_shaft_scattering);
+                gl::Uniform1f(uniforms.max_distance, settings.max_distance);
+                gl::Uniform1i(uniforms.steps, settings.steps as i32);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+                // The fog holds the light scattered towards the camera and how much of the scene is still
+                // visible through it, so the scene is scaled by the alpha before the fog is added
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::ONE, gl::SRC_ALPHA);
+            }
+
+        self.composite_program.use_shader_program();
+
+        unsafe
+            {
+                gl::BindTextureUnit(FOG_TEXTURE_UNIT, self.fog_texture);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                gl::BindTextureUnit(FOG_TEXTURE_UNIT, 0);
+            }
+    }
+
+    /// Recreates the texture the fog is ray marched into if its dimensions changed
+    ///
+    /// `fog_dimensions` - the dimensions of the fog texture
+    fn resize_fog_texture(&mut self, fog_dimensions: (i32, i32))
+    {
+        if self.fog_texture != 0 && self.fog_texture_dimensions == fog_dimensions
+        {
+            return;
+        }
+
+        unsafe
+            {
+                if self.fog_texture != 0
+                {
+                    gl::DeleteTextures(1, &self.fog_texture);
+                    gl::DeleteFramebuffers(1, &self.fog_fbo);
+                }
+
+                gl::CreateTextures(gl::TEXTURE_2D, 1, &mut self.fog_texture);
+                gl::TextureStorage2D(self.fog_texture, 1, gl::RGBA16F, fog_dimensions.0, fog_dimensions.1);
+                gl::TextureParameteri(self.fog_texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(self.fog_texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(self.fog_texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TextureParameteri(self.fog_texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+                gl::CreateFramebuffers(1, &mut self.fog_fbo);
+                gl::NamedFramebufferTexture(self.fog_fbo, gl::COLOR_ATTACHMENT0, self.fog_texture, 0);
+                gl::NamedFramebufferDrawBuffer(self.fog_fbo, gl::COLOR_ATTACHMENT0);
+            }
+
+        self.fog_texture_dimensions = fog_dimensions;
+    }
+}
+
+impl Drop for VolumetricFogPass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                if self.fog_texture != 0
+                {
+                    gl::DeleteTextures(1, &self.fog_texture);
+                    gl::DeleteFramebuffers(1, &self.fog_fbo);
+                }
+
+                gl::DeleteProgram(self.march_program.shader_program);
+                gl::DeleteProgram(self.composite_program.shader_program);
+            }
+    }
+}
@@ -0,0 +1,246 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+use nalgebra_glm::{TMat4, TVec3, vec3};
+use crate::exports::text_shaping::shape_text;
+use crate::exports::world_labels::WorldLabel;
+use crate::helper_things::environment::get_asset_folder;
+use crate::render_components::gl_state_guard::GLStateGuard;
+use crate::render_components::glyph_atlas::{GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH, GlyphAtlas, LINE_ADVANCE};
+use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::vao::VAO;
+use crate::render_system::system_information::OutputFramebuffer;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+/// Texture unit the glyph atlas is bound to when drawing labels
+const LABEL_ATLAS_TEXTURE_UNIT: u32 = 0;
+
+/// Texture unit the G-Buffer layout holding world positions is bound to when drawing labels
+pub const LABEL_POSITION_TEXTURE_UNIT: u32 = 1;
+
+/// Shader storage binding point of the glyphs drawn, after those used by the light storage buffers
+const LABEL_GLYPH_BINDING_POINT: u32 = 6;
+
+/// The view of the scene the labels are drawn for
+pub struct LabelView
+{
+    pub camera_position: TVec3<f32>,
+    pub view_matrix: TMat4<f32>,
+    pub projection_matrix: TMat4<f32>,
+    /// If false, no world positions are bound and labels are drawn over all geometry
+    pub positions_available: bool,
+}
+
+/// A glyph of a label, laid out as the LabelGlyph struct of the label vertex shader
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LabelGlyph
+{
+    anchor: [f32; 4],
+    offset: [f32; 4],
+    atlas_rect: [f32; 4],
+    colour: [f32; 4],
+}
+
+/// Locations of the uniforms of the label shader
+struct LabelUniforms
+{
+    view_projection: i32,
+    camera_right: i32,
+    camera_up: i32,
+    camera_position: i32,
+    window_dimensions: i32,
+    positions_available: i32,
+}
+
+/// Draws the labels of entities into the window as quads facing the camera, with every glyph drawn in
+/// one instanced draw call
+pub struct WorldLabelPass
+{
+    program: ShaderProgram,
+    vao: VAO,
+    uniforms: LabelUniforms,
+    atlas: GlyphAtlas,
+    glyph_buffer: u32,
+}
+
+impl WorldLabelPass
+{
+    /// Creates the resources required to draw labels. An OpenGL context must be current
+    pub fn new() -> Result<WorldLabelPass, String>
+    {
+        let version = "#version 430 core\n";
+        let shaders = vec!
+        [
+            ShaderInitInformation::from_file(gl::VERTEX_SHADER, get_asset_folder().join("shaders/world_label_vertex.glsl"), Some(version), None)?,
+            ShaderInitInformation::from_file(gl::FRAGMENT_SHADER, get_asset_folder().join("shaders/world_label_frag.glsl"), Some(version), None)?,
+        ];
+
+        let program = ShaderProgram::new(&shaders)?;
+
+        let uniforms = LabelUniforms
+        {
+            view_projection: program.uniform_location("viewProjection"),
+            camera_right: program.uniform_location("cameraRight"),
+            camera_up: program.uniform_location("cameraUp"),
+            camera_position: program.uniform_location("cameraPosition"),
+            window_dimensions: program.uniform_location("windowDimensions"),
+            positions_available: program.uniform_location("positionsAvailable"),
+        };
+
+        let mut glyph_buffer: u32 = 0;
+
+        unsafe
+            {
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("glyphAtlas"), LABEL_ATLAS_TEXTURE_UNIT as i32);
+                gl::ProgramUniform1i(program.shader_program, program.uniform_location("gPosition"), LABEL_POSITION_TEXTURE_UNIT as i32);
+                gl::ProgramUniform2f(program.shader_program, program.uniform_location("glyphSize"), GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32);
+                gl::CreateBuffers(1, &mut glyph_buffer);
+            }
+
+        Ok(WorldLabelPass{ program, vao: VAO::new(), uniforms, atlas: GlyphAtlas::new(), glyph_buffer })
+    }
+
+    /// Draws the labels into the window. If world positions are available, they must be bound to their
+    /// texture unit. The OpenGL state is the same after this call as before it
+    ///
+    /// `labels` - the labels to draw, along with the bounding volume of the entity they are drawn above
+    /// `view` - the view of the scene the labels are drawn for
+    /// `output_framebuffer` - the frame buffer the frame was rendered into, which is drawn over
+    pub fn apply(&mut self, labels: &[(StaticAABB, WorldLabel)], view: &LabelView, output_framebuffer: OutputFramebuffer)
+    {
+        let window_dimensions = output_framebuffer.dimensions;
+
+        if window_dimensions.0 <= 0 || window_dimensions.1 <= 0
+        {
+            return;
+        }
+
+        let glyphs = self.layout_glyphs(labels, view.camera_position);
+
+        if glyphs.is_empty()
+        {
+            return;
+        }
+
+        let _state_guard = GLStateGuard::new();
+
+        let view_projection = view.projection_matrix * view.view_matrix;
+        let camera_right = vec3(view.view_matrix[(0, 0)], view.view_matrix[(0, 1)], view.view_matrix[(0, 2)]);
+        let camera_up = vec3(view.view_matrix[(1, 0)], view.view_matrix[(1, 1)], view.view_matrix[(1, 2)]);
+
+        unsafe
+            {
+                gl::NamedBufferData(self.glyph_buffer, (glyphs.len() * size_of::<LabelGlyph>()) as isize, glyphs.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, LABEL_GLYPH_BINDING_POINT, self.glyph_buffer);
+
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::CULL_FACE);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, output_framebuffer.framebuffer);
+                gl::Viewport(0, 0, window_dimensions.0, window_dimensions.1);
+            }
+
+        self.program.use_shader_program();
+        self.vao.bind();
+        self.atlas.bind(LABEL_ATLAS_TEXTURE_UNIT);
+
+        unsafe
+            {
+                gl::UniformMatrix4fv(self.uniforms.view_projection, 1, gl::FALSE, view_projection.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_right, 1, camera_right.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_up, 1, camera_up.as_ptr());
+                gl::Uniform3fv(self.uniforms.camera_position, 1, view.camera_position.as_ptr());
+                gl::Uniform2f(self.uniforms.window_dimensions, window_dimensions.0 as f32, window_dimensions.1 as f32);
+                gl::Uniform1i(self.uniforms.positions_available, view.positions_available as i32);
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, glyphs.len() as i32);
+
+                gl::BindTextureUnit(LABEL_ATLAS_TEXTURE_UNIT, 0);
+            }
+    }
+
+    /// Lays out the glyphs of every visible label, with every line centred above the entity. Labels are
+    /// ordered from farthest to nearest to the camera so that nearer labels are blended over farther ones
+    ///
+    /// `labels` - the labels to lay out, along with the bounding volume of the entity they are drawn above
+    /// `camera_position` - the position of the camera the labels are drawn for
+    fn layout_glyphs(&self, labels: &[(StaticAABB, WorldLabel)], camera_position: TVec3<f32>) -> Vec<LabelGlyph>
+    {
+        let mut visible_labels = labels.iter()
+            .filter_map(|(aabb, label)|
+                {
+                    let centre = aabb.centre();
+                    let anchor = vec3(centre.x, aabb.y_range.max + label.offset, centre.z);
+                    let distance = nalgebra_glm::distance(&anchor, &camera_position);
+                    let alpha = label.colour.w * label.fade(distance);
+
+                    if alpha <= 0.0 || label.text.is_empty() || label.line_height <= 0.0
+                    {
+                        return None;
+                    }
+
+                    Some((anchor, distance, alpha, label))
+                })
+            .collect::<Vec<(TVec3<f32>, f32, f32, &WorldLabel)>>();
+
+        visible_labels.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut glyphs = Vec::new();
+
+        for (anchor, _, alpha, label) in visible_labels
+        {
+            let shaped_glyphs = shape_text(&label.text, &self.atlas);
+            let number_lines = shaped_glyphs.iter().map(|x| x.line + 1).max().unwrap_or(0);
+            let mut line_columns = vec![0; number_lines as usize];
+
+            for glyph in &shaped_glyphs
+            {
+                let columns = &mut line_columns[glyph.line as usize];
+                *columns = (*columns).max(glyph.column + 1);
+            }
+
+            let texel_size = label.line_height / GLYPH_HEIGHT as f32;
+            let colour = [label.colour.x, label.colour.y, label.colour.z, alpha];
+            let occluded = if label.occluded { 1.0 } else { 0.0 };
+
+            for glyph in shaped_glyphs
+            {
+                let atlas_rect = match self.atlas.glyph_rect(glyph.character)
+                {
+                    Some(i) if glyph.character != ' ' => i,
+                    _ => continue
+                };
+
+                // The gap after the last glyph of a line is not part of the width that is centred
+                let line_width = (line_columns[glyph.line as usize] * GLYPH_ADVANCE as u32) as f32 - (GLYPH_ADVANCE - GLYPH_WIDTH) as f32;
+                let x = (glyph.column * GLYPH_ADVANCE as u32) as f32 - line_width * 0.5;
+                let y = ((number_lines - 1 - glyph.line) * LINE_ADVANCE as u32) as f32;
+
+                glyphs.push(LabelGlyph
+                {
+                    anchor: [anchor.x, anchor.y, anchor.z, texel_size],
+                    offset: [x, y, occluded, 0.0],
+                    atlas_rect: [atlas_rect.min.0, atlas_rect.min.1, atlas_rect.max.0, atlas_rect.max.1],
+                    colour
+                });
+            }
+        }
+
+        glyphs
+    }
+}
+
+impl Drop for WorldLabelPass
+{
+    fn drop(&mut self)
+    {
+        unsafe
+            {
+                gl::DeleteBuffers(1, &self.glyph_buffer);
+                gl::DeleteProgram(self.program.shader_program);
+            }
+    }
+}
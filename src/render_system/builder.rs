@@ -1,13 +1,15 @@
 use hashbrown::HashMap;
+use tracing::warn;
 use crate::exports::load_models::MaxNumLights;
-use crate::exports::rendering::LevelOfView;
+use crate::exports::rendering::{LevelOfView, ScreenSpaceReflections, ShadowQuality};
 use crate::helper_things::environment::{get_asset_folder, get_generated_shaders_folder};
 use crate::models::model_definitions::MeshGeometry;
+use crate::prelude::pbr_render_system::{pbr_first_pass_fragment_shader, pbr_second_pass_fragment_shader, pbr_vertex_shader};
 use crate::render_components::frame_buffer::FBO;
 use crate::render_components::mapped_buffer::BufferWriteInfo;
-use crate::render_system::initialize_logic::create_render_system;
+use crate::render_system::initialize_logic::{create_render_system, prepare_render_system, PreparedRenderSystem};
 use crate::render_system::render_system::RenderSystem;
-use crate::render_system::system_information::{Constant, DrawFunction, FragmentShaderInformation, GLSLVersion, SystemInformation, Uniform, UniformBlock, UniformType, VertexShaderInformation};
+use crate::render_system::system_information::{Constant, DrawFunction, FragmentShaderInformation, GLSLVersion, LayoutInformation, LayoutInstance, LayoutType, LayoutUse, SystemInformation, Uniform, UniformBlock, UniformType, VertexShaderInformation};
 use crate::specify_model_geometry_layouts;
 
 /// Builder to start the process of creating a render system
@@ -87,7 +89,18 @@ impl RenderSystemBuilder
                     apply_lights: false,
                     max_num_lights,
                     no_light_source_cutoff: 0.0,
-                    default_diffuse_factor: 0.0
+                    default_diffuse_factor: 0.0,
+                    shadow_quality: ShadowQuality::Pcf3x3,
+                    clustered_lighting: false,
+                    baked_lighting: false,
+                    environment_lighting: false,
+                    damage_masks: false,
+                    material_maps: false,
+                    tangent_space: false,
+                    shared_texture_arrays: HashMap::new(),
+                    gpu_level_of_views: false,
+                    gpu_culling: false,
+                    screen_space_reflections: None
                 }
             )
     }
@@ -109,6 +122,22 @@ impl FirstPassVertexShaderBuilder
         self.0.first_pass_vertex_shader = Some(vertex_shader);
         FirstPassFragmentShaderBuilder(self.0)
     }
+
+    /// Uses the ready-made physically based shaders for the first pass, which write the albedo, normal,
+    /// metallic, roughness and ambient occlusion maps of models into the g-buffer, scaled by the PbrMaterial
+    /// of each entity. Models uploaded to the render system have all of their material maps loaded. The
+    /// materials are lit by using with_pbr_second_pass_fragment_shader for the second pass
+    ///
+    /// `window_resolution` - the initial size of the g-buffer
+    /// `texture_resolution` - the largest size of the material maps of the models of the render system
+    /// `number_textures` - the number of material maps that can be loaded into the render system
+    pub fn with_pbr_shaders(mut self, window_resolution: (i32, i32), texture_resolution: (i32, i32), number_textures: i32) -> SecondPassVertexShaderBuilder
+    {
+        self.0.first_pass_vertex_shader = Some(pbr_vertex_shader());
+        self.0.first_pass_fragment_shader = Some(pbr_first_pass_fragment_shader(window_resolution, texture_resolution, number_textures));
+        self.0.material_maps = true;
+        SecondPassVertexShaderBuilder(self.0)
+    }
 }
 
 impl FirstPassFragmentShaderBuilder
@@ -160,6 +189,20 @@ impl SecondPassFragmentShaderBuilder
         self.0.second_pass_frag_shader = Some(fragment_shader);
         DrawFunctionBuilder(self.0)
     }
+
+    /// Uses the ready-made physically based shader for the second pass, which lights the materials written
+    /// by the first pass of with_pbr_shaders using the Cook-Torrance BRDF. With environment lighting, the
+    /// ambient light of each material comes from the environment maps
+    pub fn with_pbr_second_pass_fragment_shader(mut self) -> DrawFunctionBuilder
+    {
+        if !self.0.material_maps
+        {
+            warn!("The PBR second pass fragment shader reads the g-buffer written by the PBR first pass shaders, which are not used");
+        }
+
+        self.0.second_pass_frag_shader = Some(pbr_second_pass_fragment_shader());
+        DrawFunctionBuilder(self.0)
+    }
 }
 
 impl DrawFunctionBuilder
@@ -234,8 +277,220 @@ impl NoLightDiffuseParam
 
 impl CreateRenderSystemBuilder
 {
+    /// Specifies how the shadow maps are filtered in the generated fragment shaders. Defaults to Pcf3x3
+    ///
+    /// `shadow_quality` - how soft the edges of shadows should be
+    pub fn with_shadow_quality(mut self, shadow_quality: ShadowQuality) -> CreateRenderSystemBuilder
+    {
+        self.0.shadow_quality = shadow_quality;
+        self
+    }
+
+    /// Specifies if lights are binned into clusters of the view frustum, so that fragments only evaluate the
+    /// lights near them. Requires the second pass fragment shader to use light storage buffers; the generated
+    /// shader then defines CLUSTERED_LIGHTING and the findLightCluster function. Disabled by default
+    ///
+    /// `clustered_lighting` - true if clustered lighting should be used
+    pub fn with_clustered_lighting(mut self, clustered_lighting: bool) -> CreateRenderSystemBuilder
+    {
+        if clustered_lighting && !self.0.second_pass_frag_shader.as_ref().is_some_and(|x| x.include_light_storage_buffers)
+        {
+            warn!("Clustered lighting requires the second pass fragment shader to include light storage buffers; it will not be used");
+            return self;
+        }
+
+        self.0.clustered_lighting = clustered_lighting;
+        self
+    }
+
+    /// Specifies if the second pass fragment shader can read the lighting baked for static world sections.
+    /// The generated shader then defines BAKED_LIGHTING and the sampleBakedLighting function, which gives
+    /// the baked lighting at a position if it is in a baked world section. Disabled by default
+    ///
+    /// `baked_lighting` - true if the baked lighting should be available to the shader
+    pub fn with_baked_lighting(mut self, baked_lighting: bool) -> CreateRenderSystemBuilder
+    {
+        self.0.baked_lighting = baked_lighting;
+        self
+    }
+
+    /// Specifies if the second pass fragment shader can read the maps convolved from the environment map.
+    /// The generated shader then defines ENVIRONMENT_LIGHTING and the environmentLighting function, which
+    /// gives the ambient lighting of a surface from the environment if an environment map is loaded.
+    /// Disabled by default
+    ///
+    /// `environment_lighting` - true if the environment maps should be available to the shader
+    pub fn with_environment_lighting(mut self, environment_lighting: bool) -> CreateRenderSystemBuilder
+    {
+        self.0.environment_lighting = environment_lighting;
+        self
+    }
+
+    /// Specifies if the first pass fragment shader can read the damage masks of damaged entities. The
+    /// generated shader then defines DAMAGE_MASKS and the applyDamage function, which scorches a colour
+    /// by the mask of the given layer. The layer is per-instance data written with the DamageLayer type.
+    /// Disabled by default
+    ///
+    /// `damage_masks` - true if the damage masks should be available to the shader
+    pub fn with_damage_masks(mut self, damage_masks: bool) -> CreateRenderSystemBuilder
+    {
+        self.0.damage_masks = damage_masks;
+        self
+    }
+
+    /// Specifies if the first pass shaders are given the tangent space of each vertex, for normal and
+    /// parallax mapping. The first pass vertex shader must have a Tangent and a Bitangent layout. The generated
+    /// vertex shader then defines TANGENT_SPACE and the calculateTangentBasis function, whose result is written
+    /// to the tangentBasis out variable. The generated fragment shader defines the tangentSpaceNormal,
+    /// tangentSpaceViewDirection and parallaxMapping functions. Disabled by default
+    ///
+    /// `tangent_space` - true if the tangent space should be available to the shaders
+    pub fn with_tangent_space(mut self, tangent_space: bool) -> CreateRenderSystemBuilder
+    {
+        let layouts = self.0.first_pass_vertex_shader.as_ref().map_or(&[][..], |x| &x.layout_info[..]);
+        let has_tangents = layouts.iter().any(|x| matches!(x.data_type, LayoutType::Tangent));
+        let has_bitangents = layouts.iter().any(|x| matches!(x.data_type, LayoutType::Bitangent));
+
+        if tangent_space && !(has_tangents && has_bitangents)
+        {
+            warn!("Tangent space requires the first pass vertex shader to have a Tangent and a Bitangent layout; it will not be used");
+            return self;
+        }
+
+        self.0.tangent_space = tangent_space;
+        self
+    }
+
+    /// Specifies if the first pass vertex shader is given the InstanceParams of each entity. An instanced
+    /// vec4 layout named instanceParams is added after the layouts of the vertex shader, and the engine
+    /// writes it itself, so the instance layout update function does not need to handle it. Entities
+    /// without InstanceParams are given zeros. Disabled by default
+    ///
+    /// `instance_params` - true if the instance parameters should be available to the shader
+    pub fn with_instance_params(mut self, instance_params: bool) -> CreateRenderSystemBuilder
+    {
+        self.set_engine_written_layout(instance_params, LayoutType::InstanceParams, "instanceParams", "Instance parameters");
+        self
+    }
+
+    /// Specifies if the first pass shaders can dissolve entities with a Dissolve component. An instanced
+    /// vec4 layout named dissolve is added after the layouts of the vertex shader, holding the amount the
+    /// entity has dissolved followed by the colour of the dissolving edge; the engine writes it itself. The
+    /// generated fragment shader then defines DISSOLVE and the applyDissolve function, which discards the
+    /// dissolved parts of a model and colours the edge of the rest. Disabled by default
+    ///
+    /// `dissolve` - true if entities should be able to dissolve
+    pub fn with_dissolve(mut self, dissolve: bool) -> CreateRenderSystemBuilder
+    {
+        self.set_engine_written_layout(dissolve, LayoutType::Dissolve, "dissolve", "Dissolving");
+        self
+    }
+
+    /// Specifies that a texture array of the first pass fragment shader is shared with every other render
+    /// system sharing a texture array under the same name, rather than being owned by this render system.
+    /// A texture uploaded through any of these render systems is then held once, and each render system
+    /// binds the shared texture array to its own binding point. The texture arrays must have the same
+    /// format, dimensions and capacity; if they do not, the texture array is not shared
+    ///
+    /// `sampler_name` - the sampler name of the texture array to share
+    /// `shared_name` - the name the texture array is shared under
+    pub fn with_shared_texture_array<A: Into<String>, B: Into<String>>(mut self, sampler_name: A, shared_name: B) -> CreateRenderSystemBuilder
+    {
+        let sampler_name = sampler_name.into();
+        let has_sampler = self.0.first_pass_fragment_shader.as_ref().is_some_and(|x| x.textures.iter().any(|x| x.sampler_name == sampler_name));
+
+        if !has_sampler
+        {
+            warn!("The first pass fragment shader has no texture array \"{}\" to share; it will not be shared", sampler_name);
+            return self;
+        }
+
+        self.0.shared_texture_arrays.insert(sampler_name, shared_name.into());
+        self
+    }
+
+    /// Specifies if the level of view of each instance is chosen on the GPU rather than on the CPU. A compute
+    /// pass finds the distance of every instance to the camera, copies it into the instances of its level of
+    /// view and writes indirect draw commands drawing every level of view of a model at once. Each model is
+    /// then drawn with one draw call per sortable index, and the CPU no longer finds the level of view of
+    /// each entity when sorting them. Instances of the same model are not kept in the order they were
+    /// written, so transparent entities are not drawn back to front. Disabled by default
+    ///
+    /// `gpu_level_of_views` - true if level of views should be chosen on the GPU
+    pub fn with_gpu_level_of_views(mut self, gpu_level_of_views: bool) -> CreateRenderSystemBuilder
+    {
+        self.0.gpu_level_of_views = gpu_level_of_views;
+        self
+    }
+
+    /// Specifies if instances are culled on the GPU rather than on the CPU. Every entity of the visible world
+    /// sections is written without being checked one by one; the compute pass choosing level of views then
+    /// leaves out instances outside of the view frustum, and for render systems rendering deferred, instances
+    /// hidden behind the depth of the previous frame, before writing the indirect draw commands. This also
+    /// makes the level of views be chosen on the GPU. Occlusion is judged from the previous frame, so an entity
+    /// that comes out from behind another one can be missing for a frame. Disabled by default
+    ///
+    /// `gpu_culling` - true if instances should be culled on the GPU
+    pub fn with_gpu_culling(mut self, gpu_culling: bool) -> CreateRenderSystemBuilder
+    {
+        self.0.gpu_culling = gpu_culling;
+        self
+    }
+
+    /// Specifies if screen space reflections are drawn after the second render pass lights the frame. Rays
+    /// are reflected off every lit fragment and marched through the positions of the G-Buffer, and the colour
+    /// of the surface a ray hits is blended over the fragment by how smooth it is. The roughness of render
+    /// systems using the PBR shaders is read from the G-Buffer; surfaces of other render systems are treated
+    /// as mostly rough. Reflections require deferred rendering, and are disabled by default
+    ///
+    /// `screen_space_reflections` - how reflections are ray marched, or None to not draw reflections
+    pub fn with_screen_space_reflections(mut self, screen_space_reflections: Option<ScreenSpaceReflections>) -> CreateRenderSystemBuilder
+    {
+        self.0.screen_space_reflections = screen_space_reflections;
+        self
+    }
+
     pub fn build(self) -> RenderSystem
     {
         create_render_system(self.0)
     }
+
+    /// Generates the shaders of the render system without creating any OpenGL resources, so that it can
+    /// be done on a thread without a current OpenGL context. The returned render system is built on the
+    /// thread whose context is current
+    pub fn prepare(self) -> PreparedRenderSystem
+    {
+        prepare_render_system(self.0)
+    }
+
+    /// Adds or removes a per-instance layout that the engine writes rather than the instance layout update
+    /// function, after the layouts of the first pass vertex shader
+    ///
+    /// `enabled` - true if the layout should be added
+    /// `layout_type` - the type of the layout, identifying the data the engine writes to it
+    /// `name` - the name of the layout in the vertex shader
+    /// `feature_name` - the name of the feature requiring the layout, used when reporting errors
+    fn set_engine_written_layout(&mut self, enabled: bool, layout_type: LayoutType, name: &str, feature_name: &str)
+    {
+        let vertex_shader = match self.0.first_pass_vertex_shader.as_mut()
+        {
+            Some(i) => i,
+            None =>
+                {
+                    warn!("{} requires the first pass vertex shader to be specified; it will not be used", feature_name);
+                    return;
+                }
+        };
+
+        let is_same_type = |x: &LayoutInformation| std::mem::discriminant(&x.data_type) == std::mem::discriminant(&layout_type);
+
+        if !enabled
+        {
+            vertex_shader.layout_info.retain(|x| !is_same_type(x));
+        }
+        else if !vertex_shader.layout_info.iter().any(is_same_type)
+        {
+            vertex_shader.layout_info.push(LayoutInformation::new(layout_type, LayoutInstance::Divisor1(2, 375_000), LayoutUse::PerInstance, name));
+        }
+    }
 }
\ No newline at end of file
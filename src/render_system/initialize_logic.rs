@@ -2,12 +2,28 @@ use std::any::TypeId;
 use std::mem::size_of;
 use hashbrown::HashMap;
 use nalgebra_glm::{TMat4, TMat4x4, TVec2, TVec3, TVec4, vec2, vec3, vec4};
+use tracing::warn;
+use crate::exports::gl_debug_output::with_gl_debug_group;
+use crate::exports::light_components::FindLightType;
+use crate::exports::load_models::MaxNumLights;
+use crate::exports::rendering::ShadowQuality;
 use crate::objects::ecs::ECS;
 use crate::models::model_definitions::MeshGeometry;
 use crate::render_components::cubemap::CubeMap;
+use crate::render_components::damage_masks::DAMAGE_MASK_DECLARATIONS;
+use crate::render_components::dissolve_noise::DISSOLVE_DECLARATIONS;
 use crate::render_components::frame_buffer::FBO;
+use crate::render_components::gpu_level_of_view_pass::{GpuLevelOfViewPass, InstanceLayoutDescription};
+use crate::render_components::hi_z_buffer::HiZBuffer;
+use crate::render_components::instance_upload_ring::InstanceUploadRing;
+use crate::render_components::light_clusters::LIGHT_CLUSTER_DECLARATIONS;
+use crate::render_components::environment_lighting::ENVIRONMENT_LIGHTING_DECLARATIONS;
+use crate::render_components::lightmap::BAKED_LIGHTING_DECLARATIONS;
+use crate::render_components::light_storage_buffer::{LIGHT_STORAGE_DECLARATIONS, LightStorageBuffers};
 use crate::render_components::mapped_buffer::{BindingInformation, BufferType, BufferWriteInfo, MappedBuffer};
+use crate::render_components::screen_space_reflection_pass::ScreenSpaceReflectionPass;
 use crate::render_components::shader_program::{ShaderInitInformation, ShaderProgram};
+use crate::render_components::shared_texture_arrays::share_texture_array;
 use crate::render_components::texture_array::TextureArray;
 use crate::render_components::vao::VAO;
 use crate::render_system::helper_constructs::ERROR_TEXTURE_COLOURS;
@@ -24,10 +40,16 @@ specify_model_geometry_layouts!(second_pass_update_fn,);
 /// of parameters passed into functions
 struct RenderPassInitArgs<'a>
 {
-    system_information: &'a SystemInformation,
+    constant_values: &'a Vec<Constant>,
     vertex_shader: &'a VertexShaderInformation,
     frag_shader: &'a FragmentShaderInformation,
-    g_buffer_textures: &'a mut GBufferLayouts
+    g_buffer_textures: &'a mut GBufferLayouts,
+    shadow_quality: ShadowQuality,
+    clustered_lighting: bool,
+    baked_lighting: bool,
+    environment_lighting: bool,
+    damage_masks: bool,
+    tangent_space: bool,
 }
 
 /// Holds the generated parts of a shader to assemble together to create a
@@ -41,6 +63,7 @@ struct DynamicVertexShaderGeneration
     out_variables: String,
     texture_layouts: String,
     uniforms: String,
+    functions: String,
 }
 
 /// Holds the generated parts of a shader to assemble together to create a
@@ -55,6 +78,7 @@ struct DynamicFragmentShaderGeneration
     out_variables: String,
     texture_layouts: String,
     uniforms: String,
+    functions: String,
 }
 
 impl DynamicVertexShaderGeneration
@@ -72,7 +96,8 @@ impl DynamicVertexShaderGeneration
             layout: "".to_string(),
             out_variables: "".to_string(),
             texture_layouts: "".to_string(),
-            uniforms: "".to_string()
+            uniforms: "".to_string(),
+            functions: "".to_string()
         }
     }
 
@@ -85,6 +110,7 @@ impl DynamicVertexShaderGeneration
         append_contents += &(self.out_variables.clone() + "\n");
         append_contents += &(self.texture_layouts.clone() + "\n");
         append_contents += &(self.uniforms.clone() + "\n");
+        append_contents += &(self.functions.clone() + "\n");
         append_contents
     }
 }
@@ -105,7 +131,8 @@ impl DynamicFragmentShaderGeneration
             in_variables: "".to_string(),
             out_variables: "".to_string(),
             texture_layouts: "".to_string(),
-            uniforms: "".to_string()
+            uniforms: "".to_string(),
+            functions: "".to_string()
         }
     }
 
@@ -119,25 +146,69 @@ impl DynamicFragmentShaderGeneration
         append_contents += &(self.out_variables.clone() + "\n");
         append_contents += &(self.texture_layouts.clone() + "\n");
         append_contents += &(self.uniforms.clone() + "\n");
+        append_contents += &(self.functions.clone() + "\n");
         append_contents
     }
 }
 
 /// Stores the required layout code for the second pass GBuffer
+#[derive(Clone)]
 pub struct GBufferLayouts
 {
     layouts: String,
     number_layouts: u32,
 }
 
+/// Holds the information used to create the second render pass so that it can be regenerated
+/// after the render system has been created, such as when the light constraints change
+#[derive(Clone)]
+pub struct SecondPassRebuildInformation
+{
+    constant_values: Vec<Constant>,
+    vertex_shader: VertexShaderInformation,
+    frag_shader: FragmentShaderInformation,
+    g_buffer_layouts: GBufferLayouts,
+    shadow_quality: ShadowQuality,
+    clustered_lighting: bool,
+    baked_lighting: bool,
+    environment_lighting: bool,
+}
+
+/// The shaders of a render pass, generated and read from disk but not yet compiled
+struct PreparedRenderPass
+{
+    shaders: Vec<ShaderInitInformation>,
+    shadow_map_binding_point: Option<u32>,
+}
+
+/// A render system whose shaders have been generated, but whose OpenGL resources have not been created.
+/// Preparing a render system does not require an OpenGL context, so render systems can be prepared on
+/// other threads and then built on the thread whose context is current
+pub struct PreparedRenderSystem
+{
+    system_information: SystemInformation,
+    first_pass: PreparedRenderPass,
+    second_pass: Option<(SecondPassRebuildInformation, PreparedRenderPass)>,
+}
+
 /// Creates a render system with the provided information specified in the system_information
 ///
 /// `system_information` - the information that specifies the content of shaders and required resources
 ///                        to use those shaders to create
 pub fn create_render_system(system_information: SystemInformation) -> RenderSystem
 {
-    let first_render_pass_resources;
-    let mut second_render_pass_resources = None;
+    prepare_render_system(system_information).build()
+}
+
+/// Generates the shaders of a render system with the provided information specified in the
+/// system_information. No OpenGL calls are made
+///
+/// `system_information` - the information that specifies the content of shaders and required resources
+///                        to use those shaders to create
+pub fn prepare_render_system(system_information: SystemInformation) -> PreparedRenderSystem
+{
+    let first_pass;
+    let mut second_pass = None;
     let mut g_buffer_layouts = GBufferLayouts{ layouts: "".to_string(), number_layouts: 0 };
 
     // There will always be a first-pass, otherwise the render system is invalid. Hence the panic in
@@ -146,15 +217,21 @@ pub fn create_render_system(system_information: SystemInformation) -> RenderSyst
     {
         (Some(vertex_shader), Some(frag_shader)) =>
             {
-                let render_system_init_args = RenderPassInitArgs
+                let mut render_system_init_args = RenderPassInitArgs
                 {
-                    system_information: &system_information,
+                    constant_values: &system_information.constant_values,
                     vertex_shader,
                     frag_shader,
-                    g_buffer_textures: &mut g_buffer_layouts
+                    g_buffer_textures: &mut g_buffer_layouts,
+                    shadow_quality: system_information.shadow_quality,
+                    clustered_lighting: system_information.clustered_lighting,
+                    baked_lighting: system_information.baked_lighting,
+                    environment_lighting: system_information.environment_lighting,
+                    damage_masks: system_information.damage_masks,
+                    tangent_space: system_information.tangent_space
                 };
 
-                first_render_pass_resources = Some(create_first_render_pass_resources(render_system_init_args));
+                first_pass = generate_first_render_pass_shaders(&mut render_system_init_args);
             },
         _ => panic!()
     }
@@ -163,32 +240,227 @@ pub fn create_render_system(system_information: SystemInformation) -> RenderSyst
     {
         (Some(vertex_shader), Some(frag_shader)) =>
             {
-                let render_system_init_args = RenderPassInitArgs
+                let mut rebuild_information = SecondPassRebuildInformation
                 {
-                    system_information: &system_information,
-                    vertex_shader,
-                    frag_shader,
-                    g_buffer_textures: &mut g_buffer_layouts
+                    constant_values: system_information.constant_values.clone(),
+                    vertex_shader: vertex_shader.clone(),
+                    frag_shader: frag_shader.clone(),
+                    g_buffer_layouts: g_buffer_layouts.clone(),
+                    shadow_quality: system_information.shadow_quality,
+                    clustered_lighting: system_information.clustered_lighting,
+                    baked_lighting: system_information.baked_lighting,
+                    environment_lighting: system_information.environment_lighting
                 };
 
-                second_render_pass_resources = Some(create_second_render_pass_resources(render_system_init_args));
+                let prepared_pass = prepare_second_render_pass(&mut rebuild_information, system_information.max_num_lights);
+                second_pass = Some((rebuild_information, prepared_pass));
             },
         _ => {}
     }
 
-    RenderSystem::new(first_render_pass_resources.unwrap(), second_render_pass_resources,
-                      system_information.draw_function.unwrap(), system_information.light_draw_function.unwrap(),
-                      system_information.transparency_draw_function.unwrap(), system_information.level_of_views,
-                      system_information.draw_fn_accessible_fbo, system_information.apply_lights,
-                      system_information.max_num_lights, system_information.no_light_source_cutoff,
-                      system_information.default_diffuse_factor)
+    PreparedRenderSystem{ system_information, first_pass, second_pass }
 }
 
-/// Creates the resources required for the first render pass of the render system
+impl PreparedRenderSystem
+{
+    /// Creates the OpenGL resources of the render system and compiles its shaders. An OpenGL context
+    /// must be current
+    pub fn build(self) -> RenderSystem
+    {
+        let system_information = self.system_information;
+
+        // The g-buffer layouts were generated when the shaders were prepared; they are not used again
+        let mut g_buffer_layouts = GBufferLayouts{ layouts: "".to_string(), number_layouts: 0 };
+
+        let render_system_init_args = RenderPassInitArgs
+        {
+            constant_values: &system_information.constant_values,
+            vertex_shader: system_information.first_pass_vertex_shader.as_ref().unwrap(),
+            frag_shader: system_information.first_pass_fragment_shader.as_ref().unwrap(),
+            g_buffer_textures: &mut g_buffer_layouts,
+            shadow_quality: system_information.shadow_quality,
+            clustered_lighting: system_information.clustered_lighting,
+            baked_lighting: system_information.baked_lighting,
+            environment_lighting: system_information.environment_lighting,
+            damage_masks: system_information.damage_masks,
+            tangent_space: system_information.tangent_space
+        };
+
+        let first_pass = self.first_pass;
+        let first_render_pass_resources = with_gl_debug_group("creating the first render pass of a render system", ||
+            create_first_render_pass_resources(&render_system_init_args, first_pass, &system_information.shared_texture_arrays));
+
+        let (second_render_pass_resources, second_pass_rebuild_information) = match self.second_pass
+        {
+            Some((rebuild_information, prepared_pass)) =>
+                {
+                    (Some(create_prepared_second_render_pass(&rebuild_information, prepared_pass)), Some(rebuild_information))
+                },
+            None => (None, None)
+        };
+
+        // Instances are culled by the pass choosing level of views on the GPU
+        let gpu_level_of_views = if system_information.gpu_level_of_views || system_information.gpu_culling
+        {
+            match GpuLevelOfViewPass::new(describe_instance_layouts(system_information.first_pass_vertex_shader.as_ref().unwrap()))
+            {
+                Ok(i) => Some(i),
+                Err(err) =>
+                    {
+                        warn!("Level of views will be chosen on the CPU: {}", err);
+                        None
+                    }
+            }
+        }
+        else
+        {
+            None
+        };
+
+        // Reflections are drawn over the frame lit by the second render pass, so cannot be drawn without it
+        let screen_space_reflections = match (system_information.screen_space_reflections, second_render_pass_resources.is_some())
+        {
+            (Some(settings), true) =>
+                {
+                    match ScreenSpaceReflectionPass::new(settings)
+                    {
+                        Ok(i) => Some(i),
+                        Err(err) =>
+                            {
+                                warn!("Screen space reflections will not be drawn: {}", err);
+                                None
+                            }
+                    }
+                },
+            (Some(_), false) =>
+                {
+                    warn!("Screen space reflections require deferred rendering; they will not be drawn");
+                    None
+                },
+            (None, _) => None
+        };
+
+        let mut render_system = RenderSystem::new(first_render_pass_resources, second_render_pass_resources,
+                          system_information.draw_function.unwrap(), system_information.light_draw_function.unwrap(),
+                          system_information.transparency_draw_function.unwrap(), system_information.level_of_views,
+                          system_information.draw_fn_accessible_fbo, system_information.apply_lights,
+                          system_information.max_num_lights, system_information.no_light_source_cutoff,
+                          system_information.default_diffuse_factor, second_pass_rebuild_information,
+                          system_information.material_maps);
+
+        if let Some(gpu_level_of_views) = gpu_level_of_views
+        {
+            render_system.set_gpu_level_of_views(gpu_level_of_views);
+
+            if system_information.gpu_culling
+            {
+                match HiZBuffer::new()
+                {
+                    Ok(i) => render_system.set_gpu_culling(i),
+                    Err(err) => warn!("Instances will be culled on the CPU: {}", err)
+                }
+            }
+        }
+
+        if let Some(screen_space_reflections) = screen_space_reflections
+        {
+            render_system.set_screen_space_reflections(screen_space_reflections);
+        }
+
+        render_system
+    }
+}
+
+/// Creates the resources for the second render pass, sizing any uniform arrays that scale with the
+/// number of lights to the given light constraints
+///
+/// `rebuild_information` - the information used to generate the second render pass; light array
+///                         uniforms are resized in place
+/// `max_num_lights` - the maximum number of each type of light to reserve uniform space for
+pub fn rebuild_second_render_pass_resources(rebuild_information: &mut SecondPassRebuildInformation, max_num_lights: MaxNumLights) -> RenderPassResources
+{
+    let prepared_pass = prepare_second_render_pass(rebuild_information, max_num_lights);
+    create_prepared_second_render_pass(rebuild_information, prepared_pass)
+}
+
+/// Generates the shaders of the second render pass, sizing any uniform arrays that scale with the
+/// number of lights to the given light constraints
+///
+/// `rebuild_information` - the information used to generate the second render pass; light array
+///                         uniforms are resized in place
+/// `max_num_lights` - the maximum number of each type of light to reserve uniform space for
+fn prepare_second_render_pass(rebuild_information: &mut SecondPassRebuildInformation, max_num_lights: MaxNumLights) -> PreparedRenderPass
+{
+    scale_light_uniforms(&mut rebuild_information.vertex_shader.uniforms, max_num_lights);
+    scale_light_uniforms(&mut rebuild_information.frag_shader.uniforms, max_num_lights);
+
+    // Generating the shaders does not modify the g-buffer layouts; a copy is used so that the
+    // rebuild information stays borrowed immutably
+    let mut g_buffer_layouts = rebuild_information.g_buffer_layouts.clone();
+    generate_second_render_pass_shaders(&mut second_pass_init_args(rebuild_information, &mut g_buffer_layouts))
+}
+
+/// Creates the resources for the second render pass from its generated shaders
 ///
-/// `render_system_init_args` - structure holding the parameters required to generate a shader and
-///                             required OpenGL resources to use that shader
-fn create_first_render_pass_resources(render_system_init_args: RenderPassInitArgs) -> RenderPassResources
+/// `rebuild_information` - the information used to generate the second render pass
+/// `prepared_pass` - the generated shaders of the second render pass
+fn create_prepared_second_render_pass(rebuild_information: &SecondPassRebuildInformation, prepared_pass: PreparedRenderPass) -> RenderPassResources
+{
+    let mut g_buffer_layouts = rebuild_information.g_buffer_layouts.clone();
+
+    with_gl_debug_group("creating the second render pass of a render system", ||
+        create_second_render_pass_resources(&second_pass_init_args(rebuild_information, &mut g_buffer_layouts), prepared_pass))
+}
+
+/// Gets the parameters used to generate the second render pass and its resources
+///
+/// `rebuild_information` - the information used to generate the second render pass
+/// `g_buffer_layouts` - the g-buffer layouts read by the second render pass
+fn second_pass_init_args<'a>(rebuild_information: &'a SecondPassRebuildInformation, g_buffer_layouts: &'a mut GBufferLayouts) -> RenderPassInitArgs<'a>
+{
+    RenderPassInitArgs
+    {
+        constant_values: &rebuild_information.constant_values,
+        vertex_shader: &rebuild_information.vertex_shader,
+        frag_shader: &rebuild_information.frag_shader,
+        g_buffer_textures: g_buffer_layouts,
+        shadow_quality: rebuild_information.shadow_quality,
+        clustered_lighting: rebuild_information.clustered_lighting,
+        baked_lighting: rebuild_information.baked_lighting,
+        environment_lighting: rebuild_information.environment_lighting,
+        // Damage masks and tangent space are only used by first passes
+        damage_masks: false,
+        tangent_space: false
+    }
+}
+
+/// Resizes the uniform arrays that were declared to scale with a type of light
+///
+/// `uniform_blocks` - the uniform blocks containing the uniforms to resize
+/// `max_num_lights` - the maximum number of each type of light
+fn scale_light_uniforms(uniform_blocks: &mut [UniformBlock], max_num_lights: MaxNumLights)
+{
+    for uniform in uniform_blocks.iter_mut().flat_map(|x| x.uniforms.iter_mut())
+    {
+        if let Some(light_type) = uniform.light_scaling
+        {
+            let number_lights = match light_type
+            {
+                FindLightType::Directional => max_num_lights.directional,
+                FindLightType::Point => max_num_lights.point,
+                FindLightType::Spot => max_num_lights.spot
+            };
+
+            uniform.uniform_type = uniform.uniform_type.with_array_length(number_lights);
+        }
+    }
+}
+
+/// Generates the shaders of the first render pass of the render system
+///
+/// `render_system_init_args` - structure holding the parameters required to generate a shader; the layouts
+///                             of the g-buffer read by the second pass are stored in it
+fn generate_first_render_pass_shaders(render_system_init_args: &mut RenderPassInitArgs) -> PreparedRenderPass
 {
     let mut dynamic_vertex_shader = DynamicVertexShaderGeneration::new(render_system_init_args.vertex_shader.write_generated_shader.clone());
     let mut dynamic_frag_shader = DynamicFragmentShaderGeneration::new(render_system_init_args.frag_shader.write_generated_shader.clone());
@@ -196,27 +468,70 @@ fn create_first_render_pass_resources(render_system_init_args: RenderPassInitArg
     dynamic_vertex_shader.glsl_version = render_system_init_args.vertex_shader.glsl_version.to_string();
     dynamic_frag_shader.glsl_version = render_system_init_args.frag_shader.glsl_version.to_string();
 
-    extract_shared_constants(&render_system_init_args.system_information.constant_values, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    extract_shared_constants(render_system_init_args.constant_values, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
     extract_shared_variables(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
 
+    if !render_system_init_args.frag_shader.layouts.is_empty()
+    {
+        extract_frag_layouts(&render_system_init_args.frag_shader, &mut dynamic_frag_shader, render_system_init_args.g_buffer_textures);
+    }
+
+    let shadow_map_binding_point = extract_textures(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+
+    if shadow_map_binding_point.is_some()
+    {
+        dynamic_frag_shader.functions += &generate_shadow_sampling(render_system_init_args.shadow_quality);
+    }
+
+    if render_system_init_args.damage_masks
+    {
+        dynamic_frag_shader.functions += DAMAGE_MASK_DECLARATIONS;
+    }
+
+    if render_system_init_args.vertex_shader.layout_info.iter().any(|x| matches!(x.data_type, LayoutType::Dissolve))
+    {
+        dynamic_frag_shader.functions += DISSOLVE_DECLARATIONS;
+    }
+
+    if render_system_init_args.tangent_space
+    {
+        let (vertex_functions, frag_functions) = generate_tangent_space(render_system_init_args.vertex_shader);
+        dynamic_vertex_shader.functions += &vertex_functions;
+        dynamic_frag_shader.functions += &frag_functions;
+    }
+
+    extract_uniforms(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    extract_light_storage_buffers(render_system_init_args.frag_shader, &mut dynamic_frag_shader, render_system_init_args.clustered_lighting);
+    extract_vertex_layouts(render_system_init_args.vertex_shader, &mut dynamic_vertex_shader);
+
+    let shaders = generate_shader_sources(render_system_init_args.vertex_shader, render_system_init_args.frag_shader, dynamic_vertex_shader, dynamic_frag_shader);
+    PreparedRenderPass{ shaders, shadow_map_binding_point }
+}
+
+/// Creates the resources required for the first render pass of the render system
+///
+/// `render_system_init_args` - structure holding the parameters required to create the OpenGL resources
+///                             used by the shader
+/// `prepared_pass` - the generated shaders of the first render pass
+/// `shared_texture_arrays` - the name each shared texture array is shared under, by its sampler name
+fn create_first_render_pass_resources(render_system_init_args: &RenderPassInitArgs, prepared_pass: PreparedRenderPass, shared_texture_arrays: &HashMap<String, String>) -> RenderPassResources
+{
     let deferred_rendering_fbo = if render_system_init_args.frag_shader.layouts.is_empty()
     {
         None
     }
     else
     {
-        Some(extract_frag_layouts(&render_system_init_args.frag_shader, &mut dynamic_frag_shader, render_system_init_args.g_buffer_textures))
+        Some(create_g_buffer_fbo(render_system_init_args.frag_shader))
     };
 
-    let shadow_map_binding_point = extract_textures(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
-
-    extract_uniforms(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    let light_storage_buffers = create_light_storage_buffers(render_system_init_args.frag_shader, render_system_init_args.clustered_lighting);
 
     let mut vao = VAO::new();
-    let vertex_shader_resource =    create_first_pass_vertex_resources(&render_system_init_args.vertex_shader, &mut vao, &mut dynamic_vertex_shader);
-    let fragment_shader_resource = extract_frag_texture_resources(&render_system_init_args.frag_shader);
-    let uniform_resources = create_padded_uniform_block(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader);
-    let shader_program = create_shader_program(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, dynamic_vertex_shader, dynamic_frag_shader);
+    let vertex_shader_resource =    create_first_pass_vertex_resources(render_system_init_args.vertex_shader, &mut vao);
+    let fragment_shader_resource = extract_frag_texture_resources(render_system_init_args.frag_shader, shared_texture_arrays);
+    let uniform_resources = create_padded_uniform_block(render_system_init_args.vertex_shader, render_system_init_args.frag_shader);
+    let shader_program = ShaderProgram::new(&prepared_pass.shaders).unwrap();
 
     RenderPassResources
     {
@@ -225,17 +540,17 @@ fn create_first_render_pass_resources(render_system_init_args: RenderPassInitArg
         vertex_shader_resource,
         fragment_shader_resource,
         uniform_resources,
-        uploaded_textures: HashMap::new(),
-        shadow_map_binding_point,
-        deferred_rendering_fbo
+        uploaded_textures: UploadedTextures::new(),
+        shadow_map_binding_point: prepared_pass.shadow_map_binding_point,
+        deferred_rendering_fbo,
+        light_storage_buffers
     }
 }
 
-/// Creates the resources required for the second render pass of the render system
+/// Generates the shaders of the second render pass of the render system
 ///
-/// `render_system_init_args` - structure holding the parameters required to generate a shader and
-///                             required OpenGL resources to use that shader
-fn create_second_render_pass_resources(render_system_init_args: RenderPassInitArgs) -> RenderPassResources
+/// `render_system_init_args` - structure holding the parameters required to generate a shader
+fn generate_second_render_pass_shaders(render_system_init_args: &mut RenderPassInitArgs) -> PreparedRenderPass
 {
     let mut dynamic_vertex_shader = DynamicVertexShaderGeneration::new(render_system_init_args.vertex_shader.write_generated_shader.clone());
     let mut dynamic_frag_shader = DynamicFragmentShaderGeneration::new(render_system_init_args.frag_shader.write_generated_shader.clone());
@@ -244,14 +559,14 @@ fn create_second_render_pass_resources(render_system_init_args: RenderPassInitAr
     dynamic_vertex_shader.glsl_version = render_system_init_args.vertex_shader.glsl_version.to_string();
     dynamic_frag_shader.glsl_version = render_system_init_args.frag_shader.glsl_version.to_string();
 
-    extract_shared_constants(&render_system_init_args.system_information.constant_values, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
-    extract_shared_variables(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    extract_shared_constants(render_system_init_args.constant_values, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    extract_shared_variables(render_system_init_args.vertex_shader, render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
 
-    let deferred_rendering_fbo = None;
     let shadow_map_binding_point = if render_system_init_args.frag_shader.include_shadow_maps
     {
         // Indexes start at 0, hence why number_layouts does not have a +1
         dynamic_frag_shader.layout += &format!("layout (binding = {}) uniform sampler2DArray shadowMaps;\n", render_system_init_args.g_buffer_textures.number_layouts);
+        dynamic_frag_shader.functions += &generate_shadow_sampling(render_system_init_args.shadow_quality);
         Some(render_system_init_args.g_buffer_textures.number_layouts)
     }
     else
@@ -259,13 +574,38 @@ fn create_second_render_pass_resources(render_system_init_args: RenderPassInitAr
         None
     };
 
-    extract_uniforms(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    extract_uniforms(render_system_init_args.vertex_shader, render_system_init_args.frag_shader, &mut dynamic_vertex_shader, &mut dynamic_frag_shader);
+    extract_light_storage_buffers(render_system_init_args.frag_shader, &mut dynamic_frag_shader, render_system_init_args.clustered_lighting);
+
+    if render_system_init_args.baked_lighting
+    {
+        dynamic_frag_shader.functions += BAKED_LIGHTING_DECLARATIONS;
+    }
+
+    if render_system_init_args.environment_lighting
+    {
+        dynamic_frag_shader.functions += ENVIRONMENT_LIGHTING_DECLARATIONS;
+    }
+
+    let shaders = generate_shader_sources(render_system_init_args.vertex_shader, render_system_init_args.frag_shader, dynamic_vertex_shader, dynamic_frag_shader);
+    PreparedRenderPass{ shaders, shadow_map_binding_point }
+}
+
+/// Creates the resources required for the second render pass of the render system
+///
+/// `render_system_init_args` - structure holding the parameters required to create the OpenGL resources
+///                             used by the shader
+/// `prepared_pass` - the generated shaders of the second render pass
+fn create_second_render_pass_resources(render_system_init_args: &RenderPassInitArgs, prepared_pass: PreparedRenderPass) -> RenderPassResources
+{
+    let light_storage_buffers = create_light_storage_buffers(render_system_init_args.frag_shader, render_system_init_args.clustered_lighting);
+
     let mut vao = VAO::new();
     let vertex_shader_resource =  create_second_pass_vertex_resources(&mut vao);
-    let fragment_shader_resource = extract_frag_texture_resources(&render_system_init_args.frag_shader);
+    let fragment_shader_resource = extract_frag_texture_resources(&render_system_init_args.frag_shader, &HashMap::new());
 
     let uniform_resources = create_padded_uniform_block(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader);
-    let shader_program = create_shader_program(&render_system_init_args.vertex_shader, &render_system_init_args.frag_shader, dynamic_vertex_shader, dynamic_frag_shader);
+    let shader_program = ShaderProgram::new(&prepared_pass.shaders).unwrap();
 
     RenderPassResources
     {
@@ -274,20 +614,20 @@ fn create_second_render_pass_resources(render_system_init_args: RenderPassInitAr
         vertex_shader_resource,
         fragment_shader_resource,
         uniform_resources,
-        uploaded_textures: HashMap::new(),
-        shadow_map_binding_point,
-        deferred_rendering_fbo
+        uploaded_textures: UploadedTextures::new(),
+        shadow_map_binding_point: prepared_pass.shadow_map_binding_point,
+        deferred_rendering_fbo: None,
+        light_storage_buffers
     }
 }
 
 /// Finds that required fragment layouts to write to the g-buffer
 ///
-/// `frag_info` - layout information to include in the generated shader as well as associated resources to create
+/// `frag_info` - layout information to include in the generated shader
 /// `dynamic_frag` - location to store the generated shader g-buffer layout code for the first render pass
 /// `g_buffer_textures` - location to store texture bindings of the g-buffer for the second pass
-fn extract_frag_layouts(frag_info: &FragmentShaderInformation, dynamic_frag: &mut DynamicFragmentShaderGeneration, g_buffer_textures: &mut GBufferLayouts) -> FBO
+fn extract_frag_layouts(frag_info: &FragmentShaderInformation, dynamic_frag: &mut DynamicFragmentShaderGeneration, g_buffer_textures: &mut GBufferLayouts)
 {
-    let mut colour_attachments = vec![];
     g_buffer_textures.number_layouts = frag_info.layouts.len() as u32;
 
     for (index, layout) in frag_info.layouts.iter().enumerate()
@@ -298,7 +638,18 @@ fn extract_frag_layouts(frag_info: &FragmentShaderInformation, dynamic_frag: &mu
         // texture bindings. Need to have the same number of layouts and bindings- same name for layout and
         // and bindings helps with readability
         g_buffer_textures.layouts += &format!("layout (binding = {}) uniform sampler2DArray {};\n", index, layout.name);
+    }
+}
 
+/// Creates the g-buffer the first render pass writes to
+///
+/// `frag_info` - the layouts of the first pass fragment shader, each of which is backed by a g-buffer texture
+fn create_g_buffer_fbo(frag_info: &FragmentShaderInformation) -> FBO
+{
+    let mut colour_attachments = vec![];
+
+    for layout in &frag_info.layouts
+    {
         let texture_information = TextureInformation
         {
             sampler_name: layout.name.clone(),
@@ -339,7 +690,8 @@ fn extract_frag_layouts(frag_info: &FragmentShaderInformation, dynamic_frag: &mu
 
 /// ******************* Shader Program Functions *************************
 
-/// Create a shader program from the given system information
+/// Creates the sources of the shaders of a shader program from the given system information. The
+/// shaders are not compiled, so no OpenGL calls are made
 ///
 /// `vertex_shader` - structure holding the location of the file that has the logic to append to the
 ///                     generated vertex shader inputs and outputs
@@ -347,8 +699,8 @@ fn extract_frag_layouts(frag_info: &FragmentShaderInformation, dynamic_frag: &mu
 ///                     generated fragment shader inputs and outputs
 /// `dynamic_vertex` - structure holding the generated shader source for the vertex shader
 /// `dynamic_frag` - structure holding the generated shader source for the vertex shader
-fn create_shader_program(vertex_shader_info: &VertexShaderInformation, frag_shader_info: &FragmentShaderInformation,
-                         dynamic_vertex: DynamicVertexShaderGeneration, dynamic_frag: DynamicFragmentShaderGeneration) -> ShaderProgram
+fn generate_shader_sources(vertex_shader_info: &VertexShaderInformation, frag_shader_info: &FragmentShaderInformation,
+                           dynamic_vertex: DynamicVertexShaderGeneration, dynamic_frag: DynamicFragmentShaderGeneration) -> Vec<ShaderInitInformation>
 {
     let mut shaders_init_information = Vec::new();
 
@@ -360,7 +712,7 @@ fn create_shader_program(vertex_shader_info: &VertexShaderInformation, frag_shad
     let fragment_init_info = ShaderInitInformation::from_file(gl::FRAGMENT_SHADER,fragment_shader_source, Some(dynamic_frag.to_string()), dynamic_frag.generated_name).unwrap();
     shaders_init_information.push(fragment_init_info);
 
-    ShaderProgram::new(&shaders_init_information).unwrap()
+    shaders_init_information
 }
 
 /// Creates the shader code to use constant variables
@@ -476,6 +828,173 @@ fn extract_textures(vertex_shader: &VertexShaderInformation, frag_shader: &Fragm
     shadow_map_binding_point
 }
 
+/// Generates the shadowFactor function, which returns how much of a fragment is in shadow from 0 (lit)
+/// to 1 (fully in shadow), filtering the shadow map according to the shadow quality
+///
+/// `shadow_quality` - how the shadow map should be filtered
+fn generate_shadow_sampling(shadow_quality: ShadowQuality) -> String
+{
+    // Reads outside of the shadow map are clamped to its edge, as the border would otherwise count
+    // towards the fragment being lit
+    let compare_fn = "float compareShadowDepth(vec2 coords, float fragDepth, int layer)\n\
+                      {\n\
+                      \treturn fragDepth > texture(shadowMaps, vec3(clamp(coords, 0.0, 1.0), layer)).r ? 1.0 : 0.0;\n\
+                      }\n";
+
+    let poisson_disc = "const vec2 POISSON_DISC[16] = vec2[](\n\
+                        \tvec2(-0.94201624, -0.39906216), vec2(0.94558609, -0.76890725), vec2(-0.09418410, -0.92938870), vec2(0.34495938, 0.29387760),\n\
+                        \tvec2(-0.91588581, 0.45771432), vec2(-0.81544232, -0.87912464), vec2(-0.38277543, 0.27676845), vec2(0.97484398, 0.75648379),\n\
+                        \tvec2(0.44323325, -0.97511554), vec2(0.53742981, -0.47373420), vec2(-0.26496911, -0.41893023), vec2(0.79197514, 0.19090188),\n\
+                        \tvec2(-0.24188840, 0.99706507), vec2(-0.81409955, 0.91437590), vec2(0.19984126, 0.78641367), vec2(0.14383161, -0.14100790));\n";
+
+    let grid_pcf = |radius: i32|
+        {
+            format!("float shadowFactor(vec3 projCoords, float fragDepth, int layer)\n\
+                     {{\n\
+                     \tvec2 texelSize = 1.0 / textureSize(shadowMaps, 0).xy;\n\
+                     \tfloat shadow = 0.0;\n\
+                     \tfor(int x = -{0}; x <= {0}; ++x)\n\
+                     \t{{\n\
+                     \t\tfor(int y = -{0}; y <= {0}; ++y)\n\
+                     \t\t{{\n\
+                     \t\t\tshadow += compareShadowDepth(projCoords.xy + vec2(x, y) * texelSize, fragDepth, layer);\n\
+                     \t\t}}\n\
+                     \t}}\n\
+                     \treturn shadow / {1}.0;\n\
+                     }}\n", radius, (radius * 2 + 1) * (radius * 2 + 1))
+        };
+
+    let shadow_fn = match shadow_quality
+    {
+        ShadowQuality::Hard =>
+            "float shadowFactor(vec3 projCoords, float fragDepth, int layer)\n\
+             {\n\
+             \treturn compareShadowDepth(projCoords.xy, fragDepth, layer);\n\
+             }\n".to_string(),
+        ShadowQuality::Pcf3x3 => grid_pcf(1),
+        ShadowQuality::Pcf5x5 => grid_pcf(2),
+        ShadowQuality::Poisson =>
+            poisson_disc.to_string() +
+            "float shadowFactor(vec3 projCoords, float fragDepth, int layer)\n\
+             {\n\
+             \tvec2 texelSize = 1.0 / textureSize(shadowMaps, 0).xy;\n\
+             \tfloat shadow = 0.0;\n\
+             \tfor(int i = 0; i < 16; ++i)\n\
+             \t{\n\
+             \t\tshadow += compareShadowDepth(projCoords.xy + POISSON_DISC[i] * texelSize * 2.0, fragDepth, layer);\n\
+             \t}\n\
+             \treturn shadow / 16.0;\n\
+             }\n",
+        ShadowQuality::Pcss { light_size } =>
+            {
+                // The blockers found around the fragment give the distance from the occluder to the fragment,
+                // which determines how wide the penumbra is
+                format!("{}const float PCSS_LIGHT_SIZE = {:?};\n\
+                          float shadowFactor(vec3 projCoords, float fragDepth, int layer)\n\
+                          {{\n\
+                          \tfloat searchRadius = PCSS_LIGHT_SIZE * fragDepth;\n\
+                          \tfloat blockerDepthSum = 0.0;\n\
+                          \tint numberBlockers = 0;\n\
+                          \tfor(int i = 0; i < 16; ++i)\n\
+                          \t{{\n\
+                          \t\tfloat sampleDepth = texture(shadowMaps, vec3(clamp(projCoords.xy + POISSON_DISC[i] * searchRadius, 0.0, 1.0), layer)).r;\n\
+                          \t\tif(sampleDepth < fragDepth)\n\
+                          \t\t{{\n\
+                          \t\t\tblockerDepthSum += sampleDepth;\n\
+                          \t\t\tnumberBlockers++;\n\
+                          \t\t}}\n\
+                          \t}}\n\
+                          \tif(numberBlockers == 0)\n\
+                          \t{{\n\
+                          \t\treturn 0.0;\n\
+                          \t}}\n\
+                          \tfloat averageBlockerDepth = blockerDepthSum / float(numberBlockers);\n\
+                          \tvec2 texelSize = 1.0 / textureSize(shadowMaps, 0).xy;\n\
+                          \tfloat penumbraWidth = (fragDepth - averageBlockerDepth) * PCSS_LIGHT_SIZE / max(averageBlockerDepth, 0.0001);\n\
+                          \tvec2 filterRadius = max(vec2(penumbraWidth), texelSize);\n\
+                          \tfloat shadow = 0.0;\n\
+                          \tfor(int i = 0; i < 16; ++i)\n\
+                          \t{{\n\
+                          \t\tshadow += compareShadowDepth(projCoords.xy + POISSON_DISC[i] * filterRadius, fragDepth, layer);\n\
+                          \t}}\n\
+                          \treturn shadow / 16.0;\n\
+                          }}\n", poisson_disc, light_size.max(0.0))
+            }
+    };
+
+    format!("{}{}", compare_fn, shadow_fn)
+}
+
+/// Generates the functions that give the first pass shaders the tangent space of a vertex. The vertex shader
+/// gets the calculateTangentBasis function, whose result should be written to tangentBasis. The fragment
+/// shader gets functions to bring normal maps and the view direction into and out of tangent space, as well
+/// as parallax occlusion mapping for height maps where white is the deepest point
+///
+/// `vertex_shader` - the first pass vertex shader, holding the tangent and bitangent layouts
+fn generate_tangent_space(vertex_shader: &VertexShaderInformation) -> (String, String)
+{
+    let layout_name = |is_layout: fn(&LayoutType) -> bool|
+        {
+            vertex_shader.layout_info.iter().find(|x| is_layout(&x.data_type)).map(|x| x.name.clone()).unwrap()
+        };
+
+    let tangent = layout_name(|x| matches!(x, LayoutType::Tangent));
+    let bitangent = layout_name(|x| matches!(x, LayoutType::Bitangent));
+
+    // The tangent is re-orthogonalised against the normal, as interpolation and non-uniform scaling leave
+    // them slightly off of perpendicular. The bitangent is only used for the handedness of the basis, so
+    // that mirrored texture coordinates still sample normal maps the right way around
+    let vertex_functions = format!("#define TANGENT_SPACE\n\
+                                    out mat3 tangentBasis;\n\
+                                    mat3 calculateTangentBasis(mat4 modelMatrix, vec3 vertexNormal)\n\
+                                    {{\n\
+                                    \tvec3 normal = normalize(transpose(inverse(mat3(modelMatrix))) * vertexNormal);\n\
+                                    \tvec3 tangent = normalize(mat3(modelMatrix) * {0});\n\
+                                    \ttangent = normalize(tangent - dot(tangent, normal) * normal);\n\
+                                    \tvec3 bitangent = cross(normal, tangent);\n\
+                                    \tfloat handedness = dot(bitangent, mat3(modelMatrix) * {1}) < 0.0 ? -1.0 : 1.0;\n\
+                                    \treturn mat3(tangent, bitangent * handedness, normal);\n\
+                                    }}\n", tangent, bitangent);
+
+    // The texture gradients are taken before the loop, as derivatives are undefined inside non-uniform
+    // control flow. The final coordinates are interpolated between the layers either side of the surface
+    let frag_functions = "#define TANGENT_SPACE\n\
+                          in mat3 tangentBasis;\n\
+                          vec3 tangentSpaceNormal(vec3 mapColour)\n\
+                          {\n\
+                          \treturn normalize(tangentBasis * (mapColour * 2.0 - 1.0));\n\
+                          }\n\
+                          vec3 tangentSpaceViewDirection(vec3 fragPosition, vec3 viewPosition)\n\
+                          {\n\
+                          \treturn normalize(transpose(tangentBasis) * (viewPosition - fragPosition));\n\
+                          }\n\
+                          vec2 parallaxMapping(sampler2DArray heightMap, float layer, vec2 texCoords, vec3 viewDirection, float heightScale)\n\
+                          {\n\
+                          \tvec2 dx = dFdx(texCoords);\n\
+                          \tvec2 dy = dFdy(texCoords);\n\
+                          \tfloat numberLayers = mix(32.0, 8.0, abs(viewDirection.z));\n\
+                          \tfloat layerDepth = 1.0 / numberLayers;\n\
+                          \tvec2 deltaTexCoords = viewDirection.xy / max(viewDirection.z, 0.05) * heightScale / numberLayers;\n\
+                          \tvec2 currentTexCoords = texCoords;\n\
+                          \tfloat currentDepth = textureGrad(heightMap, vec3(currentTexCoords, layer), dx, dy).r;\n\
+                          \tfloat currentLayerDepth = 0.0;\n\
+                          \tfor(int i = 0; i < 32 && currentLayerDepth < currentDepth; ++i)\n\
+                          \t{\n\
+                          \t\tcurrentTexCoords -= deltaTexCoords;\n\
+                          \t\tcurrentDepth = textureGrad(heightMap, vec3(currentTexCoords, layer), dx, dy).r;\n\
+                          \t\tcurrentLayerDepth += layerDepth;\n\
+                          \t}\n\
+                          \tvec2 previousTexCoords = currentTexCoords + deltaTexCoords;\n\
+                          \tfloat afterDepth = currentDepth - currentLayerDepth;\n\
+                          \tfloat beforeDepth = textureGrad(heightMap, vec3(previousTexCoords, layer), dx, dy).r - currentLayerDepth + layerDepth;\n\
+                          \tfloat denominator = afterDepth - beforeDepth;\n\
+                          \tfloat weight = denominator < 0.0 ? afterDepth / denominator : 0.0;\n\
+                          \treturn mix(currentTexCoords, previousTexCoords, weight);\n\
+                          }\n".to_string();
+
+    (vertex_functions, frag_functions)
+}
+
 /// Generates the shader code to use uniforms and put them in a uniform block
 ///
 /// `vertex_shader` - structure containing the uniform variables for the vertex shader of a render pass
@@ -518,15 +1037,48 @@ fn extract_uniforms(vertex_shader_uniforms: &VertexShaderInformation, frag_shade
     add_uniforms(&frag_shader_uniforms.uniforms, &mut dynamic_frag.uniforms);
 }
 
+/// Generates the shader code to use the light storage buffers, if the fragment shader requested them
+///
+/// `frag_shader` - structure stating if the fragment shader of a render pass uses light storage buffers
+/// `dynamic_frag` - location to store generated shader code for the light storage buffers
+/// `clustered_lighting` - true if the lights are also binned into clusters of the view frustum
+fn extract_light_storage_buffers(frag_shader: &FragmentShaderInformation, dynamic_frag: &mut DynamicFragmentShaderGeneration, clustered_lighting: bool)
+{
+    if !frag_shader.include_light_storage_buffers
+    {
+        return;
+    }
+
+    dynamic_frag.uniforms += LIGHT_STORAGE_DECLARATIONS;
+
+    if clustered_lighting
+    {
+        dynamic_frag.uniforms += "\n";
+        dynamic_frag.uniforms += LIGHT_CLUSTER_DECLARATIONS;
+    }
+}
+
+/// Creates the light storage buffers, if the fragment shader requested them
+///
+/// `frag_shader` - structure stating if the fragment shader of a render pass uses light storage buffers
+/// `clustered_lighting` - true if the lights are also binned into clusters of the view frustum
+fn create_light_storage_buffers(frag_shader: &FragmentShaderInformation, clustered_lighting: bool) -> Option<LightStorageBuffers>
+{
+    if !frag_shader.include_light_storage_buffers
+    {
+        return None;
+    }
+
+    Some(LightStorageBuffers::new(3, clustered_lighting))
+}
+
 /// *********** Vertex Shader Related Functions ***************
 
-/// Stores the information required to write generated code for shader layouts and to create
-/// backing buffers for those layouts
+/// Stores the information required to create backing buffers for shader layouts
 pub struct LayoutBindingInformation
 {
     pub binding_info: Vec<BindingInformation>,
     pub num_layouts_used: u32,
-    pub glsl_type: String,
 }
 
 /// Create the required OpenGL resources for the second-pass vertex shader
@@ -589,11 +1141,30 @@ fn create_second_pass_vertex_resources(vao: &mut VAO) -> VertexShaderResources
     {
         indice_buffer: Some(indices_buffer),
         per_model_buffers: vec![vertices_buffer, texcoord_buffer],
-        per_instance_buffers: vec![],
+        instance_upload_ring: InstanceUploadRing::new(Vec::new(), 0),
         layout_update_fn: None,
         model_update_fn: second_pass_update_fn,
         model_layout_indexes: vec![],
         instance_layout_indexes: vec![],
+        instance_params_layout_index: None,
+        dissolve_layout_index: None,
+    }
+}
+
+/// Generates the shader code declaring the layouts of the first-pass vertex shader
+///
+/// `vertex_shader` - the structure containing the layout information for the vertex shader
+/// `dynamic_vertex` - structure to store the generated shader code the layouts in the vertex shader
+fn extract_vertex_layouts(vertex_shader: &VertexShaderInformation, dynamic_vertex: &mut DynamicVertexShaderGeneration)
+{
+    let mut layout_index = 0;
+
+    for layout_info in vertex_shader.layout_info.iter()
+    {
+        let (glsl_type, num_layouts_used) = layout_glsl_information(layout_info.data_type);
+        dynamic_vertex.layout += &format!("layout (location = {}) in {} {};\n", layout_index, glsl_type, layout_info.name);
+
+        layout_index += num_layouts_used;
     }
 }
 
@@ -601,14 +1172,16 @@ fn create_second_pass_vertex_resources(vao: &mut VAO) -> VertexShaderResources
 ///
 /// `vertex_shader` - the structure containing the layout information for the vertex shader
 /// `vao` - the VAO that is to be used for the first-pass rendering
-/// `dynamic_vertex` - structure to store the generated shader code the layouts in the vertex shader
-fn create_first_pass_vertex_resources(vertex_shader: &VertexShaderInformation, vao: &mut VAO, dynamic_vertex: &mut DynamicVertexShaderGeneration) -> VertexShaderResources
+fn create_first_pass_vertex_resources(vertex_shader: &VertexShaderInformation, vao: &mut VAO) -> VertexShaderResources
 {
     let mut per_model_buffers = Vec::new();
-    let mut per_instance_buffers = Vec::new();
+    let mut per_instance_layouts = Vec::new();
+    let mut number_instance_frames = 0;
 
     let mut model_layout_indexes = Vec::new();
     let mut instance_layout_indexes = Vec::new();
+    let mut instance_params_layout_index = None;
+    let mut dissolve_layout_index = None;
 
     let mut layout_index = 0;
 
@@ -616,13 +1189,10 @@ fn create_first_pass_vertex_resources(vertex_shader: &VertexShaderInformation, v
     {
         let layout_binding_info = create_layout_binding_information(layout_info.data_type, layout_index, vao);
 
-        let mapped_buffer = match layout_info.instance
+        let (number_buffers, size_buffer_bytes) = match layout_info.instance
         {
-            LayoutInstance::Divisor0(number_buffers, size_buffer_bytes) =>
-                {
-                    // By default all layouts defined are Divisor0, so no need to explicitly set layout divisor
-                    MappedBuffer::new(size_buffer_bytes, BufferType::NonIndiceArray(layout_binding_info.binding_info), number_buffers)
-                },
+            // By default all layouts defined are Divisor0, so no need to explicitly set layout divisor
+            LayoutInstance::Divisor0(number_buffers, size_buffer_bytes) => (number_buffers, size_buffer_bytes),
             LayoutInstance::Divisor1(number_buffers, size_buffer_bytes) =>
                 {
                     for count in 0..layout_binding_info.num_layouts_used
@@ -630,26 +1200,36 @@ fn create_first_pass_vertex_resources(vertex_shader: &VertexShaderInformation, v
                         vao.specify_layout_divisor(layout_index + count, 1);
                     }
 
-                    MappedBuffer::new(size_buffer_bytes, BufferType::NonIndiceArray(layout_binding_info.binding_info), number_buffers)
+                    (number_buffers, size_buffer_bytes)
                 }
         };
 
+        let buffer_type = BufferType::NonIndiceArray(layout_binding_info.binding_info);
+
         match layout_info.layout_use
         {
             LayoutUse::PerModel =>
                 {
                     model_layout_indexes.push(layout_index);
-                    per_model_buffers.push(mapped_buffer);
+                    per_model_buffers.push(MappedBuffer::new(size_buffer_bytes, buffer_type, number_buffers));
                 },
             LayoutUse::PerInstance =>
                 {
+                    match layout_info.data_type
+                    {
+                        LayoutType::InstanceParams => instance_params_layout_index = Some(layout_index),
+                        LayoutType::Dissolve => dissolve_layout_index = Some(layout_index),
+                        _ => {}
+                    }
+
+                    // Every per-instance layout is written in the same frame of the ring, so the ring holds the
+                    // most buffers requested by any of them
                     instance_layout_indexes.push(layout_index);
-                    per_instance_buffers.push(mapped_buffer);
+                    per_instance_layouts.push((size_buffer_bytes, buffer_type));
+                    number_instance_frames = number_instance_frames.max(number_buffers);
                 }
         }
 
-        dynamic_vertex.layout += &format!("layout (location = {}) in {} {};\n", layout_index, layout_binding_info.glsl_type, layout_info.name);
-
         layout_index += layout_binding_info.num_layouts_used;
     }
 
@@ -666,11 +1246,13 @@ fn create_first_pass_vertex_resources(vertex_shader: &VertexShaderInformation, v
     VertexShaderResources
     {
         per_model_buffers,
-        per_instance_buffers,
+        instance_upload_ring: InstanceUploadRing::new(per_instance_layouts, number_instance_frames),
         layout_update_fn: vertex_shader.instance_layout_update_fn,
         model_update_fn: vertex_shader.model_layout_update_fn,
         model_layout_indexes,
         instance_layout_indexes,
+        instance_params_layout_index,
+        dissolve_layout_index,
         indice_buffer,
     }
 }
@@ -692,24 +1274,22 @@ pub fn create_layout_binding_information(layout: LayoutType, index: u32, vao: &m
 
     return match layout
     {
-        LayoutType::Vec3Float =>
+        LayoutType::Vec3Float | LayoutType::Tangent | LayoutType::Bitangent =>
             {
                 vao.specify_layout_format(index, 3, gl::FLOAT, 0);
                 LayoutBindingInformation
                 {
                     binding_info: vec![BindingInformation::new(index, 0, (size_of::<TVec3<f32>>()) as i32)],
-                    num_layouts_used: 1,
-                    glsl_type: "vec3".to_string()
+                    num_layouts_used: 1
                 }
             },
-        LayoutType::Vec4Float =>
+        LayoutType::Vec4Float | LayoutType::InstanceParams | LayoutType::Dissolve =>
             {
                 vao.specify_layout_format(index, 4, gl::FLOAT, 0);
                 LayoutBindingInformation
                 {
                     binding_info: vec![BindingInformation::new(index, 0, (size_of::<TVec4<f32>>()) as i32)],
-                    num_layouts_used: 1,
-                    glsl_type: "vec4".to_string()
+                    num_layouts_used: 1
                 }
             },
         LayoutType::Vec4Uint =>
@@ -718,8 +1298,7 @@ pub fn create_layout_binding_information(layout: LayoutType, index: u32, vao: &m
                 LayoutBindingInformation
                 {
                     binding_info: vec![BindingInformation::new(index, 0, (size_of::<TVec4<u32>>()) as i32)],
-                    num_layouts_used: 1,
-                    glsl_type: "uvec4".to_string()
+                    num_layouts_used: 1
                 }
             }
         LayoutType::Mat4x4Float =>
@@ -740,19 +1319,79 @@ pub fn create_layout_binding_information(layout: LayoutType, index: u32, vao: &m
                         BindingInformation::new(index + 2, 0, size_mat4),
                         BindingInformation::new(index + 3, 0, size_mat4)
                     ],
-                    num_layouts_used: 4,
-                    glsl_type: "mat4".to_string(),
+                    num_layouts_used: 4
                 }
             },
     }
 }
 
+/// Finds the GLSL type of a layout and the number of layout locations it uses, matching the binding
+/// information created for it
+///
+/// `layout` - the data that is to be represented by the layout
+fn layout_glsl_information(layout: LayoutType) -> (&'static str, u32)
+{
+    match layout
+    {
+        LayoutType::Vec3Float | LayoutType::Tangent | LayoutType::Bitangent => ("vec3", 1),
+        LayoutType::Vec4Float | LayoutType::InstanceParams | LayoutType::Dissolve => ("vec4", 1),
+        LayoutType::Vec4Uint => ("uvec4", 1),
+        LayoutType::Mat4x4Float => ("mat4", 4),
+    }
+}
+
+/// Finds the size in bytes of a layout for one vertex or instance, matching the binding information
+/// created for it
+///
+/// `layout` - the data that is to be represented by the layout
+fn layout_stride(layout: LayoutType) -> u32
+{
+    match layout
+    {
+        LayoutType::Vec3Float | LayoutType::Tangent | LayoutType::Bitangent => size_of::<TVec3<f32>>() as u32,
+        LayoutType::Vec4Float | LayoutType::InstanceParams | LayoutType::Dissolve => size_of::<TVec4<f32>>() as u32,
+        LayoutType::Vec4Uint => size_of::<TVec4<u32>>() as u32,
+        LayoutType::Mat4x4Float => size_of::<TMat4<f32>>() as u32,
+    }
+}
+
+/// Describes the per-instance layouts of the vertex shader, in the order their buffers are created, so
+/// that their instances can be copied on the GPU
+///
+/// `vertex_shader` - the structure containing the layout information for the vertex shader
+fn describe_instance_layouts(vertex_shader: &VertexShaderInformation) -> Vec<InstanceLayoutDescription>
+{
+    let mut descriptions = Vec::new();
+    let mut layout_index = 0;
+
+    for layout_info in vertex_shader.layout_info.iter()
+    {
+        let (_, number_locations) = layout_glsl_information(layout_info.data_type);
+
+        if let (LayoutUse::PerInstance, LayoutInstance::Divisor1(_, size_buffer_bytes) | LayoutInstance::Divisor0(_, size_buffer_bytes)) = (layout_info.layout_use, layout_info.instance)
+        {
+            descriptions.push(InstanceLayoutDescription
+            {
+                layout_index,
+                number_locations,
+                stride: layout_stride(layout_info.data_type),
+                capacity: size_buffer_bytes
+            });
+        }
+
+        layout_index += number_locations;
+    }
+
+    descriptions
+}
+
 /// ************ Fragment Shader Related Functions ****************
 
 /// Creates texture resources for the texture specified for the fragment shader
 ///
 /// `frag_shader` - the structure holding the texture information for the fragment shader (either first or second pass)
-fn extract_frag_texture_resources(frag_shader: &FragmentShaderInformation) -> FragmentShaderResources
+/// `shared_texture_arrays` - the name each shared texture array is shared under, by its sampler name
+fn extract_frag_texture_resources(frag_shader: &FragmentShaderInformation, shared_texture_arrays: &HashMap<String, String>) -> FragmentShaderResources
 {
     let adjust_binding_points_shadows = if frag_shader.include_shadow_maps
     {
@@ -763,7 +1402,7 @@ fn extract_frag_texture_resources(frag_shader: &FragmentShaderInformation) -> Fr
         0
     };
 
-    let (texture_arrays, texture_lookup) = create_texture_array(frag_shader, adjust_binding_points_shadows);
+    let (texture_arrays, texture_lookup) = create_texture_array(frag_shader, adjust_binding_points_shadows, shared_texture_arrays);
     let cube_maps = create_cubemaps(frag_shader, adjust_binding_points_shadows + texture_arrays.len() as u32);
 
     FragmentShaderResources { texture_arrays, texture_lookup, cube_maps }
@@ -774,7 +1413,8 @@ fn extract_frag_texture_resources(frag_shader: &FragmentShaderInformation) -> Fr
 /// `frag_shader` - the structure containing the texture array information for the fragment shader
 /// `starting_layout_index` - starting index for texture arrays; if shadow maps are being included
 ///                             in the fragment shader then texture array indexes need to be changed
-fn create_texture_array(frag_shader: &FragmentShaderInformation, starting_layout_index: u32) -> (Vec<TextureArray>, HashMap<String, TextureArrayIndex>)
+/// `shared_texture_arrays` - the name each shared texture array is shared under, by its sampler name
+fn create_texture_array(frag_shader: &FragmentShaderInformation, starting_layout_index: u32, shared_texture_arrays: &HashMap<String, String>) -> (Vec<TextureArray>, HashMap<String, TextureArrayIndex>)
 {
     let mut texture_arrays = Vec::new();
     let mut texture_array_lookup = HashMap::new();
@@ -814,7 +1454,12 @@ fn create_texture_array(frag_shader: &FragmentShaderInformation, starting_layout
 
         // Binding point must match that in the shader. Remember that binding point used in the code is implicit- it is based off of the
         // index of the current texture array of all texture arrays defined
-        let mut texture_array = TextureArray::new(texture_info.clone(), 1, starting_layout_index + texture_arrays.len() as u32);
+        let binding_point = starting_layout_index + texture_arrays.len() as u32;
+        let mut texture_array = match shared_texture_arrays.get(&texture_info.sampler_name)
+        {
+            Some(shared_name) => share_texture_array(shared_name, texture_info, binding_point),
+            None => TextureArray::new(texture_info.clone(), 1, binding_point)
+        };
         texture_array.bind_texture_to_texture_unit();
         texture_arrays.push(texture_array);
     }
@@ -889,6 +1534,8 @@ fn create_padded_uniform_block(vertex_shader_uniforms: &VertexShaderInformation,
     ecs.register_type::<UniformMat4Array>();
     ecs.register_type::<UniformUIntArray>();
     let mut uniform_entities  = HashMap::new();
+    let mut uniform_types = HashMap::new();
+    let mut cpu_values = Vec::new();
 
     let alignment_scalar = 4;
     let alignment_mat4x4_float = 16;
@@ -1108,6 +1755,7 @@ fn create_padded_uniform_block(vertex_shader_uniforms: &VertexShaderInformation,
             };
             // All uniforms should have a unique name
             assert!(uniform_location_map.insert(uniform.name.clone(), uniform_data_location).is_none());
+            uniform_types.insert(uniform.name.clone(), uniform.uniform_type);
 
             // Now actually reserve space for the uniform in the array
             uniform_buffer_size += size_uniform;
@@ -1117,6 +1765,7 @@ fn create_padded_uniform_block(vertex_shader_uniforms: &VertexShaderInformation,
         // rather than keeping that information in the buffer itself
         let mapped_buffer = MappedBuffer::new(uniform_buffer_size as isize, BufferType::UniformBufferArray(mapped_buffers.len() as u32), uniform_block.number_buffers as usize);
         mapped_buffers.push(mapped_buffer);
+        cpu_values.push(vec![0; uniform_buffer_size]);
     }
 
     UniformResources
@@ -1125,7 +1774,9 @@ fn create_padded_uniform_block(vertex_shader_uniforms: &VertexShaderInformation,
         uniform_location_map,
         uniform_type_ids,
         uniform_entities,
-        ecs
+        ecs,
+        uniform_types,
+        cpu_values
     }
 }
 
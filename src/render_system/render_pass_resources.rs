@@ -1,15 +1,19 @@
 use std::path::PathBuf;
 use hashbrown::HashMap;
+use crate::exports::uniform_inspector::UniformValue;
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
 use crate::render_components::cubemap::CubeMap;
 use crate::render_components::frame_buffer::FBO;
+use crate::render_components::instance_upload_ring::InstanceUploadRing;
+use crate::render_components::light_storage_buffer::LightStorageBuffers;
 use crate::render_components::mapped_buffer::MappedBuffer;
 use crate::render_components::shader_program::ShaderProgram;
 use crate::render_components::texture_array::TextureArray;
 use crate::render_components::vao::VAO;
 use crate::render_system::initialize_logic::{ExpectedUniformData, UniformDataLocation};
 use crate::render_system::render_system::{ModelUpdateFunction, UploadedTextureLocation};
+use crate::render_system::system_information::UniformType;
 
 /// Holds the variables required to execute a first or second render pass
 pub struct RenderPassResources
@@ -19,9 +23,20 @@ pub struct RenderPassResources
     pub vertex_shader_resource: VertexShaderResources,
     pub fragment_shader_resource: FragmentShaderResources,
     pub uniform_resources: UniformResources,
-    pub uploaded_textures: HashMap<PathBuf, UploadedTextureLocation>,
+    pub uploaded_textures: UploadedTextures,
     pub shadow_map_binding_point: Option<u32>,
     pub deferred_rendering_fbo: Option<FBO>,
+    pub light_storage_buffers: Option<LightStorageBuffers>,
+}
+
+/// Keeps track of the textures uploaded to the texture arrays. A texture used by several models is only
+/// uploaded once, and the layer holding it is freed once no model uses it
+pub struct UploadedTextures
+{
+    pub locations: HashMap<PathBuf, UploadedTextureLocation>,
+    pub contents: HashMap<u64, UploadedTextureLocation>,
+    pub solid_colours: HashMap<[u8; 4], UploadedTextureLocation>,
+    pub reference_counts: HashMap<(usize, i32), u32>,
 }
 
 /// Holds information about updating vertex layouts
@@ -29,11 +44,13 @@ pub struct VertexShaderResources
 {
     pub indice_buffer: Option<MappedBuffer>,
     pub per_model_buffers: Vec<MappedBuffer>,
-    pub per_instance_buffers: Vec<MappedBuffer>,
+    pub instance_upload_ring: InstanceUploadRing,
     pub layout_update_fn: Option<fn(u32, &ECS, &mut Vec<u8>, EntityId)>,
     pub model_update_fn: ModelUpdateFunction,
     pub model_layout_indexes: Vec<u32>,
     pub instance_layout_indexes: Vec<u32>,
+    pub instance_params_layout_index: Option<u32>,
+    pub dissolve_layout_index: Option<u32>,
 }
 
 /// Holds information about updating textures
@@ -52,6 +69,9 @@ pub struct UniformResources
     pub uniform_type_ids: HashMap<String, ExpectedUniformData>,
     pub uniform_entities: HashMap<String, EntityId>,
     pub ecs: ECS,
+    pub uniform_types: HashMap<String, UniformType>,
+    // Copy of what was last written to each mapped buffer, which are only writeable by the CPU
+    pub cpu_values: Vec<Vec<u8>>,
 }
 
 /// Passed into uniform update function to write updated values for uniforms
@@ -62,4 +82,60 @@ pub struct UniformBufferInformation<'a>
     pub buffers: &'a mut Vec<MappedBuffer>,
     pub buffers_to_flush: Vec<usize>,
     pub buffers_to_fence: Vec<usize>,
+    pub cpu_values: &'a mut Vec<Vec<u8>>,
+    pub overrides: &'a HashMap<String, UniformValue>,
+}
+
+impl UploadedTextures
+{
+    /// Creates a structure without any uploaded textures
+    pub fn new() -> UploadedTextures
+    {
+        UploadedTextures
+        {
+            locations: HashMap::new(),
+            contents: HashMap::new(),
+            solid_colours: HashMap::new(),
+            reference_counts: HashMap::new(),
+        }
+    }
+
+    /// Adds a reference to the uploaded texture, preventing its layer from being freed
+    ///
+    /// `texture` - the location of the texture being referenced
+    pub fn add_reference(&mut self, texture: UploadedTextureLocation)
+    {
+        *self.reference_counts.entry((texture.array_index, texture.index_offset)).or_insert(0) += 1;
+    }
+
+    /// Removes a reference to the uploaded texture. If this was the last reference, the texture is forgotten
+    /// and true is returned, meaning the layer holding the texture can be freed
+    ///
+    /// `texture` - the location of the texture no longer being referenced
+    pub fn remove_reference(&mut self, texture: UploadedTextureLocation) -> bool
+    {
+        let key = (texture.array_index, texture.index_offset);
+
+        let reference_count = match self.reference_counts.get_mut(&key)
+        {
+            Some(i) => i,
+            None => return false
+        };
+
+        *reference_count -= 1;
+
+        if *reference_count != 0
+        {
+            return false;
+        }
+
+        self.reference_counts.remove(&key);
+
+        let is_other_texture = |x: &UploadedTextureLocation| x.array_index != texture.array_index || x.index_offset != texture.index_offset;
+        self.locations.retain(|_, x| is_other_texture(x));
+        self.contents.retain(|_, x| is_other_texture(x));
+        self.solid_colours.retain(|_, x| is_other_texture(x));
+
+        true
+    }
 }
\ No newline at end of file
@@ -1,24 +1,40 @@
+use std::ffi::CString;
 use std::mem;
 use std::path::PathBuf;
 use hashbrown::{HashMap, HashSet};
 use nalgebra_glm::{TMat4, TVec3, TVec4, vec3, vec4};
 use serde::{Deserialize, Serialize};
-use crate::exports::light_components::{FindLightType, LightInformation};
-use crate::exports::load_models::MaxNumLights;
-use crate::exports::movement_components::Position;
+use tracing::warn;
+use crate::exports::ambient_overrides::{AmbientSettings, blend_ambient_overrides};
+use crate::exports::frame_statistics::{record_draw_calls, RenderSystemGpuTimings};
+use crate::exports::light_components::{AreaLight, AreaLightShape, Emissive, FindLightType, LightInformation};
+use crate::exports::load_models::{DebugRenderMode, DrawFunctionSlot, MaxNumLights};
+use crate::exports::movement_components::{Position, TransformationMatrix};
 use crate::exports::rendering::{DrawBuilderSystem, DrawParam, LevelOfView};
+use crate::exports::uniform_inspector::UniformValue;
+use crate::exports::world_precision::{render_space_light_matrix, render_space_position};
 use crate::flows::render_flow::ModelRenderingInformation;
 use crate::flows::shadow_flow;
 use crate::models::model_definitions::{MeshGeometry, ModelId};
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
+use crate::render_components::debug_view_pass::DebugViewPass;
 use crate::render_components::frame_buffer::{BindingTarget, FBO};
+use crate::render_components::gpu_level_of_view_pass::{GpuLevelOfViewPass, InstanceCulling, LevelOfViewGroup};
+use crate::render_components::gpu_timer::GpuTimer;
+use crate::render_components::hi_z_buffer::HiZBuffer;
+use crate::render_components::indirect_draw_buffer::IndirectDrawBuffer;
+use crate::render_components::light_clusters::LightVolume;
+use crate::render_components::light_storage_buffer::{DirectionLightStorage, LightStorageBuffer, LightStorageBuffers, PointLightStorage, SPOT_LIGHT_SHAPE_DISK, SPOT_LIGHT_SHAPE_POINT, SPOT_LIGHT_SHAPE_RECTANGLE, SPOT_LIGHT_SHAPE_SPHERE, SpotLightStorage};
 use crate::render_components::mapped_buffer::BufferWriteInfo;
-use crate::render_components::texture_array::{TextureProperties, TextureUploadResult};
+use crate::render_components::screen_space_reflection_pass::{ReflectionView, ScreenSpaceReflectionPass};
+use crate::render_components::texture_array::{TextureContents, TextureProperties, TextureUploadResult};
 use crate::render_system::helper_constructs::NO_SUITABLE_TEXTURE_STORAGE_INDEX;
+use crate::render_system::initialize_logic::{rebuild_second_render_pass_resources, SecondPassRebuildInformation};
 use crate::render_system::render_pass_resources::{RenderPassResources, UniformBufferInformation};
-use crate::render_system::system_information::DrawPreparationParameters;
+use crate::render_system::system_information::{DrawFunction, DrawPreparationParameters, OutputFramebuffer};
 use crate::world::bounding_box_tree_v2::UniqueWorldSectionId;
+use crate::world::bounding_volumes::aabb::StaticAABB;
 
 /// ************* Helper Aliases *****************
 
@@ -51,6 +67,21 @@ pub struct ModelNameLookupResult
 
 const LIT_SOURCE_STENCIL_VALUE: i32 = 0xFF;
 
+/// Factor applied to the significance of lights rendered in the previous frame when choosing which lights to render
+const PREVIOUS_LIGHT_SIGNIFICANCE_FACTOR: f32 = 1.1;
+
+/// Draws the edges of triangles rather than filling them if the wireframe debug render mode is used
+///
+/// `debug_render_mode` - the debug render mode of the render system drawing
+/// `enabled` - true if the triangles drawn next should be drawn as their edges
+fn set_wireframe(debug_render_mode: DebugRenderMode, enabled: bool)
+{
+    if debug_render_mode == DebugRenderMode::Wireframe
+    {
+        unsafe{ gl::PolygonMode(gl::FRONT_AND_BACK, if enabled { gl::LINE } else { gl::FILL }); }
+    }
+}
+
 /// ************* Main Structure and Logic ***************
 
 /// Structure that contains that required parameters to execute a render pass
@@ -58,9 +89,9 @@ pub struct RenderSystem
 {
     first_render_pass_resources: RenderPassResources,
     second_render_pass_resources: Option<RenderPassResources>,
-    draw_function: fn(&mut DrawParam),
-    light_source_draw_function: fn(&mut DrawParam),
-    transparency_draw_function: fn(&mut DrawParam),
+    draw_function: DrawFunction,
+    light_source_draw_function: DrawFunction,
+    transparency_draw_function: DrawFunction,
     pub model_rendering_information: HashMap<ModelId, ModelRenderingInformation>,
     name_model_id_lookup: HashMap<String, ModelNameLookupResult>,
     model_id_name_lookup: HashMap<ModelId, String>,
@@ -75,6 +106,20 @@ pub struct RenderSystem
     previous_spot_lights: HashSet<EntityId>,
     no_light_source_cutoff: f32,
     default_diffuse_factor: f32,
+    second_pass_rebuild_information: Option<SecondPassRebuildInformation>,
+    material_maps: bool,
+    gpu_level_of_views: Option<GpuLevelOfViewPass>,
+    screen_space_reflections: Option<ScreenSpaceReflectionPass>,
+    level_of_view_groups: Vec<LevelOfViewGroup>,
+    level_of_view_positions: Vec<TVec4<f32>>,
+    hi_z_buffer: Option<HiZBuffer>,
+    uniform_overrides: HashMap<String, UniformValue>,
+    debug_render_mode: DebugRenderMode,
+    debug_view: Option<DebugViewPass>,
+    debug_mipmap_level_location: i32,
+    first_pass_timer: GpuTimer,
+    second_pass_timer: GpuTimer,
+    indirect_draw_buffer: IndirectDrawBuffer,
 }
 
 /// Specifies the location of an uploaded texture, as well as any scaling of the texture coordinates
@@ -98,17 +143,27 @@ impl RenderSystem
     /// `level_of_views` - the default level of views for the render system
     /// `draw_fn_accessible_fbo` - FBOs that can be bound by referring to their name
     /// `upload_local_lights` - boolean stating whether to use lights and therefore shadows
+    /// `second_pass_rebuild_information` - information to regenerate the second render pass, if there is one
+    /// `material_maps` - true if the shaders read all of the material maps of models, rather than only diffuse maps
     pub fn new(first_render_pass_resources: RenderPassResources, second_render_pass_resources: Option<RenderPassResources>,
-               draw_function: fn(&mut DrawParam),
-               light_source_draw_function: fn(&mut DrawParam),
-               transparency_draw_function: fn(&mut DrawParam),
+               draw_function: DrawFunction,
+               light_source_draw_function: DrawFunction,
+               transparency_draw_function: DrawFunction,
                level_of_views: Vec<LevelOfView>,
                draw_fn_accessible_fbo: HashMap<String, FBO>,
                upload_local_lights: bool,
                max_light_constraints: MaxNumLights,
                no_light_source_cutoff: f32,
-               default_diffuse_factor: f32) -> RenderSystem
+               default_diffuse_factor: f32,
+               second_pass_rebuild_information: Option<SecondPassRebuildInformation>,
+               material_maps: bool) -> RenderSystem
     {
+        let debug_mipmap_level_location = unsafe
+            {
+                let uniform_name = CString::new("debugMipmapLevel").unwrap();
+                gl::GetUniformLocation(first_render_pass_resources.shader_program.shader_program, uniform_name.as_ptr())
+            };
+
         RenderSystem
         {
             first_render_pass_resources,
@@ -128,10 +183,230 @@ impl RenderSystem
             previous_spot_lights: HashSet::new(),
             no_light_source_cutoff,
             previous_point_lights: HashSet::new(),
-            default_diffuse_factor
+            default_diffuse_factor,
+            second_pass_rebuild_information,
+            material_maps,
+            gpu_level_of_views: None,
+            screen_space_reflections: None,
+            level_of_view_groups: Vec::new(),
+            level_of_view_positions: Vec::new(),
+            hi_z_buffer: None,
+            uniform_overrides: HashMap::default(),
+            debug_render_mode: DebugRenderMode::Shaded,
+            debug_view: None,
+            debug_mipmap_level_location,
+            first_pass_timer: GpuTimer::new(),
+            second_pass_timer: GpuTimer::new(),
+            indirect_draw_buffer: IndirectDrawBuffer::new(),
         }
     }
 
+    /// Makes the render system choose the level of view of each instance on the GPU with the given pass
+    ///
+    /// `gpu_level_of_views` - the pass choosing the level of views of the instances of the render system
+    pub fn set_gpu_level_of_views(&mut self, gpu_level_of_views: GpuLevelOfViewPass)
+    {
+        self.gpu_level_of_views = Some(gpu_level_of_views);
+    }
+
+    /// Makes the render system cull its instances on the GPU, which requires the level of view of each instance
+    /// to be chosen on the GPU. Instances are culled against the view frustum, and if the render system renders
+    /// deferred, against the depth of the previous frame it drew from the same camera
+    ///
+    /// `hi_z_buffer` - the buffer the depth of the frames drawn by the render system is kept in
+    pub fn set_gpu_culling(&mut self, hi_z_buffer: HiZBuffer)
+    {
+        self.hi_z_buffer = Some(hi_z_buffer);
+    }
+
+    /// Makes the render system draw screen space reflections over the frame lit by its second render pass
+    ///
+    /// `screen_space_reflections` - the pass drawing the reflections
+    pub fn set_screen_space_reflections(&mut self, screen_space_reflections: ScreenSpaceReflectionPass)
+    {
+        self.screen_space_reflections = Some(screen_space_reflections);
+    }
+
+    /// Checks if the level of view of each instance is chosen on the GPU. If so, instances are written
+    /// under the model ID without a level of view
+    pub fn uses_gpu_level_of_views(&self) -> bool
+    {
+        self.gpu_level_of_views.is_some()
+    }
+
+    /// Checks if instances are culled on the GPU. If so, entities in visible world sections are not culled one
+    /// by one on the CPU
+    pub fn uses_gpu_culling(&self) -> bool
+    {
+        self.gpu_level_of_views.is_some() && self.hi_z_buffer.is_some()
+    }
+
+    /// Sets the instances whose level of views are chosen on the GPU when the render system is next drawn
+    ///
+    /// `groups` - the instances of each model and sortable index, in the order they were written
+    /// `positions` - the position of every instance written in xyz, and the radius around it holding its bounding
+    ///               volume in w, in the order they were written
+    pub fn set_level_of_view_instances(&mut self, groups: Vec<LevelOfViewGroup>, positions: Vec<TVec4<f32>>)
+    {
+        self.level_of_view_groups = groups;
+        self.level_of_view_positions = positions;
+    }
+
+    /// Sets the values that replace those written by the draw functions to the uniforms with the given names
+    ///
+    /// `overrides` - the value of each overridden uniform, by the name of the uniform
+    pub fn set_uniform_overrides(&mut self, overrides: HashMap<String, UniformValue>)
+    {
+        self.uniform_overrides = overrides;
+    }
+
+    /// Get the uniforms of the render system with the values last written to them, sorted by name. The
+    /// boolean is true for the uniforms of the second render pass
+    pub fn inspect_uniforms(&self) -> Vec<(bool, String, UniformValue)>
+    {
+        let mut uniforms = Vec::new();
+
+        for (second_pass, resources) in [Some(&self.first_render_pass_resources), self.second_render_pass_resources.as_ref()].iter().enumerate()
+        {
+            let uniform_resources = match resources
+            {
+                Some(i) => &i.uniform_resources,
+                None => continue
+            };
+
+            let mut pass_uniforms = uniform_resources.uniform_types.iter().map(|(name, uniform_type)|
+                {
+                    let location = &uniform_resources.uniform_location_map[name];
+                    let value = UniformValue::read(*uniform_type, &uniform_resources.cpu_values[location.mapped_buffer_index], location);
+                    (second_pass == 1, name.clone(), value)
+                }).collect::<Vec<(bool, String, UniformValue)>>();
+
+            pass_uniforms.sort_by(|a, b| a.1.cmp(&b.1));
+            uniforms.extend(pass_uniforms);
+        }
+
+        uniforms
+    }
+
+    /// Checks if the uniform with the given name has its value replaced by the uniform inspector
+    ///
+    /// `uniform_name` - the name of the uniform
+    pub fn is_uniform_overridden(&self, uniform_name: &str) -> bool
+    {
+        self.uniform_overrides.contains_key(uniform_name)
+    }
+
+    /// Checks if the shaders of the render system read all of the material maps of models. If not, only
+    /// the diffuse maps of models are uploaded
+    pub fn uses_material_maps(&self) -> bool
+    {
+        self.material_maps
+    }
+
+    /// Replaces one of the draw functions of the render system, returning the function it replaced
+    ///
+    /// `slot` - the draw function to replace
+    /// `draw_function` - the function to draw with
+    pub fn replace_draw_function(&mut self, slot: DrawFunctionSlot, draw_function: DrawFunction) -> DrawFunction
+    {
+        let current_function = match slot
+        {
+            DrawFunctionSlot::Draw => &mut self.draw_function,
+            DrawFunctionSlot::LightSource => &mut self.light_source_draw_function,
+            DrawFunctionSlot::Transparency => &mut self.transparency_draw_function
+        };
+
+        std::mem::replace(current_function, draw_function)
+    }
+
+    /// Changes what the render system shows in place of its lit frame. Modes the render system cannot show
+    /// are ignored, leaving the current mode in place
+    ///
+    /// `mode` - what the render system shows
+    pub fn set_debug_render_mode(&mut self, mode: DebugRenderMode)
+    {
+        let supported = match (mode, &self.first_render_pass_resources.deferred_rendering_fbo)
+        {
+            (DebugRenderMode::Shaded, _) | (DebugRenderMode::Wireframe, _) => true,
+            (DebugRenderMode::MipmapLevel, _) if self.debug_mipmap_level_location == -1 => false,
+            (_, Some(ref g_buffer)) => DebugViewPass::supports(mode, g_buffer),
+            (_, None) => false
+        };
+
+        if !supported
+        {
+            warn!("Unable to use the {:?} debug render mode, as the render system does not write what it shows", mode);
+            return;
+        }
+
+        if self.debug_view.is_none() && mode != DebugRenderMode::Shaded && mode != DebugRenderMode::Wireframe
+        {
+            match DebugViewPass::new()
+            {
+                Ok(debug_view) => self.debug_view = Some(debug_view),
+                Err(err) =>
+                    {
+                        warn!("Unable to create the debug view pass: {}", err);
+                        return;
+                    }
+            }
+        }
+
+        self.debug_render_mode = mode;
+    }
+
+    /// Get what the render system shows in place of its lit frame
+    pub fn debug_render_mode(&self) -> DebugRenderMode
+    {
+        self.debug_render_mode
+    }
+
+    /// Reads the GPU times of the passes of the render system, and starts measuring the passes of the
+    /// next frame. Call once per frame, after every draw of the render system was issued
+    pub fn end_gpu_timer_frame(&mut self) -> RenderSystemGpuTimings
+    {
+        self.first_pass_timer.end_frame();
+        self.second_pass_timer.end_frame();
+
+        RenderSystemGpuTimings{ first_pass: self.first_pass_timer.latest_time(), second_pass: self.second_pass_timer.latest_time() }
+    }
+
+    /// Changes the maximum number of lights uploaded when rendering. Uniform arrays declared to scale
+    /// with the number of lights are resized, which regenerates the second render pass shaders.
+    /// The light buffers of the first render pass are not rebuilt, so an error is returned without
+    /// changing the constraints if the render system has no second render pass
+    ///
+    /// `max_num_lights` - the new maximum number of each type of light
+    pub fn change_light_constraints(&mut self, max_num_lights: MaxNumLights) -> Result<(), String>
+    {
+        if self.max_num_lights == max_num_lights
+        {
+            return Ok(());
+        }
+
+        let rebuild_information = match self.second_pass_rebuild_information
+        {
+            Some(ref mut i) => i,
+            None => return Err("Light constraints can only be changed for render systems with a second render pass".to_string())
+        };
+
+        self.second_render_pass_resources = Some(rebuild_second_render_pass_resources(rebuild_information, max_num_lights));
+        self.max_num_lights = max_num_lights;
+
+        // Previously rendered lights may no longer fit within the new constraints
+        self.previous_directional_lights.clear();
+        self.previous_point_lights.clear();
+        self.previous_spot_lights.clear();
+
+        Ok(())
+    }
+
+    /// Get the maximum number of lights that are uploaded when rendering
+    pub fn get_light_constraints(&self) -> MaxNumLights
+    {
+        self.max_num_lights
+    }
+
     /// Binds the render system's VAO
     pub fn use_vao(&mut self)
     {
@@ -144,6 +419,24 @@ impl RenderSystem
         self.first_render_pass_resources.shader_program.use_shader_program();
     }
 
+    /// Makes the given FBO accessible to the draw functions of the render system. If an FBO with the same
+    /// name already exists, it is replaced
+    ///
+    /// `name` - the name draw functions use to refer to the FBO
+    /// `fbo` - the FBO to make accessible
+    pub fn add_accessible_fbo(&mut self, name: String, fbo: FBO)
+    {
+        self.draw_fn_accessible_fbo.insert(name, fbo);
+    }
+
+    /// Removes the FBO with the given name from those accessible to the draw functions of the render system
+    ///
+    /// `name` - the name of the FBO to remove
+    pub fn take_accessible_fbo(&mut self, name: &str) -> Option<FBO>
+    {
+        self.draw_fn_accessible_fbo.remove(name)
+    }
+
     /// Registers a type to be used as a uniform
     pub fn register_uniform_type_ecs<'a, T: 'static + Serialize + Deserialize<'a>>(&mut self)
     {
@@ -206,11 +499,11 @@ impl RenderSystem
         self.first_render_pass_resources.fragment_shader_resource.cube_maps.get_mut(cube_map_name.as_ref()).unwrap().bind();
     }
 
-    /// Obtain pointers to buffers that store data for instanced layouts
+    /// Obtain pointers to buffers that store data for instanced layouts. These are the buffers of the next frame
+    /// of the instance upload ring that the GPU is not drawing from, so this never waits for draws in flight
     pub fn get_instanced_mapped_buffers(&mut self) -> Vec<BufferWriteInfo>
     {
-        self.first_render_pass_resources.vertex_shader_resource.per_instance_buffers.iter_mut()
-            .map(|x| x.wait_for_next_free_buffer(1_000_000).unwrap()).collect()
+        self.first_render_pass_resources.vertex_shader_resource.instance_upload_ring.begin_frame()
     }
 
     /// Tell OpenGL to flush the instanced buffers. All instanced buffers must be flushed
@@ -220,10 +513,7 @@ impl RenderSystem
     ///                         second mapped buffer, and so on
     pub fn flush_per_instance_buffers(&mut self, data_changed_range: Vec<(StartBufferChangedBytes, NumberBytesChanged)>)
     {
-        for (buffer_index, (start_byte_changed, number_bytes_changed)) in data_changed_range.into_iter().enumerate()
-        {
-            self.first_render_pass_resources.vertex_shader_resource.per_instance_buffers[buffer_index].mark_buffer_updates_finish(start_byte_changed, number_bytes_changed);
-        }
+        self.first_render_pass_resources.vertex_shader_resource.instance_upload_ring.flush(data_changed_range);
     }
 
     /// Obtain pointers to buffers that store data for model layouts
@@ -246,22 +536,44 @@ impl RenderSystem
         }
     }
 
+    /// Uploads a texture of a single colour, making it available for use when rendering. A texture of the
+    /// same colour that is already uploaded is reused
+    ///
+    /// `texture_colour` - the colour of the texture
     pub fn add_solid_colour_texture(&mut self, texture_colour: TVec4<u8>) -> UploadedTextureLocation
     {
+        let colour = [texture_colour[0], texture_colour[1], texture_colour[2], texture_colour[3]];
+
+        if let Some(upload_info) = self.first_render_pass_resources.uploaded_textures.solid_colours.get(&colour).copied()
+        {
+            self.first_render_pass_resources.uploaded_textures.add_reference(upload_info);
+            return upload_info;
+        }
+
         let array_index = self.first_render_pass_resources.fragment_shader_resource.texture_arrays.len() - 1;
         let solid_colour_array = self.first_render_pass_resources.fragment_shader_resource.texture_arrays.last_mut();
 
         if let Some(texture_array) = solid_colour_array
         {
-            let colour = [texture_colour[0], texture_colour[1], texture_colour[2], texture_colour[3]];
-            let index = texture_array.add_texture_solid_colour(colour);
-            UploadedTextureLocation
+            // A render system sharing the texture array may have already uploaded the colour
+            let index = match texture_array.find_texture(TextureContents::SolidColour(colour))
+            {
+                Some((index, _, _)) => index,
+                None => texture_array.add_texture_solid_colour(colour)
+            };
+
+            let upload_info = UploadedTextureLocation
             {
                 array_index,
                 index_offset: index,
                 scale_x: 1.0,
                 scale_y: 1.0
-            }
+            };
+
+            self.first_render_pass_resources.uploaded_textures.solid_colours.insert(colour, upload_info);
+            self.first_render_pass_resources.uploaded_textures.add_reference(upload_info);
+
+            upload_info
         }
         else
         {
@@ -275,17 +587,42 @@ impl RenderSystem
         }
     }
 
-    /// Uploads the given texture to the given render system, making it available for use when rendering
+    /// Uploads the given texture to the given render system, making it available for use when rendering.
+    /// If the texture, or a texture with the same contents in a different file, is already uploaded then
+    /// the uploaded texture is reused
     ///
     /// `texture_location` - the location of the texture to upload
     pub fn add_texture(&mut self, texture_location: PathBuf) -> UploadedTextureLocation
     {
-        if let Some(upload_info) = self.first_render_pass_resources.uploaded_textures.get(&texture_location)
+        if let Some(upload_info) = self.first_render_pass_resources.uploaded_textures.locations.get(&texture_location).copied()
         {
-            return *upload_info;
+            self.first_render_pass_resources.uploaded_textures.add_reference(upload_info);
+            return upload_info;
         }
 
         let texture_properties = TextureProperties::read_image(&texture_location);
+        let content_hash = texture_properties.content_hash();
+
+        if let Some(upload_info) = self.first_render_pass_resources.uploaded_textures.contents.get(&content_hash).copied()
+        {
+            self.first_render_pass_resources.uploaded_textures.locations.insert(texture_location, upload_info);
+            self.first_render_pass_resources.uploaded_textures.add_reference(upload_info);
+            return upload_info;
+        }
+
+        // A render system sharing a texture array may have already uploaded a texture with the same contents
+        for (array_index, texture_array) in self.first_render_pass_resources.fragment_shader_resource.texture_arrays.iter_mut().enumerate()
+        {
+            if let Some((index_offset, scale_x, scale_y)) = texture_array.find_texture(TextureContents::Image(content_hash))
+            {
+                let upload_info = UploadedTextureLocation{ array_index, index_offset, scale_x, scale_y };
+
+                self.first_render_pass_resources.uploaded_textures.locations.insert(texture_location, upload_info);
+                self.first_render_pass_resources.uploaded_textures.contents.insert(content_hash, upload_info);
+                self.first_render_pass_resources.uploaded_textures.add_reference(upload_info);
+                return upload_info;
+            }
+        }
 
         let mut most_suitable_array_index = None;
         let mut least_wasted_space_found = usize::MAX;
@@ -308,38 +645,36 @@ impl RenderSystem
         {
             Some(i) =>
                 {
-                    match self.first_render_pass_resources.fragment_shader_resource.texture_arrays[i].add_texture_sequentially_from_file_stbi(&texture_properties).unwrap()
+                    let upload_info = match self.first_render_pass_resources.fragment_shader_resource.texture_arrays[i].add_texture_sequentially_from_file_stbi(&texture_properties, content_hash).unwrap()
                     {
                         TextureUploadResult::Success(index_offset) =>
                             {
-                                let upload_info = UploadedTextureLocation
+                                UploadedTextureLocation
                                 {
                                     array_index: i,
                                     index_offset,
                                     scale_x: 0.0,
                                     scale_y: 0.0
-                                };
-
-                                self.first_render_pass_resources.uploaded_textures.insert(texture_location, upload_info);
-
-                                upload_info
+                                }
                             },
                         TextureUploadResult::SuccessWithResize(index_offset, scale_x, scale_y) =>
                             {
-                                let upload_info = UploadedTextureLocation
+                                UploadedTextureLocation
                                 {
                                     array_index: i,
                                     index_offset,
                                     scale_x,
                                     scale_y
-                                };
-
-                                self.first_render_pass_resources.uploaded_textures.insert(texture_location, upload_info);
-
-                                upload_info
+                                }
                             },
                         _ => panic!()
-                    }
+                    };
+
+                    self.first_render_pass_resources.uploaded_textures.locations.insert(texture_location, upload_info);
+                    self.first_render_pass_resources.uploaded_textures.contents.insert(content_hash, upload_info);
+                    self.first_render_pass_resources.uploaded_textures.add_reference(upload_info);
+
+                    upload_info
                 },
             None =>
                 {
@@ -354,6 +689,18 @@ impl RenderSystem
         }
     }
 
+    /// Releases a texture that was uploaded for a model that was removed. Once every model using the
+    /// texture has released it, the layer holding the texture is freed for other textures to use
+    ///
+    /// `texture` - the location of the texture returned when it was uploaded
+    pub fn release_texture(&mut self, texture: UploadedTextureLocation)
+    {
+        if self.first_render_pass_resources.uploaded_textures.remove_reference(texture)
+        {
+            self.first_render_pass_resources.fragment_shader_resource.texture_arrays[texture.array_index].remove_texture(texture.index_offset);
+        }
+    }
+
     /// Creates mipmaps for the texture array associated with the given name
     ///
     /// `texture_array_name` - the name of the texture to create mipmaps for
@@ -389,13 +736,10 @@ impl RenderSystem
         }
     }
 
-    /// Set the fences for all of the instanced mapped buffers. Call this at the end of the draw function
+    /// Set the fence of the frame of the instance upload ring that was drawn. Call this at the end of the draw function
     pub fn set_fences_for_instance_buffers(&mut self)
     {
-        for x in &mut self.first_render_pass_resources.vertex_shader_resource.per_instance_buffers
-        {
-            x.set_fence();
-        }
+        self.first_render_pass_resources.vertex_shader_resource.instance_upload_ring.end_frame();
     }
 
     /// Set the fences for the indice mapped buffers. Call this at the end of the draw function
@@ -409,6 +753,25 @@ impl RenderSystem
     /// `in_draw_param` - structure holding variables required to execute the render passes
     pub fn draw(&mut self, in_draw_param: DrawPreparationParameters)
     {
+        self.first_pass_timer.begin();
+
+        // The model geometry is only known once any models uploaded this frame have been flushed, so the
+        // level of views are chosen right before drawing
+        if let Some(ref mut gpu_level_of_views) = self.gpu_level_of_views
+        {
+            // The depth of the previous frame only hides instances drawn from the camera it was drawn from
+            let culling = self.hi_z_buffer.as_ref().map(|x| InstanceCulling
+            {
+                view_projection: in_draw_param.camera.get_projection_matrix() * in_draw_param.camera.get_view_matrix(),
+                occluders: Some(x).filter(|_| in_draw_param.primary_view)
+            });
+
+            gpu_level_of_views.select_level_of_views(&self.level_of_view_groups, &self.level_of_view_positions,
+                                                     self.first_render_pass_resources.vertex_shader_resource.instance_upload_ring.current_buffers(),
+                                                     &self.model_rendering_information, in_draw_param.camera.get_position(), culling);
+            gpu_level_of_views.bind_instance_buffers();
+        }
+
         self.first_render_pass_resources.shader_program.use_shader_program();
 
         {
@@ -427,7 +790,9 @@ impl RenderSystem
                 uniform_type: &self.first_render_pass_resources.uniform_resources.uniform_type_ids,
                 buffers: &mut self.first_render_pass_resources.uniform_resources.mapped_buffers,
                 buffers_to_flush: Vec::new(),
-                buffers_to_fence: Vec::new()
+                buffers_to_fence: Vec::new(),
+                cpu_values: &mut self.first_render_pass_resources.uniform_resources.cpu_values,
+                overrides: &self.uniform_overrides
             };
 
             let uniform_ecs = UniformECS
@@ -452,6 +817,13 @@ impl RenderSystem
                 .initially_drawing_skybox(false)
                 .build();
 
+            if let Some(ref gpu_level_of_views) = self.gpu_level_of_views
+            {
+                first_render_pass_draw_param.use_indirect_draws(gpu_level_of_views.indirect_draws());
+            }
+
+            first_render_pass_draw_param.use_indirect_draw_buffer(&mut self.indirect_draw_buffer);
+
             if let Some(ref mut first_render_fbo) = self.first_render_pass_resources.deferred_rendering_fbo
             {
                 first_render_fbo.bind_fbo(BindingTarget::DrawFrameBuffer);
@@ -474,18 +846,39 @@ impl RenderSystem
                 in_draw_param.shadow_fbo.bind_depth_texture_to_specific_texture_unit(shadow_map_binding);
             }
 
+            let debug_render_mode = self.debug_render_mode;
+
+            if self.debug_mipmap_level_location != -1
+            {
+                unsafe
+                    {
+                        gl::ProgramUniform1i(self.first_render_pass_resources.shader_program.shader_program, self.debug_mipmap_level_location,
+                                             (debug_render_mode == DebugRenderMode::MipmapLevel) as i32);
+                    }
+            }
+
+            if debug_render_mode == DebugRenderMode::Overdraw
+            {
+                // Every fragment drawn counts, including those hidden behind fragments drawn before them
+                unsafe{ gl::StencilOp(gl::KEEP, gl::INCR, gl::INCR); }
+            }
+
+            set_wireframe(debug_render_mode, true);
+
             (self.draw_function)(&mut first_render_pass_draw_param);
 
             unsafe{ gl::StencilFunc(gl::ALWAYS, 0x00, 0xFF); }
 
             (self.light_source_draw_function)(&mut first_render_pass_draw_param);
 
+            set_wireframe(debug_render_mode, false);
+
             if self.is_using_skybox
             {
                 first_render_pass_draw_param.toggle_rendering_skybox(true);
                 first_render_pass_draw_param.write_uniform_value("projectionMatrix", vec![first_render_pass_draw_param.get_camera().get_projection_matrix()]);
                 first_render_pass_draw_param.write_uniform_value("viewMatrix", vec![first_render_pass_draw_param.get_camera().get_view_matrix()]);
-                first_render_pass_draw_param.write_uniform_value("cameraLocation", vec![first_render_pass_draw_param.get_camera().get_position()]);
+                first_render_pass_draw_param.write_uniform_value("cameraLocation", vec![first_render_pass_draw_param.get_camera().get_render_position()]);
 
                 let mod_view_matrix = nalgebra_glm::mat3_to_mat4(&nalgebra_glm::mat4_to_mat3(&first_render_pass_draw_param.get_camera().get_view_matrix()));
                 first_render_pass_draw_param.write_uniform_value("viewMatrix", vec![mod_view_matrix]);
@@ -498,19 +891,49 @@ impl RenderSystem
                 unsafe{ gl::DepthFunc(gl::LESS);  }
             }
 
+            // Transparent entities do not hide what is behind them, so the depth is kept before they are drawn
+            if let (Some(hi_z_buffer), Some(first_render_fbo)) = (&mut self.hi_z_buffer, &self.first_render_pass_resources.deferred_rendering_fbo)
+            {
+                if in_draw_param.primary_view
+                {
+                    let view_projection = in_draw_param.camera.get_projection_matrix() * in_draw_param.camera.get_view_matrix();
+                    hi_z_buffer.build(first_render_fbo.get_raw_resource(), in_draw_param.output_framebuffer.dimensions, view_projection);
+                }
+            }
+
+            set_wireframe(debug_render_mode, true);
+
             (self.transparency_draw_function)(&mut first_render_pass_draw_param);
 
-            if let Some(ref mut second_pass_render) = self.second_render_pass_resources
+            set_wireframe(debug_render_mode, false);
+
+            self.first_pass_timer.end();
+
+            // Debug render modes other than the wireframe mode show the G-Buffer in place of the lit frame
+            let debug_view_drawn = match (&mut self.debug_view, &mut self.first_render_pass_resources.deferred_rendering_fbo)
+            {
+                (Some(debug_view), Some(g_buffer)) if debug_render_mode != DebugRenderMode::Shaded && debug_render_mode != DebugRenderMode::Wireframe =>
+                    {
+                        debug_view.apply(debug_render_mode, g_buffer, in_draw_param.output_framebuffer);
+                        true
+                    },
+                _ => false
+            };
+
+            if let Some(second_pass_render) = self.second_render_pass_resources.as_mut().filter(|_| !debug_view_drawn)
             {
+                self.second_pass_timer.begin();
+
                 if let Some(ref mut first_render_fbo) = self.first_render_pass_resources.deferred_rendering_fbo
                 {
-                    first_render_fbo.bind_colour_textures(vec![0, 1, 2, 3]);
+                    first_render_fbo.bind_colour_textures(vec![0, 1, 2, 3, 4]);
                     first_render_fbo.bind_fbo(BindingTarget::ReadFrameBuffer);
 
                     unsafe
                         {
-                            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
-                            gl::BlitFramebuffer(0, 0, 1280, 720, 0, 0, 1280, 720, gl::STENCIL_BUFFER_BIT, gl::NEAREST);
+                            let (width, height) = in_draw_param.output_framebuffer.dimensions;
+                            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, in_draw_param.output_framebuffer.framebuffer);
+                            gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, gl::STENCIL_BUFFER_BIT, gl::NEAREST);
                         }
                 }
 
@@ -520,7 +943,9 @@ impl RenderSystem
                     uniform_type: &second_pass_render.uniform_resources.uniform_type_ids,
                     buffers: &mut second_pass_render.uniform_resources.mapped_buffers,
                     buffers_to_flush: Vec::new(),
-                    buffers_to_fence: Vec::new()
+                    buffers_to_fence: Vec::new(),
+                    cpu_values: &mut second_pass_render.uniform_resources.cpu_values,
+                    overrides: &self.uniform_overrides
                 };
 
                 let uniform_ecs = UniformECS
@@ -560,19 +985,44 @@ impl RenderSystem
                     // TODO: Add constant directional light. Otherwise if no light sources are visible,
                     // TODO: change in colours will be very abrupt as texturing without lighting is used
 
-                    any_light_source_visible |= RenderSystem::upload_directional_lights(&mut self.previous_directional_lights, in_draw_param.visible_sections_light, &mut second_render_pass_draw_param, in_draw_param.visible_directional_lights, self.max_num_lights.directional);
-                    any_light_source_visible |= RenderSystem::upload_point_lights(&mut self.previous_point_lights, in_draw_param.visible_sections_light, &mut second_render_pass_draw_param, in_draw_param.visible_point_lights,self.max_num_lights.point);
-                    any_light_source_visible |= RenderSystem::upload_spot_lights(&mut self.previous_spot_lights, in_draw_param.visible_sections_light, &mut second_render_pass_draw_param, in_draw_param.visible_spot_lights, self.max_num_lights.spot);
+                    let (directional_storage, point_storage, spot_storage) = match second_pass_render.light_storage_buffers
+                    {
+                        Some(ref mut storage) =>
+                            {
+                                // Light types without visible lights are not uploaded, so they must not keep
+                                // the volumes of the previous frame
+                                storage.point.set_light_volumes(Vec::new());
+                                storage.spot.set_light_volumes(Vec::new());
+                                (Some(&mut storage.directional), Some(&mut storage.point), Some(&mut storage.spot))
+                            },
+                        None => (None, None, None)
+                    };
+
+                    any_light_source_visible |= RenderSystem::upload_directional_lights(&mut self.previous_directional_lights, in_draw_param.visible_sections_light, &mut second_render_pass_draw_param, in_draw_param.visible_directional_lights, self.max_num_lights.directional, directional_storage);
+                    any_light_source_visible |= RenderSystem::upload_point_lights(&mut self.previous_point_lights, in_draw_param.visible_sections_light, &mut second_render_pass_draw_param, in_draw_param.visible_point_lights,self.max_num_lights.point, point_storage);
+                    any_light_source_visible |= RenderSystem::upload_spot_lights(&mut self.previous_spot_lights, in_draw_param.visible_sections_light, &mut second_render_pass_draw_param, in_draw_param.visible_spot_lights, self.max_num_lights.spot, spot_storage);
+
+                    if let Some(LightStorageBuffers{ ref point, ref spot, clusters: Some(ref mut clusters), .. }) = second_pass_render.light_storage_buffers
+                    {
+                        clusters.upload(in_draw_param.camera, point.light_volumes(), spot.light_volumes());
+                    }
                 }
 
+                // The camera may be within a region of the world with its own ambient lighting
+                let ambient_settings = blend_ambient_overrides(in_draw_param.camera.get_position(), AmbientSettings
+                {
+                    no_light_source_cutoff: self.no_light_source_cutoff,
+                    default_diffuse_factor: self.default_diffuse_factor
+                });
+
                 unsafe
                     {
                         gl::StencilFunc(gl::EQUAL, LIT_SOURCE_STENCIL_VALUE, 0xFF);
-                        second_render_pass_draw_param.write_uniform_value("noLightSourceCutoff", vec![self.no_light_source_cutoff]);
-                        second_render_pass_draw_param.write_uniform_value("defaultDiffuseFactor", vec![self.default_diffuse_factor]);
+                        second_render_pass_draw_param.write_uniform_value("noLightSourceCutoff", vec![ambient_settings.no_light_source_cutoff]);
+                        second_render_pass_draw_param.write_uniform_value("defaultDiffuseFactor", vec![ambient_settings.default_diffuse_factor]);
                         second_render_pass_draw_param.write_uniform_value("renderSkybox", vec![0_u32]);
                         second_render_pass_draw_param.write_uniform_value("renderingLightVolumes", vec![0_u32]);
-                        second_render_pass_draw_param.write_uniform_value("cameraPosition", vec![in_draw_param.camera.get_position()]);
+                        second_render_pass_draw_param.write_uniform_value("cameraPosition", vec![in_draw_param.camera.get_render_position()]);
                         second_render_pass_draw_param.write_uniform_value("anyLightSourceVisible", vec![any_light_source_visible as u32]);
                         second_render_pass_draw_param.flush_uniform_buffer();
                         gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
@@ -586,6 +1036,28 @@ impl RenderSystem
                         second_render_pass_draw_param.set_fence_uniform_buffer();
                         gl::Enable(gl::DEPTH_TEST);
                     }
+
+                record_draw_calls(2);
+
+                if let Some(ref mut screen_space_reflections) = self.screen_space_reflections
+                {
+                    let view = ReflectionView
+                    {
+                        camera_position: in_draw_param.camera.get_render_position(),
+                        view_matrix: in_draw_param.camera.get_render_view_matrix(),
+                        projection_matrix: in_draw_param.camera.get_projection_matrix()
+                    };
+
+                    screen_space_reflections.apply(&view, self.material_maps, in_draw_param.output_framebuffer);
+                    record_draw_calls(2);
+                }
+
+                if let Some(ref mut light_storage_buffers) = second_pass_render.light_storage_buffers
+                {
+                    light_storage_buffers.set_fences();
+                }
+
+                self.second_pass_timer.end();
             }
         }
 
@@ -630,6 +1102,20 @@ impl RenderSystem
         self.first_render_pass_resources.vertex_shader_resource.instance_layout_indexes.clone()
     }
 
+    /// Get the index of the layout the engine writes the InstanceParams of entities to, if the render
+    /// system was created with instance parameters
+    pub fn get_instance_params_layout_index(&self) -> Option<u32>
+    {
+        self.first_render_pass_resources.vertex_shader_resource.instance_params_layout_index
+    }
+
+    /// Get the index of the layout the engine writes the Dissolve of entities to, if the render system
+    /// was created with dissolve support
+    pub fn get_dissolve_layout_index(&self) -> Option<u32>
+    {
+        self.first_render_pass_resources.vertex_shader_resource.dissolve_layout_index
+    }
+
     /// Get the indexes of the layouts in this render system shader program that correspond to model data
     pub fn get_model_layout_indexes(&self) -> Vec<u32>
     {
@@ -658,6 +1144,42 @@ impl RenderSystem
         self.model_rendering_information = updated_rendering_info;
     }
 
+    /// Binds a layout of the G-Buffer written by the first pass to the given texture unit, so that passes
+    /// drawn after the render system can read it. Returns false if the render system has no such layout
+    ///
+    /// `layout_index` - the index of the first pass fragment shader layout to bind
+    /// `binding_point` - the texture unit to bind the layout to
+    pub fn bind_g_buffer_layout(&mut self, layout_index: usize, binding_point: u32) -> bool
+    {
+        match self.first_render_pass_resources.deferred_rendering_fbo
+        {
+            Some(ref mut fbo) => fbo.bind_colour_texture_to_specific_texture_unit(layout_index, binding_point),
+            None => false
+        }
+    }
+
+    /// Copies the depth of the G-Buffer into the given frame buffer, returning false if the render system
+    /// does not use deferred rendering
+    ///
+    /// `output_framebuffer` - the frame buffer to copy the depth into, with the dimensions of the G-Buffer
+    pub fn blit_g_buffer_depth(&self, output_framebuffer: OutputFramebuffer) -> bool
+    {
+        match self.first_render_pass_resources.deferred_rendering_fbo
+        {
+            Some(ref fbo) =>
+                {
+                    let (width, height) = output_framebuffer.dimensions;
+                    unsafe
+                        {
+                            gl::BlitNamedFramebuffer(fbo.get_raw_resource(), output_framebuffer.framebuffer, 0, 0, width, height,
+                                                     0, 0, width, height, gl::DEPTH_BUFFER_BIT, gl::NEAREST);
+                        }
+                    true
+                },
+            None => false
+        }
+    }
+
     /// Determines if this render system requires shadows
     pub fn require_shadows(&self) -> bool
     {
@@ -673,13 +1195,15 @@ impl RenderSystem
         self.first_render_pass_resources.shadow_map_binding_point.is_some() || second_pass_needs_shadows
     }
 
-    /// Uploads nearby/visible directional lights to the second render pass uniforms, and marks the
-    /// the lights as being rendered for use in the shadow flow
+    /// Uploads nearby/visible directional lights to the second render pass, and marks the
+    /// the lights as being rendered for use in the shadow flow. If the render pass has light storage
+    /// buffers, all visible lights are uploaded to them; otherwise lights are written to uniform arrays
     ///
     /// `draw_param` - the variable required to query nearby lights and upload them as uniforms
     /// `directional_lights` - map of entity ids that identify directional lights
+    /// `light_storage` - the storage buffer to write the lights to, if the render pass has one
     fn upload_directional_lights(previous_directional_lights: &mut HashSet<EntityId>, visible_world_sections: &HashSet<UniqueWorldSectionId>, draw_param: &mut DrawParam,
-                                 directional_lights: &mut HashSet::<EntityId>, max_direction_lights: u16) -> AnyLightSourceVisible
+                                 directional_lights: &mut HashSet::<EntityId>, max_direction_lights: u16, light_storage: Option<&mut LightStorageBuffer>) -> AnyLightSourceVisible
     {
         let visible_directional_lights = shadow_flow::find_nearby_lights
             (
@@ -693,29 +1217,51 @@ impl RenderSystem
             return false;
         }
 
-        let number_rendered_directional_lights = visible_directional_lights.len().min(max_direction_lights as usize);
-        let mut light_upload_information = LightUploadInformation::new(max_direction_lights as usize);
-
-        let existing_lights = previous_directional_lights.intersection(&visible_directional_lights).map(|x| *x).collect::<HashSet<EntityId>>();
-        previous_directional_lights.clear();
+        let light_significance = RenderSystem::light_significance(draw_param, false);
+        let rendered_lights = RenderSystem::select_rendered_lights(previous_directional_lights, &visible_directional_lights, max_direction_lights, light_storage.is_some(), light_significance);
 
-        for (index, directional_light) in existing_lights.iter().chain(visible_directional_lights.iter()).take(number_rendered_directional_lights).enumerate()
+        match light_storage
         {
-            let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*directional_light).unwrap();
+            Some(light_storage) =>
+                {
+                    let lights = rendered_lights.iter().map(|x|
+                        {
+                            let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*x).unwrap();
+                            let direction = light_info.direction.unwrap();
 
-            light_upload_information.directions[index] = light_info.direction.unwrap();
-            light_upload_information.diffuse_colours[index] = light_info.diffuse_colour;
-            light_upload_information.specular_colours[index] = light_info.specular_colour;
-            light_upload_information.ambient_colours[index] = light_info.ambient_colour;
+                            DirectionLightStorage
+                            {
+                                direction: vec4(direction.x, direction.y, direction.z, 0.0),
+                                diffuse_colour: vec4(light_info.diffuse_colour.x, light_info.diffuse_colour.y, light_info.diffuse_colour.z, 0.0),
+                                specular_colour: vec4(light_info.specular_colour.x, light_info.specular_colour.y, light_info.specular_colour.z, 0.0),
+                                ambient_colour: light_info.ambient_colour
+                            }
+                        }).collect::<Vec<DirectionLightStorage>>();
+
+                    light_storage.upload(&lights);
+                },
+            None =>
+                {
+                    let mut light_upload_information = LightUploadInformation::new(max_direction_lights as usize);
+
+                    for (index, directional_light) in rendered_lights.iter().enumerate()
+                    {
+                        let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*directional_light).unwrap();
 
-            previous_directional_lights.insert(*directional_light);
+                        light_upload_information.directions[index] = light_info.direction.unwrap();
+                        light_upload_information.diffuse_colours[index] = light_info.diffuse_colour;
+                        light_upload_information.specular_colours[index] = light_info.specular_colour;
+                        light_upload_information.ambient_colours[index] = light_info.ambient_colour;
+                    }
+
+                    draw_param.write_uniform_value("directionLightDirection", light_upload_information.directions);
+                    draw_param.write_uniform_value("directionLightDiffuseColour", light_upload_information.diffuse_colours);
+                    draw_param.write_uniform_value("directionLightSpecularColour", light_upload_information.specular_colours);
+                    draw_param.write_uniform_value("directionLightAmbientColour", light_upload_information.ambient_colours);
+                }
         }
 
-        draw_param.write_uniform_value("directionLightDir", light_upload_information.directions);
-        draw_param.write_uniform_value("directionLightDiffuseColour", light_upload_information.diffuse_colours);
-        draw_param.write_uniform_value("directionLightSpecularColour", light_upload_information.specular_colours);
-        draw_param.write_uniform_value("directionLightAmbientColour", light_upload_information.ambient_colours);
-        draw_param.write_uniform_value("numberDirectionLights", vec![number_rendered_directional_lights as u32]);
+        draw_param.write_uniform_value("numberDirectionLights", vec![rendered_lights.len() as u32]);
 
         // This map is looked at the shadow flow when determining what lights need to have a shadow map
         // created for them; lights being rendered have a priority
@@ -723,13 +1269,15 @@ impl RenderSystem
         true
     }
 
-    /// Uploads nearby/visible point lights to the second render pass uniforms, and marks the
-    /// the lights as being rendered for use in the shadow flow
+    /// Uploads nearby/visible point lights to the second render pass, and marks the
+    /// the lights as being rendered for use in the shadow flow. If the render pass has light storage
+    /// buffers, all visible lights are uploaded to them; otherwise lights are written to uniform arrays
     ///
     /// `draw_param` - the variable required to query nearby lights and upload them as uniforms
     /// `directional_lights` - map of entity ids that identify point lights
+    /// `light_storage` - the storage buffer to write the lights to, if the render pass has one
     fn upload_point_lights(previous_point_lights: &mut HashSet<EntityId>, visible_world_sections: &HashSet::<UniqueWorldSectionId>, draw_param: &mut DrawParam,
-                           point_lights: &mut HashSet::<EntityId>, max_point_lights: u16)  -> AnyLightSourceVisible
+                           point_lights: &mut HashSet::<EntityId>, max_point_lights: u16, light_storage: Option<&mut LightStorageBuffer>)  -> AnyLightSourceVisible
     {
         let visible_point_lights = shadow_flow::find_nearby_lights
             (
@@ -743,41 +1291,73 @@ impl RenderSystem
             return false;
         }
 
-        let number_rendered_point_lights = visible_point_lights.len().min(max_point_lights as usize);
-        let mut light_upload_information = LightUploadInformation::new(max_point_lights as usize);
-
-        let existing_lights = previous_point_lights.intersection(&visible_point_lights).map(|x| *x).collect::<HashSet<EntityId>>();
-        previous_point_lights.clear();
+        let light_significance = RenderSystem::light_significance(draw_param, true);
+        let rendered_lights = RenderSystem::select_rendered_lights(previous_point_lights, &visible_point_lights, max_point_lights, light_storage.is_some(), light_significance);
 
-        for (index, point_light) in existing_lights.iter().chain(visible_point_lights.iter()).take(number_rendered_point_lights).enumerate()
+        match light_storage
         {
-            let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*point_light).unwrap();
-            let position = draw_param.get_logical_ecs().get_ref::<Position>(*point_light).unwrap();
+            Some(light_storage) =>
+                {
+                    let lights = rendered_lights.iter().map(|x|
+                        {
+                            let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*x).unwrap();
+                            let position = render_space_position(draw_param.get_logical_ecs(), *x).unwrap();
+                            let direction = light_info.direction.unwrap();
+
+                            PointLightStorage
+                            {
+                                position: vec4(position.x, position.y, position.z, 1.0),
+                                direction: vec4(direction.x, direction.y, direction.z, 0.0),
+                                diffuse_colour: vec4(light_info.diffuse_colour.x, light_info.diffuse_colour.y, light_info.diffuse_colour.z, 0.0),
+                                specular_colour: vec4(light_info.specular_colour.x, light_info.specular_colour.y, light_info.specular_colour.z, 0.0),
+                                ambient_colour: light_info.ambient_colour,
+                                attenuation: vec4(light_info.linear_coefficient, light_info.quadratic_coefficient, light_info.cutoff.unwrap(), light_info.outer_cutoff.unwrap())
+                            }
+                        }).collect::<Vec<PointLightStorage>>();
+
+                    let light_volumes = rendered_lights.iter().map(|x|
+                        {
+                            let radius = draw_param.get_logical_ecs().get_ref::<LightInformation>(*x).unwrap().radius;
+                            LightVolume{ position: render_space_position(draw_param.get_logical_ecs(), *x).unwrap(), radius }
+                        }).collect::<Vec<LightVolume>>();
+
+                    light_storage.upload(&lights);
+                    light_storage.set_light_volumes(light_volumes);
+                },
+            None =>
+                {
+                    let mut light_upload_information = LightUploadInformation::new(max_point_lights as usize);
 
-            light_upload_information.positions[index] = position.get_position();
-            light_upload_information.diffuse_colours[index] = light_info.diffuse_colour;
-            light_upload_information.specular_colours[index] = light_info.specular_colour;
-            light_upload_information.ambient_colours[index] = light_info.ambient_colour;
-            light_upload_information.linear_coefficients[index] = light_info.linear_coefficient;
-            light_upload_information.quadratic_coefficients[index] = light_info.quadratic_coefficient;
-            light_upload_information.directions[index] = light_info.direction.unwrap();
-            light_upload_information.fov[index] = light_info.fov.unwrap();
-            light_upload_information.cutoff[index] = light_info.cutoff.unwrap();
-            light_upload_information.outer_cutoff[index] = light_info.outer_cutoff.unwrap();
+                    for (index, point_light) in rendered_lights.iter().enumerate()
+                    {
+                        let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*point_light).unwrap();
+                        let position = render_space_position(draw_param.get_logical_ecs(), *point_light).unwrap();
+
+                        light_upload_information.positions[index] = position;
+                        light_upload_information.diffuse_colours[index] = light_info.diffuse_colour;
+                        light_upload_information.specular_colours[index] = light_info.specular_colour;
+                        light_upload_information.ambient_colours[index] = light_info.ambient_colour;
+                        light_upload_information.linear_coefficients[index] = light_info.linear_coefficient;
+                        light_upload_information.quadratic_coefficients[index] = light_info.quadratic_coefficient;
+                        light_upload_information.directions[index] = light_info.direction.unwrap();
+                        light_upload_information.fov[index] = light_info.fov.unwrap();
+                        light_upload_information.cutoff[index] = light_info.cutoff.unwrap();
+                        light_upload_information.outer_cutoff[index] = light_info.outer_cutoff.unwrap();
+                    }
 
-            previous_point_lights.insert(*point_light);
+                    draw_param.write_uniform_value("pointLightPosition", light_upload_information.positions);
+                    draw_param.write_uniform_value("pointLightDirection", light_upload_information.directions);
+                    draw_param.write_uniform_value("pointLightDiffuseColour", light_upload_information.diffuse_colours);
+                    draw_param.write_uniform_value("pointLightSpecularColour", light_upload_information.specular_colours);
+                    draw_param.write_uniform_value("pointLightAmbientColour", light_upload_information.ambient_colours);
+                    draw_param.write_uniform_value("pointLightLinearCoefficient", light_upload_information.linear_coefficients);
+                    draw_param.write_uniform_value("pointLightQuadraticCoefficient", light_upload_information.quadratic_coefficients);
+                    draw_param.write_uniform_value("cutOff", light_upload_information.cutoff);
+                    draw_param.write_uniform_value("outerCutoff", light_upload_information.outer_cutoff);
+                }
         }
 
-        draw_param.write_uniform_value("pointLightPosition", light_upload_information.positions);
-        draw_param.write_uniform_value("pointLightDirection", light_upload_information.directions);
-        draw_param.write_uniform_value("pointLightDiffuseColour", light_upload_information.diffuse_colours);
-        draw_param.write_uniform_value("pointLightSpecularColour", light_upload_information.specular_colours);
-        draw_param.write_uniform_value("pointLightAmbientColour", light_upload_information.ambient_colours);
-        draw_param.write_uniform_value("pointLightLinearCoefficient", light_upload_information.linear_coefficients);
-        draw_param.write_uniform_value("pointLightQuadraticCoefficient", light_upload_information.quadratic_coefficients);
-        draw_param.write_uniform_value("cutOff", light_upload_information.cutoff);
-        draw_param.write_uniform_value("outerCutoff", light_upload_information.outer_cutoff);
-        draw_param.write_uniform_value("numberPointLights", vec![number_rendered_point_lights as u32]);
+        draw_param.write_uniform_value("numberPointLights", vec![rendered_lights.len() as u32]);
 
         // This map is looked at the shadow flow when determining what lights need to have a shadow map
         // created for them; lights being rendered have a priority
@@ -785,13 +1365,15 @@ impl RenderSystem
         true
     }
 
-    /// Uploads nearby/visible spot lights to the second render pass uniforms, and marks the
-    /// the lights as being rendered for use in the shadow flow
+    /// Uploads nearby/visible spot lights to the second render pass, and marks the
+    /// the lights as being rendered for use in the shadow flow. If the render pass has light storage
+    /// buffers, all visible lights are uploaded to them; otherwise lights are written to uniform arrays
     ///
     /// `draw_param` - the variable required to query nearby lights and upload them as uniforms
     /// `directional_lights` - map of entity ids that identify spot lights
+    /// `light_storage` - the storage buffer to write the lights to, if the render pass has one
     fn upload_spot_lights(previous_spot_lights: &mut HashSet<EntityId>, visible_world_sections: &HashSet<UniqueWorldSectionId>, draw_param: &mut DrawParam,
-                          spot_lights: &mut HashSet::<EntityId>, max_spot_lights: u16) -> AnyLightSourceVisible
+                          spot_lights: &mut HashSet::<EntityId>, max_spot_lights: u16, light_storage: Option<&mut LightStorageBuffer>) -> AnyLightSourceVisible
     {
         let visible_spot_lights = shadow_flow::find_nearby_lights
             (
@@ -805,38 +1387,73 @@ impl RenderSystem
             return false;
         }
 
-        let number_rendered_spot_lights = visible_spot_lights.len().min(max_spot_lights as usize);
-        let mut light_upload_information = LightUploadInformation::new(max_spot_lights as usize);
+        let light_significance = RenderSystem::light_significance(draw_param, true);
+        let rendered_lights = RenderSystem::select_rendered_lights(previous_spot_lights, &visible_spot_lights, max_spot_lights, light_storage.is_some(), light_significance);
 
-        let existing_lights = previous_spot_lights.intersection(&visible_spot_lights).map(|x| *x).collect::<HashSet<EntityId>>();
-        previous_spot_lights.clear();
-
-        for (index, spot_light) in existing_lights.iter().chain(visible_spot_lights.iter()).take(number_rendered_spot_lights).enumerate()
+        match light_storage
         {
-            let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*spot_light).unwrap();
-            let position = draw_param.get_logical_ecs().get_ref::<Position>(*spot_light).unwrap();
+            Some(light_storage) =>
+                {
+                    let lights = rendered_lights.iter().map(|x|
+                        {
+                            let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*x).unwrap();
+                            let position = render_space_position(draw_param.get_logical_ecs(), *x).unwrap();
+                            let (shape_right, shape_up, two_sided) = RenderSystem::spot_light_shape(draw_param.get_logical_ecs(), *x);
 
-            light_upload_information.positions[index] = position.get_position();
-            light_upload_information.diffuse_colours[index] = light_info.diffuse_colour;
-            light_upload_information.specular_colours[index] = light_info.specular_colour;
-            light_upload_information.ambient_colours[index] = light_info.ambient_colour;
-            light_upload_information.linear_coefficients[index] = light_info.linear_coefficient;
-            light_upload_information.quadratic_coefficients[index] = light_info.quadratic_coefficient;
-            light_upload_information.light_radius[index] = light_info.radius;
-            let volume_info = vec4(position.get_position().x, position.get_position().y, position.get_position().z, light_info.radius);
-            light_upload_information.light_volume_information[index] = volume_info;
+                            SpotLightStorage
+                            {
+                                position: vec4(position.x, position.y, position.z, light_info.radius),
+                                diffuse_colour: vec4(light_info.diffuse_colour.x, light_info.diffuse_colour.y, light_info.diffuse_colour.z, 0.0),
+                                specular_colour: vec4(light_info.specular_colour.x, light_info.specular_colour.y, light_info.specular_colour.z, 0.0),
+                                ambient_colour: light_info.ambient_colour,
+                                attenuation: vec4(light_info.linear_coefficient, light_info.quadratic_coefficient, if two_sided { 1.0 } else { 0.0 }, 0.0),
+                                shape_right,
+                                shape_up
+                            }
+                        }).collect::<Vec<SpotLightStorage>>();
+
+                    // The position of a spot light in the storage buffer holds its radius in the w component. Lights
+                    // shining from a shape reach further by the size of the shape
+                    let light_volumes = lights.iter().map(|x|
+                        {
+                            let shape_extent = x.shape_right.xyz().magnitude() + x.shape_up.xyz().magnitude() + x.shape_up.w;
+                            LightVolume{ position: x.position.xyz(), radius: x.position.w + shape_extent }
+                        }).collect::<Vec<LightVolume>>();
 
-            previous_spot_lights.insert(*spot_light);
+                    light_storage.upload(&lights);
+                    light_storage.set_light_volumes(light_volumes);
+                },
+            None =>
+                {
+                    let mut light_upload_information = LightUploadInformation::new(max_spot_lights as usize);
+
+                    for (index, spot_light) in rendered_lights.iter().enumerate()
+                    {
+                        let light_info = draw_param.get_logical_ecs().get_ref::<LightInformation>(*spot_light).unwrap();
+                        let position = render_space_position(draw_param.get_logical_ecs(), *spot_light).unwrap();
+
+                        light_upload_information.positions[index] = position;
+                        light_upload_information.diffuse_colours[index] = light_info.diffuse_colour;
+                        light_upload_information.specular_colours[index] = light_info.specular_colour;
+                        light_upload_information.ambient_colours[index] = light_info.ambient_colour;
+                        light_upload_information.linear_coefficients[index] = light_info.linear_coefficient;
+                        light_upload_information.quadratic_coefficients[index] = light_info.quadratic_coefficient;
+                        light_upload_information.light_radius[index] = light_info.radius;
+                        let volume_info = vec4(position.x, position.y, position.z, light_info.radius);
+                        light_upload_information.light_volume_information[index] = volume_info;
+                    }
+
+                    draw_param.write_uniform_value("spotLightPosition", light_upload_information.positions);
+                    draw_param.write_uniform_value("spotLightDiffuseColour", light_upload_information.diffuse_colours);
+                    draw_param.write_uniform_value("spotLightSpecularColour", light_upload_information.specular_colours);
+                    draw_param.write_uniform_value("spotLightAmbientColour", light_upload_information.ambient_colours);
+                    draw_param.write_uniform_value("spotLightLinearCoefficient", light_upload_information.linear_coefficients);
+                    draw_param.write_uniform_value("spotLightQuadraticCoefficient", light_upload_information.quadratic_coefficients);
+                    draw_param.write_uniform_value("spotLightRadius", light_upload_information.light_radius);
+                }
         }
 
-        draw_param.write_uniform_value("spotLightPosition", light_upload_information.positions);
-        draw_param.write_uniform_value("spotLightDiffuseColour", light_upload_information.diffuse_colours);
-        draw_param.write_uniform_value("spotLightSpecularColour", light_upload_information.specular_colours);
-        draw_param.write_uniform_value("spotLightAmbientColour", light_upload_information.ambient_colours);
-        draw_param.write_uniform_value("spotLightLinearCoefficient", light_upload_information.linear_coefficients);
-        draw_param.write_uniform_value("spotLightQuadraticCoefficient", light_upload_information.quadratic_coefficients);
-        draw_param.write_uniform_value("spotLightRadius", light_upload_information.light_radius);
-        draw_param.write_uniform_value("numberSpotLights", vec![light_upload_information.number_lights as u32]);
+        draw_param.write_uniform_value("numberSpotLights", vec![rendered_lights.len() as u32]);
 
         // This map is looked at the shadow flow when determining what lights need to have a shadow map
         // created for them; lights being rendered have a priority
@@ -844,11 +1461,130 @@ impl RenderSystem
         true
     }
 
+    /// Finds the shape a spot light shines from, giving the vectors describing the shape as stored in the
+    /// light storage buffer and if the light shines from both sides of the shape
+    ///
+    /// `ecs` - the ECS holding the light
+    /// `entity_id` - the spot light to find the shape of
+    fn spot_light_shape(ecs: &ECS, entity_id: EntityId) -> (TVec4<f32>, TVec4<f32>, bool)
+    {
+        if let Some(area_light) = ecs.get_ref::<AreaLight>(entity_id)
+        {
+            let matrix = ecs.get_ref::<TransformationMatrix>(entity_id).map(|x| x.get_matrix()).unwrap_or_else(nalgebra_glm::identity);
+            let right = nalgebra_glm::normalize(&matrix.column(0).xyz());
+            let up = nalgebra_glm::normalize(&matrix.column(1).xyz());
+
+            let (half_width, half_height, shape) = match area_light.shape
+            {
+                AreaLightShape::Rectangle{ width, height } => (width * 0.5, height * 0.5, SPOT_LIGHT_SHAPE_RECTANGLE),
+                AreaLightShape::Disk{ radius } => (radius, radius, SPOT_LIGHT_SHAPE_DISK)
+            };
+
+            // The shader divides by the half extents to find the axes of the shape
+            let right = right * half_width.max(f32::EPSILON);
+            let up = up * half_height.max(f32::EPSILON);
+
+            return (vec4(right.x, right.y, right.z, shape), vec4(up.x, up.y, up.z, 0.0), area_light.two_sided);
+        }
+
+        if ecs.get_ref::<Emissive>(entity_id).is_some()
+        {
+            let radius = ecs.get_ref::<StaticAABB>(entity_id)
+                .map(|x| x.x_range.length().min(x.y_range.length()).min(x.z_range.length()) * 0.5)
+                .unwrap_or(0.0);
+
+            return (vec4(0.0, 0.0, 0.0, SPOT_LIGHT_SHAPE_SPHERE), vec4(0.0, 0.0, 0.0, radius), false);
+        }
+
+        (vec4(0.0, 0.0, 0.0, SPOT_LIGHT_SHAPE_POINT), vec4(0.0, 0.0, 0.0, 0.0), false)
+    }
+
+    /// Determines which of the visible lights are rendered. When more lights are visible than can be rendered,
+    /// the most significant lights are chosen. Lights that were rendered in the previous frame are favoured
+    /// so that lights of similar significance do not flicker as the camera moves
+    ///
+    /// `previous_lights` - the lights rendered last frame; updated to hold the lights rendered this frame
+    /// `visible_lights` - the lights that are visible this frame
+    /// `max_lights` - the maximum number of lights that fit in the uniform arrays
+    /// `unbounded` - true if the lights are written to a light storage buffer, which has no maximum
+    /// `light_significance` - function giving how much a light contributes to the rendered scene
+    fn select_rendered_lights<F: Fn(EntityId) -> f32>(previous_lights: &mut HashSet<EntityId>, visible_lights: &HashSet<EntityId>, max_lights: u16,
+                                                      unbounded: bool, light_significance: F) -> Vec<EntityId>
+    {
+        let rendered_lights = if unbounded || visible_lights.len() <= max_lights as usize
+        {
+            visible_lights.iter().map(|x| *x).collect::<Vec<EntityId>>()
+        }
+        else
+        {
+            let mut ranked_lights = visible_lights.iter()
+                .map(|x|
+                    {
+                        let significance = light_significance(*x);
+
+                        if previous_lights.contains(x)
+                        {
+                            (*x, significance * PREVIOUS_LIGHT_SIGNIFICANCE_FACTOR)
+                        }
+                        else
+                        {
+                            (*x, significance)
+                        }
+                    })
+                .collect::<Vec<(EntityId, f32)>>();
+
+            // Ties are broken by the entity id so that the order does not depend on the order of the hash set
+            ranked_lights.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+            ranked_lights.into_iter().take(max_lights as usize).map(|x| x.0).collect::<Vec<EntityId>>()
+        };
+
+        previous_lights.clear();
+        previous_lights.extend(rendered_lights.iter());
+        rendered_lights
+    }
+
+    /// Creates the function giving the significance of a light, which is its brightness attenuated by its
+    /// distance to the camera. Lights without a position, such as directional lights, are not attenuated
+    ///
+    /// `draw_param` - the variable holding the camera and the lights
+    /// `positioned` - true if the lights being ranked have a position
+    fn light_significance<'b>(draw_param: &'b DrawParam, positioned: bool) -> impl Fn(EntityId) -> f32 + 'b
+    {
+        let camera_position = draw_param.get_camera().get_position();
+        let ecs = draw_param.get_logical_ecs();
+
+        move |entity_id: EntityId|
+            {
+                let light_info = match ecs.get_ref::<LightInformation>(entity_id)
+                {
+                    Some(i) => i,
+                    None => return 0.0
+                };
+
+                let brightness = nalgebra_glm::comp_max(&light_info.diffuse_colour).max(nalgebra_glm::comp_max(&light_info.specular_colour));
+
+                if !positioned
+                {
+                    return brightness;
+                }
+
+                let distance = match ecs.get_ref::<Position>(entity_id)
+                {
+                    Some(i) => nalgebra_glm::distance(&i.get_position(), &camera_position),
+                    None => return brightness
+                };
+
+                brightness / (1.0 + light_info.linear_coefficient * distance + light_info.quadratic_coefficient * distance * distance)
+            }
+    }
+
     fn upload_shadow_maps(draw_param: &mut DrawParam, matrices: &Vec<TMat4<f32>>, view_matrices: &Vec<TMat4<f32>>, indexes: &Vec<u32>)
     {
         assert_eq!(matrices.len(), indexes.len());
 
-        let mut matrices_data = matrices.clone();
+        // The light matrices take positions in the world, while the uploaded instance data is relative to the
+        // render origin
+        let mut matrices_data = matrices.iter().map(render_space_light_matrix).collect::<Vec<TMat4<f32>>>();
 
         while matrices_data.len() < 18
         {
@@ -898,7 +1634,6 @@ pub struct LightUploadInformation
     cutoff: Vec<f32>,
     outer_cutoff: Vec<f32>,
     light_radius: Vec<f32>,
-    number_lights: usize,
     light_volume_information: Vec<TVec4<f32>>
 }
 
@@ -923,7 +1658,6 @@ impl LightUploadInformation
             cutoff: vec![0.0; number_lights],
             outer_cutoff: vec![0.0; number_lights],
             light_radius: vec![0.0; number_lights],
-            number_lights,
             light_volume_information: vec![vec4(0.0, 0.0, 0.0, 0.0); number_lights],
         }
     }
@@ -1,10 +1,12 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 use hashbrown::{HashMap, HashSet};
 use nalgebra_glm::{TMat4, TVec3, TVec4};
 use serde::{Serialize, Deserialize};
 use crate::exports::camera_object::Camera;
+use crate::exports::light_components::FindLightType;
 use crate::exports::load_models::MaxNumLights;
-use crate::exports::rendering::{DrawParam, LevelOfView};
+use crate::exports::rendering::{DrawParam, LevelOfView, ScreenSpaceReflections, ShadowQuality};
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
 use crate::render_components::frame_buffer::FBO;
@@ -26,7 +28,18 @@ pub enum LayoutType
     Vec3Float,
     Vec4Float,
     Vec4Uint,
-    Mat4x4Float
+    Mat4x4Float,
+    /// A vec3 holding the tangent of each vertex, written from the tangents of a MeshGeometry. Used by the
+    /// tangent space functions of render systems created with tangent space
+    Tangent,
+    /// A vec3 holding the bitangent of each vertex, written from the bitangents of a MeshGeometry
+    Bitangent,
+    /// A vec4 holding the InstanceParams of each entity, written by the engine rather than by the instance
+    /// layout update function. Added to render systems created with instance parameters
+    InstanceParams,
+    /// A vec4 holding the amount each entity has dissolved followed by the colour of its dissolving edge,
+    /// written by the engine from the Dissolve component. Added to render systems created with dissolve support
+    Dissolve
 }
 
 impl LayoutType
@@ -39,7 +52,9 @@ impl LayoutType
             LayoutType::Vec3Float => "vec3".to_string(),
             LayoutType::Vec4Float => "vec4".to_string(),
             LayoutType::Vec4Uint => "uvec4".to_string(),
-            LayoutType::Mat4x4Float => "mat4x4".to_string()
+            LayoutType::Mat4x4Float => "mat4x4".to_string(),
+            LayoutType::Tangent | LayoutType::Bitangent => "vec3".to_string(),
+            LayoutType::InstanceParams | LayoutType::Dissolve => "vec4".to_string()
         }
     }
 }
@@ -49,7 +64,8 @@ pub type SizeBufferBytes = isize;
 
 /// Specifies the buffer division index for a layout.
 /// The number of buffers specify how many buffers will be used to
-/// avoid stalling the pipeline when the contents of a layout need to be updated
+/// avoid stalling the pipeline when the contents of a layout need to be updated. For per-instance
+/// layouts, it is the least number of frames in the instance upload ring, which always holds at least three
 #[derive(Copy, Clone)]
 pub enum LayoutInstance
 {
@@ -58,6 +74,7 @@ pub enum LayoutInstance
 }
 
 /// Specifies if a layout is to be used for model information or for instancing information
+#[derive(Copy, Clone)]
 pub enum LayoutUse
 {
     PerModel,
@@ -85,6 +102,7 @@ impl IndiceInformation
 
 /// The information required to specify a layout. The shader that a
 /// layout is a part of is implicitly is done as a part of the system builder.
+#[derive(Clone)]
 pub struct LayoutInformation
 {
     pub data_type: LayoutType,
@@ -104,6 +122,7 @@ impl LayoutInformation
 
 /// Specifies layout information for the fragment shader, and the parameters for the texture
 /// that the layout will write to
+#[derive(Clone)]
 pub struct FragLayoutInformation
 {
     pub data_type: LayoutType,
@@ -127,6 +146,7 @@ impl FragLayoutInformation
 }
 
 /// Information to declare GLSL version
+#[derive(Copy, Clone)]
 pub enum GLSLVersion
 {
     Core430
@@ -135,9 +155,9 @@ pub enum GLSLVersion
 impl GLSLVersion
 {
     /// Convert the enum to its string representation
-    pub fn to_string(&self) -> String
+    pub fn to_string(self) -> String
     {
-        match *self
+        match self
         {
             GLSLVersion::Core430 => "#version 430 core".to_string()
         }
@@ -145,6 +165,7 @@ impl GLSLVersion
 }
 
 /// Information to declare constants
+#[derive(Copy, Clone)]
 pub enum ConstantValue
 {
     UInt(u32),
@@ -153,9 +174,9 @@ pub enum ConstantValue
 impl ConstantValue
 {
     /// Convert the enum to its string representation
-    pub fn to_string(&self) -> (String, String)
+    pub fn to_string(self) -> (String, String)
     {
-        match *self
+        match self
         {
             ConstantValue::UInt(i) => ("uint".to_string(), i.to_string())
         }
@@ -163,6 +184,7 @@ impl ConstantValue
 }
 
 /// Specifies the location of a constant (which shader is it in)
+#[derive(Copy, Clone)]
 pub enum ConstantLocation
 {
     VertexShader,
@@ -170,6 +192,7 @@ pub enum ConstantLocation
 }
 
 /// Information to use a constant in a shader
+#[derive(Clone)]
 pub struct Constant
 {
     pub value: ConstantValue,
@@ -220,12 +243,14 @@ impl SharedVariableType
 }
 
 /// Specifies the location of where the out variable are going
+#[derive(Copy, Clone)]
 pub enum SharedTarget
 {
     FragmentShader
 }
 
 /// Information to required to create out variables
+#[derive(Clone)]
 pub struct OutVariables
 {
     pub data_type: SharedVariableType,
@@ -254,7 +279,7 @@ impl OutVariables
 
 /// Specifies the format that a texture can have
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 
 pub enum TextureFormat
 {
@@ -263,10 +288,37 @@ pub enum TextureFormat
     RGB = gl::RGB8,
     RGBA = gl::RGBA8,
     SRGBA = gl::SRGB8_ALPHA8,
-    RGBA16F = gl::RGBA32F,
+    /// The floating point formats hold colours brighter than the window can show without banding
+    RGBA16F = gl::RGBA16F,
+    RGBA32F = gl::RGBA32F,
+    /// Holds colours with more precision than RGBA, but no brighter than the window can show
+    RGB10A2 = gl::RGB10_A2,
+    R11FG11FB10F = gl::R11F_G11F_B10F,
     RG8 = gl::RG8,
 }
 
+impl TextureFormat
+{
+    /// Checks if the format holds colours, rather than depth or stencil values
+    pub fn is_colour(&self) -> bool
+    {
+        !matches!(self, TextureFormat::Depth | TextureFormat::DepthStencil)
+    }
+
+    /// Get the number of bytes a texel of the format takes up. Drivers commonly pad three channel formats
+    /// to four bytes, so they are counted as such
+    pub fn bytes_per_texel(&self) -> usize
+    {
+        match *self
+        {
+            TextureFormat::RG8 => 2,
+            TextureFormat::RGBA16F => 8,
+            TextureFormat::RGBA32F => 16,
+            _ => 4
+        }
+    }
+}
+
 /// Specifies required information to allocate a texture array
 #[derive(Clone)]
 pub struct TextureInformation
@@ -381,6 +433,23 @@ impl UniformType
             UniformType::Mat4Array(_) => "mat4".to_string(),
         }
     }
+
+    /// Creates a copy of the uniform type with the given number of array elements. Non-array
+    /// uniform types are returned unchanged
+    ///
+    /// `number_elements` - the number of elements the array uniform should hold
+    pub fn with_array_length(&self, number_elements: u16) -> UniformType
+    {
+        match *self
+        {
+            UniformType::UIntArray(_) => UniformType::UIntArray(number_elements),
+            UniformType::FloatArray(_) => UniformType::FloatArray(number_elements),
+            UniformType::Vec3Array(_) => UniformType::Vec3Array(number_elements),
+            UniformType::Vec4Array(_) => UniformType::Vec4Array(number_elements),
+            UniformType::Mat4Array(_) => UniformType::Mat4Array(number_elements),
+            _ => *self
+        }
+    }
 }
 
 /// >>>>>>>>>>> Structures <<<<<<<<<<<<<
@@ -435,6 +504,7 @@ pub struct Uniform
 {
     pub name: String,
     pub uniform_type: UniformType,
+    pub light_scaling: Option<FindLightType>,
 }
 
 impl UniformBlock
@@ -458,7 +528,19 @@ impl Uniform
     /// `uniform_type` - what data will the uniform hold
     pub fn new<T: Into<String>>(name: T, uniform_type: UniformType) -> Uniform
     {
-        Uniform { name: name.into(), uniform_type }
+        Uniform { name: name.into(), uniform_type, light_scaling: None }
+    }
+
+    /// Specifies the information needed to create an array uniform whose length follows the maximum
+    /// number of lights of the given type. When the light constraints of a render system change, the
+    /// uniform is resized and the shaders that use it are regenerated
+    ///
+    /// `name` - the name of the uniform
+    /// `uniform_type` - what data will the uniform hold; must be an array type
+    /// `light_type` - the type of light whose maximum count determines the length of the array
+    pub fn new_light_array<T: Into<String>>(name: T, uniform_type: UniformType, light_type: FindLightType) -> Uniform
+    {
+        Uniform { name: name.into(), uniform_type, light_scaling: Some(light_type) }
     }
 
     /// Helper function to determine how large a uniform is based off of its type
@@ -483,6 +565,7 @@ impl Uniform
 /// *********** Builder Structures **********
 
 /// Information to specify vertex shader and update logic
+#[derive(Clone)]
 pub struct VertexShaderInformation
 {
     pub write_generated_shader: Option<String>,
@@ -499,12 +582,14 @@ pub struct VertexShaderInformation
 }
 
 /// Information to specify fragment shader and update logic
+#[derive(Clone)]
 pub struct FragmentShaderInformation
 {
     pub layouts: Vec<FragLayoutInformation>,
     pub write_generated_shader: Option<String>,
     pub include_error_textures: bool,
     pub include_shadow_maps: bool,
+    pub include_light_storage_buffers: bool,
     pub glsl_version: GLSLVersion,
     pub shader_source: PathBuf,
     pub uniforms: Vec<UniformBlock>,
@@ -515,6 +600,14 @@ pub struct FragmentShaderInformation
 
 type EntityLookup = HashMap<String, EntityId>;
 
+/// The frame buffer that the final image of a render system is written to
+#[derive(Copy, Clone)]
+pub struct OutputFramebuffer
+{
+    pub framebuffer: u32, // 0 is the frame buffer of the window
+    pub dimensions: (i32, i32),
+}
+
 /// Holds variables required to prepare information / rendering context before calling the
 /// user-defined draw function
 pub struct DrawPreparationParameters<'a>
@@ -533,10 +626,17 @@ pub struct DrawPreparationParameters<'a>
     pub visible_spot_lights: &'a mut HashSet::<EntityId>,
     pub upload_matrices: &'a Vec<TMat4<f32>>,
     pub upload_indexes: &'a Vec<u32>,
-    pub upload_view_matrices: &'a Vec<TMat4<f32>>
+    pub upload_view_matrices: &'a Vec<TMat4<f32>>,
+
+    pub output_framebuffer: OutputFramebuffer,
+    /// True if the render system is drawing the view it draws into the window every frame, rather than a view
+    /// such as a minimap or shadow map. Only these views are hidden behind the depth of the previous frame
+    pub primary_view: bool,
 }
 
-pub type DrawFunction = fn(&mut DrawParam);
+/// Issues the draw commands of a render system. Closures can be used to give the draw function
+/// configuration, such as which models to draw
+pub type DrawFunction = Arc<dyn Fn(&mut DrawParam) + Send + Sync>;
 
 /// Aggregate structure to hold all required information to build a render system
 pub struct SystemInformation
@@ -555,5 +655,16 @@ pub struct SystemInformation
     pub apply_lights: bool,
     pub max_num_lights: MaxNumLights,
     pub no_light_source_cutoff: f32,
-    pub default_diffuse_factor: f32
+    pub default_diffuse_factor: f32,
+    pub shadow_quality: ShadowQuality,
+    pub clustered_lighting: bool,
+    pub baked_lighting: bool,
+    pub environment_lighting: bool,
+    pub damage_masks: bool,
+    pub material_maps: bool,
+    pub tangent_space: bool,
+    pub shared_texture_arrays: HashMap<String, String>,
+    pub gpu_level_of_views: bool,
+    pub gpu_culling: bool,
+    pub screen_space_reflections: Option<ScreenSpaceReflections>
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
+use std::sync::Arc;
 use nalgebra_glm::vec3;
-use render_engine::exports::load_models::{DefaultRenderSystemArgs, MaxNumLights, RenderSystemType, UserLoadRenderSystems, UserUploadInformation};
-use render_engine::exports::rendering::{DrawParam, LevelOfView, ModelDrawCommand};
+use render_engine::exports::load_models::{DefaultRenderSystemArgs, MaxNumLights, RenderSystemType, UserUploadInformation};
+use render_engine::exports::rendering::{DrawParam, LevelOfView, ModelDrawCommand, ShadowQuality};
 use render_engine::prelude::default_render_system::instance_layout_fn;
 use crate::space_logic::solar_system::skybox::create_space_skybox;
 
@@ -8,9 +9,9 @@ pub fn load_render_systems(upload_info: &mut UserUploadInformation, render_dista
 {
     let default_render_system_args = DefaultRenderSystemArgs
     {
-        draw_function,
-        draw_light_function,
-        draw_transparency_function: draw_transparent_objects_function,
+        draw_function: Arc::new(draw_function),
+        draw_light_function: Arc::new(draw_light_function),
+        draw_transparency_function: Arc::new(draw_transparent_objects_function),
         instance_layout_update_fn: instance_layout_fn,
         level_of_views: create_level_of_views(render_distance),
         window_resolution: (1280, 720),
@@ -22,16 +23,15 @@ pub fn load_render_systems(upload_info: &mut UserUploadInformation, render_dista
             spot: 2
         },
         no_light_source_cutoff: 0.2,
-        default_diffuse_factor: 0.2
+        default_diffuse_factor: 0.2,
+        shadow_quality: ShadowQuality::Pcf3x3,
+        clustered_lighting: true,
+        gpu_level_of_views: false,
+        gpu_culling: false,
+        screen_space_reflections: None
     };
 
-    let render_system = UserLoadRenderSystems
-    {
-        render_system: RenderSystemType::Default(default_render_system_args),
-        render_system_name: "default".to_string()
-    };
-
-    upload_info.render_systems.push(render_system);
+    upload_info.add_render_system("default", RenderSystemType::Default(default_render_system_args));
 }
 
 fn draw_function(mut draw_param:  &mut DrawParam)
@@ -99,8 +99,8 @@ fn draw_transparent_objects_function(mut draw_param:  &mut DrawParam)
 fn update_common_uniforms(draw_param: &mut DrawParam)
 {
     draw_param.write_uniform_value("projectionMatrix", vec![draw_param.get_camera().get_projection_matrix()]);
-    draw_param.write_uniform_value("viewMatrix", vec![draw_param.get_camera().get_view_matrix()]);
-    draw_param.write_uniform_value("cameraLocation", vec![draw_param.get_camera().get_position()]);
+    draw_param.write_uniform_value("viewMatrix", vec![draw_param.get_camera().get_render_view_matrix()]);
+    draw_param.write_uniform_value("cameraLocation", vec![draw_param.get_camera().get_render_position()]);
 }
 
 fn create_level_of_views(render_distance: f32) -> Vec<LevelOfView>
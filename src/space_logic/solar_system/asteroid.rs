@@ -53,6 +53,7 @@ fn load_asteroid(upload_info: &mut UserUploadInformation)
         ],
         custom_level_of_view: None,
         solid_colour_texture: None,
+        import_settings: None,
     };
 
     upload_info.load_models.push(asteroid_model);
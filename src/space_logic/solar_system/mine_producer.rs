@@ -20,7 +20,7 @@ fn mine_producer_logic(_: EntityId, _: &ECS, _tree: &BoundingBoxTree, _: f32) ->
 
 pub fn create_mine_producer(upload_info: &mut UserUploadInformation)
 {
-    upload_info.instance_logic.entity_logic.insert(TypeIdentifier::from(TypeId::of::<MineProducer>()), EntityLogic{ logic: mine_producer_logic });
+    upload_info.instance_logic.entity_logic.insert(TypeIdentifier::from(TypeId::of::<MineProducer>()), EntityLogic::new(mine_producer_logic));
 
     load_mine_producer(upload_info);
     load_mine_producer_instances(upload_info);
@@ -46,7 +46,8 @@ fn load_mine_producer(upload_info: &mut UserUploadInformation)
             get_mine_producer_model()
         ],
         custom_level_of_view: None,
-        solid_colour_texture: Some(vec4(200, 150, 200, 64))
+        solid_colour_texture: Some(vec4(200, 150, 200, 64)),
+        import_settings: None,
     };
 
     upload_info.load_models.push(mine_producer_model);
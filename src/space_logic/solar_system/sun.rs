@@ -1,7 +1,7 @@
 use std::any::TypeId;
-use nalgebra_glm::{vec3, vec4};
+use nalgebra_glm::vec3;
 use render_engine::exports::entity_transformer::EntityTransformationBuilder;
-use render_engine::exports::light_components::{FindLightType, LightInformation, SpotLight};
+use render_engine::exports::light_components::{Emissive, FindLightType, LightInformation, SpotLight};
 use render_engine::exports::load_models::{UserLoadModelInfo, UserLoadModelInstances, UserUploadInformation};
 use render_engine::exports::movement_components::{Position, Rotation, Scale, VelocityRotation};
 use render_engine::objects::ecs::{ECS, TypeIdentifier};
@@ -43,6 +43,7 @@ fn load_star_model(upload_info: &mut UserUploadInformation)
         ],
         custom_level_of_view: None,
         solid_colour_texture: None,
+        import_settings: None,
     };
 
     let blue_star_model = UserLoadModelInfo
@@ -58,7 +59,8 @@ fn load_star_model(upload_info: &mut UserUploadInformation)
             get_blue_star_model(),
         ],
         custom_level_of_view: None,
-        solid_colour_texture: None
+        solid_colour_texture: None,
+        import_settings: None,
     };
 
     upload_info.load_models.push(yellow_star_model);
@@ -102,21 +104,11 @@ fn load_yellow_star_instance_helper(ecs: &mut ECS, created_entities: Vec<EntityI
             .with_scale(Scale::new(vec3(10.0, 10.0, 10.0)))
             .apply_choices(aabb, ecs, bounding_tree);
 
-        let light_information = LightInformation
-        {
-            radius: 500.0,
-            diffuse_colour: vec3(1.0, 0.6, 0.0),
-            specular_colour: vec3(1.0, 0.6, 0.0),
-            ambient_colour: vec4(1.0, 0.6, 0.0, 0.25),
-            linear_coefficient: 0.007,
-            quadratic_coefficient: 0.0002,
-            cutoff: None,
-            outer_cutoff: None,
-            direction: None,
-            fov: None,
-        };
+        // The star lights nearby entities from its surface rather than from its centre
+        let emissive = Emissive{ colour: vec3(1.0, 0.6, 0.0), strength: 1.0 };
 
-        ecs.write_component::<LightInformation>(*entity, light_information);
+        ecs.write_component::<Emissive>(*entity, emissive);
+        ecs.write_component::<LightInformation>(*entity, emissive.light_information(500.0));
         ecs.write_sortable_component(*entity, TypeIdentifier::from(TypeId::of::<SpotLight>()));
 
         let mut lock = INSTANCES.lock().unwrap();
@@ -136,21 +128,10 @@ fn load_blue_star_instance_helper(ecs: &mut ECS, created_entities: Vec<EntityId>
             .with_scale(Scale::new(vec3(15.0, 15.0, 15.0)))
             .apply_choices(aabb, ecs, bounding_tree);
 
-        let light_information = LightInformation
-        {
-            radius: 500.0,
-            diffuse_colour: vec3(0.2, 0.3, 1.0),
-            specular_colour: vec3(0.2, 0.3, 1.0),
-            ambient_colour: vec4(0.2, 0.3, 1.0, 0.25),
-            linear_coefficient: 0.007,
-            quadratic_coefficient: 0.0002,
-            cutoff: None,
-            outer_cutoff: None,
-            direction: None,
-            fov: None,
-        };
+        let emissive = Emissive{ colour: vec3(0.2, 0.3, 1.0), strength: 1.0 };
 
-        ecs.write_component::<LightInformation>(*entity, light_information);
+        ecs.write_component::<Emissive>(*entity, emissive);
+        ecs.write_component::<LightInformation>(*entity, emissive.light_information(500.0));
         ecs.write_sortable_component(*entity, TypeIdentifier::from(TypeId::of::<SpotLight>()));
 
         let mut lock = INSTANCES.lock().unwrap();
@@ -14,7 +14,7 @@ pub fn per_frame_logic(_: EntityId, _: &ECS, _: &BoundingBoxTree, _: f32) -> Vec
     vec![]
 }
 
-pub fn collision_logic(self_id: EntityId, other_id: EntityIdRead, ecs: &ECS, _: &BoundingBoxTree) -> Vec<EntityChangeInformation>
+pub fn collision_logic(self_id: EntityId, other_id: EntityIdRead, _: f32, ecs: &ECS, _: &BoundingBoxTree) -> Vec<EntityChangeInformation>
 {
     println!("collision");
     if let Some(entity_type) = ecs.get_entity_type_read(other_id)
@@ -3,6 +3,7 @@ use nalgebra_glm::{vec3, vec4};
 use render_engine::exports::entity_transformer::EntityTransformationBuilder;
 use render_engine::exports::load_models::{UserLoadModelInfo, UserLoadModelInstances, UserUploadInformation};
 use render_engine::exports::movement_components::{Position, Scale};
+use render_engine::exports::transparency::Transparent;
 use render_engine::objects::ecs::{ECS, TypeIdentifier};
 use render_engine::objects::entity_change_request::EntityChangeInformation;
 use render_engine::objects::entity_id::EntityId;
@@ -43,7 +44,8 @@ fn load_wormhole(upload_info: &mut UserUploadInformation)
             get_wormhole_model()
         ],
         custom_level_of_view: None,
-        solid_colour_texture: Some(vec4(230, 87, 230, 64))
+        solid_colour_texture: Some(vec4(230, 87, 230, 64)),
+        import_settings: None,
     };
 
     upload_info.load_models.push(wormhole_model);
@@ -71,5 +73,6 @@ fn load_wormhole_instance_helper(ecs: &mut ECS, created_entities: Vec<EntityId>,
             .apply_choices(aabb, ecs, bounding_tree);
 
         ecs.write_entity_type(entity, TypeIdentifier::from(TypeId::of::<WormHole>()));
+        ecs.write_component::<Transparent>(entity, Transparent);
     }
 }
\ No newline at end of file
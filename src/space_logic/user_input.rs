@@ -1,4 +1,4 @@
-use glfw::Key;
+use glfw::{Key, MouseButton};
 use nalgebra_glm::vec3;
 use render_engine::exports::camera_object::{Camera, MovementFactor};
 use render_engine::exports::logic_components::UserInputLogic;
@@ -9,15 +9,35 @@ use render_engine::objects::entity_id::EntityId;
 use render_engine::window::gl_window::MIDDLE_BUTTON;
 use render_engine::window::input_state::{CurrentFrameInput, InputHistory};
 use render_engine::world::bounding_box_tree_v2::BoundingBoxTree;
+use tracing::debug;
 
 pub fn create_user_logic() -> Vec<UserInputLogic>
 {
     vec!
     [
-        UserInputLogic{ logic: move_camera }
+        UserInputLogic::new(move_camera),
+        UserInputLogic::new(select_entity)
     ]
 }
 
+pub fn select_entity(_: EntityId, ecs: &ECS, bounding_box_tree: &BoundingBoxTree, camera: &mut Camera, _: &InputHistory, current_input: &CurrentFrameInput, _: f32) -> Vec<EntityChangeInformation>
+{
+    if current_input.is_mouse_down(MouseButton::Button1)
+    {
+        if let Some((cursor_x, cursor_y)) = current_input.get_latest_cursor_pos()
+        {
+            let viewport = (0, 0, camera.window_width, camera.window_height);
+
+            if let Some(entity) = camera.pick_entity(cursor_x, cursor_y, viewport, ecs, bounding_box_tree)
+            {
+                debug!("Selected entity {:?}", entity);
+            }
+        }
+    }
+
+    Vec::new()
+}
+
 pub fn move_camera(this: EntityId, ecs: &ECS, _: &BoundingBoxTree, camera: &mut Camera, input: &InputHistory, current_input: &CurrentFrameInput, elapsed_time: f32) -> Vec<EntityChangeInformation>
 {
 
@@ -6,9 +6,11 @@ use std::sync::Arc;
 use std::time::Duration;
 use hashbrown::HashMap;
 use parking_lot::{Condvar, Mutex};
+use tracing::warn;
 use crate::{ArrayIndexer, ChangeHistory, EXIT_GRACEFULLY_COUNT, FAILURE_COUNT, FrameVectors,
             get_debug_logs_folder, HISTORY_THREAD_ID, HISTORY_THREAD_SUCCESS_COUNT, RENDER_THREAD_SUCCESS_COUNT};
 use crate::exports::logic_components::OutOfBoundsLogic;
+use crate::exports::memory_budget::{MemoryCategory, needs_reclaiming, record_usage};
 use crate::objects::ecs::{ECS, TypeIdentifier};
 use crate::threads::private_common_structures::{CAMERA, DELTA_TIME};
 use crate::threads::public_common_structures::FrameChange;
@@ -31,6 +33,11 @@ pub struct StoredHistoryState
     game_history_bounding_box_tree: BoundingBoxTree,
     game_history_changes_to_apply: VecDeque<ChangeHistory>,
     out_of_bounds_logic: HashMap<TypeIdentifier, OutOfBoundsLogic>,
+    next_frame_vector_index: usize,
+    synchronization_requested: bool,
+    // Estimated size of the synchronized state and of the changes recorded since, for the memory budget
+    state_bytes: usize,
+    changes_bytes: usize,
 }
 
 impl StoredHistoryState
@@ -44,6 +51,10 @@ impl StoredHistoryState
             game_history_bounding_box_tree: BoundingBoxTree::new(0, 0),
             game_history_changes_to_apply: VecDeque::new(),
             out_of_bounds_logic: HashMap::default(),
+            next_frame_vector_index: 0,
+            synchronization_requested: false,
+            state_bytes: 0,
+            changes_bytes: 0,
         }
     }
 
@@ -58,6 +69,48 @@ impl StoredHistoryState
         self.game_history_bounding_box_tree = tree.clone();
         self.game_history_changes_to_apply.clear();
         self.out_of_bounds_logic = out_of_bounds_logic.clone();
+        self.synchronization_requested = false;
+
+        self.state_bytes = (bincode::serialized_size(&self.game_history_ecs).unwrap_or(0) + bincode::serialized_size(&self.game_history_bounding_box_tree).unwrap_or(0)) as usize;
+        self.changes_bytes = 0;
+        record_usage(MemoryCategory::History, self.state_bytes);
+    }
+
+    /// Records the changes of a frame, keeping track of the memory they use
+    ///
+    /// `frame_changes` - the changes made during the frame
+    fn push_changes(&mut self, frame_changes: ChangeHistory)
+    {
+        self.changes_bytes += bincode::serialized_size(&frame_changes.changes).unwrap_or(0) as usize;
+        self.game_history_changes_to_apply.push_back(frame_changes);
+        record_usage(MemoryCategory::History, self.state_bytes + self.changes_bytes);
+    }
+
+    /// Checks if the recorded changes should be dropped by synchronizing the state with the game state to
+    /// stay within the memory budget. This is only done if the changes use more memory than the synchronized
+    /// state, as otherwise synchronizing would not free enough memory to be worth the copy of the game state
+    fn should_reclaim_changes(&self) -> bool
+    {
+        needs_reclaiming(MemoryCategory::History) && self.changes_bytes > self.state_bytes
+    }
+
+    /// Requests that the state is synchronized with the game state, such as after the history thread
+    /// restarted and the changes it was recording may have been lost
+    pub fn request_synchronization(&mut self)
+    {
+        self.synchronization_requested = true;
+    }
+
+    /// Checks if the state needs to be synchronized with the game state
+    pub fn is_synchronization_requested(&self) -> bool
+    {
+        self.synchronization_requested
+    }
+
+    /// Get the index of the frame vector that the history thread will record next
+    pub fn get_next_frame_vector_index(&self) -> usize
+    {
+        self.next_frame_vector_index
     }
 }
 
@@ -68,6 +121,10 @@ pub fn store_history(mut args: HistoryInputArgs)
 {
     let update_timeout_seconds = Duration::from_secs(5);
 
+    // When restarted after a panic, changes that were recorded before the state is synchronized again
+    // are already a part of the synchronized state, and so are discarded
+    let mut awaiting_synchronization = args.state.lock().is_synchronization_requested();
+
     loop
     {
         let mut frame_vector = args.frame_vectors[args.indexer.index()].lock();
@@ -94,7 +151,23 @@ pub fn store_history(mut args: HistoryInputArgs)
         let mut frame_changes = ChangeHistory::new(HISTORY_THREAD_ID);
         swap(&mut frame_changes, &mut *frame_vector);
 
-        state.game_history_changes_to_apply.push_back(frame_changes);
+        if awaiting_synchronization
+        {
+            awaiting_synchronization = !frame_changes.synchronized_state;
+        }
+        else
+        {
+            state.push_changes(frame_changes);
+
+            // Recording restarts from the current game state, so the recorded changes are no longer needed.
+            // Changes of frames before the synchronization are part of the synchronized state
+            if state.should_reclaim_changes()
+            {
+                warn!("Recorded history is over its memory budget; history is recorded again from the current game state");
+                state.request_synchronization();
+                awaiting_synchronization = true;
+            }
+        }
 
         // Check if render thread crashed when it applied the set of changes that this thread will apply
         // at some point in the future
@@ -107,6 +180,7 @@ pub fn store_history(mut args: HistoryInputArgs)
 
         frame_vector.last_thread_to_access = HISTORY_THREAD_ID;
         args.indexer = args.indexer.increment();
+        state.next_frame_vector_index = args.indexer.index();
         *HISTORY_THREAD_SUCCESS_COUNT.lock() += 1;
 
         // Tell render thread it can overwrite current frame vector if it is waiting to do so
@@ -138,7 +212,8 @@ fn store_last_camera_status(recorded_state: &mut StoredHistoryState)
             FrameChange::EndFrameChange,
         ]),
         timestamp: 0,
-        last_thread_to_access: HISTORY_THREAD_ID
+        last_thread_to_access: HISTORY_THREAD_ID,
+        synchronized_state: false,
     };
 
     recorded_state.game_history_changes_to_apply.push_back(last_frame_change);
@@ -181,6 +256,17 @@ pub fn write_to_disk(mut recorded_state: StoredHistoryState)
     let tree_bytes = attempt_write(&bounding_box_tree_string, "Bounding Box Tree Contents");
     bytes_written_history.push(tree_bytes);
 
+    // Resources are not serialized with the ECS, and so are recorded as a change applied in the first frame
+    let resource_changes = recorded_state.game_history_ecs.resource_changes();
+
+    if !resource_changes.is_empty()
+    {
+        let serialized_change = bincode::serialize(&FrameChange::EntityChange(resource_changes)).unwrap();
+
+        let change_bytes = attempt_write(&serialized_change, "Resource contents");
+        bytes_written_history.push(change_bytes);
+    }
+
     for x in recorded_state.game_history_changes_to_apply
     {
         if let Some(changes) = x.changes
@@ -13,6 +13,7 @@ pub enum FrameChange
     WindowDimensionsChange((i32, i32)), // Width, Height
     EntityChange(Vec<EntityChangeInformation>),
     EndFrameChange,
+    StateHash(u64), // Hash of the gameplay components at the start of the frame
 }
 
 /// Represents the all of the changes that occur in a single frame
@@ -21,7 +22,11 @@ pub struct ChangeHistory
 {
     pub changes: Option<Vec<FrameChange>>,
     pub timestamp: u64,
-    pub last_thread_to_access: SerializableThreadId
+    pub last_thread_to_access: SerializableThreadId,
+    /// True if the history state was synchronized with the game state at the end of this frame. Only
+    /// used while the history thread is being restarted, and so is not recorded
+    #[serde(skip)]
+    pub synchronized_state: bool,
 }
 
 /// A thread ID that can be written to disk
@@ -52,6 +57,7 @@ impl ChangeHistory
             last_thread_to_access: thread_id,
             timestamp: 0,
             changes: None,
+            synchronized_state: false,
         }
     }
 }
\ No newline at end of file
@@ -2,23 +2,46 @@ use std::fs::File;
 use std::io::Write;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use glfw::Key::{Escape, Insert, Right, Up};
+use glfw::{ContextRobustnessHint, WindowHint};
+use glfw::Key::{Escape, F3, Insert, Right, Up};
 use hashbrown::HashMap;
 use parking_lot::{Condvar, Mutex, MutexGuard};
+use rayon::prelude::*;
+use tracing::{error, info};
 use crate::{ArrayIndexer, ChangeHistory, EXIT_GRACEFULLY_COUNT, FAILURE_COUNT, FrameVectors,
             get_debug_logs_folder, HISTORY_THREAD_SUCCESS_COUNT, LoadParam, RENDER_THREAD_ID,
             RENDER_THREAD_SUCCESS_COUNT, StoredHistoryState, UserUploadInformation};
-use crate::exports::load_models::RenderSystemType;
+use crate::exports::accessibility::{accessibility_settings, ColourFilter, set_colour_filter};
+use crate::exports::engine_hooks::{apply_engine_hook_requests, EngineHook, run_engine_hook};
+use crate::exports::frame_callback::FrameCallbacks;
+use crate::exports::benchmark::BenchmarkRun;
+use crate::exports::launch_self_test::{is_launch_self_test_enabled, record_self_test_report};
+use crate::exports::gl_debug_output::apply_gl_debug_output;
+use crate::exports::frame_statistics::{end_frame, is_statistics_overlay_visible, latest_frame_statistics, set_statistics_overlay_visible};
+use crate::exports::load_models::{CreateRenderSystemFunction, DefaultRenderSystemArgs, RenderSystemType};
 use crate::exports::logic_components::RenderSystemIndex;
 use crate::exports::rendering::LevelOfView;
+use crate::exports::section_length_tuning::record_tuning_frame;
+use crate::exports::startup_statistics::{record_render_system_init_times, RenderSystemInitTime};
 use crate::exports::user_focused_entities::user_type_identifier;
+use crate::exports::section_export::SectionExport;
+use crate::exports::world_snapshot::{SnapshotRequest, World, WorldSnapshot};
 use crate::flows::pipeline::Pipeline;
+use crate::flows::render_flow::RenderFlow;
+use crate::flows::shared_constants::{RENDER_SYSTEM_NAMES, REQUESTED_SNAPSHOT_OPERATIONS};
 use crate::helper_things::environment::get_asset_folder;
 use crate::models::model_storage::LoadModelInfo;
-use crate::prelude::default_render_system::{create_default_render_system, create_level_of_views};
+use crate::prelude::default_render_system::{create_level_of_views, finish_default_render_system, prepare_default_render_system};
+use crate::render_components::colour_filter_pass::ColourFilterPass;
+use crate::render_components::self_test_pass::run_self_test;
+use crate::render_components::shared_texture_arrays::clear_shared_texture_arrays;
+use crate::render_system::initialize_logic::PreparedRenderSystem;
+use crate::render_system::render_system::RenderSystem;
+use crate::render_system::system_information::DrawFunction;
 use crate::threads::private_common_structures::{CAMERA, DELTA_TIME};
 use crate::threads::public_common_structures::FrameChange;
 use crate::window::gl_window::{GLWindow, GLWindowBuilder};
+use crate::window::statistics_overlay::StatisticsOverlay;
 
 pub struct RenderInputArgs
 {
@@ -29,6 +52,31 @@ pub struct RenderInputArgs
     pub state: Arc<Mutex<StoredHistoryState>>,
 }
 
+/// Specifies how to create a render system again after the GL context is lost
+enum RenderSystemSource
+{
+    Default(DefaultRenderSystemArgs),
+    Function(CreateRenderSystemFunction),
+    /// The render system was created by the user before being given to the engine
+    Unavailable,
+}
+
+/// Holds the information required to recreate the window and render systems after the GL context is lost
+struct RenderRecreationInfo
+{
+    window_resolution: (u32, u32),
+    max_fps: i64,
+    fullscreen: bool,
+    render_system_sources: Vec<RenderSystemSource>,
+    render_system_names: Vec<String>,
+    shadow_lov: Vec<LevelOfView>,
+    no_light_source_cutoff: f32,
+    default_diffuse_factor: f32,
+    shadow_draw_fn: DrawFunction,
+    shadow_light_draw_fn: DrawFunction,
+    shadow_transparency_draw_fn: DrawFunction,
+}
+
 #[derive(Eq, PartialEq)]
 enum CurrentMode
 {
@@ -51,49 +99,58 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
         None => CurrentMode::Run,
     };
 
-    let mut window = GLWindowBuilder::new(user_load_info.window_resolution)
-        .with_forced_fps(user_load_info.max_fps)
-        .with_window_resolution(user_load_info.window_resolution)
-        .build()
-        .unwrap();
+    let mut window = create_window(user_load_info.window_resolution, user_load_info.max_fps, user_load_info.fullscreen);
+
+    // A misconfigured driver or context is caught here rather than once the world has loaded
+    if is_launch_self_test_enabled()
+    {
+        record_self_test_report(run_self_test());
+    }
 
     *CAMERA.write() = user_load_info.initial_camera;
 
-    let mut render_systems = Vec::new();
-    let mut render_systems_with_sky_boxes = Vec::new();
-    let mut render_system_map = HashMap::new();
+    // Names are resolved to indexes for the lifetime of the render systems created here
+    RENDER_SYSTEM_NAMES.lock().clear();
+
+    let mut render_system_names = Vec::new();
+    let mut render_system_sources = Vec::new();
+    let mut created_render_systems = Vec::new();
     let mut no_light_source_cutoff = 0.0;
     let mut default_diffuse_factor = 1.0;
     for x in user_load_info.render_systems
     {
-        let render_system_index = RenderSystemIndex{ index: render_systems.len() };
-        render_system_map.insert(x.render_system_name, render_system_index);
+        let render_system_index = RenderSystemIndex{ index: render_system_sources.len() };
+        if RENDER_SYSTEM_NAMES.lock().insert(x.render_system_name.clone(), render_system_index).is_some()
+        {
+            panic!("More than one render system has the name: {}", x.render_system_name);
+        }
 
-        let render_system = match x.render_system
+        let (created_render_system, render_system_source) = match x.render_system
         {
             RenderSystemType::Default(i) =>
                 {
                     no_light_source_cutoff = i.no_light_source_cutoff;
                     default_diffuse_factor = i.default_diffuse_factor;
 
-                    create_default_render_system
-                        (
-                            i.draw_function, i.draw_light_function, i.draw_transparency_function,
-                            i.instance_layout_update_fn, i.level_of_views, i.window_resolution, i.sky_boxes, i.max_count_lights,
-                            no_light_source_cutoff, default_diffuse_factor
-                        )
+                    (None, RenderSystemSource::Default(i))
                 }
-            RenderSystemType::Custom(i) => i
+            RenderSystemType::Custom(i) => (Some(i), RenderSystemSource::Unavailable),
+            RenderSystemType::CustomFunction(i) => (None, RenderSystemSource::Function(i))
         };
 
-        if render_system.will_render_skybox()
-        {
-            render_systems_with_sky_boxes.push(render_system_index);
-        }
-
-        render_systems.push(render_system);
+        render_system_names.push(x.render_system_name);
+        render_system_sources.push(render_system_source);
+        created_render_systems.push(created_render_system);
     }
 
+    let render_systems = create_render_systems(&render_system_sources, &render_system_names, created_render_systems);
+
+    let render_systems_with_sky_boxes = render_systems.iter()
+        .enumerate()
+        .filter(|(_, x)| x.will_render_skybox())
+        .map(|(index, _)| RenderSystemIndex{ index })
+        .collect::<Vec<RenderSystemIndex>>();
+
     let shadow_lov = if let Some(shadow_lov) = user_load_info.shadow_render_system_lov
     {
         shadow_lov
@@ -103,6 +160,21 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
         create_level_of_views(CAMERA.read().get_render_distance())
     };
 
+    let recreation_info = RenderRecreationInfo
+    {
+        window_resolution: user_load_info.window_resolution,
+        max_fps: user_load_info.max_fps,
+        fullscreen: user_load_info.fullscreen,
+        render_system_sources,
+        render_system_names,
+        shadow_lov: shadow_lov.clone(),
+        no_light_source_cutoff,
+        default_diffuse_factor,
+        shadow_draw_fn: user_load_info.shadow_draw_fn.clone(),
+        shadow_light_draw_fn: user_load_info.shadow_light_draw_fn.clone(),
+        shadow_transparency_draw_fn: user_load_info.shadow_transparency_draw_fn.clone(),
+    };
+
     let mut render_pipeline;
     user_load_info.instance_logic.collision_logic.insert(user_type_identifier(), user_load_info.user_collision_function);
     user_load_info.instance_logic.entity_logic.insert(user_type_identifier(), user_load_info.user_logic_function);
@@ -139,9 +211,9 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
     let mut loaded_models = HashMap::new();
     for x in user_load_info.load_models
     {
-        let render_system_index = match render_system_map.get(&x.render_system_index)
+        let render_system_index = match RenderSystemIndex::from_name(&x.render_system_index)
         {
-            Some(i) => *i,
+            Some(i) => i,
             None => panic!("Unable to find a render system with the name: {}", x.render_system_index)
         };
 
@@ -152,7 +224,8 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
             location: x.location,
             custom_level_of_view: None,
             model_texture_dir: user_load_info.model_texture_dir.clone(),
-            solid_colour_texture: x.solid_colour_texture
+            solid_colour_texture: x.solid_colour_texture,
+            import_settings: x.import_settings,
         };
 
         loaded_models.insert(x.model_name, render_pipeline.upload_model(load_info));
@@ -168,6 +241,7 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
             custom_level_of_view: Some(vec![LevelOfView{ min_distance: 0.0, max_distance: f32::MAX }]),
             model_texture_dir: user_load_info.model_texture_dir.clone(),
             solid_colour_texture: None,
+            import_settings: None,
         };
 
         render_pipeline.upload_model(load_info);
@@ -186,16 +260,28 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
             render_pipeline.register_model_instances(model_id, x.num_instances, x.upload_fn);
         }
 
-        let render_system_index = match render_system_map.get("default")
+        for x in user_load_info.load_prefabs
+        {
+            render_pipeline.spawn_prefab_instances(&x.prefab, &x.transforms);
+        }
+
+        let render_system_index = match RenderSystemIndex::from_name("default")
         {
-            Some(i) => *i,
+            Some(i) => i,
             None => panic!("Unable to find a render system with the name: default")
         };
-        render_pipeline.create_user_entity_instance(render_system_index);
+        render_pipeline.create_user_entity_instance(render_system_index, user_load_info.user_aabb_from_model);
     }
 
+    if user_load_info.hot_reload_models
+    {
+        render_pipeline.enable_model_hot_reload();
+    }
+
+    render_pipeline.set_skybox(user_load_info.skybox.take());
+
     let error_message = unsafe { std::ffi::CStr::from_ptr(gl::GetString(gl::VENDOR) as *const i8).to_str().unwrap() };
-    println!("Company: {}", error_message);
+    info!("Company: {}", error_message);
 
     unsafe
         {
@@ -213,9 +299,26 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
     let mut first_frame = true;
 
     let mut play = false;
+    let mut statistics_overlay = StatisticsOverlay::new();
+    let mut frame_callbacks = FrameCallbacks::new();
+    let mut colour_filter_pass = None;
+
+    // Replays move the camera as it was recorded, so a benchmark is only run during normal execution
+    let mut benchmark_run = match user_load_info.benchmark.take()
+    {
+        Some(benchmark) if current_mode == CurrentMode::Run => Some(BenchmarkRun::start(benchmark, &mut CAMERA.write())),
+        _ => None
+    };
 
     while !window.should_window_close()
     {
+        if is_context_lost()
+        {
+            // The resources of the filter belong to the lost context
+            colour_filter_pass = None;
+            window = recover_from_context_loss(window, &mut render_pipeline, &recreation_info);
+        }
+
         update_delta_time(first_frame, &mut last_frame_time_keeper);
 
         // The change lock must be released before the notify_all is called; otherwise the call will
@@ -229,9 +332,41 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
             window.handle_events();
             handle_window_size_update(&window, &mut render_pipeline);
             handle_user_input(&mut window, &mut current_mode, &mut play);
+            render_pipeline.reload_changed_models();
 
+            apply_engine_hook_requests();
+            apply_gl_debug_output();
+            run_engine_hook(EngineHook::FrameStart, &CAMERA.read(), window.window.get_size(), None);
+
+            let cpu_time_keeper = Instant::now();
             render_scene(&mut change_lock, &mut window, &mut render_pipeline, &mut current_mode, &mut play);
 
+            frame_callbacks.apply_requests();
+            frame_callbacks.execute(&CAMERA.read(), window.window.get_size(), *DELTA_TIME.read());
+            apply_colour_filter(&mut colour_filter_pass, &window);
+
+            let cpu_time = cpu_time_keeper.elapsed().as_secs_f32();
+
+            statistics_overlay.draw(&mut window);
+            run_engine_hook(EngineHook::FrameEnd, &CAMERA.read(), window.window.get_size(), None);
+
+            let gpu_wait_time_keeper = Instant::now();
+            window.swap_buffers();
+            end_frame(*DELTA_TIME.read(), cpu_time, gpu_wait_time_keeper.elapsed().as_secs_f32());
+
+            if let Some(report) = benchmark_run.as_mut().and_then(|x| x.record_frame(latest_frame_statistics(), *DELTA_TIME.read()))
+            {
+                info!("{}", report);
+                window.set_window_close();
+                *RENDER_THREAD_SUCCESS_COUNT.lock() = EXIT_GRACEFULLY_COUNT;
+                benchmark_run = None;
+            }
+
+            tune_section_length(&mut render_pipeline, &args.state);
+
+            handle_snapshot_requests(&window, &mut render_pipeline, &args.state);
+            synchronize_history_if_requested(&mut change_lock, &render_pipeline, &args.state);
+
             change_lock.timestamp = time_keeper.elapsed().as_secs();
             change_lock.last_thread_to_access = RENDER_THREAD_ID;
         }
@@ -250,6 +385,261 @@ pub fn render_world(mut args: RenderInputArgs, mut user_load_info: UserUploadInf
     }
 }
 
+/// Filters the rendered frame for colour blindness if a colour filter is enabled in the accessibility settings
+///
+/// `colour_filter_pass` - the pass that filters the frame, created when a filter is first enabled
+/// `window` - the window the frame was rendered to
+fn apply_colour_filter(colour_filter_pass: &mut Option<ColourFilterPass>, window: &GLWindow)
+{
+    let colour_matrix = match accessibility_settings().colour_filter.colour_matrix()
+    {
+        Some(i) => i,
+        None => return
+    };
+
+    if colour_filter_pass.is_none()
+    {
+        match ColourFilterPass::new()
+        {
+            Ok(i) => *colour_filter_pass = Some(i),
+            Err(err) =>
+                {
+                    error!("Failed to create the colour filter, disabling it: {}", err);
+                    set_colour_filter(ColourFilter::None);
+                    return;
+                }
+        }
+    }
+
+    colour_filter_pass.as_mut().unwrap().apply(&colour_matrix, window.window.get_size());
+}
+
+/// Creates the window being rendered to. Its context is created such that a GPU reset is reported as the
+/// context being lost, rather than terminating the program
+///
+/// `window_resolution` - the initial resolution of the window
+/// `max_fps` - the maximum number of frames rendered per second
+fn create_window(window_resolution: (u32, u32), max_fps: i64, fullscreen: bool) -> GLWindow
+{
+    GLWindowBuilder::new(window_resolution)
+        .with_forced_fps(max_fps)
+        .with_window_resolution(window_resolution)
+        .as_fullscreen(fullscreen)
+        .with_window_hints(vec![WindowHint::ContextRobustness(ContextRobustnessHint::LoseContextOnReset)])
+        .build()
+        .unwrap()
+}
+
+/// Creates the render systems from the information specifying how to create them. The shaders of default
+/// render systems are generated in parallel, as doing so does not need the GL context, after which the
+/// OpenGL resources of each render system are created in order on the calling thread. The time taken to
+/// create each render system is recorded
+///
+/// `render_system_sources` - specifies how each render system is created
+/// `render_system_names` - the name of each render system
+/// `created_render_systems` - the render systems created by the user, at the index of their source
+fn create_render_systems(render_system_sources: &[RenderSystemSource], render_system_names: &[String], created_render_systems: Vec<Option<RenderSystem>>) -> Vec<RenderSystem>
+{
+    let prepared_render_systems = render_system_sources.par_iter()
+        .map(|x|
+            {
+                match x
+                {
+                    RenderSystemSource::Default(i) =>
+                        {
+                            let preparation_start = Instant::now();
+                            let prepared_render_system = prepare_default_render_system(i);
+
+                            Some((prepared_render_system, preparation_start.elapsed()))
+                        },
+                    _ => None
+                }
+            })
+        .collect::<Vec<Option<(PreparedRenderSystem, Duration)>>>();
+
+    let mut render_systems = Vec::with_capacity(render_system_sources.len());
+    let mut init_times = Vec::with_capacity(render_system_sources.len());
+
+    let render_system_information = render_system_sources.iter().zip(render_system_names).zip(prepared_render_systems).zip(created_render_systems);
+
+    for (((render_system_source, name), prepared_render_system), created_render_system) in render_system_information
+    {
+        let creation_start = Instant::now();
+
+        let (render_system, preparation_time) = match (render_system_source, prepared_render_system, created_render_system)
+        {
+            (_, _, Some(i)) => (i, Duration::default()),
+            (RenderSystemSource::Default(i), Some((prepared, preparation_time)), None) =>
+                {
+                    (finish_default_render_system(prepared.build(), i.sky_boxes.clone()), preparation_time)
+                },
+            (RenderSystemSource::Function(i), _, None) => (i(), Duration::default()),
+            _ => panic!("Unable to create the render system: {}", name)
+        };
+
+        init_times.push(RenderSystemInitTime
+        {
+            name: name.clone(),
+            preparation_time: preparation_time.as_secs_f32() * 1000.0,
+            creation_time: creation_start.elapsed().as_secs_f32() * 1000.0,
+        });
+
+        render_systems.push(render_system);
+    }
+
+    record_render_system_init_times(init_times);
+    render_systems
+}
+
+/// Checks if the GL context was lost, which happens if the GPU was reset, such as after a driver crash
+fn is_context_lost() -> bool
+{
+    unsafe
+        {
+            gl::GetGraphicsResetStatus() != gl::NO_ERROR
+        }
+}
+
+/// Recreates the window and all of the rendering resources after the GL context was lost. The state of
+/// the game is kept, so rendering resumes from where it was when the context was lost
+///
+/// `window` - the window whose context was lost
+/// `render_pipeline` - the pipeline used for rendering
+/// `recreation_info` - the information required to recreate the rendering resources
+fn recover_from_context_loss(window: GLWindow, render_pipeline: &mut Pipeline, recreation_info: &RenderRecreationInfo) -> GLWindow
+{
+    if recreation_info.render_system_sources.iter().any(|x| matches!(x, RenderSystemSource::Unavailable))
+    {
+        panic!("The GL context was lost and cannot be recreated, as a custom render system was given that cannot be created again");
+    }
+
+    error!("The GL context was lost. Recreating the window and uploading all models again");
+
+    // The window is destroyed first so that its context is not current when the new context is created
+    drop(window);
+    let window = create_window(recreation_info.window_resolution, recreation_info.max_fps, recreation_info.fullscreen);
+
+    // The shared texture arrays belonged to the lost context
+    clear_shared_texture_arrays();
+
+    let created_render_systems = recreation_info.render_system_sources.iter().map(|_| None).collect();
+    let render_systems = create_render_systems(&recreation_info.render_system_sources, &recreation_info.render_system_names, created_render_systems);
+
+    let render_flow = RenderFlow::new(render_systems, recreation_info.no_light_source_cutoff, recreation_info.default_diffuse_factor,
+                                      recreation_info.shadow_lov.clone(), window.window.get_size(),
+                                      recreation_info.shadow_draw_fn.clone(), recreation_info.shadow_light_draw_fn.clone(),
+                                      recreation_info.shadow_transparency_draw_fn.clone());
+
+    render_pipeline.recreate_render_resources(render_flow);
+
+    CAMERA.write().account_window_change(window.window.get_size());
+
+    unsafe
+        {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Enable(gl::STENCIL_TEST);
+        }
+
+    window
+}
+
+/// Saves and loads the snapshots of the game that were requested during the frame
+///
+/// `window` - the window being rendered to
+/// `render_pipeline` - the pipeline holding the current game state
+/// `state` - the recorded history state
+fn handle_snapshot_requests(window: &GLWindow, render_pipeline: &mut Pipeline, state: &Mutex<StoredHistoryState>)
+{
+    let requests = std::mem::take(&mut *REQUESTED_SNAPSHOT_OPERATIONS.lock());
+
+    for request in requests
+    {
+        match request
+        {
+            SnapshotRequest::Save(path) =>
+                {
+                    let result = render_pipeline.create_snapshot(&CAMERA.read()).write_to_file(&path);
+                    World::store_snapshot_result(result.map(|_| path));
+                },
+            SnapshotRequest::Load(path) =>
+                {
+                    match WorldSnapshot::read_from_file(&path).and_then(|snapshot| render_pipeline.restore_snapshot(snapshot))
+                    {
+                        Ok(camera) =>
+                            {
+                                *CAMERA.write() = camera;
+                                CAMERA.write().account_window_change(window.window.get_size());
+
+                                // The recorded history does not lead to the loaded state
+                                state.lock().request_synchronization();
+                                World::store_snapshot_result(Ok(path));
+                            },
+                        Err(err) => World::store_snapshot_result(Err(err))
+                    }
+                },
+            SnapshotRequest::ExportSections(sections, path) =>
+                {
+                    let result = render_pipeline.export_sections(sections).and_then(|x| x.write_to_file(&path));
+                    World::store_snapshot_result(result.map(|_| path));
+                },
+            SnapshotRequest::ImportSections(path, offset) =>
+                {
+                    match SectionExport::read_from_file(&path).and_then(|x| render_pipeline.import_sections(&x, offset))
+                    {
+                        Ok(_) =>
+                            {
+                                // The recorded history does not create the imported entities
+                                state.lock().request_synchronization();
+                                World::store_snapshot_result(Ok(path));
+                            },
+                        Err(err) => World::store_snapshot_result(Err(err))
+                    }
+                }
+        }
+    }
+}
+
+/// Measures the frame for the tuning of the world section length if it is in progress, rebuilding the world
+/// sections when tuning moves on to another length
+///
+/// `render_pipeline` - the pipeline holding the current game state
+/// `state` - the recorded history state
+fn tune_section_length(render_pipeline: &mut Pipeline, state: &Mutex<StoredHistoryState>)
+{
+    let (rebuild_length, report) = record_tuning_frame(latest_frame_statistics(), render_pipeline.world_section_length());
+
+    if let Some(atomic_section_length) = rebuild_length
+    {
+        render_pipeline.rebuild_world_sections(atomic_section_length);
+
+        // The recorded history holds world sections of the previous length
+        state.lock().request_synchronization();
+    }
+
+    if let Some(report) = report
+    {
+        info!("{}", report);
+    }
+}
+
+/// Synchronizes the recorded history with the current game state if the history thread requested it.
+/// The changes of the current frame are part of the synchronized state, and so are not recorded
+///
+/// `change_lock` - mutex lock to the structure that holds changes made in the current frame
+/// `render_pipeline` - the pipeline holding the current game state
+/// `state` - the recorded history state
+fn synchronize_history_if_requested(change_lock: &mut MutexGuard<ChangeHistory>, render_pipeline: &Pipeline, state: &Mutex<StoredHistoryState>)
+{
+    let mut state = state.lock();
+
+    if state.is_synchronization_requested()
+    {
+        render_pipeline.synchronize_state(&mut state);
+        change_lock.changes = None;
+        change_lock.synchronized_state = true;
+    }
+}
+
 /// Stores how much time has passed since the last iteration of the render loop
 ///
 /// `first_frame` - boolean variable indicating if this is the first iteration of the render loop
@@ -322,6 +712,11 @@ fn handle_window_size_update(window: &GLWindow, render_pipeline: &mut Pipeline)
 ///          in debug mode
 fn handle_user_input(window: &mut GLWindow, current_mode: &mut CurrentMode, play: &mut bool)
 {
+    if window.get_current_input().was_key_released(F3)
+    {
+        set_statistics_overlay_visible(!is_statistics_overlay_visible());
+    }
+
     match current_mode
     {
         CurrentMode::Debug  =>
@@ -480,7 +875,5 @@ fn render_scene(change_lock: &mut MutexGuard<ChangeHistory>, window: &mut GLWind
                         change_lock.changes = Some(changes);
                     }
             }
-
-            window.swap_buffers();
         }
 }
\ No newline at end of file
@@ -3,7 +3,8 @@ use std::thread;
 use std::time::{Duration, Instant};
 use glfw::{Action, Context, Glfw, InitError, Key, MouseButton, SwapInterval, Window,
            WindowEvent, WindowHint, WindowMode};
-use crate::window::input_state::{CurrentFrameInput, InputHistory};
+use crate::exports::gl_debug_output::install_gl_debug_output;
+use crate::window::input_state::{CurrentFrameInput, InputHistory, take_due_injected_input};
 use crate::window::movement_keys;
 use crate::window::movement_keys::MovementKeys;
 
@@ -14,6 +15,7 @@ pub struct GLWindow
 {
     pub glfw: Glfw,
     pub window: Window,
+    title: String,
     input_history: InputHistory,
     current_input_history: CurrentFrameInput,
     events: Receiver<(f64, WindowEvent)>,
@@ -152,6 +154,13 @@ impl GLWindowBuilder
             window.set_cursor_pos_polling(true);
             window.set_size_polling(true);
             window.make_current();
+
+            // The tooling UI also needs typed text and scrolling
+            #[cfg(feature = "tooling_ui")]
+            {
+                window.set_char_polling(true);
+                window.set_scroll_polling(true);
+            }
         }
 
         if self.force_fps.is_none()
@@ -202,20 +211,10 @@ impl GLWindowBuilder
         unsafe
             {
                 gl::Viewport(0, 0, window.get_size().0, window.get_size().1);
-
-                gl::Enable(gl::DEBUG_OUTPUT);
-                gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS); // makes sure errors are displayed synchronously
-                gl::DebugMessageCallback(Some(gl_debug_output), std::ptr::null());
-                gl::DebugMessageControl(
-                    gl::DONT_CARE,
-                    gl::DONT_CARE,
-                    gl::DONT_CARE,
-                    0,
-                    std::ptr::null(),
-                    gl::TRUE,
-                );
             }
 
+        install_gl_debug_output();
+
         let time_per_frame = match self.force_fps
         {
             Some(i) => Some(1000 / i),
@@ -224,7 +223,7 @@ impl GLWindowBuilder
 
         let window = GLWindow
         {
-            glfw, window, events, wasd_keys: MovementKeys::new(),
+            glfw, window, title: self.window_title.clone(), events, wasd_keys: MovementKeys::new(),
             current_input_history: CurrentFrameInput::new(), latest_cursor_pos: None, middle_button_down: false,
             time_per_frame, instant: Instant::now(), latest_window_size: None, input_history: InputHistory::new(),
         };
@@ -242,6 +241,22 @@ impl GLWindow
         &self.wasd_keys
     }
 
+    /// Get the title of the window set by the application. Overlays that temporarily show other text in
+    /// the title bar do not change this title
+    pub fn get_title(&self) -> &str
+    {
+        &self.title
+    }
+
+    /// Changes the title of the window
+    ///
+    /// `title` - the new title of the window
+    pub fn set_title<T: Into<String>>(&mut self, title: T)
+    {
+        self.title = title.into();
+        self.window.set_title(&self.title);
+    }
+
     /// Query if the window should close in the next render loop
 
     pub fn should_window_close(&self) -> bool
@@ -303,116 +318,136 @@ impl GLWindow
         self.clear_input_history();
 
         self.glfw.poll_events();
-        for (_, event) in glfw::flush_messages(&self.events)
+
+        let events = glfw::flush_messages(&self.events).map(|(_, event)| event).collect::<Vec<WindowEvent>>();
+        for event in events
         {
-            match event
-            {
-                glfw::WindowEvent::Key(Key::W, _, Action::Press, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyW,
-                            movement_keys::KeyStatus::Pressed,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::A, _, Action::Press, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyA,
-                            movement_keys::KeyStatus::Pressed,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::S, _, Action::Press, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyS,
-                            movement_keys::KeyStatus::Pressed,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::D, _, Action::Press, _) =>
-                    {
-                        self.wasd_keys.set_key_state(
-                            movement_keys::KeyID::KeyD,
-                            movement_keys::KeyStatus::Pressed,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::W, _, Action::Release, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyW,
-                            movement_keys::KeyStatus::Released,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::A, _, Action::Release, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyA,
-                            movement_keys::KeyStatus::Released,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::S, _, Action::Release, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyS,
-                            movement_keys::KeyStatus::Released,
-                        );
-                    }
-                glfw::WindowEvent::Key(Key::D, _, Action::Release, _) =>
-                    {
-                        self.wasd_keys.set_key_state
-                        (
-                            movement_keys::KeyID::KeyD,
-                            movement_keys::KeyStatus::Released,
-                        );
-                    },
-                glfw::WindowEvent::MouseButton(MIDDLE_BUTTON, Action::Press, _) =>
-                    {
-                        self.middle_button_down = true;
+            self.handle_event(event);
+        }
 
-                    },
-                glfw::WindowEvent::MouseButton(MIDDLE_BUTTON, Action::Release, _) =>
-                    {
-                        self.middle_button_down = false;
+        // Injected input is handled after the real input so that it takes precedence when both act on the
+        // same key or button within a frame
+        for event in take_due_injected_input()
+        {
+            self.handle_event(event);
+        }
+    }
 
-                    },
-                glfw::WindowEvent::Size(width, height) =>
-                    {
-                        unsafe
-                            {
-                                gl::Viewport(0, 0, width, height);
-                            }
-                    }
-                _ =>
-                    {}
-            }
+    /// Changes state as required by the given event, and stores it in the input history
+    ///
+    /// `event` - the window event to handle
+    fn handle_event(&mut self, event: WindowEvent)
+    {
+        #[cfg(feature = "tooling_ui")]
+        crate::exports::tooling_ui::queue_window_event(&event);
 
-            match event
-            {
-                glfw::WindowEvent::Key(key, _, action, _) =>
-                    {
-                        self.input_history.update_key_members(key, action);
-                        self.current_input_history.update_key_members(key, action);
-                    }
-                glfw::WindowEvent::MouseButton(button, action, _) =>
-                    {
-                        self.input_history.update_mouse_members(button, action);
-                        self.current_input_history.update_mouse_members(button, action);
-                    },
-                glfw::WindowEvent::CursorPos(x, y) =>
-                    {
-                        self.current_input_history.update_latest_cursor_pos((x as i32, y as i32))
-                    },
-                glfw::WindowEvent::Size(width, height) =>
-                    {
-                        self.latest_window_size = Some((width, height));
-                    }
-                _ => {}
-            }
+        match event
+        {
+            glfw::WindowEvent::Key(Key::W, _, Action::Press, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyW,
+                        movement_keys::KeyStatus::Pressed,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::A, _, Action::Press, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyA,
+                        movement_keys::KeyStatus::Pressed,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::S, _, Action::Press, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyS,
+                        movement_keys::KeyStatus::Pressed,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::D, _, Action::Press, _) =>
+                {
+                    self.wasd_keys.set_key_state(
+                        movement_keys::KeyID::KeyD,
+                        movement_keys::KeyStatus::Pressed,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::W, _, Action::Release, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyW,
+                        movement_keys::KeyStatus::Released,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::A, _, Action::Release, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyA,
+                        movement_keys::KeyStatus::Released,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::S, _, Action::Release, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyS,
+                        movement_keys::KeyStatus::Released,
+                    );
+                }
+            glfw::WindowEvent::Key(Key::D, _, Action::Release, _) =>
+                {
+                    self.wasd_keys.set_key_state
+                    (
+                        movement_keys::KeyID::KeyD,
+                        movement_keys::KeyStatus::Released,
+                    );
+                },
+            glfw::WindowEvent::MouseButton(MIDDLE_BUTTON, Action::Press, _) =>
+                {
+                    self.middle_button_down = true;
+
+                },
+            glfw::WindowEvent::MouseButton(MIDDLE_BUTTON, Action::Release, _) =>
+                {
+                    self.middle_button_down = false;
+
+                },
+            glfw::WindowEvent::Size(width, height) =>
+                {
+                    unsafe
+                        {
+                            gl::Viewport(0, 0, width, height);
+                        }
+                }
+            _ =>
+                {}
+        }
+
+        match event
+        {
+            glfw::WindowEvent::Key(key, _, action, _) =>
+                {
+                    self.input_history.update_key_members(key, action);
+                    self.current_input_history.update_key_members(key, action);
+                }
+            glfw::WindowEvent::MouseButton(button, action, _) =>
+                {
+                    self.input_history.update_mouse_members(button, action);
+                    self.current_input_history.update_mouse_members(button, action);
+                },
+            glfw::WindowEvent::CursorPos(x, y) =>
+                {
+                    self.current_input_history.update_latest_cursor_pos((x as i32, y as i32))
+                },
+            glfw::WindowEvent::Size(width, height) =>
+                {
+                    self.latest_window_size = Some((width, height));
+                }
+            _ => {}
         }
     }
 
@@ -452,24 +487,3 @@ impl GLWindow
         self.instant = Instant::now();
     }
 }
-
-extern "system" fn gl_debug_output(
-    _source: gl::types::GLenum,
-    _type_: gl::types::GLenum,
-    id: gl::types::GLuint,
-    _: gl::types::GLenum,
-    _length: gl::types::GLsizei,
-    message: *const gl::types::GLchar,
-    _user_param: *mut std::ffi::c_void,
-)
-{
-    let message = unsafe { std::ffi::CStr::from_ptr(message).to_str().unwrap() };
-    let gpu_vendor = unsafe { std::ffi::CStr::from_ptr(gl::GetString(gl::VENDOR) as *const i8).to_str().unwrap() };
-
-    if gpu_vendor.contains("Intel") && message.contains("API_ID_SYNC_FLUSH")
-    {
-        return;
-    }
-
-    println!("Debug message ({}): {}", id, message);
-}
\ No newline at end of file
@@ -1,5 +1,12 @@
-use glfw::{Action, Key, MouseButton};
+use glfw::{Action, Key, Modifiers, MouseButton, WindowEvent};
 use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+lazy_static!
+{
+    static ref INJECTED_INPUT: Mutex<Vec<ScheduledInput>> = Mutex::new(Vec::new());
+}
 
 /// Stores the state of the input so that it can be accessed from the draw function
 pub struct InputHistory
@@ -15,6 +22,23 @@ pub struct CurrentFrameInput
     latest_cursor_pos: Option<(i32, i32)>
 }
 
+/// Input that is injected programmatically rather than coming from the user, such as for tutorials,
+/// demos and integration tests
+#[derive(Copy, Clone, Debug)]
+pub enum InjectedInput
+{
+    Key(Key, Action),
+    MouseButton(MouseButton, Action),
+    CursorPos(i32, i32),
+}
+
+/// Injected input waiting for the frame it is to be applied in
+struct ScheduledInput
+{
+    input: InjectedInput,
+    frames_remaining: u32,
+}
+
 impl InputHistory
 {
     /// Creates a new empty input history
@@ -155,4 +179,68 @@ impl CurrentFrameInput
         self.keys.clear();
         self.latest_cursor_pos = None;
     }
-}
\ No newline at end of file
+}
+
+/// Injects input that is handled at the start of the next frame. The input is handled exactly like input
+/// from the user, and so is stored in both the input history and the current frame input
+///
+/// `input` - the input to inject
+pub fn inject_input(input: InjectedInput)
+{
+    inject_input_after_frames(input, 0);
+}
+
+/// Injects input that is handled at the start of the frame that is the given number of frames after the
+/// next frame. Input injected for the same frame is handled in the order it was injected
+///
+/// `input` - the input to inject
+/// `number_frames` - the number of frames to skip before the input is handled
+pub fn inject_input_after_frames(input: InjectedInput, number_frames: u32)
+{
+    INJECTED_INPUT.lock().push(ScheduledInput{ input, frames_remaining: number_frames });
+}
+
+/// Removes all injected input that has not yet been handled
+pub fn clear_injected_input()
+{
+    INJECTED_INPUT.lock().clear();
+}
+
+/// Takes the injected input that is to be handled this frame as window events. Call once per frame
+pub(crate) fn take_due_injected_input() -> Vec<WindowEvent>
+{
+    let mut injected_input = INJECTED_INPUT.lock();
+    let mut due_input = Vec::new();
+
+    injected_input.retain(|scheduled|
+        {
+            if scheduled.frames_remaining == 0
+            {
+                due_input.push(scheduled.input.to_window_event());
+                return false;
+            }
+
+            true
+        });
+
+    for scheduled in injected_input.iter_mut()
+    {
+        scheduled.frames_remaining -= 1;
+    }
+
+    due_input
+}
+
+impl InjectedInput
+{
+    /// Converts the injected input into the window event that user input of the same kind generates
+    fn to_window_event(self) -> WindowEvent
+    {
+        match self
+        {
+            InjectedInput::Key(key, action) => WindowEvent::Key(key, 0, action, Modifiers::empty()),
+            InjectedInput::MouseButton(button, action) => WindowEvent::MouseButton(button, action, Modifiers::empty()),
+            InjectedInput::CursorPos(x, y) => WindowEvent::CursorPos(x as f64, y as f64),
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod gl_window;
 pub mod movement_keys;
 pub mod input_state;
+pub mod statistics_overlay;
@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+use crate::exports::accessibility::ui_scale;
+use crate::exports::frame_statistics::{frame_statistics_history, FRAME_STATISTICS_HISTORY_LENGTH, FrameStatistics, is_statistics_overlay_visible};
+use crate::exports::ui_layout::safe_area;
+use crate::window::gl_window::GLWindow;
+
+const BAR_WIDTH: i32 = 3;
+const PIXELS_PER_MILLISECOND: f32 = 4.0;
+const MARGIN: i32 = 10;
+const SPLIT_BAR_HEIGHT: i32 = 8;
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Frame time of a frame rendered at 60 frames per second, in milliseconds
+const TARGET_FRAME_TIME: f32 = 1000.0 / 60.0;
+
+// The overlay is drawn by clearing small scissored regions of the window, which requires no shaders or
+// buffers. As there is no way to draw text yet, the numerical statistics are shown in the window title,
+// and the title of the application is shown again once the overlay is hidden
+
+/// Draws the statistics of recent frames over the rendered scene when enabled
+pub struct StatisticsOverlay
+{
+    was_visible: bool,
+    last_title_update: Instant,
+}
+
+impl StatisticsOverlay
+{
+    /// Creates an overlay that draws nothing until it is made visible
+    pub fn new() -> StatisticsOverlay
+    {
+        StatisticsOverlay{ was_visible: false, last_title_update: Instant::now() }
+    }
+
+    /// Draws the overlay into the window if it is visible. Call after the scene has been rendered and
+    /// before the buffers of the window are swapped
+    ///
+    /// `window` - the window being rendered to
+    pub fn draw(&mut self, window: &mut GLWindow)
+    {
+        if !is_statistics_overlay_visible()
+        {
+            if self.was_visible
+            {
+                let application_title = window.get_title().to_string();
+                window.window.set_title(&application_title);
+                self.was_visible = false;
+            }
+
+            return;
+        }
+
+        let history = frame_statistics_history();
+
+        if self.last_title_update.elapsed() >= TITLE_UPDATE_INTERVAL || !self.was_visible
+        {
+            window.window.set_title(&StatisticsOverlay::format_statistics(&history));
+            self.last_title_update = Instant::now();
+        }
+
+        self.was_visible = true;
+
+        unsafe
+            {
+                gl::Enable(gl::SCISSOR_TEST);
+            }
+
+        let scale = ui_scale();
+
+        // The overlay is kept in the bottom left of the safe area so that it is not cut off by the display
+        let safe_area = safe_area(window.window.get_framebuffer_size());
+        let origin = (safe_area.x, safe_area.y);
+
+        StatisticsOverlay::draw_frame_time_graph(&history, origin, scale);
+
+        if let Some(latest_frame) = history.last()
+        {
+            StatisticsOverlay::draw_cpu_gpu_split(latest_frame, origin, scale);
+        }
+
+        unsafe
+            {
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+    }
+
+    /// Creates the description of the statistics of the most recent frame, with the FPS averaged over the
+    /// recent frames to keep it readable
+    ///
+    /// `history` - the statistics of the recent frames
+    fn format_statistics(history: &[FrameStatistics]) -> String
+    {
+        let latest_frame = history.last().copied().unwrap_or_default();
+        let average_frame_time = history.iter().map(|x| x.frame_time).sum::<f32>() / history.len().max(1) as f32;
+        let average_fps = if average_frame_time > 0.0 { 1000.0 / average_frame_time } else { 0.0 };
+
+        format!("FPS: {:.0} | Frame: {:.2} ms (CPU: {:.2} ms, GPU: {:.2} ms, GPU wait: {:.2} ms) | Visible sections: {} | Draw calls: {}",
+                average_fps, latest_frame.frame_time, latest_frame.cpu_time, latest_frame.gpu_timings.total(), latest_frame.gpu_wait_time,
+                latest_frame.visible_sections, latest_frame.draw_calls)
+    }
+
+    /// Draws a bar for every recent frame whose height is the time the frame took. Bars are green if the
+    /// frame met 60 FPS, yellow if it met 30 FPS and red otherwise
+    ///
+    /// `history` - the statistics of the recent frames
+    /// `origin` - the bottom left corner of the overlay, in pixels
+    /// `scale` - the multiplier of the size of the overlay
+    fn draw_frame_time_graph(history: &[FrameStatistics], origin: (i32, i32), scale: f32)
+    {
+        let graph_bottom = MARGIN + SPLIT_BAR_HEIGHT + MARGIN / 2;
+
+        StatisticsOverlay::draw_rectangle((MARGIN, graph_bottom), (FRAME_STATISTICS_HISTORY_LENGTH as i32 * BAR_WIDTH, (TARGET_FRAME_TIME * 2.0 * PIXELS_PER_MILLISECOND) as i32),
+                                          (0.0, 0.0, 0.0, 1.0), origin, scale);
+
+        for (index, x) in history.iter().enumerate()
+        {
+            let colour = if x.frame_time <= TARGET_FRAME_TIME
+            {
+                (0.2, 0.8, 0.2, 1.0)
+            }
+            else if x.frame_time <= TARGET_FRAME_TIME * 2.0
+            {
+                (0.9, 0.8, 0.1, 1.0)
+            }
+            else
+            {
+                (0.9, 0.2, 0.2, 1.0)
+            };
+
+            let height = ((x.frame_time * PIXELS_PER_MILLISECOND) as i32).max(1);
+            StatisticsOverlay::draw_rectangle((MARGIN + index as i32 * BAR_WIDTH, graph_bottom), (BAR_WIDTH - 1, height), colour, origin, scale);
+        }
+
+        // Line marking the frame time required for 60 FPS
+        StatisticsOverlay::draw_rectangle((MARGIN, graph_bottom + (TARGET_FRAME_TIME * PIXELS_PER_MILLISECOND) as i32),
+                                          (FRAME_STATISTICS_HISTORY_LENGTH as i32 * BAR_WIDTH, 1), (1.0, 1.0, 1.0, 1.0), origin, scale);
+    }
+
+    /// Draws a bar showing how the time of the most recent frame was split between the CPU and waiting on the GPU
+    ///
+    /// `latest_frame` - the statistics of the most recent frame
+    /// `origin` - the bottom left corner of the overlay, in pixels
+    /// `scale` - the multiplier of the size of the overlay
+    fn draw_cpu_gpu_split(latest_frame: &FrameStatistics, origin: (i32, i32), scale: f32)
+    {
+        let total_width = FRAME_STATISTICS_HISTORY_LENGTH as i32 * BAR_WIDTH;
+        let total_time = latest_frame.cpu_time + latest_frame.gpu_wait_time;
+
+        if total_time <= 0.0
+        {
+            return;
+        }
+
+        let cpu_width = (total_width as f32 * latest_frame.cpu_time / total_time) as i32;
+
+        StatisticsOverlay::draw_rectangle((MARGIN, MARGIN), (cpu_width, SPLIT_BAR_HEIGHT), (0.2, 0.4, 0.9, 1.0), origin, scale);
+        StatisticsOverlay::draw_rectangle((MARGIN + cpu_width, MARGIN), (total_width - cpu_width, SPLIT_BAR_HEIGHT), (0.9, 0.5, 0.1, 1.0), origin, scale);
+    }
+
+    /// Fills a rectangle of the window with the given colour. The scissor test must be enabled
+    ///
+    /// `position` - the bottom left corner of the rectangle relative to the origin, in pixels
+    /// `size` - the width and height of the rectangle, in pixels
+    /// `colour` - the colour to fill the rectangle with
+    /// `origin` - the bottom left corner of the overlay, in pixels. Not multiplied by the scale
+    /// `scale` - the multiplier of the position and size of the rectangle
+    fn draw_rectangle(position: (i32, i32), size: (i32, i32), colour: (f32, f32, f32, f32), origin: (i32, i32), scale: f32)
+    {
+        let position = (origin.0 + (position.0 as f32 * scale) as i32, origin.1 + (position.1 as f32 * scale) as i32);
+        let size = ((size.0 as f32 * scale).round() as i32, (size.1 as f32 * scale).round() as i32);
+
+        if size.0 <= 0 || size.1 <= 0
+        {
+            return;
+        }
+
+        unsafe
+            {
+                gl::Scissor(position.0, position.1, size.0, size.1);
+                gl::ClearColor(colour.0, colour.1, colour.2, colour.3);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+    }
+}
+
+impl Default for StatisticsOverlay
+{
+    fn default() -> Self
+    {
+        StatisticsOverlay::new()
+    }
+}
@@ -1,4 +1,5 @@
 use hashbrown::{HashMap, HashSet};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use serde::{Serialize, Deserialize};
 use crate::culling::r#trait::TraversalDecider;
 use crate::exports::light_components::FindLightType;
@@ -6,6 +7,8 @@ use crate::helper_things::aabb_helper_functions;
 use crate::objects::ecs::ECS;
 use crate::objects::entity_id::EntityId;
 use crate::world::bounding_volumes::aabb::StaticAABB;
+use crate::world::bounding_volumes::bounding_sphere::BoundingSphere;
+use crate::world::bounding_volumes::ray::Ray;
 use crate::world::dimension::range::{XRange, YRange, ZRange};
 
 /// Represents a unique world section at a given level
@@ -63,6 +66,18 @@ impl UniqueWorldSectionId
             })
     }
 
+    /// Get the level of the world section. Level 0 world sections have the atomic section length
+    pub fn level(&self) -> u16
+    {
+        self.level
+    }
+
+    /// Get the x, y and z offsets of the world section at its level
+    pub fn offsets(&self) -> (u16, u16, u16)
+    {
+        (self.index.x, self.index.y, self.index.z)
+    }
+
     /// Compute the next lower level child world sections of this world section
     pub fn lower_level_world_section(&self) -> Option<[UniqueWorldSectionId; 8]>
     {
@@ -315,6 +330,14 @@ impl SharedWorldSectionEntities
 
         self.entity_aabb_lookup.remove(&entity_id);
     }
+
+    /// Get the bounding volume the entity had when it was added to this shared section
+    ///
+    /// `entity_id` - the entity to get the bounding volume of
+    pub fn get_entity_aabb(&self, entity_id: EntityId) -> Option<&StaticAABB>
+    {
+        self.entity_aabb_lookup.get(&entity_id)
+    }
 }
 
 /// Used to find which world section, unique or shared, an entity is located in
@@ -325,6 +348,30 @@ pub enum WorldSectionLookup
     Unique(UniqueWorldSectionId),
 }
 
+/// Entities treated as one unit, such as the ships of a fleet or the modules of a station. The group is
+/// identified by one of its entities, usually the leader, whose bounding volume is part of the group's
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntityGroup
+{
+    members: HashSet::<EntityId>,
+    aabb: Option<StaticAABB>,
+}
+
+/// Where an entity is placed in the tree given its bounding volume. Finding the placement does not change the
+/// tree, so the placements of many moved entities can be found in parallel before they are placed one by one
+#[derive(Clone, Debug)]
+pub struct EntityPlacement
+{
+    bounding_volume: StaticAABB,
+    bounding_sphere: BoundingSphere,
+    location: WorldSectionLookup,
+    // The unique world sections at the level of the entity that its bounding volume covers
+    covered_sections: Vec<UniqueWorldSectionId>,
+}
+
+/// Least number of moved entities each thread finds the placement of
+const PLACEMENT_CHUNK_SIZE: usize = 64;
+
 /// Keeps track of where entities are located in the game world
 #[derive(Clone, Serialize, Deserialize)]
 pub struct BoundingBoxTree
@@ -335,6 +382,10 @@ pub struct BoundingBoxTree
     pub shared_section_indexes: HashMap<SharedWorldSectionId, SharedWorldSectionEntities>,
     pub reverse_shared_section_lookup: HashMap<SharedWorldSectionId, Vec<UniqueWorldSectionId>>,
     pub unique_sections_with_lights: HashSet::<UniqueWorldSectionId>,
+    entity_bounding_spheres: HashMap<EntityId, BoundingSphere>,
+    entity_groups: HashMap<EntityId, EntityGroup>,
+    entity_group_lookup: HashMap<EntityId, EntityId>,
+    changed_entity_groups: HashSet::<EntityId>,
     shared_section_lights: HashSet::<SharedWorldSectionId>,
     static_world_sections: HashSet::<UniqueWorldSectionId>,
     changed_static_unique_sections: HashSet::<UniqueWorldSectionId>,
@@ -373,6 +424,10 @@ impl BoundingBoxTree
             static_world_sections: HashSet::default(),
             changed_static_unique_sections: HashSet::default(),
             unique_sections_with_lights: HashSet::default(),
+            entity_bounding_spheres: HashMap::default(),
+            entity_groups: HashMap::default(),
+            entity_group_lookup: HashMap::default(),
+            changed_entity_groups: HashSet::default(),
             shared_section_lights: HashSet::default(),
             outline_length,
             atomic_section_length,
@@ -382,6 +437,186 @@ impl BoundingBoxTree
         }
     }
 
+    /// Get the sphere surrounding the bounding volume the entity had when it was added to the tree
+    ///
+    /// `entity_id` - the entity to get the bounding sphere of
+    pub fn get_entity_bounding_sphere(&self, entity_id: EntityId) -> Option<&BoundingSphere>
+    {
+        self.entity_bounding_spheres.get(&entity_id)
+    }
+
+    /// Adds the member entity to the group, creating the group if it does not exist. An entity can be a member
+    /// of one group at a time, so a member of another group is moved to this one. The bounding volume of the
+    /// group includes the member from the next time changes to the tree end
+    ///
+    /// `group` - the entity identifying the group
+    /// `member` - the entity to add to the group
+    pub fn add_group_member(&mut self, group: EntityId, member: EntityId)
+    {
+        if group == member
+        {
+            return;
+        }
+
+        if let Some(previous_group) = self.entity_group_lookup.get(&member).copied()
+        {
+            self.remove_group_member(previous_group, member);
+        }
+
+        self.entity_groups.entry(group).or_insert_with(|| EntityGroup{ members: HashSet::default(), aabb: None }).members.insert(member);
+        self.entity_group_lookup.insert(member, group);
+        self.changed_entity_groups.insert(group);
+    }
+
+    /// Removes the member entity from the group, if it is a member of it. A group without members is removed
+    ///
+    /// `group` - the entity identifying the group
+    /// `member` - the entity to remove from the group
+    pub fn remove_group_member(&mut self, group: EntityId, member: EntityId)
+    {
+        if self.entity_group_lookup.get(&member) != Some(&group)
+        {
+            return;
+        }
+
+        self.entity_group_lookup.remove(&member);
+
+        let group_empty = match self.entity_groups.get_mut(&group)
+        {
+            Some(i) =>
+                {
+                    i.members.remove(&member);
+                    i.members.is_empty()
+                },
+            None => false
+        };
+
+        if group_empty
+        {
+            self.entity_groups.remove(&group);
+            self.changed_entity_groups.remove(&group);
+        }
+        else
+        {
+            self.changed_entity_groups.insert(group);
+        }
+    }
+
+    /// Removes the entity from the group it is a member of, and removes the group it identifies, if any.
+    /// Called when the entity is deleted
+    ///
+    /// `entity_id` - the entity to remove from any groups
+    pub fn remove_entity_from_groups(&mut self, entity_id: EntityId)
+    {
+        if let Some(group) = self.entity_group_lookup.get(&entity_id).copied()
+        {
+            self.remove_group_member(group, entity_id);
+        }
+
+        if let Some(group) = self.entity_groups.remove(&entity_id)
+        {
+            for member in group.members
+            {
+                self.entity_group_lookup.remove(&member);
+            }
+
+            self.changed_entity_groups.remove(&entity_id);
+        }
+    }
+
+    /// Get the group the entity is a member of, if any
+    ///
+    /// `entity_id` - the entity to get the group of
+    pub fn get_entity_group(&self, entity_id: EntityId) -> Option<EntityId>
+    {
+        self.entity_group_lookup.get(&entity_id).copied()
+    }
+
+    /// Get the members of the group, not including the entity identifying the group
+    ///
+    /// `group` - the entity identifying the group
+    pub fn get_group_members(&self, group: EntityId) -> Option<&HashSet::<EntityId>>
+    {
+        self.entity_groups.get(&group).map(|x| &x.members)
+    }
+
+    /// Get the bounding volume holding the entity identifying the group and all of its members in the tree,
+    /// as of the last time changes to the tree ended. None is returned if the group does not exist or none
+    /// of its entities are in the tree
+    ///
+    /// `group` - the entity identifying the group
+    pub fn get_group_aabb(&self, group: EntityId) -> Option<&StaticAABB>
+    {
+        self.entity_groups.get(&group).and_then(|x| x.aabb.as_ref())
+    }
+
+    /// Finds the groups whose bounding volumes intersect the given volume, such as the groups within range
+    /// of a weapon
+    ///
+    /// `aabb` - the volume to find the groups in
+    pub fn find_groups_in_aabb(&self, aabb: &StaticAABB) -> Vec<EntityId>
+    {
+        let mut found_groups = self.entity_groups.iter()
+            .filter(|(_, group)| group.aabb.is_some_and(|x| x.intersect(aabb)))
+            .map(|(group, _)| *group)
+            .collect::<Vec<EntityId>>();
+
+        found_groups.sort();
+        found_groups
+    }
+
+    /// Finds the groups whose bounding volumes the ray passes through, along with the distance along the ray
+    /// at which it enters each of them. The groups are ordered from closest to furthest
+    ///
+    /// `ray` - the ray to find the groups along
+    /// `max_distance` - groups further along the ray than this distance are ignored
+    pub fn find_groups_along_ray(&self, ray: &Ray, max_distance: f32) -> Vec<(EntityId, f32)>
+    {
+        let mut found_groups = self.entity_groups.iter()
+            .filter_map(|(group, x)| x.aabb.as_ref().and_then(|aabb| ray.intersect_aabb(aabb)).map(|distance| (*group, distance)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect::<Vec<(EntityId, f32)>>();
+
+        found_groups.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+        found_groups
+    }
+
+    /// Marks the group the entity is part of as changed, so its bounding volume is found again
+    ///
+    /// `entity_id` - the entity whose bounding volume changed
+    fn mark_entity_group_changed(&mut self, entity_id: EntityId)
+    {
+        if let Some(group) = self.entity_group_lookup.get(&entity_id)
+        {
+            self.changed_entity_groups.insert(*group);
+        }
+
+        if self.entity_groups.contains_key(&entity_id)
+        {
+            self.changed_entity_groups.insert(entity_id);
+        }
+    }
+
+    /// Finds the bounding volumes of the groups whose entities changed, from the bounding volumes of the
+    /// entities that are in the tree
+    ///
+    /// `ecs` - the ECS holding the bounding volumes of the entities
+    fn update_entity_group_aabbs(&mut self, ecs: &ECS)
+    {
+        let entities_index_lookup = &self.entities_index_lookup;
+
+        for group_entity in self.changed_entity_groups.drain()
+        {
+            if let Some(group) = self.entity_groups.get_mut(&group_entity)
+            {
+                group.aabb = std::iter::once(&group_entity).chain(group.members.iter())
+                    .filter(|x| entities_index_lookup.contains_key(*x))
+                    .filter_map(|x| ecs.get_ref::<StaticAABB>(*x))
+                    .fold(None, |aabb: Option<StaticAABB>, x| Some(aabb.map_or(*x, |aabb| aabb.combine_aabb(x))));
+            }
+        }
+    }
+
     /// Checks if the given world sections exists, meaning that either it has an entity in it or
     /// is a key to a shared world section
     ///
@@ -412,6 +647,13 @@ impl BoundingBoxTree
         self.changed_static_unique_sections.clear();
     }
 
+    /// Marks every world section holding entities as having changed static entities, so that the rendering
+    /// information of all static entities is recreated
+    pub fn mark_all_static_unique_changed(&mut self)
+    {
+        self.changed_static_unique_sections.extend(self.stored_entities_indexes.keys().copied());
+    }
+
     /// Determines if an entity is static or is active
     ///
     /// `entity_id` - the entity to query
@@ -430,12 +672,60 @@ impl BoundingBoxTree
         None
     }
 
+    /// Get the type of light the entity was added to the tree as, if it is a light
+    ///
+    /// `entity_id` - the entity to query
+    pub fn get_entity_light_type(&self, entity_id: EntityId) -> Option<FindLightType>
+    {
+        let lights = match self.entities_index_lookup.get(&entity_id)?
+        {
+            WorldSectionLookup::Unique(i) => &self.stored_entities_indexes.get(i)?.lights,
+            WorldSectionLookup::Shared(i) => &self.shared_section_indexes.get(i)?.lights,
+        };
+
+        [FindLightType::Directional, FindLightType::Point, FindLightType::Spot].iter()
+            .copied()
+            .find(|x| lights.get_light_entities(*x).contains(&entity_id))
+    }
+
+    /// Get the entities stored in the given world sections, including the entities of shared sections that
+    /// any of the given world sections contribute to, sorted by their IDs
+    ///
+    /// `sections` - the world sections whose entities are returned
+    pub fn get_section_entities(&self, sections: &[UniqueWorldSectionId]) -> Vec<EntityId>
+    {
+        let sections = sections.iter().copied().collect::<HashSet::<UniqueWorldSectionId>>();
+
+        let mut entities = self.entities_index_lookup.iter()
+            .filter(|(_, lookup)|
+                {
+                    match lookup
+                    {
+                        WorldSectionLookup::Unique(i) => sections.contains(i),
+                        WorldSectionLookup::Shared(i) => i.to_world_sections().iter().flatten().any(|x| sections.contains(x))
+                    }
+                })
+            .map(|(entity_id, _)| *entity_id)
+            .collect::<Vec<EntityId>>();
+
+        entities.sort();
+        entities
+    }
+
     /// Get the length of the game world that this bounding tree is representing
     pub fn outline_length(&self) -> u32
     {
         self.outline_length
     }
 
+    /// Get the bounding volume that the given world section occupies
+    ///
+    /// `world_section` - the world section to find the bounding volume of
+    pub fn world_section_aabb(&self, world_section: UniqueWorldSectionId) -> StaticAABB
+    {
+        world_section.to_aabb(self.atomic_section_length)
+    }
+
     /// Get the smallest length if a section that the game world can be divided into
 
     pub fn atomic_world_section_length(&self) -> u32
@@ -561,6 +851,18 @@ impl BoundingBoxTree
     /// `is_static` - true if the added entity is a static object
     /// `light_type` - the type of light the entity is representing, if any
     pub fn add_entity(&mut self, entity_id: EntityId, bounding_volume: &StaticAABB, add_if_out_bounds: bool, is_static: bool, light_type: Option<FindLightType>) -> Result<(), ()>
+    {
+        let placement = self.find_entity_placement(bounding_volume, add_if_out_bounds).ok_or(())?;
+        self.place_entity(entity_id, placement, is_static, light_type);
+        Ok(())
+    }
+
+    /// Finds where an entity with the given bounding volume is placed in the tree, without changing the tree.
+    /// None is returned if the bounding volume is out of bounds and should not be added
+    ///
+    /// `bounding_volume` - the volume represents the physical space used by the entity
+    /// `add_if_out_bounds` - if false, an out of bounds AABB is considered an error
+    pub fn find_entity_placement(&self, bounding_volume: &StaticAABB, add_if_out_bounds: bool) -> Option<EntityPlacement>
     {
         let mut bounding_volume = bounding_volume.clone();
 
@@ -568,21 +870,59 @@ impl BoundingBoxTree
 
         if out_of_bounds && !add_if_out_bounds
         {
-            return Err(());
+            return None;
         }
 
         // Need to check first how many world sections the AABB takes to know if it should go in a shared section
         // or a unique world section
-        let shared_sections = self.find_all_unique_world_section_ids(&bounding_volume);
+        let covered_sections = self.find_all_unique_world_section_ids(&bounding_volume);
 
         // The bounding volume takes more than one world section at the appropriate world division level
-        if shared_sections.len() != 1
+        let location = if covered_sections.len() != 1
+        {
+            WorldSectionLookup::Shared(SharedWorldSectionId::new(&covered_sections))
+        }
+        else
         {
-            let shared_section_index = SharedWorldSectionId::new(&shared_sections);
+            WorldSectionLookup::Unique(BoundingBoxTree::find_unique_world_section_id(bounding_volume.clone(), self.atomic_section_length))
+        };
+
+        Some(EntityPlacement{ bounding_sphere: BoundingSphere::from_aabb(&bounding_volume), bounding_volume, location, covered_sections })
+    }
+
+    /// Finds where each of the given entities are placed in the tree in parallel, without changing the tree. The
+    /// placements are returned in the order of the given entities, and are then placed with place_entity
+    ///
+    /// `entities` - the bounding volume of every entity, and whether it is added if it is out of bounds
+    pub fn find_entity_placements(&self, entities: &[(StaticAABB, bool)]) -> Vec<Option<EntityPlacement>>
+    {
+        entities.par_iter()
+            .with_min_len(PLACEMENT_CHUNK_SIZE)
+            .map(|(bounding_volume, add_if_out_bounds)| self.find_entity_placement(bounding_volume, *add_if_out_bounds))
+            .collect()
+    }
+
+    /// Places the entity where it was found to belong in the tree, moving it out of the world section it was in
+    /// if that is a different world section
+    ///
+    /// `entity_id` - the id of the entity to place in the tree
+    /// `placement` - where the entity belongs, found from its current bounding volume
+    /// `is_static` - true if the placed entity is a static object
+    /// `light_type` - the type of light the entity is representing, if any
+    pub fn place_entity(&mut self, entity_id: EntityId, placement: EntityPlacement, is_static: bool, light_type: Option<FindLightType>)
+    {
+        let EntityPlacement{ bounding_volume, bounding_sphere, location, covered_sections } = placement;
+
+        self.entity_bounding_spheres.insert(entity_id, bounding_sphere);
+        self.mark_entity_group_changed(entity_id);
+
+        if let WorldSectionLookup::Shared(shared_section_index) = location
+        {
+            let shared_sections = covered_sections;
 
             if self.entity_exists_in_section(entity_id, &WorldSectionLookup::Shared(shared_section_index))
             {
-                return Ok(());
+                return;
             }
 
             if is_static
@@ -676,13 +1016,11 @@ impl BoundingBoxTree
 
             self.changed_shared_sections.insert(shared_section_index);
         }
-        else
+        else if let WorldSectionLookup::Unique(world_section_id) = location
         {
-            let world_section_id = BoundingBoxTree::find_unique_world_section_id(bounding_volume.clone(), self.atomic_section_length);
-
             if self.entity_exists_in_section(entity_id, &WorldSectionLookup::Unique(world_section_id))
             {
-                return Ok(());
+                return;
             }
 
             if light_type.is_some()
@@ -757,8 +1095,6 @@ impl BoundingBoxTree
 
             self.changed_world_sections.insert(world_section_id);
         }
-
-        Ok(())
     }
 
     fn entity_exists_in_section(&mut self, entity_id: EntityId, section: &WorldSectionLookup) -> bool
@@ -786,6 +1122,9 @@ impl BoundingBoxTree
     /// `entity_id` - the entity to remove from the tree
     pub fn remove_entity(&mut self, entity_id: EntityId)
     {
+        self.entity_bounding_spheres.remove(&entity_id);
+        self.mark_entity_group_changed(entity_id);
+
         // If the entity was actually added at some point in the past
         if let Some(entity_lookup_key) = self.entities_index_lookup.remove(&entity_id)
         {
@@ -1124,11 +1463,56 @@ impl BoundingBoxTree
             }
         }
 
+        self.update_entity_group_aabbs(ecs);
+
         self.changed_shared_sections.clear();
         self.changed_world_sections.clear();
         self.total_world_aabb_combining = 0;
     }
 
+    /// Finds the entities whose bounding volumes the ray passes through, along with the distance along the
+    /// ray at which it enters each of them. The entities are ordered from closest to furthest
+    ///
+    /// `ecs` - the ECS holding the bounding volumes of the entities
+    /// `ray` - the ray to find the entities along
+    /// `max_distance` - entities further along the ray than this distance are ignored
+    pub fn find_entities_along_ray(&self, ecs: &ECS, ray: &Ray, max_distance: f32) -> Vec<(EntityId, f32)>
+    {
+        let mut found_entities = Vec::new();
+
+        let mut test_entity = |entity: &EntityId|
+            {
+                if let Some(distance) = ecs.get_ref::<StaticAABB>(*entity).and_then(|x| ray.intersect_aabb(x))
+                {
+                    if distance <= max_distance
+                    {
+                        found_entities.push((*entity, distance));
+                    }
+                }
+            };
+
+        // The bounding volume of a unique world section holds all of its entities, allowing entire world
+        // sections to be skipped
+        for world_section in self.stored_entities_indexes.values()
+        {
+            match ray.intersect_aabb(&world_section.aabb)
+            {
+                Some(distance) if distance <= max_distance => {},
+                _ => continue
+            }
+
+            world_section.local_entities.iter().chain(world_section.static_entities.iter()).for_each(&mut test_entity);
+        }
+
+        for shared_section in self.shared_section_indexes.values()
+        {
+            shared_section.entities.iter().chain(shared_section.static_entities.iter()).for_each(&mut test_entity);
+        }
+
+        found_entities.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+        found_entities
+    }
+
     /// Finds any changes to which world sections contain active entities
     fn update_static_world_sections(&mut self)
     {
@@ -1472,12 +1856,12 @@ mod tests
         let mut ecs = ECS::new();
         let mut created_entities = Vec::new();
 
-        let mut bounding_box_tree = BoundingBoxTree::new(256, ATOMIC_SECTION_LENGTH);
+        let mut bounding_box_tree = BoundingBoxTree::new(section_size, ATOMIC_SECTION_LENGTH);
 
         for x in entities
         {
             let entity = ecs.create_entity();
-            bounding_box_tree.add_entity(entity, &x, false);
+            bounding_box_tree.add_entity(entity, &x, false, false, None).unwrap();
             created_entities.push(entity);
         }
 
@@ -1488,7 +1872,7 @@ mod tests
     {
         iter.into_iter()
             .map(|x| format!("{:?}", x))
-            .fold("\n".to_string(), |previous_value, new_value| previous_value + &new_value + "\n")
+            .fold("\n".to_string(), |previous_value, new_value| format!("{}{}\n", previous_value, new_value))
     }
 
     fn check_world_entity_lookup(tree: &BoundingBoxTree, information: Vec<StoredSectionInformation>)
@@ -1826,9 +2210,7 @@ mod tests
 
         let (tree, mut entities) = create_relationship_tree(vec![], false);
 
-        let temp = entities[0];
-        entities[0] = entities[1];
-        entities[1] = temp;
+        entities.swap(0, 1);
 
         test_relationship_tree(&tree, &entities, vec![]);
     }
@@ -1898,7 +2280,7 @@ mod tests
                 },
             ];
 
-        let mut entity_information =
+        let entity_information =
             vec!
             [
                 EntityInformation
@@ -1945,7 +2327,7 @@ mod tests
         // Obtained from test_relationship_tree()
         let shared_section_id = SharedWorldSectionId::new(&vec![UniqueWorldSectionId::new(1, 0, 0, 0), UniqueWorldSectionId::new(1, 1, 0, 0)]);
 
-        let mut stored_section_information =
+        let stored_section_information =
             vec!
             [
                 StoredSectionInformation
@@ -1988,14 +2370,14 @@ mod tests
                 },
             ];
 
-        let mut shared_section_info = SharedSectionInformation
+        let shared_section_info = SharedSectionInformation
         {
             shared_section_id,
             entities: vec![entities[2]],
             referenced_by: vec![UniqueWorldSectionId::new(1, 0, 0, 0), UniqueWorldSectionId::new(1, 1, 0, 0)]
         };
 
-        let mut entity_information =
+        let entity_information =
             vec!
             [
                 EntityInformation
@@ -2022,7 +2404,7 @@ mod tests
 
         tree.remove_entity(entities[2]);
 
-        let mut stored_section_information =
+        let stored_section_information =
             vec!
             [
                 StoredSectionInformation
@@ -2054,7 +2436,7 @@ mod tests
                 },
             ];
 
-        let mut entity_information =
+        let entity_information =
             vec!
             [
                 EntityInformation
@@ -2093,7 +2475,7 @@ mod tests
         test_relationship_tree(&tree, &entities, vec![]);
         tree.remove_entity(entities[2]);
 
-        let mut stored_section_information =
+        let stored_section_information =
             vec!
             [
                 StoredSectionInformation
@@ -2136,7 +2518,7 @@ mod tests
                 },
             ];
 
-        let mut entity_information =
+        let entity_information =
             vec!
             [
                 EntityInformation
@@ -2163,7 +2545,7 @@ mod tests
 
         tree.remove_entity(entities[1]);
 
-        let mut stored_section_information =
+        let stored_section_information =
             vec!
             [
                 StoredSectionInformation
@@ -2195,7 +2577,7 @@ mod tests
                 },
             ];
 
-        let mut entity_information =
+        let entity_information =
             vec!
             [
                 EntityInformation
@@ -2238,40 +2620,47 @@ mod tests
 
         let (tree, entities) = create_tree(256, vec![small_aabb, large_aabb, large_aabb2, offset_large_aabb2, very_large_entity, unrelated_small_aabb]);
 
-        let entity_maps = vec![
-            FnvHashSet::from_iter(vec![entities[0]].into_iter()),
-            FnvHashSet::from_iter(vec![entities[1], entities[2]].into_iter()),
-            FnvHashSet::from_iter(vec![entities[3]].into_iter()),
-            FnvHashSet::from_iter(vec![entities[4]].into_iter()),
+        let entity_maps = [
+            HashSet::from_iter([entities[0]]),
+            HashSet::from_iter([entities[1], entities[2]]),
+            HashSet::from_iter([entities[3]]),
+            HashSet::from_iter([entities[4]]),
             HashSet::default(),
-            FnvHashSet::from_iter(vec![entities[5]].into_iter()) // Unrelated entity search result
+            HashSet::from_iter([entities[5]]) // Unrelated entity search result
         ];
 
+        let no_static_entities = HashSet::default();
+
         let expected_related_entities = vec![
             RelatedEntitySearchResult
             {
                 location: WorldSectionLookup::Unique(UniqueWorldSectionId::new(0, 0, 0, 0)),
-                entities: &entity_maps[0]
+                entities: &entity_maps[0],
+                static_entities: &no_static_entities
             },
             RelatedEntitySearchResult
             {
                 location: WorldSectionLookup::Unique(UniqueWorldSectionId::new(1, 0, 0, 0)),
-                entities: &entity_maps[1]
+                entities: &entity_maps[1],
+                static_entities: &no_static_entities
             },
             RelatedEntitySearchResult
             {
                 location: WorldSectionLookup::Shared(SharedWorldSectionId::new(&vec![UniqueWorldSectionId::new(1, 0, 0, 0), UniqueWorldSectionId::new(1, 1, 0, 0)])),
-                entities: &entity_maps[2]
+                entities: &entity_maps[2],
+                static_entities: &no_static_entities
             },
             RelatedEntitySearchResult
             {
                 location: WorldSectionLookup::Unique(UniqueWorldSectionId::new(2, 0, 0, 0)),
-                entities: &entity_maps[3]
+                entities: &entity_maps[3],
+                static_entities: &no_static_entities
             },
             RelatedEntitySearchResult
             {
                 location: WorldSectionLookup::Unique(UniqueWorldSectionId::new(1, 1, 0, 0)),
-                entities: &entity_maps[4]
+                entities: &entity_maps[4],
+                static_entities: &no_static_entities
             }
         ];
 
@@ -2279,13 +2668,14 @@ mod tests
             RelatedEntitySearchResult
             {
                 location: WorldSectionLookup::Unique(UniqueWorldSectionId::new(0, 4, 0, 0)),
-                entities: &entity_maps[5]
+                entities: &entity_maps[5],
+                static_entities: &no_static_entities
             }
         ];
 
         let check_expected_entities = |aabb: StaticAABB, expected_entities: &Vec<RelatedEntitySearchResult>|
             {
-                let mut actual_related_entities = tree.find_related_entities_internal::<LogicFrustumCuller>(tree.find_all_unique_world_section_ids(&aabb), None);
+                let actual_related_entities = tree.find_related_entities_internal::<LogicFrustumCuller, LogicFrustumCuller>(tree.find_all_unique_world_section_ids(&aabb), None, None);
 
                 for search_result in expected_entities
                 {
@@ -2344,7 +2734,7 @@ mod tests
         create_tree(256, aabb_vec)
     }
 
-    fn test_relationship_tree(tree: &BoundingBoxTree, entities: &Vec<EntityId>, append_entities: Vec<EntityInformation>)
+    fn test_relationship_tree(tree: &BoundingBoxTree, entities: &[EntityId], append_entities: Vec<EntityInformation>)
     {
         let shared_section_id = SharedWorldSectionId::new(&vec![UniqueWorldSectionId::new(1, 0, 0, 0), UniqueWorldSectionId::new(1, 1, 0, 0)]);
 
@@ -2427,17 +2817,17 @@ mod tests
         {
             match x.lookup_info
             {
-                WorldSectionLookup::Shared(ref i) => { shared_section_info.entities.push(x.entity_id); },
+                WorldSectionLookup::Shared(_) => { shared_section_info.entities.push(x.entity_id); },
                 WorldSectionLookup::Unique(ref i) =>
                     {
-                        let mut world_section = stored_section_information.iter_mut().find(|x| x.world_section == *i).unwrap();
+                        let world_section = stored_section_information.iter_mut().find(|x| x.world_section == *i).unwrap();
 
                         world_section.local_entities.push(x.entity_id);
                     }
             }
         }
 
-        entity_information.extend(append_entities.into_iter());
+        entity_information.extend(append_entities);
 
         check_world_entity_lookup(tree, stored_section_information);
         check_related_world_sections(tree, related_section_information);
@@ -2445,4 +2835,73 @@ mod tests
         check_entity_lookup(tree, entity_information);
 
     }
+
+    #[test]
+    pub fn group_aabb_holds_members()
+    {
+        let mut ecs = ECS::new();
+        ecs.register_type::<StaticAABB>();
+
+        let mut tree = BoundingBoxTree::new(256, ATOMIC_SECTION_LENGTH);
+        let leader = ecs.create_entity();
+        let member = ecs.create_entity();
+
+        let mut member_aabb = small_entity_section();
+        member_aabb.translate(vec3(40.0, 0.0, 0.0));
+
+        ecs.write_component::<StaticAABB>(leader, small_entity_section());
+        ecs.write_component::<StaticAABB>(member, member_aabb);
+        tree.add_entity(leader, &small_entity_section(), false, false, None).unwrap();
+        tree.add_entity(member, &member_aabb, false, false, None).unwrap();
+
+        tree.add_group_member(leader, member);
+        tree.end_of_changes(&ecs);
+
+        let group_aabb = tree.get_group_aabb(leader).unwrap();
+        assert!(approx_eq!(f32, group_aabb.x_range.min, 0.0, ulps = 2));
+        assert!(approx_eq!(f32, group_aabb.x_range.max, 50.0, ulps = 2));
+        assert_eq!(tree.get_entity_group(member), Some(leader));
+
+        tree.remove_entity_from_groups(member);
+        assert!(tree.get_group_aabb(leader).is_none());
+        assert_eq!(tree.get_entity_group(member), None);
+    }
+
+    #[test]
+    pub fn placed_entities_match_added_entities()
+    {
+        let mut ecs = ECS::new();
+        let mut added_tree = BoundingBoxTree::new(256, ATOMIC_SECTION_LENGTH);
+        let mut placed_tree = BoundingBoxTree::new(256, ATOMIC_SECTION_LENGTH);
+
+        let mut shared_aabb = medium_entity_section();
+        shared_aabb.translate(vec3(16.0, 0.0, 0.0));
+
+        let mut out_of_bounds_aabb = small_entity_section();
+        out_of_bounds_aabb.translate(vec3(300.0, 0.0, 0.0));
+
+        let aabbs = [small_entity_section(), shared_aabb, large_entity_section(), out_of_bounds_aabb];
+        let entities = aabbs.iter().map(|_| ecs.create_entity()).collect::<Vec<EntityId>>();
+
+        for (entity, aabb) in entities.iter().zip(aabbs.iter())
+        {
+            let _ = added_tree.add_entity(*entity, aabb, false, false, None);
+        }
+
+        let placement_requests = aabbs.iter().map(|x| (*x, false)).collect::<Vec<(StaticAABB, bool)>>();
+        let placements = placed_tree.find_entity_placements(&placement_requests);
+        assert!(placements[3].is_none());
+
+        for (entity, placement) in entities.iter().zip(placements)
+        {
+            if let Some(placement) = placement
+            {
+                placed_tree.place_entity(*entity, placement, false, None);
+            }
+        }
+
+        assert_eq!(added_tree.entities_index_lookup, placed_tree.entities_index_lookup);
+        assert_eq!(added_tree.reverse_shared_section_lookup, placed_tree.reverse_shared_section_lookup);
+        assert_eq!(added_tree.stored_entities_indexes.keys().collect::<HashSet<&UniqueWorldSectionId>>(), placed_tree.stored_entities_indexes.keys().collect::<HashSet<&UniqueWorldSectionId>>());
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use nalgebra_glm::{TVec3, vec3, vec4};
 use serde::{Serialize, Deserialize};
+use crate::helper_things::aabb_helper_functions;
 use crate::world::dimension::range::{XRange, YRange, ZRange};
 
 /// Represents a bounding volume in a 3D space
@@ -72,6 +73,72 @@ impl StaticAABB
             self.z_range.overlap_range(&other_aabb.z_range)
     }
 
+    /// Finds the first point in time this bounding volume touches the other volume as this volume moves
+    /// by the given displacement, with the other volume staying still. The time is the fraction of the
+    /// displacement moved when the volumes first touch, from 0 to 1. If the volumes already overlap
+    /// then the time is 0. None is returned if the volumes never touch during the movement
+    ///
+    /// `other_aabb` - the volume to check for a collision with this one
+    /// `displacement` - vector specifying how much this volume moves in each dimension
+    pub fn swept_intersect(&self, other_aabb: &StaticAABB, displacement: TVec3<f32>) -> Option<f32>
+    {
+        let dimensions =
+            [
+                (self.x_range.min, self.x_range.max, other_aabb.x_range.min, other_aabb.x_range.max, displacement.x),
+                (self.y_range.min, self.y_range.max, other_aabb.y_range.min, other_aabb.y_range.max, displacement.y),
+                (self.z_range.min, self.z_range.max, other_aabb.z_range.min, other_aabb.z_range.max, displacement.z),
+            ];
+
+        let mut entry_time: f32 = 0.0;
+        let mut exit_time: f32 = 1.0;
+
+        for (self_min, self_max, other_min, other_max, movement) in dimensions.iter()
+        {
+            if *movement == 0.0
+            {
+                // Without movement in this dimension, the volumes must always overlap in it
+                if self_max < other_min || self_min > other_max
+                {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let (dimension_entry_time, dimension_exit_time) = if *movement > 0.0
+            {
+                ((other_min - self_max) / movement, (other_max - self_min) / movement)
+            }
+            else
+            {
+                ((other_max - self_min) / movement, (other_min - self_max) / movement)
+            };
+
+            entry_time = entry_time.max(dimension_entry_time);
+            exit_time = exit_time.min(dimension_exit_time);
+
+            if entry_time > exit_time
+            {
+                return None;
+            }
+        }
+
+        Some(entry_time)
+    }
+
+    /// Finds the shortest distance from the given point to the bounding volume. If the point is
+    /// within the volume, the distance is zero
+    ///
+    /// `point` - the point to find the distance from
+    pub fn distance_to_point(&self, point: TVec3<f32>) -> f32
+    {
+        let distance_x = (self.x_range.min - point.x).max(point.x - self.x_range.max).max(0.0);
+        let distance_y = (self.y_range.min - point.y).max(point.y - self.y_range.max).max(0.0);
+        let distance_z = (self.z_range.min - point.z).max(point.z - self.z_range.max).max(0.0);
+
+        nalgebra_glm::length(&vec3(distance_x, distance_y, distance_z))
+    }
+
     /// Scales the bounding volume by the given amount
     ///
     /// `factor` - vector specifying how much to scale the volume in each dimension
@@ -94,23 +161,12 @@ impl StaticAABB
     /// `transformation` - the transformation to apply to this AABB
     pub fn apply_transformation(&mut self, transformation: &nalgebra_glm::Mat4x4) -> StaticAABB
     {
-        let first_corner = transformation * vec4(self.x_range.min, self.y_range.min, self.z_range.min, 1.0);
-        let second_corner = transformation * vec4(self.x_range.max, self.y_range.max, self.z_range.max, 1.0);
-
-        let min_x = first_corner.x.min(second_corner.x);
-        let min_y = first_corner.y.min(second_corner.y);
-        let min_z = first_corner.z.min(second_corner.z);
-
-        let max_x = first_corner.x.max(second_corner.x);
-        let max_y = first_corner.y.max(second_corner.y);
-        let max_z = first_corner.z.max(second_corner.z);
+        // All corners are transformed, as a rotation can move any corner to the outside of the volume
+        let transformed_points = self.get_aabb_points().iter()
+            .map(|x| (transformation * vec4(x.x, x.y, x.z, 1.0)).xyz())
+            .collect::<Vec<TVec3<f32>>>();
 
-        StaticAABB::new
-            (
-                XRange::new(min_x, max_x),
-                YRange::new(min_y, max_y),
-                ZRange::new(min_z, max_z)
-            )
+        aabb_helper_functions::calculate_aabb(&transformed_points)
     }
 
     /// Get a default AABB centred at the origin, and has no length
@@ -138,4 +194,94 @@ impl StaticAABB
             vec3(self.x_range.max, self.y_range.max, self.z_range.max)
         ]
     }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use nalgebra_glm::vec3;
+    use float_cmp::approx_eq;
+    use crate::world::bounding_volumes::aabb::StaticAABB;
+    use crate::world::dimension::range::{XRange, YRange, ZRange};
+
+    fn unit_aabb_at(x: f32, y: f32, z: f32) -> StaticAABB
+    {
+        StaticAABB::new
+            (
+                XRange::new(x, x + 1.0),
+                YRange::new(y, y + 1.0),
+                ZRange::new(z, z + 1.0)
+            )
+    }
+
+    #[test]
+    fn swept_intersect_hit()
+    {
+        let moving_aabb = unit_aabb_at(0.0, 0.0, 0.0);
+        let still_aabb = unit_aabb_at(5.0, 0.0, 0.0);
+
+        let time_of_impact = moving_aabb.swept_intersect(&still_aabb, vec3(10.0, 0.0, 0.0)).unwrap();
+        assert!(approx_eq!(f32, 0.4, time_of_impact, ulps = 2));
+
+        let time_of_impact = still_aabb.swept_intersect(&moving_aabb, vec3(-10.0, 0.0, 0.0)).unwrap();
+        assert!(approx_eq!(f32, 0.4, time_of_impact, ulps = 2));
+    }
+
+    #[test]
+    fn swept_intersect_miss()
+    {
+        let moving_aabb = unit_aabb_at(0.0, 0.0, 0.0);
+
+        // Does not move far enough to reach the other volume
+        assert_eq!(None, moving_aabb.swept_intersect(&unit_aabb_at(5.0, 0.0, 0.0), vec3(2.0, 0.0, 0.0)));
+
+        // Moves away from the other volume
+        assert_eq!(None, moving_aabb.swept_intersect(&unit_aabb_at(5.0, 0.0, 0.0), vec3(-10.0, 0.0, 0.0)));
+
+        // Passes beside the other volume
+        assert_eq!(None, moving_aabb.swept_intersect(&unit_aabb_at(5.0, 3.0, 0.0), vec3(10.0, 0.0, 0.0)));
+
+        // Passes over the other volume in a dimension it moves in
+        assert_eq!(None, moving_aabb.swept_intersect(&unit_aabb_at(5.0, 8.0, 0.0), vec3(10.0, 10.0, 0.0)));
+    }
+
+    #[test]
+    fn swept_intersect_touching()
+    {
+        let moving_aabb = unit_aabb_at(0.0, 0.0, 0.0);
+
+        // Only touches the other volume at the end of the movement
+        let time_of_impact = moving_aabb.swept_intersect(&unit_aabb_at(2.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0)).unwrap();
+        assert!(approx_eq!(f32, 1.0, time_of_impact, ulps = 2));
+
+        // Touches the other volume before moving
+        let time_of_impact = moving_aabb.swept_intersect(&unit_aabb_at(1.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0)).unwrap();
+        assert!(approx_eq!(f32, 0.0, time_of_impact, ulps = 2));
+
+        // Overlaps the other volume before moving
+        let time_of_impact = moving_aabb.swept_intersect(&unit_aabb_at(0.5, 0.5, 0.5), vec3(-1.0, 0.0, 0.0)).unwrap();
+        assert!(approx_eq!(f32, 0.0, time_of_impact, ulps = 2));
+    }
+
+    #[test]
+    fn swept_intersect_zero_velocity()
+    {
+        let still_aabb = unit_aabb_at(0.0, 0.0, 0.0);
+
+        assert_eq!(Some(0.0), still_aabb.swept_intersect(&unit_aabb_at(0.5, 0.5, 0.5), vec3(0.0, 0.0, 0.0)));
+        assert_eq!(Some(0.0), still_aabb.swept_intersect(&unit_aabb_at(1.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)));
+        assert_eq!(None, still_aabb.swept_intersect(&unit_aabb_at(2.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn distance_to_point()
+    {
+        let aabb = unit_aabb_at(0.0, 0.0, 0.0);
+
+        assert!(approx_eq!(f32, 0.0, aabb.distance_to_point(vec3(0.5, 0.5, 0.5)), ulps = 2));
+        assert!(approx_eq!(f32, 0.0, aabb.distance_to_point(vec3(1.0, 0.5, 0.5)), ulps = 2));
+        assert!(approx_eq!(f32, 3.0, aabb.distance_to_point(vec3(4.0, 0.5, 0.5)), ulps = 2));
+        assert!(approx_eq!(f32, 3.0, aabb.distance_to_point(vec3(0.5, -3.0, 0.5)), ulps = 2));
+        assert!(approx_eq!(f32, 5.0, aabb.distance_to_point(vec3(4.0, 5.0, 0.5)), ulps = 2));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,85 @@
+use nalgebra_glm::{TVec3, vec4};
+use serde::{Serialize, Deserialize};
+use crate::exports::movement_components::TransformationMatrix;
+use crate::models::model_definitions::OriginalAABB;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+/// Sphere surrounding an entity, which is cheaper to test against than an AABB when the orientation
+/// of the entity does not matter
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoundingSphere
+{
+    pub centre: TVec3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere
+{
+    /// Creates a sphere with the given centre and radius
+    ///
+    /// `centre` - the centre of the sphere
+    /// `radius` - the radius of the sphere
+    pub fn new(centre: TVec3<f32>, radius: f32) -> BoundingSphere
+    {
+        BoundingSphere{ centre, radius }
+    }
+
+    /// Creates the smallest sphere centred on the AABB that holds the AABB
+    ///
+    /// `aabb` - the AABB to surround
+    pub fn from_aabb(aabb: &StaticAABB) -> BoundingSphere
+    {
+        let half_diagonal = nalgebra_glm::vec3(aabb.x_range.length(), aabb.y_range.length(), aabb.z_range.length()) * 0.5;
+
+        BoundingSphere::new(aabb.centre(), nalgebra_glm::length(&half_diagonal))
+    }
+
+    /// Creates the sphere surrounding an entity from the AABB of its model, placed by its transformation
+    /// matrix. None is returned if the entity does not have both of these components
+    ///
+    /// `ecs` - the ECS holding the entity
+    /// `entity_id` - the entity to surround
+    pub fn for_entity(ecs: &ECS, entity_id: EntityId) -> Option<BoundingSphere>
+    {
+        let original_aabb = ecs.get_copy::<OriginalAABB>(entity_id)?;
+        let transformation = ecs.get_copy::<TransformationMatrix>(entity_id)?;
+
+        Some(BoundingSphere::from_aabb(&original_aabb.aabb).apply_transformation(&transformation.get_matrix()))
+    }
+
+    /// Get the sphere after being transformed by the transformation matrix. The radius is scaled by the
+    /// largest scale of the transformation, so that the sphere still surrounds the transformed volume
+    ///
+    /// `transformation` - the transformation to apply to the sphere
+    pub fn apply_transformation(&self, transformation: &nalgebra_glm::Mat4x4) -> BoundingSphere
+    {
+        let centre = transformation * vec4(self.centre.x, self.centre.y, self.centre.z, 1.0);
+
+        let largest_scale = (0..3)
+            .map(|x| nalgebra_glm::length(&nalgebra_glm::column(transformation, x).xyz()))
+            .fold(0.0_f32, f32::max);
+
+        BoundingSphere::new(centre.xyz(), self.radius * largest_scale)
+    }
+
+    /// Checks if this sphere overlaps the given sphere
+    ///
+    /// `other_sphere` - the sphere to check for an overlap with
+    pub fn intersect(&self, other_sphere: &BoundingSphere) -> bool
+    {
+        let radius_sum = self.radius + other_sphere.radius;
+
+        nalgebra_glm::length2(&(self.centre - other_sphere.centre)) <= radius_sum * radius_sum
+    }
+
+    /// Get the distance from the surface of the sphere to the given point. If the point is inside the
+    /// sphere, the distance is zero
+    ///
+    /// `point` - the point to find the distance from
+    pub fn distance_to_point(&self, point: TVec3<f32>) -> f32
+    {
+        (nalgebra_glm::length(&(point - self.centre)) - self.radius).max(0.0)
+    }
+}
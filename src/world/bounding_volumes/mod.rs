@@ -1 +1,4 @@
-pub mod aabb;
\ No newline at end of file
+pub mod aabb;
+pub mod bounding_sphere;
+pub mod oriented_box;
+pub mod ray;
\ No newline at end of file
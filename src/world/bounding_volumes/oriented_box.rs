@@ -0,0 +1,112 @@
+use nalgebra_glm::{TVec3, vec3, vec4};
+use serde::{Serialize, Deserialize};
+use crate::exports::movement_components::TransformationMatrix;
+use crate::helper_things::aabb_helper_functions;
+use crate::models::model_definitions::OriginalAABB;
+use crate::objects::ecs::ECS;
+use crate::objects::entity_id::EntityId;
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+/// Box surrounding an entity that is rotated along with the entity, which surrounds a rotated entity
+/// more tightly than an AABB
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OrientedBoundingBox
+{
+    pub centre: TVec3<f32>,
+    /// Unit length directions of the sides of the box
+    pub axes: [TVec3<f32>; 3],
+    /// Half of the length of the box along each of its axes
+    pub half_extents: TVec3<f32>,
+}
+
+impl OrientedBoundingBox
+{
+    /// Creates the box occupying the same space as the AABB
+    ///
+    /// `aabb` - the AABB to convert
+    pub fn from_aabb(aabb: &StaticAABB) -> OrientedBoundingBox
+    {
+        OrientedBoundingBox
+        {
+            centre: aabb.centre(),
+            axes: [vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0)],
+            half_extents: vec3(aabb.x_range.length(), aabb.y_range.length(), aabb.z_range.length()) * 0.5,
+        }
+    }
+
+    /// Creates the box surrounding an entity from the AABB of its model, placed by its transformation
+    /// matrix. None is returned if the entity does not have both of these components
+    ///
+    /// `ecs` - the ECS holding the entity
+    /// `entity_id` - the entity to surround
+    pub fn for_entity(ecs: &ECS, entity_id: EntityId) -> Option<OrientedBoundingBox>
+    {
+        let original_aabb = ecs.get_copy::<OriginalAABB>(entity_id)?;
+        let transformation = ecs.get_copy::<TransformationMatrix>(entity_id)?;
+
+        Some(OrientedBoundingBox::from_aabb(&original_aabb.aabb).apply_transformation(&transformation.get_matrix()))
+    }
+
+    /// Get the box after being transformed by the transformation matrix, which may rotate, scale and
+    /// translate the box. The transformation must not shear the box
+    ///
+    /// `transformation` - the transformation to apply to the box
+    pub fn apply_transformation(&self, transformation: &nalgebra_glm::Mat4x4) -> OrientedBoundingBox
+    {
+        let centre = transformation * vec4(self.centre.x, self.centre.y, self.centre.z, 1.0);
+
+        let mut axes = self.axes;
+        let mut half_extents = self.half_extents;
+
+        for index in 0..3
+        {
+            let transformed_axis = (transformation * vec4(self.axes[index].x, self.axes[index].y, self.axes[index].z, 0.0)).xyz();
+            let scale = nalgebra_glm::length(&transformed_axis);
+
+            // An axis scaled to nothing keeps its direction, as the box has no size along it
+            if scale > 0.0
+            {
+                axes[index] = transformed_axis / scale;
+            }
+
+            half_extents[index] *= scale;
+        }
+
+        OrientedBoundingBox{ centre: centre.xyz(), axes, half_extents }
+    }
+
+    /// Checks if the given point is inside the box
+    ///
+    /// `point` - the point to check
+    pub fn contains_point(&self, point: TVec3<f32>) -> bool
+    {
+        let offset = point - self.centre;
+
+        (0..3).all(|x| nalgebra_glm::dot(&offset, &self.axes[x]).abs() <= self.half_extents[x])
+    }
+
+    /// Get the corners of the box
+    pub fn get_points(&self) -> [TVec3<f32>; 8]
+    {
+        let x = self.axes[0] * self.half_extents.x;
+        let y = self.axes[1] * self.half_extents.y;
+        let z = self.axes[2] * self.half_extents.z;
+
+        [
+            self.centre - x - y - z,
+            self.centre - x - y + z,
+            self.centre - x + y - z,
+            self.centre - x + y + z,
+            self.centre + x - y - z,
+            self.centre + x - y + z,
+            self.centre + x + y - z,
+            self.centre + x + y + z,
+        ]
+    }
+
+    /// Get the smallest AABB that holds this box
+    pub fn to_aabb(&self) -> StaticAABB
+    {
+        aabb_helper_functions::calculate_aabb(&self.get_points())
+    }
+}
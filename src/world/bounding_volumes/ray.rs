@@ -0,0 +1,75 @@
+use nalgebra_glm::TVec3;
+use serde::{Serialize, Deserialize};
+use crate::world::bounding_volumes::aabb::StaticAABB;
+
+/// A half-line starting at an origin and extending infinitely in one direction
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ray
+{
+    pub origin: TVec3<f32>,
+    pub direction: TVec3<f32>,
+}
+
+impl Ray
+{
+    /// Creates a ray starting at the origin, going in the given direction. The direction is normalized
+    ///
+    /// `origin` - the start of the ray
+    /// `direction` - the direction the ray extends in
+    pub fn new(origin: TVec3<f32>, direction: TVec3<f32>) -> Ray
+    {
+        Ray{ origin, direction: nalgebra_glm::normalize(&direction) }
+    }
+
+    /// Get the point that is the given distance along the ray
+    ///
+    /// `distance` - how far along the ray the point is
+    pub fn point_at(&self, distance: f32) -> TVec3<f32>
+    {
+        self.origin + self.direction * distance
+    }
+
+    /// Finds the distance along the ray at which it first enters the bounding volume. If the origin of
+    /// the ray is within the volume then the distance is 0. None is returned if the ray misses the volume
+    ///
+    /// `aabb` - the volume to check for an intersection with the ray
+    pub fn intersect_aabb(&self, aabb: &StaticAABB) -> Option<f32>
+    {
+        let dimensions =
+            [
+                (aabb.x_range.min, aabb.x_range.max, self.origin.x, self.direction.x),
+                (aabb.y_range.min, aabb.y_range.max, self.origin.y, self.direction.y),
+                (aabb.z_range.min, aabb.z_range.max, self.origin.z, self.direction.z),
+            ];
+
+        let mut entry_distance: f32 = 0.0;
+        let mut exit_distance = f32::MAX;
+
+        for (min, max, origin, direction) in dimensions.iter()
+        {
+            if *direction == 0.0
+            {
+                // The ray is parallel to this dimension, so it must already be within the volume in it
+                if origin < min || origin > max
+                {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let first_distance = (min - origin) / direction;
+            let second_distance = (max - origin) / direction;
+
+            entry_distance = entry_distance.max(first_distance.min(second_distance));
+            exit_distance = exit_distance.min(first_distance.max(second_distance));
+
+            if entry_distance > exit_distance
+            {
+                return None;
+            }
+        }
+
+        Some(entry_distance)
+    }
+}
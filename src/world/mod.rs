@@ -1,3 +1,4 @@
 pub mod bounding_volumes;
 pub mod dimension;
-pub mod bounding_box_tree_v2;
\ No newline at end of file
+pub mod bounding_box_tree_v2;
+pub mod visible_world_sections;
\ No newline at end of file
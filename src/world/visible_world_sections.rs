@@ -0,0 +1,67 @@
+use nalgebra_glm::TVec3;
+use crate::flows::visible_world_flow::CullResult;
+use crate::helper_things::aabb_helper_functions::distance_to_aabb;
+use crate::world::bounding_box_tree_v2::{BoundingBoxTree, UniqueWorldSectionId};
+
+/// Read-only view of the world sections that were considered visible in the current frame, which are the
+/// world sections whose entities are rendered and have their logic executed
+pub struct VisibleWorldSections<'a>
+{
+    cull_result: &'a CullResult,
+    bounding_box_tree: &'a BoundingBoxTree,
+    camera_position: TVec3<f32>,
+}
+
+impl<'a> VisibleWorldSections<'a>
+{
+    /// Creates a view of the visible world sections, with distances measured from the given camera position
+    ///
+    /// `cull_result` - the world sections that are visible
+    /// `bounding_box_tree` - the tree the world sections are part of
+    /// `camera_position` - the position of the camera that the world sections are visible to
+    pub(crate) fn new(cull_result: &'a CullResult, bounding_box_tree: &'a BoundingBoxTree, camera_position: TVec3<f32>) -> VisibleWorldSections<'a>
+    {
+        VisibleWorldSections{ cull_result, bounding_box_tree, camera_position }
+    }
+
+    /// Checks if the given world section is visible
+    ///
+    /// `world_section` - the world section to check
+    pub fn contains(&self, world_section: UniqueWorldSectionId) -> bool
+    {
+        self.cull_result.visible_sections_map.contains(&world_section)
+    }
+
+    /// Get the distance from the camera to the closest point of the world section. None is returned
+    /// if the world section is not visible
+    ///
+    /// `world_section` - the world section to find the distance to
+    pub fn distance(&self, world_section: UniqueWorldSectionId) -> Option<f32>
+    {
+        if !self.contains(world_section)
+        {
+            return None;
+        }
+
+        Some(distance_to_aabb(&self.bounding_box_tree.world_section_aabb(world_section), self.camera_position))
+    }
+
+    /// Iterates over the visible world sections along with the distance from the camera to each of them
+    pub fn iter(&self) -> impl Iterator<Item = (UniqueWorldSectionId, f32)> + '_
+    {
+        self.cull_result.visible_sections_vec.iter()
+            .map(move |x| (*x, distance_to_aabb(&self.bounding_box_tree.world_section_aabb(*x), self.camera_position)))
+    }
+
+    /// Get the number of visible world sections
+    pub fn len(&self) -> usize
+    {
+        self.cull_result.visible_sections_vec.len()
+    }
+
+    /// Checks if no world sections are visible
+    pub fn is_empty(&self) -> bool
+    {
+        self.cull_result.visible_sections_vec.is_empty()
+    }
+}